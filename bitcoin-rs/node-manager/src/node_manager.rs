@@ -59,6 +59,10 @@ impl NodeManager {
 			user_agent: "bitcoin-rs-test".to_string(),
 			start_height: 0,
 			relay: false,
+			v2_transport: false,
+			txreconciliation: false,
+			proxy: None,
+			onion_proxy: None,
 		};
 
 		NodeManager {
@@ -124,7 +128,7 @@ impl NodeManager {
 
 	pub async fn connect_p2p(&mut self) -> &mut NodeManager {
 		for _ in 0..5 {
-			if let Ok(connection) = connect(&self.config.local_address, &self.config).await {
+			if let Ok(connection) = connect(&self.config.local_address.into(), None, &self.config).await {
 				self.connection = Some(connection);
 				return self;
 			}
@@ -0,0 +1,60 @@
+use db::ham_db::HamDb;
+use db::BlockChainDatabase;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Number of filters, built while the database is locked.
+const BLOCK_FILTERS_BATCH_SIZE: u32 = 256;
+
+/// Builds filters of blocks, which have been canonized before block filters were enabled.
+pub struct BlockFiltersBuilder {
+	is_stopping: Arc<AtomicBool>,
+	worker_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl BlockFiltersBuilder {
+	pub fn new(db: Arc<BlockChainDatabase<HamDb>>) -> Self {
+		let is_stopping = Arc::new(AtomicBool::default());
+		BlockFiltersBuilder {
+			is_stopping: is_stopping.clone(),
+			worker_thread: Some(
+				std::thread::Builder::new()
+					.name("Block filters thread".to_owned())
+					.spawn(move || BlockFiltersBuilder::worker(db, is_stopping))
+					.expect("Error creating block filters thread"),
+			),
+		}
+	}
+
+	fn worker(db: Arc<BlockChainDatabase<HamDb>>, is_stopping: Arc<AtomicBool>) {
+		let mut total_built = 0;
+		while !is_stopping.load(Ordering::SeqCst) {
+			match db.build_missing_block_filters(BLOCK_FILTERS_BATCH_SIZE) {
+				Ok(0) => break,
+				Ok(built) => {
+					total_built += built;
+					if total_built % (BLOCK_FILTERS_BATCH_SIZE * 100) == 0 {
+						info!(target: "bitcoin-rs", "Built filters of {} canon blocks", total_built);
+					}
+				}
+				Err(err) => {
+					error!(target: "bitcoin-rs", "Failed to build block filters: {}", err);
+					break;
+				}
+			}
+		}
+		if total_built != 0 {
+			info!(target: "bitcoin-rs", "Built filters of {} canon blocks", total_built);
+		}
+		trace!(target: "bitcoin-rs", "Block filters thread stopped");
+	}
+}
+
+impl Drop for BlockFiltersBuilder {
+	fn drop(&mut self) {
+		if let Some(join_handle) = self.worker_thread.take() {
+			self.is_stopping.store(true, Ordering::SeqCst);
+			join_handle.join().expect("Clean shutdown.");
+		}
+	}
+}
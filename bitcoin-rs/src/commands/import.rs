@@ -7,8 +7,12 @@ use storage::CanonStore;
 use sync::{create_sync_blocks_writer, Error};
 
 pub fn import(cfg: &Config, matches: &ArgMatches) -> Result<(), String> {
-	let db =
-		Arc::new(db::BlockChainDatabase::persistent(&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block()).unwrap());
+	let db = Arc::new(
+		db::BlockChainDatabase::persistent(&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block())
+			.unwrap()
+			.with_address_index(cfg.address_index)
+			.with_block_filters(cfg.block_filters),
+	);
 	let blk_path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
 	let blk_dir = open_blk_dir(blk_path).map_err(|err| format!("Failed to open import directory: {}", err))?;
 
@@ -1,19 +1,34 @@
 use crate::app_dir::app_path;
+use crate::block_filters_builder::BlockFiltersBuilder;
 use crate::block_notifier::BlockNotifier;
 use crate::config;
 use memory::Memory;
 use network::network::{PROTOCOL_MINIMUM, PROTOCOL_VERSION};
+use network::Network;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use storage::CanonStore;
 use sync::{create_local_sync_node, create_sync_connection_factory, create_sync_peers};
+use wallet::{Wallet, WalletNotifier, WalletStore};
 
 /// Some setup functions in here spawn new threads (which should be done off the main thread)
 /// At the moment only the p2p context runs on the Tokio runtime. RPC server has its own Tokio runtime.
 pub fn start(cfg: config::Config) -> Result<(), String> {
-	let db =
-		Arc::new(db::BlockChainDatabase::persistent(&&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block()).unwrap());
+	let db = Arc::new(
+		db::BlockChainDatabase::persistent(&&app_path(&cfg.data_dir, "db"), cfg.db_cache, &cfg.network.genesis_block())
+			.unwrap()
+			.with_address_index(cfg.address_index)
+			.with_block_filters(cfg.block_filters),
+	);
+
+	// filters of blocks, canonized while filters were disabled, are built in background
+	let block_filters_builder = if cfg.block_filters {
+		Some(BlockFiltersBuilder::new(db.clone()))
+	} else {
+		None
+	};
 
 	let runtime = tokio::runtime::Builder::new_multi_thread()
 		.enable_io()
@@ -21,22 +36,49 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 		.build()
 		.expect("Failure starting Tokio runtime");
 
+	let mut memory_pool_params = cfg.memory_pool_params.clone();
+	if cfg.persist_memory_pool {
+		memory_pool_params.dump_path = Some(PathBuf::from(app_path(&cfg.data_dir, "mempool")).join("mempool.dat"));
+	}
+
 	let sync_peers = create_sync_peers();
 	let local_sync_node = create_local_sync_node(
 		cfg.consensus.clone(),
 		db.clone(),
 		sync_peers.clone(),
 		cfg.verification_params.clone(),
+		memory_pool_params,
 	);
+	match local_sync_node.load_memory_pool() {
+		Ok(count) => info!("Loaded {} memory pool transactions", count),
+		Err(err) => warn!("Failed to load memory pool: {}", err),
+	}
 	let sync_connection_factory = create_sync_connection_factory(sync_peers.clone(), local_sync_node.clone());
 
 	if let Some(block_notify_command) = cfg.block_notify_command.clone() {
 		local_sync_node.install_sync_listener(Box::new(BlockNotifier::new(block_notify_command)));
 	}
 
+	let wallet = if cfg.wallet {
+		let network = match cfg.network {
+			Network::Mainnet => keys::Network::Mainnet,
+			Network::Regtest => keys::Network::Regtest,
+			_ => keys::Network::Testnet,
+		};
+		let store = WalletStore::persistent(&app_path(&cfg.data_dir, "wallet"), cfg.db_cache).map_err(|e| e.to_string())?;
+		let wallet = Arc::new(Wallet::open(store, network, db.as_store()).map_err(|e| e.to_string())?);
+		// catch up with blocks, canonized while the wallet was disabled
+		wallet.scan(db.as_store()).map_err(|e| e.to_string())?;
+		local_sync_node.install_sync_listener(Box::new(WalletNotifier::new(wallet.clone(), db.clone())));
+		Some(wallet)
+	} else {
+		None
+	};
+
 	let p2p_cfg = p2p::Config {
 		inbound_connections: cfg.inbound_connections,
 		outbound_connections: cfg.outbound_connections,
+		block_relay_connections: cfg.block_relay_connections,
 		connection: p2p::NetConfig {
 			protocol_version: PROTOCOL_VERSION,
 			protocol_minimum: PROTOCOL_MINIMUM,
@@ -46,14 +88,32 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 			user_agent: cfg.user_agent,
 			start_height: 0,
 			relay: true,
+			v2_transport: cfg.v2_transport,
+			txreconciliation: cfg.txreconciliation,
+			proxy: cfg.proxy,
+			onion_proxy: cfg.onion_proxy,
 		},
 		peers: cfg.connect.map_or_else(|| vec![], |x| vec![x]),
 		seed: cfg.seednode,
+		dns_seed: cfg.dns_seed,
 		node_table_path: app_path(&cfg.data_dir, "p2p"),
-		preferable_services: cfg.services,
+		ban_list_path: app_path(&cfg.data_dir, "p2p"),
+		// do not prefer peers that are serving block filters or supporting v2 transport
+		preferable_services: cfg.services.with_compact_filters(false).with_p2p_v2(false),
 		internet_protocol: cfg.internet_protocol,
+		asmap_path: cfg.asmap_path,
+		tor_control: cfg.tor_control,
+		max_upload_target: cfg.max_upload_target,
 	};
-	let p2p_context = Arc::new(p2p::Context::new(runtime.handle().clone(), sync_connection_factory, p2p_cfg).map_err(|e| e.to_string())?);
+	let p2p_context = Arc::new(
+		p2p::Context::new(
+			runtime.handle().clone(),
+			sync_connection_factory,
+			p2p_cfg,
+			Arc::new(p2p::SystemResolver),
+		)
+		.map_err(|e| e.to_string())?,
+	);
 	let p2p = p2p::P2P::new(p2p_context.clone());
 
 	let shutdown_signal = Arc::new(tokio::sync::Notify::new());
@@ -65,9 +125,20 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 		p2p_context,
 		memory: Arc::new(Memory::default()),
 		shutdown_signal: shutdown_signal.clone(),
+		wallet: wallet.clone(),
 	};
 	let rpc_server = rpc_server::new_http(cfg.rpc_config, rpc_deps)?.unwrap();
 
+	let electrum_deps = electrum::Dependencies {
+		consensus: cfg.consensus.clone(),
+		storage: db.clone(),
+		local_sync_node: local_sync_node.clone(),
+	};
+	let electrum_server = electrum::new_tcp(cfg.electrum_config, electrum_deps)?;
+	if let Some(ref electrum_server) = electrum_server {
+		local_sync_node.install_sync_listener(Box::new(electrum_server.notifier()));
+	}
+
 	let p2p2 = p2p.clone();
 	runtime.spawn(async move { p2p2.run().await });
 
@@ -80,9 +151,16 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 
 	info!("Shutting down, please wait...");
 	rpc_server.close();
+	if let Some(electrum_server) = electrum_server {
+		electrum_server.close();
+	}
 	p2p.shutdown();
 	local_sync_node.shutdown();
 	runtime.shutdown_timeout(Duration::from_secs(30));
+	if let Some(wallet) = wallet {
+		wallet.shutdown();
+	}
+	drop(block_filters_builder);
 	db.as_store().shutdown();
 
 	Ok(())
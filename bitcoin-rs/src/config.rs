@@ -1,11 +1,13 @@
 use clap;
+use electrum::ElectrumConfiguration;
+use message::common::{NetworkAddress, NodeAddress};
 use message::Services;
 use network::{ConsensusParams, Network};
-use p2p::InternetProtocol;
+use p2p::{InternetProtocol, Proxy, TorControl};
 use rpc_server::rpc_apis::ApiSet;
 use rpc_server::HttpConfiguration as RpcHttpConfig;
 use std::net;
-use sync::VerificationParameters;
+use sync::{MemoryPoolParameters, VerificationParameters};
 use verification::VerificationLevel;
 
 pub const USER_AGENT: &'static str = env!("CARGO_PKG_NAME");
@@ -19,18 +21,33 @@ pub struct Config {
 	pub consensus: ConsensusParams,
 	pub services: Services,
 	pub port: u16,
-	pub connect: Option<net::SocketAddr>,
+	pub connect: Option<NodeAddress>,
 	pub host: Option<net::IpAddr>,
 	pub seednode: Option<net::SocketAddr>,
+	pub dns_seed: bool,
 	pub inbound_connections: u32,
 	pub outbound_connections: u32,
+	pub block_relay_connections: u32,
+	pub asmap_path: Option<String>,
+	pub max_upload_target: Option<u64>,
 	pub db_cache: usize,
+	pub address_index: bool,
+	pub block_filters: bool,
+	pub v2_transport: bool,
+	pub txreconciliation: bool,
+	pub proxy: Option<Proxy>,
+	pub onion_proxy: Option<Proxy>,
+	pub tor_control: Option<TorControl>,
+	pub wallet: bool,
 	pub data_dir: Option<String>,
 	pub user_agent: String,
 	pub internet_protocol: InternetProtocol,
 	pub rpc_config: RpcHttpConfig,
+	pub electrum_config: ElectrumConfiguration,
 	pub block_notify_command: Option<String>,
 	pub verification_params: VerificationParameters,
+	pub memory_pool_params: MemoryPoolParameters,
+	pub persist_memory_pool: bool,
 }
 
 pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
@@ -53,9 +70,9 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 
 	let consensus = ConsensusParams::new(network);
 
-	let (in_connections, out_connections) = match network {
-		Network::Testnet | Network::Mainnet | Network::Other(_) => (10, 10),
-		Network::Regtest | Network::Unitest => (1, 0),
+	let (in_connections, out_connections, block_relay_connections) = match network {
+		Network::Testnet | Network::Mainnet | Network::Other(_) => (10, 10, 2),
+		Network::Regtest | Network::Unitest => (1, 0, 0),
 	};
 
 	let user_agent = match network {
@@ -69,10 +86,10 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 	};
 
 	let connect = match matches.value_of("connect") {
-		Some(s) => Some(match s.parse::<net::SocketAddr>() {
+		Some(s) => Some(match s.parse::<NodeAddress>() {
 			Err(_) => s
-				.parse::<net::IpAddr>()
-				.map(|ip| net::SocketAddr::new(ip, network.port()))
+				.parse::<NetworkAddress>()
+				.map(|address| NodeAddress::new(address, network.port()))
 				.map_err(|_| "Invalid connect".to_owned()),
 			Ok(a) => Ok(a),
 		}?),
@@ -84,27 +101,119 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		None => None,
 	};
 
+	// connect-only nodes do not need addresses of the other peers
+	let dns_seed = match matches.value_of("dnsseed") {
+		Some("0") => false,
+		Some("1") => true,
+		Some(_) => return Err("Invalid dnsseed - should be 0 or 1".into()),
+		None => connect.is_none(),
+	};
+
 	let only_net = match matches.value_of("only-net") {
 		Some(s) => s.parse()?,
 		None => InternetProtocol::default(),
 	};
 
+	let asmap_path = matches.value_of("asmap").map(ToOwned::to_owned);
+
+	// target is given in MiB, zero means no limit
+	let max_upload_target = match matches.value_of("maxuploadtarget") {
+		Some(s) => {
+			let target: u64 = s
+				.parse()
+				.map_err(|_| "Invalid maxuploadtarget - should be number in MiB".to_owned())?;
+			Some(target * 1024 * 1024).filter(|target| *target != 0)
+		}
+		None => None,
+	};
+
+	let mut memory_pool_params = MemoryPoolParameters::default();
+	// max size is given in MB, expiry is given in hours
+	if let Some(s) = matches.value_of("maxmempool") {
+		let max_memory_usage: usize = s.parse().map_err(|_| "Invalid maxmempool - should be number in MB".to_owned())?;
+		memory_pool_params.max_memory_usage = max_memory_usage * 1_000_000;
+	}
+	if let Some(s) = matches.value_of("mempoolexpiry") {
+		let expiry: u64 = s
+			.parse()
+			.map_err(|_| "Invalid mempoolexpiry - should be number in hours".to_owned())?;
+		memory_pool_params.expiry = expiry * 60 * 60;
+	}
+	memory_pool_params.full_rbf = matches.is_present("mempoolfullrbf");
+	// package sizes are given in kB
+	let package_limits = &mut memory_pool_params.package_limits;
+	if let Some(s) = matches.value_of("limitancestorcount") {
+		package_limits.ancestors_count = s.parse().map_err(|_| "Invalid limitancestorcount".to_owned())?;
+	}
+	if let Some(s) = matches.value_of("limitancestorsize") {
+		let size: usize = s
+			.parse()
+			.map_err(|_| "Invalid limitancestorsize - should be number in kB".to_owned())?;
+		package_limits.ancestors_size = size * 1000;
+	}
+	if let Some(s) = matches.value_of("limitdescendantcount") {
+		package_limits.descendants_count = s.parse().map_err(|_| "Invalid limitdescendantcount".to_owned())?;
+	}
+	if let Some(s) = matches.value_of("limitdescendantsize") {
+		let size: usize = s
+			.parse()
+			.map_err(|_| "Invalid limitdescendantsize - should be number in kB".to_owned())?;
+		package_limits.descendants_size = size * 1000;
+	}
+
+	let randomize_credentials = !matches.is_present("no-proxy-randomize");
+	let proxy = match matches.value_of("proxy") {
+		Some(s) => Some(Proxy {
+			address: s.parse().map_err(|_| "Invalid proxy".to_owned())?,
+			randomize_credentials,
+		}),
+		None => None,
+	};
+
+	let onion_proxy = match matches.value_of("onion") {
+		Some(s) => Some(Proxy {
+			address: s.parse().map_err(|_| "Invalid onion proxy".to_owned())?,
+			randomize_credentials,
+		}),
+		None => None,
+	};
+
+	if only_net == InternetProtocol::Tor && proxy.is_none() && onion_proxy.is_none() {
+		return Err("Onion network is reachable only through the proxy, use --proxy or --onion".into());
+	}
+
+	let tor_control = match matches.value_of("tor-control") {
+		Some(s) => Some(TorControl {
+			address: s.parse().map_err(|_| "Invalid tor-control".to_owned())?,
+			password: matches.value_of("tor-password").map(ToOwned::to_owned),
+		}),
+		None => None,
+	};
+
 	let host = match matches.value_of("host") {
 		Some(s) => Some(s.parse::<net::IpAddr>().map_err(|_| "Invalid host".to_owned())?),
 		None => match only_net {
-			InternetProtocol::IpV6 => Some("::".parse().unwrap()),
+			InternetProtocol::IpV6 | InternetProtocol::Cjdns => Some("::".parse().unwrap()),
 			_ => Some("0.0.0.0".parse().unwrap()),
 		},
 	};
 
 	let rpc_config = parse_rpc_config(network, matches)?;
 
+	let electrum_config = parse_electrum_config(network, matches)?;
+
 	let block_notify_command = match matches.value_of("blocknotify") {
 		Some(s) => Some(s.parse().map_err(|_| "Invalid blocknotify commmand".to_owned())?),
 		None => None,
 	};
 
-	let services = Services::default().with_network(true).with_witness(true);
+	let block_filters = matches.is_present("block-filters");
+	let v2_transport = matches.is_present("v2-transport");
+	let services = Services::default()
+		.with_network(true)
+		.with_witness(true)
+		.with_compact_filters(block_filters)
+		.with_p2p_v2(v2_transport);
 
 	let verification_level = match matches.value_of("verification-level") {
 		Some(s) if s == "full" => VerificationLevel::Full,
@@ -127,18 +236,33 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		connect,
 		host,
 		seednode,
+		dns_seed,
 		inbound_connections: in_connections,
 		outbound_connections: out_connections,
+		block_relay_connections,
+		asmap_path,
+		max_upload_target,
 		db_cache,
+		address_index: matches.is_present("address-index") || electrum_config.enabled,
+		block_filters,
+		v2_transport,
+		txreconciliation: matches.is_present("txreconciliation"),
+		proxy,
+		onion_proxy,
+		tor_control,
+		wallet: matches.is_present("wallet"),
 		data_dir,
 		user_agent,
 		internet_protocol: only_net,
 		rpc_config,
+		electrum_config,
 		block_notify_command,
 		verification_params: VerificationParameters {
 			verification_level,
 			verification_edge,
 		},
+		memory_pool_params,
+		persist_memory_pool: !matches.is_present("nopersistmempool"),
 	};
 
 	Ok(config)
@@ -169,3 +293,20 @@ fn parse_rpc_config(network: Network, matches: &clap::ArgMatches) -> Result<RpcH
 
 	Ok(config)
 }
+
+fn parse_electrum_config(network: Network, matches: &clap::ArgMatches) -> Result<ElectrumConfiguration, String> {
+	let mut config = ElectrumConfiguration::with_port(network.electrum_port());
+	config.enabled = matches.is_present("electrum");
+	if !config.enabled {
+		return Ok(config);
+	}
+
+	if let Some(port) = matches.value_of("electrum-port") {
+		config.port = port.parse().map_err(|_| "Invalid Electrum port".to_owned())?;
+	}
+	if let Some(interface) = matches.value_of("electrum-interface") {
+		config.interface = interface.to_owned();
+	}
+
+	Ok(config)
+}
@@ -8,6 +8,7 @@ extern crate app_dirs;
 extern crate libc;
 
 mod app_dir;
+mod block_filters_builder;
 mod block_notifier;
 mod commands;
 mod config;
@@ -0,0 +1,272 @@
+//! BIP158 compact block filters.
+//! https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki
+
+use crate::bytes::Bytes;
+use crate::IndexedBlock;
+use bitcrypto::{dhash256, siphash24, SHA256D};
+use ser::{CompactInteger, Reader, Serializable, Stream};
+use std::collections::HashSet;
+
+/// Type of the basic block filter
+pub const BASIC_FILTER_TYPE: u8 = 0;
+/// Golomb-Rice coding parameter of the basic block filter
+const BASIC_FILTER_P: u8 = 19;
+/// Inverse of the false positive rate of the basic block filter
+const BASIC_FILTER_M: u64 = 784_931;
+/// OP_RETURN opcode. Outputs, starting with this opcode, are not included into the basic filter
+const OP_RETURN: u8 = 0x6a;
+
+/// Golomb-coded set of block elements: number of elements, followed by Golomb-Rice coded deltas
+/// between sorted hashes of elements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockFilter {
+	pub content: Bytes,
+}
+
+impl BlockFilter {
+	pub fn new(content: Bytes) -> Self {
+		BlockFilter { content }
+	}
+
+	/// Builds the basic filter of the block. The filter includes all scripts of the block outputs
+	/// (except empty and OP_RETURN ones) and all scripts of outputs, spent by the block.
+	pub fn basic<'a, I>(block: &'a IndexedBlock, spent_scripts: I) -> Self
+	where
+		I: IntoIterator<Item = &'a [u8]>,
+	{
+		let mut elements: HashSet<&[u8]> = block
+			.transactions
+			.iter()
+			.flat_map(|tx| tx.raw.outputs.iter())
+			.map(|output| &output.script_pubkey[..])
+			.filter(|script| !script.is_empty() && script[0] != OP_RETURN)
+			.collect();
+		elements.extend(spent_scripts.into_iter().filter(|script| !script.is_empty()));
+
+		let mut hashes = hashed_set(block.hash(), elements.into_iter());
+		hashes.sort_unstable();
+
+		let mut stream = Stream::new();
+		stream.append(&CompactInteger::from(hashes.len()));
+		let mut writer = BitWriter::default();
+		let mut last_hash = 0;
+		for hash in hashes {
+			golomb_rice_encode(&mut writer, hash - last_hash);
+			last_hash = hash;
+		}
+		stream.append_slice(&writer.finish());
+
+		BlockFilter::new(stream.out())
+	}
+
+	/// Hash of the filter
+	pub fn hash(&self) -> SHA256D {
+		dhash256(&self.content)
+	}
+
+	/// Filter header, which commits to this filter and to all previous filters
+	pub fn header(&self, previous_header: &SHA256D) -> SHA256D {
+		let mut data = Vec::with_capacity(64);
+		data.extend_from_slice(&self.hash()[..]);
+		data.extend_from_slice(&previous_header[..]);
+		dhash256(&data)
+	}
+
+	/// Returns true if any of elements (probably) belongs to the filter.
+	/// Returns false if none of elements belong to the filter or if filter is malformed.
+	pub fn match_any<'a, I>(&self, block_hash: &SHA256D, elements: I) -> bool
+	where
+		I: Iterator<Item = &'a [u8]>,
+	{
+		let mut reader = Reader::new(&self.content);
+		let count = match reader.read::<CompactInteger>() {
+			Ok(count) => count,
+			Err(_) => return false,
+		};
+		let data = &self.content[count.serialized_size()..];
+		let count: u64 = count.into();
+		let mut queries = hashed_set_with_count(block_hash, elements, count);
+		queries.sort_unstable();
+
+		let mut bit_reader = BitReader::new(data);
+		let mut queries = queries.into_iter().peekable();
+		let mut value = 0;
+		for _ in 0..count {
+			value += match golomb_rice_decode(&mut bit_reader) {
+				Some(delta) => delta,
+				None => return false,
+			};
+			while let Some(query) = queries.peek() {
+				if *query > value {
+					break;
+				}
+				if *query == value {
+					return true;
+				}
+				queries.next();
+			}
+			if queries.peek().is_none() {
+				return false;
+			}
+		}
+		false
+	}
+}
+
+/// Maps elements to the [0; N * M) range
+fn hashed_set<'a, I>(block_hash: &SHA256D, elements: I) -> Vec<u64>
+where
+	I: ExactSizeIterator<Item = &'a [u8]>,
+{
+	let count = elements.len() as u64;
+	hashed_set_with_count(block_hash, elements, count)
+}
+
+fn hashed_set_with_count<'a, I>(block_hash: &SHA256D, elements: I, count: u64) -> Vec<u64>
+where
+	I: Iterator<Item = &'a [u8]>,
+{
+	let mut key0 = [0u8; 8];
+	let mut key1 = [0u8; 8];
+	key0.copy_from_slice(&block_hash[0..8]);
+	key1.copy_from_slice(&block_hash[8..16]);
+	let (key0, key1) = (u64::from_le_bytes(key0), u64::from_le_bytes(key1));
+	let range = count * BASIC_FILTER_M;
+	elements
+		.map(|element| ((siphash24(key0, key1, element) as u128 * range as u128) >> 64) as u64)
+		.collect()
+}
+
+fn golomb_rice_encode(writer: &mut BitWriter, value: u64) {
+	let quotient = value >> BASIC_FILTER_P;
+	for _ in 0..quotient {
+		writer.write_bit(true);
+	}
+	writer.write_bit(false);
+	writer.write_bits(value, BASIC_FILTER_P);
+}
+
+fn golomb_rice_decode(reader: &mut BitReader) -> Option<u64> {
+	let mut quotient = 0;
+	while reader.read_bit()? {
+		quotient += 1;
+	}
+	let remainder = reader.read_bits(BASIC_FILTER_P)?;
+	Some((quotient << BASIC_FILTER_P) + remainder)
+}
+
+/// Writes bits, most significant bit of every byte first
+#[derive(Default)]
+struct BitWriter {
+	data: Vec<u8>,
+	bits_in_last_byte: u8,
+}
+
+impl BitWriter {
+	fn write_bit(&mut self, bit: bool) {
+		if self.bits_in_last_byte == 0 {
+			self.data.push(0);
+		}
+		if bit {
+			*self.data.last_mut().expect("pushed above") |= 0x80 >> self.bits_in_last_byte;
+		}
+		self.bits_in_last_byte = (self.bits_in_last_byte + 1) % 8;
+	}
+
+	fn write_bits(&mut self, value: u64, count: u8) {
+		for bit in (0..count).rev() {
+			self.write_bit(value & (1 << bit) != 0);
+		}
+	}
+
+	fn finish(self) -> Vec<u8> {
+		self.data
+	}
+}
+
+/// Reads bits, written by the `BitWriter`
+struct BitReader<'a> {
+	data: &'a [u8],
+	position: usize,
+}
+
+impl<'a> BitReader<'a> {
+	fn new(data: &'a [u8]) -> Self {
+		BitReader { data, position: 0 }
+	}
+
+	fn read_bit(&mut self) -> Option<bool> {
+		let byte = self.data.get(self.position / 8)?;
+		let bit = byte & (0x80 >> (self.position % 8)) != 0;
+		self.position += 1;
+		Some(bit)
+	}
+
+	fn read_bits(&mut self, count: u8) -> Option<u64> {
+		let mut value = 0;
+		for _ in 0..count {
+			value = (value << 1) | self.read_bit()? as u64;
+		}
+		Some(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Block, TransactionOutput};
+	use bitcrypto::FromStr;
+
+	// testnet genesis block
+	const BLOCK_0: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff001d1aa4ae180101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+	#[test]
+	fn basic_filter_bip158_test_vector() {
+		let block: IndexedBlock = BLOCK_0.into();
+		assert_eq!(
+			*block.hash(),
+			SHA256D::from_str("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943").unwrap()
+		);
+
+		let filter = BlockFilter::basic(&block, vec![]);
+		assert_eq!(filter.content, "019dfca8".into());
+		assert_eq!(
+			filter.header(&SHA256D::default()),
+			SHA256D::from_str("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750").unwrap()
+		);
+	}
+
+	#[test]
+	fn basic_filter_empty() {
+		let mut block: Block = BLOCK_0.into();
+		block.transactions[0].outputs[0].script_pubkey = vec![OP_RETURN].into();
+		let block = IndexedBlock::from_raw(block);
+		let filter = BlockFilter::basic(&block, vec![&[][..]]);
+		assert_eq!(filter.content, "00".into());
+		assert!(!filter.match_any(block.hash(), vec![&[OP_RETURN][..]].into_iter()));
+	}
+
+	#[test]
+	fn basic_filter_match_any() {
+		let mut block: Block = BLOCK_0.into();
+		let scripts: Vec<Vec<u8>> = (0..100u32).map(|i| i.to_le_bytes().to_vec()).collect();
+		block.transactions[0].outputs = scripts[0..50]
+			.iter()
+			.map(|script| TransactionOutput {
+				value: 0,
+				script_pubkey: script.clone().into(),
+			})
+			.collect();
+		let block = IndexedBlock::from_raw(block);
+		let spent: Vec<&[u8]> = scripts[50..60].iter().map(|script| &script[..]).collect();
+		let filter = BlockFilter::basic(&block, spent);
+
+		for script in &scripts[0..60] {
+			assert!(filter.match_any(block.hash(), vec![&script[..]].into_iter()));
+		}
+		assert!(filter.match_any(block.hash(), scripts[55..70].iter().map(|script| &script[..])));
+		// false positive rate is 1/784931 => there should be no false positives here
+		assert!(!filter.match_any(block.hash(), scripts[60..100].iter().map(|script| &script[..])));
+		assert!(!filter.match_any(&SHA256D::default(), scripts[0..1].iter().map(|script| &script[..])));
+	}
+}
@@ -6,12 +6,14 @@ extern crate serialization as ser;
 extern crate serialization_derive;
 
 pub use block::Block;
+pub use block_filter::{BlockFilter, BASIC_FILTER_TYPE};
 pub use block_header::BlockHeader;
 pub use indexed_block::IndexedBlock;
 pub use indexed_header::IndexedBlockHeader;
 pub use indexed_transaction::IndexedTransaction;
 pub use merkle_root::{merkle_node_hash, merkle_root};
 pub use primitives::{bytes, compact, hash};
+pub use psbt::{KeySource, Psbt, PsbtError, PsbtInput, PsbtOutput, PSBT_MAGIC};
 pub use read_and_hash::{HashedData, ReadAndHash};
 pub use short_transaction_id::ShortTransactionID;
 pub use transaction::{OutPoint, Transaction, TransactionInput, TransactionOutput};
@@ -19,11 +21,13 @@ pub use transaction::{OutPoint, Transaction, TransactionInput, TransactionOutput
 pub mod constants;
 
 mod block;
+mod block_filter;
 mod block_header;
 mod indexed_block;
 mod indexed_header;
 mod indexed_transaction;
 mod merkle_root;
+mod psbt;
 /// `IndexedBlock` extension
 mod read_and_hash;
 mod short_transaction_id;
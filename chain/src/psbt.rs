@@ -0,0 +1,1161 @@
+//! Partially signed bitcoin transactions.
+//! https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki
+
+use crate::bytes::Bytes;
+use crate::constants::{LOCKTIME_THRESHOLD, SEQUENCE_FINAL};
+use crate::transaction::transaction_hash;
+use crate::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+use bitcrypto::SHA256D;
+use ser::{deserialize, serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use ser::{CompactInteger, Deserializable, Reader, Serializable, Stream};
+use std::collections::BTreeMap;
+use std::{fmt, str};
+
+/// Every serialized PSBT starts with these bytes
+pub const PSBT_MAGIC: [u8; 5] = *b"psbt\xff";
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_GLOBAL_TX_VERSION: u8 = 0x02;
+const PSBT_GLOBAL_FALLBACK_LOCKTIME: u8 = 0x03;
+const PSBT_GLOBAL_INPUT_COUNT: u8 = 0x04;
+const PSBT_GLOBAL_OUTPUT_COUNT: u8 = 0x05;
+const PSBT_GLOBAL_TX_MODIFIABLE: u8 = 0x06;
+const PSBT_GLOBAL_VERSION: u8 = 0xfb;
+
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+const PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+const PSBT_IN_BIP32_DERIVATION: u8 = 0x06;
+const PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+const PSBT_IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+const PSBT_IN_PREVIOUS_TXID: u8 = 0x0e;
+const PSBT_IN_OUTPUT_INDEX: u8 = 0x0f;
+const PSBT_IN_SEQUENCE: u8 = 0x10;
+const PSBT_IN_REQUIRED_TIME_LOCKTIME: u8 = 0x11;
+const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u8 = 0x12;
+
+const PSBT_OUT_REDEEM_SCRIPT: u8 = 0x00;
+const PSBT_OUT_WITNESS_SCRIPT: u8 = 0x01;
+const PSBT_OUT_BIP32_DERIVATION: u8 = 0x02;
+const PSBT_OUT_AMOUNT: u8 = 0x03;
+const PSBT_OUT_SCRIPT: u8 = 0x04;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PsbtError {
+	/// Data doesn't start with the PSBT magic bytes
+	InvalidMagic,
+	/// String is not a valid base64
+	InvalidBase64,
+	/// Key-value maps are truncated or followed by the unexpected data
+	Malformed,
+	/// The same key appears in the map twice
+	DuplicateKey,
+	/// Key data doesn't match the key type
+	InvalidKey,
+	/// Value can't be decoded
+	InvalidValue,
+	UnsupportedVersion(u32),
+	/// Required field is missing
+	MissingField(&'static str),
+	/// Field is not allowed in the PSBT of this version
+	UnexpectedField(&'static str),
+	/// Unsigned transaction has non-empty script sigs or witnesses
+	UnsignedTransactionHasScripts,
+	/// Non-witness UTXO is not the transaction, spent by the input
+	InvalidNonWitnessUtxo,
+	/// PSBTs are describing different transactions
+	MismatchedTransaction,
+	/// Some inputs require height based lock time, others require time based lock time
+	LockTimeConflict,
+	InputOutOfRange(usize),
+	/// Input has no final script sig or witness
+	NotFinalized(usize),
+	/// Output, spent by the input, is unknown
+	MissingUtxo(usize),
+	/// Redeem or witness script doesn't match the output, spent by the input
+	ScriptMismatch(usize),
+	/// Input spends the output of unsupported type
+	UnsupportedScript(usize),
+	/// Private key can't sign the input
+	Signing,
+}
+
+impl fmt::Display for PsbtError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			PsbtError::InvalidMagic => "Invalid PSBT magic bytes".fmt(f),
+			PsbtError::InvalidBase64 => "Invalid base64".fmt(f),
+			PsbtError::Malformed => "Malformed PSBT".fmt(f),
+			PsbtError::DuplicateKey => "Duplicate key".fmt(f),
+			PsbtError::InvalidKey => "Invalid key".fmt(f),
+			PsbtError::InvalidValue => "Invalid value".fmt(f),
+			PsbtError::UnsupportedVersion(version) => write!(f, "Unsupported PSBT version {}", version),
+			PsbtError::MissingField(field) => write!(f, "Missing {}", field),
+			PsbtError::UnexpectedField(field) => write!(f, "Unexpected {}", field),
+			PsbtError::UnsignedTransactionHasScripts => "Unsigned transaction has scripts".fmt(f),
+			PsbtError::InvalidNonWitnessUtxo => "Non-witness UTXO doesn't match the input".fmt(f),
+			PsbtError::MismatchedTransaction => "PSBTs are describing different transactions".fmt(f),
+			PsbtError::LockTimeConflict => "Inputs have conflicting lock times".fmt(f),
+			PsbtError::InputOutOfRange(index) => write!(f, "Input {} is out of range", index),
+			PsbtError::NotFinalized(index) => write!(f, "Input {} is not finalized", index),
+			PsbtError::MissingUtxo(index) => write!(f, "Output, spent by input {}, is unknown", index),
+			PsbtError::ScriptMismatch(index) => write!(f, "Scripts of input {} don't match the spent output", index),
+			PsbtError::UnsupportedScript(index) => write!(f, "Input {} spends unsupported script", index),
+			PsbtError::Signing => "Signing has failed".fmt(f),
+		}
+	}
+}
+
+impl std::error::Error for PsbtError {}
+
+impl From<ser::Error> for PsbtError {
+	fn from(_: ser::Error) -> Self {
+		PsbtError::Malformed
+	}
+}
+
+/// Origin of the key: fingerprint of the master key and derivation path from the master key
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KeySource {
+	pub fingerprint: [u8; 4],
+	pub path: Vec<u32>,
+}
+
+impl KeySource {
+	fn from_value(value: &[u8]) -> Result<Self, PsbtError> {
+		if value.len() < 4 || !value.len().is_multiple_of(4) {
+			return Err(PsbtError::InvalidValue);
+		}
+
+		let mut fingerprint = [0u8; 4];
+		fingerprint.copy_from_slice(&value[..4]);
+		let path = value[4..]
+			.chunks(4)
+			.map(|index| u32::from_le_bytes([index[0], index[1], index[2], index[3]]))
+			.collect();
+		Ok(KeySource { fingerprint, path })
+	}
+
+	fn to_value(&self) -> Bytes {
+		let mut stream = Stream::new();
+		stream.append_slice(&self.fingerprint);
+		for index in &self.path {
+			stream.append(index);
+		}
+		stream.out()
+	}
+}
+
+/// Per-input data of the PSBT
+#[derive(Debug, Clone, PartialEq)]
+pub struct PsbtInput {
+	pub previous_output: OutPoint,
+	pub sequence: u32,
+	/// Time based lock time, required by the input (version 2 only)
+	pub required_time_lock_time: Option<u32>,
+	/// Height based lock time, required by the input (version 2 only)
+	pub required_height_lock_time: Option<u32>,
+	/// Transaction, spent by the input
+	pub non_witness_utxo: Option<Transaction>,
+	/// Output, spent by the input
+	pub witness_utxo: Option<TransactionOutput>,
+	/// Signatures, keyed by public keys
+	pub partial_sigs: BTreeMap<Bytes, Bytes>,
+	pub sighash_type: Option<u32>,
+	pub redeem_script: Option<Bytes>,
+	pub witness_script: Option<Bytes>,
+	/// Origins of public keys
+	pub bip32_derivation: BTreeMap<Bytes, KeySource>,
+	pub final_script_sig: Option<Bytes>,
+	pub final_script_witness: Option<Vec<Bytes>>,
+	/// Unknown and proprietary pairs, keyed by the whole key
+	pub unknown: BTreeMap<Bytes, Bytes>,
+}
+
+impl PsbtInput {
+	pub fn new(previous_output: OutPoint, sequence: u32) -> Self {
+		PsbtInput {
+			previous_output,
+			sequence,
+			required_time_lock_time: None,
+			required_height_lock_time: None,
+			non_witness_utxo: None,
+			witness_utxo: None,
+			partial_sigs: BTreeMap::new(),
+			sighash_type: None,
+			redeem_script: None,
+			witness_script: None,
+			bip32_derivation: BTreeMap::new(),
+			final_script_sig: None,
+			final_script_witness: None,
+			unknown: BTreeMap::new(),
+		}
+	}
+
+	/// Output, spent by the input
+	pub fn utxo(&self) -> Option<TransactionOutput> {
+		match self.non_witness_utxo {
+			Some(ref transaction) => transaction.outputs.get(self.previous_output.index as usize).cloned(),
+			None => self.witness_utxo.clone(),
+		}
+	}
+
+	pub fn is_finalized(&self) -> bool {
+		self.final_script_sig.is_some() || self.final_script_witness.is_some()
+	}
+
+	fn combine(&mut self, other: PsbtInput) {
+		self.non_witness_utxo = self.non_witness_utxo.take().or(other.non_witness_utxo);
+		self.witness_utxo = self.witness_utxo.take().or(other.witness_utxo);
+		self.sighash_type = self.sighash_type.or(other.sighash_type);
+		self.redeem_script = self.redeem_script.take().or(other.redeem_script);
+		self.witness_script = self.witness_script.take().or(other.witness_script);
+		self.final_script_sig = self.final_script_sig.take().or(other.final_script_sig);
+		self.final_script_witness = self.final_script_witness.take().or(other.final_script_witness);
+		merge(&mut self.partial_sigs, other.partial_sigs);
+		merge(&mut self.bip32_derivation, other.bip32_derivation);
+		merge(&mut self.unknown, other.unknown);
+	}
+
+	/// `unsigned` is the input of the unsigned transaction (version 0 only)
+	fn parse(pairs: Vec<(Bytes, Bytes)>, version: u32, unsigned: Option<&TransactionInput>) -> Result<Self, PsbtError> {
+		let mut input = PsbtInput::new(OutPoint::default(), SEQUENCE_FINAL);
+		let mut previous_hash = None;
+		let mut previous_index = None;
+		let mut sequence = None;
+		for (key, value) in pairs {
+			match key[0] {
+				PSBT_IN_NON_WITNESS_UTXO => {
+					expect_no_key_data(&key)?;
+					input.non_witness_utxo = Some(parse_value(&value)?);
+				}
+				PSBT_IN_WITNESS_UTXO => {
+					expect_no_key_data(&key)?;
+					input.witness_utxo = Some(parse_value(&value)?);
+				}
+				PSBT_IN_PARTIAL_SIG => {
+					input.partial_sigs.insert(public_key_data(&key)?, value);
+				}
+				PSBT_IN_SIGHASH_TYPE => {
+					expect_no_key_data(&key)?;
+					input.sighash_type = Some(parse_value(&value)?);
+				}
+				PSBT_IN_REDEEM_SCRIPT => {
+					expect_no_key_data(&key)?;
+					input.redeem_script = Some(value);
+				}
+				PSBT_IN_WITNESS_SCRIPT => {
+					expect_no_key_data(&key)?;
+					input.witness_script = Some(value);
+				}
+				PSBT_IN_BIP32_DERIVATION => {
+					input
+						.bip32_derivation
+						.insert(public_key_data(&key)?, KeySource::from_value(&value)?);
+				}
+				PSBT_IN_FINAL_SCRIPTSIG => {
+					expect_no_key_data(&key)?;
+					input.final_script_sig = Some(value);
+				}
+				PSBT_IN_FINAL_SCRIPTWITNESS => {
+					expect_no_key_data(&key)?;
+					input.final_script_witness = Some(parse_value::<ser::List<Bytes>>(&value)?.into());
+				}
+				PSBT_IN_PREVIOUS_TXID => {
+					expect_version_2(&key, version, "previous txid")?;
+					previous_hash = Some(parse_value::<SHA256D>(&value)?);
+				}
+				PSBT_IN_OUTPUT_INDEX => {
+					expect_version_2(&key, version, "output index")?;
+					previous_index = Some(parse_value::<u32>(&value)?);
+				}
+				PSBT_IN_SEQUENCE => {
+					expect_version_2(&key, version, "sequence")?;
+					sequence = Some(parse_value::<u32>(&value)?);
+				}
+				PSBT_IN_REQUIRED_TIME_LOCKTIME => {
+					expect_version_2(&key, version, "required time lock time")?;
+					let lock_time = parse_value::<u32>(&value)?;
+					if lock_time < LOCKTIME_THRESHOLD {
+						return Err(PsbtError::InvalidValue);
+					}
+					input.required_time_lock_time = Some(lock_time);
+				}
+				PSBT_IN_REQUIRED_HEIGHT_LOCKTIME => {
+					expect_version_2(&key, version, "required height lock time")?;
+					let lock_time = parse_value::<u32>(&value)?;
+					if lock_time == 0 || lock_time >= LOCKTIME_THRESHOLD {
+						return Err(PsbtError::InvalidValue);
+					}
+					input.required_height_lock_time = Some(lock_time);
+				}
+				_ => {
+					input.unknown.insert(key, value);
+				}
+			}
+		}
+
+		match unsigned {
+			Some(unsigned) => {
+				input.previous_output = unsigned.previous_output.clone();
+				input.sequence = unsigned.sequence;
+			}
+			None => {
+				input.previous_output = OutPoint {
+					hash: previous_hash.ok_or(PsbtError::MissingField("previous txid"))?,
+					index: previous_index.ok_or(PsbtError::MissingField("output index"))?,
+				};
+				input.sequence = sequence.unwrap_or(SEQUENCE_FINAL);
+			}
+		}
+
+		if let Some(ref transaction) = input.non_witness_utxo {
+			if transaction_hash(transaction) != input.previous_output.hash {
+				return Err(PsbtError::InvalidNonWitnessUtxo);
+			}
+		}
+
+		Ok(input)
+	}
+
+	fn serialize(&self, stream: &mut Stream, version: u32) {
+		if version != 0 {
+			write_pair(stream, &[PSBT_IN_PREVIOUS_TXID], &serialize(&self.previous_output.hash));
+			write_pair(stream, &[PSBT_IN_OUTPUT_INDEX], &serialize(&self.previous_output.index));
+			if self.sequence != SEQUENCE_FINAL {
+				write_pair(stream, &[PSBT_IN_SEQUENCE], &serialize(&self.sequence));
+			}
+			if let Some(lock_time) = self.required_time_lock_time {
+				write_pair(stream, &[PSBT_IN_REQUIRED_TIME_LOCKTIME], &serialize(&lock_time));
+			}
+			if let Some(lock_time) = self.required_height_lock_time {
+				write_pair(stream, &[PSBT_IN_REQUIRED_HEIGHT_LOCKTIME], &serialize(&lock_time));
+			}
+		}
+		if let Some(ref transaction) = self.non_witness_utxo {
+			write_pair(
+				stream,
+				&[PSBT_IN_NON_WITNESS_UTXO],
+				&serialize_with_flags(transaction, SERIALIZE_TRANSACTION_WITNESS),
+			);
+		}
+		if let Some(ref output) = self.witness_utxo {
+			write_pair(stream, &[PSBT_IN_WITNESS_UTXO], &serialize(output));
+		}
+		for (public, signature) in &self.partial_sigs {
+			write_pair(stream, &key(PSBT_IN_PARTIAL_SIG, public), signature);
+		}
+		if let Some(sighash_type) = self.sighash_type {
+			write_pair(stream, &[PSBT_IN_SIGHASH_TYPE], &serialize(&sighash_type));
+		}
+		if let Some(ref script) = self.redeem_script {
+			write_pair(stream, &[PSBT_IN_REDEEM_SCRIPT], script);
+		}
+		if let Some(ref script) = self.witness_script {
+			write_pair(stream, &[PSBT_IN_WITNESS_SCRIPT], script);
+		}
+		for (public, source) in &self.bip32_derivation {
+			write_pair(stream, &key(PSBT_IN_BIP32_DERIVATION, public), &source.to_value());
+		}
+		if let Some(ref script) = self.final_script_sig {
+			write_pair(stream, &[PSBT_IN_FINAL_SCRIPTSIG], script);
+		}
+		if let Some(ref witness) = self.final_script_witness {
+			let mut value = Stream::new();
+			value.append_list(witness);
+			write_pair(stream, &[PSBT_IN_FINAL_SCRIPTWITNESS], &value.out());
+		}
+		write_map_end(stream, &self.unknown);
+	}
+}
+
+/// Per-output data of the PSBT
+#[derive(Debug, Clone, PartialEq)]
+pub struct PsbtOutput {
+	pub value: u64,
+	pub script_pubkey: Bytes,
+	pub redeem_script: Option<Bytes>,
+	pub witness_script: Option<Bytes>,
+	/// Origins of public keys
+	pub bip32_derivation: BTreeMap<Bytes, KeySource>,
+	/// Unknown and proprietary pairs, keyed by the whole key
+	pub unknown: BTreeMap<Bytes, Bytes>,
+}
+
+impl PsbtOutput {
+	pub fn new(value: u64, script_pubkey: Bytes) -> Self {
+		PsbtOutput {
+			value,
+			script_pubkey,
+			redeem_script: None,
+			witness_script: None,
+			bip32_derivation: BTreeMap::new(),
+			unknown: BTreeMap::new(),
+		}
+	}
+
+	fn combine(&mut self, other: PsbtOutput) {
+		self.redeem_script = self.redeem_script.take().or(other.redeem_script);
+		self.witness_script = self.witness_script.take().or(other.witness_script);
+		merge(&mut self.bip32_derivation, other.bip32_derivation);
+		merge(&mut self.unknown, other.unknown);
+	}
+
+	/// `unsigned` is the output of the unsigned transaction (version 0 only)
+	fn parse(pairs: Vec<(Bytes, Bytes)>, version: u32, unsigned: Option<&TransactionOutput>) -> Result<Self, PsbtError> {
+		let mut output = PsbtOutput::new(0, Bytes::new());
+		let mut value = None;
+		let mut script_pubkey = None;
+		for (key, pair_value) in pairs {
+			match key[0] {
+				PSBT_OUT_REDEEM_SCRIPT => {
+					expect_no_key_data(&key)?;
+					output.redeem_script = Some(pair_value);
+				}
+				PSBT_OUT_WITNESS_SCRIPT => {
+					expect_no_key_data(&key)?;
+					output.witness_script = Some(pair_value);
+				}
+				PSBT_OUT_BIP32_DERIVATION => {
+					output
+						.bip32_derivation
+						.insert(public_key_data(&key)?, KeySource::from_value(&pair_value)?);
+				}
+				PSBT_OUT_AMOUNT => {
+					expect_version_2(&key, version, "amount")?;
+					value = Some(parse_value::<u64>(&pair_value)?);
+				}
+				PSBT_OUT_SCRIPT => {
+					expect_version_2(&key, version, "script")?;
+					script_pubkey = Some(pair_value);
+				}
+				_ => {
+					output.unknown.insert(key, pair_value);
+				}
+			}
+		}
+
+		match unsigned {
+			Some(unsigned) => {
+				output.value = unsigned.value;
+				output.script_pubkey = unsigned.script_pubkey.clone();
+			}
+			None => {
+				output.value = value.ok_or(PsbtError::MissingField("amount"))?;
+				output.script_pubkey = script_pubkey.ok_or(PsbtError::MissingField("script"))?;
+			}
+		}
+
+		Ok(output)
+	}
+
+	fn serialize(&self, stream: &mut Stream, version: u32) {
+		if version != 0 {
+			write_pair(stream, &[PSBT_OUT_AMOUNT], &serialize(&self.value));
+			write_pair(stream, &[PSBT_OUT_SCRIPT], &self.script_pubkey);
+		}
+		if let Some(ref script) = self.redeem_script {
+			write_pair(stream, &[PSBT_OUT_REDEEM_SCRIPT], script);
+		}
+		if let Some(ref script) = self.witness_script {
+			write_pair(stream, &[PSBT_OUT_WITNESS_SCRIPT], script);
+		}
+		for (public, source) in &self.bip32_derivation {
+			write_pair(stream, &key(PSBT_OUT_BIP32_DERIVATION, public), &source.to_value());
+		}
+		write_map_end(stream, &self.unknown);
+	}
+}
+
+/// Partially signed bitcoin transaction of version 0 (BIP174) or version 2 (BIP370).
+/// Both versions are represented the same way: the unsigned transaction of version 0 PSBT
+/// is split between global, input and output fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Psbt {
+	pub version: u32,
+	pub tx_version: i32,
+	/// Lock time of the transaction, if none of inputs require a specific lock time
+	pub fallback_lock_time: Option<u32>,
+	/// Flags, describing which parts of the transaction could be modified (version 2 only)
+	pub tx_modifiable: Option<u8>,
+	pub inputs: Vec<PsbtInput>,
+	pub outputs: Vec<PsbtOutput>,
+	/// Unknown, proprietary and extended public key pairs, keyed by the whole key
+	pub unknown: BTreeMap<Bytes, Bytes>,
+}
+
+impl Psbt {
+	/// Creates PSBT from the transaction without script sigs and witnesses
+	pub fn from_unsigned_transaction(transaction: Transaction, version: u32) -> Result<Self, PsbtError> {
+		if version != 0 && version != 2 {
+			return Err(PsbtError::UnsupportedVersion(version));
+		}
+		if transaction
+			.inputs
+			.iter()
+			.any(|input| !input.script_sig.is_empty() || input.has_witness())
+		{
+			return Err(PsbtError::UnsignedTransactionHasScripts);
+		}
+
+		Ok(Psbt {
+			version,
+			tx_version: transaction.version,
+			fallback_lock_time: Some(transaction.lock_time),
+			tx_modifiable: None,
+			inputs: transaction
+				.inputs
+				.into_iter()
+				.map(|input| PsbtInput::new(input.previous_output, input.sequence))
+				.collect(),
+			outputs: transaction
+				.outputs
+				.into_iter()
+				.map(|output| PsbtOutput::new(output.value, output.script_pubkey))
+				.collect(),
+			unknown: BTreeMap::new(),
+		})
+	}
+
+	/// Parses serialized PSBT
+	pub fn from_slice(data: &[u8]) -> Result<Self, PsbtError> {
+		if data.len() < PSBT_MAGIC.len() || data[..PSBT_MAGIC.len()] != PSBT_MAGIC {
+			return Err(PsbtError::InvalidMagic);
+		}
+
+		let mut reader = Reader::new(&data[PSBT_MAGIC.len()..]);
+		let mut unsigned_transaction = None;
+		let mut version = None;
+		let mut tx_version = None;
+		let mut fallback_lock_time = None;
+		let mut input_count = None;
+		let mut output_count = None;
+		let mut tx_modifiable = None;
+		let mut unknown = BTreeMap::new();
+		for (key, value) in read_map(&mut reader)? {
+			match key[0] {
+				PSBT_GLOBAL_UNSIGNED_TX => {
+					expect_no_key_data(&key)?;
+					unsigned_transaction = Some(parse_unsigned_transaction(&value)?);
+				}
+				PSBT_GLOBAL_TX_VERSION => {
+					expect_no_key_data(&key)?;
+					tx_version = Some(parse_value::<i32>(&value)?);
+				}
+				PSBT_GLOBAL_FALLBACK_LOCKTIME => {
+					expect_no_key_data(&key)?;
+					fallback_lock_time = Some(parse_value::<u32>(&value)?);
+				}
+				PSBT_GLOBAL_INPUT_COUNT => {
+					expect_no_key_data(&key)?;
+					input_count = Some(usize::from(parse_value::<CompactInteger>(&value)?));
+				}
+				PSBT_GLOBAL_OUTPUT_COUNT => {
+					expect_no_key_data(&key)?;
+					output_count = Some(usize::from(parse_value::<CompactInteger>(&value)?));
+				}
+				PSBT_GLOBAL_TX_MODIFIABLE => {
+					expect_no_key_data(&key)?;
+					tx_modifiable = Some(parse_value::<u8>(&value)?);
+				}
+				PSBT_GLOBAL_VERSION => {
+					expect_no_key_data(&key)?;
+					version = Some(parse_value::<u32>(&value)?);
+				}
+				_ => {
+					unknown.insert(key, value);
+				}
+			}
+		}
+
+		let version = version.unwrap_or(0);
+		let psbt = match version {
+			0 => {
+				let transaction = unsigned_transaction.ok_or(PsbtError::MissingField("unsigned transaction"))?;
+				if tx_version.is_some() {
+					return Err(PsbtError::UnexpectedField("transaction version"));
+				}
+				if fallback_lock_time.is_some() {
+					return Err(PsbtError::UnexpectedField("fallback lock time"));
+				}
+				if input_count.is_some() || output_count.is_some() {
+					return Err(PsbtError::UnexpectedField("input or output count"));
+				}
+				if tx_modifiable.is_some() {
+					return Err(PsbtError::UnexpectedField("transaction modifiable flags"));
+				}
+
+				let inputs = transaction
+					.inputs
+					.iter()
+					.map(|input| PsbtInput::parse(read_map(&mut reader)?, version, Some(input)))
+					.collect::<Result<_, _>>()?;
+				let outputs = transaction
+					.outputs
+					.iter()
+					.map(|output| PsbtOutput::parse(read_map(&mut reader)?, version, Some(output)))
+					.collect::<Result<_, _>>()?;
+				Psbt {
+					version,
+					tx_version: transaction.version,
+					fallback_lock_time: Some(transaction.lock_time),
+					tx_modifiable,
+					inputs,
+					outputs,
+					unknown,
+				}
+			}
+			2 => {
+				if unsigned_transaction.is_some() {
+					return Err(PsbtError::UnexpectedField("unsigned transaction"));
+				}
+				let tx_version = tx_version.ok_or(PsbtError::MissingField("transaction version"))?;
+				let input_count = input_count.ok_or(PsbtError::MissingField("input count"))?;
+				let output_count = output_count.ok_or(PsbtError::MissingField("output count"))?;
+
+				let inputs = (0..input_count)
+					.map(|_| PsbtInput::parse(read_map(&mut reader)?, version, None))
+					.collect::<Result<_, _>>()?;
+				let outputs = (0..output_count)
+					.map(|_| PsbtOutput::parse(read_map(&mut reader)?, version, None))
+					.collect::<Result<_, _>>()?;
+				Psbt {
+					version,
+					tx_version,
+					fallback_lock_time,
+					tx_modifiable,
+					inputs,
+					outputs,
+					unknown,
+				}
+			}
+			version => return Err(PsbtError::UnsupportedVersion(version)),
+		};
+
+		if !reader.is_finished() {
+			return Err(PsbtError::Malformed);
+		}
+
+		Ok(psbt)
+	}
+
+	/// Lock time of the transaction. Version 2 PSBT inputs may require lock times of the same kind,
+	/// in which case the greatest of them is used. Height based lock time is preferred, if inputs allow both.
+	pub fn lock_time(&self) -> Result<u32, PsbtError> {
+		let locked: Vec<_> = self
+			.inputs
+			.iter()
+			.filter(|input| input.required_time_lock_time.is_some() || input.required_height_lock_time.is_some())
+			.collect();
+		if self.version == 0 || locked.is_empty() {
+			return Ok(self.fallback_lock_time.unwrap_or_default());
+		}
+
+		if let Some(heights) = locked
+			.iter()
+			.map(|input| input.required_height_lock_time)
+			.collect::<Option<Vec<_>>>()
+		{
+			return Ok(heights.into_iter().max().unwrap_or_default());
+		}
+		if let Some(times) = locked.iter().map(|input| input.required_time_lock_time).collect::<Option<Vec<_>>>() {
+			return Ok(times.into_iter().max().unwrap_or_default());
+		}
+		Err(PsbtError::LockTimeConflict)
+	}
+
+	/// Transaction with empty script sigs and witnesses
+	pub fn unsigned_transaction(&self) -> Result<Transaction, PsbtError> {
+		Ok(Transaction {
+			version: self.tx_version,
+			inputs: self
+				.inputs
+				.iter()
+				.map(|input| TransactionInput {
+					previous_output: input.previous_output.clone(),
+					script_sig: Bytes::new(),
+					sequence: input.sequence,
+					script_witness: vec![],
+				})
+				.collect(),
+			outputs: self
+				.outputs
+				.iter()
+				.map(|output| TransactionOutput {
+					value: output.value,
+					script_pubkey: output.script_pubkey.clone(),
+				})
+				.collect(),
+			lock_time: self.lock_time()?,
+		})
+	}
+
+	/// Merges data of the other PSBT, describing the same transaction, into this PSBT
+	pub fn combine(&mut self, other: Psbt) -> Result<(), PsbtError> {
+		if self.version != other.version
+			|| transaction_hash(&self.unsigned_transaction()?) != transaction_hash(&other.unsigned_transaction()?)
+		{
+			return Err(PsbtError::MismatchedTransaction);
+		}
+
+		for (input, other_input) in self.inputs.iter_mut().zip(other.inputs) {
+			input.combine(other_input);
+		}
+		for (output, other_output) in self.outputs.iter_mut().zip(other.outputs) {
+			output.combine(other_output);
+		}
+		merge(&mut self.unknown, other.unknown);
+		Ok(())
+	}
+
+	pub fn is_finalized(&self) -> bool {
+		self.inputs.iter().all(PsbtInput::is_finalized)
+	}
+
+	/// Fee, paid by the transaction. None if some of spent outputs are unknown or if outputs
+	/// are spending more than inputs.
+	pub fn fee(&self) -> Option<u64> {
+		let spent: u64 = self
+			.inputs
+			.iter()
+			.map(|input| input.utxo().map(|output| output.value))
+			.sum::<Option<u64>>()?;
+		spent.checked_sub(self.outputs.iter().map(|output| output.value).sum())
+	}
+
+	/// Builds the signed transaction from finalized inputs
+	pub fn extract(&self) -> Result<Transaction, PsbtError> {
+		let mut transaction = self.unsigned_transaction()?;
+		for (index, (input, psbt_input)) in transaction.inputs.iter_mut().zip(&self.inputs).enumerate() {
+			if !psbt_input.is_finalized() {
+				return Err(PsbtError::NotFinalized(index));
+			}
+			input.script_sig = psbt_input.final_script_sig.clone().unwrap_or_default();
+			input.script_witness = psbt_input.final_script_witness.clone().unwrap_or_default();
+		}
+		Ok(transaction)
+	}
+}
+
+impl Serializable for Psbt {
+	fn serialize(&self, stream: &mut Stream) {
+		stream.append_slice(&PSBT_MAGIC);
+		if self.version == 0 {
+			let transaction = Transaction {
+				version: self.tx_version,
+				inputs: self
+					.inputs
+					.iter()
+					.map(|input| TransactionInput {
+						previous_output: input.previous_output.clone(),
+						script_sig: Bytes::new(),
+						sequence: input.sequence,
+						script_witness: vec![],
+					})
+					.collect(),
+				outputs: self
+					.outputs
+					.iter()
+					.map(|output| TransactionOutput {
+						value: output.value,
+						script_pubkey: output.script_pubkey.clone(),
+					})
+					.collect(),
+				lock_time: self.fallback_lock_time.unwrap_or_default(),
+			};
+			write_pair(stream, &[PSBT_GLOBAL_UNSIGNED_TX], &serialize(&transaction));
+		} else {
+			write_pair(stream, &[PSBT_GLOBAL_TX_VERSION], &serialize(&self.tx_version));
+			if let Some(lock_time) = self.fallback_lock_time {
+				write_pair(stream, &[PSBT_GLOBAL_FALLBACK_LOCKTIME], &serialize(&lock_time));
+			}
+			write_pair(
+				stream,
+				&[PSBT_GLOBAL_INPUT_COUNT],
+				&serialize(&CompactInteger::from(self.inputs.len())),
+			);
+			write_pair(
+				stream,
+				&[PSBT_GLOBAL_OUTPUT_COUNT],
+				&serialize(&CompactInteger::from(self.outputs.len())),
+			);
+			if let Some(tx_modifiable) = self.tx_modifiable {
+				write_pair(stream, &[PSBT_GLOBAL_TX_MODIFIABLE], &serialize(&tx_modifiable));
+			}
+			write_pair(stream, &[PSBT_GLOBAL_VERSION], &serialize(&self.version));
+		}
+		write_map_end(stream, &self.unknown);
+
+		for input in &self.inputs {
+			input.serialize(stream, self.version);
+		}
+		for output in &self.outputs {
+			output.serialize(stream, self.version);
+		}
+	}
+}
+
+/// PSBT is displayed as base64 string
+impl fmt::Display for Psbt {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		base64_encode(&serialize(self)).fmt(f)
+	}
+}
+
+impl str::FromStr for Psbt {
+	type Err = PsbtError;
+
+	fn from_str(s: &str) -> Result<Self, PsbtError> {
+		let data = base64_decode(s).ok_or(PsbtError::InvalidBase64)?;
+		Psbt::from_slice(&data)
+	}
+}
+
+/// Reads key-value pairs until the separator
+fn read_map(reader: &mut Reader<&[u8]>) -> Result<Vec<(Bytes, Bytes)>, PsbtError> {
+	let mut pairs: Vec<(Bytes, Bytes)> = Vec::new();
+	loop {
+		let key: Bytes = reader.read()?;
+		if key.is_empty() {
+			return Ok(pairs);
+		}
+		let value: Bytes = reader.read()?;
+		if pairs.iter().any(|(other, _)| *other == key) {
+			return Err(PsbtError::DuplicateKey);
+		}
+		pairs.push((key, value));
+	}
+}
+
+/// Unsigned transaction is always serialized without witness => transaction without inputs is not ambiguous
+fn parse_unsigned_transaction(value: &[u8]) -> Result<Transaction, PsbtError> {
+	let mut reader = Reader::new(value);
+	let transaction = Transaction {
+		version: reader.read().map_err(|_| PsbtError::InvalidValue)?,
+		inputs: reader.read_list().map_err(|_| PsbtError::InvalidValue)?,
+		outputs: reader.read_list().map_err(|_| PsbtError::InvalidValue)?,
+		lock_time: reader.read().map_err(|_| PsbtError::InvalidValue)?,
+	};
+	if !reader.is_finished() {
+		return Err(PsbtError::InvalidValue);
+	}
+	if transaction.inputs.iter().any(|input| !input.script_sig.is_empty()) {
+		return Err(PsbtError::UnsignedTransactionHasScripts);
+	}
+	Ok(transaction)
+}
+
+fn parse_value<T: Deserializable>(value: &[u8]) -> Result<T, PsbtError> {
+	deserialize(value).map_err(|_| PsbtError::InvalidValue)
+}
+
+fn expect_no_key_data(key: &[u8]) -> Result<(), PsbtError> {
+	if key.len() != 1 {
+		return Err(PsbtError::InvalidKey);
+	}
+	Ok(())
+}
+
+fn expect_version_2(key: &[u8], version: u32, field: &'static str) -> Result<(), PsbtError> {
+	if version != 2 {
+		return Err(PsbtError::UnexpectedField(field));
+	}
+	expect_no_key_data(key)
+}
+
+/// Key data of partial signatures and key origins is a compressed or uncompressed public key
+fn public_key_data(key: &[u8]) -> Result<Bytes, PsbtError> {
+	match key.len() {
+		34 | 66 => Ok(key[1..].into()),
+		_ => Err(PsbtError::InvalidKey),
+	}
+}
+
+fn key(key_type: u8, key_data: &[u8]) -> Bytes {
+	let mut key = Vec::with_capacity(key_data.len() + 1);
+	key.push(key_type);
+	key.extend_from_slice(key_data);
+	key.into()
+}
+
+fn write_pair(stream: &mut Stream, key: &[u8], value: &[u8]) {
+	stream
+		.append(&CompactInteger::from(key.len()))
+		.append_slice(key)
+		.append(&CompactInteger::from(value.len()))
+		.append_slice(value);
+}
+
+/// Writes unknown pairs, followed by the map separator
+fn write_map_end(stream: &mut Stream, unknown: &BTreeMap<Bytes, Bytes>) {
+	for (key, value) in unknown {
+		write_pair(stream, key, value);
+	}
+	stream.append(&0u8);
+}
+
+/// Inserts pairs, which are missing in the map
+fn merge<V>(map: &mut BTreeMap<Bytes, V>, other: BTreeMap<Bytes, V>) {
+	for (key, value) in other {
+		map.entry(key).or_insert(value);
+	}
+}
+
+fn base64_encode(data: &[u8]) -> String {
+	let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+	for chunk in data.chunks(3) {
+		let group = chunk
+			.iter()
+			.enumerate()
+			.fold(0u32, |group, (i, byte)| group | ((*byte as u32) << (16 - 8 * i)));
+		for i in 0..4 {
+			if i <= chunk.len() {
+				result.push(BASE64_ALPHABET[((group >> (18 - 6 * i)) & 0x3f) as usize] as char);
+			} else {
+				result.push('=');
+			}
+		}
+	}
+	result
+}
+
+fn base64_decode(s: &str) -> Option<Vec<u8>> {
+	let s = s.as_bytes();
+	if !s.len().is_multiple_of(4) {
+		return None;
+	}
+
+	let mut result = Vec::with_capacity(s.len() / 4 * 3);
+	for (n, chunk) in s.chunks(4).enumerate() {
+		let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+		if padding > 2 || (padding != 0 && n != s.len() / 4 - 1) {
+			return None;
+		}
+		let mut group = 0u32;
+		for c in &chunk[..4 - padding] {
+			group = (group << 6) | BASE64_ALPHABET.iter().position(|a| a == c)? as u32;
+		}
+		group <<= 6 * padding;
+		result.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+	}
+	Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{base64_decode, base64_encode, KeySource, Psbt, PsbtError, PsbtInput};
+	use crate::bytes::Bytes;
+	use crate::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+	use bitcrypto::{FromStr, SHA256D};
+	use ser::serialize;
+
+	fn unsigned_transaction() -> Transaction {
+		Transaction {
+			version: 2,
+			inputs: vec![
+				TransactionInput {
+					previous_output: OutPoint {
+						hash: SHA256D::from_str("75ddabb27b8845f5247975c8a5ba7c6f336c4570708ebe230caf6db5217ae858").unwrap(),
+						index: 0,
+					},
+					script_sig: Bytes::new(),
+					sequence: 0xffff_fffe,
+					script_witness: vec![],
+				},
+				TransactionInput {
+					previous_output: OutPoint {
+						hash: SHA256D::from_str("1dea7cd05979072a3578cab271c02244ea8a090bbb46aa680a65ecd027048d83").unwrap(),
+						index: 1,
+					},
+					script_sig: Bytes::new(),
+					sequence: 0xffff_ffff,
+					script_witness: vec![],
+				},
+			],
+			outputs: vec![
+				TransactionOutput {
+					value: 149_990_000,
+					script_pubkey: "0014d85c2b71d0060b09c9886aeb815e50991dda124d".into(),
+				},
+				TransactionOutput {
+					value: 100_000_000,
+					script_pubkey: "001400aea9a2e5f0f876a588df5546e8742d1d87008f".into(),
+				},
+			],
+			lock_time: 0,
+		}
+	}
+
+	fn funding_transaction() -> Transaction {
+		Transaction {
+			version: 1,
+			inputs: vec![TransactionInput::coinbase("0101".into())],
+			outputs: vec![TransactionOutput {
+				value: 150_000_000,
+				script_pubkey: "76a914d0c59903c5bac2868760e90fd521a4665aa7652088ac".into(),
+			}],
+			lock_time: 0,
+		}
+	}
+
+	#[test]
+	fn test_base64() {
+		let vectors = [
+			("", ""),
+			("f", "Zg=="),
+			("fo", "Zm8="),
+			("foo", "Zm9v"),
+			("foob", "Zm9vYg=="),
+			("fooba", "Zm9vYmE="),
+			("foobar", "Zm9vYmFy"),
+		];
+		for (data, encoded) in vectors.iter() {
+			assert_eq!(base64_encode(data.as_bytes()), *encoded);
+			assert_eq!(base64_decode(encoded).unwrap(), data.as_bytes());
+		}
+		assert_eq!(base64_decode("Zm9"), None);
+		assert_eq!(base64_decode("Zg==Zm9v"), None);
+		assert_eq!(base64_decode("Zm9*"), None);
+	}
+
+	/// BIP174 test vector: PSBT with one P2PKH input
+	#[test]
+	fn test_psbt_bip174_vector() {
+		let encoded = "cHNidP8BAHUCAAAAASaBcTce3/KF6Tet7qSze3gADAVmy7OtZGQXE8pCFxv2AAAAAAD+////AtPf9QUAAAAAGXapFNDFmQPFusKGh2DpD9UhpGZap2UgiKwA4fUFAAAAABepFDVF5uM7gyxHBQ8k0+65PJwDlIvHh7MuEwAAAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAAAA";
+		let psbt: Psbt = encoded.parse().unwrap();
+		assert_eq!(psbt.version, 0);
+		assert_eq!(psbt.inputs.len(), 1);
+		assert_eq!(psbt.outputs.len(), 2);
+		assert!(psbt.inputs[0].non_witness_utxo.is_some());
+		assert_eq!(psbt.inputs[0].utxo().unwrap().value, 200_000_000);
+		assert_eq!(psbt.to_string(), encoded);
+	}
+
+	#[test]
+	fn test_psbt_round_trip() {
+		let mut psbt = Psbt::from_unsigned_transaction(unsigned_transaction(), 0).unwrap();
+		psbt.inputs[0].witness_utxo = Some(TransactionOutput {
+			value: 200_000_000,
+			script_pubkey: "00142b6a2924aa9b1b115d1ac3098b0ba0e6ed510f2a".into(),
+		});
+		psbt.inputs[0].partial_sigs.insert(
+			"03b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd46".into(),
+			"304302200424b58effaaa694e1559ea5c93bbfd4a89064224055cdf070b6771469442d07021f5c8eb0fea6516d60b8acb33ad64ede60e8785bfb3aa94b99bdf86151db9a9a01".into(),
+		);
+		psbt.inputs[0].bip32_derivation.insert(
+			"03b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd46".into(),
+			KeySource {
+				fingerprint: [0xd9, 0x0c, 0x6a, 0x4f],
+				path: vec![0x8000_0054, 0x8000_0000, 0x8000_0000, 0, 0],
+			},
+		);
+		psbt.inputs[1].non_witness_utxo = Some(funding_transaction());
+		psbt.inputs[1].previous_output.hash = funding_transaction().hash();
+		psbt.inputs[1].previous_output.index = 0;
+		psbt.outputs[1].unknown.insert("0f01".into(), "beef".into());
+
+		let encoded = psbt.to_string();
+		assert!(encoded.starts_with("cHNidP8B"));
+		assert_eq!(encoded.parse::<Psbt>().unwrap(), psbt);
+
+		psbt.version = 2;
+		psbt.inputs[0].required_height_lock_time = Some(700_000);
+		let decoded: Psbt = psbt.to_string().parse().unwrap();
+		assert_eq!(decoded, psbt);
+		assert_eq!(decoded.unsigned_transaction().unwrap().lock_time, 700_000);
+	}
+
+	#[test]
+	fn test_psbt_parse_errors() {
+		let psbt = Psbt::from_unsigned_transaction(unsigned_transaction(), 0).unwrap();
+		let data = serialize(&psbt);
+		assert_eq!(Psbt::from_slice(&data[1..]), Err(PsbtError::InvalidMagic));
+		assert_eq!(Psbt::from_slice(&data[..data.len() - 1]), Err(PsbtError::Malformed));
+		assert_eq!("cHNidP8=".parse::<Psbt>(), Err(PsbtError::Malformed));
+		assert_eq!("cHNidP8".parse::<Psbt>(), Err(PsbtError::InvalidBase64));
+
+		// the same unknown global key twice
+		let mut duplicate = data.clone().take();
+		duplicate.splice(5..5, [0x02, 0x0f, 0x00, 0x00, 0x02, 0x0f, 0x00, 0x00].iter().cloned());
+		assert_eq!(Psbt::from_slice(&duplicate), Err(PsbtError::DuplicateKey));
+
+		// unsigned transaction with the script sig
+		let mut signed = unsigned_transaction();
+		signed.inputs[0].script_sig = "00".into();
+		assert_eq!(
+			Psbt::from_unsigned_transaction(signed, 0),
+			Err(PsbtError::UnsignedTransactionHasScripts)
+		);
+
+		// version 2 field in version 0 psbt
+		let mut psbt = psbt;
+		psbt.inputs[0].required_time_lock_time = Some(600_000_000);
+		psbt.version = 2;
+		let mut data = serialize(&psbt).take();
+		// drop PSBT_GLOBAL_VERSION pair: 01 fb 04 02000000
+		let position = data
+			.windows(7)
+			.position(|w| w == [0x01, 0xfb, 0x04, 0x02, 0x00, 0x00, 0x00])
+			.unwrap();
+		data.drain(position..position + 7);
+		assert_eq!(Psbt::from_slice(&data), Err(PsbtError::MissingField("unsigned transaction")));
+	}
+
+	#[test]
+	fn test_psbt_combine_and_extract() {
+		let psbt = Psbt::from_unsigned_transaction(unsigned_transaction(), 0).unwrap();
+		let mut first = psbt.clone();
+		first.inputs[0].final_script_witness = Some(vec!["3044".into(), "02".into()]);
+		first.inputs[1].witness_utxo = Some(TransactionOutput {
+			value: 100_000_000,
+			script_pubkey: "a9143545e6e33b832c47050f24d3eeb93c9c03948bc787".into(),
+		});
+		let mut second = psbt.clone();
+		second.inputs[1].final_script_sig = Some("0001".into());
+		second.inputs[1].witness_utxo = Some(TransactionOutput {
+			value: 1,
+			script_pubkey: Bytes::new(),
+		});
+		assert_eq!(psbt.extract(), Err(PsbtError::NotFinalized(0)));
+		assert_eq!(first.fee(), None);
+
+		first.combine(second).unwrap();
+		assert!(first.is_finalized());
+		// the first psbt data wins
+		assert_eq!(first.inputs[1].witness_utxo.as_ref().unwrap().value, 100_000_000);
+		let transaction = first.extract().unwrap();
+		assert_eq!(transaction.inputs[0].script_witness, vec![Bytes::from("3044"), Bytes::from("02")]);
+		assert_eq!(transaction.inputs[1].script_sig, "0001".into());
+		assert_eq!(transaction.outputs, unsigned_transaction().outputs);
+
+		let mut other = Psbt::from_unsigned_transaction(funding_transaction(), 0);
+		assert_eq!(other, Err(PsbtError::UnsignedTransactionHasScripts));
+		other = Psbt::from_unsigned_transaction(unsigned_transaction(), 2);
+		assert_eq!(first.combine(other.unwrap()), Err(PsbtError::MismatchedTransaction));
+	}
+
+	#[test]
+	fn test_psbt_lock_time() {
+		let mut psbt = Psbt::from_unsigned_transaction(unsigned_transaction(), 2).unwrap();
+		psbt.fallback_lock_time = Some(10);
+		assert_eq!(psbt.lock_time(), Ok(10));
+		psbt.inputs[0].required_time_lock_time = Some(600_000_000);
+		psbt.inputs[1].required_time_lock_time = Some(600_000_001);
+		psbt.inputs[1].required_height_lock_time = Some(100);
+		assert_eq!(psbt.lock_time(), Ok(600_000_001));
+		psbt.inputs[0].required_height_lock_time = Some(200);
+		assert_eq!(psbt.lock_time(), Ok(200));
+		psbt.inputs[0].required_height_lock_time = None;
+		psbt.inputs[1].required_time_lock_time = None;
+		assert_eq!(psbt.lock_time(), Err(PsbtError::LockTimeConflict));
+
+		let input = PsbtInput::new(OutPoint::default(), 0);
+		assert_eq!(input.utxo(), None);
+	}
+}
@@ -0,0 +1,399 @@
+//! ChaCha20 stream cipher, Poly1305 authenticator and their AEAD construction as specified in RFC 8439,
+//! together with the forward-secure wrappers used by the BIP324 transport.
+//! https://datatracker.ietf.org/doc/html/rfc8439
+//! https://github.com/bitcoin/bips/blob/master/bip-0324.mediawiki
+
+/// Length of the ChaCha20 key
+pub const CHACHA20_KEY_LEN: usize = 32;
+/// Length of the ChaCha20 nonce
+pub const CHACHA20_NONCE_LEN: usize = 12;
+/// Length of the Poly1305 tag
+pub const POLY1305_TAG_LEN: usize = 16;
+/// Number of messages encrypted with the same key by the forward-secure ciphers
+pub const REKEY_INTERVAL: u64 = 224;
+
+const BLOCK_LEN: usize = 64;
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+	state[a] = state[a].wrapping_add(state[b]);
+	state[d] = (state[d] ^ state[a]).rotate_left(16);
+	state[c] = state[c].wrapping_add(state[d]);
+	state[b] = (state[b] ^ state[c]).rotate_left(12);
+	state[a] = state[a].wrapping_add(state[b]);
+	state[d] = (state[d] ^ state[a]).rotate_left(8);
+	state[c] = state[c].wrapping_add(state[d]);
+	state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+	u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Computes single 64 bytes long block of the keystream
+pub fn chacha20_block(key: &[u8; CHACHA20_KEY_LEN], nonce: &[u8; CHACHA20_NONCE_LEN], counter: u32) -> [u8; BLOCK_LEN] {
+	let mut initial = [0u32; 16];
+	initial[0] = 0x6170_7865;
+	initial[1] = 0x3320_646e;
+	initial[2] = 0x7962_2d32;
+	initial[3] = 0x6b20_6574;
+	for i in 0..8 {
+		initial[4 + i] = read_u32(&key[i * 4..]);
+	}
+	initial[12] = counter;
+	for i in 0..3 {
+		initial[13 + i] = read_u32(&nonce[i * 4..]);
+	}
+
+	let mut state = initial;
+	for _ in 0..10 {
+		quarter_round(&mut state, 0, 4, 8, 12);
+		quarter_round(&mut state, 1, 5, 9, 13);
+		quarter_round(&mut state, 2, 6, 10, 14);
+		quarter_round(&mut state, 3, 7, 11, 15);
+		quarter_round(&mut state, 0, 5, 10, 15);
+		quarter_round(&mut state, 1, 6, 11, 12);
+		quarter_round(&mut state, 2, 7, 8, 13);
+		quarter_round(&mut state, 3, 4, 9, 14);
+	}
+
+	let mut block = [0u8; BLOCK_LEN];
+	for (i, (word, initial)) in state.iter().zip(initial.iter()).enumerate() {
+		block[i * 4..i * 4 + 4].copy_from_slice(&word.wrapping_add(*initial).to_le_bytes());
+	}
+	block
+}
+
+/// Xors data with the keystream, starting at the given block counter
+pub fn chacha20_xor(key: &[u8; CHACHA20_KEY_LEN], nonce: &[u8; CHACHA20_NONCE_LEN], counter: u32, data: &mut [u8]) {
+	for (i, chunk) in data.chunks_mut(BLOCK_LEN).enumerate() {
+		let block = chacha20_block(key, nonce, counter.wrapping_add(i as u32));
+		for (byte, key) in chunk.iter_mut().zip(block.iter()) {
+			*byte ^= key;
+		}
+	}
+}
+
+/// Computes Poly1305 one-time authenticator of the message
+pub fn poly1305(key: &[u8; 32], message: &[u8]) -> [u8; POLY1305_TAG_LEN] {
+	const MASK: u32 = 0x3ff_ffff;
+
+	// r is clamped and split into 26 bits long limbs
+	let r0 = read_u32(&key[0..]) & 0x3ff_ffff;
+	let r1 = (read_u32(&key[3..]) >> 2) & 0x3ff_ff03;
+	let r2 = (read_u32(&key[6..]) >> 4) & 0x3ff_c0ff;
+	let r3 = (read_u32(&key[9..]) >> 6) & 0x3f0_3fff;
+	let r4 = (read_u32(&key[12..]) >> 8) & 0x00f_ffff;
+	let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+	let mut h = [0u32; 5];
+	for chunk in message.chunks(16) {
+		let mut block = [0u8; 17];
+		block[..chunk.len()].copy_from_slice(chunk);
+		block[chunk.len()] = 1;
+
+		h[0] += read_u32(&block[0..]) & MASK;
+		h[1] += (read_u32(&block[3..]) >> 2) & MASK;
+		h[2] += (read_u32(&block[6..]) >> 4) & MASK;
+		h[3] += (read_u32(&block[9..]) >> 6) & MASK;
+		h[4] += (read_u32(&block[12..]) >> 8) | ((block[16] as u32) << 24);
+
+		let m = |a: u32, b: u32| a as u64 * b as u64;
+		let d0 = m(h[0], r0) + m(h[1], s4) + m(h[2], s3) + m(h[3], s2) + m(h[4], s1);
+		let mut d1 = m(h[0], r1) + m(h[1], r0) + m(h[2], s4) + m(h[3], s3) + m(h[4], s2);
+		let mut d2 = m(h[0], r2) + m(h[1], r1) + m(h[2], r0) + m(h[3], s4) + m(h[4], s3);
+		let mut d3 = m(h[0], r3) + m(h[1], r2) + m(h[2], r1) + m(h[3], r0) + m(h[4], s4);
+		let mut d4 = m(h[0], r4) + m(h[1], r3) + m(h[2], r2) + m(h[3], r1) + m(h[4], r0);
+
+		d1 += d0 >> 26;
+		h[0] = d0 as u32 & MASK;
+		d2 += d1 >> 26;
+		h[1] = d1 as u32 & MASK;
+		d3 += d2 >> 26;
+		h[2] = d2 as u32 & MASK;
+		d4 += d3 >> 26;
+		h[3] = d3 as u32 & MASK;
+		h[0] += (d4 >> 26) as u32 * 5;
+		h[4] = d4 as u32 & MASK;
+		h[1] += h[0] >> 26;
+		h[0] &= MASK;
+	}
+
+	// fully carry h
+	for i in 1..5 {
+		h[i] += h[i - 1] >> 26;
+		h[i - 1] &= MASK;
+	}
+	h[0] += (h[4] >> 26) * 5;
+	h[4] &= MASK;
+	h[1] += h[0] >> 26;
+	h[0] &= MASK;
+
+	// compute h - p and select it if it does not underflow
+	let mut g = [0u32; 5];
+	let mut carry = 5;
+	for i in 0..5 {
+		g[i] = h[i] + carry;
+		carry = g[i] >> 26;
+		g[i] &= MASK;
+	}
+	let select_g = (carry != 0) as u32;
+	let mask = 0u32.wrapping_sub(select_g);
+	for i in 0..5 {
+		h[i] = (h[i] & !mask) | (g[i] & mask);
+	}
+
+	// tag = (h + s) mod 2^128
+	let h = [
+		h[0] | (h[1] << 26),
+		(h[1] >> 6) | (h[2] << 20),
+		(h[2] >> 12) | (h[3] << 14),
+		(h[3] >> 18) | (h[4] << 8),
+	];
+	let mut tag = [0u8; POLY1305_TAG_LEN];
+	let mut carry = 0u64;
+	for i in 0..4 {
+		let f = h[i] as u64 + read_u32(&key[16 + i * 4..]) as u64 + carry;
+		tag[i * 4..i * 4 + 4].copy_from_slice(&(f as u32).to_le_bytes());
+		carry = f >> 32;
+	}
+	tag
+}
+
+fn aead_tag(key: &[u8; CHACHA20_KEY_LEN], nonce: &[u8; CHACHA20_NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> [u8; POLY1305_TAG_LEN] {
+	let mut poly_key = [0u8; 32];
+	poly_key.copy_from_slice(&chacha20_block(key, nonce, 0)[..32]);
+
+	let pad = |len: usize| (16 - len % 16) % 16;
+	let mut mac_data = Vec::with_capacity(aad.len() + ciphertext.len() + 48);
+	mac_data.extend_from_slice(aad);
+	mac_data.resize(mac_data.len() + pad(aad.len()), 0);
+	mac_data.extend_from_slice(ciphertext);
+	mac_data.resize(mac_data.len() + pad(ciphertext.len()), 0);
+	mac_data.extend_from_slice(&(aad.len() as u64).to_le_bytes());
+	mac_data.extend_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+	poly1305(&poly_key, &mac_data)
+}
+
+/// Encrypts plaintext and appends authentication tag of the ciphertext and additional data
+pub fn aead_chacha20_poly1305_encrypt(
+	key: &[u8; CHACHA20_KEY_LEN],
+	nonce: &[u8; CHACHA20_NONCE_LEN],
+	aad: &[u8],
+	plaintext: &[u8],
+) -> Vec<u8> {
+	let mut ciphertext = plaintext.to_vec();
+	chacha20_xor(key, nonce, 1, &mut ciphertext);
+	let tag = aead_tag(key, nonce, aad, &ciphertext);
+	ciphertext.extend_from_slice(&tag);
+	ciphertext
+}
+
+/// Verifies authentication tag and decrypts ciphertext. Returns None if the tag does not match.
+pub fn aead_chacha20_poly1305_decrypt(
+	key: &[u8; CHACHA20_KEY_LEN],
+	nonce: &[u8; CHACHA20_NONCE_LEN],
+	aad: &[u8],
+	ciphertext: &[u8],
+) -> Option<Vec<u8>> {
+	if ciphertext.len() < POLY1305_TAG_LEN {
+		return None;
+	}
+	let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - POLY1305_TAG_LEN);
+	let expected = aead_tag(key, nonce, aad, ciphertext);
+	// constant time comparison
+	if expected.iter().zip(tag.iter()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) != 0 {
+		return None;
+	}
+	let mut plaintext = ciphertext.to_vec();
+	chacha20_xor(key, nonce, 1, &mut plaintext);
+	Some(plaintext)
+}
+
+/// Forward-secure ChaCha20 stream cipher, which encrypts chunks with the continuous keystream
+/// and replaces its key every `REKEY_INTERVAL` chunks
+pub struct FSChaCha20 {
+	key: [u8; CHACHA20_KEY_LEN],
+	chunk_counter: u64,
+	block_counter: u32,
+	keystream: Vec<u8>,
+}
+
+impl FSChaCha20 {
+	pub fn new(key: [u8; CHACHA20_KEY_LEN]) -> Self {
+		FSChaCha20 {
+			key,
+			chunk_counter: 0,
+			block_counter: 0,
+			keystream: Vec::new(),
+		}
+	}
+
+	fn keystream(&mut self, len: usize) -> Vec<u8> {
+		let mut nonce = [0u8; CHACHA20_NONCE_LEN];
+		nonce[4..].copy_from_slice(&(self.chunk_counter / REKEY_INTERVAL).to_le_bytes());
+		while self.keystream.len() < len {
+			self.keystream
+				.extend_from_slice(&chacha20_block(&self.key, &nonce, self.block_counter));
+			self.block_counter += 1;
+		}
+		self.keystream.drain(..len).collect()
+	}
+
+	/// Encrypts or decrypts single chunk
+	pub fn crypt(&mut self, chunk: &mut [u8]) {
+		let keystream = self.keystream(chunk.len());
+		for (byte, key) in chunk.iter_mut().zip(keystream.iter()) {
+			*byte ^= key;
+		}
+		if (self.chunk_counter + 1).is_multiple_of(REKEY_INTERVAL) {
+			let new_key = self.keystream(CHACHA20_KEY_LEN);
+			self.key.copy_from_slice(&new_key);
+			self.block_counter = 0;
+			self.keystream.clear();
+		}
+		self.chunk_counter += 1;
+	}
+}
+
+/// Forward-secure ChaCha20-Poly1305 AEAD, which uses packet counter as a nonce
+/// and replaces its key every `REKEY_INTERVAL` packets
+pub struct FSChaCha20Poly1305 {
+	key: [u8; CHACHA20_KEY_LEN],
+	packet_counter: u64,
+}
+
+impl FSChaCha20Poly1305 {
+	pub fn new(key: [u8; CHACHA20_KEY_LEN]) -> Self {
+		FSChaCha20Poly1305 { key, packet_counter: 0 }
+	}
+
+	fn nonce(&self) -> [u8; CHACHA20_NONCE_LEN] {
+		let mut nonce = [0u8; CHACHA20_NONCE_LEN];
+		nonce[..4].copy_from_slice(&((self.packet_counter % REKEY_INTERVAL) as u32).to_le_bytes());
+		nonce[4..].copy_from_slice(&(self.packet_counter / REKEY_INTERVAL).to_le_bytes());
+		nonce
+	}
+
+	fn next_packet(&mut self, nonce: [u8; CHACHA20_NONCE_LEN]) {
+		if (self.packet_counter + 1).is_multiple_of(REKEY_INTERVAL) {
+			let mut rekey_nonce = nonce;
+			rekey_nonce[..4].copy_from_slice(&[0xff; 4]);
+			let new_key = aead_chacha20_poly1305_encrypt(&self.key, &rekey_nonce, &[], &[0u8; CHACHA20_KEY_LEN]);
+			self.key.copy_from_slice(&new_key[..CHACHA20_KEY_LEN]);
+		}
+		self.packet_counter += 1;
+	}
+
+	pub fn encrypt(&mut self, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+		let nonce = self.nonce();
+		let ciphertext = aead_chacha20_poly1305_encrypt(&self.key, &nonce, aad, plaintext);
+		self.next_packet(nonce);
+		ciphertext
+	}
+
+	pub fn decrypt(&mut self, aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+		let nonce = self.nonce();
+		let plaintext = aead_chacha20_poly1305_decrypt(&self.key, &nonce, aad, ciphertext);
+		self.next_packet(nonce);
+		plaintext
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		aead_chacha20_poly1305_decrypt, aead_chacha20_poly1305_encrypt, chacha20_block, chacha20_xor, poly1305, FSChaCha20,
+		FSChaCha20Poly1305, REKEY_INTERVAL,
+	};
+	use crate::{FromHex, ToHex};
+
+	fn array<T: Default + AsMut<[u8]>>(hex: &str) -> T {
+		let mut result = T::default();
+		result.as_mut().copy_from_slice(&Vec::<u8>::from_hex(hex).unwrap());
+		result
+	}
+
+	/// RFC 8439, section 2.3.2
+	#[test]
+	fn test_chacha20_block() {
+		let key: [u8; 32] = array("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+		let nonce: [u8; 12] = array("000000090000004a00000000");
+		assert_eq!(
+			chacha20_block(&key, &nonce, 1)[..].to_hex(),
+			"10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4ed2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+		);
+	}
+
+	/// RFC 8439, section 2.4.2
+	#[test]
+	fn test_chacha20_encryption() {
+		let key: [u8; 32] = array("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+		let nonce: [u8; 12] = array("000000000000004a00000000");
+		let mut data =
+			b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.".to_vec();
+		chacha20_xor(&key, &nonce, 1, &mut data);
+		assert_eq!(
+			data.to_hex(),
+			"6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0bf91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d807ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab77937365af90bbf74a35be6b40b8eedf2785e42874d"
+		);
+	}
+
+	/// RFC 8439, section 2.5.2
+	#[test]
+	fn test_poly1305() {
+		let key: [u8; 32] = array("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+		assert_eq!(
+			poly1305(&key, b"Cryptographic Forum Research Group")[..].to_hex(),
+			"a8061dc1305136c6c22b8baf0c0127a9"
+		);
+	}
+
+	/// RFC 8439, section 2.8.2
+	#[test]
+	fn test_aead_chacha20_poly1305() {
+		let key: [u8; 32] = array("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+		let nonce: [u8; 12] = array("070000004041424344454647");
+		let aad: Vec<u8> = FromHex::from_hex("50515253c0c1c2c3c4c5c6c7").unwrap();
+		let plaintext =
+			b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+		let ciphertext = aead_chacha20_poly1305_encrypt(&key, &nonce, &aad, plaintext);
+		assert_eq!(ciphertext[..16].to_hex(), "d31a8d34648e60db7b86afbc53ef7ec2");
+		assert_eq!(ciphertext[plaintext.len()..].to_hex(), "1ae10b594f09e26a7e902ecbd0600691");
+		assert_eq!(
+			aead_chacha20_poly1305_decrypt(&key, &nonce, &aad, &ciphertext).unwrap(),
+			&plaintext[..]
+		);
+
+		let mut tampered = ciphertext.clone();
+		tampered[0] ^= 1;
+		assert_eq!(aead_chacha20_poly1305_decrypt(&key, &nonce, &aad, &tampered), None);
+		assert_eq!(aead_chacha20_poly1305_decrypt(&key, &nonce, &[], &ciphertext), None);
+	}
+
+	#[test]
+	fn test_forward_secure_ciphers_rekey() {
+		let key = [7u8; 32];
+		let mut encryptor = FSChaCha20::new(key);
+		let mut decryptor = FSChaCha20::new(key);
+		let mut aead_encryptor = FSChaCha20Poly1305::new(key);
+		let mut aead_decryptor = FSChaCha20Poly1305::new(key);
+		let mut ciphertexts = Vec::new();
+		for i in 0..REKEY_INTERVAL * 2 + 1 {
+			let mut chunk = (i as u32).to_le_bytes()[..3].to_vec();
+			encryptor.crypt(&mut chunk);
+			ciphertexts.push(chunk.clone());
+			decryptor.crypt(&mut chunk);
+			assert_eq!(chunk, (i as u32).to_le_bytes()[..3].to_vec());
+
+			let ciphertext = aead_encryptor.encrypt(b"aad", &chunk);
+			assert_eq!(aead_decryptor.decrypt(b"aad", &ciphertext).unwrap(), chunk);
+		}
+
+		// the first chunks after the rekey are encrypted with the new key
+		let mut fresh = FSChaCha20::new(key);
+		let mut chunk = (REKEY_INTERVAL as u32).to_le_bytes()[..3].to_vec();
+		fresh.crypt(&mut chunk);
+		assert_ne!(chunk, ciphertexts[REKEY_INTERVAL as usize]);
+	}
+}
@@ -3,14 +3,23 @@ extern crate bitcoin_hashes;
 #[macro_use]
 extern crate heapsize;
 
+mod chacha20;
+mod sha3;
+
+pub use self::chacha20::{
+	aead_chacha20_poly1305_decrypt, aead_chacha20_poly1305_encrypt, chacha20_block, chacha20_xor, poly1305, FSChaCha20, FSChaCha20Poly1305,
+	CHACHA20_KEY_LEN, CHACHA20_NONCE_LEN, POLY1305_TAG_LEN, REKEY_INTERVAL,
+};
+pub use self::sha3::{sha3_256, SHA3_256_LEN};
 pub use bitcoin_hashes::core::str::FromStr;
 use bitcoin_hashes::hash160;
 pub use bitcoin_hashes::hex::Error as HexError;
 pub use bitcoin_hashes::hex::{FromHex, ToHex};
+use bitcoin_hashes::hmac::{Hmac, HmacEngine};
 use bitcoin_hashes::siphash24;
 pub use bitcoin_hashes::Hash;
 pub use bitcoin_hashes::HashEngine;
-use bitcoin_hashes::{ripemd160, sha1, sha256, sha256d};
+use bitcoin_hashes::{ripemd160, sha1, sha256, sha256d, sha512};
 
 hash_newtype!(
 	RIPEMD160,
@@ -43,6 +52,16 @@ pub fn sha256(input: &[u8]) -> SHA256 {
 	SHA256(sha256::Hash::hash(input))
 }
 
+/// BIP340 tagged hash: `sha256(sha256(tag) || sha256(tag) || input)`
+pub fn tagged_hash(tag: &str, input: &[u8]) -> SHA256 {
+	let tag_hash = sha256::Hash::hash(tag.as_bytes());
+	let mut engine = sha256::Hash::engine();
+	engine.input(&tag_hash[..]);
+	engine.input(&tag_hash[..]);
+	engine.input(input);
+	SHA256(sha256::Hash::from_engine(engine))
+}
+
 hash_newtype!(
 	HASH160,
 	hash160::Hash,
@@ -86,9 +105,74 @@ pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
 	siphash24::Hash::hash_to_u64_with_keys(key0, key1, input)
 }
 
+#[inline]
+pub fn hmac_sha256(key: &[u8], input: &[u8]) -> [u8; 32] {
+	let mut engine = HmacEngine::<sha256::Hash>::new(key);
+	engine.input(input);
+	Hmac::from_engine(engine).into_inner()
+}
+
+/// HKDF-SHA256 extract step (RFC 5869), returning pseudorandom key
+#[inline]
+pub fn hkdf_sha256_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+	hmac_sha256(salt, ikm)
+}
+
+/// HKDF-SHA256 expand step (RFC 5869), deriving up to 8160 bytes of the output keying material
+pub fn hkdf_sha256_expand(prk: &[u8; 32], info: &[u8], len: usize) -> Vec<u8> {
+	assert!(len <= 255 * 32, "HKDF output is limited to 255 blocks");
+	let mut result = Vec::with_capacity(len);
+	let mut block: Vec<u8> = Vec::new();
+	let mut counter = 1u8;
+	while result.len() < len {
+		// T(i) = HMAC(PRK, T(i-1) || info || i)
+		let mut engine = HmacEngine::<sha256::Hash>::new(prk);
+		engine.input(&block);
+		engine.input(info);
+		engine.input(&[counter]);
+		block = Hmac::from_engine(engine).into_inner().to_vec();
+		result.extend_from_slice(&block);
+		counter = counter.wrapping_add(1);
+	}
+	result.truncate(len);
+	result
+}
+
+#[inline]
+pub fn hmac_sha512(key: &[u8], input: &[u8]) -> [u8; 64] {
+	let mut engine = HmacEngine::<sha512::Hash>::new(key);
+	engine.input(input);
+	Hmac::from_engine(engine).into_inner()
+}
+
+/// PBKDF2 key derivation with HMAC-SHA512 pseudorandom function and 64 bytes long derived key
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 64] {
+	let keyed_engine = HmacEngine::<sha512::Hash>::new(password);
+
+	// U1 = PRF(password, salt || INT(1))
+	let mut engine = keyed_engine.clone();
+	engine.input(salt);
+	engine.input(&1u32.to_be_bytes());
+	let mut u = Hmac::from_engine(engine).into_inner();
+	let mut result = u;
+	// Ui = PRF(password, Ui-1)
+	for _ in 1..iterations {
+		let mut engine = keyed_engine.clone();
+		engine.input(&u);
+		u = Hmac::from_engine(engine).into_inner();
+		for (r, u) in result.iter_mut().zip(u.iter()) {
+			*r ^= u;
+		}
+	}
+	result
+}
+
 #[cfg(test)]
 mod tests {
-	use super::{dhash160, ripemd160, sha1, sha256, siphash24, FromStr};
+	use super::{
+		dhash160, hkdf_sha256_expand, hkdf_sha256_extract, hmac_sha256, hmac_sha512, pbkdf2_hmac_sha512, ripemd160, sha1, sha256,
+		siphash24, tagged_hash, FromStr,
+	};
 	use crate::{dhash256, FromInnerHex, HASH160, RIPEMD160, SHA1, SHA256, SHA256D};
 	use bitcoin_hashes::hex::FromHex;
 
@@ -113,6 +197,15 @@ mod tests {
 		assert_eq!(result, expected);
 	}
 
+	#[test]
+	fn test_tagged_hash() {
+		let tag = sha256(b"TapLeaf");
+		let mut preimage = tag.to_vec();
+		preimage.extend_from_slice(&tag[..]);
+		preimage.extend_from_slice(b"hello");
+		assert_eq!(tagged_hash("TapLeaf", b"hello"), sha256(&preimage));
+	}
+
 	#[test]
 	fn test_dhash160() {
 		let expected = HASH160::from_str("b6a9c8c230722b7c748331a8b450f05566dc7d0f").unwrap();
@@ -134,6 +227,49 @@ mod tests {
 		assert_eq!(result, expected);
 	}
 
+	/// Test case 2 from https://tools.ietf.org/html/rfc4231
+	#[test]
+	fn test_hmac_sha256() {
+		let expected: Vec<u8> = FromHex::from_hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843").unwrap();
+		let result = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+		assert_eq!(&result[..], &expected[..]);
+	}
+
+	/// Test case 1 from https://tools.ietf.org/html/rfc5869
+	#[test]
+	fn test_hkdf_sha256() {
+		let ikm = [0x0b; 22];
+		let salt: Vec<u8> = FromHex::from_hex("000102030405060708090a0b0c").unwrap();
+		let info: Vec<u8> = FromHex::from_hex("f0f1f2f3f4f5f6f7f8f9").unwrap();
+		let prk = hkdf_sha256_extract(&salt, &ikm);
+		let expected_prk: Vec<u8> = FromHex::from_hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5").unwrap();
+		assert_eq!(&prk[..], &expected_prk[..]);
+		let expected_okm: Vec<u8> =
+			FromHex::from_hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865").unwrap();
+		assert_eq!(hkdf_sha256_expand(&prk, &info, 42), expected_okm);
+	}
+
+	/// Test case 2 from https://tools.ietf.org/html/rfc4231
+	#[test]
+	fn test_hmac_sha512() {
+		let expected: Vec<u8> = FromHex::from_hex("164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737").unwrap();
+		let result = hmac_sha512(b"Jefe", b"what do ya want for nothing?");
+		assert_eq!(&result[..], &expected[..]);
+	}
+
+	#[test]
+	fn test_pbkdf2_hmac_sha512() {
+		// single iteration is HMAC of salt with big-endian block index appended
+		let mut salt = b"salt".to_vec();
+		salt.extend_from_slice(&[0, 0, 0, 1]);
+		let u1 = hmac_sha512(b"password", &salt);
+		assert_eq!(&pbkdf2_hmac_sha512(b"password", b"salt", 1)[..], &u1[..]);
+
+		let u2 = hmac_sha512(b"password", &u1);
+		let expected: Vec<u8> = u1.iter().zip(u2.iter()).map(|(a, b)| a ^ b).collect();
+		assert_eq!(&pbkdf2_hmac_sha512(b"password", b"salt", 2)[..], &expected[..]);
+	}
+
 	#[test]
 	fn test_siphash24() {
 		let expected = 0x74f839c593dc67fd_u64;
@@ -0,0 +1,135 @@
+//! SHA3-256 hash function as specified in FIPS 202, used by the Tor v3 onion addresses.
+//! https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+
+/// Length of the SHA3-256 digest
+pub const SHA3_256_LEN: usize = 32;
+
+/// Rate of the sponge in bytes, equal to 1600 - 2 * 256 bits
+const RATE: usize = 136;
+
+const ROUND_CONSTANTS: [u64; 24] = [
+	0x0000_0000_0000_0001,
+	0x0000_0000_0000_8082,
+	0x8000_0000_0000_808a,
+	0x8000_0000_8000_8000,
+	0x0000_0000_0000_808b,
+	0x0000_0000_8000_0001,
+	0x8000_0000_8000_8081,
+	0x8000_0000_0000_8009,
+	0x0000_0000_0000_008a,
+	0x0000_0000_0000_0088,
+	0x0000_0000_8000_8009,
+	0x0000_0000_8000_000a,
+	0x0000_0000_8000_808b,
+	0x8000_0000_0000_008b,
+	0x8000_0000_0000_8089,
+	0x8000_0000_0000_8003,
+	0x8000_0000_0000_8002,
+	0x8000_0000_0000_0080,
+	0x0000_0000_0000_800a,
+	0x8000_0000_8000_000a,
+	0x8000_0000_8000_8081,
+	0x8000_0000_0000_8080,
+	0x0000_0000_8000_0001,
+	0x8000_0000_8000_8008,
+];
+
+/// Rotation offsets of the rho step, in the order of the pi step lanes
+const ROTATIONS: [u32; 24] = [
+	1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+/// Lane indices visited by the combined rho and pi steps
+const PI_LANES: [usize; 24] = [
+	10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+fn keccak_f(state: &mut [u64; 25]) {
+	for round_constant in ROUND_CONSTANTS.iter() {
+		// theta
+		let mut parity = [0u64; 5];
+		for (x, p) in parity.iter_mut().enumerate() {
+			*p = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+		}
+		for x in 0..5 {
+			let d = parity[(x + 4) % 5] ^ parity[(x + 1) % 5].rotate_left(1);
+			for y in 0..5 {
+				state[x + 5 * y] ^= d;
+			}
+		}
+
+		// rho and pi
+		let mut last = state[1];
+		for (&lane, &rotation) in PI_LANES.iter().zip(ROTATIONS.iter()) {
+			let current = state[lane];
+			state[lane] = last.rotate_left(rotation);
+			last = current;
+		}
+
+		// chi
+		for y in 0..5 {
+			let row = [state[5 * y], state[5 * y + 1], state[5 * y + 2], state[5 * y + 3], state[5 * y + 4]];
+			for x in 0..5 {
+				state[x + 5 * y] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+			}
+		}
+
+		// iota
+		state[0] ^= round_constant;
+	}
+}
+
+fn absorb_block(state: &mut [u64; 25], block: &[u8]) {
+	for (lane, chunk) in state.iter_mut().zip(block.chunks(8)) {
+		let mut bytes = [0u8; 8];
+		bytes.copy_from_slice(chunk);
+		*lane ^= u64::from_le_bytes(bytes);
+	}
+	keccak_f(state);
+}
+
+/// Computes SHA3-256 digest of the input
+pub fn sha3_256(input: &[u8]) -> [u8; SHA3_256_LEN] {
+	let mut state = [0u64; 25];
+	let mut blocks = input.chunks_exact(RATE);
+	for block in &mut blocks {
+		absorb_block(&mut state, block);
+	}
+
+	// SHA3 domain separation bits followed by pad10*1
+	let remainder = blocks.remainder();
+	let mut last = [0u8; RATE];
+	last[..remainder.len()].copy_from_slice(remainder);
+	last[remainder.len()] ^= 0x06;
+	last[RATE - 1] ^= 0x80;
+	absorb_block(&mut state, &last);
+
+	let mut digest = [0u8; SHA3_256_LEN];
+	for (chunk, lane) in digest.chunks_mut(8).zip(state.iter()) {
+		chunk.copy_from_slice(&lane.to_le_bytes());
+	}
+	digest
+}
+
+#[cfg(test)]
+mod tests {
+	use super::sha3_256;
+	use bitcoin_hashes::hex::ToHex;
+
+	#[test]
+	fn test_sha3_256() {
+		assert_eq!(
+			sha3_256(b"").to_hex(),
+			"a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+		);
+		assert_eq!(
+			sha3_256(b"abc").to_hex(),
+			"3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+		);
+		// input longer than a single block
+		assert_eq!(
+			sha3_256(&[0xa3; 200]).to_hex(),
+			"79f38adec5c20307a98ef76e8324afbfd46cfd81b22e3973c65fa1bd9de31787"
+		);
+	}
+}
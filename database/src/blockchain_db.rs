@@ -1,15 +1,16 @@
-use crate::db_interface::DbInterface;
+use crate::db_interface::{AddressIndexRange, DbInterface};
 use crate::ham_db::HamDb;
 use crate::overlay_db::OverlayDatabase;
-use bitcrypto::SHA256D;
-use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction, OutPoint, TransactionOutput};
+use bitcrypto::{sha256, SHA256, SHA256D};
+use chain::{BlockFilter, IndexedBlock, IndexedBlockHeader, IndexedTransaction, OutPoint, TransactionOutput};
 use parking_lot::RwLock;
 use serialization::serialize;
 use std::collections::HashMap;
 use storage::bytes::Bytes;
 use storage::{
-	BlockChain, BlockHeaderProvider, BlockHeight, BlockMeta, BlockOrigin, BlockProvider, BlockRef, CanonStore, Error, ForkChain, Forkable,
-	SideChainOrigin, Store, TransactionMeta, TransactionMetaProvider, TransactionOutputProvider, TransactionProvider,
+	AddressIndexProvider, BlockChain, BlockFilterProvider, BlockHeaderProvider, BlockHeight, BlockMeta, BlockOrigin, BlockProvider,
+	BlockRef, CanonStore, Error, ForkChain, Forkable, ScriptHashEntry, SideChainOrigin, Store, TransactionMeta, TransactionMetaProvider,
+	TransactionOutputProvider, TransactionProvider,
 };
 
 const MAX_FORK_ROUTE_PRESET: usize = 2048;
@@ -27,6 +28,8 @@ where
 {
 	best_block: RwLock<BlockHeight>,
 	db: T,
+	address_index: bool,
+	block_filters: bool,
 }
 
 pub struct ForkChainDatabase<'a, T>
@@ -126,11 +129,30 @@ where
 		Ok(BlockChainDatabase {
 			db,
 			best_block: RwLock::new(best_block),
+			address_index: false,
+			block_filters: false,
 		})
 	}
 
+	/// Enables or disables maintaining of the address (script hash) index.
+	/// Index only covers blocks that are canonized after it has been enabled. When
+	/// index has been disabled for a while, it restarts from the next canonized block.
+	pub fn with_address_index(mut self, address_index: bool) -> Self {
+		self.address_index = address_index;
+		self
+	}
+
+	/// Enables or disables building of BIP158 basic block filters.
+	/// Filters of blocks that have been canonized before are built by `build_missing_block_filters`.
+	pub fn with_block_filters(mut self, block_filters: bool) -> Self {
+		self.block_filters = block_filters;
+		self
+	}
+
 	pub fn fork(&self, side_chain: SideChainOrigin) -> Result<ForkChainDatabase<T>, storage::Error> {
-		let overlay_db = BlockChainDatabase::open(OverlayDatabase::new(&self.db))?;
+		let overlay_db = BlockChainDatabase::open(OverlayDatabase::new(&self.db))?
+			.with_address_index(self.address_index)
+			.with_block_filters(self.block_filters);
 
 		for hash in side_chain.decanonized_route.into_iter().rev() {
 			let decanonized_hash = overlay_db.decanonize()?;
@@ -302,6 +324,18 @@ where
 		}
 
 		best_block_meta.total_supply = total_supply + new_supply;
+		if self.address_index {
+			self.index_block_scripts(&new_best_block, best_block_meta.number)?;
+			// blocks, canonized while index has been disabled, are not indexed => restart index range
+			let start = match self.db.address_index_range()? {
+				Some(ref range) if range.tip == new_best_block.header.raw.previous_header_hash => range.start,
+				_ => best_block_meta.number,
+			};
+			self.db.set_address_index_range(&AddressIndexRange { start, tip: *block_hash })?;
+		}
+		if self.block_filters {
+			self.index_block_filter(&new_best_block, best_block_meta.number)?;
+		}
 		self.db.set_block_by_number(&block_hash, best_block_meta.number)?;
 		self.db.set_best(best_block_meta.number)?;
 		self.db.update_block_meta(&block_hash, &best_block_meta)?;
@@ -373,10 +407,175 @@ where
 			self.db.update_transaction_meta(&hash, meta)?;
 		}
 
+		if self.address_index {
+			// blocks, canonized before index has been enabled, have no entries
+			match self.db.address_index_range()? {
+				Some(ref range) if range.tip == best_height.hash && range.start <= best_height.number => {
+					self.unindex_block_scripts(&best_block, best_height.number)?;
+					self.db.set_address_index_range(&AddressIndexRange {
+						start: range.start,
+						tip: new_best_block.hash,
+					})?;
+				}
+				_ => (),
+			}
+		}
+
 		*best = new_best_block;
 		Ok(best_height.hash)
 	}
 
+	/// Pushes script hash entries for all outputs of the block and for all outputs spent by the block.
+	fn index_block_scripts(&self, block: &IndexedBlock, height: u32) -> Result<(), storage::Error> {
+		for tx in &block.transactions {
+			if !tx.raw.is_coinbase() {
+				for input in &tx.raw.inputs {
+					let prevout = &input.previous_output;
+					let spent_output = match self.spent_output(prevout)? {
+						Some(spent_output) => spent_output,
+						None => {
+							error!("Cannot find spent output during indexing of tx {}/{}", prevout.hash, prevout.index);
+							return Err(storage::Error::CannotCanonize);
+						}
+					};
+					let entry = ScriptHashEntry {
+						height,
+						tx_hash: tx.hash,
+						outpoint: prevout.clone(),
+						value: spent_output.value,
+						is_spending: true,
+					};
+					self.db.push_script_hash_entry(&sha256(&spent_output.script_pubkey), entry)?;
+				}
+			}
+
+			for (index, output) in tx.raw.outputs.iter().enumerate() {
+				let entry = ScriptHashEntry {
+					height,
+					tx_hash: tx.hash,
+					outpoint: OutPoint {
+						hash: tx.hash,
+						index: index as u32,
+					},
+					value: output.value,
+					is_spending: false,
+				};
+				self.db.push_script_hash_entry(&sha256(&output.script_pubkey), entry)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Pops script hash entries, pushed by `index_block_scripts`.
+	fn unindex_block_scripts(&self, block: &IndexedBlock, height: u32) -> Result<(), storage::Error> {
+		for tx in block.transactions.iter().rev() {
+			let mut script_hashes: Vec<SHA256> = Vec::new();
+			if !tx.raw.is_coinbase() {
+				for input in &tx.raw.inputs {
+					match self.spent_output(&input.previous_output)? {
+						Some(spent_output) => script_hashes.push(sha256(&spent_output.script_pubkey)),
+						None => return Err(storage::Error::CannotDecanonize),
+					}
+				}
+			}
+			script_hashes.extend(tx.raw.outputs.iter().map(|output| sha256(&output.script_pubkey)));
+
+			for script_hash in script_hashes.iter().rev() {
+				match self.db.pop_script_hash_entry(script_hash)? {
+					Some(ref entry) if entry.height == height && entry.tx_hash == tx.hash => (),
+					_ => {
+						error!("Unexpected script hash entry during decanonization of tx {}", tx.hash);
+						return Err(storage::Error::CannotDecanonize);
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Builds and stores filter of the block, which is about to be canonized at given height.
+	/// Filter headers are chained => filter is only built if the previous canon block has filter.
+	/// Missing filters of the previous canon blocks are built by `build_missing_block_filters`.
+	fn index_block_filter(&self, block: &IndexedBlock, height: u32) -> Result<(), storage::Error> {
+		let previous_header = if height == 0 {
+			SHA256D::default()
+		} else {
+			match self.db.fetch_block_filter(&block.header.raw.previous_header_hash)? {
+				Some((_, header)) => header,
+				None => return Ok(()),
+			}
+		};
+		self.insert_block_filter(block, &previous_header)?;
+		Ok(())
+	}
+
+	/// Builds filters of at most `max_blocks` canon blocks, which have been canonized before
+	/// block filters were enabled. Returns number of built filters => zero when all canon blocks have filters.
+	pub fn build_missing_block_filters(&self, max_blocks: u32) -> Result<u32, storage::Error> {
+		if !self.block_filters {
+			return Err(storage::Error::BlockFilterIndexDisabled);
+		}
+
+		// filter is only built when parent has filter => canon blocks with filters are the prefix of canon chain
+		let best_block = self.best_block.read();
+		let has_filter = |number: u32| -> Result<bool, storage::Error> {
+			let hash = self.db.block_hash(number)?.ok_or(storage::Error::InconsistentData)?;
+			Ok(self.db.fetch_block_filter(&hash)?.is_some())
+		};
+		let (mut first_missing, mut last_missing) = (0, best_block.number + 1);
+		while first_missing < last_missing {
+			let middle = first_missing + (last_missing - first_missing) / 2;
+			if has_filter(middle)? {
+				first_missing = middle + 1;
+			} else {
+				last_missing = middle;
+			}
+		}
+
+		let mut previous_header = if first_missing == 0 {
+			SHA256D::default()
+		} else {
+			let hash = self.db.block_hash(first_missing - 1)?.ok_or(storage::Error::InconsistentData)?;
+			self.db.fetch_block_filter(&hash)?.ok_or(storage::Error::InconsistentData)?.1
+		};
+		let mut built = 0;
+		for number in (first_missing..=best_block.number).take(max_blocks as usize) {
+			let hash = self.db.block_hash(number)?.ok_or(storage::Error::InconsistentData)?;
+			let block = self.db.fetch_block(&hash)?.ok_or(storage::Error::InconsistentData)?;
+			previous_header = self.insert_block_filter(&block, &previous_header)?;
+			built += 1;
+		}
+		Ok(built)
+	}
+
+	fn insert_block_filter(&self, block: &IndexedBlock, previous_header: &SHA256D) -> Result<SHA256D, storage::Error> {
+		let mut spent_scripts = Vec::new();
+		for tx in block.transactions.iter().filter(|tx| !tx.raw.is_coinbase()) {
+			for input in &tx.raw.inputs {
+				let prevout = &input.previous_output;
+				match self.spent_output(prevout)? {
+					Some(spent_output) => spent_scripts.push(spent_output.script_pubkey),
+					None => {
+						error!("Cannot find spent output during filtering of tx {}/{}", prevout.hash, prevout.index);
+						return Err(storage::Error::CannotCanonize);
+					}
+				}
+			}
+		}
+
+		let filter = BlockFilter::basic(block, spent_scripts.iter().map(|script| &script[..]));
+		let header = filter.header(previous_header);
+		self.db.insert_block_filter(block.hash(), &filter, &header)?;
+		Ok(header)
+	}
+
+	fn spent_output(&self, prevout: &OutPoint) -> Result<Option<TransactionOutput>, storage::Error> {
+		Ok(self
+			.db
+			.fetch_transaction(&prevout.hash)?
+			.and_then(|tx| tx.raw.outputs.get(prevout.index as usize).cloned()))
+	}
+
 	pub fn best_block(&self) -> BlockHeight {
 		self.best_block.read().clone()
 	}
@@ -545,6 +744,37 @@ where
 	}
 }
 
+impl<T> AddressIndexProvider for BlockChainDatabase<T>
+where
+	T: DbInterface,
+{
+	fn script_hash_history(&self, script_hash: &SHA256) -> Result<Vec<ScriptHashEntry>, storage::Error> {
+		if !self.address_index {
+			return Err(storage::Error::AddressIndexDisabled);
+		}
+		self.db.fetch_script_hash_entries(script_hash)
+	}
+}
+
+impl<T> BlockFilterProvider for BlockChainDatabase<T>
+where
+	T: DbInterface,
+{
+	fn block_filter(&self, block_hash: &SHA256D) -> Result<Option<BlockFilter>, storage::Error> {
+		if !self.block_filters {
+			return Err(storage::Error::BlockFilterIndexDisabled);
+		}
+		Ok(self.db.fetch_block_filter(block_hash)?.map(|(filter, _)| filter))
+	}
+
+	fn block_filter_header(&self, block_hash: &SHA256D) -> Result<Option<SHA256D>, storage::Error> {
+		if !self.block_filters {
+			return Err(storage::Error::BlockFilterIndexDisabled);
+		}
+		Ok(self.db.fetch_block_filter(block_hash)?.map(|(_, header)| header))
+	}
+}
+
 impl<T> BlockChain for BlockChainDatabase<T>
 where
 	T: DbInterface,
@@ -575,8 +805,12 @@ mod tests {
 	use super::SHA256D;
 	use crate::blockchain_db::BlockChainDatabase;
 	use crate::ham_db::HamDb;
-	use chain::IndexedBlock;
-	use storage::{AsSubstore, BlockMeta, BlockProvider, BlockRef, TransactionMetaProvider};
+	use bitcrypto::sha256;
+	use chain::{BlockFilter, IndexedBlock, OutPoint};
+	use storage::{
+		AddressIndexProvider, AsSubstore, BlockFilterProvider, BlockMeta, BlockProvider, BlockRef, Error, ScriptHashEntry, SideChainOrigin,
+		TransactionMetaProvider,
+	};
 	use test_data::{block_h0, block_h1, block_h2};
 
 	#[test]
@@ -695,4 +929,190 @@ mod tests {
 		assert_eq!(tx.height(), 0);
 		assert!(tx.is_coinbase());
 	}
+
+	fn address_index_chain() -> (IndexedBlock, IndexedBlock, IndexedBlock) {
+		let b0: IndexedBlock = block_h0().into();
+		#[rustfmt::skip]
+		let b1: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(50).script_pubkey("51").build().build()
+			.merkled_header().parent(*b0.hash()).build()
+			.build()
+			.into();
+		#[rustfmt::skip]
+		let b2: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(60).script_pubkey("53").build().build()
+			.transaction()
+				.input().hash(b1.transactions[0].hash).index(0).build()
+				.output().value(50).script_pubkey("52").build()
+				.build()
+			.merkled_header().parent(*b1.hash()).build()
+			.build()
+			.into();
+		(b0, b1, b2)
+	}
+
+	#[test]
+	fn test_address_index() {
+		let (b0, b1, b2) = address_index_chain();
+		let db = BlockChainDatabase::transient(&b0).unwrap().with_address_index(true);
+		for block in vec![b1.clone(), b2.clone()] {
+			let hash = *block.hash();
+			db.insert(block).unwrap();
+			db.canonize(&hash).unwrap();
+		}
+
+		let script_hash = sha256(&b1.transactions[0].raw.outputs[0].script_pubkey);
+		let funded = OutPoint {
+			hash: b1.transactions[0].hash,
+			index: 0,
+		};
+		assert_eq!(
+			db.script_hash_history(&script_hash).unwrap(),
+			vec![
+				ScriptHashEntry {
+					height: 1,
+					tx_hash: b1.transactions[0].hash,
+					outpoint: funded.clone(),
+					value: 50,
+					is_spending: false,
+				},
+				ScriptHashEntry {
+					height: 2,
+					tx_hash: b2.transactions[1].hash,
+					outpoint: funded.clone(),
+					value: 50,
+					is_spending: true,
+				},
+			]
+		);
+		let spending_script_hash = sha256(&b2.transactions[1].raw.outputs[0].script_pubkey);
+		assert_eq!(db.script_hash_history(&spending_script_hash).unwrap().len(), 1);
+
+		assert_eq!(db.decanonize().unwrap(), *b2.hash());
+		assert_eq!(db.script_hash_history(&script_hash).unwrap().len(), 1);
+		assert!(db.script_hash_history(&spending_script_hash).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_address_index_is_reverted_by_fork() {
+		let (b0, b1, b2) = address_index_chain();
+		let db = BlockChainDatabase::transient(&b0).unwrap().with_address_index(true);
+		for block in vec![b1.clone(), b2.clone()] {
+			let hash = *block.hash();
+			db.insert(block).unwrap();
+			db.canonize(&hash).unwrap();
+		}
+
+		let script_hash = sha256(&b1.transactions[0].raw.outputs[0].script_pubkey);
+		let fork = db
+			.fork(SideChainOrigin {
+				ancestor: 1,
+				canonized_route: vec![],
+				decanonized_route: vec![*b2.hash()],
+				block_number: 1,
+			})
+			.unwrap();
+		assert_eq!(fork.blockchain.script_hash_history(&script_hash).unwrap().len(), 1);
+		assert_eq!(db.script_hash_history(&script_hash).unwrap().len(), 2);
+
+		db.switch_to_fork(fork).unwrap();
+		assert_eq!(db.script_hash_history(&script_hash).unwrap().len(), 1);
+	}
+
+	#[test]
+	fn test_address_index_enabled_on_existing_database() {
+		let (b0, b1, b2) = address_index_chain();
+		let db = BlockChainDatabase::transient(&b0).unwrap();
+		let hash1 = *b1.hash();
+		db.insert(b1.clone()).unwrap();
+		db.canonize(&hash1).unwrap();
+
+		let db = db.with_address_index(true);
+		let hash2 = *b2.hash();
+		db.insert(b2.clone()).unwrap();
+		db.canonize(&hash2).unwrap();
+
+		let script_hash = sha256(&b1.transactions[0].raw.outputs[0].script_pubkey);
+		assert_eq!(db.script_hash_history(&script_hash).unwrap().len(), 1);
+
+		// block 1 has been canonized before index has been enabled => it has no entries to remove
+		assert_eq!(db.decanonize().unwrap(), hash2);
+		assert_eq!(db.decanonize().unwrap(), hash1);
+		assert!(db.script_hash_history(&script_hash).unwrap().is_empty());
+
+		// index restarts from the re-canonized block
+		db.canonize(&hash1).unwrap();
+		assert_eq!(db.script_hash_history(&script_hash).unwrap().len(), 1);
+		assert_eq!(db.decanonize().unwrap(), hash1);
+		assert!(db.script_hash_history(&script_hash).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_address_index_disabled() {
+		let db = BlockChainDatabase::transient(&block_h0().into()).unwrap();
+		assert_eq!(db.script_hash_history(&sha256(&[])).unwrap_err(), Error::AddressIndexDisabled);
+	}
+
+	#[test]
+	fn test_block_filters() {
+		let (b0, b1, b2) = address_index_chain();
+		// genesis block is canonized before filters are enabled
+		let db = BlockChainDatabase::transient(&b0).unwrap().with_block_filters(true);
+		assert_eq!(db.block_filter(b0.hash()).unwrap(), None);
+		for block in vec![b1.clone(), b2.clone()] {
+			let hash = *block.hash();
+			db.insert(block).unwrap();
+			db.canonize(&hash).unwrap();
+		}
+
+		// filters are chained => blocks are not filtered until filters of previous blocks are built
+		assert_eq!(db.block_filter(b2.hash()).unwrap(), None);
+		assert_eq!(db.build_missing_block_filters(1).unwrap(), 1);
+		assert_eq!(db.block_filter(b1.hash()).unwrap(), None);
+		assert_eq!(db.build_missing_block_filters(10).unwrap(), 2);
+		assert_eq!(db.build_missing_block_filters(10).unwrap(), 0);
+
+		let f0 = BlockFilter::basic(&b0, vec![]);
+		let f1 = BlockFilter::basic(&b1, vec![]);
+		let spent_script = b1.transactions[0].raw.outputs[0].script_pubkey.clone();
+		let f2 = BlockFilter::basic(&b2, vec![&spent_script[..]]);
+		let h0 = f0.header(&SHA256D::default());
+		let h1 = f1.header(&h0);
+		let h2 = f2.header(&h1);
+		assert_eq!(db.block_filter(b0.hash()).unwrap(), Some(f0));
+		assert_eq!(db.block_filter(b2.hash()).unwrap(), Some(f2.clone()));
+		assert_eq!(db.block_filter_header(b0.hash()).unwrap(), Some(h0));
+		assert_eq!(db.block_filter_header(b1.hash()).unwrap(), Some(h1));
+		assert_eq!(db.block_filter_header(b2.hash()).unwrap(), Some(h2));
+		assert!(f2.match_any(b2.hash(), vec![&spent_script[..]].into_iter()));
+
+		// filters of decanonized blocks are still valid => they are kept
+		db.decanonize().unwrap();
+		assert_eq!(db.block_filter_header(b2.hash()).unwrap(), Some(h2));
+
+		// filter of the new block is built on canonization, once previous blocks are filtered
+		#[rustfmt::skip]
+		let b3: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(70).build().build()
+			.merkled_header().parent(*b2.hash()).build()
+			.build()
+			.into();
+		db.canonize(b2.hash()).unwrap();
+		db.insert(b3.clone()).unwrap();
+		db.canonize(b3.hash()).unwrap();
+		assert_eq!(
+			db.block_filter_header(b3.hash()).unwrap(),
+			Some(BlockFilter::basic(&b3, vec![]).header(&h2))
+		);
+	}
+
+	#[test]
+	fn test_block_filters_disabled() {
+		let db = BlockChainDatabase::transient(&block_h0().into()).unwrap();
+		assert_eq!(db.block_filter(&SHA256D::default()).unwrap_err(), Error::BlockFilterIndexDisabled);
+		assert_eq!(
+			db.block_filter_header(&SHA256D::default()).unwrap_err(),
+			Error::BlockFilterIndexDisabled
+		);
+	}
 }
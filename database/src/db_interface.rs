@@ -1,6 +1,15 @@
-use bitcrypto::SHA256D;
-use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
-use storage::{BlockHeight, BlockMeta, TransactionMeta};
+use bitcrypto::{SHA256, SHA256D};
+use chain::{BlockFilter, IndexedBlock, IndexedBlockHeader, IndexedTransaction};
+use storage::{BlockHeight, BlockMeta, ScriptHashEntry, TransactionMeta};
+
+/// Range of canon blocks, which are covered by the address index.
+#[derive(Debug, Clone, PartialEq, Serializable, Deserializable)]
+pub struct AddressIndexRange {
+	/// Height of the first indexed block
+	pub start: u32,
+	/// Hash of the last indexed block
+	pub tip: SHA256D,
+}
 
 pub trait DbInterface: Send + Sync {
 	fn insert_block(&self, block: IndexedBlock) -> Result<(), storage::Error>;
@@ -31,6 +40,20 @@ pub trait DbInterface: Send + Sync {
 
 	fn set_best(&self, block_number: u32) -> Result<(), storage::Error>;
 
+	fn push_script_hash_entry(&self, script_hash: &SHA256, entry: ScriptHashEntry) -> Result<(), storage::Error>;
+
+	fn pop_script_hash_entry(&self, script_hash: &SHA256) -> Result<Option<ScriptHashEntry>, storage::Error>;
+
+	fn fetch_script_hash_entries(&self, script_hash: &SHA256) -> Result<Vec<ScriptHashEntry>, storage::Error>;
+
+	fn address_index_range(&self) -> Result<Option<AddressIndexRange>, storage::Error>;
+
+	fn set_address_index_range(&self, range: &AddressIndexRange) -> Result<(), storage::Error>;
+
+	fn insert_block_filter(&self, block_hash: &SHA256D, filter: &BlockFilter, header: &SHA256D) -> Result<(), storage::Error>;
+
+	fn fetch_block_filter(&self, block_hash: &SHA256D) -> Result<Option<(BlockFilter, SHA256D)>, storage::Error>;
+
 	fn flush(&self) -> Result<(), storage::Error>;
 
 	fn stats(&self) -> Result<(), storage::Error>;
@@ -1,12 +1,15 @@
-use crate::db_interface::DbInterface;
-use crate::ham_types::{DbBlock, DbInputKey, DbOutputKey, DbTransaction};
-use bitcrypto::SHA256D;
-use chain::{BlockHeader, IndexedBlock, IndexedBlockHeader, IndexedTransaction, Transaction};
+use crate::db_interface::{AddressIndexRange, DbInterface};
+use crate::ham_types::{
+	DbAddressIndexRangeKey, DbBlock, DbBlockFilter, DbBlockFilterKey, DbScriptHashEntry, DbScriptHashHistory, DbScriptHashKey,
+	DbTransaction,
+};
+use bitcrypto::{SHA256, SHA256D};
+use chain::{BlockFilter, BlockHeader, IndexedBlock, IndexedBlockHeader, IndexedTransaction, Transaction};
 use hammersbald::{persistent, transient, HammersbaldAPI};
 use parking_lot::RwLock;
 use serialization::{deserialize, serialize, Deserializable, Serializable};
 use std::sync::Arc;
-use storage::{BlockHeight, BlockMeta, Error, TransactionMeta};
+use storage::{BlockHeight, BlockMeta, Error, ScriptHashEntry, TransactionMeta};
 
 pub type PRef = u64;
 const BEST_PREF: PRef = 0;
@@ -110,17 +113,6 @@ impl DbInterface for HamDb {
 			let tx_pref = self.put(&tx.raw)?;
 			let db_tx = DbTransaction { tx_pref, meta_pref };
 			let db_tx_pref = self.put_keyed(&tx.hash, &db_tx)?;
-
-			for input in &tx.raw.inputs {
-				if !input.previous_output.is_null() {
-					let input = DbInputKey::from(input);
-					self.put_keyed(&input, &db_tx_pref)?;
-				}
-			}
-			for output in &tx.raw.outputs {
-				let key = DbOutputKey::from(output);
-				self.put_keyed(&key, &db_tx_pref)?;
-			}
 			tx_refs.push(db_tx_pref);
 		}
 		let mut meta = BlockMeta::default();
@@ -275,6 +267,87 @@ impl DbInterface for HamDb {
 		return Ok(());
 	}
 
+	fn push_script_hash_entry(&self, script_hash: &SHA256, entry: ScriptHashEntry) -> Result<(), storage::Error> {
+		let key = DbScriptHashKey::from(script_hash);
+		let history = self
+			.get_by_key::<DbScriptHashKey, DbScriptHashHistory>(&key)?
+			.map(|(_, history)| history)
+			.unwrap_or_default();
+		let last = self.put(&DbScriptHashEntry { entry, prev: history.last })?;
+		// history has fixed size => it is updated in place
+		self.put_keyed(
+			&key,
+			&DbScriptHashHistory {
+				last,
+				len: history.len + 1,
+			},
+		)?;
+		Ok(())
+	}
+
+	fn pop_script_hash_entry(&self, script_hash: &SHA256) -> Result<Option<ScriptHashEntry>, storage::Error> {
+		let key = DbScriptHashKey::from(script_hash);
+		let history = match self.get_by_key::<DbScriptHashKey, DbScriptHashHistory>(&key)? {
+			Some((_, history)) if history.len > 0 => history,
+			_ => return Ok(None),
+		};
+		let last = self
+			.get_by_pref::<DbScriptHashEntry>(history.last)?
+			.ok_or(storage::Error::InconsistentData)?;
+		self.put_keyed(
+			&key,
+			&DbScriptHashHistory {
+				last: last.prev,
+				len: history.len - 1,
+			},
+		)?;
+		Ok(Some(last.entry))
+	}
+
+	fn fetch_script_hash_entries(&self, script_hash: &SHA256) -> Result<Vec<ScriptHashEntry>, storage::Error> {
+		let history = match self.get_by_key::<DbScriptHashKey, DbScriptHashHistory>(&DbScriptHashKey::from(script_hash))? {
+			Some((_, history)) => history,
+			None => return Ok(Vec::new()),
+		};
+		let mut entries = Vec::with_capacity(history.len as usize);
+		let mut pref = history.last;
+		for _ in 0..history.len {
+			let db_entry = self
+				.get_by_pref::<DbScriptHashEntry>(pref)?
+				.ok_or(storage::Error::InconsistentData)?;
+			pref = db_entry.prev;
+			entries.push(db_entry.entry);
+		}
+		entries.reverse();
+		Ok(entries)
+	}
+
+	fn address_index_range(&self) -> Result<Option<AddressIndexRange>, storage::Error> {
+		Ok(self
+			.get_by_key::<DbAddressIndexRangeKey, AddressIndexRange>(&DbAddressIndexRangeKey::new())?
+			.map(|(_, range)| range))
+	}
+
+	fn set_address_index_range(&self, range: &AddressIndexRange) -> Result<(), storage::Error> {
+		self.put_keyed(&DbAddressIndexRangeKey::new(), range)?;
+		Ok(())
+	}
+
+	fn insert_block_filter(&self, block_hash: &SHA256D, filter: &BlockFilter, header: &SHA256D) -> Result<(), storage::Error> {
+		let db_filter = DbBlockFilter {
+			filter: filter.content.clone(),
+			header: *header,
+		};
+		self.put_keyed(&DbBlockFilterKey::from(block_hash), &db_filter)?;
+		Ok(())
+	}
+
+	fn fetch_block_filter(&self, block_hash: &SHA256D) -> Result<Option<(BlockFilter, SHA256D)>, storage::Error> {
+		Ok(self
+			.get_by_key::<DbBlockFilterKey, DbBlockFilter>(&DbBlockFilterKey::from(block_hash))?
+			.map(|(_, db_filter)| (BlockFilter::new(db_filter.filter), db_filter.header)))
+	}
+
 	fn flush(&self) -> Result<(), storage::Error> {
 		self.hammersbald.write().batch().map_err(from_ham)
 	}
@@ -1,6 +1,7 @@
 use crate::ham_db::PRef;
-use bitcrypto::sha256;
-use chain::{TransactionInput, TransactionOutput};
+use bitcrypto::{SHA256, SHA256D};
+use storage::bytes::Bytes;
+use storage::ScriptHashEntry;
 
 // Keyed by block hash.
 #[derive(Debug, Clone, Serializable, Deserializable)]
@@ -17,30 +18,65 @@ pub struct DbTransaction {
 	pub meta_pref: PRef,
 }
 
+// Keyed by script hash. Length of the serialized key (34 bytes) differs from the other keys.
 #[derive(Debug, Clone, Serializable, Deserializable)]
-pub struct DbInputKey {
-	pub hash_prefix: Vec<u8>,
-	pub index: u16,
+pub struct DbScriptHashKey {
+	prefix: u8,
+	script_hash: Vec<u8>,
 }
 
+// Head of the script hash history list.
+#[derive(Debug, Clone, Default, Serializable, Deserializable)]
+pub struct DbScriptHashHistory {
+	pub last: PRef, // PRef of the last DbScriptHashEntry, unused if len == 0
+	pub len: u32,
+}
+
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbScriptHashEntry {
+	pub entry: ScriptHashEntry,
+	pub prev: PRef, // PRef of the previous DbScriptHashEntry
+}
+
+// Keyed by block hash. Length of the serialized key (33 bytes) differs from the other keys.
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbBlockFilterKey {
+	prefix: u8,
+	block_hash: SHA256D,
+}
+
+#[derive(Debug, Clone, Serializable, Deserializable)]
+pub struct DbBlockFilter {
+	pub filter: Bytes,
+	pub header: SHA256D,
+}
+
+// Key of the address index range. Length of the serialized key (1 byte) differs from the other keys.
 #[derive(Debug, Clone, Serializable, Deserializable)]
-pub struct DbOutputKey {
-	script_hash_prefix: Vec<u8>,
+pub struct DbAddressIndexRangeKey {
+	prefix: u8,
 }
 
-impl DbInputKey {
-	pub fn from(input: &TransactionInput) -> DbInputKey {
-		DbInputKey {
-			hash_prefix: input.previous_output.hash[0..7].to_vec(),
-			index: input.previous_output.index as u16,
+impl DbScriptHashKey {
+	pub fn from(script_hash: &SHA256) -> DbScriptHashKey {
+		DbScriptHashKey {
+			prefix: b's',
+			script_hash: script_hash.to_vec(),
 		}
 	}
 }
 
-impl DbOutputKey {
-	pub fn from(output: &TransactionOutput) -> DbOutputKey {
-		DbOutputKey {
-			script_hash_prefix: sha256(&output.script_pubkey[..]).to_vec(),
+impl DbAddressIndexRangeKey {
+	pub fn new() -> DbAddressIndexRangeKey {
+		DbAddressIndexRangeKey { prefix: b'a' }
+	}
+}
+
+impl DbBlockFilterKey {
+	pub fn from(block_hash: &SHA256D) -> DbBlockFilterKey {
+		DbBlockFilterKey {
+			prefix: b'f',
+			block_hash: *block_hash,
 		}
 	}
 }
@@ -1,9 +1,9 @@
-use crate::db_interface::DbInterface;
-use bitcrypto::SHA256D;
-use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
+use crate::db_interface::{AddressIndexRange, DbInterface};
+use bitcrypto::{SHA256, SHA256D};
+use chain::{BlockFilter, IndexedBlock, IndexedBlockHeader, IndexedTransaction};
 use parking_lot::RwLock;
 use std::collections::HashMap;
-use storage::{BlockHeight, BlockMeta, Error, TransactionMeta};
+use storage::{BlockHeight, BlockMeta, Error, ScriptHashEntry, TransactionMeta};
 
 #[derive(Default)]
 struct MemoryDatabase {
@@ -14,6 +14,12 @@ struct MemoryDatabase {
 	tx_metas: HashMap<SHA256D, TransactionMeta>,
 	block_numbers: HashMap<u32, SHA256D>,
 	best_block: Option<u32>,
+	/// entries, pushed on top of the underlying database entries
+	script_hash_pushed: HashMap<SHA256, Vec<ScriptHashEntry>>,
+	/// number of entries, popped from the underlying database entries
+	script_hash_popped: HashMap<SHA256, usize>,
+	address_index_range: Option<AddressIndexRange>,
+	block_filters: HashMap<SHA256D, (BlockFilter, SHA256D)>,
 }
 
 pub struct OverlayDatabase<'a, H>
@@ -184,6 +190,59 @@ impl<'a, T: DbInterface> DbInterface for OverlayDatabase<'a, T> {
 		Ok(())
 	}
 
+	fn push_script_hash_entry(&self, script_hash: &SHA256, entry: ScriptHashEntry) -> Result<(), storage::Error> {
+		self.mem_db.write().script_hash_pushed.entry(*script_hash).or_default().push(entry);
+		Ok(())
+	}
+
+	fn pop_script_hash_entry(&self, script_hash: &SHA256) -> Result<Option<ScriptHashEntry>, storage::Error> {
+		let mut db = self.mem_db.write();
+		if let Some(entry) = db.script_hash_pushed.get_mut(script_hash).and_then(Vec::pop) {
+			return Ok(Some(entry));
+		}
+
+		let mut entries = self.ham_db.fetch_script_hash_entries(script_hash)?;
+		let popped = db.script_hash_popped.entry(*script_hash).or_default();
+		if *popped == entries.len() {
+			return Ok(None);
+		}
+		*popped += 1;
+		Ok(Some(entries.swap_remove(entries.len() - *popped)))
+	}
+
+	fn fetch_script_hash_entries(&self, script_hash: &SHA256) -> Result<Vec<ScriptHashEntry>, storage::Error> {
+		let mut entries = self.ham_db.fetch_script_hash_entries(script_hash)?;
+		let db = self.mem_db.read();
+		let popped = db.script_hash_popped.get(script_hash).cloned().unwrap_or_default();
+		entries.truncate(entries.len() - popped);
+		entries.extend(db.script_hash_pushed.get(script_hash).cloned().unwrap_or_default());
+		Ok(entries)
+	}
+
+	fn address_index_range(&self) -> Result<Option<AddressIndexRange>, storage::Error> {
+		match self.mem_db.read().address_index_range {
+			Some(ref range) => Ok(Some(range.clone())),
+			None => self.ham_db.address_index_range(),
+		}
+	}
+
+	fn set_address_index_range(&self, range: &AddressIndexRange) -> Result<(), storage::Error> {
+		self.mem_db.write().address_index_range = Some(range.clone());
+		Ok(())
+	}
+
+	fn insert_block_filter(&self, block_hash: &SHA256D, filter: &BlockFilter, header: &SHA256D) -> Result<(), storage::Error> {
+		self.mem_db.write().block_filters.insert(*block_hash, (filter.clone(), *header));
+		Ok(())
+	}
+
+	fn fetch_block_filter(&self, block_hash: &SHA256D) -> Result<Option<(BlockFilter, SHA256D)>, storage::Error> {
+		if let Some(filter) = self.mem_db.read().block_filters.get(block_hash) {
+			return Ok(Some(filter.clone()));
+		}
+		self.ham_db.fetch_block_filter(block_hash)
+	}
+
 	fn flush(&self) -> Result<(), storage::Error> {
 		let mut blocks = vec![];
 		for header in self.mem_db.read().block_headers.values() {
@@ -203,6 +262,22 @@ impl<'a, T: DbInterface> DbInterface for OverlayDatabase<'a, T> {
 		for (number, hash) in db.block_numbers.iter() {
 			self.ham_db.set_block_by_number(hash, *number)?;
 		}
+		for (script_hash, popped) in db.script_hash_popped.iter() {
+			for _ in 0..*popped {
+				self.ham_db.pop_script_hash_entry(script_hash)?;
+			}
+		}
+		for (script_hash, pushed) in db.script_hash_pushed.iter() {
+			for entry in pushed {
+				self.ham_db.push_script_hash_entry(script_hash, entry.clone())?;
+			}
+		}
+		for (block_hash, (filter, header)) in db.block_filters.iter() {
+			self.ham_db.insert_block_filter(block_hash, filter, header)?;
+		}
+		if let Some(ref range) = db.address_index_range {
+			self.ham_db.set_address_index_range(range)?;
+		}
 		if db.best_block.is_some() {
 			self.ham_db.set_best(db.best_block.unwrap())?;
 		}
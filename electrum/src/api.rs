@@ -0,0 +1,461 @@
+use crate::errors::{execution, invalid_params, storage as storage_error, transaction_not_found};
+use crate::fee::estimate_fee_rate;
+use crate::history::{memory_pool_entries, ScriptHashHistory};
+use crate::session::Session;
+use crate::types::{Balance, HeaderNotification, HistoryItem, ScriptHash, Unspent};
+use bitcrypto::{FromHex, ToHex, SHA256D};
+use chain::constants::SATOSHIS_IN_COIN;
+use chain::{IndexedTransaction, Transaction};
+use jsonrpc_core::{Error, Value};
+use jsonrpc_derive::rpc;
+use ser::{deserialize, serialize, serialize_with_flags, Reader, SERIALIZE_TRANSACTION_WITNESS};
+use std::sync::Arc;
+
+/// Name and version of the server, reported to clients
+const SERVER_VERSION: &str = concat!("bitcoin-rs ", env!("CARGO_PKG_VERSION"));
+/// Version of the Electrum protocol we're speaking
+const PROTOCOL_VERSION: &str = "1.4";
+
+/// Electrum protocol methods.
+/// @author: https://electrumx.readthedocs.io/en/latest/protocol-methods.html
+#[rpc(server)]
+pub trait Electrum {
+	type Metadata;
+
+	/// Identifies the server and negotiates protocol version.
+	#[rpc(name = "server.version")]
+	fn server_version(&self, client_name: Option<String>, protocol_version: Option<Value>) -> Result<Vec<String>, Error>;
+	/// Keeps connection alive.
+	#[rpc(name = "server.ping")]
+	fn server_ping(&self) -> Result<Value, Error>;
+	/// Subscribes to best header updates. Returns current best header.
+	#[rpc(meta, name = "blockchain.headers.subscribe")]
+	fn headers_subscribe(&self, session: Self::Metadata) -> Result<HeaderNotification, Error>;
+	/// Returns confirmed and unconfirmed history of the script hash.
+	#[rpc(name = "blockchain.scripthash.get_history")]
+	fn script_hash_get_history(&self, script_hash: ScriptHash) -> Result<Vec<HistoryItem>, Error>;
+	/// Returns confirmed and unconfirmed balance of the script hash.
+	#[rpc(name = "blockchain.scripthash.get_balance")]
+	fn script_hash_get_balance(&self, script_hash: ScriptHash) -> Result<Balance, Error>;
+	/// Returns unspent outputs of the script hash.
+	#[rpc(name = "blockchain.scripthash.listunspent")]
+	fn script_hash_list_unspent(&self, script_hash: ScriptHash) -> Result<Vec<Unspent>, Error>;
+	/// Subscribes to the script hash status updates. Returns current status.
+	#[rpc(meta, name = "blockchain.scripthash.subscribe")]
+	fn script_hash_subscribe(&self, session: Self::Metadata, script_hash: ScriptHash) -> Result<Option<String>, Error>;
+	/// Unsubscribes from the script hash status updates.
+	#[rpc(meta, name = "blockchain.scripthash.unsubscribe")]
+	fn script_hash_unsubscribe(&self, session: Self::Metadata, script_hash: ScriptHash) -> Result<bool, Error>;
+	/// Returns raw transaction.
+	#[rpc(name = "blockchain.transaction.get")]
+	fn transaction_get(&self, tx_hash: SHA256D, verbose: Option<bool>) -> Result<String, Error>;
+	/// Broadcasts raw transaction to the network.
+	#[rpc(name = "blockchain.transaction.broadcast")]
+	fn transaction_broadcast(&self, raw_tx: String) -> Result<SHA256D, Error>;
+	/// Estimates fee rate (in BTC per kilobyte), required for transaction to be confirmed within given number of blocks.
+	#[rpc(name = "blockchain.estimatefee")]
+	fn estimate_fee(&self, blocks: u32) -> Result<f64, Error>;
+}
+
+pub struct ElectrumClient<T: ElectrumClientCoreApi> {
+	core: Arc<T>,
+}
+
+pub trait ElectrumClientCoreApi: Send + Sync + 'static {
+	fn best_header(&self) -> HeaderNotification;
+	fn script_hash_history(&self, script_hash: &ScriptHash) -> Result<ScriptHashHistory, storage::Error>;
+	fn transaction(&self, tx_hash: &SHA256D) -> Option<IndexedTransaction>;
+	fn accept_transaction(&self, transaction: Transaction) -> Result<SHA256D, String>;
+	fn fee_rate(&self, blocks: u32) -> u64;
+}
+
+pub struct ElectrumClientCore {
+	storage: storage::SharedStore,
+	local_sync_node: sync::LocalNodeRef,
+	max_block_size: usize,
+}
+
+impl ElectrumClientCore {
+	pub fn new(storage: storage::SharedStore, local_sync_node: sync::LocalNodeRef, max_block_size: usize) -> Self {
+		ElectrumClientCore {
+			storage,
+			local_sync_node,
+			max_block_size,
+		}
+	}
+}
+
+impl ElectrumClientCoreApi for ElectrumClientCore {
+	fn best_header(&self) -> HeaderNotification {
+		let best_block = self.storage.best_block();
+		let best_header = self.storage.best_header();
+		HeaderNotification {
+			height: best_block.number,
+			hex: serialize(&best_header.raw)[..].to_hex(),
+		}
+	}
+
+	fn script_hash_history(&self, script_hash: &ScriptHash) -> Result<ScriptHashHistory, storage::Error> {
+		// read memory pool first, so that transactions that are mined in between are listed at least once
+		let memory_pool = self.local_sync_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		let unconfirmed = memory_pool_entries(self.storage.as_transaction_output_provider(), &memory_pool, script_hash);
+		let confirmed = self.storage.script_hash_history(&script_hash.0)?;
+		Ok(ScriptHashHistory { confirmed, unconfirmed })
+	}
+
+	fn transaction(&self, tx_hash: &SHA256D) -> Option<IndexedTransaction> {
+		let memory_pool = self.local_sync_node.memory_pool();
+		let transaction = memory_pool.read().get(tx_hash).cloned();
+		transaction
+			.map(|transaction| IndexedTransaction::new(*tx_hash, transaction))
+			.or_else(|| self.storage.transaction(tx_hash))
+	}
+
+	fn accept_transaction(&self, transaction: Transaction) -> Result<SHA256D, String> {
+		self.local_sync_node.accept_transaction(IndexedTransaction::from_raw(transaction))
+	}
+
+	fn fee_rate(&self, blocks: u32) -> u64 {
+		let memory_pool = self.local_sync_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		estimate_fee_rate(&memory_pool, blocks as usize * self.max_block_size)
+	}
+}
+
+impl<T> ElectrumClient<T>
+where
+	T: ElectrumClientCoreApi,
+{
+	pub fn new(core: Arc<T>) -> Self {
+		ElectrumClient { core }
+	}
+
+	fn history(&self, script_hash: &ScriptHash) -> Result<ScriptHashHistory, Error> {
+		self.core.script_hash_history(script_hash).map_err(storage_error)
+	}
+}
+
+impl<T> Electrum for ElectrumClient<T>
+where
+	T: ElectrumClientCoreApi,
+{
+	type Metadata = Session;
+
+	fn server_version(&self, _client_name: Option<String>, _protocol_version: Option<Value>) -> Result<Vec<String>, Error> {
+		Ok(vec![SERVER_VERSION.into(), PROTOCOL_VERSION.into()])
+	}
+
+	fn server_ping(&self) -> Result<Value, Error> {
+		Ok(Value::Null)
+	}
+
+	fn headers_subscribe(&self, session: Session) -> Result<HeaderNotification, Error> {
+		session.subscribe_headers();
+		Ok(self.core.best_header())
+	}
+
+	fn script_hash_get_history(&self, script_hash: ScriptHash) -> Result<Vec<HistoryItem>, Error> {
+		Ok(self.history(&script_hash)?.items())
+	}
+
+	fn script_hash_get_balance(&self, script_hash: ScriptHash) -> Result<Balance, Error> {
+		Ok(self.history(&script_hash)?.balance())
+	}
+
+	fn script_hash_list_unspent(&self, script_hash: ScriptHash) -> Result<Vec<Unspent>, Error> {
+		Ok(self.history(&script_hash)?.unspent())
+	}
+
+	fn script_hash_subscribe(&self, session: Session, script_hash: ScriptHash) -> Result<Option<String>, Error> {
+		let status = self.history(&script_hash)?.status();
+		session.subscribe_script_hash(script_hash, status.clone());
+		Ok(status)
+	}
+
+	fn script_hash_unsubscribe(&self, session: Session, script_hash: ScriptHash) -> Result<bool, Error> {
+		Ok(session.unsubscribe_script_hash(&script_hash))
+	}
+
+	fn transaction_get(&self, tx_hash: SHA256D, verbose: Option<bool>) -> Result<String, Error> {
+		if verbose.unwrap_or(false) {
+			return Err(invalid_params("verbose", "verbose transactions are not supported"));
+		}
+
+		self.core
+			.transaction(&tx_hash)
+			.map(|transaction| serialize_with_flags(&transaction.raw, SERIALIZE_TRANSACTION_WITNESS)[..].to_hex())
+			.ok_or_else(|| transaction_not_found(tx_hash))
+	}
+
+	fn transaction_broadcast(&self, raw_tx: String) -> Result<SHA256D, Error> {
+		let raw_tx: Vec<u8> = FromHex::from_hex(&raw_tx).map_err(|e| invalid_params("raw_tx", e))?;
+		let transaction = deserialize(Reader::new(&raw_tx)).map_err(|e| invalid_params("raw_tx", e))?;
+		self.core.accept_transaction(transaction).map_err(execution)
+	}
+
+	fn estimate_fee(&self, blocks: u32) -> Result<f64, Error> {
+		if blocks == 0 {
+			return Err(invalid_params("number", "number of blocks must be positive"));
+		}
+
+		Ok(self.core.fee_rate(blocks) as f64 / SATOSHIS_IN_COIN as f64)
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::history::UnconfirmedEntry;
+	use crate::session::tests::SharedWriter;
+	use jsonrpc_core::MetaIoHandler;
+	use test_data::script_hash_entry;
+
+	const RAW_TRANSACTION: &str =
+		"01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff00ffffffff0100000000000000000000000000";
+	const SCRIPT_HASH: &str = "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161";
+
+	#[derive(Default)]
+	pub struct SuccessElectrumClientCore;
+	#[derive(Default)]
+	struct ErrorElectrumClientCore;
+
+	fn raw_transaction() -> Transaction {
+		RAW_TRANSACTION.into()
+	}
+
+	impl ElectrumClientCoreApi for SuccessElectrumClientCore {
+		fn best_header(&self) -> HeaderNotification {
+			HeaderNotification {
+				height: 1,
+				hex: "00".into(),
+			}
+		}
+
+		fn script_hash_history(&self, _script_hash: &ScriptHash) -> Result<ScriptHashHistory, storage::Error> {
+			Ok(ScriptHashHistory {
+				confirmed: vec![script_hash_entry(1, 1, 1, 10, false)],
+				unconfirmed: vec![UnconfirmedEntry {
+					entry: script_hash_entry(0, 2, 1, 10, true),
+					fee: 100,
+					has_unconfirmed_inputs: false,
+				}],
+			})
+		}
+
+		fn transaction(&self, _tx_hash: &SHA256D) -> Option<IndexedTransaction> {
+			Some(raw_transaction().into())
+		}
+
+		fn accept_transaction(&self, transaction: Transaction) -> Result<SHA256D, String> {
+			Ok(IndexedTransaction::from_raw(transaction).hash)
+		}
+
+		fn fee_rate(&self, blocks: u32) -> u64 {
+			blocks as u64 * 1_000
+		}
+	}
+
+	impl ElectrumClientCoreApi for ErrorElectrumClientCore {
+		fn best_header(&self) -> HeaderNotification {
+			HeaderNotification {
+				height: 0,
+				hex: "00".into(),
+			}
+		}
+
+		fn script_hash_history(&self, _script_hash: &ScriptHash) -> Result<ScriptHashHistory, storage::Error> {
+			Err(storage::Error::AddressIndexDisabled)
+		}
+
+		fn transaction(&self, _tx_hash: &SHA256D) -> Option<IndexedTransaction> {
+			None
+		}
+
+		fn accept_transaction(&self, _transaction: Transaction) -> Result<SHA256D, String> {
+			Err("error".into())
+		}
+
+		fn fee_rate(&self, _blocks: u32) -> u64 {
+			0
+		}
+	}
+
+	pub fn handler<T: ElectrumClientCoreApi>(core: T) -> MetaIoHandler<Session> {
+		let mut handler = MetaIoHandler::default();
+		handler.extend_with(ElectrumClient::new(Arc::new(core)).to_delegate());
+		handler
+	}
+
+	fn request(handler: &MetaIoHandler<Session>, session: &Session, method: &str, params: &str) -> String {
+		let request = format!(r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#, method, params);
+		handler.handle_request_sync(&request, session.clone()).unwrap()
+	}
+
+	fn session() -> Session {
+		Session::new(Box::new(SharedWriter::default()))
+	}
+
+	#[test]
+	fn server_version() {
+		let sample = request(
+			&handler(SuccessElectrumClientCore),
+			&session(),
+			"server.version",
+			r#"["electrum", "1.4"]"#,
+		);
+		assert_eq!(
+			sample,
+			format!(r#"{{"jsonrpc":"2.0","result":["{}","1.4"],"id":1}}"#, SERVER_VERSION)
+		);
+	}
+
+	#[test]
+	fn headers_subscribe() {
+		let session = session();
+		assert!(!session.is_subscribed_to_headers());
+		let sample = request(&handler(SuccessElectrumClientCore), &session, "blockchain.headers.subscribe", "[]");
+		assert_eq!(sample, r#"{"jsonrpc":"2.0","result":{"height":1,"hex":"00"},"id":1}"#);
+		assert!(session.is_subscribed_to_headers());
+	}
+
+	#[test]
+	fn script_hash_get_history() {
+		let sample = request(
+			&handler(SuccessElectrumClientCore),
+			&session(),
+			"blockchain.scripthash.get_history",
+			&format!(r#"["{}"]"#, SCRIPT_HASH),
+		);
+		assert_eq!(
+			sample,
+			r#"{"jsonrpc":"2.0","result":[{"height":1,"tx_hash":"0101010101010101010101010101010101010101010101010101010101010101"},{"fee":100,"height":0,"tx_hash":"0202020202020202020202020202020202020202020202020202020202020202"}],"id":1}"#
+		);
+	}
+
+	#[test]
+	fn script_hash_get_balance() {
+		let sample = request(
+			&handler(SuccessElectrumClientCore),
+			&session(),
+			"blockchain.scripthash.get_balance",
+			&format!(r#"["{}"]"#, SCRIPT_HASH),
+		);
+		assert_eq!(sample, r#"{"jsonrpc":"2.0","result":{"confirmed":10,"unconfirmed":-10},"id":1}"#);
+	}
+
+	#[test]
+	fn script_hash_list_unspent() {
+		let sample = request(
+			&handler(SuccessElectrumClientCore),
+			&session(),
+			"blockchain.scripthash.listunspent",
+			&format!(r#"["{}"]"#, SCRIPT_HASH),
+		);
+		assert_eq!(sample, r#"{"jsonrpc":"2.0","result":[],"id":1}"#);
+	}
+
+	#[test]
+	fn script_hash_subscribe_and_unsubscribe() {
+		let session = session();
+		let handler = handler(SuccessElectrumClientCore);
+		let params = format!(r#"["{}"]"#, SCRIPT_HASH);
+		let status = SuccessElectrumClientCore
+			.script_hash_history(&ScriptHash::from_electrum_hex(SCRIPT_HASH).unwrap())
+			.unwrap()
+			.status()
+			.unwrap();
+
+		let sample = request(&handler, &session, "blockchain.scripthash.subscribe", &params);
+		assert_eq!(sample, format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, status));
+		assert_eq!(
+			session.subscribed_script_hashes(),
+			vec![ScriptHash::from_electrum_hex(SCRIPT_HASH).unwrap()]
+		);
+
+		let sample = request(&handler, &session, "blockchain.scripthash.unsubscribe", &params);
+		assert_eq!(sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+		let sample = request(&handler, &session, "blockchain.scripthash.unsubscribe", &params);
+		assert_eq!(sample, r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+	}
+
+	#[test]
+	fn script_hash_invalid() {
+		let sample = request(
+			&handler(SuccessElectrumClientCore),
+			&session(),
+			"blockchain.scripthash.get_balance",
+			r#"["00"]"#,
+		);
+		assert!(sample.contains(r#""code":-32602"#));
+	}
+
+	#[test]
+	fn script_hash_index_disabled() {
+		let sample = request(
+			&handler(ErrorElectrumClientCore),
+			&session(),
+			"blockchain.scripthash.get_history",
+			&format!(r#"["{}"]"#, SCRIPT_HASH),
+		);
+		assert_eq!(
+			sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32095,"message":"Address index is disabled. Restart node with --address-index"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn transaction_get() {
+		let tx_hash = IndexedTransaction::from_raw(raw_transaction()).hash;
+		let params = format!(r#"["{}"]"#, tx_hash);
+		let sample = request(
+			&handler(SuccessElectrumClientCore),
+			&session(),
+			"blockchain.transaction.get",
+			&params,
+		);
+		assert_eq!(sample, format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, RAW_TRANSACTION));
+
+		let sample = request(&handler(ErrorElectrumClientCore), &session(), "blockchain.transaction.get", &params);
+		assert!(sample.contains(r#""code":-32096"#));
+
+		let params = format!(r#"["{}", true]"#, tx_hash);
+		let sample = request(
+			&handler(SuccessElectrumClientCore),
+			&session(),
+			"blockchain.transaction.get",
+			&params,
+		);
+		assert!(sample.contains(r#""code":-32602"#));
+	}
+
+	#[test]
+	fn transaction_broadcast() {
+		let raw = &format!(r#"["{}"]"#, RAW_TRANSACTION);
+		let sample = request(
+			&handler(SuccessElectrumClientCore),
+			&session(),
+			"blockchain.transaction.broadcast",
+			raw,
+		);
+		let tx_hash = IndexedTransaction::from_raw(raw_transaction()).hash;
+		assert_eq!(sample, format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, tx_hash));
+
+		let sample = request(
+			&handler(ErrorElectrumClientCore),
+			&session(),
+			"blockchain.transaction.broadcast",
+			raw,
+		);
+		assert!(sample.contains(r#""code":-32015"#));
+	}
+
+	#[test]
+	fn estimate_fee() {
+		let sample = request(&handler(SuccessElectrumClientCore), &session(), "blockchain.estimatefee", "[2]");
+		assert_eq!(sample, r#"{"jsonrpc":"2.0","result":0.00002,"id":1}"#);
+
+		let sample = request(&handler(SuccessElectrumClientCore), &session(), "blockchain.estimatefee", "[0]");
+		assert!(sample.contains(r#""code":-32602"#));
+	}
+}
@@ -0,0 +1,48 @@
+//! Electrum error codes and error objects
+mod codes {
+	pub const EXECUTION_ERROR: i64 = -32015;
+	pub const ADDRESS_INDEX_DISABLED: i64 = -32095;
+	pub const TRANSACTION_NOT_FOUND: i64 = -32096;
+}
+
+use jsonrpc_core::{Error, ErrorCode, Value};
+use std::fmt;
+
+pub fn invalid_params<T: fmt::Debug>(param: &str, details: T) -> Error {
+	Error {
+		code: ErrorCode::InvalidParams,
+		message: format!("Invalid parameter: {}", param),
+		data: Some(Value::String(format!("{:?}", details))),
+	}
+}
+
+pub fn execution<T: fmt::Debug>(data: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::EXECUTION_ERROR),
+		message: "Execution error.".into(),
+		data: Some(Value::String(format!("{:?}", data))),
+	}
+}
+
+pub fn transaction_not_found<T: fmt::Debug>(data: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::TRANSACTION_NOT_FOUND),
+		message: "Transaction with given hash is not found".into(),
+		data: Some(Value::String(format!("{:?}", data))),
+	}
+}
+
+pub fn address_index_disabled() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::ADDRESS_INDEX_DISABLED),
+		message: "Address index is disabled. Restart node with --address-index".into(),
+		data: None,
+	}
+}
+
+pub fn storage(error: storage::Error) -> Error {
+	match error {
+		storage::Error::AddressIndexDisabled => address_index_disabled(),
+		error => execution(error),
+	}
+}
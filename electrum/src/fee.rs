@@ -0,0 +1,58 @@
+use miner::{MemoryPool, MemoryPoolOrderingStrategy as OrderingStrategy};
+use std::cmp::max;
+
+/// Minimal fee rate (in satoshis per kilobyte), required to relay transaction.
+pub const MIN_FEE_RATE: u64 = 1_000;
+
+/// Estimates fee rate (in satoshis per kilobyte), required for transaction to be included in one of the blocks,
+/// which are going to be mined next, given that these blocks have total size of `size_limit` bytes.
+///
+/// Memory pool transactions are sorted by their fee rate. If they are not filling `size_limit` bytes,
+/// any transaction that pays minimal relay fee is expected to be mined.
+pub fn estimate_fee_rate(memory_pool: &MemoryPool, size_limit: usize) -> u64 {
+	let mut total_size = 0;
+	for entry in memory_pool.iter(OrderingStrategy::ByTransactionScore) {
+		total_size += entry.size;
+		if total_size > size_limit {
+			return max(entry.miner_fee * 1_000 / entry.size as u64, MIN_FEE_RATE);
+		}
+	}
+	MIN_FEE_RATE
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use super::*;
+	use miner::NonZeroFeeCalculator;
+
+	#[test]
+	fn estimate_fee_rate_empty_memory_pool() {
+		assert_eq!(estimate_fee_rate(&MemoryPool::new(), 1_000_000), MIN_FEE_RATE);
+	}
+
+	#[test]
+	fn estimate_fee_rate_full_memory_pool() {
+		let mut memory_pool = MemoryPool::new();
+		// NonZeroFeeCalculator: the larger outputs sum is, the larger is the fee
+		memory_pool
+			.insert_verified(test_data::TransactionBuilder::with_output(10).into(), &NonZeroFeeCalculator)
+			.unwrap();
+		memory_pool
+			.insert_verified(test_data::TransactionBuilder::with_output(20).into(), &NonZeroFeeCalculator)
+			.unwrap();
+		let entries: Vec<_> = memory_pool
+			.iter(OrderingStrategy::ByTransactionScore)
+			.map(|entry| (entry.size, entry.miner_fee * 1_000 / entry.size as u64))
+			.collect();
+		assert_eq!(entries.len(), 2);
+
+		// nothing fits into block => best transaction rate is required
+		assert_eq!(estimate_fee_rate(&memory_pool, 0), entries[0].1);
+		// best transaction fits into block => worst transaction rate is required
+		assert_eq!(estimate_fee_rate(&memory_pool, entries[0].0), entries[1].1);
+		// whole memory pool fits into block
+		assert_eq!(estimate_fee_rate(&memory_pool, entries[0].0 + entries[1].0), MIN_FEE_RATE);
+	}
+}
@@ -0,0 +1,286 @@
+use crate::types::{Balance, HistoryItem, ScriptHash, Unspent};
+use bitcrypto::{sha256, ToHex};
+use miner::{MemoryPool, MemoryPoolOrderingStrategy as OrderingStrategy};
+use std::collections::HashSet;
+use storage::{DuplexTransactionOutputProvider, ScriptHashEntry, TransactionOutputProvider};
+
+/// Funding or spending of an output by memory pool transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnconfirmedEntry {
+	/// Entry itself. Height is always 0
+	pub entry: ScriptHashEntry,
+	/// Fee of the memory pool transaction
+	pub fee: u64,
+	/// True if memory pool transaction spends outputs of other memory pool transactions
+	pub has_unconfirmed_inputs: bool,
+}
+
+/// Full history of the script hash: confirmed entries, followed by memory pool entries.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScriptHashHistory {
+	pub confirmed: Vec<ScriptHashEntry>,
+	pub unconfirmed: Vec<UnconfirmedEntry>,
+}
+
+impl ScriptHashHistory {
+	/// Transactions, touching the script hash. Every transaction is listed once.
+	pub fn items(&self) -> Vec<HistoryItem> {
+		let mut known_tx_hashes = HashSet::new();
+		let confirmed = self.confirmed.iter().map(|entry| HistoryItem {
+			tx_hash: entry.tx_hash,
+			height: entry.height as i64,
+			fee: None,
+		});
+		let unconfirmed = self.unconfirmed.iter().map(|unconfirmed| HistoryItem {
+			tx_hash: unconfirmed.entry.tx_hash,
+			height: if unconfirmed.has_unconfirmed_inputs { -1 } else { 0 },
+			fee: Some(unconfirmed.fee),
+		});
+		confirmed
+			.chain(unconfirmed)
+			.filter(|item| known_tx_hashes.insert(item.tx_hash))
+			.collect()
+	}
+
+	/// Electrum status of the script hash: `sha256("tx_hash:height:" for all history items)`.
+	/// None if script hash has no history.
+	pub fn status(&self) -> Option<String> {
+		let items = self.items();
+		if items.is_empty() {
+			return None;
+		}
+
+		let status: String = items.iter().map(|item| format!("{}:{}:", item.tx_hash, item.height)).collect();
+		Some(sha256(status.as_bytes())[..].to_hex())
+	}
+
+	pub fn balance(&self) -> Balance {
+		let confirmed = self.confirmed.iter().fold(0, |acc, entry| match entry.is_spending {
+			true => acc - entry.value as i64,
+			false => acc + entry.value as i64,
+		});
+		let unconfirmed = self
+			.unconfirmed
+			.iter()
+			.fold(0, |acc, unconfirmed| match unconfirmed.entry.is_spending {
+				true => acc - unconfirmed.entry.value as i64,
+				false => acc + unconfirmed.entry.value as i64,
+			});
+		Balance {
+			confirmed: confirmed as u64,
+			unconfirmed,
+		}
+	}
+
+	/// Outputs that are spent neither by canon chain, nor by memory pool transactions.
+	pub fn unspent(&self) -> Vec<Unspent> {
+		let entries = self
+			.confirmed
+			.iter()
+			.chain(self.unconfirmed.iter().map(|unconfirmed| &unconfirmed.entry));
+		let spent: HashSet<_> = entries
+			.clone()
+			.filter(|entry| entry.is_spending)
+			.map(|entry| entry.outpoint.clone())
+			.collect();
+		entries
+			.filter(|entry| !entry.is_spending && !spent.contains(&entry.outpoint))
+			.map(|entry| Unspent {
+				tx_hash: entry.outpoint.hash,
+				tx_pos: entry.outpoint.index,
+				height: entry.height,
+				value: entry.value,
+			})
+			.collect()
+	}
+}
+
+/// Scans memory pool for transactions, funding or spending outputs with given script hash.
+pub fn memory_pool_entries(
+	storage: &dyn TransactionOutputProvider,
+	memory_pool: &MemoryPool,
+	script_hash: &ScriptHash,
+) -> Vec<UnconfirmedEntry> {
+	let output_provider = DuplexTransactionOutputProvider::new(storage, memory_pool);
+	let mut entries = Vec::new();
+	for mempool_entry in memory_pool.iter(OrderingStrategy::ByTimestamp) {
+		let transaction = &mempool_entry.transaction;
+		let mut push = |entry| {
+			entries.push(UnconfirmedEntry {
+				entry,
+				fee: mempool_entry.miner_fee,
+				has_unconfirmed_inputs: !mempool_entry.ancestors.is_empty(),
+			})
+		};
+
+		for input in &transaction.inputs {
+			let output = match output_provider.transaction_output(&input.previous_output, usize::MAX) {
+				Some(output) => output,
+				None => continue,
+			};
+			if ScriptHash::from_script(&output.script_pubkey) == *script_hash {
+				push(ScriptHashEntry {
+					height: 0,
+					tx_hash: mempool_entry.hash,
+					outpoint: input.previous_output.clone(),
+					value: output.value,
+					is_spending: true,
+				});
+			}
+		}
+
+		for (index, output) in transaction.outputs.iter().enumerate() {
+			if ScriptHash::from_script(&output.script_pubkey) == *script_hash {
+				push(ScriptHashEntry {
+					height: 0,
+					tx_hash: mempool_entry.hash,
+					outpoint: chain::OutPoint {
+						hash: mempool_entry.hash,
+						index: index as u32,
+					},
+					value: output.value,
+					is_spending: false,
+				});
+			}
+		}
+	}
+	entries
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use super::*;
+	use bitcrypto::{Hash, SHA256D};
+	use chain::{IndexedBlock, OutPoint};
+	use db::BlockChainDatabase;
+	use miner::FeeCalculator;
+	use storage::SharedStore;
+	use test_data::script_hash_entry;
+
+	fn unconfirmed(tx_hash: u8, funding_tx_hash: u8, value: u64, is_spending: bool, has_unconfirmed_inputs: bool) -> UnconfirmedEntry {
+		UnconfirmedEntry {
+			entry: script_hash_entry(0, tx_hash, funding_tx_hash, value, is_spending),
+			fee: 100,
+			has_unconfirmed_inputs,
+		}
+	}
+
+	fn history() -> ScriptHashHistory {
+		ScriptHashHistory {
+			// tx#1 funds output#1, tx#2 funds output#2, tx#3 spends output#1 and funds output#3
+			confirmed: vec![
+				script_hash_entry(1, 1, 1, 10, false),
+				script_hash_entry(2, 2, 2, 20, false),
+				script_hash_entry(3, 3, 1, 10, true),
+				script_hash_entry(3, 3, 3, 5, false),
+			],
+			// tx#4 spends output#2, tx#5 spends output#4, funded by tx#4
+			unconfirmed: vec![
+				unconfirmed(4, 2, 20, true, false),
+				unconfirmed(4, 4, 15, false, false),
+				unconfirmed(5, 4, 15, true, true),
+			],
+		}
+	}
+
+	#[test]
+	fn history_items() {
+		let items = history().items();
+		let heights: Vec<_> = items.iter().map(|item| item.height).collect();
+		assert_eq!(heights, vec![1, 2, 3, 0, -1]);
+		assert_eq!(items[3].fee, Some(100));
+		assert_eq!(items[2].fee, None);
+	}
+
+	#[test]
+	fn history_status() {
+		assert_eq!(ScriptHashHistory::default().status(), None);
+
+		let history = ScriptHashHistory {
+			confirmed: vec![script_hash_entry(1, 1, 1, 10, false)],
+			unconfirmed: vec![],
+		};
+		let expected = sha256(b"0101010101010101010101010101010101010101010101010101010101010101:1:")[..].to_hex();
+		assert_eq!(history.status(), Some(expected));
+		assert_ne!(self::history().status(), history.status());
+	}
+
+	#[test]
+	fn history_balance() {
+		assert_eq!(
+			history().balance(),
+			Balance {
+				confirmed: 25,
+				unconfirmed: -20,
+			}
+		);
+	}
+
+	#[test]
+	fn history_unspent() {
+		let unspent = history().unspent();
+		assert_eq!(
+			unspent,
+			vec![Unspent {
+				tx_hash: SHA256D::from_inner([3; 32]),
+				tx_pos: 0,
+				height: 3,
+				value: 5,
+			}]
+		);
+	}
+
+	#[test]
+	fn memory_pool_entries_funding_and_spending() {
+		let script_pubkey = vec![0x51];
+		let script_hash = ScriptHash::from_script(&script_pubkey);
+		let genesis: IndexedBlock = test_data::genesis().into();
+		#[rustfmt::skip]
+		let block: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(50).script_pubkey_bytes(script_pubkey.clone().into()).build().build()
+			.merkled_header().parent(*genesis.hash()).build()
+			.build()
+			.into();
+		let storage: SharedStore = std::sync::Arc::new(BlockChainDatabase::init_test_chain(vec![genesis, block.clone()]));
+		let funding_hash = block.transactions[0].hash;
+
+		// spends confirmed output and creates new output with the same script
+		let tx1: chain::IndexedTransaction = test_data::TransactionBuilder::with_output(40)
+			.add_input(&block.transactions[0].raw, 0)
+			.into();
+		let mut tx1 = tx1.raw;
+		tx1.outputs[0].script_pubkey = script_pubkey.clone().into();
+		let tx1 = chain::IndexedTransaction::from_raw(tx1);
+		// spends unconfirmed output
+		let tx2: chain::IndexedTransaction = test_data::TransactionBuilder::with_output(30).add_input(&tx1.raw, 0).into();
+
+		let mut memory_pool = MemoryPool::new();
+		memory_pool
+			.insert_verified(tx1.clone(), &FeeCalculator(storage.as_transaction_output_provider()))
+			.unwrap();
+		memory_pool
+			.insert_verified(tx2.clone(), &FeeCalculator(storage.as_transaction_output_provider()))
+			.unwrap();
+
+		let entries = memory_pool_entries(storage.as_transaction_output_provider(), &memory_pool, &script_hash);
+		assert_eq!(entries.len(), 3);
+		assert_eq!(
+			entries[0].entry.outpoint,
+			OutPoint {
+				hash: funding_hash,
+				index: 0
+			}
+		);
+		assert!(entries[0].entry.is_spending);
+		assert_eq!(entries[0].fee, 10);
+		assert!(!entries[0].has_unconfirmed_inputs);
+		assert!(!entries[1].entry.is_spending);
+		assert_eq!(entries[1].entry.tx_hash, tx1.hash);
+		assert!(entries[2].entry.is_spending);
+		assert_eq!(entries[2].entry.tx_hash, tx2.hash);
+		assert_eq!(entries[2].fee, 10);
+		assert!(entries[2].has_unconfirmed_inputs);
+	}
+}
@@ -0,0 +1,33 @@
+//! Electrum protocol server.
+//!
+//! Serves Electrum wallets over newline-delimited JSON-RPC on a TCP socket. All requests are answered from
+//! the node storage (which must maintain address index) and the memory pool.
+
+#[macro_use]
+extern crate log;
+extern crate bitcrypto;
+extern crate chain;
+extern crate jsonrpc_core;
+extern crate jsonrpc_derive;
+extern crate miner;
+extern crate network;
+extern crate parking_lot;
+extern crate serde;
+extern crate serde_json;
+extern crate serialization as ser;
+extern crate storage;
+extern crate sync;
+
+mod api;
+mod errors;
+mod fee;
+mod history;
+mod notifier;
+mod server;
+mod session;
+mod types;
+
+pub use crate::api::{Electrum, ElectrumClient, ElectrumClientCore, ElectrumClientCoreApi};
+pub use crate::notifier::ElectrumNotifier;
+pub use crate::server::{new_tcp, Dependencies, ElectrumConfiguration, ElectrumServer};
+pub use crate::session::Session;
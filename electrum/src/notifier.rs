@@ -0,0 +1,245 @@
+use crate::api::ElectrumClientCoreApi;
+use crate::session::Session;
+use crate::types::ScriptHash;
+use bitcrypto::SHA256D;
+use parking_lot::Mutex;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use sync::SyncListener;
+
+/// All connected sessions
+pub type Sessions = Arc<Mutex<Vec<Session>>>;
+
+enum ElectrumNotifierTask {
+	NewBlock,
+	Stop,
+}
+
+/// Pushes best header and script hash status notifications to subscribed sessions, when new best block is inserted.
+pub struct ElectrumNotifier {
+	tx: Sender<ElectrumNotifierTask>,
+	is_synchronizing: Arc<AtomicBool>,
+	worker_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl ElectrumNotifier {
+	pub fn new(core: Arc<dyn ElectrumClientCoreApi>, sessions: Sessions) -> Self {
+		let (tx, rx) = channel();
+		ElectrumNotifier {
+			tx,
+			is_synchronizing: Arc::new(AtomicBool::default()),
+			worker_thread: Some(
+				std::thread::Builder::new()
+					.name("Electrum notification thread".to_owned())
+					.spawn(move || ElectrumNotifier::worker(rx, core, sessions))
+					.expect("Error creating electrum notification thread"),
+			),
+		}
+	}
+
+	fn worker(rx: Receiver<ElectrumNotifierTask>, core: Arc<dyn ElectrumClientCoreApi>, sessions: Sessions) {
+		for task in rx {
+			match task {
+				ElectrumNotifierTask::NewBlock => {
+					let sessions = sessions.lock().clone();
+					notify_sessions(&*core, &sessions);
+				}
+				ElectrumNotifierTask::Stop => break,
+			}
+		}
+		trace!(target: "electrum", "Electrum notification thread stopped");
+	}
+}
+
+/// Notifies sessions about new best header and about changed statuses of subscribed script hashes.
+pub fn notify_sessions(core: &dyn ElectrumClientCoreApi, sessions: &[Session]) {
+	let header = to_value(core.best_header()).expect("header serialization never fails");
+	// the same script hash could be subscribed by several sessions => compute status once
+	let mut statuses: HashMap<ScriptHash, Option<String>> = HashMap::new();
+	for session in sessions {
+		if session.is_subscribed_to_headers() {
+			if let Err(err) = session.notify("blockchain.headers.subscribe", vec![header.clone()]) {
+				trace!(target: "electrum", "Error sending header notification: {}", err);
+				continue;
+			}
+		}
+
+		for script_hash in session.subscribed_script_hashes() {
+			let status = match statuses.get(&script_hash) {
+				Some(status) => status.clone(),
+				None => match core.script_hash_history(&script_hash) {
+					Ok(history) => {
+						let status = history.status();
+						statuses.insert(script_hash, status.clone());
+						status
+					}
+					Err(err) => {
+						warn!(target: "electrum", "Error reading script hash history: {:?}", err);
+						continue;
+					}
+				},
+			};
+
+			if !session.update_script_hash_status(&script_hash, &status) {
+				continue;
+			}
+
+			let params = vec![
+				to_value(script_hash).expect("script hash serialization never fails"),
+				status.map_or(Value::Null, Value::String),
+			];
+			if let Err(err) = session.notify("blockchain.scripthash.subscribe", params) {
+				trace!(target: "electrum", "Error sending script hash notification: {}", err);
+				break;
+			}
+		}
+	}
+}
+
+impl SyncListener for ElectrumNotifier {
+	fn synchronization_state_switched(&self, is_synchronizing: bool) {
+		self.is_synchronizing.store(is_synchronizing, Ordering::SeqCst);
+	}
+
+	fn best_storage_block_inserted(&self, _block_hash: &SHA256D) {
+		if !self.is_synchronizing.load(Ordering::SeqCst) {
+			self.tx
+				.send(ElectrumNotifierTask::NewBlock)
+				.expect("Electrum notification thread have the same lifetime as `ElectrumNotifier`")
+		}
+	}
+}
+
+impl Drop for ElectrumNotifier {
+	fn drop(&mut self) {
+		if let Some(join_handle) = self.worker_thread.take() {
+			let _ = self.tx.send(ElectrumNotifierTask::Stop);
+			join_handle.join().expect("Clean shutdown.");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::history::ScriptHashHistory;
+	use crate::session::tests::SharedWriter;
+	use crate::types::HeaderNotification;
+	use chain::{IndexedTransaction, Transaction};
+	use storage::ScriptHashEntry;
+	use test_data::script_hash_entry;
+
+	#[derive(Default)]
+	struct TestElectrumClientCore {
+		height: Mutex<u32>,
+		history: Mutex<Vec<ScriptHashEntry>>,
+	}
+
+	impl ElectrumClientCoreApi for TestElectrumClientCore {
+		fn best_header(&self) -> HeaderNotification {
+			HeaderNotification {
+				height: *self.height.lock(),
+				hex: "00".into(),
+			}
+		}
+
+		fn script_hash_history(&self, _script_hash: &ScriptHash) -> Result<ScriptHashHistory, storage::Error> {
+			Ok(ScriptHashHistory {
+				confirmed: self.history.lock().clone(),
+				unconfirmed: vec![],
+			})
+		}
+
+		fn transaction(&self, _tx_hash: &SHA256D) -> Option<IndexedTransaction> {
+			None
+		}
+
+		fn accept_transaction(&self, _transaction: Transaction) -> Result<SHA256D, String> {
+			Err("error".into())
+		}
+
+		fn fee_rate(&self, _blocks: u32) -> u64 {
+			0
+		}
+	}
+
+	#[test]
+	fn notify_sessions_headers() {
+		let core = TestElectrumClientCore::default();
+		let (subscribed_writer, writer) = (SharedWriter::default(), SharedWriter::default());
+		let subscribed = Session::new(Box::new(subscribed_writer.clone()));
+		subscribed.subscribe_headers();
+		let sessions = vec![subscribed, Session::new(Box::new(writer.clone()))];
+
+		*core.height.lock() = 10;
+		notify_sessions(&core, &sessions);
+		assert_eq!(
+			subscribed_writer.take_lines(),
+			vec![r#"{"jsonrpc":"2.0","method":"blockchain.headers.subscribe","params":[{"height":10,"hex":"00"}]}"#.to_owned()]
+		);
+		assert!(writer.take_lines().is_empty());
+	}
+
+	#[test]
+	fn notify_sessions_script_hash_status_changes() {
+		let core = TestElectrumClientCore::default();
+		let script_hash = ScriptHash::from_script(&[0x51]);
+		let writer = SharedWriter::default();
+		let session = Session::new(Box::new(writer.clone()));
+		session.subscribe_script_hash(script_hash, None);
+		let sessions = vec![session];
+
+		// status is not changed => no notification
+		notify_sessions(&core, &sessions);
+		assert!(writer.take_lines().is_empty());
+
+		// status is changed => notification
+		core.history.lock().push(script_hash_entry(1, 1, 1, 10, false));
+		let status = core.script_hash_history(&script_hash).unwrap().status().unwrap();
+		notify_sessions(&core, &sessions);
+		assert_eq!(
+			writer.take_lines(),
+			vec![format!(
+				r#"{{"jsonrpc":"2.0","method":"blockchain.scripthash.subscribe","params":["{}","{}"]}}"#,
+				script_hash.to_electrum_hex(),
+				status
+			)]
+		);
+
+		// status is not changed since last notification => no notification
+		notify_sessions(&core, &sessions);
+		assert!(writer.take_lines().is_empty());
+
+		// history is reorganized => null status
+		core.history.lock().clear();
+		notify_sessions(&core, &sessions);
+		assert_eq!(
+			writer.take_lines(),
+			vec![format!(
+				r#"{{"jsonrpc":"2.0","method":"blockchain.scripthash.subscribe","params":["{}",null]}}"#,
+				script_hash.to_electrum_hex()
+			)]
+		);
+	}
+
+	#[test]
+	fn notifier_ignores_blocks_while_synchronizing() {
+		let core = Arc::new(TestElectrumClientCore::default());
+		let writer = SharedWriter::default();
+		let session = Session::new(Box::new(writer.clone()));
+		session.subscribe_headers();
+		let notifier = ElectrumNotifier::new(core, Arc::new(Mutex::new(vec![session])));
+
+		notifier.synchronization_state_switched(true);
+		notifier.best_storage_block_inserted(&SHA256D::default());
+		notifier.synchronization_state_switched(false);
+		notifier.best_storage_block_inserted(&SHA256D::default());
+		// wait for worker thread to process all tasks
+		drop(notifier);
+
+		assert_eq!(writer.take_lines().len(), 1);
+	}
+}
@@ -0,0 +1,289 @@
+use crate::api::{Electrum, ElectrumClient, ElectrumClientCore, ElectrumClientCoreApi};
+use crate::notifier::{ElectrumNotifier, Sessions};
+use crate::session::Session;
+use jsonrpc_core::{Compatibility, MetaIoHandler};
+use network::ConsensusParams;
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Maximal number of simultaneous client connections
+const MAX_CONNECTIONS: usize = 100;
+/// Maximal size of single request line (in bytes)
+const MAX_REQUEST_SIZE: u64 = 1_000_000;
+
+pub struct Dependencies {
+	pub consensus: ConsensusParams,
+	pub local_sync_node: sync::LocalNodeRef,
+	pub storage: storage::SharedStore,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct ElectrumConfiguration {
+	pub enabled: bool,
+	pub interface: String,
+	pub port: u16,
+}
+
+impl ElectrumConfiguration {
+	pub fn with_port(port: u16) -> Self {
+		ElectrumConfiguration {
+			enabled: true,
+			interface: "127.0.0.1".into(),
+			port,
+		}
+	}
+}
+
+/// Running Electrum server. Connections are closed when server is closed.
+pub struct ElectrumServer {
+	local_addr: SocketAddr,
+	core: Arc<dyn ElectrumClientCoreApi>,
+	sessions: Sessions,
+	is_closed: Arc<AtomicBool>,
+	listener_thread: Option<thread::JoinHandle<()>>,
+}
+
+pub fn new_tcp(conf: ElectrumConfiguration, deps: Dependencies) -> Result<Option<ElectrumServer>, String> {
+	if !conf.enabled {
+		return Ok(None);
+	}
+
+	let url = format!("{}:{}", conf.interface, conf.port);
+	let addr = url
+		.parse()
+		.map_err(|_| format!("Invalid Electrum listen host/port given: {}", url))?;
+	let core = ElectrumClientCore::new(deps.storage, deps.local_sync_node, deps.consensus.max_block_size);
+	match ElectrumServer::start(&addr, Arc::new(core)) {
+		Ok(server) => Ok(Some(server)),
+		Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => Err(format!(
+			"Electrum address {} is already in use, make sure that another instance of a Bitcoin node is not running or change the address using the --electrum-port and --electrum-interface options.",
+			addr
+		)),
+		Err(err) => Err(format!("Electrum error: {:?}", err)),
+	}
+}
+
+impl ElectrumServer {
+	pub fn start<T: ElectrumClientCoreApi>(addr: &SocketAddr, core: Arc<T>) -> Result<Self, io::Error> {
+		let listener = TcpListener::bind(addr)?;
+		let local_addr = listener.local_addr()?;
+
+		let mut handler = MetaIoHandler::with_compatibility(Compatibility::Both);
+		handler.extend_with(ElectrumClient::new(core.clone()).to_delegate());
+		let handler = Arc::new(handler);
+
+		let sessions = Sessions::default();
+		let is_closed = Arc::new(AtomicBool::new(false));
+		let listener_thread = {
+			let sessions = sessions.clone();
+			let is_closed = is_closed.clone();
+			thread::Builder::new()
+				.name("Electrum listener thread".to_owned())
+				.spawn(move || ElectrumServer::listen(listener, handler, sessions, is_closed))?
+		};
+
+		info!(target: "electrum", "Electrum server is listening on {}", local_addr);
+
+		Ok(ElectrumServer {
+			local_addr,
+			core,
+			sessions,
+			is_closed,
+			listener_thread: Some(listener_thread),
+		})
+	}
+
+	/// Address the server is listening on
+	pub fn local_addr(&self) -> SocketAddr {
+		self.local_addr
+	}
+
+	/// Creates listener, that pushes notifications to the server clients
+	pub fn notifier(&self) -> ElectrumNotifier {
+		ElectrumNotifier::new(self.core.clone(), self.sessions.clone())
+	}
+
+	/// Stops accepting new connections and closes all existing connections
+	pub fn close(mut self) {
+		self.stop();
+	}
+
+	fn stop(&mut self) {
+		if let Some(listener_thread) = self.listener_thread.take() {
+			self.is_closed.store(true, Ordering::SeqCst);
+			// wake up listener thread
+			let _ = TcpStream::connect(self.local_addr);
+			listener_thread.join().expect("Clean shutdown.");
+			for session in self.sessions.lock().drain(..) {
+				session.close();
+			}
+		}
+	}
+
+	fn listen(listener: TcpListener, handler: Arc<MetaIoHandler<Session>>, sessions: Sessions, is_closed: Arc<AtomicBool>) {
+		for stream in listener.incoming() {
+			if is_closed.load(Ordering::SeqCst) {
+				break;
+			}
+
+			let stream = match stream {
+				Ok(stream) => stream,
+				Err(err) => {
+					warn!(target: "electrum", "Error accepting Electrum connection: {}", err);
+					continue;
+				}
+			};
+			if sessions.lock().len() >= MAX_CONNECTIONS {
+				warn!(target: "electrum", "Dropping Electrum connection: too many connections");
+				continue;
+			}
+			let session = match Session::with_tcp_stream(&stream) {
+				Ok(session) => session,
+				Err(err) => {
+					warn!(target: "electrum", "Error accepting Electrum connection: {}", err);
+					continue;
+				}
+			};
+
+			sessions.lock().push(session.clone());
+			let handler = handler.clone();
+			let sessions = sessions.clone();
+			let spawn_result = thread::Builder::new().name("Electrum connection thread".to_owned()).spawn(move || {
+				if let Err(err) = ElectrumServer::serve(stream, &handler, &session) {
+					trace!(target: "electrum", "Electrum connection closed: {}", err);
+				}
+				session.close();
+				sessions.lock().retain(|s| !s.is_same(&session));
+			});
+			if let Err(err) = spawn_result {
+				warn!(target: "electrum", "Error creating Electrum connection thread: {}", err);
+			}
+		}
+		trace!(target: "electrum", "Electrum listener thread stopped");
+	}
+
+	/// Reads newline-delimited requests from the connection and writes responses back.
+	/// Fails if request is larger than `MAX_REQUEST_SIZE`.
+	fn serve(stream: TcpStream, handler: &MetaIoHandler<Session>, session: &Session) -> Result<(), io::Error> {
+		let mut reader = BufReader::new(stream);
+		loop {
+			let mut request = Vec::new();
+			(&mut reader).take(MAX_REQUEST_SIZE).read_until(b'\n', &mut request)?;
+			if request.is_empty() {
+				return Ok(());
+			}
+			if request.len() as u64 == MAX_REQUEST_SIZE && !request.ends_with(b"\n") {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "Request is too large"));
+			}
+
+			let request = String::from_utf8(request).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+			if request.trim().is_empty() {
+				continue;
+			}
+
+			if let Some(response) = handler.handle_request_sync(&request, session.clone()) {
+				session.send(&response)?;
+			}
+		}
+	}
+}
+
+impl Drop for ElectrumServer {
+	fn drop(&mut self) {
+		self.stop();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::api::tests::SuccessElectrumClientCore;
+	use std::io::{Read, Write};
+
+	fn start() -> ElectrumServer {
+		ElectrumServer::start(&"127.0.0.1:0".parse().unwrap(), Arc::new(SuccessElectrumClientCore)).unwrap()
+	}
+
+	fn read_line(reader: &mut BufReader<TcpStream>) -> String {
+		let mut line = String::new();
+		reader.read_line(&mut line).unwrap();
+		line
+	}
+
+	#[test]
+	fn tcp_requests_and_notifications() {
+		let server = start();
+		let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+		let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+		stream
+			.write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"server.ping\",\"params\":[],\"id\":1}\n\n")
+			.unwrap();
+		assert_eq!(read_line(&mut reader), "{\"jsonrpc\":\"2.0\",\"result\":null,\"id\":1}\n");
+
+		stream
+			.write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"blockchain.headers.subscribe\",\"params\":[],\"id\":2}\n")
+			.unwrap();
+		assert_eq!(
+			read_line(&mut reader),
+			"{\"jsonrpc\":\"2.0\",\"result\":{\"height\":1,\"hex\":\"00\"},\"id\":2}\n"
+		);
+
+		let notifier = server.notifier();
+		sync::SyncListener::best_storage_block_inserted(&notifier, &Default::default());
+		assert_eq!(
+			read_line(&mut reader),
+			"{\"jsonrpc\":\"2.0\",\"method\":\"blockchain.headers.subscribe\",\"params\":[{\"height\":1,\"hex\":\"00\"}]}\n"
+		);
+	}
+
+	#[test]
+	fn close_drops_connections() {
+		let server = start();
+		let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+		stream
+			.write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"server.ping\",\"params\":[],\"id\":1}\n")
+			.unwrap();
+		let mut reader = BufReader::new(stream.try_clone().unwrap());
+		read_line(&mut reader);
+
+		server.close();
+		let mut buffer = Vec::new();
+		assert_eq!(reader.read_to_end(&mut buffer).unwrap(), 0);
+	}
+
+	#[test]
+	fn oversized_request_drops_connection() {
+		let server = start();
+		let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+		let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+		let request = vec![b' '; MAX_REQUEST_SIZE as usize + 1];
+		let _ = stream.write_all(&request);
+		let mut buffer = Vec::new();
+		assert_eq!(reader.read_to_end(&mut buffer).unwrap_or_default(), 0);
+	}
+
+	#[test]
+	fn connections_above_limit_are_dropped() {
+		let server = start();
+		let streams: Vec<_> = (0..MAX_CONNECTIONS)
+			.map(|_| TcpStream::connect(server.local_addr()).unwrap())
+			.collect();
+
+		let stream = TcpStream::connect(server.local_addr()).unwrap();
+		let mut reader = BufReader::new(stream);
+		let mut buffer = Vec::new();
+		assert_eq!(reader.read_to_end(&mut buffer).unwrap_or_default(), 0);
+
+		let mut stream = streams.last().unwrap();
+		stream
+			.write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"server.ping\",\"params\":[],\"id\":1}\n")
+			.unwrap();
+		let mut reader = BufReader::new(stream.try_clone().unwrap());
+		assert_eq!(read_line(&mut reader), "{\"jsonrpc\":\"2.0\",\"result\":null,\"id\":1}\n");
+	}
+}
@@ -0,0 +1,169 @@
+use crate::types::ScriptHash;
+use jsonrpc_core::{Metadata, Notification, Params, Value, Version};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Single client connection.
+///
+/// Passed to every request handler of the connection as request metadata and shared with the notifier,
+/// which pushes notifications to subscribed connections.
+#[derive(Clone)]
+pub struct Session {
+	inner: Arc<SessionData>,
+}
+
+struct SessionData {
+	/// Outgoing half of the connection. Both responses and notifications are written here
+	writer: Mutex<Box<dyn Write + Send>>,
+	/// Underlying socket (if any). Used to close the connection
+	socket: Option<TcpStream>,
+	/// True if client is subscribed to best header updates
+	headers_subscribed: AtomicBool,
+	/// Subscribed script hashes => last status, sent to the client
+	script_hashes: Mutex<HashMap<ScriptHash, Option<String>>>,
+}
+
+impl Metadata for Session {}
+
+impl Session {
+	pub fn new(writer: Box<dyn Write + Send>) -> Self {
+		Session::with_socket(writer, None)
+	}
+
+	/// Creates session, writing to the TCP socket
+	pub fn with_tcp_stream(stream: &TcpStream) -> Result<Self, io::Error> {
+		Ok(Session::with_socket(Box::new(stream.try_clone()?), Some(stream.try_clone()?)))
+	}
+
+	fn with_socket(writer: Box<dyn Write + Send>, socket: Option<TcpStream>) -> Self {
+		Session {
+			inner: Arc::new(SessionData {
+				writer: Mutex::new(writer),
+				socket,
+				headers_subscribed: AtomicBool::new(false),
+				script_hashes: Mutex::default(),
+			}),
+		}
+	}
+
+	/// True if both sessions are referring the same connection
+	pub fn is_same(&self, other: &Session) -> bool {
+		Arc::ptr_eq(&self.inner, &other.inner)
+	}
+
+	/// Closes underlying connection (if any)
+	pub fn close(&self) {
+		if let Some(ref socket) = self.inner.socket {
+			let _ = socket.shutdown(Shutdown::Both);
+		}
+	}
+
+	/// Sends single newline-terminated message to the client
+	pub fn send(&self, message: &str) -> Result<(), io::Error> {
+		let mut writer = self.inner.writer.lock();
+		writer.write_all(message.as_bytes())?;
+		writer.write_all(b"\n")?;
+		writer.flush()
+	}
+
+	/// Sends notification to the client
+	pub fn notify(&self, method: &str, params: Vec<Value>) -> Result<(), io::Error> {
+		let notification = Notification {
+			jsonrpc: Some(Version::V2),
+			method: method.into(),
+			params: Params::Array(params),
+		};
+		let message = serde_json::to_string(&notification).expect("notification serialization never fails");
+		self.send(&message)
+	}
+
+	pub fn subscribe_headers(&self) {
+		self.inner.headers_subscribed.store(true, Ordering::SeqCst);
+	}
+
+	pub fn is_subscribed_to_headers(&self) -> bool {
+		self.inner.headers_subscribed.load(Ordering::SeqCst)
+	}
+
+	pub fn subscribe_script_hash(&self, script_hash: ScriptHash, status: Option<String>) {
+		self.inner.script_hashes.lock().insert(script_hash, status);
+	}
+
+	/// Returns true if client has been subscribed to the script hash
+	pub fn unsubscribe_script_hash(&self, script_hash: &ScriptHash) -> bool {
+		self.inner.script_hashes.lock().remove(script_hash).is_some()
+	}
+
+	pub fn subscribed_script_hashes(&self) -> Vec<ScriptHash> {
+		self.inner.script_hashes.lock().keys().cloned().collect()
+	}
+
+	/// Remembers new status of subscribed script hash. Returns true if status has changed.
+	pub fn update_script_hash_status(&self, script_hash: &ScriptHash, status: &Option<String>) -> bool {
+		match self.inner.script_hashes.lock().get_mut(script_hash) {
+			Some(last_status) if last_status != status => {
+				*last_status = status.clone();
+				true
+			}
+			_ => false,
+		}
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	/// Writer that collects everything written to the session
+	#[derive(Clone, Default)]
+	pub struct SharedWriter(pub Arc<Mutex<Vec<u8>>>);
+
+	impl SharedWriter {
+		pub fn take_lines(&self) -> Vec<String> {
+			let data = ::std::mem::take(&mut *self.0.lock());
+			String::from_utf8(data).unwrap().lines().map(Into::into).collect()
+		}
+	}
+
+	impl Write for SharedWriter {
+		fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+			self.0.lock().write(buf)
+		}
+
+		fn flush(&mut self) -> Result<(), io::Error> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn session_notify() {
+		let writer = SharedWriter::default();
+		let session = Session::new(Box::new(writer.clone()));
+		session.notify("server.ping", vec![Value::Null]).unwrap();
+		assert_eq!(
+			writer.take_lines(),
+			vec![r#"{"jsonrpc":"2.0","method":"server.ping","params":[null]}"#.to_owned()]
+		);
+	}
+
+	#[test]
+	fn session_script_hash_status() {
+		let session = Session::new(Box::new(io::sink()));
+		let script_hash = ScriptHash::from_script(&[0x51]);
+		assert!(!session.update_script_hash_status(&script_hash, &Some("status".into())));
+
+		session.subscribe_script_hash(script_hash, None);
+		assert_eq!(session.subscribed_script_hashes(), vec![script_hash]);
+		assert!(!session.update_script_hash_status(&script_hash, &None));
+		assert!(session.update_script_hash_status(&script_hash, &Some("status".into())));
+		assert!(!session.update_script_hash_status(&script_hash, &Some("status".into())));
+
+		assert!(session.unsubscribe_script_hash(&script_hash));
+		assert!(!session.unsubscribe_script_hash(&script_hash));
+		assert!(session.subscribed_script_hashes().is_empty());
+	}
+}
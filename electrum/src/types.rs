@@ -0,0 +1,151 @@
+use bitcrypto::{FromHex, Hash, ToHex, SHA256, SHA256D};
+use serde::de::{Error as DeError, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Electrum script hash: `sha256(script_pubkey)`, hex-encoded in reversed byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScriptHash(pub SHA256);
+
+impl ScriptHash {
+	pub fn from_script(script_pubkey: &[u8]) -> Self {
+		ScriptHash(bitcrypto::sha256(script_pubkey))
+	}
+
+	pub fn to_electrum_hex(self) -> String {
+		let mut bytes = self.0.into_inner();
+		bytes.reverse();
+		bytes.to_hex()
+	}
+
+	pub fn from_electrum_hex(hex: &str) -> Result<Self, String> {
+		let mut bytes: Vec<u8> = FromHex::from_hex(hex).map_err(|err| format!("{:?}", err))?;
+		bytes.reverse();
+		SHA256::from_slice(&bytes).map(ScriptHash).map_err(|err| format!("{:?}", err))
+	}
+}
+
+impl Serialize for ScriptHash {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(&self.to_electrum_hex())
+	}
+}
+
+impl<'a> Deserialize<'a> for ScriptHash {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'a>,
+	{
+		struct ScriptHashVisitor;
+
+		impl<'b> Visitor<'b> for ScriptHashVisitor {
+			type Value = ScriptHash;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a hex-encoded script hash")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+			where
+				E: DeError,
+			{
+				ScriptHash::from_electrum_hex(value).map_err(E::custom)
+			}
+		}
+
+		deserializer.deserialize_str(ScriptHashVisitor)
+	}
+}
+
+/// Response of `blockchain.headers.subscribe` and payload of its notifications
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HeaderNotification {
+	/// Height of the best block
+	pub height: u32,
+	/// Serialized header of the best block
+	pub hex: String,
+}
+
+/// Single entry of `blockchain.scripthash.get_history` response
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HistoryItem {
+	/// Transaction hash
+	pub tx_hash: SHA256D,
+	/// Height of the block, containing transaction. 0 for memory pool transactions, spending only confirmed outputs,
+	/// -1 for other memory pool transactions
+	pub height: i64,
+	/// Transaction fee. Only for memory pool transactions
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<u64>,
+}
+
+/// Response of `blockchain.scripthash.get_balance`
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Balance {
+	/// Confirmed balance in satoshis
+	pub confirmed: u64,
+	/// Balance change in satoshis, introduced by memory pool transactions
+	pub unconfirmed: i64,
+}
+
+/// Single entry of `blockchain.scripthash.listunspent` response
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Unspent {
+	/// Hash of transaction, containing the output
+	pub tx_hash: SHA256D,
+	/// Index of the output
+	pub tx_pos: u32,
+	/// Height of the block, containing transaction. 0 for memory pool transactions
+	pub height: u32,
+	/// Value of the output in satoshis
+	pub value: u64,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn script_hash_electrum_hex() {
+		// https://electrumx.readthedocs.io/en/latest/protocol-basics.html#script-hashes
+		let script_pubkey: Vec<u8> = FromHex::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+		let script_hash = ScriptHash::from_script(&script_pubkey);
+		let hex = "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161";
+		assert_eq!(script_hash.to_electrum_hex(), hex);
+		assert_eq!(ScriptHash::from_electrum_hex(hex), Ok(script_hash));
+		assert_eq!(serde_json::to_string(&script_hash).unwrap(), format!(r#""{}""#, hex));
+		assert_eq!(serde_json::from_str::<ScriptHash>(&format!(r#""{}""#, hex)).unwrap(), script_hash);
+	}
+
+	#[test]
+	fn script_hash_invalid() {
+		assert!(ScriptHash::from_electrum_hex("zz").is_err());
+		assert!(ScriptHash::from_electrum_hex("0011").is_err());
+	}
+
+	#[test]
+	fn history_item_serialize() {
+		let tx_hash = SHA256D::from_inner([1; 32]);
+		assert_eq!(
+			serde_json::to_string(&HistoryItem {
+				tx_hash,
+				height: 10,
+				fee: None
+			})
+			.unwrap(),
+			r#"{"tx_hash":"0101010101010101010101010101010101010101010101010101010101010101","height":10}"#
+		);
+		assert_eq!(
+			serde_json::to_string(&HistoryItem {
+				tx_hash,
+				height: -1,
+				fee: Some(200)
+			})
+			.unwrap(),
+			r#"{"tx_hash":"0101010101010101010101010101010101010101010101010101010101010101","height":-1,"fee":200}"#
+		);
+	}
+}
@@ -3,13 +3,19 @@
 //! A Bitcoin address, or simply address, is an identifier of 26-35 alphanumeric characters, beginning with the number 1
 //! or 3, that represents a possible destination for a bitcoin payment.
 //!
+//! Segwit addresses are encoded with bech32 (version 0) or bech32m (version 1+) instead, eg: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4.
+//!
 //! https://en.bitcoin.it/wiki/Address
+//! https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
 
 use crate::network::Network;
 use crate::{AddressHash, DisplayLayout, Error};
 use base58::{FromBase58, ToBase58};
+use bech32::{FromBase32, ToBase32, Variant};
 use bitcrypto::Hash;
 use primitives::checksum::Checksum;
+use std::convert::TryInto;
 use std::fmt;
 use std::ops::Deref;
 use std::str::FromStr;
@@ -26,6 +32,74 @@ pub enum Type {
 	/// Newer P2SH type starting with the number 3, eg: 3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy.
 	/// https://bitcoin.org/en/glossary/p2sh-address
 	P2SH,
+	/// Pay to Witness PubKey Hash
+	/// Version 0 witness program of 20 bytes, eg: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#p2wpkh
+	P2WPKH,
+	/// Pay to Witness Script Hash
+	/// Version 0 witness program of 32 bytes, eg: bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#p2wsh
+	P2WSH,
+	/// Pay to Taproot
+	/// Version 1 witness program of 32 bytes, eg: bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki
+	P2TR,
+	/// Pay to Witness Program of unknown version
+	/// Version 1 to 16 witness program of 2 to 40 bytes, which is not defined yet (e.g. version 2, or version 1
+	/// program that is not 32 bytes). Such outputs are valid to pay to and are reserved for future soft forks.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+	WitnessUnknown(u8),
+}
+
+impl Type {
+	/// Witness version of segwit address types, `None` for base58 address types.
+	pub fn witness_version(&self) -> Option<u8> {
+		match *self {
+			Type::P2PKH | Type::P2SH => None,
+			Type::P2WPKH | Type::P2WSH => Some(0),
+			Type::P2TR => Some(1),
+			Type::WitnessUnknown(version) => Some(version),
+		}
+	}
+}
+
+/// Data the address commits to.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Payload {
+	/// 20 bytes public key hash or script hash of P2PKH, P2SH and P2WPKH addresses.
+	Hash(AddressHash),
+	/// 32 bytes witness program of P2WSH (script hash) and P2TR (output key) addresses.
+	WitnessProgram([u8; 32]),
+	/// 2 to 40 bytes witness program of unknown version.
+	UnknownWitnessProgram(Vec<u8>),
+}
+
+impl Payload {
+	/// Returns 20 bytes hash, if any.
+	pub fn hash(&self) -> Option<&AddressHash> {
+		match *self {
+			Payload::Hash(ref hash) => Some(hash),
+			Payload::WitnessProgram(_) | Payload::UnknownWitnessProgram(_) => None,
+		}
+	}
+}
+
+impl Deref for Payload {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		match *self {
+			Payload::Hash(ref hash) => &hash[..],
+			Payload::WitnessProgram(ref program) => program,
+			Payload::UnknownWitnessProgram(ref program) => program,
+		}
+	}
+}
+
+impl From<AddressHash> for Payload {
+	fn from(hash: AddressHash) -> Self {
+		Payload::Hash(hash)
+	}
 }
 
 /// `AddressHash` with network identifier and format type
@@ -35,11 +109,89 @@ pub struct Address {
 	pub kind: Type,
 	/// The network of the address.
 	pub network: Network,
-	/// Public key hash.
-	pub hash: AddressHash,
+	/// Public key hash, script hash or witness program.
+	pub payload: Payload,
+}
+
+impl Address {
+	/// Creates witness address from witness version and program.
+	pub fn from_witness_program(network: Network, version: u8, program: &[u8]) -> Result<Self, Error> {
+		let (kind, payload) = match (version, program.len()) {
+			(0, 20) => (
+				Type::P2WPKH,
+				Payload::Hash(AddressHash::from_slice(program).map_err(|_| Error::InvalidAddress)?),
+			),
+			(0, 32) => (
+				Type::P2WSH,
+				Payload::WitnessProgram(program.try_into().expect("program.len() == 32; qed")),
+			),
+			(1, 32) => (
+				Type::P2TR,
+				Payload::WitnessProgram(program.try_into().expect("program.len() == 32; qed")),
+			),
+			(1..=16, 2..=40) => (Type::WitnessUnknown(version), Payload::UnknownWitnessProgram(program.to_vec())),
+			_ => return Err(Error::InvalidAddress),
+		};
+
+		let address = Address { kind, network, payload };
+		if !address.is_valid() {
+			return Err(Error::InvalidAddress);
+		}
+
+		Ok(address)
+	}
+
+	/// Returns true if payload matches the address type.
+	pub fn is_valid(&self) -> bool {
+		match (self.kind, &self.payload) {
+			(Type::P2PKH, Payload::Hash(_)) | (Type::P2SH, Payload::Hash(_)) | (Type::P2WPKH, Payload::Hash(_)) => true,
+			(Type::P2WSH, Payload::WitnessProgram(_)) | (Type::P2TR, Payload::WitnessProgram(_)) => true,
+			(Type::WitnessUnknown(version), Payload::UnknownWitnessProgram(program)) => {
+				(1..=16).contains(&version) && (2..=40).contains(&program.len()) && !(version == 1 && program.len() == 32)
+			}
+			_ => false,
+		}
+	}
+
+	/// Returns bech32 human readable part of the network.
+	fn hrp(&self) -> &'static str {
+		match self.network {
+			Network::Mainnet => "bc",
+			Network::Testnet => "tb",
+			Network::Regtest => "bcrt",
+		}
+	}
+
+	fn to_bech32(&self, version: u8) -> String {
+		let variant = if version == 0 { Variant::Bech32 } else { Variant::Bech32m };
+		let mut data = vec![bech32::u5::try_from_u8(version).expect("witness version is lower than 17; qed")];
+		data.extend((&*self.payload).to_base32());
+		bech32::encode(self.hrp(), data, variant).expect("hrp is valid; qed")
+	}
+
+	fn from_bech32(s: &str) -> Result<Self, Error> {
+		let (hrp, data, variant) = bech32::decode(s).map_err(|_| Error::InvalidAddress)?;
+		let network = match hrp.as_str() {
+			"bc" => Network::Mainnet,
+			"tb" => Network::Testnet,
+			"bcrt" => Network::Regtest,
+			_ => return Err(Error::InvalidNetwork),
+		};
+
+		let (version, program) = data.split_first().ok_or(Error::InvalidAddress)?;
+		let version = version.to_u8();
+		match (version, variant) {
+			(0, Variant::Bech32) => (),
+			(1..=16, Variant::Bech32m) => (),
+			_ => return Err(Error::InvalidChecksum),
+		}
+
+		let program = Vec::<u8>::from_base32(program).map_err(|_| Error::InvalidAddress)?;
+		Address::from_witness_program(network, version, &program)
+	}
 }
 
-pub struct AddressDisplayLayout([u8; 25]);
+pub struct AddressDisplayLayout(Vec<u8>);
 
 impl Deref for AddressDisplayLayout {
 	type Target = [u8];
@@ -52,19 +204,28 @@ impl Deref for AddressDisplayLayout {
 impl DisplayLayout for Address {
 	type Target = AddressDisplayLayout;
 
+	/// Base58 layout of P2PKH and P2SH addresses. Witness addresses are laid out as version byte followed by
+	/// witness program, same as the data part of bech32 encoding.
 	fn layout(&self) -> Self::Target {
-		let mut result = [0u8; 25];
+		let mut result = Vec::with_capacity(25);
 
-		result[0] = match (self.network, self.kind) {
+		if let Some(version) = self.kind.witness_version() {
+			result.push(version);
+			result.extend_from_slice(&self.payload);
+			return AddressDisplayLayout(result);
+		}
+
+		let prefix = match (self.network, self.kind) {
 			(Network::Mainnet, Type::P2PKH) => 0,
-			(Network::Mainnet, Type::P2SH) => 5,
-			(Network::Testnet, Type::P2PKH) => 111,
-			(Network::Testnet, Type::P2SH) => 196,
+			(Network::Mainnet, _) => 5,
+			(Network::Testnet, Type::P2PKH) | (Network::Regtest, Type::P2PKH) => 111,
+			(Network::Testnet, _) | (Network::Regtest, _) => 196,
 		};
 
-		result[1..21].copy_from_slice(&*self.hash);
-		let cs = Checksum::generate(&result[0..21]);
-		result[21..25].copy_from_slice(&*cs);
+		result.push(prefix);
+		result.extend_from_slice(&self.payload);
+		let cs = Checksum::generate(&result);
+		result.extend_from_slice(&*cs);
 		AddressDisplayLayout(result)
 	}
 
@@ -91,7 +252,11 @@ impl DisplayLayout for Address {
 
 		let hash = AddressHash::from_slice(&data[1..21]).map_err(|_| Error::InvalidAddress)?;
 
-		let address = Address { kind, network, hash };
+		let address = Address {
+			kind,
+			network,
+			payload: Payload::Hash(hash),
+		};
 
 		Ok(address)
 	}
@@ -99,7 +264,10 @@ impl DisplayLayout for Address {
 
 impl fmt::Display for Address {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		self.layout().to_base58().fmt(f)
+		match self.kind.witness_version() {
+			Some(version) => self.to_bech32(version).fmt(f),
+			None => self.layout().to_base58().fmt(f),
+		}
 	}
 }
 
@@ -110,8 +278,11 @@ impl FromStr for Address {
 	where
 		Self: Sized,
 	{
-		let hex = s.from_base58().map_err(|_| Error::InvalidAddress)?;
-		Address::from_layout(&hex)
+		// bech32 charset overlaps with base58 one, so rely on the decoded length as well
+		match s.from_base58() {
+			Ok(ref hex) if hex.len() == 25 => Address::from_layout(hex),
+			_ => Address::from_bech32(s),
+		}
 	}
 }
 
@@ -123,9 +294,10 @@ impl From<&'static str> for Address {
 
 #[cfg(test)]
 mod tests {
-	use super::{Address, Type};
+	use super::{Address, Payload, Type};
 	use crate::network::Network;
-	use crate::AddressHash;
+	use crate::{AddressHash, Error};
+	use bech32::{ToBase32, Variant};
 	use bitcrypto::FromHex;
 
 	#[test]
@@ -133,7 +305,7 @@ mod tests {
 		let address = Address {
 			kind: Type::P2PKH,
 			network: Network::Mainnet,
-			hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap(),
+			payload: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap().into(),
 		};
 
 		assert_eq!("16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".to_owned(), address.to_string());
@@ -144,9 +316,129 @@ mod tests {
 		let address = Address {
 			kind: Type::P2PKH,
 			network: Network::Mainnet,
-			hash: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap(),
+			payload: AddressHash::from_hex("3f4aa1fedf1f54eeb03b759deadb36676b184911").unwrap().into(),
 		};
 
 		assert_eq!(address, "16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".into());
 	}
+
+	fn witness_program(hex: &'static str) -> [u8; 32] {
+		let mut program = [0u8; 32];
+		program.copy_from_slice(&Vec::<u8>::from_hex(hex).unwrap());
+		program
+	}
+
+	#[test]
+	fn test_witness_address_to_string() {
+		let p2wpkh = Address {
+			kind: Type::P2WPKH,
+			network: Network::Mainnet,
+			payload: AddressHash::from_hex("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap().into(),
+		};
+		assert_eq!("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_owned(), p2wpkh.to_string());
+
+		let p2wsh = Address {
+			kind: Type::P2WSH,
+			network: Network::Testnet,
+			payload: Payload::WitnessProgram(witness_program("1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262")),
+		};
+		assert_eq!(
+			"tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7".to_owned(),
+			p2wsh.to_string()
+		);
+
+		let p2tr = Address {
+			kind: Type::P2TR,
+			network: Network::Mainnet,
+			payload: Payload::WitnessProgram(witness_program("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")),
+		};
+		assert_eq!(
+			"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".to_owned(),
+			p2tr.to_string()
+		);
+	}
+
+	#[test]
+	fn test_witness_address_from_str() {
+		let p2wpkh: Address = "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4".into();
+		assert_eq!(p2wpkh.kind, Type::P2WPKH);
+		assert_eq!(p2wpkh.network, Network::Mainnet);
+		assert_eq!(
+			p2wpkh.payload,
+			AddressHash::from_hex("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap().into()
+		);
+
+		let p2wsh: Address = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7".into();
+		assert_eq!(p2wsh.kind, Type::P2WSH);
+		assert_eq!(p2wsh.network, Network::Testnet);
+
+		let p2tr: Address = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".into();
+		assert_eq!(p2tr.kind, Type::P2TR);
+		assert_eq!(
+			p2tr.payload,
+			Payload::WitnessProgram(witness_program("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"))
+		);
+	}
+
+	#[test]
+	fn test_regtest_witness_address_roundtrip() {
+		let address = Address {
+			kind: Type::P2WPKH,
+			network: Network::Regtest,
+			payload: AddressHash::from_hex("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap().into(),
+		};
+		let encoded = address.to_string();
+		assert!(encoded.starts_with("bcrt1q"));
+		assert_eq!(encoded.parse::<Address>(), Ok(address));
+	}
+
+	#[test]
+	fn test_witness_address_invalid() {
+		// wrong checksum
+		assert_eq!(
+			"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5".parse::<Address>(),
+			Err(Error::InvalidAddress)
+		);
+		// unknown hrp
+		assert_eq!(
+			"ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9".parse::<Address>(),
+			Err(Error::InvalidNetwork)
+		);
+		// taproot program encoded with bech32 instead of bech32m
+		let mut data = vec![bech32::u5::try_from_u8(1).unwrap()];
+		data.extend(witness_program("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").to_base32());
+		let encoded = bech32::encode("bc", data, Variant::Bech32).unwrap();
+		assert_eq!(encoded.parse::<Address>(), Err(Error::InvalidChecksum));
+		// version 0 program of invalid length
+		assert_eq!(
+			Address::from_witness_program(Network::Mainnet, 0, &[0; 21]),
+			Err(Error::InvalidAddress)
+		);
+		// program of invalid length
+		assert_eq!(
+			Address::from_witness_program(Network::Mainnet, 2, &[0; 41]),
+			Err(Error::InvalidAddress)
+		);
+	}
+
+	#[test]
+	fn test_unknown_witness_address() {
+		// BIP350 test vectors
+		let v16: Address = "BC1SW50QGDZ25J".into();
+		assert_eq!(v16.kind, Type::WitnessUnknown(16));
+		assert_eq!(v16.payload, Payload::UnknownWitnessProgram(vec![0x75, 0x1e]));
+		assert_eq!(v16.to_string(), "bc1sw50qgdz25j");
+
+		let v2: Address = "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs".into();
+		assert_eq!(v2.kind, Type::WitnessUnknown(2));
+		assert_eq!(
+			v2.payload,
+			Payload::UnknownWitnessProgram(Vec::<u8>::from_hex("751e76e8199196d454941c45d1b3a323").unwrap())
+		);
+		assert_eq!(v2.to_string(), "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs");
+
+		let v1: Address = "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y".into();
+		assert_eq!(v1.kind, Type::WitnessUnknown(1));
+		assert_eq!(v1.payload.len(), 40);
+	}
 }
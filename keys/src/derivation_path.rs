@@ -0,0 +1,190 @@
+//! BIP32 derivation paths.
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+
+use crate::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Child numbers starting from this value are hardened
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// Index of the child key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChildNumber {
+	/// Child, which public key could be derived from the parent public key. Index is below `HARDENED_OFFSET`
+	Normal(u32),
+	/// Child, which could only be derived from the parent private key. Index is below `HARDENED_OFFSET`
+	Hardened(u32),
+}
+
+impl ChildNumber {
+	pub fn normal(index: u32) -> Result<Self, Error> {
+		if index >= HARDENED_OFFSET {
+			return Err(Error::InvalidChildNumber);
+		}
+		Ok(ChildNumber::Normal(index))
+	}
+
+	pub fn hardened(index: u32) -> Result<Self, Error> {
+		if index >= HARDENED_OFFSET {
+			return Err(Error::InvalidChildNumber);
+		}
+		Ok(ChildNumber::Hardened(index))
+	}
+
+	pub fn is_hardened(&self) -> bool {
+		match *self {
+			ChildNumber::Normal(_) => false,
+			ChildNumber::Hardened(_) => true,
+		}
+	}
+}
+
+impl From<u32> for ChildNumber {
+	fn from(number: u32) -> Self {
+		if number >= HARDENED_OFFSET {
+			ChildNumber::Hardened(number - HARDENED_OFFSET)
+		} else {
+			ChildNumber::Normal(number)
+		}
+	}
+}
+
+impl From<ChildNumber> for u32 {
+	fn from(number: ChildNumber) -> Self {
+		match number {
+			ChildNumber::Normal(index) => index,
+			ChildNumber::Hardened(index) => index + HARDENED_OFFSET,
+		}
+	}
+}
+
+impl fmt::Display for ChildNumber {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ChildNumber::Normal(index) => index.fmt(f),
+			ChildNumber::Hardened(index) => write!(f, "{}'", index),
+		}
+	}
+}
+
+impl FromStr for ChildNumber {
+	type Err = Error;
+
+	/// Hardened child number is marked with either `'` or `h` suffix
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let (index, is_hardened) = match s.strip_suffix('\'').or_else(|| s.strip_suffix('h')) {
+			Some(index) => (index, true),
+			None => (s, false),
+		};
+		// u32::from_str accepts leading `+`
+		if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+			return Err(Error::InvalidChildNumber);
+		}
+		let index = index.parse().map_err(|_| Error::InvalidChildNumber)?;
+		if is_hardened {
+			ChildNumber::hardened(index)
+		} else {
+			ChildNumber::normal(index)
+		}
+	}
+}
+
+/// Path from the master key to the derived key
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<ChildNumber>);
+
+impl DerivationPath {
+	pub fn new(children: Vec<ChildNumber>) -> Self {
+		DerivationPath(children)
+	}
+
+	/// Path of the master key
+	pub fn master() -> Self {
+		DerivationPath(Vec::new())
+	}
+
+	pub fn children(&self) -> &[ChildNumber] {
+		&self.0
+	}
+
+	/// Path of the child key of the key at this path
+	pub fn child(&self, number: ChildNumber) -> Self {
+		let mut children = self.0.clone();
+		children.push(number);
+		DerivationPath(children)
+	}
+}
+
+impl From<Vec<ChildNumber>> for DerivationPath {
+	fn from(children: Vec<ChildNumber>) -> Self {
+		DerivationPath(children)
+	}
+}
+
+impl fmt::Display for DerivationPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		"m".fmt(f)?;
+		for child in &self.0 {
+			write!(f, "/{}", child)?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for DerivationPath {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let mut parts = s.split('/');
+		if parts.next() != Some("m") {
+			return Err(Error::InvalidDerivationPath);
+		}
+		parts
+			.map(|part| part.parse().map_err(|_| Error::InvalidDerivationPath))
+			.collect::<Result<Vec<_>, _>>()
+			.map(DerivationPath)
+	}
+}
+
+impl From<&'static str> for DerivationPath {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ChildNumber, DerivationPath, HARDENED_OFFSET};
+	use crate::Error;
+
+	#[test]
+	fn test_child_number_u32() {
+		assert_eq!(ChildNumber::from(1), ChildNumber::Normal(1));
+		assert_eq!(ChildNumber::from(HARDENED_OFFSET + 1), ChildNumber::Hardened(1));
+		assert_eq!(u32::from(ChildNumber::Hardened(1)), HARDENED_OFFSET + 1);
+		assert_eq!(ChildNumber::hardened(HARDENED_OFFSET), Err(Error::InvalidChildNumber));
+	}
+
+	#[test]
+	fn test_derivation_path_from_str() {
+		assert_eq!("m".parse::<DerivationPath>().unwrap(), DerivationPath::master());
+		assert_eq!(
+			"m/44'/0h/2".parse::<DerivationPath>().unwrap(),
+			DerivationPath::new(vec![ChildNumber::Hardened(44), ChildNumber::Hardened(0), ChildNumber::Normal(2)])
+		);
+		assert_eq!("m/2147483648".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("m/+1".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("m/1/".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("44'/0'".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+	}
+
+	#[test]
+	fn test_derivation_path_to_string() {
+		let path = DerivationPath::master()
+			.child(ChildNumber::Hardened(84))
+			.child(ChildNumber::Normal(7));
+		assert_eq!(path.to_string(), "m/84'/7");
+		assert_eq!(DerivationPath::from("m/84h/7"), path);
+	}
+}
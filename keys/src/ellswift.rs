@@ -0,0 +1,470 @@
+//! ElligatorSwift encoding of public keys, which makes them indistinguishable from uniformly random bytes.
+//! https://github.com/bitcoin/bips/blob/master/bip-0324.mediawiki#ellswift-encoding
+
+use crate::{Error, Secret, SECP256K1};
+use bitcrypto::ToHex;
+use secp256k1::key;
+use secp256k1::rand::rngs::StdRng;
+use secp256k1::rand::{FromEntropy, RngCore};
+use std::{fmt, ops};
+
+/// Field size `p = 2^256 - 2^32 - 977` as little-endian limbs
+const P: [u64; 4] = [0xffff_fffe_ffff_fc2f, u64::MAX, u64::MAX, u64::MAX];
+/// `2^256 mod p`
+const R: u64 = 0x1_0000_03d1;
+/// `p - 2`, used to compute inverses
+const P_MINUS_2: [u64; 4] = [0xffff_fffe_ffff_fc2d, u64::MAX, u64::MAX, u64::MAX];
+/// `(p + 1) / 4`, used to compute square roots
+const P_PLUS_1_DIV_4: [u64; 4] = [0xffff_ffff_bfff_ff0c, u64::MAX, u64::MAX, 0x3fff_ffff_ffff_ffff];
+/// Square root of -3
+const MINUS_3_SQRT: FieldElement = FieldElement([
+	0x7d8d_27ae_1cd5_f852,
+	0xc61f_6d15_da14_ecd4,
+	0x2337_70c2_a797_962c,
+	0x0a2d_2ba9_3507_f1df,
+]);
+
+/// Element of the secp256k1 base field, stored as reduced little-endian limbs
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FieldElement([u64; 4]);
+
+impl FieldElement {
+	fn from_u64(value: u64) -> Self {
+		FieldElement([value, 0, 0, 0])
+	}
+
+	/// Interprets big-endian bytes as an integer, reduced modulo p
+	fn from_bytes(bytes: &[u8]) -> Self {
+		let mut limbs = [0u64; 4];
+		for (i, limb) in limbs.iter_mut().enumerate() {
+			let mut be = [0u8; 8];
+			be.copy_from_slice(&bytes[24 - i * 8..32 - i * 8]);
+			*limb = u64::from_be_bytes(be);
+		}
+		FieldElement(limbs).reduce(false)
+	}
+
+	fn to_bytes(self) -> [u8; 32] {
+		let mut bytes = [0u8; 32];
+		for (i, limb) in self.0.iter().enumerate() {
+			bytes[24 - i * 8..32 - i * 8].copy_from_slice(&limb.to_be_bytes());
+		}
+		bytes
+	}
+
+	fn is_zero(&self) -> bool {
+		self.0 == [0; 4]
+	}
+
+	/// Subtracts p if the value (with an optional 2^256 overflow bit) is not smaller than p
+	fn reduce(self, overflow: bool) -> Self {
+		let less_than_p = self
+			.0
+			.iter()
+			.rev()
+			.zip(P.iter().rev())
+			.find(|(a, b)| a != b)
+			.is_some_and(|(a, b)| a < b);
+		if less_than_p && !overflow {
+			return self;
+		}
+		self.sub_unreduced(FieldElement(P))
+	}
+
+	fn add(self, other: Self) -> Self {
+		let mut result = [0u64; 4];
+		let mut carry = false;
+		for (result, (a, b)) in result.iter_mut().zip(self.0.iter().zip(other.0.iter())) {
+			let (value, carry1) = a.overflowing_add(*b);
+			let (value, carry2) = value.overflowing_add(carry as u64);
+			*result = value;
+			carry = carry1 || carry2;
+		}
+		FieldElement(result).reduce(carry)
+	}
+
+	fn neg(self) -> Self {
+		if self.is_zero() {
+			return self;
+		}
+		FieldElement(P).sub_unreduced(self)
+	}
+
+	/// Subtracts limbs, wrapping around 2^256
+	fn sub_unreduced(self, other: Self) -> Self {
+		let mut result = [0u64; 4];
+		let mut borrow = false;
+		for (result, (a, b)) in result.iter_mut().zip(self.0.iter().zip(other.0.iter())) {
+			let (value, borrow1) = a.overflowing_sub(*b);
+			let (value, borrow2) = value.overflowing_sub(borrow as u64);
+			*result = value;
+			borrow = borrow1 || borrow2;
+		}
+		FieldElement(result)
+	}
+
+	fn sub(self, other: Self) -> Self {
+		self.add(other.neg())
+	}
+
+	fn mul(self, other: Self) -> Self {
+		let mut product = [0u64; 8];
+		for i in 0..4 {
+			let mut carry = 0u128;
+			for j in 0..4 {
+				let value = product[i + j] as u128 + self.0[i] as u128 * other.0[j] as u128 + carry;
+				product[i + j] = value as u64;
+				carry = value >> 64;
+			}
+			product[i + 4] = carry as u64;
+		}
+
+		// fold upper half using 2^256 = R (mod p)
+		let mut result = [0u64; 4];
+		let mut carry = 0u128;
+		for i in 0..4 {
+			let value = product[i] as u128 + product[i + 4] as u128 * R as u128 + carry;
+			result[i] = value as u64;
+			carry = value >> 64;
+		}
+		let mut carry = carry * R as u128;
+		for limb in result.iter_mut() {
+			let value = *limb as u128 + carry;
+			*limb = value as u64;
+			carry = value >> 64;
+		}
+		// the last carry may only appear when the lower limbs are small, so adding R can't overflow again
+		if carry != 0 {
+			let mut carry = R as u128;
+			for limb in result.iter_mut() {
+				let value = *limb as u128 + carry;
+				*limb = value as u64;
+				carry = value >> 64;
+			}
+		}
+		FieldElement(result).reduce(false)
+	}
+
+	fn square(self) -> Self {
+		self.mul(self)
+	}
+
+	fn pow(self, exponent: &[u64; 4]) -> Self {
+		let mut result = FieldElement::from_u64(1);
+		for limb in exponent.iter().rev() {
+			for bit in (0..64).rev() {
+				result = result.square();
+				if limb & (1 << bit) != 0 {
+					result = result.mul(self);
+				}
+			}
+		}
+		result
+	}
+
+	fn inverse(self) -> Self {
+		self.pow(&P_MINUS_2)
+	}
+
+	fn div(self, other: Self) -> Self {
+		self.mul(other.inverse())
+	}
+
+	fn sqrt(self) -> Option<Self> {
+		let root = self.pow(&P_PLUS_1_DIV_4);
+		if root.square() == self {
+			Some(root)
+		} else {
+			None
+		}
+	}
+
+	/// `x^3 + 7`
+	fn curve_rhs(self) -> Self {
+		self.square().mul(self).add(FieldElement::from_u64(7))
+	}
+
+	/// Checks if there is a point on the curve with given X coordinate
+	fn is_valid_x(self) -> bool {
+		self.curve_rhs().sqrt().is_some()
+	}
+}
+
+/// Maps field elements `(u, t)` to X coordinate of the point on the curve
+fn xswiftec(u: FieldElement, t: FieldElement) -> FieldElement {
+	let one = FieldElement::from_u64(1);
+	let two = FieldElement::from_u64(2);
+	let u = if u.is_zero() { one } else { u };
+	let mut t = if t.is_zero() { one } else { t };
+	if u.curve_rhs().add(t.square()).is_zero() {
+		t = t.add(t);
+	}
+
+	let x = u.curve_rhs().sub(t.square()).div(t.add(t));
+	let y = x.add(t).div(MINUS_3_SQRT.mul(u));
+	let x_div_y = x.div(y);
+	let four_y_square = y.square().add(y.square()).add(y.square().add(y.square()));
+	let candidates = [u.add(four_y_square), x_div_y.neg().sub(u).div(two), x_div_y.sub(u).div(two)];
+	*candidates
+		.iter()
+		.find(|x| x.is_valid_x())
+		.expect("one of the candidates is always on the curve")
+}
+
+/// Finds `t` such that `xswiftec(u, t) = x`, if it exists for the given `u` and case (0..8)
+fn xswiftec_inv(x: FieldElement, u: FieldElement, case: u8) -> Option<FieldElement> {
+	let two = FieldElement::from_u64(2);
+	let (v, s) = if case & 2 == 0 {
+		if x.neg().sub(u).is_valid_x() {
+			return None;
+		}
+		let v = if case & 1 == 0 { x } else { x.neg().sub(u) };
+		let s = u.curve_rhs().neg().div(u.square().add(u.mul(v)).add(v.square()));
+		(v, s)
+	} else {
+		let s = x.sub(u);
+		if s.is_zero() {
+			return None;
+		}
+		let four = FieldElement::from_u64(4);
+		let three = FieldElement::from_u64(3);
+		let r = s.neg().mul(four.mul(u.curve_rhs()).add(three.mul(s).mul(u.square()))).sqrt()?;
+		if case & 1 != 0 && r.is_zero() {
+			return None;
+		}
+		let r_div_s = if case & 1 == 0 { r.div(s) } else { r.div(s).neg() };
+		(u.neg().add(r_div_s).div(two), s)
+	};
+
+	let w = s.sqrt()?;
+	let w = if case & 4 == 0 { w } else { w.neg() };
+	Some(w.mul(u.mul(MINUS_3_SQRT.sub(FieldElement::from_u64(1))).div(two).sub(v)))
+}
+
+/// 64 bytes long ElligatorSwift encoding of the public key
+#[derive(Clone, Copy, PartialEq)]
+pub struct EllSwiftPublic([u8; 64]);
+
+impl EllSwiftPublic {
+	/// Every 64 bytes long slice is a valid encoding
+	pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+		if data.len() != 64 {
+			return Err(Error::InvalidPublic);
+		}
+		let mut bytes = [0u8; 64];
+		bytes.copy_from_slice(data);
+		Ok(EllSwiftPublic(bytes))
+	}
+
+	/// Encodes X coordinate of the point as random-looking bytes
+	fn encode(x: FieldElement, rng: &mut StdRng) -> Self {
+		loop {
+			let mut u = [0u8; 32];
+			rng.fill_bytes(&mut u);
+			let u = FieldElement::from_bytes(&u);
+			if u.is_zero() {
+				continue;
+			}
+			let case = (rng.next_u32() & 7) as u8;
+			if let Some(t) = xswiftec_inv(x, u, case) {
+				let mut bytes = [0u8; 64];
+				bytes[..32].copy_from_slice(&u.to_bytes());
+				bytes[32..].copy_from_slice(&t.to_bytes());
+				return EllSwiftPublic(bytes);
+			}
+		}
+	}
+
+	/// Decodes X coordinate of the encoded point
+	pub fn x(&self) -> [u8; 32] {
+		xswiftec(FieldElement::from_bytes(&self.0[..32]), FieldElement::from_bytes(&self.0[32..])).to_bytes()
+	}
+}
+
+impl ops::Deref for EllSwiftPublic {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl fmt::Debug for EllSwiftPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "ellswift: {}", self.0.to_hex())
+	}
+}
+
+/// Secret key together with ElligatorSwift encoding of its public key
+pub struct EllSwiftKeyPair {
+	secret: Secret,
+	public: EllSwiftPublic,
+}
+
+impl EllSwiftKeyPair {
+	/// Generates random key pair
+	pub fn generate() -> Self {
+		let mut rng = StdRng::from_entropy();
+		let secret = key::SecretKey::new(&mut rng);
+		let public = key::PublicKey::from_secret_key(&SECP256K1, &secret);
+		let x = FieldElement::from_bytes(&public.serialize()[1..]);
+		let mut bytes = [0u8; 32];
+		bytes.copy_from_slice(&secret[..]);
+		EllSwiftKeyPair {
+			secret: bytes,
+			public: EllSwiftPublic::encode(x, &mut rng),
+		}
+	}
+
+	/// Creates key pair from the secret and the encoding of its public key
+	pub fn from_parts(secret: &[u8], public: EllSwiftPublic) -> Result<Self, Error> {
+		let secret = key::SecretKey::from_slice(secret)?;
+		let point = key::PublicKey::from_secret_key(&SECP256K1, &secret);
+		if point.serialize()[1..] != public.x() {
+			return Err(Error::InvalidPublic);
+		}
+		let mut bytes = [0u8; 32];
+		bytes.copy_from_slice(&secret[..]);
+		Ok(EllSwiftKeyPair { secret: bytes, public })
+	}
+
+	pub fn public(&self) -> &EllSwiftPublic {
+		&self.public
+	}
+
+	/// Computes X coordinate of the ECDH point, shared with the owner of the other key
+	pub fn ecdh_x(&self, other: &EllSwiftPublic) -> Result<[u8; 32], Error> {
+		let mut point = [2u8; 33];
+		point[1..].copy_from_slice(&other.x());
+		let mut point = key::PublicKey::from_slice(&point)?;
+		point.mul_assign(&SECP256K1, &self.secret)?;
+		let mut x = [0u8; 32];
+		x.copy_from_slice(&point.serialize()[1..]);
+		Ok(x)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{xswiftec, xswiftec_inv, EllSwiftKeyPair, EllSwiftPublic, FieldElement};
+	use bitcrypto::{FromHex, ToHex};
+
+	#[test]
+	fn test_field_arithmetic() {
+		let a = FieldElement::from_bytes(&[0xff; 32]);
+		assert_eq!(
+			a.to_bytes()[..].to_hex(),
+			"00000000000000000000000000000000000000000000000000000001000003d0"
+		);
+		let minus_one = FieldElement::from_u64(1).neg();
+		assert_eq!(minus_one.mul(minus_one), FieldElement::from_u64(1));
+		assert_eq!(minus_one.add(FieldElement::from_u64(1)), FieldElement::from_u64(0));
+		assert_eq!(a.mul(a.inverse()), FieldElement::from_u64(1));
+		assert_eq!(
+			FieldElement::from_u64(4).sqrt().map(|r| r.square()),
+			Some(FieldElement::from_u64(4))
+		);
+		assert_eq!(super::MINUS_3_SQRT.square(), FieldElement::from_u64(3).neg());
+	}
+
+	/// Rows from https://github.com/bitcoin/bips/blob/master/bip-0324/ellswift_decode_test_vectors.csv
+	const DECODE_VECTORS: [(&str, &str); 3] = [
+		(
+			"00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+			"edd1fd3e327ce90cc7a3542614289aee9682003e9cf7dcc9cf2ca9743be5aa0c",
+		),
+		(
+			"000000000000000000000000000000000000000000000000000000000000000001d3475bf7655b0fb2d852921035b2ef607f49069b97454e6795251062741771",
+			"b5da00b73cd6560520e7c364086e7cd23a34bf60d0e707be9fc34d4cd5fdfa2c",
+		),
+		(
+			"000000000000000000000000000000000000000000000000000000000000000082277c4a71f9d22e66ece523f8fa08741a7c0912c66a69ce68514bfd3515b49f",
+			"f482f2e241753ad0fb89150d8491dc1e34ff0b8acfbb442cfe999e2e5e6fd1d2",
+		),
+	];
+
+	/// Rows from https://github.com/bitcoin/bips/blob/master/bip-0324/xswiftec_inv_test_vectors.csv:
+	/// `u`, `x` and the expected `t` for each of 8 cases, empty if the case has no solution.
+	const INVERSE_VECTORS: [(&str, &str, [&str; 8]); 2] = [
+		(
+			"05ff6bdad900fc3261bc7fe34e2fb0f569f06e091ae437d3a52e9da0cbfb9590",
+			"80cdf63774ec7022c89a5a8558e373a279170285e0ab27412dbce510bdfe23fc",
+			[
+				"",
+				"",
+				"45654798ece071ba79286d04f7f3eb1c3f1d17dd883610f2ad2efd82a287466b",
+				"0aeaa886f6b76c7158452418cbf5033adc5747e9e9b5d3b2303db96936528557",
+				"",
+				"",
+				"ba9ab867131f8e4586d792fb080c14e3c0e2e82277c9ef0d52d1027c5d78b5c4",
+				"f51557790948938ea7badbe7340afcc523a8b816164a2c4dcfc24695c9ad76d8",
+			],
+		),
+		(
+			"2323a1d079b0fd72fc8bb62ec34230a815cb0596c2bfac998bd6b84260f5dc26",
+			"239342dfb675500a34a196310b8d87d54f49dcac9da50c1743ceab41a7b249ff",
+			[
+				"f63580b8aa49c4846de56e39e1b3e73f171e881eba8c66f614e67e5c975dfc07",
+				"b6307b332e699f1cf77841d90af25365404deb7fed5edb3090db49e642a156b6",
+				"",
+				"",
+				"09ca7f4755b63b7b921a91c61e4c18c0e8e177e145739909eb1981a268a20028",
+				"49cf84ccd19660e30887be26f50dac9abfb2148012a124cf6f24b618bd5ea579",
+				"",
+				"",
+			],
+		),
+	];
+
+	#[test]
+	fn test_ellswift_decode() {
+		for (encoded, x) in DECODE_VECTORS.iter() {
+			let encoded: Vec<u8> = FromHex::from_hex(encoded).unwrap();
+			let public = EllSwiftPublic::from_slice(&encoded).unwrap();
+			assert_eq!(public.x()[..].to_hex(), *x);
+		}
+	}
+
+	#[test]
+	fn test_ellswift_inverse_vectors() {
+		let field = |hex: &str| FieldElement::from_bytes(&Vec::<u8>::from_hex(hex).unwrap());
+		for (u, x, expected) in INVERSE_VECTORS.iter() {
+			let (u, x) = (field(u), field(x));
+			for (case, t) in expected.iter().enumerate() {
+				let result = xswiftec_inv(x, u, case as u8);
+				assert_eq!(
+					result.map(|t| t.to_bytes()[..].to_hex()),
+					if t.is_empty() { None } else { Some(t.to_string()) }
+				);
+				if let Some(t) = result {
+					assert_eq!(xswiftec(u, t), x);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_ellswift_inverse() {
+		let x = FieldElement::from_bytes(&Vec::<u8>::from_hex("edd1fd3e327ce90cc7a3542614289aee9682003e9cf7dcc9cf2ca9743be5aa0c").unwrap());
+		let mut found = 0;
+		for u in 1..20 {
+			let u = FieldElement::from_u64(u);
+			for case in 0..8 {
+				if let Some(t) = xswiftec_inv(x, u, case) {
+					assert_eq!(xswiftec(u, t), x);
+					found += 1;
+				}
+			}
+		}
+		assert!(found > 0);
+	}
+
+	#[test]
+	fn test_ellswift_ecdh() {
+		let alice = EllSwiftKeyPair::generate();
+		let bob = EllSwiftKeyPair::generate();
+		assert_ne!(alice.public(), bob.public());
+		assert_eq!(alice.ecdh_x(bob.public()).unwrap(), bob.ecdh_x(alice.public()).unwrap());
+		assert_eq!(EllSwiftPublic::from_slice(&[0u8; 63]), Err(crate::Error::InvalidPublic));
+	}
+}
@@ -0,0 +1,566 @@
+//! BIP32 hierarchical deterministic keys.
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+//!
+//! Besides standard `xprv`/`xpub` (`tprv`/`tpub` on test networks) versions, SLIP-132 versions of
+//! single key segwit accounts are supported.
+//! https://github.com/satoshilabs/slips/blob/master/slip-0132.md
+
+use crate::derivation_path::{ChildNumber, DerivationPath};
+use crate::network::Network;
+use crate::{AddressHash, DisplayLayout, Error, Private, Public, Secret, SECP256K1};
+use base58::{FromBase58, ToBase58};
+use bitcrypto::{dhash160, hmac_sha512};
+use primitives::checksum::Checksum;
+use secp256k1::key;
+use std::fmt;
+use std::str::FromStr;
+
+/// First 4 bytes of the key identifier
+pub type Fingerprint = [u8; 4];
+/// 32 bytes long chain code
+pub type ChainCode = [u8; 32];
+
+/// Length of the serialized key without checksum
+const EXTENDED_KEY_LEN: usize = 78;
+
+/// Type of scripts, encoded in the version bytes of the serialized key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtendedKeyFormat {
+	/// `xprv`/`xpub` (`tprv`/`tpub`): P2PKH or any other scripts
+	Standard,
+	/// `yprv`/`ypub` (`uprv`/`upub`): P2WPKH nested in P2SH
+	P2shP2wpkh,
+	/// `zprv`/`zpub` (`vprv`/`vpub`): P2WPKH
+	P2wpkh,
+}
+
+const VERSIONS: [(Network, ExtendedKeyFormat, u32, u32); 6] = [
+	(Network::Mainnet, ExtendedKeyFormat::Standard, 0x0488_ade4, 0x0488_b21e),
+	(Network::Mainnet, ExtendedKeyFormat::P2shP2wpkh, 0x049d_7878, 0x049d_7cb2),
+	(Network::Mainnet, ExtendedKeyFormat::P2wpkh, 0x04b2_430c, 0x04b2_4746),
+	(Network::Testnet, ExtendedKeyFormat::Standard, 0x0435_8394, 0x0435_87cf),
+	(Network::Testnet, ExtendedKeyFormat::P2shP2wpkh, 0x044a_4e28, 0x044a_5262),
+	(Network::Testnet, ExtendedKeyFormat::P2wpkh, 0x045f_18bc, 0x045f_1cf6),
+];
+
+/// Returns (private, public) version bytes
+fn versions(network: Network, format: ExtendedKeyFormat) -> (u32, u32) {
+	// regtest keys are serialized with testnet versions
+	let network = match network {
+		Network::Regtest => Network::Testnet,
+		network => network,
+	};
+	VERSIONS
+		.iter()
+		.find(|v| v.0 == network && v.1 == format)
+		.map(|v| (v.2, v.3))
+		.expect("all network and format combinations are listed")
+}
+
+/// Returns network, format and true if version is private key version
+fn parse_version(version: u32) -> Result<(Network, ExtendedKeyFormat, bool), Error> {
+	VERSIONS
+		.iter()
+		.find_map(|v| match version {
+			_ if version == v.2 => Some((v.0, v.1, true)),
+			_ if version == v.3 => Some((v.0, v.1, false)),
+			_ => None,
+		})
+		.ok_or(Error::InvalidExtendedKey)
+}
+
+/// Key, chain code and position in the keys tree, shared by private and public extended keys
+#[derive(Debug, Clone, PartialEq)]
+struct Header {
+	network: Network,
+	format: ExtendedKeyFormat,
+	depth: u8,
+	parent_fingerprint: Fingerprint,
+	child_number: ChildNumber,
+	chain_code: ChainCode,
+}
+
+fn serialize(header: &Header, is_private: bool, key: &[u8; 33]) -> Vec<u8> {
+	let (private_version, public_version) = versions(header.network, header.format);
+	let version = if is_private { private_version } else { public_version };
+	let mut result = Vec::with_capacity(EXTENDED_KEY_LEN + 4);
+	result.extend_from_slice(&version.to_be_bytes());
+	result.push(header.depth);
+	result.extend_from_slice(&header.parent_fingerprint);
+	result.extend_from_slice(&u32::from(header.child_number).to_be_bytes());
+	result.extend_from_slice(&header.chain_code);
+	result.extend_from_slice(key);
+	let cs = Checksum::generate(&result);
+	result.extend_from_slice(&*cs);
+	result
+}
+
+fn deserialize(data: &[u8]) -> Result<(Header, bool, [u8; 33]), Error> {
+	if data.len() != EXTENDED_KEY_LEN + 4 {
+		return Err(Error::InvalidExtendedKey);
+	}
+	let cs = Checksum::generate(&data[0..EXTENDED_KEY_LEN]);
+	if data[EXTENDED_KEY_LEN..] != *cs {
+		return Err(Error::InvalidChecksum);
+	}
+
+	let mut u32_bytes = [0u8; 4];
+	u32_bytes.copy_from_slice(&data[0..4]);
+	let (network, format, is_private) = parse_version(u32::from_be_bytes(u32_bytes))?;
+	let depth = data[4];
+	let mut parent_fingerprint = Fingerprint::default();
+	parent_fingerprint.copy_from_slice(&data[5..9]);
+	u32_bytes.copy_from_slice(&data[9..13]);
+	let child_number = ChildNumber::from(u32::from_be_bytes(u32_bytes));
+	// master key has neither parent nor index
+	if depth == 0 && (parent_fingerprint != Fingerprint::default() || u32::from(child_number) != 0) {
+		return Err(Error::InvalidExtendedKey);
+	}
+	let mut chain_code = ChainCode::default();
+	chain_code.copy_from_slice(&data[13..45]);
+	let mut key = [0u8; 33];
+	key.copy_from_slice(&data[45..78]);
+	if is_private && key[0] != 0 {
+		return Err(Error::InvalidExtendedKey);
+	}
+
+	let header = Header {
+		network,
+		format,
+		depth,
+		parent_fingerprint,
+		child_number,
+		chain_code,
+	};
+	Ok((header, is_private, key))
+}
+
+/// Splits HMAC-SHA512 output into key tweak and chain code
+fn split_hmac(hmac: [u8; 64]) -> ([u8; 32], ChainCode) {
+	let mut tweak = [0u8; 32];
+	let mut chain_code = ChainCode::default();
+	tweak.copy_from_slice(&hmac[0..32]);
+	chain_code.copy_from_slice(&hmac[32..64]);
+	(tweak, chain_code)
+}
+
+fn child_depth(depth: u8) -> Result<u8, Error> {
+	depth.checked_add(1).ok_or(Error::InvalidChildNumber)
+}
+
+/// Extended private key
+#[derive(Clone, PartialEq)]
+pub struct ExtendedPrivate {
+	header: Header,
+	secret: Secret,
+}
+
+impl ExtendedPrivate {
+	/// Creates master key from the seed (16 to 64 bytes long)
+	pub fn from_seed(network: Network, seed: &[u8]) -> Result<Self, Error> {
+		if seed.len() < 16 || seed.len() > 64 {
+			return Err(Error::InvalidSecret);
+		}
+		let (secret, chain_code) = split_hmac(hmac_sha512(b"Bitcoin seed", seed));
+		// check that secret is in [1; n) range
+		key::SecretKey::from_slice(&secret)?;
+
+		Ok(ExtendedPrivate {
+			header: Header {
+				network,
+				format: ExtendedKeyFormat::Standard,
+				depth: 0,
+				parent_fingerprint: Fingerprint::default(),
+				child_number: ChildNumber::Normal(0),
+				chain_code,
+			},
+			secret,
+		})
+	}
+
+	/// Returns the same key, serialized with the version of the given format
+	pub fn with_format(mut self, format: ExtendedKeyFormat) -> Self {
+		self.header.format = format;
+		self
+	}
+
+	pub fn network(&self) -> Network {
+		self.header.network
+	}
+
+	pub fn format(&self) -> ExtendedKeyFormat {
+		self.header.format
+	}
+
+	/// Number of derivations from the master key
+	pub fn depth(&self) -> u8 {
+		self.header.depth
+	}
+
+	pub fn parent_fingerprint(&self) -> Fingerprint {
+		self.header.parent_fingerprint
+	}
+
+	pub fn child_number(&self) -> ChildNumber {
+		self.header.child_number
+	}
+
+	pub fn chain_code(&self) -> &ChainCode {
+		&self.header.chain_code
+	}
+
+	/// Private key with compressed public key
+	pub fn private(&self) -> Private {
+		Private {
+			network: self.header.network,
+			secret: self.secret,
+			compressed: true,
+		}
+	}
+
+	pub fn extended_public(&self) -> ExtendedPublic {
+		let context = &SECP256K1;
+		let secret = key::SecretKey::from_slice(&self.secret).expect("secret is checked on construction");
+		ExtendedPublic {
+			header: self.header.clone(),
+			public: key::PublicKey::from_secret_key(context, &secret),
+		}
+	}
+
+	/// Hash160 of the public key
+	pub fn identifier(&self) -> AddressHash {
+		self.extended_public().identifier()
+	}
+
+	pub fn fingerprint(&self) -> Fingerprint {
+		self.extended_public().fingerprint()
+	}
+
+	pub fn derive_child(&self, number: ChildNumber) -> Result<Self, Error> {
+		let mut data = Vec::with_capacity(37);
+		if number.is_hardened() {
+			data.push(0);
+			data.extend_from_slice(&self.secret);
+		} else {
+			data.extend_from_slice(&self.extended_public().public.serialize());
+		}
+		data.extend_from_slice(&u32::from(number).to_be_bytes());
+
+		let (tweak, chain_code) = split_hmac(hmac_sha512(&self.header.chain_code, &data));
+		let mut secret = key::SecretKey::from_slice(&self.secret)?;
+		// fails if tweak is not below curve order or if resulting key is zero
+		secret.add_assign(&tweak)?;
+		let mut child_secret = Secret::default();
+		child_secret.copy_from_slice(&secret[..]);
+
+		Ok(ExtendedPrivate {
+			header: Header {
+				network: self.header.network,
+				format: self.header.format,
+				depth: child_depth(self.header.depth)?,
+				parent_fingerprint: self.fingerprint(),
+				child_number: number,
+				chain_code,
+			},
+			secret: child_secret,
+		})
+	}
+
+	/// Derives key at the given path, relative to this key
+	pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, Error> {
+		path.children()
+			.iter()
+			.try_fold(self.clone(), |key, number| key.derive_child(*number))
+	}
+}
+
+impl DisplayLayout for ExtendedPrivate {
+	type Target = Vec<u8>;
+
+	fn layout(&self) -> Self::Target {
+		let mut key = [0u8; 33];
+		key[1..].copy_from_slice(&self.secret);
+		serialize(&self.header, true, &key)
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error>
+	where
+		Self: Sized,
+	{
+		let (header, is_private, key) = deserialize(data)?;
+		if !is_private {
+			return Err(Error::InvalidExtendedKey);
+		}
+		key::SecretKey::from_slice(&key[1..])?;
+		let mut secret = Secret::default();
+		secret.copy_from_slice(&key[1..]);
+		Ok(ExtendedPrivate { header, secret })
+	}
+}
+
+impl fmt::Debug for ExtendedPrivate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "{:?}", self.header)?;
+		self.private().fmt(f)
+	}
+}
+
+impl fmt::Display for ExtendedPrivate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.layout().to_base58().fmt(f)
+	}
+}
+
+impl FromStr for ExtendedPrivate {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error>
+	where
+		Self: Sized,
+	{
+		let data = s.from_base58().map_err(|_| Error::InvalidExtendedKey)?;
+		ExtendedPrivate::from_layout(&data)
+	}
+}
+
+impl From<&'static str> for ExtendedPrivate {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+/// Extended public key
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedPublic {
+	header: Header,
+	public: key::PublicKey,
+}
+
+impl ExtendedPublic {
+	/// Returns the same key, serialized with the version of the given format
+	pub fn with_format(mut self, format: ExtendedKeyFormat) -> Self {
+		self.header.format = format;
+		self
+	}
+
+	pub fn network(&self) -> Network {
+		self.header.network
+	}
+
+	pub fn format(&self) -> ExtendedKeyFormat {
+		self.header.format
+	}
+
+	/// Number of derivations from the master key
+	pub fn depth(&self) -> u8 {
+		self.header.depth
+	}
+
+	pub fn parent_fingerprint(&self) -> Fingerprint {
+		self.header.parent_fingerprint
+	}
+
+	pub fn child_number(&self) -> ChildNumber {
+		self.header.child_number
+	}
+
+	pub fn chain_code(&self) -> &ChainCode {
+		&self.header.chain_code
+	}
+
+	/// Compressed public key
+	pub fn public(&self) -> Public {
+		Public::Compressed(self.public.serialize())
+	}
+
+	/// Hash160 of the public key
+	pub fn identifier(&self) -> AddressHash {
+		dhash160(&self.public.serialize())
+	}
+
+	pub fn fingerprint(&self) -> Fingerprint {
+		let mut fingerprint = Fingerprint::default();
+		fingerprint.copy_from_slice(&self.identifier()[0..4]);
+		fingerprint
+	}
+
+	/// Derives public key of the non-hardened child
+	pub fn derive_child(&self, number: ChildNumber) -> Result<Self, Error> {
+		if number.is_hardened() {
+			return Err(Error::InvalidChildNumber);
+		}
+		let mut data = Vec::with_capacity(37);
+		data.extend_from_slice(&self.public.serialize());
+		data.extend_from_slice(&u32::from(number).to_be_bytes());
+
+		let (tweak, chain_code) = split_hmac(hmac_sha512(&self.header.chain_code, &data));
+		let mut public = self.public;
+		// fails if tweak is not below curve order or if resulting point is at infinity
+		public.add_exp_assign(&SECP256K1, &tweak)?;
+
+		Ok(ExtendedPublic {
+			header: Header {
+				network: self.header.network,
+				format: self.header.format,
+				depth: child_depth(self.header.depth)?,
+				parent_fingerprint: self.fingerprint(),
+				child_number: number,
+				chain_code,
+			},
+			public,
+		})
+	}
+
+	/// Derives key at the given path, relative to this key. Path must not contain hardened children.
+	pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, Error> {
+		path.children()
+			.iter()
+			.try_fold(self.clone(), |key, number| key.derive_child(*number))
+	}
+}
+
+impl DisplayLayout for ExtendedPublic {
+	type Target = Vec<u8>;
+
+	fn layout(&self) -> Self::Target {
+		serialize(&self.header, false, &self.public.serialize())
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error>
+	where
+		Self: Sized,
+	{
+		let (header, is_private, key) = deserialize(data)?;
+		if is_private {
+			return Err(Error::InvalidExtendedKey);
+		}
+		let public = key::PublicKey::from_slice(&key)?;
+		Ok(ExtendedPublic { header, public })
+	}
+}
+
+impl fmt::Display for ExtendedPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.layout().to_base58().fmt(f)
+	}
+}
+
+impl FromStr for ExtendedPublic {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error>
+	where
+		Self: Sized,
+	{
+		let data = s.from_base58().map_err(|_| Error::InvalidExtendedKey)?;
+		ExtendedPublic::from_layout(&data)
+	}
+}
+
+impl From<&'static str> for ExtendedPublic {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ExtendedKeyFormat, ExtendedPrivate, ExtendedPublic};
+	use crate::derivation_path::{ChildNumber, DerivationPath};
+	use crate::network::Network;
+	use crate::Error;
+	use bitcrypto::FromHex;
+
+	fn check_derivation(master: &ExtendedPrivate, path: &'static str, xprv: &'static str, xpub: &'static str) {
+		let key = master.derive_path(&path.into()).unwrap();
+		assert_eq!(key.to_string(), xprv);
+		assert_eq!(key.extended_public().to_string(), xpub);
+		assert_eq!(ExtendedPrivate::from(xprv), key);
+		assert_eq!(ExtendedPublic::from(xpub), key.extended_public());
+	}
+
+	/// Test vector 1 from https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vectors
+	#[test]
+	fn test_bip32_test_vector_1() {
+		let seed: Vec<u8> = FromHex::from_hex("000102030405060708090a0b0c0d0e0f").unwrap();
+		let master = ExtendedPrivate::from_seed(Network::Mainnet, &seed).unwrap();
+		check_derivation(
+			&master,
+			"m",
+			"xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+			"xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+		);
+		check_derivation(
+			&master,
+			"m/0'",
+			"xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+			"xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+		);
+		check_derivation(
+			&master,
+			"m/0'/1/2'/2/1000000000",
+			"xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+			"xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+		);
+	}
+
+	#[test]
+	fn test_public_derivation_matches_private_derivation() {
+		let master = ExtendedPrivate::from_seed(Network::Testnet, &[1u8; 32]).unwrap();
+		let account = master.derive_path(&"m/84'/1'/0'".into()).unwrap();
+		let path: DerivationPath = "m/0/5".into();
+		assert_eq!(
+			account.extended_public().derive_path(&path).unwrap(),
+			account.derive_path(&path).unwrap().extended_public()
+		);
+		assert_eq!(
+			account.extended_public().derive_child(ChildNumber::Hardened(0)),
+			Err(Error::InvalidChildNumber)
+		);
+
+		let child = account.derive_child(ChildNumber::Normal(1)).unwrap();
+		assert_eq!(child.depth(), 4);
+		assert_eq!(child.parent_fingerprint(), account.fingerprint());
+		assert_eq!(child.child_number(), ChildNumber::Normal(1));
+	}
+
+	#[test]
+	fn test_extended_key_formats() {
+		let master = ExtendedPrivate::from_seed(Network::Mainnet, &[2u8; 32]).unwrap();
+		let prefixes = |key: &ExtendedPrivate| (key.to_string()[0..4].to_owned(), key.extended_public().to_string()[0..4].to_owned());
+		assert_eq!(prefixes(&master), ("xprv".into(), "xpub".into()));
+		let key = master.clone().with_format(ExtendedKeyFormat::P2shP2wpkh);
+		assert_eq!(prefixes(&key), ("yprv".into(), "ypub".into()));
+		assert_eq!(key.to_string().parse::<ExtendedPrivate>().unwrap(), key);
+		let key = master.clone().with_format(ExtendedKeyFormat::P2wpkh);
+		assert_eq!(prefixes(&key), ("zprv".into(), "zpub".into()));
+		assert_eq!(
+			key.extended_public().to_string().parse::<ExtendedPublic>().unwrap(),
+			key.extended_public()
+		);
+
+		let master = ExtendedPrivate::from_seed(Network::Regtest, &[2u8; 32]).unwrap();
+		assert_eq!(prefixes(&master), ("tprv".into(), "tpub".into()));
+		assert_eq!(
+			prefixes(&master.clone().with_format(ExtendedKeyFormat::P2shP2wpkh)),
+			("uprv".into(), "upub".into())
+		);
+		assert_eq!(
+			prefixes(&master.clone().with_format(ExtendedKeyFormat::P2wpkh)),
+			("vprv".into(), "vpub".into())
+		);
+		// regtest keys are read back as testnet keys
+		assert_eq!(master.to_string().parse::<ExtendedPrivate>().unwrap().network(), Network::Testnet);
+	}
+
+	#[test]
+	fn test_extended_key_invalid() {
+		let xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+		assert_eq!(xpub.parse::<ExtendedPrivate>(), Err(Error::InvalidExtendedKey));
+		let mut invalid_checksum = xpub.to_owned();
+		invalid_checksum.pop();
+		invalid_checksum.push('9');
+		assert_eq!(invalid_checksum.parse::<ExtendedPublic>(), Err(Error::InvalidChecksum));
+		assert_eq!(ExtendedPrivate::from_seed(Network::Mainnet, &[0u8; 15]), Err(Error::InvalidSecret));
+	}
+}
@@ -69,7 +69,7 @@ impl KeyPair {
 		Address {
 			kind: Type::P2PKH,
 			network: self.private.network,
-			hash: self.public.address_hash(),
+			payload: self.public.address_hash().into(),
 		}
 	}
 }
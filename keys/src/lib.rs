@@ -3,14 +3,20 @@
 #[macro_use]
 extern crate lazy_static;
 extern crate base58;
+extern crate bech32;
 extern crate primitives;
 extern crate secp256k1;
+extern crate unicode_normalization;
 
 mod address;
+mod derivation_path;
 mod display;
+mod ellswift;
 mod error;
+mod extended;
 pub mod generator;
 mod keypair;
+mod mnemonic;
 mod network;
 mod private;
 mod public;
@@ -18,15 +24,19 @@ mod signature;
 
 pub use primitives::{bytes, hash};
 
-pub use address::{Address, Type};
+pub use address::{Address, Payload, Type};
 use bitcrypto::{HASH160, SHA256D};
+pub use derivation_path::{ChildNumber, DerivationPath, HARDENED_OFFSET};
 pub use display::DisplayLayout;
+pub use ellswift::{EllSwiftKeyPair, EllSwiftPublic};
 pub use error::Error;
+pub use extended::{ChainCode, ExtendedKeyFormat, ExtendedPrivate, ExtendedPublic, Fingerprint};
 pub use keypair::KeyPair;
+pub use mnemonic::Mnemonic;
 pub use network::Network;
 pub use private::Private;
-pub use public::Public;
-pub use signature::{CompactSignature, Signature};
+pub use public::{Public, XOnlyPublic};
+pub use signature::{CompactSignature, SchnorrSignature, Signature};
 
 /// 20 bytes long hash derived from public `ripemd160(sha256(public))`
 pub type AddressHash = HASH160;
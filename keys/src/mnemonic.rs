@@ -0,0 +1,180 @@
+//! BIP39 mnemonic code for generating deterministic keys. Only english wordlist is supported.
+//! https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+
+use crate::Error;
+use bitcrypto::{pbkdf2_hmac_sha512, sha256};
+use secp256k1::rand::rngs::StdRng;
+use secp256k1::rand::{FromEntropy, RngCore};
+use std::fmt;
+use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
+
+/// Number of PBKDF2 rounds, used to derive the seed
+const PBKDF2_ROUNDS: u32 = 2048;
+/// Every word encodes 11 bits
+const BITS_PER_WORD: usize = 11;
+
+lazy_static! {
+	static ref ENGLISH: Vec<&'static str> = include_str!("bip39_english.txt").lines().collect();
+}
+
+/// Mnemonic sentence, encoding 128 to 256 bits of entropy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mnemonic {
+	/// Indices of words in the wordlist
+	words: Vec<u16>,
+}
+
+impl Mnemonic {
+	/// Encodes entropy (16, 20, 24, 28 or 32 bytes) as a mnemonic sentence
+	pub fn from_entropy(entropy: &[u8]) -> Result<Self, Error> {
+		if entropy.len() < 16 || entropy.len() > 32 || !entropy.len().is_multiple_of(4) {
+			return Err(Error::InvalidMnemonic);
+		}
+
+		// entropy is followed by the first (entropy bits / 32) bits of its hash
+		let mut bits = entropy.to_vec();
+		bits.push(sha256(entropy)[0]);
+		let words_count = (entropy.len() * 8 + entropy.len() / 4) / BITS_PER_WORD;
+		let words = (0..words_count)
+			.map(|word| {
+				(0..BITS_PER_WORD).fold(0u16, |index, bit| {
+					let bit = word * BITS_PER_WORD + bit;
+					(index << 1) | ((bits[bit / 8] >> (7 - bit % 8)) & 1) as u16
+				})
+			})
+			.collect();
+		Ok(Mnemonic { words })
+	}
+
+	/// Generates mnemonic sentence of 12, 15, 18, 21 or 24 random words
+	pub fn generate(words_count: usize) -> Result<Self, Error> {
+		if !words_count.is_multiple_of(3) {
+			return Err(Error::InvalidMnemonic);
+		}
+		let mut entropy = vec![0u8; words_count * 4 / 3];
+		StdRng::from_entropy().fill_bytes(&mut entropy);
+		Mnemonic::from_entropy(&entropy)
+	}
+
+	/// Entropy, encoded by this sentence
+	pub fn entropy(&self) -> Vec<u8> {
+		let bits_count = self.words.len() * BITS_PER_WORD;
+		let mut bits = vec![0u8; bits_count.div_ceil(8)];
+		for (word, index) in self.words.iter().enumerate() {
+			for bit in 0..BITS_PER_WORD {
+				if index & (1 << (BITS_PER_WORD - 1 - bit)) != 0 {
+					let bit = word * BITS_PER_WORD + bit;
+					bits[bit / 8] |= 0x80 >> (bit % 8);
+				}
+			}
+		}
+		bits.truncate(self.words.len() * 4 / 3);
+		bits
+	}
+
+	pub fn words(&self) -> impl Iterator<Item = &'static str> + '_ {
+		self.words.iter().map(|index| ENGLISH[*index as usize])
+	}
+
+	/// Derives 64 bytes long seed, protected by the optional passphrase
+	pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+		let salt = format!("mnemonic{}", passphrase).nfkd().collect::<String>();
+		pbkdf2_hmac_sha512(self.to_string().as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS)
+	}
+}
+
+impl fmt::Display for Mnemonic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.words().collect::<Vec<_>>().join(" ").fmt(f)
+	}
+}
+
+impl FromStr for Mnemonic {
+	type Err = Error;
+
+	/// Parses sentence, checking its length and checksum
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let words = s
+			.split_whitespace()
+			.map(|word| ENGLISH.binary_search(&word).map(|index| index as u16))
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|_| Error::InvalidMnemonic)?;
+		if words.len() < 12 || words.len() > 24 || !words.len().is_multiple_of(3) {
+			return Err(Error::InvalidMnemonic);
+		}
+
+		let mnemonic = Mnemonic { words };
+		if Mnemonic::from_entropy(&mnemonic.entropy())? != mnemonic {
+			return Err(Error::InvalidChecksum);
+		}
+		Ok(mnemonic)
+	}
+}
+
+impl From<&'static str> for Mnemonic {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Mnemonic, ENGLISH};
+	use crate::Error;
+	use bitcrypto::{FromHex, ToHex};
+
+	#[test]
+	fn test_english_wordlist() {
+		assert_eq!(ENGLISH.len(), 2048);
+		assert!(ENGLISH.windows(2).all(|words| words[0] < words[1]));
+	}
+
+	/// Test vectors from https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+	#[test]
+	fn test_mnemonic_test_vectors() {
+		let vectors = [
+			(
+				"00000000000000000000000000000000",
+				"abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+				"c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+			),
+			(
+				"7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+				"legal winner thank year wave sausage worth useful legal winner thank yellow",
+				"2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+			),
+			(
+				"ffffffffffffffffffffffffffffffff",
+				"zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+				"ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+			),
+		];
+		for (entropy, words, seed) in vectors.iter() {
+			let entropy: Vec<u8> = FromHex::from_hex(entropy).unwrap();
+			let mnemonic = Mnemonic::from_entropy(&entropy).unwrap();
+			assert_eq!(mnemonic.to_string(), *words);
+			assert_eq!(Mnemonic::from(*words), mnemonic);
+			assert_eq!(mnemonic.entropy(), entropy);
+			assert_eq!(mnemonic.to_seed("TREZOR")[..].to_hex(), *seed);
+		}
+	}
+
+	#[test]
+	fn test_mnemonic_generate() {
+		for words_count in &[12, 15, 18, 21, 24] {
+			let mnemonic = Mnemonic::generate(*words_count).unwrap();
+			assert_eq!(mnemonic.words().count(), *words_count);
+			assert_eq!(mnemonic.to_string().parse::<Mnemonic>().unwrap(), mnemonic);
+		}
+		assert_eq!(Mnemonic::generate(13), Err(Error::InvalidMnemonic));
+	}
+
+	#[test]
+	fn test_mnemonic_invalid() {
+		let words = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+		assert_eq!(words.parse::<Mnemonic>(), Err(Error::InvalidMnemonic));
+		assert_eq!(format!("{} abandon", words).parse::<Mnemonic>(), Err(Error::InvalidChecksum));
+		assert_eq!(format!("{} bitcoin", words).parse::<Mnemonic>(), Err(Error::InvalidMnemonic));
+	}
+}
@@ -1,17 +1,18 @@
 //! Secret with additional network identifier and format type
 
 use crate::network::Network;
-use crate::{CompactSignature, DisplayLayout, Error, Message, Secret, Signature, SECP256K1};
+use crate::{CompactSignature, DisplayLayout, Error, Message, SchnorrSignature, Secret, Signature, SECP256K1};
 use base58::{FromBase58, ToBase58};
-use bitcrypto::ToHex;
+use bitcrypto::{ToHex, SHA256};
 use primitives::checksum::Checksum;
 use secp256k1::key;
+use secp256k1::schnorrsig;
 use secp256k1::Message as SecpMessage;
 use std::fmt;
 use std::str::FromStr;
 
 /// Secret with additional network identifier and format type
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone)]
 pub struct Private {
 	/// The network on which this key should be used.
 	pub network: Network,
@@ -48,6 +49,35 @@ impl Private {
 		}
 		Ok(CompactSignature(signature))
 	}
+
+	/// Creates BIP340 signature using the given auxiliary random data
+	pub fn sign_schnorr(&self, message: &SHA256, aux_rand: &[u8; 32]) -> Result<SchnorrSignature, Error> {
+		let context = &SECP256K1;
+		let keypair = schnorrsig::KeyPair::from_seckey_slice(context, &self.secret)?;
+		let message = SecpMessage::from_slice(message)?;
+		let signature = context.schnorrsig_sign_with_aux_rand(&message, &keypair, aux_rand);
+		SchnorrSignature::from_slice(signature.as_ref())
+	}
+
+	/// Tweaks the key by adding `tweak` to it. Tweaked key corresponds to the public key, returned by
+	/// `XOnlyPublic::tweak_add` of this key's x-only public key.
+	pub fn tweak_add_x_only(&self, tweak: &[u8]) -> Result<Private, Error> {
+		let context = &SECP256K1;
+		let mut secret = key::SecretKey::from_slice(&self.secret)?;
+		// x-only public key always has even Y coordinate => secret must be negated if it's odd
+		if key::PublicKey::from_secret_key(context, &secret).serialize()[0] == 0x03 {
+			secret.negate_assign();
+		}
+		secret.add_assign(tweak)?;
+
+		let mut tweaked = [0u8; 32];
+		tweaked.copy_from_slice(&secret[..]);
+		Ok(Private {
+			network: self.network,
+			secret: tweaked,
+			compressed: self.compressed,
+		})
+	}
 }
 
 impl DisplayLayout for Private {
@@ -57,7 +87,7 @@ impl DisplayLayout for Private {
 		let mut result = vec![];
 		let network_byte = match self.network {
 			Network::Mainnet => 128,
-			Network::Testnet => 239,
+			Network::Testnet | Network::Regtest => 239,
 		};
 
 		result.push(network_byte);
@@ -144,7 +174,8 @@ impl From<&'static str> for Private {
 mod tests {
 	use super::Private;
 	use crate::network::Network;
-	use bitcrypto::FromHex;
+	use crate::{KeyPair, SchnorrSignature};
+	use bitcrypto::{FromHex, Hash, SHA256};
 
 	#[test]
 	fn test_private_to_string() {
@@ -157,6 +188,19 @@ mod tests {
 		assert_eq!("5KSCKP8NUyBZPCCQusxRwgmz9sfvJQEgbGukmmHepWw5Bzp95mu", private.to_string());
 	}
 
+	/// Test vector #0 from https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+	#[test]
+	fn test_private_sign_schnorr() {
+		let private = Private {
+			network: Network::Mainnet,
+			secret: FromHex::from_hex("0000000000000000000000000000000000000000000000000000000000000003").unwrap(),
+			compressed: true,
+		};
+		let message = SHA256::from_inner([0; 32]);
+		let signature: SchnorrSignature = "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0".into();
+		assert_eq!(private.sign_schnorr(&message, &[0; 32]).unwrap(), signature);
+	}
+
 	#[test]
 	fn test_private_from_str() {
 		let private = Private {
@@ -167,4 +211,28 @@ mod tests {
 
 		assert_eq!(private, "5KSCKP8NUyBZPCCQusxRwgmz9sfvJQEgbGukmmHepWw5Bzp95mu".into());
 	}
+
+	#[test]
+	fn test_private_tweak_add_x_only() {
+		let tweak = [7u8; 32];
+		// public key of 5G has even Y coordinate, public key of 6G has odd Y coordinate
+		for i in 5..7 {
+			let mut secret = [0u8; 32];
+			secret[31] = i;
+			let private = Private {
+				network: Network::Mainnet,
+				secret,
+				compressed: true,
+			};
+			let (expected, _) = KeyPair::from_private(private.clone())
+				.unwrap()
+				.public()
+				.x_only()
+				.unwrap()
+				.tweak_add(&tweak)
+				.unwrap();
+			let tweaked = KeyPair::from_private(private.tweak_add_x_only(&tweak).unwrap()).unwrap();
+			assert!(tweaked.public().x_only().unwrap() == expected);
+		}
+	}
 }
@@ -1,11 +1,13 @@
-use crate::{AddressHash, CompactSignature, Error, Message, Signature, SECP256K1};
-use bitcrypto::{dhash160, ToHex};
+use crate::{AddressHash, CompactSignature, Error, Message, SchnorrSignature, Signature, SECP256K1};
+use bitcrypto::{dhash160, ToHex, SHA256};
 use secp256k1::key;
 use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+use secp256k1::schnorrsig;
 use secp256k1::{Error as SecpError, Message as SecpMessage, Signature as SecpSignature};
 use std::{fmt, ops};
 
 /// Secret public key
+#[derive(Clone)]
 pub enum Public {
 	/// Normal version of public key (0x04 byte + X and Y coordinate on curve)
 	Normal([u8; 65]),
@@ -65,6 +67,12 @@ impl Public {
 		};
 		Ok(public)
 	}
+
+	/// Returns the BIP340 x-only form of this key, dropping the Y coordinate parity
+	pub fn x_only(&self) -> Result<XOnlyPublic, Error> {
+		let public = key::PublicKey::from_slice(self)?;
+		XOnlyPublic::from_slice(&public.serialize()[1..33])
+	}
 }
 
 impl ops::Deref for Public {
@@ -100,3 +108,108 @@ impl fmt::Display for Public {
 		self.to_hex().fmt(f)
 	}
 }
+
+/// BIP340 public key, only X coordinate of the point is stored
+#[derive(Clone, Copy, PartialEq)]
+pub struct XOnlyPublic([u8; 32]);
+
+impl XOnlyPublic {
+	pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+		// make sure that the point is actually on the curve
+		let public = schnorrsig::PublicKey::from_slice(data)?;
+		Ok(XOnlyPublic(public.serialize()))
+	}
+
+	pub fn verify(&self, message: &SHA256, signature: &SchnorrSignature) -> Result<bool, Error> {
+		let context = &SECP256K1;
+		let public = schnorrsig::PublicKey::from_slice(&self.0)?;
+		let signature = schnorrsig::Signature::from_slice(signature)?;
+		let message = SecpMessage::from_slice(message)?;
+		match context.schnorrsig_verify(&signature, &message, &public) {
+			Ok(_) => Ok(true),
+			Err(SecpError::InvalidSignature) => Ok(false),
+			Err(x) => Err(x.into()),
+		}
+	}
+
+	/// Tweaks the key by adding `tweak * G` to it. Returns tweaked key and parity of its Y coordinate.
+	pub fn tweak_add(&self, tweak: &[u8]) -> Result<(XOnlyPublic, bool), Error> {
+		let context = &SECP256K1;
+		let mut public = schnorrsig::PublicKey::from_slice(&self.0)?;
+		let parity = public.tweak_add_assign(context, tweak)?;
+		Ok((XOnlyPublic(public.serialize()), parity))
+	}
+
+	/// Checks that `tweaked` (with given Y parity) is equal to this key tweaked by `tweak`.
+	pub fn is_tweaked(&self, tweaked: &XOnlyPublic, parity: bool, tweak: [u8; 32]) -> bool {
+		let context = &SECP256K1;
+		match (
+			schnorrsig::PublicKey::from_slice(&self.0),
+			schnorrsig::PublicKey::from_slice(&tweaked.0),
+		) {
+			(Ok(internal), Ok(tweaked)) => internal.tweak_add_check(context, &tweaked, parity, tweak),
+			_ => false,
+		}
+	}
+}
+
+impl ops::Deref for XOnlyPublic {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl fmt::Debug for XOnlyPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "x-only: {}", self.0.to_hex())
+	}
+}
+
+impl fmt::Display for XOnlyPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.to_hex().fmt(f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::XOnlyPublic;
+	use crate::SchnorrSignature;
+	use bitcrypto::{FromHex, Hash, SHA256};
+
+	/// Test vector #0 from https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+	const PUBLIC_0: &'static str = "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+	const SIGNATURE_0: &'static str =
+		"e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0";
+
+	fn x_only(hex: &'static str) -> XOnlyPublic {
+		XOnlyPublic::from_slice(&Vec::<u8>::from_hex(hex).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn test_x_only_verify() {
+		let public = x_only(PUBLIC_0);
+		let signature: SchnorrSignature = SIGNATURE_0.into();
+		assert!(public.verify(&SHA256::from_inner([0; 32]), &signature).unwrap());
+		assert!(!public.verify(&SHA256::from_inner([1; 32]), &signature).unwrap());
+	}
+
+	#[test]
+	fn test_x_only_from_slice() {
+		assert!(XOnlyPublic::from_slice(&[0; 31]).is_err());
+		// x = 0 is not on the curve
+		assert!(XOnlyPublic::from_slice(&[0; 32]).is_err());
+	}
+
+	#[test]
+	fn test_x_only_tweak() {
+		let internal = x_only(PUBLIC_0);
+		let tweak = [7; 32];
+		let (tweaked, parity) = internal.tweak_add(&tweak).unwrap();
+		assert!(internal.is_tweaked(&tweaked, parity, tweak));
+		assert!(!internal.is_tweaked(&tweaked, !parity, tweak));
+		assert!(!internal.is_tweaked(&tweaked, parity, [8; 32]));
+	}
+}
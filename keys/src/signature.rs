@@ -112,3 +112,61 @@ impl From<&'static str> for CompactSignature {
 		s.parse().unwrap()
 	}
 }
+
+/// BIP340 Schnorr signature.
+pub struct SchnorrSignature(pub [u8; 64]);
+
+impl SchnorrSignature {
+	pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+		if data.len() != 64 {
+			return Err(Error::InvalidSignature);
+		}
+
+		let mut signature = [0; 64];
+		signature.copy_from_slice(data);
+		Ok(SchnorrSignature(signature))
+	}
+}
+
+impl fmt::Debug for SchnorrSignature {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.0.to_hex())
+	}
+}
+
+impl fmt::Display for SchnorrSignature {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.0.to_hex())
+	}
+}
+
+impl ops::Deref for SchnorrSignature {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl PartialEq for SchnorrSignature {
+	fn eq(&self, other: &Self) -> bool {
+		let s_slice: &[u8] = self;
+		let o_slice: &[u8] = other;
+		s_slice == o_slice
+	}
+}
+
+impl str::FromStr for SchnorrSignature {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let sig: Vec<u8> = FromHex::from_hex(s).map_err(|_| Error::InvalidSignature)?;
+		SchnorrSignature::from_slice(&sig)
+	}
+}
+
+impl From<&'static str> for SchnorrSignature {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
@@ -1,10 +1,115 @@
+use super::block_transactions_request::{differential_decode, differential_encode};
 use crate::common::PrefilledTransaction;
-use chain::{BlockHeader, ShortTransactionID};
+use chain::{BlockHeader, ShortTransactionID, Transaction};
+use ser::{CompactInteger, Deserializable, Error as ReaderError, Reader, Serializable, Stream};
+use std::io;
 
-#[derive(Debug, PartialEq, Serializable, Deserializable)]
+/// Prefilled transactions indexes are stored as absolute positions in block, but are
+/// differentially encoded on the wire (BIP152).
+#[derive(Debug, PartialEq)]
 pub struct BlockHeaderAndIDs {
 	pub header: BlockHeader,
 	pub nonce: u64,
 	pub short_ids: Vec<ShortTransactionID>,
 	pub prefilled_transactions: Vec<PrefilledTransaction>,
 }
+
+impl Serializable for BlockHeaderAndIDs {
+	fn serialize(&self, stream: &mut Stream) {
+		stream.append(&self.header).append(&self.nonce).append_list(&self.short_ids);
+
+		// differential encoding requires strictly increasing indexes
+		let mut prefilled_transactions: Vec<&PrefilledTransaction> = self.prefilled_transactions.iter().collect();
+		prefilled_transactions.sort_by_key(|tx| tx.index);
+		prefilled_transactions.dedup_by_key(|tx| tx.index);
+
+		let differences = differential_encode(prefilled_transactions.iter().map(|tx| tx.index));
+		stream.append(&CompactInteger::from(prefilled_transactions.len()));
+		for (difference, prefilled) in differences.into_iter().zip(prefilled_transactions) {
+			stream.append(&CompactInteger::from(difference)).append(&prefilled.transaction);
+		}
+	}
+}
+
+impl Deserializable for BlockHeaderAndIDs {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError>
+	where
+		T: io::Read,
+	{
+		let header = reader.read()?;
+		let nonce = reader.read()?;
+		let short_ids = reader.read_list()?;
+
+		let prefilled_len: usize = reader.read::<CompactInteger>()?.into();
+		let mut differences = Vec::new();
+		let mut transactions: Vec<Transaction> = Vec::new();
+		for _ in 0..prefilled_len {
+			differences.push(reader.read::<CompactInteger>()?.into());
+			transactions.push(reader.read()?);
+		}
+		let prefilled_transactions = differential_decode(differences)?
+			.into_iter()
+			.zip(transactions)
+			.map(|(index, transaction)| PrefilledTransaction { index, transaction })
+			.collect();
+
+		Ok(BlockHeaderAndIDs {
+			header,
+			nonce,
+			short_ids,
+			prefilled_transactions,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::BlockHeaderAndIDs;
+	use crate::common::PrefilledTransaction;
+	use chain::Transaction;
+	use ser::{deserialize, serialize, Serializable};
+
+	#[test]
+	fn test_block_header_and_ids_prefilled_indexes_roundtrip() {
+		let transaction: Transaction = "01000000015fe01688dd8ae4428e21835c0e1b7af571c4223658d94da0c123e6fd7399862a010000006b483045022100f9e6d1bd3c9f54dcc72405994ec9ac2795878dd0b3cfbdc52bed28c2737fbecc02201fd68deab17bfaef1626e232cc4488dc273ba6fa5d807712b111d017cb96e0990121021fff64d1a21ede90d77cafa35fe7621db8aa433d947267980b395c35d23bd87fffffffff021ea56f72000000001976a9146fae1c8e7a648fff905dfdac9b019d3e887d7e8f88ac80f0fa02000000001976a9147f29b567c7dd9fc59cd3a7f716914966cc91ffa188ac00000000".into();
+		let compact_block = BlockHeaderAndIDs {
+			header: "000000205a54771c6a1a2bcc8f3412184f319dc02f7258b56fd5060100000000000000001de7a03cefe565d11cdfa369f6ffe59b9368a257203726c9cc363d31b4e3c2ebca4f3c58d4e6031830ccfd80".into(),
+			nonce: 7,
+			short_ids: vec![],
+			prefilled_transactions: vec![
+				PrefilledTransaction {
+					index: 0,
+					transaction: transaction.clone(),
+				},
+				PrefilledTransaction {
+					index: 3,
+					transaction,
+				},
+			],
+		};
+		let transaction_size = compact_block.prefilled_transactions[0].transaction.serialized_size();
+		let serialized = serialize(&compact_block);
+		// header (80) + nonce (8) + short_ids len (1) + prefilled len (1) => first index difference
+		assert_eq!(serialized[90], 0);
+		assert_eq!(serialized[91 + transaction_size], 2);
+		assert_eq!(deserialize::<_, BlockHeaderAndIDs>(serialized.as_ref()).unwrap(), compact_block);
+	}
+
+	#[test]
+	fn test_block_header_and_ids_unordered_prefilled_indexes() {
+		let transaction = Transaction::default();
+		let prefilled = |index| PrefilledTransaction {
+			index,
+			transaction: transaction.clone(),
+		};
+		let compact_block = BlockHeaderAndIDs {
+			header: "000000205a54771c6a1a2bcc8f3412184f319dc02f7258b56fd5060100000000000000001de7a03cefe565d11cdfa369f6ffe59b9368a257203726c9cc363d31b4e3c2ebca4f3c58d4e6031830ccfd80".into(),
+			nonce: 7,
+			short_ids: vec![],
+			prefilled_transactions: vec![prefilled(3), prefilled(0), prefilled(3)],
+		};
+		let deserialized = deserialize::<_, BlockHeaderAndIDs>(serialize(&compact_block).as_ref()).unwrap();
+		let indexes: Vec<usize> = deserialized.prefilled_transactions.iter().map(|tx| tx.index).collect();
+		assert_eq!(indexes, vec![0, 3]);
+	}
+}
@@ -2,6 +2,8 @@ use bitcrypto::SHA256D;
 use ser::{CompactInteger, Deserializable, Error as ReaderError, Reader, Serializable, Stream};
 use std::io;
 
+/// Transactions indexes are stored as absolute positions in block, but are
+/// differentially encoded on the wire (BIP152).
 #[derive(Debug, PartialEq)]
 pub struct BlockTransactionsRequest {
 	pub blockhash: SHA256D,
@@ -10,7 +12,10 @@ pub struct BlockTransactionsRequest {
 
 impl Serializable for BlockTransactionsRequest {
 	fn serialize(&self, stream: &mut Stream) {
-		let indexes: Vec<CompactInteger> = self.indexes.iter().map(|x| (*x).into()).collect();
+		let indexes: Vec<CompactInteger> = differential_encode(self.indexes.iter().cloned())
+			.into_iter()
+			.map(Into::into)
+			.collect();
 
 		stream.append(&self.blockhash).append_list(&indexes);
 	}
@@ -26,9 +31,92 @@ impl Deserializable for BlockTransactionsRequest {
 
 		let request = BlockTransactionsRequest {
 			blockhash,
-			indexes: indexes.into_iter().map(Into::into).collect(),
+			indexes: differential_decode(indexes.into_iter().map(Into::into))?,
 		};
 
 		Ok(request)
 	}
 }
+
+/// Converts absolute indexes into differences from the previous index.
+/// Indexes are sorted and deduplicated first, since only strictly increasing indexes can be encoded.
+pub(crate) fn differential_encode<I>(indexes: I) -> Vec<usize>
+where
+	I: IntoIterator<Item = usize>,
+{
+	let mut indexes: Vec<usize> = indexes.into_iter().collect();
+	indexes.sort_unstable();
+	indexes.dedup();
+
+	let mut next_index = 0;
+	indexes
+		.into_iter()
+		.map(|index| {
+			let difference = index - next_index;
+			next_index = index + 1;
+			difference
+		})
+		.collect()
+}
+
+/// Converts differentially encoded indexes back to absolute indexes.
+pub(crate) fn differential_decode<I>(differences: I) -> Result<Vec<usize>, ReaderError>
+where
+	I: IntoIterator<Item = usize>,
+{
+	let mut next_index: u64 = 0;
+	differences
+		.into_iter()
+		.map(|difference| {
+			let index = next_index + difference as u64;
+			// indexes are 16-bit in the reference implementation
+			if index > u16::MAX as u64 {
+				return Err(ReaderError::MalformedData);
+			}
+			next_index = index + 1;
+			Ok(index as usize)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::BlockTransactionsRequest;
+	use bitcrypto::SHA256D;
+	use ser::{deserialize, serialize, Error as ReaderError};
+
+	#[test]
+	fn test_block_transactions_request_indexes_are_differentially_encoded() {
+		let request = BlockTransactionsRequest {
+			blockhash: SHA256D::default(),
+			indexes: vec![0, 1, 5, 6],
+		};
+		let serialized = serialize(&request);
+		assert_eq!(&serialized[32..], &[4u8, 0, 0, 3, 0][..]);
+		assert_eq!(deserialize::<_, BlockTransactionsRequest>(serialized.as_ref()).unwrap(), request);
+	}
+
+	#[test]
+	fn test_block_transactions_request_unordered_indexes() {
+		let request = BlockTransactionsRequest {
+			blockhash: SHA256D::default(),
+			indexes: vec![6, 5, 1, 1, 0, 6],
+		};
+		let serialized = serialize(&request);
+		assert_eq!(&serialized[32..], &[4u8, 0, 0, 3, 0][..]);
+		assert_eq!(
+			deserialize::<_, BlockTransactionsRequest>(serialized.as_ref()).unwrap().indexes,
+			vec![0, 1, 5, 6]
+		);
+	}
+
+	#[test]
+	fn test_block_transactions_request_index_overflow() {
+		let mut raw = vec![0u8; 32];
+		raw.extend_from_slice(&[2u8, 0xfd, 0xff, 0xff, 0x00]);
+		assert_eq!(
+			deserialize::<_, BlockTransactionsRequest>(&raw[..]).unwrap_err(),
+			ReaderError::MalformedData
+		);
+	}
+}
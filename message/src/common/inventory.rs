@@ -10,6 +10,8 @@ pub enum InventoryType {
 	MessageBlock = 2,
 	MessageFilteredBlock = 3,
 	MessageCompactBlock = 4,
+	/// Transaction identified by its witness hash (BIP339).
+	MessageWtx = 5,
 	MessageWitnessTx = 0x40000001,
 	MessageWitnessBlock = 0x40000002,
 	MessageWitnessFilteredBlock = 0x40000003,
@@ -23,6 +25,7 @@ impl InventoryType {
 			2 => Some(InventoryType::MessageBlock),
 			3 => Some(InventoryType::MessageFilteredBlock),
 			4 => Some(InventoryType::MessageCompactBlock),
+			5 => Some(InventoryType::MessageWtx),
 			0x40000001 => Some(InventoryType::MessageWitnessTx),
 			0x40000002 => Some(InventoryType::MessageWitnessBlock),
 			0x40000003 => Some(InventoryType::MessageWitnessFilteredBlock),
@@ -74,6 +77,13 @@ impl InventoryVector {
 		}
 	}
 
+	pub fn wtx(witness_hash: SHA256D) -> Self {
+		InventoryVector {
+			inv_type: InventoryType::MessageWtx,
+			hash: witness_hash,
+		}
+	}
+
 	pub fn block(hash: SHA256D) -> Self {
 		InventoryVector {
 			inv_type: InventoryType::MessageBlock,
@@ -148,6 +158,7 @@ mod tests {
 		assert_eq!(2u32, InventoryType::MessageBlock.into());
 		assert_eq!(3u32, InventoryType::MessageFilteredBlock.into());
 		assert_eq!(4u32, InventoryType::MessageCompactBlock.into());
+		assert_eq!(5u32, InventoryType::MessageWtx.into());
 		assert_eq!(0x40000001u32, InventoryType::MessageWitnessTx.into());
 		assert_eq!(0x40000002u32, InventoryType::MessageWitnessBlock.into());
 		assert_eq!(0x40000003u32, InventoryType::MessageWitnessFilteredBlock.into());
@@ -157,6 +168,7 @@ mod tests {
 		assert_eq!(InventoryType::from_u32(2).unwrap(), InventoryType::MessageBlock);
 		assert_eq!(InventoryType::from_u32(3).unwrap(), InventoryType::MessageFilteredBlock);
 		assert_eq!(InventoryType::from_u32(4).unwrap(), InventoryType::MessageCompactBlock);
+		assert_eq!(InventoryType::from_u32(5).unwrap(), InventoryType::MessageWtx);
 		assert_eq!(InventoryType::from_u32(0x40000001).unwrap(), InventoryType::MessageWitnessTx);
 		assert_eq!(InventoryType::from_u32(0x40000002).unwrap(), InventoryType::MessageWitnessBlock);
 		assert_eq!(
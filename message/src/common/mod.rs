@@ -5,6 +5,7 @@ mod block_transactions_request;
 mod command;
 mod inventory;
 mod ip;
+mod network_address;
 mod port;
 mod prefilled_transaction;
 mod service;
@@ -16,6 +17,7 @@ pub use self::block_transactions_request::BlockTransactionsRequest;
 pub use self::command::Command;
 pub use self::inventory::{InventoryType, InventoryVector};
 pub use self::ip::IpAddress;
+pub use self::network_address::{NetworkAddress, NodeAddress, MAX_ADDRESS_LEN};
 pub use self::port::Port;
 pub use self::prefilled_transaction::PrefilledTransaction;
 pub use self::service::Services;
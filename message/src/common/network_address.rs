@@ -0,0 +1,347 @@
+//! Network-tagged addresses, which are relayed in the `addrv2` message.
+//! https://github.com/bitcoin/bips/blob/master/bip-0155.mediawiki
+
+use crypto::sha3_256;
+use ser::{Error as ReaderError, Serializable, Stream};
+use std::cmp::Ordering;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::{fmt, str};
+
+/// Network id of the IPv4 addresses
+const NETWORK_IPV4: u8 = 1;
+/// Network id of the IPv6 addresses
+const NETWORK_IPV6: u8 = 2;
+/// Network id of the Tor v3 addresses
+const NETWORK_TORV3: u8 = 4;
+/// Network id of the I2P addresses
+const NETWORK_I2P: u8 = 5;
+/// Network id of the CJDNS addresses
+const NETWORK_CJDNS: u8 = 6;
+/// Maximum length of the address in the `addrv2` message
+pub const MAX_ADDRESS_LEN: usize = 512;
+
+const TORV3_VERSION: u8 = 3;
+const TORV3_CHECKSUM_LEN: usize = 2;
+const ONION_SUFFIX: &str = ".onion";
+const I2P_SUFFIX: &str = ".b32.i2p";
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Address of the node in one of the networks supported by BIP155
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum NetworkAddress {
+	IpV4(Ipv4Addr),
+	IpV6(Ipv6Addr),
+	/// Ed25519 public key of the Tor v3 hidden service
+	TorV3([u8; 32]),
+	/// SHA256 hash of the I2P destination
+	I2P([u8; 32]),
+	/// IPv6 address in the fc00::/8 range
+	Cjdns(Ipv6Addr),
+}
+
+impl NetworkAddress {
+	/// Returns BIP155 network id of the address
+	pub fn network_id(&self) -> u8 {
+		match *self {
+			NetworkAddress::IpV4(_) => NETWORK_IPV4,
+			NetworkAddress::IpV6(_) => NETWORK_IPV6,
+			NetworkAddress::TorV3(_) => NETWORK_TORV3,
+			NetworkAddress::I2P(_) => NETWORK_I2P,
+			NetworkAddress::Cjdns(_) => NETWORK_CJDNS,
+		}
+	}
+
+	/// Returns raw address bytes, as they are serialized in the `addrv2` message
+	pub fn to_bytes(&self) -> Vec<u8> {
+		match *self {
+			NetworkAddress::IpV4(ip) => ip.octets().to_vec(),
+			NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => ip.octets().to_vec(),
+			NetworkAddress::TorV3(key) => key.to_vec(),
+			NetworkAddress::I2P(hash) => hash.to_vec(),
+		}
+	}
+
+	/// Creates address from the network id and raw bytes.
+	/// Returns None for the unknown and deprecated networks, which should be ignored.
+	pub fn from_network_bytes(network: u8, bytes: &[u8]) -> Result<Option<Self>, ReaderError> {
+		let address = match network {
+			NETWORK_IPV4 => NetworkAddress::IpV4(Ipv4Addr::from(fixed::<4>(bytes)?)),
+			NETWORK_IPV6 => NetworkAddress::IpV6(Ipv6Addr::from(fixed::<16>(bytes)?)),
+			NETWORK_TORV3 => NetworkAddress::TorV3(fixed(bytes)?),
+			NETWORK_I2P => NetworkAddress::I2P(fixed(bytes)?),
+			NETWORK_CJDNS => NetworkAddress::Cjdns(Ipv6Addr::from(fixed::<16>(bytes)?)),
+			_ => return Ok(None),
+		};
+
+		Ok(Some(address))
+	}
+
+	/// Returns ip address of the IPv4, IPv6 and CJDNS addresses
+	pub fn ip(&self) -> Option<IpAddr> {
+		match *self {
+			NetworkAddress::IpV4(ip) => Some(ip.into()),
+			NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => Some(ip.into()),
+			NetworkAddress::TorV3(_) | NetworkAddress::I2P(_) => None,
+		}
+	}
+
+	/// Returns true if the address can be relayed in the `addr` message
+	pub fn is_addr_v1_compatible(&self) -> bool {
+		match *self {
+			NetworkAddress::IpV4(_) | NetworkAddress::IpV6(_) => true,
+			NetworkAddress::TorV3(_) | NetworkAddress::I2P(_) | NetworkAddress::Cjdns(_) => false,
+		}
+	}
+}
+
+fn fixed<const N: usize>(bytes: &[u8]) -> Result<[u8; N], ReaderError> {
+	let mut result = [0u8; N];
+	if bytes.len() != N {
+		return Err(ReaderError::MalformedData);
+	}
+	result.copy_from_slice(bytes);
+	Ok(result)
+}
+
+impl From<IpAddr> for NetworkAddress {
+	fn from(ip: IpAddr) -> Self {
+		match ip {
+			IpAddr::V4(ip) => NetworkAddress::IpV4(ip),
+			IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+				Some(ip) => NetworkAddress::IpV4(ip),
+				None => NetworkAddress::IpV6(ip),
+			},
+		}
+	}
+}
+
+impl Serializable for NetworkAddress {
+	fn serialize(&self, stream: &mut Stream) {
+		stream.append(&self.network_id()).append_list(&self.to_bytes());
+	}
+}
+
+impl fmt::Display for NetworkAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			NetworkAddress::IpV4(ip) => write!(f, "{}", ip),
+			NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => write!(f, "{}", ip),
+			NetworkAddress::TorV3(key) => {
+				let mut bytes = key.to_vec();
+				bytes.extend_from_slice(&torv3_checksum(&key));
+				bytes.push(TORV3_VERSION);
+				write!(f, "{}{}", base32_encode(&bytes), ONION_SUFFIX)
+			}
+			NetworkAddress::I2P(hash) => write!(f, "{}{}", base32_encode(&hash), I2P_SUFFIX),
+		}
+	}
+}
+
+impl str::FromStr for NetworkAddress {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some(name) = s.strip_suffix(ONION_SUFFIX) {
+			let bytes = base32_decode(name).ok_or("Invalid onion address")?;
+			if bytes.len() != 32 + TORV3_CHECKSUM_LEN + 1 || bytes[34] != TORV3_VERSION {
+				return Err("Unsupported onion address");
+			}
+			let key = fixed::<32>(&bytes[..32]).map_err(|_| "Invalid onion address")?;
+			if bytes[32..34] != torv3_checksum(&key) {
+				return Err("Invalid onion address checksum");
+			}
+			return Ok(NetworkAddress::TorV3(key));
+		}
+
+		if let Some(name) = s.strip_suffix(I2P_SUFFIX) {
+			let bytes = base32_decode(name).ok_or("Invalid i2p address")?;
+			return fixed(&bytes).map(NetworkAddress::I2P).map_err(|_| "Invalid i2p address");
+		}
+
+		match s.parse().map_err(|_| "Invalid network address")? {
+			IpAddr::V6(ip) if ip.octets()[0] == 0xfc => Ok(NetworkAddress::Cjdns(ip)),
+			ip => Ok(ip.into()),
+		}
+	}
+}
+
+fn torv3_checksum(key: &[u8; 32]) -> [u8; TORV3_CHECKSUM_LEN] {
+	let mut preimage = b".onion checksum".to_vec();
+	preimage.extend_from_slice(key);
+	preimage.push(TORV3_VERSION);
+	let hash = sha3_256(&preimage);
+	[hash[0], hash[1]]
+}
+
+/// Lowercase RFC 4648 base32 without padding
+fn base32_encode(data: &[u8]) -> String {
+	let mut result = String::with_capacity((data.len() * 8).div_ceil(5));
+	let mut buffer = 0u32;
+	let mut bits = 0;
+	for byte in data {
+		buffer = (buffer << 8) | u32::from(*byte);
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			result.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+		}
+	}
+	if bits > 0 {
+		result.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+	}
+	result
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+	let mut result = Vec::with_capacity(s.len() * 5 / 8);
+	let mut buffer = 0u32;
+	let mut bits = 0;
+	for c in s.bytes() {
+		let value = BASE32_ALPHABET.iter().position(|a| *a == c)? as u32;
+		buffer = (buffer << 5) | value;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			result.push((buffer >> bits) as u8);
+		}
+	}
+	// leftover bits are padding and must be zero
+	if buffer & ((1 << bits) - 1) != 0 {
+		return None;
+	}
+	Some(result)
+}
+
+/// Network address and port of the node
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct NodeAddress {
+	pub address: NetworkAddress,
+	pub port: u16,
+}
+
+impl NodeAddress {
+	pub fn new(address: NetworkAddress, port: u16) -> Self {
+		NodeAddress { address, port }
+	}
+
+	/// Returns socket address, if the node is reachable over IP
+	pub fn socket_addr(&self) -> Option<SocketAddr> {
+		self.address.ip().map(|ip| SocketAddr::new(ip, self.port))
+	}
+}
+
+impl From<SocketAddr> for NodeAddress {
+	fn from(addr: SocketAddr) -> Self {
+		NodeAddress::new(addr.ip().into(), addr.port())
+	}
+}
+
+impl Ord for NodeAddress {
+	fn cmp(&self, other: &Self) -> Ordering {
+		// ports are compared first to keep the ordering of nodes used before the network-tagged addresses
+		self.port.cmp(&other.port).then_with(|| self.address.cmp(&other.address))
+	}
+}
+
+impl PartialOrd for NodeAddress {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl fmt::Display for NodeAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.address {
+			NetworkAddress::IpV6(_) | NetworkAddress::Cjdns(_) => write!(f, "[{}]:{}", self.address, self.port),
+			_ => write!(f, "{}:{}", self.address, self.port),
+		}
+	}
+}
+
+impl str::FromStr for NodeAddress {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (address, port) = s.rsplit_once(':').ok_or("Missing port")?;
+		let address = address.strip_prefix('[').and_then(|a| a.strip_suffix(']')).unwrap_or(address);
+		let port = port.parse().map_err(|_| "Invalid port")?;
+		Ok(NodeAddress::new(address.parse()?, port))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{NetworkAddress, NodeAddress, NETWORK_I2P};
+	use ser::serialize;
+	use std::net::SocketAddr;
+
+	const ONION: &str = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion";
+	const ONION_KEY: &str = "79bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1f";
+
+	#[test]
+	fn test_network_address_to_string() {
+		let key: crate::bytes::Bytes = ONION_KEY.into();
+		let mut onion = [0u8; 32];
+		onion.copy_from_slice(&key);
+		assert_eq!(NetworkAddress::TorV3(onion).to_string(), ONION);
+
+		let mut hash = [0u8; 32];
+		for (i, byte) in hash.iter_mut().enumerate() {
+			*byte = i as u8;
+		}
+		assert_eq!(
+			NetworkAddress::I2P(hash).to_string(),
+			"aaaqeayeaudaocajbifqydiob4ibceqtcqkrmfyydenbwha5dypq.b32.i2p"
+		);
+		assert_eq!(NetworkAddress::Cjdns("fc00::1".parse().unwrap()).to_string(), "fc00::1");
+	}
+
+	#[test]
+	fn test_network_address_from_str() {
+		for s in &[
+			ONION,
+			"aaaqeayeaudaocajbifqydiob4ibceqtcqkrmfyydenbwha5dypq.b32.i2p",
+			"1.2.3.4",
+			"2001:db8::1",
+			"fc00::1",
+		] {
+			assert_eq!(s.parse::<NetworkAddress>().unwrap().to_string(), *s);
+		}
+		assert!(matches!("fc00::1".parse().unwrap(), NetworkAddress::Cjdns(_)));
+		assert!(matches!("::ffff:1.2.3.4".parse().unwrap(), NetworkAddress::IpV4(_)));
+
+		// invalid checksum
+		let invalid = ONION.replacen("pg6", "pg7", 1);
+		assert!(invalid.parse::<NetworkAddress>().is_err());
+		assert!("abc.b32.i2p".parse::<NetworkAddress>().is_err());
+		assert!("localhost".parse::<NetworkAddress>().is_err());
+	}
+
+	#[test]
+	fn test_network_address_serialize() {
+		let address: NetworkAddress = "1.2.3.4".parse().unwrap();
+		assert_eq!(serialize(&address), "010401020304".into());
+
+		let bytes = [1u8; 32];
+		assert_eq!(
+			NetworkAddress::from_network_bytes(NETWORK_I2P, &bytes).unwrap(),
+			Some(NetworkAddress::I2P(bytes))
+		);
+		assert!(NetworkAddress::from_network_bytes(NETWORK_I2P, &bytes[..16]).is_err());
+		// deprecated Tor v2 addresses are ignored
+		assert_eq!(NetworkAddress::from_network_bytes(3, &bytes[..10]).unwrap(), None);
+		assert_eq!(NetworkAddress::from_network_bytes(42, &bytes).unwrap(), None);
+	}
+
+	#[test]
+	fn test_node_address() {
+		for s in &["127.0.0.1:8333", "[2001:db8::1]:8333", "[fc00::1]:8333", &format!("{}:8333", ONION)] {
+			assert_eq!(s.parse::<NodeAddress>().unwrap().to_string(), *s);
+		}
+
+		let socket: SocketAddr = "[2001:db8::1]:8333".parse().unwrap();
+		assert_eq!(NodeAddress::from(socket).socket_addr(), Some(socket));
+		assert_eq!(format!("{}:8333", ONION).parse::<NodeAddress>().unwrap().socket_addr(), None);
+		assert!("127.0.0.1".parse::<NodeAddress>().is_err());
+	}
+}
@@ -59,6 +59,24 @@ impl Services {
 		self
 	}
 
+	pub fn compact_filters(&self) -> bool {
+		self.bit_at(6)
+	}
+
+	pub fn with_compact_filters(mut self, v: bool) -> Self {
+		self.set_bit(6, v);
+		self
+	}
+
+	pub fn p2p_v2(&self) -> bool {
+		self.bit_at(11)
+	}
+
+	pub fn with_p2p_v2(mut self, v: bool) -> Self {
+		self.set_bit(11, v);
+		self
+	}
+
 	pub fn includes(&self, other: &Self) -> bool {
 		self.0 & other.0 == other.0
 	}
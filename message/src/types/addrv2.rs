@@ -0,0 +1,197 @@
+use crate::common::{NetAddress, NetworkAddress, Services, MAX_ADDRESS_LEN};
+use crate::types::addr::AddressEntry;
+use crate::{MessageResult, Payload};
+use ser::{CompactInteger, Error as ReaderError, Reader, Serializable, Stream};
+use std::io;
+
+/// Maximum number of addresses in the `addrv2` message
+pub const ADDRV2_MAX_ADDRESSES: usize = 1000;
+
+/// Addresses of the nodes in any of the BIP155 networks.
+/// https://github.com/bitcoin/bips/blob/master/bip-0155.mediawiki
+#[derive(Debug, PartialEq)]
+pub struct AddrV2 {
+	pub addresses: Vec<AddressV2Entry>,
+}
+
+impl AddrV2 {
+	pub fn new(addresses: Vec<AddressV2Entry>) -> Self {
+		AddrV2 { addresses }
+	}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AddressV2Entry {
+	pub timestamp: u32,
+	pub services: Services,
+	pub address: NetworkAddress,
+	pub port: u16,
+}
+
+impl AddressV2Entry {
+	/// Converts entry to the `addr` message entry, if the address can be represented there
+	pub fn to_v1(&self) -> Option<AddressEntry> {
+		if !self.address.is_addr_v1_compatible() {
+			return None;
+		}
+
+		self.address.ip().map(|ip| AddressEntry {
+			timestamp: self.timestamp,
+			address: NetAddress {
+				services: self.services,
+				address: ip.into(),
+				port: self.port.into(),
+			},
+		})
+	}
+}
+
+impl From<AddressEntry> for AddressV2Entry {
+	fn from(entry: AddressEntry) -> Self {
+		AddressV2Entry {
+			timestamp: entry.timestamp,
+			services: entry.address.services,
+			address: std::net::IpAddr::from(entry.address.address).into(),
+			port: entry.address.port.into(),
+		}
+	}
+}
+
+impl Serializable for AddressV2Entry {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.timestamp)
+			.append(&CompactInteger::from(u64::from(self.services)))
+			.append(&self.address)
+			.append_slice(&self.port.to_be_bytes());
+	}
+}
+
+/// Reads single entry. Entries from the unknown networks are read and then dropped.
+fn read_entry<T>(reader: &mut Reader<T>) -> Result<Option<AddressV2Entry>, ReaderError>
+where
+	T: io::Read,
+{
+	let timestamp = reader.read()?;
+	let services = u64::from(reader.read::<CompactInteger>()?).into();
+	let network: u8 = reader.read()?;
+	let len: usize = reader.read::<CompactInteger>()?.into();
+	if len > MAX_ADDRESS_LEN {
+		return Err(ReaderError::MalformedData);
+	}
+	let mut bytes = vec![0u8; len];
+	reader.read_slice(&mut bytes)?;
+	let mut port = [0u8; 2];
+	reader.read_slice(&mut port)?;
+
+	let entry = NetworkAddress::from_network_bytes(network, &bytes)?.map(|address| AddressV2Entry {
+		timestamp,
+		services,
+		address,
+		port: u16::from_be_bytes(port),
+	});
+
+	Ok(entry)
+}
+
+impl Payload for AddrV2 {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"addrv2"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let len: usize = reader.read::<CompactInteger>()?.into();
+		if len > ADDRV2_MAX_ADDRESSES {
+			return Err(ReaderError::MalformedData.into());
+		}
+
+		let mut addresses = Vec::with_capacity(len);
+		for _ in 0..len {
+			if let Some(entry) = read_entry(reader)? {
+				addresses.push(entry);
+			}
+		}
+
+		Ok(AddrV2 { addresses })
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append_list(&self.addresses);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{AddrV2, AddressV2Entry};
+	use crate::bytes::Bytes;
+	use crate::common::{NetworkAddress, Services};
+	use crate::{deserialize_payload, serialize_payload};
+
+	fn entries() -> Vec<AddressV2Entry> {
+		vec![
+			AddressV2Entry {
+				timestamp: 0x4d1015e2,
+				services: Services::default().with_network(true),
+				address: "1.2.3.4".parse().unwrap(),
+				port: 8333,
+			},
+			AddressV2Entry {
+				timestamp: 0x4d1015e2,
+				services: Services::default(),
+				address: NetworkAddress::I2P([0xab; 32]),
+				port: 0,
+			},
+		]
+	}
+
+	#[test]
+	fn test_addrv2_serialize() {
+		#[rustfmt::skip]
+		let expected: Bytes = concat!(
+			"02",
+			"e215104d", "01", "01", "04", "01020304", "208d",
+			"e215104d", "00", "05", "20", "abababababababababababababababababababababababababababababababab", "0000",
+		)
+		.into();
+
+		assert_eq!(serialize_payload(&AddrV2::new(entries()), 0).unwrap(), expected);
+		assert_eq!(deserialize_payload::<AddrV2>(&expected, 0).unwrap(), AddrV2::new(entries()));
+	}
+
+	#[test]
+	fn test_addrv2_deserialize_unknown_network() {
+		// Tor v2 and unknown networks are skipped
+		#[rustfmt::skip]
+		let raw: Bytes = concat!(
+			"03",
+			"e215104d", "01", "03", "0a", "00000000000000000000", "208d",
+			"e215104d", "01", "01", "04", "01020304", "208d",
+			"e215104d", "01", "2a", "02", "0000", "208d",
+		)
+		.into();
+		assert_eq!(
+			deserialize_payload::<AddrV2>(&raw, 0).unwrap(),
+			AddrV2::new(vec![entries()[0].clone()])
+		);
+
+		// known network with the invalid address length
+		let raw: Bytes = concat!("01", "e215104d", "01", "01", "05", "0102030405", "208d").into();
+		assert!(deserialize_payload::<AddrV2>(&raw, 0).is_err());
+	}
+
+	#[test]
+	fn test_addrv2_entry_to_v1() {
+		let entries = entries();
+		let v1 = entries[0].to_v1().unwrap();
+		assert_eq!(AddressV2Entry::from(v1), entries[0]);
+		assert_eq!(entries[1].to_v1(), None);
+	}
+}
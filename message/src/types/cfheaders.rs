@@ -0,0 +1,76 @@
+use crate::types::GETCFHEADERS_MAX_RESPONSE_HEADERS;
+use crate::{MessageResult, Payload};
+use bitcrypto::SHA256D;
+use ser::{Reader, Stream};
+use std::io;
+
+#[derive(Debug, PartialEq)]
+pub struct CFHeaders {
+	pub filter_type: u8,
+	pub stop_hash: SHA256D,
+	/// Header of the filter, preceding the first requested filter
+	pub previous_filter_header: SHA256D,
+	/// Hashes of the requested filters
+	pub filter_hashes: Vec<SHA256D>,
+}
+
+impl Payload for CFHeaders {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"cfheaders"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let cfheaders = CFHeaders {
+			filter_type: reader.read()?,
+			stop_hash: reader.read()?,
+			previous_filter_header: reader.read()?,
+			filter_hashes: reader.read_list_max(GETCFHEADERS_MAX_RESPONSE_HEADERS as usize)?,
+		};
+
+		Ok(cfheaders)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.filter_type)
+			.append(&self.stop_hash)
+			.append(&self.previous_filter_header)
+			.append_list::<SHA256D, SHA256D>(&self.filter_hashes);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::CFHeaders;
+	use crate::bytes::Bytes;
+	use crate::{deserialize_payload, serialize_payload};
+	use bitcrypto::{Hash, SHA256D};
+
+	#[test]
+	fn test_cfheaders_serialization() {
+		let cfheaders = CFHeaders {
+			filter_type: 0,
+			stop_hash: SHA256D::from_inner([1; 32]),
+			previous_filter_header: SHA256D::from_inner([2; 32]),
+			filter_hashes: vec![SHA256D::from_inner([3; 32])],
+		};
+		let mut expected = vec![0];
+		expected.extend_from_slice(&[1; 32]);
+		expected.extend_from_slice(&[2; 32]);
+		expected.push(1);
+		expected.extend_from_slice(&[3; 32]);
+		let expected: Bytes = expected.into();
+
+		let serialized = serialize_payload(&cfheaders, 0).unwrap();
+		assert_eq!(serialized, expected);
+		assert_eq!(deserialize_payload::<CFHeaders>(&serialized, 0).unwrap(), cfheaders);
+	}
+}
@@ -0,0 +1,42 @@
+use crate::{MessageResult, Payload};
+use bitcrypto::SHA256D;
+use ser::{Reader, Stream};
+use std::io;
+
+pub const GETCFILTERS_MAX_RESPONSE_FILTERS: u32 = 1_000;
+
+/// Requests filters of the canon blocks from `start_height` up to the block `stop_hash` (inclusive).
+#[derive(Debug, PartialEq)]
+pub struct GetCFilters {
+	pub filter_type: u8,
+	pub start_height: u32,
+	pub stop_hash: SHA256D,
+}
+
+impl Payload for GetCFilters {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"getcfilters"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let get_cfilters = GetCFilters {
+			filter_type: reader.read()?,
+			start_height: reader.read()?,
+			stop_hash: reader.read()?,
+		};
+
+		Ok(get_cfilters)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&self.filter_type).append(&self.start_height).append(&self.stop_hash);
+		Ok(())
+	}
+}
@@ -1,6 +1,10 @@
 pub mod addr;
+mod addrv2;
 mod block;
 mod blocktxn;
+mod cfcheckpt;
+mod cfheaders;
+mod cfilter;
 mod compactblock;
 mod feefilter;
 mod filteradd;
@@ -9,6 +13,9 @@ mod filterload;
 mod getaddr;
 mod getblocks;
 mod getblocktxn;
+mod getcfcheckpt;
+mod getcfheaders;
+mod getcfilters;
 mod getdata;
 mod getheaders;
 mod headers;
@@ -18,16 +25,26 @@ mod merkle_block;
 mod notfound;
 mod ping;
 mod pong;
+mod reconcildiff;
 pub mod reject;
+mod reqrecon;
+mod sendaddrv2;
 mod sendcompact;
 mod sendheaders;
+mod sendtxrcncl;
+mod sketch;
 mod tx;
 mod verack;
 pub mod version;
+mod wtxidrelay;
 
 pub use self::addr::Addr;
+pub use self::addrv2::{AddrV2, AddressV2Entry, ADDRV2_MAX_ADDRESSES};
 pub use self::block::Block;
 pub use self::blocktxn::BlockTxn;
+pub use self::cfcheckpt::CFCheckpt;
+pub use self::cfheaders::CFHeaders;
+pub use self::cfilter::CFilter;
 pub use self::compactblock::CompactBlock;
 pub use self::feefilter::FeeFilter;
 pub use self::filteradd::{FilterAdd, FILTERADD_MAX_DATA_LEN};
@@ -37,6 +54,9 @@ pub use self::filterload::{FilterLoad, FILTERLOAD_MAX_FILTER_LEN, FILTERLOAD_MAX
 pub use self::getaddr::GetAddr;
 pub use self::getblocks::{GetBlocks, GETBLOCKS_MAX_RESPONSE_HASHES};
 pub use self::getblocktxn::GetBlockTxn;
+pub use self::getcfcheckpt::{GetCFCheckpt, CFCHECKPT_INTERVAL};
+pub use self::getcfheaders::{GetCFHeaders, GETCFHEADERS_MAX_RESPONSE_HEADERS};
+pub use self::getcfilters::{GetCFilters, GETCFILTERS_MAX_RESPONSE_FILTERS};
 pub use self::getdata::{GetData, GETDATA_MAX_INVENTORY_LEN};
 pub use self::getheaders::{GetHeaders, GETHEADERS_MAX_RESPONSE_HEADERS};
 pub use self::headers::{Headers, HEADERS_MAX_HEADERS_LEN};
@@ -46,9 +66,15 @@ pub use self::merkle_block::MerkleBlock;
 pub use self::notfound::NotFound;
 pub use self::ping::Ping;
 pub use self::pong::Pong;
+pub use self::reconcildiff::ReconcilDiff;
 pub use self::reject::Reject;
+pub use self::reqrecon::ReqRecon;
+pub use self::sendaddrv2::SendAddrV2;
 pub use self::sendcompact::SendCompact;
 pub use self::sendheaders::SendHeaders;
+pub use self::sendtxrcncl::SendTxRcncl;
+pub use self::sketch::Sketch;
 pub use self::tx::Tx;
 pub use self::verack::Verack;
 pub use self::version::Version;
+pub use self::wtxidrelay::WtxidRelay;
@@ -0,0 +1,39 @@
+use crate::{MessageResult, Payload};
+use ser::{Reader, Stream};
+use std::io;
+
+/// Result of the reconciliation, sent by the reconciliation initiator (BIP330).
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReconcilDiff {
+	/// Was the set difference decoded from the sketch?
+	pub success: bool,
+	/// Short ids of the transactions, which are missing from the initiator set
+	pub ask_shortids: Vec<u32>,
+}
+
+impl Payload for ReconcilDiff {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"reconcildiff"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let reconcildiff = ReconcilDiff {
+			success: reader.read()?,
+			ask_shortids: reader.read_list()?,
+		};
+
+		Ok(reconcildiff)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&self.success).append_list(&self.ask_shortids);
+		Ok(())
+	}
+}
@@ -0,0 +1,39 @@
+use crate::{MessageResult, Payload};
+use ser::{Reader, Stream};
+use std::io;
+
+/// Requests reconciliation of the transactions announcements (BIP330).
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReqRecon {
+	/// Size of the reconciliation set of the requesting peer
+	pub set_size: u16,
+	/// Coefficient, used to estimate the set difference
+	pub q: u16,
+}
+
+impl Payload for ReqRecon {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"reqrecon"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let reqrecon = ReqRecon {
+			set_size: reader.read()?,
+			q: reader.read()?,
+		};
+
+		Ok(reqrecon)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&self.set_size).append(&self.q);
+		Ok(())
+	}
+}
@@ -0,0 +1,40 @@
+use crate::{MessageResult, Payload};
+use ser::{Reader, Stream};
+use std::io;
+
+/// Signals support of the transactions reconciliation (BIP330).
+/// Sent between `version` and `verack` messages.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SendTxRcncl {
+	/// Highest supported reconciliation protocol version
+	pub version: u32,
+	/// Salt, used to compute short transactions ids
+	pub salt: u64,
+}
+
+impl Payload for SendTxRcncl {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"sendtxrcncl"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let sendtxrcncl = SendTxRcncl {
+			version: reader.read()?,
+			salt: reader.read()?,
+		};
+
+		Ok(sendtxrcncl)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&self.version).append(&self.salt);
+		Ok(())
+	}
+}
@@ -0,0 +1,35 @@
+use crate::bytes::Bytes;
+use crate::{MessageResult, Payload};
+use ser::{Reader, Stream};
+use std::io;
+
+/// Sketch of the reconciliation set, sent in response to `reqrecon` (BIP330).
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sketch {
+	/// Serialized sketch of the short transactions ids
+	pub skdata: Bytes,
+}
+
+impl Payload for Sketch {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"sketch"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		let sketch = Sketch { skdata: reader.read()? };
+
+		Ok(sketch)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&self.skdata);
+		Ok(())
+	}
+}
@@ -0,0 +1,29 @@
+use crate::{MessageResult, Payload};
+use ser::{Reader, Stream};
+use std::io;
+
+/// Signals that transactions should be announced by their witness hashes (BIP339).
+/// Sent between `version` and `verack` messages.
+#[derive(Debug, PartialEq)]
+pub struct WtxidRelay;
+
+impl Payload for WtxidRelay {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"wtxidrelay"
+	}
+
+	fn deserialize_payload<T>(_reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+	where
+		T: io::Read,
+	{
+		Ok(WtxidRelay)
+	}
+
+	fn serialize_payload(&self, _stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		Ok(())
+	}
+}
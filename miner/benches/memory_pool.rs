@@ -62,7 +62,9 @@ fn memory_pool_insert_independent_transactions(c: &mut Criterion) {
 	c.bench_function("memory_pool_insert_independent_transactions", |b| {
 		b.iter(|| {
 			let mut transactions = transactions.clone();
-			(0..iterations).for_each(|_| pool.insert_verified(transactions.pop_front().unwrap().into(), &NonZeroFeeCalculator {}))
+			(0..iterations).for_each(|_| {
+				let _ = pool.insert_verified(transactions.pop_front().unwrap().into(), &NonZeroFeeCalculator {});
+			})
 		})
 	});
 }
@@ -76,8 +78,11 @@ fn memory_pool_insert_descendant_transaction(c: &mut Criterion) {
 		b.iter(|| {
 			let mut transactions = transactions.clone();
 			let mut pool = MemoryPool::new();
-			pool.insert_verified(transactions.pop_front().unwrap().into(), &NonZeroFeeCalculator {});
-			(0..iterations).for_each(|_| pool.insert_verified(transactions.pop_front().unwrap().into(), &NonZeroFeeCalculator {}))
+			pool.insert_verified(transactions.pop_front().unwrap().into(), &NonZeroFeeCalculator {})
+				.unwrap();
+			(0..iterations).for_each(|_| {
+				let _ = pool.insert_verified(transactions.pop_front().unwrap().into(), &NonZeroFeeCalculator {});
+			})
 		})
 	});
 }
@@ -94,8 +99,11 @@ fn memory_pool_insert_ancestor_transaction(c: &mut Criterion) {
 		b.iter(|| {
 			let mut transactions = transactions.clone();
 			let mut pool = MemoryPool::new();
-			pool.insert_verified(transactions.pop_front().unwrap().into(), &NonZeroFeeCalculator {});
-			(0..iterations).for_each(|_| pool.insert_verified(transactions.pop_back().unwrap().into(), &NonZeroFeeCalculator {}))
+			pool.insert_verified(transactions.pop_front().unwrap().into(), &NonZeroFeeCalculator {})
+				.unwrap();
+			(0..iterations).for_each(|_| {
+				let _ = pool.insert_verified(transactions.pop_back().unwrap().into(), &NonZeroFeeCalculator {});
+			})
 		})
 	});
 }
@@ -105,7 +113,7 @@ fn memory_pool_remove_independent_in_order(c: &mut Criterion) {
 	let iterations = 100;
 	let mut pool = MemoryPool::new();
 	for transaction in prepare_independent_transactions(iterations) {
-		pool.insert_verified(transaction.into(), &NonZeroFeeCalculator {})
+		pool.insert_verified(transaction.into(), &NonZeroFeeCalculator {}).unwrap();
 	}
 	c.bench_function("memory_pool_remove_independent_in_order", |b| {
 		b.iter(|| {
@@ -121,7 +129,7 @@ fn memory_pool_remove_dependent_in_order(c: &mut Criterion) {
 	let iterations = 100;
 	let mut pool = MemoryPool::new();
 	for transaction in prepare_dependent_transactions(iterations) {
-		pool.insert_verified(transaction.into(), &NonZeroFeeCalculator {})
+		pool.insert_verified(transaction.into(), &NonZeroFeeCalculator {}).unwrap();
 	}
 	c.bench_function("memory_pool_remove_dependent_in_order", |b| {
 		b.iter(|| {
@@ -363,8 +363,8 @@ mod tests {
 
 			let mut pool = MemoryPool::new();
 			let storage: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
-			pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
-			pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+			pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+			pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
 
 			(
 				BlockAssembler {
@@ -393,7 +393,8 @@ mod tests {
 
 		let storage: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
 		let mut pool = MemoryPool::new();
-		pool.insert_verified(tx0, &FeeCalculator(storage.as_transaction_output_provider()));
+		pool.insert_verified(tx0, &FeeCalculator(storage.as_transaction_output_provider()))
+			.unwrap();
 
 		let consensus = ConsensusParams::new(Network::Mainnet);
 		let block = BlockAssembler {
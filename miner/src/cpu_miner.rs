@@ -3,7 +3,7 @@ use bitcrypto::dhash256;
 use bitcrypto::SHA256D;
 use byteorder::{LittleEndian, WriteBytesExt};
 use chain::{merkle_root, BlockHeader, IndexedBlock, Transaction, TransactionInput, TransactionOutput};
-use keys::AddressHash;
+use keys::{Address, AddressHash, Network, Type};
 use primitives::bytes::Bytes;
 use primitives::compact::Compact;
 use primitives::U256;
@@ -70,13 +70,13 @@ pub trait CoinbaseTransactionBuilder {
 	fn finish(self) -> Transaction;
 }
 
-pub struct P2shCoinbaseTransactionBuilder {
+pub struct AddressCoinbaseTransactionBuilder {
 	transaction: Transaction,
 }
 
-impl P2shCoinbaseTransactionBuilder {
-	pub fn new(hash: &AddressHash, value: u64) -> Self {
-		let script_pubkey = Builder::build_p2sh(hash).into();
+impl AddressCoinbaseTransactionBuilder {
+	pub fn new(address: &Address, value: u64) -> Result<Self, keys::Error> {
+		let script_pubkey = Builder::build_address(address)?.into();
 
 		let transaction = Transaction {
 			version: 0,
@@ -85,11 +85,11 @@ impl P2shCoinbaseTransactionBuilder {
 			lock_time: 0,
 		};
 
-		P2shCoinbaseTransactionBuilder { transaction }
+		Ok(AddressCoinbaseTransactionBuilder { transaction })
 	}
 }
 
-impl CoinbaseTransactionBuilder for P2shCoinbaseTransactionBuilder {
+impl CoinbaseTransactionBuilder for AddressCoinbaseTransactionBuilder {
 	fn set_extranonce(&mut self, extranonce: &[u8]) {
 		self.transaction.inputs[0].script_sig = extranonce.to_vec().into();
 	}
@@ -179,9 +179,15 @@ where
 	None
 }
 
-pub fn mine_block(mut block_template: BlockTemplate, address: Option<AddressHash>, max_tries: usize) -> Option<IndexedBlock> {
-	let hash = address.unwrap_or_default();
-	let coinbase_builder = P2shCoinbaseTransactionBuilder::new(&hash, block_template.coinbase_value);
+/// Mines block, paying to the given address. Returns `None` if the address is invalid or the solution is not found.
+pub fn mine_block(mut block_template: BlockTemplate, address: Option<Address>, max_tries: usize) -> Option<IndexedBlock> {
+	// pay to empty script hash if no address is given
+	let address = address.unwrap_or_else(|| Address {
+		kind: Type::P2SH,
+		network: Network::Mainnet,
+		payload: AddressHash::default().into(),
+	});
+	let coinbase_builder = AddressCoinbaseTransactionBuilder::new(&address, block_template.coinbase_value).ok()?;
 	if let Some(solution) = find_solution(&block_template, coinbase_builder, U256::max_value(), max_tries) {
 		block_template.transactions.push(solution.coinbase_transaction.into());
 		let hashes: Vec<SHA256D> = block_template.transactions.iter().map(|t| t.hash).collect();
@@ -209,12 +215,13 @@ pub fn mine_block(mut block_template: BlockTemplate, address: Option<AddressHash
 
 #[cfg(test)]
 mod tests {
-	use super::{find_solution, P2shCoinbaseTransactionBuilder};
+	use super::{find_solution, AddressCoinbaseTransactionBuilder};
 	use crate::block_template::BlockTemplate;
 	use crate::cpu_miner::mine_block;
 	use bitcrypto::SHA256D;
-	use keys::AddressHash;
+	use keys::{Address, AddressHash, Network, Type};
 	use primitives::U256;
+	use script::Builder;
 
 	#[test]
 	fn test_cpu_miner_low_difficulty() {
@@ -230,8 +237,12 @@ mod tests {
 			sigop_limit: 100,
 		};
 
-		let hash = AddressHash::default();
-		let coinbase_builder = P2shCoinbaseTransactionBuilder::new(&hash, 10);
+		let address = Address {
+			kind: Type::P2SH,
+			network: Network::Mainnet,
+			payload: AddressHash::default().into(),
+		};
+		let coinbase_builder = AddressCoinbaseTransactionBuilder::new(&address, 10).unwrap();
 		let solution = find_solution(&block_template, coinbase_builder, U256::max_value(), usize::max_value());
 		assert!(solution.is_some());
 	}
@@ -253,4 +264,27 @@ mod tests {
 		let block = mine_block(block_template, None, usize::max_value());
 		assert!(block.is_some());
 	}
+
+	#[test]
+	fn test_cpu_miner_mine_block_to_witness_address() {
+		let block_template = BlockTemplate {
+			version: 0,
+			previous_header_hash: SHA256D::default(),
+			time: 0,
+			bits: U256::max_value().into(),
+			height: 0,
+			transactions: Vec::new(),
+			coinbase_value: 10,
+			size_limit: 1000,
+			sigop_limit: 100,
+		};
+
+		let address: Address = "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080".into();
+		let block = mine_block(block_template, Some(address.clone()), usize::max_value()).unwrap();
+		let coinbase = &block.transactions[0].raw;
+		assert_eq!(
+			coinbase.outputs[0].script_pubkey,
+			Builder::build_address(&address).unwrap().to_bytes()
+		);
+	}
 }
@@ -18,12 +18,15 @@ pub use cpu_miner::{find_solution, mine_block};
 pub use fee::NonZeroFeeCalculator;
 pub use fee::{transaction_fee, transaction_fee_rate, FeeCalculator};
 pub use memory_pool::{
-	DoubleSpendCheckResult, HashedOutPoint, Information as MemoryPoolInformation, MemoryPool, NonFinalDoubleSpendSet,
-	OrderingStrategy as MemoryPoolOrderingStrategy,
+	DoubleSpendCheckResult, EntryInformation as MemoryPoolEntryInformation, HashedOutPoint, Information as MemoryPoolInformation,
+	MemoryPool, OrderingStrategy as MemoryPoolOrderingStrategy, PackageLimits as MemoryPoolPackageLimits, ReplaceableDoubleSpendSet,
+	DEFAULT_EXPIRY as DEFAULT_MEMORY_POOL_EXPIRY, DEFAULT_MAX_MEMORY_USAGE as DEFAULT_MEMORY_POOL_MAX_MEMORY_USAGE,
 };
+pub use memory_pool_dump::{DumpedTransaction, MemoryPoolDump, MEMORY_POOL_DUMP_VERSION};
 
 mod block_assembler;
 pub mod block_template;
 mod cpu_miner;
 mod fee;
 mod memory_pool;
+mod memory_pool_dump;
@@ -6,18 +6,37 @@
 //! It also guarantees that ancestor-descendant relation won't break during ordered removal (ancestors always removed
 //! before descendants). Removal using `remove_by_hash` can break this rule.
 use crate::fee::MemoryPoolFeeCalculator;
+use crate::memory_pool_dump::{DumpedTransaction, MemoryPoolDump};
 use bitcrypto::SHA256D;
 use chain::{IndexedTransaction, OutPoint, Transaction, TransactionOutput};
 use heapsize::HeapSizeOf;
 use primitives::bytes::Bytes;
-use ser::{serialize, Serializable};
+use primitives::time::{RealTime, Time};
+use ser::{serialize, Serializable, SERIALIZE_TRANSACTION_WITNESS};
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::hash::{Hash, Hasher};
+use std::mem::size_of;
 use storage::{TransactionOutputProvider, TransactionProvider};
+use verification::{PackageError, TransactionError};
+
+/// Default max memory usage of the `MemoryPool` (in bytes)
+pub const DEFAULT_MAX_MEMORY_USAGE: usize = 300 * 1_000_000;
+/// Default time (in seconds) after which transactions are expired from the `MemoryPool`
+pub const DEFAULT_EXPIRY: u64 = 14 * 24 * 60 * 60;
+/// Fee rate (in satoshis per 1000 bytes), added to the fee rate of evicted package, when raising the min fee rate
+const INCREMENTAL_FEE_RATE: u64 = 1000;
+/// Rolling min fee rate is halved during this time (in seconds)
+const ROLLING_FEE_HALFLIFE: u64 = 12 * 60 * 60;
+/// Transaction with any input sequence less than or equal to this value signals replaceability (BIP125)
+pub const MAX_BIP125_RBF_SEQUENCE: u32 = 0xfffffffd;
+/// Max number of transactions that could be evicted by single replacement (BIP125)
+const MAX_REPLACEMENT_EVICTIONS: usize = 100;
+/// Max size of transaction that could exceed the descendants limits of its single in-pool ancestor (CPFP carve-out)
+const EXTRA_DESCENDANT_SIZE_LIMIT: usize = 10_000;
 
 /// Transactions ordering strategy
 #[cfg_attr(feature = "cargo-clippy", allow(enum_variant_names))]
@@ -38,6 +57,60 @@ pub struct Information {
 	pub transactions_count: usize,
 	/// Total number of bytes occupied by transactions from the `MemoryPool`
 	pub transactions_size_in_bytes: usize,
+	/// Total memory usage of the `MemoryPool` entries
+	pub memory_usage: usize,
+	/// Max memory usage of the `MemoryPool`
+	pub max_memory_usage: usize,
+	/// Min fee rate (in satoshis per 1000 bytes) for transaction to be accepted to the `MemoryPool`
+	pub minimum_fee_rate: u64,
+}
+
+/// Limits on in-pool ancestors and descendants of single transaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PackageLimits {
+	/// Max number of in-pool ancestors, including transaction itself
+	pub ancestors_count: usize,
+	/// Max size of transaction with all its in-pool ancestors
+	pub ancestors_size: usize,
+	/// Max number of in-pool descendants of every ancestor, including ancestor itself
+	pub descendants_count: usize,
+	/// Max size of every ancestor with all its in-pool descendants
+	pub descendants_size: usize,
+}
+
+/// Information on single `MemoryPool` transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryInformation {
+	/// Transaction hash
+	pub hash: SHA256D,
+	/// Transaction witness hash
+	pub witness_hash: SHA256D,
+	/// Transaction size
+	pub size: usize,
+	/// Transaction weight (BIP141)
+	pub weight: usize,
+	/// Time when transaction has entered the memory pool
+	pub time: u64,
+	/// Transaction fee
+	pub fee: u64,
+	/// Transaction fee, including virtual fee
+	pub modified_fee: u64,
+	/// Number of in-pool ancestors
+	pub ancestors_count: usize,
+	/// size + Sum(size) for all in-pool ancestors
+	pub ancestors_size: usize,
+	/// modified_fee + Sum(modified_fee) for all in-pool ancestors
+	pub ancestors_modified_fee: u64,
+	/// 1 + number of in-pool descendants
+	pub package_count: usize,
+	/// size + Sum(size) for all in-pool descendants
+	pub package_size: usize,
+	/// modified_fee + Sum(modified_fee) for all in-pool descendants
+	pub package_modified_fee: u64,
+	/// Hashes of in-pool transactions, spent by this transaction
+	pub depends: Vec<SHA256D>,
+	/// Does transaction signal replaceability (BIP125), either explicitly or through its in-pool ancestors?
+	pub bip125_replaceable: bool,
 }
 
 /// Transactions memory pool
@@ -47,6 +120,21 @@ pub struct MemoryPool {
 	storage: Storage,
 	/// Do we accept zero fee transactions?
 	accept_zero_fee_transactions: bool,
+	/// Do we allow replacement of transactions that do not signal replaceability?
+	full_rbf: bool,
+	/// Limits on in-pool ancestors and descendants of inserted transactions
+	package_limits: PackageLimits,
+	/// Max memory usage. Lowest package score transactions are evicted when it is exceeded
+	max_memory_usage: usize,
+	/// Transactions are expired after staying in the pool for this time (in seconds)
+	expiry: u64,
+	/// Min fee rate (in satoshis per 1000 bytes), raised after evictions and decaying over time
+	rolling_minimum_fee_rate: f64,
+	/// Time of the last rolling min fee rate update
+	last_rolling_fee_update: u64,
+	/// Virtual fees, set with `set_virtual_fee`. They are also applied to transactions, which are not yet in the pool,
+	/// when these are inserted, and are forgotten when transaction is removed from the pool by hash (i.e. is mined)
+	virtual_fees: HashMap<SHA256D, i64>,
 }
 
 /// Single entry
@@ -62,10 +150,14 @@ pub struct Entry {
 	pub size: usize,
 	/// Throughout index of this transaction in memory pool (non persistent)
 	pub storage_index: u64,
+	/// Time when transaction has entered the memory pool
+	pub time: u64,
 	/// Transaction fee (stored for efficiency)
 	pub miner_fee: u64,
 	/// Virtual transaction fee (a way to prioritize/penalize transaction)
 	pub miner_virtual_fee: i64,
+	/// 1 + number of in-pool descendants
+	pub package_count: usize,
 	/// size + Sum(size) for all in-pool descendants
 	pub package_size: usize,
 	/// miner_fee + Sum(miner_fee) for all in-pool descendants
@@ -81,10 +173,16 @@ struct Storage {
 	counter: u64,
 	/// Total transactions size (when serialized) in bytes
 	transactions_size_in_bytes: usize,
+	/// Total memory usage of all entries
+	memory_usage: usize,
 	/// By-hash storage
 	by_hash: HashMap<SHA256D, Entry>,
+	/// Transactions hashes by their witness hashes
+	by_witness_hash: HashMap<SHA256D, SHA256D>,
 	/// Transactions by previous output
 	by_previous_output: HashMap<HashedOutPoint, SHA256D>,
+	/// Transactions by the time they have entered the pool
+	by_entry_time: BTreeSet<(u64, SHA256D)>,
 	/// References storage
 	references: ReferenceStorage,
 }
@@ -154,21 +252,26 @@ pub struct HashedOutPoint {
 pub enum DoubleSpendCheckResult {
 	/// No double spend
 	NoDoubleSpend,
-	/// Input {self.1, self.2} of new transaction is already spent in previous final memory-pool transaction {self.0}
+	/// Input {self.1, self.2} of new transaction is already spent in previous non-replaceable memory-pool transaction {self.0}
 	DoubleSpend(SHA256D, SHA256D, u32),
-	/// Some inputs of new transaction are already spent by non-final memory-pool transactions
-	NonFinalDoubleSpend(NonFinalDoubleSpendSet),
+	/// Some inputs of new transaction are already spent by replaceable memory-pool transactions
+	ReplaceableDoubleSpend(ReplaceableDoubleSpendSet),
 }
 
 /// Set of transaction outputs, which can be replaced if newer transaction
-/// replaces non-final transaction in memory pool
+/// replaces replaceable transaction in memory pool
 #[derive(Debug, PartialEq)]
-pub struct NonFinalDoubleSpendSet {
-	/// Double-spend outputs (outputs of newer transaction, which are also spent by nonfinal transactions of mempool)
+pub struct ReplaceableDoubleSpendSet {
+	/// Double-spend outputs (outputs of newer transaction, which are also spent by replaceable transactions of mempool)
 	pub double_spends: HashSet<HashedOutPoint>,
 	/// Outputs which also will be removed from memory pool in case of newer transaction insertion
-	/// (i.e. outputs of nonfinal transactions && their descendants)
+	/// (i.e. outputs of replaceable transactions && their descendants)
 	pub dependent_spends: HashSet<HashedOutPoint>,
+	/// Memory-pool transactions, directly conflicting with newer transaction
+	pub conflicts: HashSet<SHA256D>,
+	/// All memory-pool transactions, which will be removed in case of newer transaction insertion
+	/// (i.e. conflicting transactions && their descendants)
+	pub replaced: HashSet<SHA256D>,
 }
 
 impl From<OutPoint> for HashedOutPoint {
@@ -275,6 +378,34 @@ impl Ord for ByPackageScoreOrderedEntry {
 	}
 }
 
+impl Entry {
+	/// Memory occupied by this entry
+	fn memory_usage(&self) -> usize {
+		size_of::<Entry>() + self.heap_size_of_children()
+	}
+
+	/// Transaction fee, including virtual fee
+	fn modified_fee(&self) -> u64 {
+		(self.miner_fee as i64 + self.miner_virtual_fee).max(0) as u64
+	}
+}
+
+/// Checks if transaction signals replaceability (BIP125)
+fn signals_replaceability(transaction: &Transaction) -> bool {
+	transaction.inputs.iter().any(|input| input.sequence <= MAX_BIP125_RBF_SEQUENCE)
+}
+
+impl Default for PackageLimits {
+	fn default() -> Self {
+		PackageLimits {
+			ancestors_count: 25,
+			ancestors_size: 101_000,
+			descendants_count: 25,
+			descendants_size: 101_000,
+		}
+	}
+}
+
 impl HeapSizeOf for Entry {
 	fn heap_size_of_children(&self) -> usize {
 		self.transaction.heap_size_of_children() + self.ancestors.heap_size_of_children()
@@ -286,8 +417,11 @@ impl Storage {
 		Storage {
 			counter: 0,
 			transactions_size_in_bytes: 0,
+			memory_usage: 0,
 			by_hash: HashMap::new(),
+			by_witness_hash: HashMap::new(),
 			by_previous_output: HashMap::new(),
+			by_entry_time: BTreeSet::new(),
 			references: ReferenceStorage {
 				by_input: HashMap::new(),
 				pending: HashSet::new(),
@@ -303,6 +437,8 @@ impl Storage {
 	pub fn insert(&mut self, entry: Entry) {
 		// update pool information
 		self.transactions_size_in_bytes += entry.size;
+		self.memory_usage += entry.memory_usage();
+		self.by_entry_time.insert((entry.time, entry.hash));
 
 		// remember that this transactions depends on its inputs
 		for input_hash in entry.transaction.inputs.iter().map(|input| &input.previous_output.hash) {
@@ -318,6 +454,7 @@ impl Storage {
 			if let Some(ancestor_entry) = self.by_hash.get_mut(ancestor_hash) {
 				let removed = self.references.ordered.by_package_score.remove(&(ancestor_entry as &Entry).into());
 
+				ancestor_entry.package_count += 1;
 				ancestor_entry.package_size += entry.size;
 				ancestor_entry.package_miner_fee += entry.package_miner_fee;
 				ancestor_entry.package_miner_virtual_fee += entry.package_miner_virtual_fee;
@@ -344,6 +481,7 @@ impl Storage {
 		}
 
 		// add to by_hash storage
+		self.by_witness_hash.insert(entry.transaction.witness_hash(), entry.hash);
 		self.by_hash.insert(entry.hash.clone(), entry);
 	}
 
@@ -355,6 +493,10 @@ impl Storage {
 		self.by_hash.contains_key(hash)
 	}
 
+	pub fn hash_by_witness_hash(&self, witness_hash: &SHA256D) -> Option<SHA256D> {
+		self.by_witness_hash.get(witness_hash).cloned()
+	}
+
 	pub fn is_output_spent(&self, prevout: &OutPoint) -> bool {
 		self.by_previous_output.contains_key(&prevout.clone().into())
 	}
@@ -375,6 +517,7 @@ impl Storage {
 			}
 
 			entry.miner_virtual_fee = virtual_fee;
+			entry.package_miner_virtual_fee += miner_virtual_fee_change;
 
 			if insert_to_transaction_score {
 				self.references.ordered.by_transaction_score.insert((entry as &Entry).into());
@@ -400,6 +543,14 @@ impl Storage {
 		}
 	}
 
+	pub fn set_time(&mut self, h: &SHA256D, time: u64) {
+		if let Some(entry) = self.by_hash.get_mut(h) {
+			self.by_entry_time.remove(&(entry.time, entry.hash));
+			entry.time = time;
+			self.by_entry_time.insert((entry.time, entry.hash));
+		}
+	}
+
 	pub fn read_by_hash(&self, h: &SHA256D) -> Option<&Transaction> {
 		self.by_hash.get(h).map(|e| &e.transaction)
 	}
@@ -434,6 +585,9 @@ impl Storage {
 		self.by_hash.remove(h).map(|entry| {
 			// update pool information
 			self.transactions_size_in_bytes -= entry.size;
+			self.memory_usage -= entry.memory_usage();
+			self.by_entry_time.remove(&(entry.time, entry.hash));
+			self.by_witness_hash.remove(&entry.transaction.witness_hash());
 
 			// forget that all inputs of this transaction are spent
 			for input in &entry.transaction.inputs {
@@ -444,6 +598,29 @@ impl Storage {
 				assert_eq!(&spent_in_tx, h);
 			}
 
+			// update score of all packages this transaction was in
+			for ancestor_hash in &entry.ancestors {
+				if let Some(ancestor_entry) = self.by_hash.get_mut(ancestor_hash) {
+					let removed = self.references.ordered.by_package_score.remove(&(ancestor_entry as &Entry).into());
+
+					ancestor_entry.package_count -= 1;
+					ancestor_entry.package_size -= entry.size;
+					ancestor_entry.package_miner_fee -= entry.miner_fee;
+					ancestor_entry.package_miner_virtual_fee -= entry.miner_virtual_fee;
+
+					if removed {
+						self.references.ordered.by_package_score.insert((ancestor_entry as &Entry).into());
+					}
+				}
+			}
+
+			// descendants are not included in the package of this transaction anymore
+			for descendant_hash in self.get_descendants(h) {
+				if let Some(descendant_entry) = self.by_hash.get_mut(&descendant_hash) {
+					descendant_entry.ancestors.remove(h);
+				}
+			}
+
 			// remove from storage
 			self.references.remove(None, &self.by_hash, &entry);
 
@@ -451,21 +628,33 @@ impl Storage {
 		})
 	}
 
-	pub fn check_double_spend(&self, transaction: &Transaction) -> DoubleSpendCheckResult {
+	pub fn is_replaceable(&self, entry: &Entry) -> bool {
+		signals_replaceability(&entry.transaction)
+			|| entry.ancestors.iter().any(|ancestor_hash| {
+				self.by_hash
+					.get(ancestor_hash)
+					.is_some_and(|ancestor_entry| signals_replaceability(&ancestor_entry.transaction))
+			})
+	}
+
+	pub fn check_double_spend(&self, transaction: &Transaction, full_rbf: bool) -> DoubleSpendCheckResult {
 		let mut double_spends: HashSet<HashedOutPoint> = HashSet::new();
 		let mut dependent_spends: HashSet<HashedOutPoint> = HashSet::new();
+		let mut conflicts: HashSet<SHA256D> = HashSet::new();
+		let mut replaced: HashSet<SHA256D> = HashSet::new();
 
 		for input in &transaction.inputs {
 			// find transaction that spends the same output
 			let prevout: HashedOutPoint = input.previous_output.clone().into();
 			if let Some(entry_hash) = self.by_previous_output.get(&prevout).cloned() {
-				// check if this is final transaction. If so, that's a potential double-spend error
+				// check if this transaction could be replaced. If not, that's a potential double-spend error
 				let entry = self.by_hash.get(&entry_hash).expect("checked that it exists line above; qed");
-				if entry.transaction.is_final() {
+				if !full_rbf && !self.is_replaceable(entry) {
 					return DoubleSpendCheckResult::DoubleSpend(entry_hash, prevout.out_point.hash, prevout.out_point.index);
 				}
 				// else remember this double spend
 				double_spends.insert(prevout.clone());
+				conflicts.insert(entry_hash);
 				// and 'virtually' remove entry && all descendants from mempool
 				let mut queue: VecDeque<HashedOutPoint> = VecDeque::new();
 				queue.push_back(prevout);
@@ -476,6 +665,7 @@ impl Storage {
 							.by_hash
 							.get(&dependent_entry_hash)
 							.expect("checked that it exists line above; qed");
+						replaced.insert(dependent_entry_hash);
 						let dependent_outputs: Vec<_> = dependent_entry
 							.transaction
 							.outputs
@@ -499,9 +689,11 @@ impl Storage {
 		if double_spends.is_empty() {
 			DoubleSpendCheckResult::NoDoubleSpend
 		} else {
-			DoubleSpendCheckResult::NonFinalDoubleSpend(NonFinalDoubleSpendSet {
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(ReplaceableDoubleSpendSet {
 				double_spends,
 				dependent_spends,
+				conflicts,
+				replaced,
 			})
 		}
 	}
@@ -525,6 +717,39 @@ impl Storage {
 		Some(removed)
 	}
 
+	pub fn get_descendants(&self, h: &SHA256D) -> HashSet<SHA256D> {
+		let mut descendants: HashSet<SHA256D> = HashSet::new();
+		let mut queue: Vec<SHA256D> = vec![*h];
+		while let Some(hash) = queue.pop() {
+			if let Some(children) = self.references.by_input.get(&hash) {
+				for child in children {
+					if self.by_hash.contains_key(child) && descendants.insert(*child) {
+						queue.push(*child);
+					}
+				}
+			}
+		}
+		descendants
+	}
+
+	pub fn remove_with_descendants(&mut self, h: &SHA256D) -> Vec<IndexedTransaction> {
+		let entry = match self.remove_by_hash(h) {
+			Some(entry) => entry,
+			None => return Vec::new(),
+		};
+
+		let outputs_count = entry.transaction.outputs.len();
+		let mut removed = vec![IndexedTransaction::new(entry.hash, entry.transaction)];
+		for index in 0..outputs_count {
+			let prevout = OutPoint {
+				hash: *h,
+				index: index as u32,
+			};
+			removed.extend(self.remove_by_prevout(&prevout).unwrap_or_default());
+		}
+		removed
+	}
+
 	pub fn remove_by_parent_hash(&mut self, h: &SHA256D) -> Option<Vec<IndexedTransaction>> {
 		// this code will run only when ancestor transaction is inserted
 		// in memory pool after its descendants
@@ -683,7 +908,7 @@ impl OrderedReferenceStorage {
 
 impl HeapSizeOf for Storage {
 	fn heap_size_of_children(&self) -> usize {
-		self.by_hash.heap_size_of_children() + self.references.heap_size_of_children()
+		self.by_hash.heap_size_of_children() + self.by_witness_hash.heap_size_of_children() + self.references.heap_size_of_children()
 	}
 }
 
@@ -696,7 +921,6 @@ impl HeapSizeOf for ReferenceStorage {
 impl HeapSizeOf for OrderedReferenceStorage {
 	fn heap_size_of_children(&self) -> usize {
 		// HeapSizeOf is not implemented for BTreeSet => rough estimation here
-		use std::mem::size_of;
 		let len = self.by_storage_index.len();
 		len * (size_of::<ByTimestampOrderedEntry>() + size_of::<ByTransactionScoreOrderedEntry>() + size_of::<ByPackageScoreOrderedEntry>())
 	}
@@ -714,6 +938,13 @@ impl MemoryPool {
 		MemoryPool {
 			storage: Storage::new(),
 			accept_zero_fee_transactions: false,
+			full_rbf: false,
+			package_limits: PackageLimits::default(),
+			max_memory_usage: DEFAULT_MAX_MEMORY_USAGE,
+			expiry: DEFAULT_EXPIRY,
+			rolling_minimum_fee_rate: 0f64,
+			last_rolling_fee_update: 0,
+			virtual_fees: HashMap::new(),
 		}
 	}
 
@@ -722,19 +953,144 @@ impl MemoryPool {
 		self.accept_zero_fee_transactions = true;
 	}
 
-	/// Insert verified transaction to the `MemoryPool`
-	pub fn insert_verified<FC: MemoryPoolFeeCalculator>(&mut self, t: IndexedTransaction, fc: &FC) {
-		if let Some(entry) = self.make_entry(t, fc) {
-			let descendants = self.storage.remove_by_parent_hash(&entry.hash);
-			self.storage.insert(entry);
-			if let Some(descendants_iter) = descendants.map(|d| d.into_iter()) {
-				for descendant in descendants_iter {
-					if let Some(descendant_entry) = self.make_entry(descendant, fc) {
-						self.storage.insert(descendant_entry);
-					}
+	/// Allow replacement of transactions that do not signal replaceability.
+	pub fn set_full_rbf(&mut self, full_rbf: bool) {
+		self.full_rbf = full_rbf;
+	}
+
+	/// Set limits on in-pool ancestors and descendants of inserted transactions.
+	pub fn set_package_limits(&mut self, package_limits: PackageLimits) {
+		self.package_limits = package_limits;
+	}
+
+	/// Set max memory usage of the `MemoryPool`.
+	pub fn set_max_memory_usage(&mut self, max_memory_usage: usize) {
+		self.max_memory_usage = max_memory_usage;
+	}
+
+	/// Set time (in seconds) after which transactions are expired from the `MemoryPool`.
+	pub fn set_expiry(&mut self, expiry: u64) {
+		self.expiry = expiry;
+	}
+
+	/// Insert verified transaction to the `MemoryPool`.
+	/// Expired transactions are removed and the pool is trimmed to its max size afterwards.
+	/// Returns transactions, which have been removed from the pool during insertion. If the transaction
+	/// has been rejected, these are returned along with the error.
+	pub fn insert_verified<FC: MemoryPoolFeeCalculator>(
+		&mut self,
+		t: IndexedTransaction,
+		fc: &FC,
+	) -> Result<Vec<IndexedTransaction>, (TransactionError, Vec<IndexedTransaction>)> {
+		let now = RealTime.now().as_secs();
+		let hash = t.hash;
+		let entry = self.make_entry(t, fc, now).map_err(|err| (err, Vec::new()))?;
+		let mut removed = Vec::new();
+		let descendants = self.storage.remove_by_parent_hash(&entry.hash);
+		self.storage.insert(entry);
+		for descendant in descendants.into_iter().flatten() {
+			match self.make_entry(descendant.clone(), fc, now) {
+				Ok(descendant_entry) => self.storage.insert(descendant_entry),
+				Err(_) => removed.push(descendant),
+			}
+		}
+
+		removed.extend(self.expire(now));
+		removed.extend(self.trim_to_size(now));
+		if removed.iter().any(|transaction| transaction.hash == hash) {
+			removed.retain(|transaction| transaction.hash != hash);
+			return Err((TransactionError::MemoryPoolFull, removed));
+		}
+
+		Ok(removed)
+	}
+
+	/// Insert verified package of transactions to the `MemoryPool`.
+	/// Package must be topologically sorted. Transactions, which are already in the pool, are ignored and all
+	/// other package transactions are accepted together if their total fee rate is enough, even if some of
+	/// them (i.e. parents) are not paying enough on their own.
+	/// Returns transactions, which have been removed from the pool during insertion.
+	pub fn insert_verified_package<FC: MemoryPoolFeeCalculator>(
+		&mut self,
+		package: Vec<IndexedTransaction>,
+		fc: &FC,
+	) -> Result<Vec<IndexedTransaction>, PackageError> {
+		let now = RealTime.now().as_secs();
+		let mut inserted: Vec<SHA256D> = Vec::new();
+		let (mut package_fee, mut package_size) = (0u64, 0usize);
+		for t in package {
+			if self.storage.contains(&t.hash) {
+				continue;
+			}
+
+			let hash = t.hash;
+			match self.make_package_entry(t, fc, now) {
+				Ok(entry) => {
+					package_fee += entry.modified_fee();
+					package_size += entry.size;
+					inserted.push(hash);
+					self.storage.insert(entry);
+				}
+				Err(err) => {
+					self.remove_package(&inserted);
+					return Err(PackageError::Transaction(hash, err));
 				}
 			}
 		}
+
+		if !inserted.is_empty() && !self.is_acceptable_fee(package_fee, package_size, now) {
+			self.remove_package(&inserted);
+			return Err(PackageError::FeeRate(package_fee * 1000 / package_size as u64));
+		}
+
+		let mut removed = self.expire(now);
+		removed.extend(self.trim_to_size(now));
+		Ok(removed)
+	}
+
+	/// Removes transactions (and all their descendants) which have entered the pool more than `expiry` seconds ago.
+	pub fn expire(&mut self, now: u64) -> Vec<IndexedTransaction> {
+		let cutoff = now.saturating_sub(self.expiry);
+		let mut expired = Vec::new();
+		while let Some((time, hash)) = self.storage.by_entry_time.iter().next().copied() {
+			if time >= cutoff {
+				break;
+			}
+			expired.extend(self.storage.remove_with_descendants(&hash));
+		}
+		expired
+	}
+
+	/// Evicts transactions (and all their descendants) with the lowest package score until memory usage
+	/// is under the limit. Min fee rate is raised above the fee rate of every evicted package.
+	pub fn trim_to_size(&mut self, now: u64) -> Vec<IndexedTransaction> {
+		let mut evicted = Vec::new();
+		while self.storage.memory_usage > self.max_memory_usage {
+			let (hash, package_fee_rate) = match self.storage.references.ordered.by_package_score.iter().next_back() {
+				Some(entry) => {
+					let package_fee = (entry.package_miner_fee as i64 + entry.package_miner_virtual_fee).max(0) as u64;
+					(entry.hash, package_fee * 1000 / entry.package_size as u64)
+				}
+				None => break,
+			};
+
+			let removed_fee_rate = (package_fee_rate + INCREMENTAL_FEE_RATE) as f64;
+			self.rolling_minimum_fee_rate = self.rolling_minimum_fee_rate(now).max(removed_fee_rate);
+			self.last_rolling_fee_update = now;
+
+			evicted.extend(self.storage.remove_with_descendants(&hash));
+		}
+		evicted
+	}
+
+	/// Min fee rate (in satoshis per 1000 bytes) for transaction to be accepted to the `MemoryPool`.
+	pub fn minimum_fee_rate(&self, now: u64) -> u64 {
+		let rolling_minimum_fee_rate = self.rolling_minimum_fee_rate(now) as u64;
+		if rolling_minimum_fee_rate == 0 {
+			return 0;
+		}
+
+		rolling_minimum_fee_rate.max(INCREMENTAL_FEE_RATE)
 	}
 
 	/// Iterator over memory pool transactions according to specified strategy
@@ -745,6 +1101,7 @@ impl MemoryPool {
 	/// Removes single transaction by its hash.
 	/// All descendants remain in the pool.
 	pub fn remove_by_hash(&mut self, h: &SHA256D) -> Option<IndexedTransaction> {
+		self.virtual_fees.remove(h);
 		self.storage
 			.remove_by_hash(h)
 			.map(|entry| IndexedTransaction::new(entry.hash, entry.transaction))
@@ -752,7 +1109,131 @@ impl MemoryPool {
 
 	/// Checks if `transaction` spends some outputs, already spent by inpool transactions.
 	pub fn check_double_spend(&self, transaction: &Transaction) -> DoubleSpendCheckResult {
-		self.storage.check_double_spend(transaction)
+		self.storage.check_double_spend(transaction, self.full_rbf)
+	}
+
+	/// Checks that `transaction`, paying `fee`, could replace in-pool transactions from the `set` (BIP125)
+	/// without exceeding package limits.
+	pub fn check_replacement(&self, transaction: &Transaction, fee: u64, set: &ReplaceableDoubleSpendSet) -> Result<(), TransactionError> {
+		if set.replaced.len() > MAX_REPLACEMENT_EVICTIONS {
+			return Err(TransactionError::ReplacementEvictions(set.replaced.len()));
+		}
+
+		// replacement may only spend unconfirmed outputs that are already spent by replaced transactions
+		let conflicts: Vec<&Entry> = set.conflicts.iter().filter_map(|hash| self.storage.get_by_hash(hash)).collect();
+		let conflicts_parents: HashSet<SHA256D> = conflicts
+			.iter()
+			.flat_map(|entry| entry.transaction.inputs.iter().map(|input| input.previous_output.hash))
+			.collect();
+		for (index, input) in transaction.inputs.iter().enumerate() {
+			let parent_hash = &input.previous_output.hash;
+			if self.storage.contains(parent_hash) && !conflicts_parents.contains(parent_hash) {
+				return Err(TransactionError::ReplacementUnconfirmedInput(index));
+			}
+		}
+
+		// replacement must have greater fee rate than every directly replaced transaction
+		let size = transaction.serialized_size() as u64;
+		for entry in conflicts {
+			if fee * entry.size as u64 <= entry.modified_fee() * size {
+				return Err(TransactionError::ReplacementFeeRate(entry.hash));
+			}
+		}
+
+		// replacement must pay for all replaced transactions and for its own relay
+		let replaced_fee: u64 = set
+			.replaced
+			.iter()
+			.filter_map(|hash| self.storage.get_by_hash(hash))
+			.map(Entry::modified_fee)
+			.sum();
+		if fee < replaced_fee || (fee - replaced_fee) * 1000 < INCREMENTAL_FEE_RATE * size {
+			return Err(TransactionError::ReplacementFee);
+		}
+
+		// replacement must not exceed package limits once replaced transactions are removed
+		self.check_package_limits_after_removal(transaction, &set.replaced)
+	}
+
+	/// Checks that `transaction` won't exceed package limits, once inserted to the `MemoryPool`.
+	pub fn check_package_limits(&self, transaction: &Transaction) -> Result<(), TransactionError> {
+		self.check_package_limits_after_removal(transaction, &HashSet::new())
+	}
+
+	/// Checks that `transaction` won't exceed package limits, once `removed` transactions (i.e. transactions,
+	/// replaced by it) are removed from the `MemoryPool` and `transaction` is inserted.
+	pub fn check_package_limits_after_removal(
+		&self,
+		transaction: &Transaction,
+		removed: &HashSet<SHA256D>,
+	) -> Result<(), TransactionError> {
+		let size = self.get_transaction_size(transaction);
+		let ancestors = self.get_ancestors(transaction);
+		let ancestors: Vec<&Entry> = ancestors
+			.iter()
+			.filter(|hash| !removed.contains(*hash))
+			.filter_map(|hash| self.storage.get_by_hash(hash))
+			.collect();
+		let removed: Vec<&Entry> = removed.iter().filter_map(|hash| self.storage.get_by_hash(hash)).collect();
+
+		if ancestors.len() + 1 > self.package_limits.ancestors_count {
+			return Err(TransactionError::TooManyAncestors(ancestors.len()));
+		}
+
+		let ancestors_size = ancestors.iter().fold(size, |ancestors_size, entry| ancestors_size + entry.size);
+		if ancestors_size > self.package_limits.ancestors_size {
+			return Err(TransactionError::TooLargeAncestors(ancestors_size));
+		}
+
+		// CPFP carve-out: small transaction with single in-pool ancestor is allowed to exceed descendants limits
+		// => every party of two-party contract is able to bump the fee of shared transaction
+		let (descendants_count, descendants_size) = if ancestors.len() == 1 && size <= EXTRA_DESCENDANT_SIZE_LIMIT {
+			(
+				self.package_limits.descendants_count + 1,
+				self.package_limits.descendants_size + EXTRA_DESCENDANT_SIZE_LIMIT,
+			)
+		} else {
+			(self.package_limits.descendants_count, self.package_limits.descendants_size)
+		};
+
+		for entry in ancestors {
+			let (removed_count, removed_size) = removed
+				.iter()
+				.filter(|descendant| descendant.ancestors.contains(&entry.hash))
+				.fold((0, 0), |(count, size), descendant| (count + 1, size + descendant.size));
+			if entry.package_count - removed_count + 1 > descendants_count {
+				return Err(TransactionError::TooManyDescendants(entry.hash));
+			}
+			if entry.package_size - removed_size + size > descendants_size {
+				return Err(TransactionError::TooLargeDescendants(entry.hash));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns hashes of in-pool ancestors of the `MemoryPool` transaction.
+	pub fn ancestors(&self, hash: &SHA256D) -> Option<Vec<SHA256D>> {
+		self.storage
+			.get_by_hash(hash)
+			.map(|entry| entry.ancestors.iter().cloned().collect())
+	}
+
+	/// Returns hashes of in-pool descendants of the `MemoryPool` transaction.
+	pub fn descendants(&self, hash: &SHA256D) -> Option<Vec<SHA256D>> {
+		if !self.storage.contains(hash) {
+			return None;
+		}
+
+		Some(self.storage.get_descendants(hash).into_iter().collect())
+	}
+
+	/// Checks if in-pool transaction signals replaceability (BIP125), either explicitly
+	/// or through its in-pool ancestors.
+	pub fn is_replaceable(&self, hash: &SHA256D) -> bool {
+		self.storage
+			.get_by_hash(hash)
+			.is_some_and(|entry| self.storage.is_replaceable(entry))
 	}
 
 	/// Removes transaction (and all its descendants) which has spent given output
@@ -790,11 +1271,37 @@ impl MemoryPool {
 		self.storage.remove_n_with_strategy(n, strategy)
 	}
 
-	/// Set miner virtual fee for transaction
+	/// Set miner virtual fee for transaction.
+	/// If transaction is not in the pool yet, virtual fee is applied when it is inserted.
 	pub fn set_virtual_fee(&mut self, h: &SHA256D, virtual_fee: i64) {
+		match virtual_fee {
+			0 => self.virtual_fees.remove(h),
+			_ => self.virtual_fees.insert(*h, virtual_fee),
+		};
 		self.storage.set_virtual_fee(h, virtual_fee)
 	}
 
+	/// Set time when transaction has entered the memory pool (used when the pool is restored from the dump)
+	pub fn set_entry_time(&mut self, h: &SHA256D, time: u64) {
+		self.storage.set_time(h, time)
+	}
+
+	/// Returns dump of all memory pool transactions, sorted so that every transaction follows its in-pool ancestors
+	pub fn dump(&self) -> MemoryPoolDump {
+		let mut entries: Vec<&Entry> = self.storage.by_hash.values().collect();
+		entries.sort_by_key(|entry| (entry.ancestors.len(), entry.time, entry.hash));
+		MemoryPoolDump {
+			transactions: entries
+				.into_iter()
+				.map(|entry| DumpedTransaction {
+					transaction: entry.transaction.clone(),
+					time: entry.time,
+					virtual_fee: entry.miner_virtual_fee,
+				})
+				.collect(),
+		}
+	}
+
 	/// Get transaction by hash
 	pub fn get(&self, hash: &SHA256D) -> Option<&Transaction> {
 		self.storage.get_by_hash(hash).map(|entry| &entry.transaction)
@@ -805,52 +1312,157 @@ impl MemoryPool {
 		self.storage.contains(hash)
 	}
 
+	/// Returns hash of the transaction with given witness hash, if it is in the mempool
+	pub fn hash_by_witness_hash(&self, witness_hash: &SHA256D) -> Option<SHA256D> {
+		self.storage.hash_by_witness_hash(witness_hash)
+	}
+
 	/// Returns information on `MemoryPool` (as in GetMemPoolInfo RPC)
 	/// https://bitcoin.org/en/developer-reference#getmempoolinfo
 	pub fn information(&self) -> Information {
 		Information {
 			transactions_count: self.storage.by_hash.len(),
 			transactions_size_in_bytes: self.storage.transactions_size_in_bytes,
+			memory_usage: self.storage.memory_usage,
+			max_memory_usage: self.max_memory_usage,
+			minimum_fee_rate: self.minimum_fee_rate(RealTime.now().as_secs()),
 		}
 	}
 
+	/// Returns information on single `MemoryPool` transaction (as in GetMemPoolEntry RPC)
+	pub fn entry_information(&self, hash: &SHA256D) -> Option<EntryInformation> {
+		self.storage.get_by_hash(hash).map(|entry| {
+			let mut depends: Vec<SHA256D> = entry
+				.transaction
+				.inputs
+				.iter()
+				.map(|input| input.previous_output.hash)
+				.filter(|parent_hash| self.storage.contains(parent_hash))
+				.collect();
+			depends.sort();
+			depends.dedup();
+
+			let ancestors: Vec<&Entry> = entry.ancestors.iter().filter_map(|hash| self.storage.get_by_hash(hash)).collect();
+
+			EntryInformation {
+				hash: entry.hash,
+				witness_hash: entry.transaction.witness_hash(),
+				size: entry.size,
+				weight: entry.size * 3 + entry.transaction.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS),
+				time: entry.time,
+				fee: entry.miner_fee,
+				modified_fee: entry.modified_fee(),
+				ancestors_count: entry.ancestors.len(),
+				ancestors_size: ancestors.iter().fold(entry.size, |size, ancestor| size + ancestor.size),
+				ancestors_modified_fee: ancestors
+					.iter()
+					.fold(entry.modified_fee(), |fee, ancestor| fee + ancestor.modified_fee()),
+				package_count: entry.package_count,
+				package_size: entry.package_size,
+				package_modified_fee: (entry.package_miner_fee as i64 + entry.package_miner_virtual_fee).max(0) as u64,
+				depends,
+				bip125_replaceable: self.storage.is_replaceable(entry),
+			}
+		})
+	}
+
 	/// Returns TXIDs of all transactions in `MemoryPool` (as in GetRawMemPool RPC)
 	/// https://bitcoin.org/en/developer-reference#getrawmempool
 	pub fn get_transactions_ids(&self) -> Vec<SHA256D> {
 		self.storage.get_transactions_ids()
 	}
 
+	/// Returns WTXIDs of all transactions in `MemoryPool`
+	pub fn get_transactions_witness_ids(&self) -> Vec<SHA256D> {
+		self.storage.by_witness_hash.keys().cloned().collect()
+	}
+
 	/// Returns true if output was spent
 	pub fn is_spent(&self, prevout: &OutPoint) -> bool {
 		self.storage.is_output_spent(prevout)
 	}
 
-	fn make_entry<FC: MemoryPoolFeeCalculator>(&mut self, t: IndexedTransaction, fc: &FC) -> Option<Entry> {
+	fn make_entry<FC: MemoryPoolFeeCalculator>(&mut self, t: IndexedTransaction, fc: &FC, now: u64) -> Result<Entry, TransactionError> {
+		let entry = self.make_package_entry(t, fc, now)?;
+		if !self.is_acceptable_fee(entry.modified_fee(), entry.size, now) {
+			return Err(TransactionError::MemoryPoolFeeRate(entry.modified_fee() * 1000 / entry.size as u64));
+		}
+
+		Ok(entry)
+	}
+
+	/// Makes entry without checking its fee. Fee is checked for the whole package instead.
+	fn make_package_entry<FC: MemoryPoolFeeCalculator>(
+		&mut self,
+		t: IndexedTransaction,
+		fc: &FC,
+		now: u64,
+	) -> Result<Entry, TransactionError> {
+		// do not accept too long chains of in-pool transactions
+		self.check_package_limits(&t.raw)?;
+
 		let ancestors = self.get_ancestors(&t.raw);
 		let size = self.get_transaction_size(&t.raw);
 		let storage_index = self.get_storage_index();
 		let miner_fee = fc.calculate(self, &t.raw);
+		let miner_virtual_fee = self.virtual_fees.get(&t.hash).cloned().unwrap_or_default();
 
-		// do not accept any transactions that have negative OR zero fee
-		if !self.accept_zero_fee_transactions && miner_fee == 0 {
-			return None;
-		}
-
-		Some(Entry {
+		Ok(Entry {
 			transaction: t.raw,
 			hash: t.hash,
 			ancestors,
 			storage_index,
+			time: now,
 			size,
 			miner_fee,
-			miner_virtual_fee: 0,
+			miner_virtual_fee,
 			// following fields are also updated when inserted to storage
+			package_count: 1,
 			package_size: size,
 			package_miner_fee: miner_fee,
-			package_miner_virtual_fee: 0,
+			package_miner_virtual_fee: miner_virtual_fee,
 		})
 	}
 
+	fn is_acceptable_fee(&self, fee: u64, size: usize, now: u64) -> bool {
+		// do not accept any transactions that have negative OR zero fee
+		if !self.accept_zero_fee_transactions && fee == 0 {
+			return false;
+		}
+
+		// do not accept transactions that would be evicted right away
+		fee * 1000 >= self.minimum_fee_rate(now) * size as u64
+	}
+
+	/// Removes partially inserted package, child first.
+	fn remove_package(&mut self, inserted: &[SHA256D]) {
+		for hash in inserted.iter().rev() {
+			self.storage.remove_by_hash(hash);
+		}
+	}
+
+	/// Rolling min fee rate decays faster when the pool is far from being full.
+	fn rolling_minimum_fee_rate(&self, now: u64) -> f64 {
+		if self.rolling_minimum_fee_rate == 0f64 {
+			return 0f64;
+		}
+
+		let mut halflife = ROLLING_FEE_HALFLIFE;
+		if self.storage.memory_usage < self.max_memory_usage / 4 {
+			halflife /= 4;
+		} else if self.storage.memory_usage < self.max_memory_usage / 2 {
+			halflife /= 2;
+		}
+
+		let elapsed = now.saturating_sub(self.last_rolling_fee_update);
+		let rolling_minimum_fee_rate = self.rolling_minimum_fee_rate * 0.5f64.powf(elapsed as f64 / halflife as f64);
+		if rolling_minimum_fee_rate < INCREMENTAL_FEE_RATE as f64 / 2f64 {
+			return 0f64;
+		}
+
+		rolling_minimum_fee_rate
+	}
+
 	fn get_ancestors(&self, t: &Transaction) -> HashSet<SHA256D> {
 		let mut ancestors: HashSet<SHA256D> = HashSet::new();
 		let ancestors_entries = t
@@ -976,16 +1588,22 @@ pub mod tests {
 	extern crate test_data;
 
 	use self::test_data::{ChainBuilder, TransactionBuilder};
-	use super::{DoubleSpendCheckResult, MemoryPool, OrderingStrategy};
+	use super::{
+		DoubleSpendCheckResult, MemoryPool, OrderingStrategy, PackageLimits, DEFAULT_EXPIRY, INCREMENTAL_FEE_RATE,
+		MAX_REPLACEMENT_EVICTIONS, ROLLING_FEE_HALFLIFE,
+	};
 	use crate::fee::NonZeroFeeCalculator;
-	use bitcrypto::SHA256D;
+	use bitcrypto::{dhash256, SHA256D};
 	use chain::{OutPoint, Transaction};
 	use heapsize::HeapSizeOf;
+	use primitives::time::{RealTime, Time};
+	use ser::Serializable;
+	use verification::{PackageError, TransactionError};
 
 	fn to_memory_pool(chain: &mut ChainBuilder) -> MemoryPool {
 		let mut pool = MemoryPool::new();
 		for transaction in chain.transactions.iter().cloned() {
-			pool.insert_verified(transaction.into(), &NonZeroFeeCalculator);
+			pool.insert_verified(transaction.into(), &NonZeroFeeCalculator).unwrap();
 		}
 		pool
 	}
@@ -1000,11 +1618,11 @@ pub mod tests {
 
 		let size1 = pool.heap_size_of_children();
 
-		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator);
+		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator).unwrap();
 		let size2 = pool.heap_size_of_children();
 		assert!(size2 > size1);
 
-		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator);
+		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator).unwrap();
 		let size3 = pool.heap_size_of_children();
 		assert!(size3 > size2);
 	}
@@ -1012,11 +1630,11 @@ pub mod tests {
 	#[test]
 	fn test_memory_pool_insert_same_transaction() {
 		let mut pool = MemoryPool::new();
-		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator);
+		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator).unwrap();
 		assert_eq!(pool.get_transactions_ids().len(), 1);
 
 		// insert the same transaction again
-		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator);
+		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator).unwrap();
 		assert_eq!(pool.get_transactions_ids().len(), 1);
 	}
 
@@ -1026,7 +1644,7 @@ pub mod tests {
 		assert_eq!(pool.read_with_strategy(OrderingStrategy::ByTimestamp), None);
 		assert_eq!(pool.read_n_with_strategy(100, OrderingStrategy::ByTimestamp), Vec::<SHA256D>::new());
 
-		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator);
+		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator).unwrap();
 		assert_eq!(pool.read_with_strategy(OrderingStrategy::ByTimestamp), Some(default_tx().hash()));
 		assert_eq!(
 			pool.read_n_with_strategy(100, OrderingStrategy::ByTimestamp),
@@ -1045,12 +1663,12 @@ pub mod tests {
 		assert_eq!(pool.remove_with_strategy(OrderingStrategy::ByTimestamp), None);
 		assert_eq!(pool.remove_n_with_strategy(100, OrderingStrategy::ByTimestamp), vec![]);
 
-		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator);
+		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator).unwrap();
 		let removed = pool.remove_with_strategy(OrderingStrategy::ByTimestamp);
 		assert!(removed.is_some());
 		assert_eq!(removed.unwrap(), default_tx().into());
 
-		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator);
+		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator).unwrap();
 		let removed = pool.remove_n_with_strategy(100, OrderingStrategy::ByTimestamp);
 		assert_eq!(removed.len(), 1);
 		assert_eq!(removed[0], default_tx().into());
@@ -1063,7 +1681,7 @@ pub mod tests {
 	fn test_memory_pool_remove_by_hash() {
 		let mut pool = MemoryPool::new();
 
-		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator);
+		pool.insert_verified(default_tx().into(), &NonZeroFeeCalculator).unwrap();
 		assert_eq!(pool.get_transactions_ids().len(), 1);
 
 		// remove and check remaining transactions
@@ -1077,6 +1695,23 @@ pub mod tests {
 		assert_eq!(pool.get_transactions_ids().len(), 0);
 	}
 
+	#[test]
+	fn test_memory_pool_hash_by_witness_hash() {
+		let mut pool = MemoryPool::new();
+		let mut transaction: Transaction = TransactionBuilder::with_default_input(0).add_output(1).into();
+		transaction.inputs[0].script_witness = vec![vec![1, 2, 3].into()];
+		let witness_hash = transaction.witness_hash();
+		assert_ne!(witness_hash, transaction.hash());
+
+		pool.insert_verified(transaction.clone().into(), &NonZeroFeeCalculator).unwrap();
+		assert_eq!(pool.hash_by_witness_hash(&witness_hash), Some(transaction.hash()));
+		assert_eq!(pool.hash_by_witness_hash(&transaction.hash()), None);
+		assert_eq!(pool.get_transactions_witness_ids(), vec![witness_hash]);
+
+		pool.remove_by_hash(&transaction.hash());
+		assert_eq!(pool.hash_by_witness_hash(&witness_hash), None);
+	}
+
 	#[test]
 	fn test_memory_pool_insert_parent_after_child() {
 		let chain = &mut ChainBuilder::new();
@@ -1091,9 +1726,9 @@ pub mod tests {
 
 		// insert child, then parent
 		let mut pool = MemoryPool::new();
-		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator); // timestamp 0
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator); // timestamp 1
-		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator); // timestamp 2
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator).unwrap(); // timestamp 0
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap(); // timestamp 1
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap(); // timestamp 2
 
 		// check that parent transaction was removed before child trnasaction
 		let transactions = pool.remove_n_with_strategy(3, OrderingStrategy::ByTimestamp);
@@ -1146,7 +1781,7 @@ pub mod tests {
 		assert_eq!(pool.get_transactions_ids().len(), 2);
 
 		// insert child transaction back to the pool & assert transactions are removed in correct order
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
 		let transactions = pool.remove_n_with_strategy(3, OrderingStrategy::ByTransactionScore);
 		assert_eq!(transactions.len(), 3);
 		assert_eq!(transactions[0], chain.at(0).into());
@@ -1166,7 +1801,8 @@ pub mod tests {
 
 		let mut transactions_size = 0;
 		for transaction_index in 0..4 {
-			pool.insert_verified(chain.at(transaction_index).into(), &NonZeroFeeCalculator);
+			pool.insert_verified(chain.at(transaction_index).into(), &NonZeroFeeCalculator)
+				.unwrap();
 			transactions_size += chain.size(transaction_index);
 
 			let info = pool.information();
@@ -1270,8 +1906,8 @@ pub mod tests {
 		// <
 		// score({ transaction2 }) = 35/60
 		let expected = vec![chain.hash(2), chain.hash(0)];
-		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator).unwrap();
 		assert_eq!(pool.read_n_with_strategy(2, OrderingStrategy::ByPackageScore), expected);
 
 		// { transaction0, transaction1 } now have bigger score than { transaction2 }:
@@ -1280,7 +1916,7 @@ pub mod tests {
 		// score({ transaction2 }) = 35/60 ~ 0.583
 		// => chain1 is boosted
 		// => so transaction with lesser individual score (but with bigger package score) is mined first
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![chain.hash(0), chain.hash(1), chain.hash(2)];
 		assert_eq!(pool.read_n_with_strategy(3, OrderingStrategy::ByPackageScore), expected);
 
@@ -1289,7 +1925,7 @@ pub mod tests {
 		// >
 		// score({ transaction2, transaction3 }) = (35 + 10) / 120 ~ 0.375
 		// => chain2 is not boosted
-		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![chain.hash(0), chain.hash(1), chain.hash(2), chain.hash(3)];
 		assert_eq!(pool.read_n_with_strategy(4, OrderingStrategy::ByPackageScore), expected);
 
@@ -1298,7 +1934,7 @@ pub mod tests {
 		// <
 		// score({ transaction2, transaction3, transaction4 }) = (35 + 10 + 100) / 180 ~ 0.806
 		// => chain2 is boosted
-		pool.insert_verified(chain.at(4).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(4).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![chain.hash(2), chain.hash(3), chain.hash(4), chain.hash(0), chain.hash(1)];
 		assert_eq!(pool.read_n_with_strategy(5, OrderingStrategy::ByPackageScore), expected);
 
@@ -1330,15 +1966,15 @@ pub mod tests {
 		// transaction0 is not linked to the transaction2
 		// => they are in separate chains now
 		// => transaction3 has greater score than both of these chains
-		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![chain.hash(3), chain.hash(0), chain.hash(2)];
 		assert_eq!(pool.read_n_with_strategy(3, OrderingStrategy::ByPackageScore), expected);
 
 		// insert the missing transaction to link together chain1
 		// => it now will have better score than chain2
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![chain.hash(0), chain.hash(1), chain.hash(3), chain.hash(2)];
 		assert_eq!(pool.read_n_with_strategy(4, OrderingStrategy::ByPackageScore), expected);
 	}
@@ -1392,9 +2028,9 @@ pub mod tests {
 		// insert level1 + level2. There are two chains:
 		// score({ transaction3, transaction5 }) = 40 + 60
 		// score({ transaction4, transaction5 }) = 50 + 60
-		pool.insert_verified(chain.at(5).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(4).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(5).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(4).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![chain.hash(4), chain.hash(3), chain.hash(5)];
 		assert_eq!(pool.read_n_with_strategy(3, OrderingStrategy::ByTransactionScore), expected);
 		assert_eq!(pool.read_n_with_strategy(3, OrderingStrategy::ByPackageScore), expected);
@@ -1403,7 +2039,7 @@ pub mod tests {
 		// score({ transaction3, transaction5 }) = 40 + 60
 		// score({ transaction4, transaction5 }) = 50 + 60
 		// score({ transaction2, transaction5 }) = 30 + 60
-		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![chain.hash(4), chain.hash(3), chain.hash(2), chain.hash(5)];
 		assert_eq!(pool.read_n_with_strategy(4, OrderingStrategy::ByTransactionScore), expected);
 		assert_eq!(pool.read_n_with_strategy(4, OrderingStrategy::ByPackageScore), expected);
@@ -1413,7 +2049,7 @@ pub mod tests {
 		// score({ transaction1, transaction4, transaction5 }) = 20 + 50 + 60 / 3 ~ 0.333
 		// score({ transaction2, transaction5 }) = 30 + 60 / 2 = 0.45
 		// but second chain will be removed first anyway because previous #1 ({ transaction4, transaction5}) now depends on level 01
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![chain.hash(3), chain.hash(2), chain.hash(1), chain.hash(4), chain.hash(5)];
 		assert_eq!(pool.read_n_with_strategy(5, OrderingStrategy::ByTransactionScore), expected);
 		assert_eq!(pool.read_n_with_strategy(5, OrderingStrategy::ByPackageScore), expected);
@@ -1423,7 +2059,7 @@ pub mod tests {
 		// score({ transaction0, transaction4, transaction5 }) = (10 + 50 + 60) / (60 + 60 + 142) ~ 0.458
 		// score({ transaction1, transaction3, transaction5 }) = (20 + 50 + 60) / (60 + 60 + 142) ~ 0.496
 		// score({ transaction2, transaction5 }) = (30 + 60) / (60 + 142) ~ 0.445
-		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
 		let expected = vec![
 			chain.hash(2),
 			chain.hash(1),
@@ -1464,7 +2100,7 @@ pub mod tests {
 			index: 0,
 		}));
 
-		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
 		assert!(!pool.is_spent(&OutPoint {
 			hash: chain.hash(0),
 			index: 0,
@@ -1478,7 +2114,7 @@ pub mod tests {
 			index: 0,
 		}));
 
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
 		assert!(!pool.is_spent(&OutPoint {
 			hash: chain.hash(0),
 			index: 0,
@@ -1492,7 +2128,7 @@ pub mod tests {
 			index: 0,
 		}));
 
-		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator).unwrap();
 		assert!(pool.is_spent(&OutPoint {
 			hash: chain.hash(0),
 			index: 0,
@@ -1540,10 +2176,10 @@ pub mod tests {
 			.store(chain); // transaction3
 		let mut pool = MemoryPool::new();
 
-		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator).unwrap();
 		assert_eq!(pool.information().transactions_count, 4);
 
 		assert_eq!(
@@ -1568,7 +2204,7 @@ pub mod tests {
 			.set_input(&chain.at(0), 0)
 			.add_output(20)
 			.lock()
-			.store(chain) // nonfinal: t0[0] -> t1
+			.store(chain) // replaceable: t0[0] -> t1
 			.reset()
 			.set_input(&chain.at(1), 0)
 			.add_output(30)
@@ -1580,7 +2216,7 @@ pub mod tests {
 			.reset()
 			.set_input(&chain.at(0), 1)
 			.add_output(50)
-			.store(chain) // final: t0[1] -> t4
+			.store(chain) // non-replaceable: t0[1] -> t4
 			.reset()
 			.set_input(&chain.at(0), 1)
 			.add_output(60)
@@ -1591,12 +2227,12 @@ pub mod tests {
 			.store(chain); // no double spend: t0[2] -> t6
 
 		let mut pool = MemoryPool::new();
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
-		pool.insert_verified(chain.at(4).into(), &NonZeroFeeCalculator);
-		// when output is spent by nonfinal transaction
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(4).into(), &NonZeroFeeCalculator).unwrap();
+		// when output is spent by replaceable transaction
 		match pool.check_double_spend(&chain.at(3)) {
-			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => {
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(set) => {
 				assert_eq!(set.double_spends.len(), 1);
 				assert!(set.double_spends.contains(&chain.at(1).inputs[0].previous_output.clone().into()));
 				assert_eq!(set.dependent_spends.len(), 2);
@@ -1617,7 +2253,7 @@ pub mod tests {
 			}
 			_ => panic!("unexpected"),
 		}
-		// when output is spent by final transaction
+		// when output is spent by non-replaceable transaction
 		match pool.check_double_spend(&chain.at(5)) {
 			DoubleSpendCheckResult::DoubleSpend(inpool_hash, prev_hash, prev_index) => {
 				assert_eq!(inpool_hash, chain.at(4).hash());
@@ -1645,18 +2281,18 @@ pub mod tests {
 			.add_output(30)
 			.add_output(50)
 			.lock()
-			.store(chain) // nonfinal: t0[0] -> t1
+			.store(chain) // replaceable: t0[0] -> t1
 			.reset()
 			.set_input(&chain.at(0), 0)
 			.add_output(40)
 			.store(chain); // good replacement: t0[0] -> t2
 
 		let mut pool = MemoryPool::new();
-		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
 
-		// when output is spent by nonfinal transaction
+		// when output is spent by replaceable transaction
 		match pool.check_double_spend(&chain.at(2)) {
-			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => {
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(set) => {
 				assert_eq!(set.double_spends.len(), 1);
 				assert!(set.double_spends.contains(&chain.at(1).inputs[0].previous_output.clone().into()));
 				assert_eq!(set.dependent_spends.len(), 3);
@@ -1693,8 +2329,428 @@ pub mod tests {
 		let out1 = tx1.inputs[0].previous_output.clone();
 		let out2 = tx2.inputs[0].previous_output.clone();
 		let mut memory_pool = MemoryPool::new();
-		memory_pool.insert_verified(tx1.into(), &NonZeroFeeCalculator);
+		memory_pool.insert_verified(tx1.into(), &NonZeroFeeCalculator).unwrap();
 		assert!(memory_pool.is_spent(&out1));
 		assert!(!memory_pool.is_spent(&out2));
 	}
+
+	#[test]
+	fn test_memory_pool_full_returns_removed_transactions() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_default_input(0).set_output(10).store(chain);
+		TransactionBuilder::with_default_input(1).set_output(30).store(chain);
+		TransactionBuilder::with_default_input(2).set_output(20).store(chain);
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
+		let max_memory_usage = pool.information().memory_usage;
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+		pool.set_max_memory_usage(max_memory_usage);
+
+		// transaction 0 is evicted first, but transaction 2 still doesn't fit => it is rejected
+		let (err, removed) = pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator).unwrap_err();
+		assert_eq!(err, TransactionError::MemoryPoolFull);
+		assert_eq!(removed.into_iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![chain.hash(0)]);
+		assert_eq!(pool.get_transactions_ids(), vec![chain.hash(1)]);
+	}
+
+	#[test]
+	fn test_memory_pool_trim_to_size() {
+		let chain = &mut ChainBuilder::new();
+		#[rustfmt::skip]
+		TransactionBuilder::with_default_input(0).set_output(10).store(chain)
+			.into_input(0).set_output(5).store(chain);
+		TransactionBuilder::with_default_input(1).set_output(30).store(chain);
+		TransactionBuilder::with_default_input(2).set_output(20).store(chain);
+		let mut pool = to_memory_pool(chain);
+		assert_eq!(pool.minimum_fee_rate(RealTime.now().as_secs()), 0);
+
+		// package of transactions 0 && 1 has the lowest score => evicted
+		let now = RealTime.now().as_secs();
+		pool.set_max_memory_usage(pool.information().memory_usage - 1);
+		let evicted: Vec<SHA256D> = pool.trim_to_size(now).into_iter().map(|tx| tx.hash).collect();
+		assert_eq!(evicted, vec![chain.hash(0), chain.hash(1)]);
+		assert_eq!(pool.get_transactions_ids().len(), 2);
+
+		// min fee rate is raised above the evicted package fee rate
+		let package_fee = 200_000_015;
+		let package_fee_rate = package_fee * 1000 / (chain.size(0) + chain.size(1)) as u64 + INCREMENTAL_FEE_RATE;
+		assert_eq!(pool.minimum_fee_rate(now), package_fee_rate);
+
+		// => transactions with lesser fee rate are not accepted anymore
+		let fee_rate = 100_000_010 * 1000 / chain.size(0) as u64;
+		assert_eq!(
+			pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator),
+			Err((TransactionError::MemoryPoolFeeRate(fee_rate), vec![]))
+		);
+		assert!(!pool.contains(&chain.hash(0)));
+
+		// ...unless virtual fee has been set for them before insertion
+		let virtual_fee = (package_fee_rate * chain.size(0) as u64 / 1000) as i64 + 1;
+		pool.set_virtual_fee(&chain.hash(0), virtual_fee);
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+		assert_eq!(
+			pool.entry_information(&chain.hash(0)).unwrap().modified_fee,
+			100_000_010 + virtual_fee as u64
+		);
+
+		// min fee rate is decaying over time
+		pool.set_max_memory_usage(pool.information().memory_usage);
+		assert_eq!(pool.minimum_fee_rate(now + ROLLING_FEE_HALFLIFE), package_fee_rate / 2);
+		assert_eq!(pool.minimum_fee_rate(now + 100 * ROLLING_FEE_HALFLIFE), 0);
+	}
+
+	#[test]
+	fn test_memory_pool_expire() {
+		let chain = &mut ChainBuilder::new();
+		#[rustfmt::skip]
+		TransactionBuilder::with_output(10).store(chain)
+			.into_input(0).add_output(20).store(chain);
+		let mut pool = to_memory_pool(chain);
+
+		let now = RealTime.now().as_secs();
+		assert!(pool.expire(now).is_empty());
+		assert_eq!(pool.get_transactions_ids().len(), 2);
+
+		let expired: Vec<SHA256D> = pool.expire(now + DEFAULT_EXPIRY + 1).into_iter().map(|tx| tx.hash).collect();
+		assert_eq!(expired, vec![chain.hash(0), chain.hash(1)]);
+		assert_eq!(pool.get_transactions_ids().len(), 0);
+		assert_eq!(pool.information().memory_usage, 0);
+	}
+
+	#[test]
+	fn test_memory_pool_dump_and_restore() {
+		let chain = &mut ChainBuilder::new();
+		#[rustfmt::skip]
+		TransactionBuilder::with_output(10).store(chain)
+			.into_input(0).add_output(20).store(chain);
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+
+		let now = RealTime.now().as_secs();
+		pool.set_entry_time(&chain.hash(0), now - 100);
+		pool.set_virtual_fee(&chain.hash(1), 50);
+
+		// parent goes first, even though child has been inserted first
+		let dump = pool.dump();
+		let dumped: Vec<(SHA256D, u64, i64)> = dump
+			.transactions
+			.iter()
+			.map(|tx| (tx.transaction.hash(), tx.time, tx.virtual_fee))
+			.collect();
+		assert_eq!(dumped[0], (chain.hash(0), now - 100, 0));
+		assert_eq!(dumped[1].0, chain.hash(1));
+		assert_eq!(dumped[1].2, 50);
+
+		let mut restored = MemoryPool::new();
+		for tx in dump.transactions {
+			let hash = tx.transaction.hash();
+			restored.insert_verified(tx.transaction.into(), &NonZeroFeeCalculator).unwrap();
+			restored.set_entry_time(&hash, tx.time);
+			restored.set_virtual_fee(&hash, tx.virtual_fee);
+		}
+		assert_eq!(restored.entry_information(&chain.hash(0)), pool.entry_information(&chain.hash(0)));
+		assert_eq!(restored.entry_information(&chain.hash(1)), pool.entry_information(&chain.hash(1)));
+
+		// restored entry time is used for expiration
+		restored.set_entry_time(&chain.hash(0), now - DEFAULT_EXPIRY - 1);
+		assert_eq!(restored.expire(now).len(), 2);
+	}
+
+	#[test]
+	fn test_memory_pool_full_rbf() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.into_input(0)
+			.set_output(20)
+			.store(chain) // non-replaceable: t0[0] -> t1
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.set_output(30)
+			.store(chain); // replacement: t0[0] -> t2
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator).unwrap();
+		assert!(!pool.is_replaceable(&chain.hash(1)));
+		match pool.check_double_spend(&chain.at(2)) {
+			DoubleSpendCheckResult::DoubleSpend(inpool_hash, _, _) => assert_eq!(inpool_hash, chain.hash(1)),
+			_ => panic!("unexpected"),
+		}
+
+		pool.set_full_rbf(true);
+		match pool.check_double_spend(&chain.at(2)) {
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(set) => assert_eq!(set.conflicts, vec![chain.hash(1)].into_iter().collect()),
+			_ => panic!("unexpected"),
+		}
+	}
+
+	#[test]
+	fn test_memory_pool_inherited_replaceability() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_default_input(0)
+			.set_output(10)
+			.lock()
+			.store(chain) // replaceable: t0
+			.into_input(0)
+			.set_output(20)
+			.store(chain); // replaceable, because of t0: t0[0] -> t1
+
+		let mut pool = to_memory_pool(chain);
+		assert!(pool.is_replaceable(&chain.hash(0)));
+		assert!(pool.is_replaceable(&chain.hash(1)));
+		assert!(pool.entry_information(&chain.hash(1)).unwrap().bip125_replaceable);
+		assert_eq!(pool.entry_information(&chain.hash(1)).unwrap().depends, vec![chain.hash(0)]);
+
+		// once t0 is mined, t1 is not replaceable anymore
+		pool.remove_by_hash(&chain.hash(0));
+		assert!(!pool.is_replaceable(&chain.hash(1)));
+	}
+
+	#[test]
+	fn test_memory_pool_check_replacement() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.set_output(20)
+			.lock()
+			.store(chain) // replaceable: t0[0] -> t1
+			.into_input(0)
+			.set_output(30)
+			.store(chain) // descendant: t0[0] -> t1[0] -> t2
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.set_output(40)
+			.store(chain) // replacement: t0[0] -> t3
+			.reset()
+			.add_default_input(7)
+			.set_output(50)
+			.store(chain) // unrelated: t4
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.add_input(&chain.at(4), 0)
+			.set_output(60)
+			.store(chain); // replacement with new unconfirmed input: t0[0] + t4[0] -> t5
+
+		let mut pool = MemoryPool::new();
+		for index in &[0, 1, 2, 4] {
+			pool.insert_verified(chain.at(*index).into(), &NonZeroFeeCalculator).unwrap();
+		}
+		let set = match pool.check_double_spend(&chain.at(3)) {
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(set) => set,
+			_ => panic!("unexpected"),
+		};
+		assert_eq!(set.replaced, vec![chain.hash(1), chain.hash(2)].into_iter().collect());
+
+		// fees of replaced transactions (as computed by NonZeroFeeCalculator)
+		let replaced_fee = 100_000_020 + 100_000_030;
+		let relay_fee = INCREMENTAL_FEE_RATE * chain.at(3).serialized_size() as u64 / 1000;
+		assert_eq!(
+			pool.check_replacement(&chain.at(3), replaced_fee, &set),
+			Err(TransactionError::ReplacementFee)
+		);
+		assert_eq!(pool.check_replacement(&chain.at(3), replaced_fee + relay_fee, &set), Ok(()));
+		assert_eq!(
+			pool.check_replacement(&chain.at(3), 100_000_000, &set),
+			Err(TransactionError::ReplacementFeeRate(chain.hash(1)))
+		);
+
+		// package limits are checked as if replaced transactions are already removed
+		pool.set_package_limits(PackageLimits {
+			descendants_count: 1,
+			..Default::default()
+		});
+		assert_eq!(
+			pool.check_package_limits(&chain.at(3)),
+			Err(TransactionError::TooManyDescendants(chain.hash(0)))
+		);
+		assert_eq!(pool.check_replacement(&chain.at(3), replaced_fee + relay_fee, &set), Ok(()));
+		pool.set_package_limits(PackageLimits {
+			ancestors_count: 1,
+			..Default::default()
+		});
+		assert_eq!(
+			pool.check_replacement(&chain.at(3), replaced_fee + relay_fee, &set),
+			Err(TransactionError::TooManyAncestors(1))
+		);
+		pool.set_package_limits(PackageLimits::default());
+
+		let set = match pool.check_double_spend(&chain.at(5)) {
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(set) => set,
+			_ => panic!("unexpected"),
+		};
+		assert_eq!(
+			pool.check_replacement(&chain.at(5), 1_000_000_000, &set),
+			Err(TransactionError::ReplacementUnconfirmedInput(1))
+		);
+
+		let mut set = set;
+		set.replaced = (0..MAX_REPLACEMENT_EVICTIONS as u8 + 1).map(|i| dhash256(&[i])).collect();
+		assert_eq!(
+			pool.check_replacement(&chain.at(3), 1_000_000_000, &set),
+			Err(TransactionError::ReplacementEvictions(MAX_REPLACEMENT_EVICTIONS + 1))
+		);
+	}
+
+	#[test]
+	fn test_memory_pool_ancestors_limits() {
+		let chain = &mut ChainBuilder::new();
+		#[rustfmt::skip]
+		TransactionBuilder::with_output(10).store(chain)
+			.into_input(0).set_output(20).store(chain)
+			.into_input(0).set_output(30).store(chain)
+			.into_input(0).set_output(40).store(chain);
+
+		let mut pool = MemoryPool::new();
+		pool.set_package_limits(PackageLimits {
+			ancestors_count: 3,
+			..Default::default()
+		});
+		for index in 0..3 {
+			pool.insert_verified(chain.at(index).into(), &NonZeroFeeCalculator).unwrap();
+		}
+		assert_eq!(
+			pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator),
+			Err((TransactionError::TooManyAncestors(3), vec![]))
+		);
+		assert!(pool.contains(&chain.hash(2)));
+		assert!(!pool.contains(&chain.hash(3)));
+		assert_eq!(pool.check_package_limits(&chain.at(3)), Err(TransactionError::TooManyAncestors(3)));
+
+		let mut ancestors = pool.ancestors(&chain.hash(2)).unwrap();
+		ancestors.sort();
+		let mut expected = vec![chain.hash(0), chain.hash(1)];
+		expected.sort();
+		assert_eq!(ancestors, expected);
+		let mut descendants = pool.descendants(&chain.hash(0)).unwrap();
+		descendants.sort();
+		let mut expected = vec![chain.hash(1), chain.hash(2)];
+		expected.sort();
+		assert_eq!(descendants, expected);
+
+		let information = pool.entry_information(&chain.hash(0)).unwrap();
+		// transaction without witness => every byte weighs 4 units
+		assert_eq!(information.weight, chain.size(0) * 4);
+		assert_eq!(information.package_count, 3);
+		assert_eq!(information.package_size, chain.size(0) + chain.size(1) + chain.size(2));
+		assert_eq!(
+			pool.entry_information(&chain.hash(2)).unwrap().ancestors_size,
+			information.package_size
+		);
+
+		// package of ancestor is updated when descendant is removed
+		pool.remove_by_hash(&chain.hash(2));
+		let information = pool.entry_information(&chain.hash(0)).unwrap();
+		assert_eq!(information.package_count, 2);
+		assert_eq!(information.package_size, chain.size(0) + chain.size(1));
+
+		// and descendants forget about removed ancestor
+		pool.remove_by_hash(&chain.hash(0));
+		assert_eq!(pool.ancestors(&chain.hash(1)), Some(vec![]));
+		assert_eq!(pool.check_package_limits(&chain.at(2)), Ok(()));
+	}
+
+	#[test]
+	fn test_memory_pool_descendants_limits() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10)
+			.add_output(10)
+			.add_output(10)
+			.add_output(10)
+			.store(chain) // t0
+			.reset()
+			.set_input(&chain.at(0), 0)
+			.set_output(20)
+			.store(chain) // t0[0] -> t1
+			.reset()
+			.set_input(&chain.at(0), 1)
+			.set_output(20)
+			.store(chain) // t0[1] -> t2
+			.reset()
+			.set_input(&chain.at(0), 2)
+			.set_output(20)
+			.store(chain) // carve-out: t0[2] -> t3
+			.reset()
+			.set_input(&chain.at(0), 3)
+			.set_output(20)
+			.store(chain) // t0[3] -> t4
+			.reset()
+			.set_input(&chain.at(1), 0)
+			.set_output(30)
+			.store(chain); // t0[0] -> t1[0] -> t5
+
+		let mut pool = MemoryPool::new();
+		pool.set_package_limits(PackageLimits {
+			descendants_count: 3,
+			..Default::default()
+		});
+		for index in 0..3 {
+			pool.insert_verified(chain.at(index).into(), &NonZeroFeeCalculator).unwrap();
+		}
+
+		// t0 already has 2 descendants => t5 would be the third one
+		assert_eq!(
+			pool.check_package_limits(&chain.at(5)),
+			Err(TransactionError::TooManyDescendants(chain.hash(0)))
+		);
+		// but t3 has single in-pool ancestor => it is allowed to exceed the limit
+		assert_eq!(pool.check_package_limits(&chain.at(3)), Ok(()));
+		pool.insert_verified(chain.at(3).into(), &NonZeroFeeCalculator).unwrap();
+		assert!(pool.contains(&chain.hash(3)));
+		// but only by one transaction
+		assert_eq!(
+			pool.check_package_limits(&chain.at(4)),
+			Err(TransactionError::TooManyDescendants(chain.hash(0)))
+		);
+		assert_eq!(
+			pool.insert_verified(chain.at(4).into(), &NonZeroFeeCalculator),
+			Err((TransactionError::TooManyDescendants(chain.hash(0)), vec![]))
+		);
+		assert!(!pool.contains(&chain.hash(4)));
+	}
+
+	#[test]
+	fn test_memory_pool_insert_package() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_default_input(0)
+			.set_output(10)
+			.store(chain) // t0
+			.into_input(0)
+			.set_output(1_000_000_000_000)
+			.store(chain); // t0[0] -> t1
+
+		// t0 doesn't pay enough on its own
+		let mut pool = MemoryPool::new();
+		let parent_fee_rate = 100_000_010 * 1000 / chain.size(0) as u64;
+		pool.rolling_minimum_fee_rate = (parent_fee_rate * 2) as f64;
+		pool.last_rolling_fee_update = RealTime.now().as_secs();
+		assert_eq!(
+			pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator),
+			Err((TransactionError::MemoryPoolFeeRate(parent_fee_rate), vec![]))
+		);
+		assert!(!pool.contains(&chain.hash(0)));
+		assert_eq!(
+			pool.insert_verified_package(vec![chain.at(0).into()], &NonZeroFeeCalculator),
+			Err(PackageError::FeeRate(parent_fee_rate))
+		);
+		assert_eq!(pool.get_transactions_ids().len(), 0);
+
+		// but t1 pays for both
+		assert_eq!(
+			pool.insert_verified_package(vec![chain.at(0).into(), chain.at(1).into()], &NonZeroFeeCalculator),
+			Ok(vec![])
+		);
+		assert!(pool.contains(&chain.hash(0)));
+		assert!(pool.contains(&chain.hash(1)));
+
+		// transactions that are already in the pool are ignored
+		assert_eq!(
+			pool.insert_verified_package(vec![chain.at(0).into(), chain.at(1).into()], &NonZeroFeeCalculator),
+			Ok(vec![])
+		);
+		assert_eq!(pool.get_transactions_ids().len(), 2);
+	}
 }
@@ -0,0 +1,128 @@
+use chain::Transaction;
+use ser::{
+	deserialize, serialize_with_flags, Deserializable, Error as ReaderError, Reader, Serializable, Stream, SERIALIZE_TRANSACTION_WITNESS,
+};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Version of the memory pool dump format
+pub const MEMORY_POOL_DUMP_VERSION: u32 = 1;
+
+/// Memory pool transaction, stored in the dump
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpedTransaction {
+	/// Transaction
+	pub transaction: Transaction,
+	/// Time when transaction has entered the memory pool
+	pub time: u64,
+	/// Virtual transaction fee, set with `MemoryPool::set_virtual_fee`
+	pub virtual_fee: i64,
+}
+
+/// Memory pool dump, which is written on shutdown and read on startup
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MemoryPoolDump {
+	/// Topologically sorted memory pool transactions
+	pub transactions: Vec<DumpedTransaction>,
+}
+
+impl MemoryPoolDump {
+	/// Reads memory pool dump from the file
+	pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+		let mut data = Vec::new();
+		fs::File::open(path)?.read_to_end(&mut data)?;
+		deserialize(data.as_slice()).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))
+	}
+
+	/// Writes memory pool dump to the file. Dump is written to the temporary file first,
+	/// so that the previous dump is not lost if write fails.
+	pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+		let path = path.as_ref();
+		let temporary_path = path.with_extension("new");
+		let mut file = fs::File::create(&temporary_path)?;
+		file.write_all(&serialize_with_flags(self, SERIALIZE_TRANSACTION_WITNESS))?;
+		file.sync_all()?;
+		fs::rename(&temporary_path, path)
+	}
+}
+
+impl Serializable for DumpedTransaction {
+	fn serialize(&self, stream: &mut Stream) {
+		stream.append(&self.transaction).append(&self.time).append(&self.virtual_fee);
+	}
+}
+
+impl Deserializable for DumpedTransaction {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError>
+	where
+		T: io::Read,
+	{
+		Ok(DumpedTransaction {
+			transaction: reader.read()?,
+			time: reader.read()?,
+			virtual_fee: reader.read()?,
+		})
+	}
+}
+
+impl Serializable for MemoryPoolDump {
+	fn serialize(&self, stream: &mut Stream) {
+		stream.append(&MEMORY_POOL_DUMP_VERSION).append_list(&self.transactions);
+	}
+}
+
+impl Deserializable for MemoryPoolDump {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError>
+	where
+		T: io::Read,
+	{
+		let version: u32 = reader.read()?;
+		if version != MEMORY_POOL_DUMP_VERSION {
+			return Err(ReaderError::MalformedData);
+		}
+
+		Ok(MemoryPoolDump {
+			transactions: reader.read_list()?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use super::{DumpedTransaction, MemoryPoolDump};
+	use ser::{deserialize, serialize_with_flags, Error as ReaderError, SERIALIZE_TRANSACTION_WITNESS};
+
+	#[test]
+	fn memory_pool_dump_roundtrip() {
+		let dump = MemoryPoolDump {
+			transactions: vec![
+				DumpedTransaction {
+					transaction: test_data::TransactionBuilder::with_default_input(0).set_output(10).into(),
+					time: 1_600_000_000,
+					virtual_fee: -100,
+				},
+				DumpedTransaction {
+					transaction: test_data::TransactionBuilder::with_default_input(1).set_output(20).into(),
+					time: 1_600_000_001,
+					virtual_fee: 200,
+				},
+			],
+		};
+
+		let serialized = serialize_with_flags(&dump, SERIALIZE_TRANSACTION_WITNESS);
+		assert_eq!(deserialize::<_, MemoryPoolDump>(serialized.as_ref()), Ok(dump));
+	}
+
+	#[test]
+	fn memory_pool_dump_unknown_version() {
+		let mut serialized: Vec<u8> = serialize_with_flags(&MemoryPoolDump::default(), SERIALIZE_TRANSACTION_WITNESS).into();
+		serialized[0] += 1;
+		assert_eq!(
+			deserialize::<_, MemoryPoolDump>(serialized.as_slice()),
+			Err(ReaderError::MalformedData)
+		);
+	}
+}
@@ -26,6 +26,8 @@ pub struct ConsensusParams {
 	pub csv_deployment: Option<Deployment>,
 	/// BIP141, BIP143, BIP147 deployment
 	pub segwit_deployment: Option<Deployment>,
+	/// BIP340, BIP341, BIP342 deployment
+	pub taproot_deployment: Option<Deployment>,
 
 	/// Absolute maximum block size.
 	pub absolute_maximum_block_size: usize,
@@ -58,6 +60,13 @@ impl ConsensusParams {
 					timeout: 1510704000,
 					activation: Some(481824),
 				}),
+				taproot_deployment: Some(Deployment {
+					name: "taproot",
+					bit: 2,
+					start_time: 1619222400,
+					timeout: 1628640000,
+					activation: Some(709632),
+				}),
 				absolute_maximum_block_size: 32_000_000,
 				absolute_maximum_block_sigops: 160_000,
 				witness_scale_factor: 4,
@@ -90,6 +99,13 @@ impl ConsensusParams {
 					timeout: 1493596800,
 					activation: Some(834624),
 				}),
+				taproot_deployment: Some(Deployment {
+					name: "taproot",
+					bit: 2,
+					start_time: 1619222400,
+					timeout: 1628640000,
+					activation: None,
+				}),
 				absolute_maximum_block_size: 32_000_000,
 				absolute_maximum_block_sigops: 160_000,
 				witness_scale_factor: 4,
@@ -122,6 +138,13 @@ impl ConsensusParams {
 					timeout: ::std::u32::MAX,
 					activation: None,
 				}),
+				taproot_deployment: Some(Deployment {
+					name: "taproot",
+					bit: 2,
+					start_time: 0,
+					timeout: u32::MAX,
+					activation: Some(0),
+				}),
 				absolute_maximum_block_size: 32_000_000,
 				absolute_maximum_block_sigops: 160_000,
 				witness_scale_factor: 4,
@@ -214,4 +237,12 @@ mod tests {
 		assert_eq!(ConsensusParams::new(Network::Testnet).miner_confirmation_window, 2016);
 		assert_eq!(ConsensusParams::new(Network::Regtest).miner_confirmation_window, 144);
 	}
+
+	#[test]
+	fn test_consensus_taproot_deployment() {
+		let activation = |network| ConsensusParams::new(network).taproot_deployment.unwrap().activation;
+		assert_eq!(activation(Network::Mainnet), Some(709632));
+		assert_eq!(activation(Network::Testnet), None);
+		assert_eq!(activation(Network::Regtest), Some(0));
+	}
 }
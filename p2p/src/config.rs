@@ -1,6 +1,6 @@
+use crate::net::TorControl;
 use crate::{InternetProtocol, NetConfig};
-use message::common::Services;
-use std::net;
+use message::common::{NodeAddress, Services};
 use std::net::SocketAddr;
 
 #[derive(Debug, Clone)]
@@ -9,16 +9,28 @@ pub struct Config {
 	pub inbound_connections: u32,
 	/// Number of outbound connections.
 	pub outbound_connections: u32,
+	/// Number of outbound connections, which relay only blocks.
+	pub block_relay_connections: u32,
 	/// Configuration for every connection.
 	pub connection: NetConfig,
 	/// Connect only to these nodes.
-	pub peers: Vec<net::SocketAddr>,
+	pub peers: Vec<NodeAddress>,
 	/// Optional seed node to use instead of default bitcoin-core seeds.
 	pub seed: Option<SocketAddr>,
-	/// p2p/nodes.csv file path.
+	/// Query DNS seeds for the addresses when none of the known addresses is good.
+	pub dns_seed: bool,
+	/// Directory of the p2p/addresses.dat file.
 	pub node_table_path: String,
-	/// Peers with this services will get a boost in node_table.
+	/// p2p/banlist.csv file path.
+	pub ban_list_path: String,
+	/// Peers with this services are more likely to be selected for the connection.
 	pub preferable_services: Services,
 	/// Internet protocol.
 	pub internet_protocol: InternetProtocol,
+	/// Path to the asmap file, used to group peers by their autonomous systems.
+	pub asmap_path: Option<String>,
+	/// Tor control port used to create onion service for incoming connections.
+	pub tor_control: Option<TorControl>,
+	/// Max number of bytes to send during 24 hours. Historical blocks are not served once the target is close.
+	pub max_upload_target: Option<u64>,
 }
@@ -1,14 +1,53 @@
-use crate::io::Error;
-use crate::io::{read_message, write_message, SharedTcpStream};
-use message::types::{Verack, Version};
-use message::{Error as MessageError, Message};
+use crate::io::transport::{initiate_v2, respond_v2, v1_prefix, Transport, V1_PREFIX_LEN};
+use crate::io::{read_message, read_payload, Error, SharedTcpStream};
+use message::types::{SendAddrV2, SendTxRcncl, Verack, Version, WtxidRelay};
+use message::{deserialize_payload, Error as MessageError, Message, MessageHeader, Payload};
 use network::Magic;
 use std::cmp;
 
-pub async fn handshake(stream: &SharedTcpStream, magic: Magic, version: Version, min_version: u32) -> Result<HandshakeResult, Error> {
-	write_message(stream, version_message(magic, &version)).await?;
+/// Peers starting from this protocol version understand the `sendaddrv2` message
+pub const SENDADDRV2_VERSION: u32 = 70016;
+/// Peers starting from this protocol version understand the `wtxidrelay` message
+pub const WTXID_RELAY_VERSION: u32 = 70016;
+/// Version of the transactions reconciliation protocol (BIP330) we support
+pub const TXRECONCILIATION_VERSION: u32 = 1;
+
+/// Transactions reconciliation is offered to the peer if `txrcncl_salt` is set.
+pub async fn handshake(
+	stream: &SharedTcpStream,
+	magic: Magic,
+	version: Version,
+	min_version: u32,
+	txrcncl_salt: Option<u64>,
+) -> Result<HandshakeResult, Error> {
+	initiate_handshake(stream, &Transport::V1, magic, version, min_version, txrcncl_salt).await
+}
+
+/// Performs v2 key exchange and then exchanges version messages over the encrypted transport.
+/// Fails if the peer does not support v2 transport, in which case the caller should reconnect using `handshake`.
+pub async fn handshake_v2(
+	stream: &SharedTcpStream,
+	magic: Magic,
+	version: Version,
+	min_version: u32,
+	txrcncl_salt: Option<u64>,
+) -> Result<(HandshakeResult, Transport), Error> {
+	let transport = initiate_v2(stream, magic).await?;
+	let result = initiate_handshake(stream, &transport, magic, version, min_version, txrcncl_salt).await?;
+	Ok((result, transport))
+}
+
+async fn initiate_handshake(
+	stream: &SharedTcpStream,
+	transport: &Transport,
+	magic: Magic,
+	version: Version,
+	min_version: u32,
+	txrcncl_salt: Option<u64>,
+) -> Result<HandshakeResult, Error> {
+	transport.write_message(stream, version_message(magic, &version).as_ref()).await?;
 
-	let peer_version: Version = read_message(stream, magic, 0).await?;
+	let peer_version: Version = transport.read_payload(stream, magic, 0).await?;
 
 	if peer_version.version() < min_version {
 		return Err(MessageError::InvalidVersion.into());
@@ -18,19 +57,97 @@ pub async fn handshake(stream: &SharedTcpStream, magic: Magic, version: Version,
 			return Err(MessageError::InvalidVersion.into());
 		}
 	}
-	write_message(stream, verack_message(magic)).await?;
-
-	let _: Verack = read_message(stream, magic, 0).await?;
+	if peer_version.version() >= WTXID_RELAY_VERSION {
+		transport.write_message(stream, wtxidrelay_message(magic).as_ref()).await?;
+	}
+	if peer_version.version() >= SENDADDRV2_VERSION {
+		transport.write_message(stream, sendaddrv2_message(magic).as_ref()).await?;
+	}
+	let local_txrcncl_salt = txrcncl_salt.filter(|_| is_txreconciliation_supported(&version, &peer_version));
+	if let Some(salt) = local_txrcncl_salt {
+		transport.write_message(stream, sendtxrcncl_message(magic, salt).as_ref()).await?;
+	}
+	transport.write_message(stream, verack_message(magic).as_ref()).await?;
+
+	// peer signals addrv2, wtxid relay and reconciliation support between the version and verack messages
+	let mut addrv2 = false;
+	let mut wtxid_relay = false;
+	let mut peer_txrcncl_salt = None;
+	loop {
+		let (command, payload) = transport.read_message(stream, magic).await?;
+		if command == SendAddrV2::command() {
+			addrv2 = true;
+		} else if command == WtxidRelay::command() {
+			wtxid_relay = peer_version.version() >= WTXID_RELAY_VERSION;
+		} else if command == SendTxRcncl::command() {
+			let message: SendTxRcncl = deserialize_payload(&payload, 0)?;
+			if message.version >= TXRECONCILIATION_VERSION {
+				peer_txrcncl_salt = Some(message.salt);
+			}
+		} else if command == Verack::command() {
+			break;
+		} else {
+			return Err(MessageError::InvalidCommand.into());
+		}
+	}
 
 	Ok(HandshakeResult {
 		negotiated_version: negotiate_version(version.version(), peer_version.version()),
 		version: peer_version,
+		addrv2,
+		wtxid_relay,
+		local_txrcncl_salt,
+		txrcncl_salt: peer_txrcncl_salt,
 	})
 }
 
-pub async fn accept_handshake(a: &SharedTcpStream, magic: Magic, version: Version, min_version: u32) -> Result<HandshakeResult, Error> {
+pub async fn accept_handshake(
+	a: &SharedTcpStream,
+	magic: Magic,
+	version: Version,
+	min_version: u32,
+	txrcncl_salt: Option<u64>,
+) -> Result<HandshakeResult, Error> {
 	let peer_version: Version = read_message(a, magic, 0).await?;
+	respond_handshake(a, &Transport::V1, magic, version, peer_version, min_version, txrcncl_salt).await
+}
+
+/// Accepts both v1 and v2 connections. Peers are using v1 transport if they start with the v1 version message header.
+pub async fn accept_handshake_v2(
+	a: &SharedTcpStream,
+	magic: Magic,
+	version: Version,
+	min_version: u32,
+	txrcncl_salt: Option<u64>,
+) -> Result<(HandshakeResult, Transport), Error> {
+	let mut prefix = [0u8; V1_PREFIX_LEN];
+	a.read_exact(&mut prefix).await?;
+
+	if prefix == v1_prefix(magic) {
+		let mut header = [0u8; 24];
+		header[..V1_PREFIX_LEN].copy_from_slice(&prefix);
+		a.read_exact(&mut header[V1_PREFIX_LEN..]).await?;
+		let header = MessageHeader::deserialize(&header, magic)?;
+		let peer_version = read_payload(a, 0, header.len as usize, header.checksum).await?;
+		let result = respond_handshake(a, &Transport::V1, magic, version, peer_version, min_version, txrcncl_salt).await?;
+		return Ok((result, Transport::V1));
+	}
 
+	let transport = respond_v2(a, magic, &prefix).await?;
+	let peer_version = transport.read_payload(a, magic, 0).await?;
+	let result = respond_handshake(a, &transport, magic, version, peer_version, min_version, txrcncl_salt).await?;
+	Ok((result, transport))
+}
+
+async fn respond_handshake(
+	a: &SharedTcpStream,
+	transport: &Transport,
+	magic: Magic,
+	version: Version,
+	peer_version: Version,
+	min_version: u32,
+	txrcncl_salt: Option<u64>,
+) -> Result<HandshakeResult, Error> {
 	if peer_version.version() < min_version {
 		return Err(MessageError::InvalidVersion.into());
 	}
@@ -39,12 +156,27 @@ pub async fn accept_handshake(a: &SharedTcpStream, magic: Magic, version: Versio
 			return Err(MessageError::InvalidVersion.into());
 		}
 	}
-	write_message(a, version_message(magic, &version)).await?;
-	write_message(a, verack_message(magic)).await?;
+	transport.write_message(a, version_message(magic, &version).as_ref()).await?;
+	if peer_version.version() >= WTXID_RELAY_VERSION {
+		transport.write_message(a, wtxidrelay_message(magic).as_ref()).await?;
+	}
+	if peer_version.version() >= SENDADDRV2_VERSION {
+		transport.write_message(a, sendaddrv2_message(magic).as_ref()).await?;
+	}
+	let local_txrcncl_salt = txrcncl_salt.filter(|_| is_txreconciliation_supported(&version, &peer_version));
+	if let Some(salt) = local_txrcncl_salt {
+		transport.write_message(a, sendtxrcncl_message(magic, salt).as_ref()).await?;
+	}
+	transport.write_message(a, verack_message(magic).as_ref()).await?;
 
+	// `sendaddrv2`, `wtxidrelay` and `sendtxrcncl` of the peer follow our version message, so they are handled after the handshake
 	Ok(HandshakeResult {
 		negotiated_version: negotiate_version(version.version(), peer_version.version()),
 		version: peer_version,
+		addrv2: false,
+		wtxid_relay: false,
+		local_txrcncl_salt,
+		txrcncl_salt: None,
 	})
 }
 
@@ -52,10 +184,23 @@ pub fn negotiate_version(local: u32, other: u32) -> u32 {
 	cmp::min(local, other)
 }
 
+/// Reconciliation requires wtxid relay and is pointless if any of peers is not relaying transactions
+fn is_txreconciliation_supported(version: &Version, peer_version: &Version) -> bool {
+	peer_version.version() >= WTXID_RELAY_VERSION && version.relay_transactions() && peer_version.relay_transactions()
+}
+
 #[derive(Debug, PartialEq)]
 pub struct HandshakeResult {
 	pub version: Version,
 	pub negotiated_version: u32,
+	/// Peer signalled support of the `addrv2` message
+	pub addrv2: bool,
+	/// Peer signalled that transactions should be announced by their witness hashes
+	pub wtxid_relay: bool,
+	/// Salt, which we have sent in the `sendtxrcncl` message
+	pub local_txrcncl_salt: Option<u64>,
+	/// Salt of the peer, which has signalled support of the transactions reconciliation
+	pub txrcncl_salt: Option<u64>,
 }
 
 fn version_message(magic: Magic, version: &Version) -> Message<Version> {
@@ -66,12 +211,29 @@ fn verack_message(magic: Magic) -> Message<Verack> {
 	Message::new(magic, 0, &Verack).expect("verack message should always be serialized correctly")
 }
 
+fn wtxidrelay_message(magic: Magic) -> Message<WtxidRelay> {
+	Message::new(magic, 0, &WtxidRelay).expect("wtxidrelay message should always be serialized correctly")
+}
+
+fn sendaddrv2_message(magic: Magic) -> Message<SendAddrV2> {
+	Message::new(magic, 0, &SendAddrV2).expect("sendaddrv2 message should always be serialized correctly")
+}
+
+fn sendtxrcncl_message(magic: Magic, salt: u64) -> Message<SendTxRcncl> {
+	let sendtxrcncl = SendTxRcncl {
+		version: TXRECONCILIATION_VERSION,
+		salt,
+	};
+	Message::new(magic, 0, &sendtxrcncl).expect("sendtxrcncl message should always be serialized correctly")
+}
+
 #[cfg(test)]
 mod tests {
-	use super::{accept_handshake, handshake, HandshakeResult};
+	use super::{accept_handshake, accept_handshake_v2, handshake, HandshakeResult};
 	use crate::io::shared_tcp_stream::SharedTcpStream;
+	use crate::io::Transport;
 	use message::types::version::{Version, V0, V106, V70001};
-	use message::types::Verack;
+	use message::types::{SendAddrV2, SendTxRcncl, Verack, WtxidRelay};
 	use message::{Error as MessageError, Message};
 	use network::Network;
 	use ser::Stream;
@@ -138,9 +300,13 @@ mod tests {
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			addrv2: false,
+			wtxid_relay: false,
+			local_txrcncl_salt: None,
+			txrcncl_salt: None,
 		};
 
-		let hs = handshake(&shared_stream, magic, local_version, 0).await;
+		let hs = handshake(&shared_stream, magic, local_version, 0, None).await;
 		assert_eq!(shared_stream.get_written().await, expected_stream.out());
 		assert_eq!(hs.unwrap(), expected);
 	}
@@ -162,13 +328,120 @@ mod tests {
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			addrv2: false,
+			wtxid_relay: false,
+			local_txrcncl_salt: None,
+			txrcncl_salt: None,
 		};
 
-		let hs = accept_handshake(&shared_stream, magic, local_version, 0).await;
+		let hs = accept_handshake(&shared_stream, magic, local_version, 0, None).await;
 		assert_eq!(shared_stream.get_written().await, expected_stream.out());
 		assert_eq!(hs.unwrap(), expected);
 	}
 
+	#[tokio::test]
+	async fn test_handshake_addrv2_and_wtxid_relay() {
+		let magic = Network::Mainnet.magic();
+		let local_version = local_version();
+		let mut remote_version = remote_version();
+		if let Version::V70001(ref mut v0, _, _) = remote_version {
+			v0.version = 70016;
+		}
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &WtxidRelay).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &SendAddrV2).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &Verack).unwrap().as_ref());
+		let shared_stream = SharedTcpStream::new(remote_stream.out());
+
+		let mut expected_stream = Stream::new();
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &WtxidRelay).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &SendAddrV2).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &Verack).unwrap().as_ref());
+		let expected_written = expected_stream.out();
+
+		let expected = HandshakeResult {
+			version: remote_version.clone(),
+			negotiated_version: 70001,
+			addrv2: true,
+			wtxid_relay: true,
+			local_txrcncl_salt: None,
+			txrcncl_salt: None,
+		};
+
+		let hs = handshake(&shared_stream, magic, local_version.clone(), 0, None).await;
+		assert_eq!(shared_stream.get_written().await, expected_written);
+		assert_eq!(hs.unwrap(), expected);
+
+		// inbound peer sends sendaddrv2 and wtxidrelay after the handshake
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
+		let shared_stream = SharedTcpStream::new(remote_stream.out());
+
+		let hs = accept_handshake(&shared_stream, magic, local_version, 0, None).await;
+		assert_eq!(shared_stream.get_written().await, expected_written);
+		let hs = hs.unwrap();
+		assert!(!hs.addrv2);
+		assert!(!hs.wtxid_relay);
+	}
+
+	#[tokio::test]
+	async fn test_handshake_txreconciliation() {
+		let magic = Network::Mainnet.magic();
+		let local_version = local_version();
+		let mut remote_version = remote_version();
+		if let Version::V70001(ref mut v0, _, _) = remote_version {
+			v0.version = 70016;
+		}
+		let local_sendtxrcncl = SendTxRcncl { version: 1, salt: 5 };
+		let remote_sendtxrcncl = SendTxRcncl { version: 1, salt: 7 };
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &WtxidRelay).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_sendtxrcncl).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &Verack).unwrap().as_ref());
+		let shared_stream = SharedTcpStream::new(remote_stream.out());
+
+		let mut expected_stream = Stream::new();
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &WtxidRelay).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &SendAddrV2).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &local_sendtxrcncl).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &Verack).unwrap().as_ref());
+		let expected_written = expected_stream.out();
+
+		let hs = handshake(&shared_stream, magic, local_version.clone(), 0, Some(5)).await.unwrap();
+		assert_eq!(shared_stream.get_written().await, expected_written);
+		assert_eq!(hs.local_txrcncl_salt, Some(5));
+		assert_eq!(hs.txrcncl_salt, Some(7));
+
+		// inbound peer sends sendtxrcncl after the handshake
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
+		let shared_stream = SharedTcpStream::new(remote_stream.out());
+
+		let hs = accept_handshake(&shared_stream, magic, local_version.clone(), 0, Some(5))
+			.await
+			.unwrap();
+		assert_eq!(shared_stream.get_written().await, expected_written);
+		assert_eq!(hs.local_txrcncl_salt, Some(5));
+		assert_eq!(hs.txrcncl_salt, None);
+
+		// reconciliation is not offered to the peers, which are not relaying transactions
+		if let Version::V70001(_, _, ref mut v70001) = remote_version {
+			v70001.relay = false;
+		}
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
+		let shared_stream = SharedTcpStream::new(remote_stream.out());
+
+		let hs = accept_handshake(&shared_stream, magic, local_version, 0, Some(5)).await.unwrap();
+		assert_eq!(hs.local_txrcncl_salt, None);
+	}
+
 	#[tokio::test]
 	async fn test_self_handshake() {
 		let magic = Network::Mainnet.magic();
@@ -181,7 +454,7 @@ mod tests {
 
 		let expected_error = MessageError::InvalidVersion;
 
-		let hs = handshake(&shared_stream, magic, local_version, 0).await;
+		let hs = handshake(&shared_stream, magic, local_version, 0, None).await;
 		assert_eq!(hs.unwrap_err().source().unwrap().to_string(), expected_error.to_string());
 	}
 
@@ -197,7 +470,36 @@ mod tests {
 
 		let expected_error = MessageError::InvalidVersion;
 
-		let hs = accept_handshake(&shared_stream, magic, local_version, 0).await;
+		let hs = accept_handshake(&shared_stream, magic, local_version, 0, None).await;
 		assert_eq!(expected_error.to_string(), hs.unwrap_err().source().unwrap().to_string());
 	}
+
+	#[tokio::test]
+	async fn test_accept_handshake_v2_falls_back_to_v1() {
+		let magic = Network::Mainnet.magic();
+		let local_version = local_version();
+		let remote_version = remote_version();
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, remote_version.version(), &remote_version).unwrap().as_ref());
+		let shared_stream = SharedTcpStream::new(remote_stream.out());
+
+		let mut expected_stream = Stream::new();
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, local_version.version(), &Verack).unwrap().as_ref());
+
+		let expected = HandshakeResult {
+			version: remote_version,
+			negotiated_version: 70001,
+			addrv2: false,
+			wtxid_relay: false,
+			local_txrcncl_salt: None,
+			txrcncl_salt: None,
+		};
+
+		let (hs, transport) = accept_handshake_v2(&shared_stream, magic, local_version, 0, None).await.unwrap();
+		assert_eq!(shared_stream.get_written().await, expected_stream.out());
+		assert_eq!(hs, expected);
+		assert!(matches!(transport, Transport::V1));
+	}
 }
@@ -5,13 +5,18 @@ mod read_header;
 mod read_message;
 mod read_payload;
 mod shared_tcp_stream;
-mod write_message;
+mod socks5;
+mod transport;
+mod v2_cipher;
 
 pub use self::error::Error;
-pub use self::handshake::{accept_handshake, handshake, HandshakeResult};
+pub use self::handshake::{
+	accept_handshake, accept_handshake_v2, handshake, handshake_v2, HandshakeResult, TXRECONCILIATION_VERSION, WTXID_RELAY_VERSION,
+};
 pub use self::read_any_message::read_any_message;
 pub use self::read_header::read_header;
 pub use self::read_message::read_message;
 pub use self::read_payload::read_payload;
 pub use self::shared_tcp_stream::SharedTcpStream;
-pub use self::write_message::write_message;
+pub use self::socks5::Credentials;
+pub use self::transport::Transport;
@@ -1,5 +1,9 @@
 #[cfg(test)]
 use crate::bytes::Bytes;
+#[cfg(not(test))]
+use crate::io::socks5::socks5_connect;
+use crate::io::socks5::Credentials;
+use message::common::NodeAddress;
 use std::io;
 #[cfg(test)]
 use std::io::Cursor;
@@ -28,6 +32,17 @@ impl SharedTcpStream {
 		Ok(Self::new(stream))
 	}
 
+	/// Connects to the target through the SOCKS5 proxy
+	pub async fn connect_socks5(
+		proxy: &SocketAddr,
+		target: &NodeAddress,
+		credentials: Option<&Credentials>,
+	) -> Result<SharedTcpStream, io::Error> {
+		let mut stream = TcpStream::connect(proxy).await?;
+		socks5_connect(&mut stream, target, credentials).await?;
+		Ok(Self::new(stream))
+	}
+
 	fn new(stream: TcpStream) -> Self {
 		SharedTcpStream {
 			io: Arc::new(Mutex::new(stream)),
@@ -76,6 +91,14 @@ impl SharedTcpStream {
 		Ok(Self::new(Bytes::new()))
 	}
 
+	pub async fn connect_socks5(
+		_proxy: &SocketAddr,
+		_target: &NodeAddress,
+		_credentials: Option<&Credentials>,
+	) -> Result<SharedTcpStream, io::Error> {
+		Ok(Self::new(Bytes::new()))
+	}
+
 	pub fn new(bytes: Bytes) -> Self {
 		SharedTcpStream {
 			read: Arc::new(Mutex::new(Cursor::new(bytes.into()))),
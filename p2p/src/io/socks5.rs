@@ -0,0 +1,242 @@
+//! SOCKS5 client with username/password authentication.
+//! https://datatracker.ietf.org/doc/html/rfc1928
+//! https://datatracker.ietf.org/doc/html/rfc1929
+
+use message::common::{NetworkAddress, NodeAddress};
+use rand::Rng;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const VERSION: u8 = 5;
+const AUTH_NONE: u8 = 0;
+const AUTH_USERNAME_PASSWORD: u8 = 2;
+const AUTH_NO_ACCEPTABLE: u8 = 0xff;
+const USERNAME_PASSWORD_VERSION: u8 = 1;
+const COMMAND_CONNECT: u8 = 1;
+const ADDRESS_IPV4: u8 = 1;
+const ADDRESS_DOMAIN: u8 = 3;
+const ADDRESS_IPV6: u8 = 4;
+const REPLY_SUCCEEDED: u8 = 0;
+
+/// Username and password sent to the proxy. Tor opens separate circuit for every distinct credentials.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Credentials {
+	pub username: String,
+	pub password: String,
+}
+
+impl Credentials {
+	/// Generates random credentials, isolating the connection from the others
+	pub fn random() -> Self {
+		let mut rng = rand::thread_rng();
+		Credentials {
+			username: format!("{:016x}", rng.gen::<u64>()),
+			password: format!("{:016x}", rng.gen::<u64>()),
+		}
+	}
+}
+
+fn error(message: &str) -> io::Error {
+	io::Error::other(format!("SOCKS5 proxy error: {}", message))
+}
+
+fn reply_error(reply: u8) -> io::Error {
+	let message = match reply {
+		1 => "general failure",
+		2 => "connection not allowed",
+		3 => "network unreachable",
+		4 => "host unreachable",
+		5 => "connection refused",
+		6 => "TTL expired",
+		7 => "command not supported",
+		8 => "address type not supported",
+		_ => "unknown reply",
+	};
+	error(message)
+}
+
+/// Asks the proxy to open connection to the target. Once this succeeds, the stream is connected to the target.
+pub async fn socks5_connect<S>(stream: &mut S, target: &NodeAddress, credentials: Option<&Credentials>) -> Result<(), io::Error>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let method = if credentials.is_some() { AUTH_USERNAME_PASSWORD } else { AUTH_NONE };
+	stream.write_all(&[VERSION, 1, method]).await?;
+
+	let mut response = [0u8; 2];
+	stream.read_exact(&mut response).await?;
+	if response[0] != VERSION {
+		return Err(error("invalid version"));
+	}
+	match (response[1], credentials) {
+		(AUTH_NONE, _) => (),
+		(AUTH_USERNAME_PASSWORD, Some(credentials)) => authenticate(stream, credentials).await?,
+		(AUTH_NO_ACCEPTABLE, _) => return Err(error("no acceptable authentication method")),
+		_ => return Err(error("unexpected authentication method")),
+	}
+
+	let mut request = vec![VERSION, COMMAND_CONNECT, 0];
+	match target.address {
+		NetworkAddress::IpV4(ip) => {
+			request.push(ADDRESS_IPV4);
+			request.extend_from_slice(&ip.octets());
+		}
+		NetworkAddress::IpV6(ip) | NetworkAddress::Cjdns(ip) => {
+			request.push(ADDRESS_IPV6);
+			request.extend_from_slice(&ip.octets());
+		}
+		NetworkAddress::TorV3(_) | NetworkAddress::I2P(_) => {
+			let domain = target.address.to_string();
+			request.push(ADDRESS_DOMAIN);
+			request.push(domain.len() as u8);
+			request.extend_from_slice(domain.as_bytes());
+		}
+	}
+	request.extend_from_slice(&target.port.to_be_bytes());
+	stream.write_all(&request).await?;
+
+	let mut response = [0u8; 4];
+	stream.read_exact(&mut response).await?;
+	if response[0] != VERSION {
+		return Err(error("invalid version"));
+	}
+	if response[1] != REPLY_SUCCEEDED {
+		return Err(reply_error(response[1]));
+	}
+
+	// skip the address bound by the proxy and its port
+	let bound_len = match response[3] {
+		ADDRESS_IPV4 => 4,
+		ADDRESS_IPV6 => 16,
+		ADDRESS_DOMAIN => {
+			let mut len = [0u8; 1];
+			stream.read_exact(&mut len).await?;
+			len[0] as usize
+		}
+		_ => return Err(error("invalid bound address type")),
+	};
+	let mut bound = vec![0u8; bound_len + 2];
+	stream.read_exact(&mut bound).await?;
+
+	Ok(())
+}
+
+async fn authenticate<S>(stream: &mut S, credentials: &Credentials) -> Result<(), io::Error>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	if credentials.username.len() > 255 || credentials.password.len() > 255 {
+		return Err(error("credentials are too long"));
+	}
+
+	let mut request = vec![USERNAME_PASSWORD_VERSION, credentials.username.len() as u8];
+	request.extend_from_slice(credentials.username.as_bytes());
+	request.push(credentials.password.len() as u8);
+	request.extend_from_slice(credentials.password.as_bytes());
+	stream.write_all(&request).await?;
+
+	let mut response = [0u8; 2];
+	stream.read_exact(&mut response).await?;
+	if response[0] != USERNAME_PASSWORD_VERSION || response[1] != 0 {
+		return Err(error("authentication failed"));
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{socks5_connect, Credentials};
+	use message::common::NodeAddress;
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::{TcpListener, TcpStream};
+
+	const ONION: &str = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion";
+
+	/// Minimal SOCKS5 proxy, which accepts single connection and then echoes received data.
+	/// Returns the target requested by the client and credentials it used.
+	async fn proxy(listener: TcpListener) -> (String, Option<Credentials>) {
+		let (mut stream, _) = listener.accept().await.unwrap();
+		let mut greeting = [0u8; 3];
+		stream.read_exact(&mut greeting).await.unwrap();
+		assert_eq!(greeting[..2], [5, 1]);
+		stream.write_all(&[5, greeting[2]]).await.unwrap();
+
+		let credentials = if greeting[2] == 2 {
+			let mut header = [0u8; 2];
+			stream.read_exact(&mut header).await.unwrap();
+			let mut username = vec![0u8; header[1] as usize];
+			stream.read_exact(&mut username).await.unwrap();
+			let mut len = [0u8; 1];
+			stream.read_exact(&mut len).await.unwrap();
+			let mut password = vec![0u8; len[0] as usize];
+			stream.read_exact(&mut password).await.unwrap();
+			stream.write_all(&[1, 0]).await.unwrap();
+			Some(Credentials {
+				username: String::from_utf8(username).unwrap(),
+				password: String::from_utf8(password).unwrap(),
+			})
+		} else {
+			None
+		};
+
+		let mut request = [0u8; 5];
+		stream.read_exact(&mut request).await.unwrap();
+		assert_eq!(request[..4], [5, 1, 0, 3]);
+		let mut domain = vec![0u8; request[4] as usize];
+		stream.read_exact(&mut domain).await.unwrap();
+		let mut port = [0u8; 2];
+		stream.read_exact(&mut port).await.unwrap();
+		stream.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).await.unwrap();
+
+		let mut data = [0u8; 4];
+		stream.read_exact(&mut data).await.unwrap();
+		stream.write_all(&data).await.unwrap();
+
+		let target = format!("{}:{}", String::from_utf8(domain).unwrap(), u16::from_be_bytes(port));
+		(target, credentials)
+	}
+
+	#[tokio::test]
+	async fn test_socks5_connect() {
+		let target: NodeAddress = format!("{}:8333", ONION).parse().unwrap();
+		for credentials in [None, Some(Credentials::random())] {
+			let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+			let proxy_address = listener.local_addr().unwrap();
+			let proxy = tokio::spawn(proxy(listener));
+
+			let mut stream = TcpStream::connect(proxy_address).await.unwrap();
+			socks5_connect(&mut stream, &target, credentials.as_ref()).await.unwrap();
+			stream.write_all(b"ping").await.unwrap();
+			let mut echo = [0u8; 4];
+			stream.read_exact(&mut echo).await.unwrap();
+			assert_eq!(&echo, b"ping");
+
+			assert_eq!(proxy.await.unwrap(), (target.to_string(), credentials));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_socks5_connect_refused() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let proxy_address = listener.local_addr().unwrap();
+		tokio::spawn(async move {
+			let (mut stream, _) = listener.accept().await.unwrap();
+			let mut request = [0u8; 10];
+			stream.read_exact(&mut request[..3]).await.unwrap();
+			stream.write_all(&[5, 0]).await.unwrap();
+			stream.read_exact(&mut request).await.unwrap();
+			stream.write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0]).await.unwrap();
+		});
+
+		let mut stream = TcpStream::connect(proxy_address).await.unwrap();
+		let target: NodeAddress = "10.0.0.1:8333".parse().unwrap();
+		let err = socks5_connect(&mut stream, &target, None).await.unwrap_err();
+		assert_eq!(err.to_string(), "SOCKS5 proxy error: connection refused");
+	}
+
+	#[test]
+	fn test_random_credentials() {
+		assert_ne!(Credentials::random(), Credentials::random());
+	}
+}
@@ -0,0 +1,216 @@
+use crate::bytes::Bytes;
+use crate::io::v2_cipher::{
+	decode_contents, encode_contents, V2Receiver, V2Sender, V2Session, ELLSWIFT_LEN, GARBAGE_TERMINATOR_LEN, LENGTH_LEN, MAX_GARBAGE_LEN,
+};
+use crate::io::{read_any_message, read_message, Error, SharedTcpStream};
+use keys::{EllSwiftKeyPair, EllSwiftPublic};
+use message::{deserialize_payload, Command, Error as MessageError, Payload};
+use network::Magic;
+use rand::{Rng, RngCore};
+use tokio::sync::Mutex;
+
+/// Length of the v1 version message header prefix, which identifies v1 peers
+pub const V1_PREFIX_LEN: usize = 16;
+
+/// Framing of the messages, negotiated during the handshake
+pub enum Transport {
+	/// Plaintext messages with network magic and checksum in the header
+	V1,
+	/// BIP324 encrypted packets
+	V2(Box<V2Transport>),
+}
+
+pub struct V2Transport {
+	sender: Mutex<V2Sender>,
+	receiver: Mutex<V2Receiver>,
+	session_id: [u8; 32],
+}
+
+impl V2Transport {
+	/// Identifier of the session, equal on both sides of the connection
+	pub fn session_id(&self) -> &[u8; 32] {
+		&self.session_id
+	}
+}
+
+impl Transport {
+	/// Writes message, serialized with the v1 header
+	pub async fn write_message(&self, stream: &SharedTcpStream, message: &[u8]) -> Result<(), std::io::Error> {
+		match self {
+			Transport::V1 => stream.write_all(message).await,
+			Transport::V2(v2) => {
+				// v1 header is magic (4 bytes), command (12 bytes), payload length (4 bytes) and checksum (4 bytes)
+				let contents = encode_contents(&Command::from(&message[4..16]), &message[24..]);
+				// lock is held until the packet is written, so that packets are sent in the order of encryption
+				let mut sender = v2.sender.lock().await;
+				let packet = sender.encrypt(&contents, &[], false);
+				stream.write_all(&packet).await
+			}
+		}
+	}
+
+	/// Reads next message, skipping v2 decoy packets
+	pub async fn read_message(&self, stream: &SharedTcpStream, magic: Magic) -> Result<(Command, Bytes), Error> {
+		match self {
+			Transport::V1 => read_any_message(stream, magic).await,
+			Transport::V2(v2) => {
+				let mut receiver = v2.receiver.lock().await;
+				loop {
+					if let Some(contents) = read_packet(stream, &mut receiver, &[]).await? {
+						return decode_contents(&contents).map_err(Into::into);
+					}
+				}
+			}
+		}
+	}
+
+	/// Reads and deserializes next message of the given type
+	pub async fn read_payload<M>(&self, stream: &SharedTcpStream, magic: Magic, version: u32) -> Result<M, Error>
+	where
+		M: Payload,
+	{
+		match self {
+			Transport::V1 => read_message(stream, magic, version).await,
+			Transport::V2(_) => {
+				let (command, payload) = self.read_message(stream, magic).await?;
+				if command != M::command() {
+					return Err(MessageError::InvalidCommand.into());
+				}
+				deserialize_payload(&payload, version).map_err(Into::into)
+			}
+		}
+	}
+}
+
+/// Beginning of the v1 version message header: network magic and the command
+pub fn v1_prefix(magic: Magic) -> [u8; V1_PREFIX_LEN] {
+	let mut prefix = [0u8; V1_PREFIX_LEN];
+	prefix[..4].copy_from_slice(&magic.to_le_bytes());
+	prefix[4..11].copy_from_slice(b"version");
+	prefix
+}
+
+/// Starts v2 key exchange as the initiator of the connection
+pub async fn initiate_v2(stream: &SharedTcpStream, magic: Magic) -> Result<Transport, Error> {
+	let key = EllSwiftKeyPair::generate();
+	let garbage = send_key_and_garbage(stream, &key).await?;
+	let mut their_key = [0u8; ELLSWIFT_LEN];
+	stream.read_exact(&mut their_key).await?;
+	complete_v2(stream, magic, key, garbage, &their_key, true).await
+}
+
+/// Responds to v2 key exchange. `prefix` is the beginning of the initiator's key, which is already read.
+pub async fn respond_v2(stream: &SharedTcpStream, magic: Magic, prefix: &[u8]) -> Result<Transport, Error> {
+	let mut their_key = [0u8; ELLSWIFT_LEN];
+	their_key[..prefix.len()].copy_from_slice(prefix);
+	stream.read_exact(&mut their_key[prefix.len()..]).await?;
+	let key = EllSwiftKeyPair::generate();
+	let garbage = send_key_and_garbage(stream, &key).await?;
+	complete_v2(stream, magic, key, garbage, &their_key, false).await
+}
+
+/// Sends our public key followed by random amount of random bytes. Returns sent garbage.
+async fn send_key_and_garbage(stream: &SharedTcpStream, key: &EllSwiftKeyPair) -> Result<Vec<u8>, Error> {
+	let garbage = random_garbage();
+	let mut data = key.public().to_vec();
+	data.extend_from_slice(&garbage);
+	stream.write_all(&data).await?;
+	Ok(garbage)
+}
+
+fn random_garbage() -> Vec<u8> {
+	let mut rng = rand::thread_rng();
+	let mut garbage = vec![0u8; rng.gen_range(0..=MAX_GARBAGE_LEN)];
+	rng.fill_bytes(&mut garbage);
+	garbage
+}
+
+async fn complete_v2(
+	stream: &SharedTcpStream,
+	magic: Magic,
+	key: EllSwiftKeyPair,
+	garbage: Vec<u8>,
+	their_key: &[u8],
+	initiator: bool,
+) -> Result<Transport, Error> {
+	let their_key = EllSwiftPublic::from_slice(their_key).map_err(|_| MessageError::Deserialize)?;
+	let V2Session {
+		mut sender,
+		mut receiver,
+		send_garbage_terminator,
+		recv_garbage_terminator,
+		session_id,
+	} = V2Session::new(&key, &their_key, initiator, magic).map_err(|_| MessageError::Deserialize)?;
+
+	// garbage terminator is followed by the version packet, which authenticates sent garbage
+	let mut data = send_garbage_terminator.to_vec();
+	data.extend(sender.encrypt(&[], &garbage, false));
+	stream.write_all(&data).await?;
+
+	// version packet may be preceded by decoys, only the first packet authenticates received garbage
+	let mut aad = read_garbage(stream, &recv_garbage_terminator).await?;
+	while read_packet(stream, &mut receiver, &aad).await?.is_none() {
+		aad.clear();
+	}
+
+	Ok(Transport::V2(Box::new(V2Transport {
+		sender: Mutex::new(sender),
+		receiver: Mutex::new(receiver),
+		session_id,
+	})))
+}
+
+/// Reads garbage until the garbage terminator. Returns received garbage without the terminator.
+async fn read_garbage(stream: &SharedTcpStream, terminator: &[u8; GARBAGE_TERMINATOR_LEN]) -> Result<Vec<u8>, Error> {
+	let mut garbage = vec![0u8; GARBAGE_TERMINATOR_LEN];
+	stream.read_exact(&mut garbage).await?;
+	while garbage[garbage.len() - GARBAGE_TERMINATOR_LEN..] != terminator[..] {
+		if garbage.len() == MAX_GARBAGE_LEN + GARBAGE_TERMINATOR_LEN {
+			return Err(MessageError::Deserialize.into());
+		}
+		let mut byte = [0u8; 1];
+		stream.read_exact(&mut byte).await?;
+		garbage.push(byte[0]);
+	}
+	garbage.truncate(garbage.len() - GARBAGE_TERMINATOR_LEN);
+	Ok(garbage)
+}
+
+/// Reads and decrypts single packet. Returns None for decoy packets.
+async fn read_packet(stream: &SharedTcpStream, receiver: &mut V2Receiver, aad: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+	let mut length = [0u8; LENGTH_LEN];
+	stream.read_exact(&mut length).await?;
+	let mut packet = vec![0u8; receiver.decrypt_length(length)?];
+	stream.read_exact(&mut packet).await?;
+	receiver.decrypt(aad, &packet).map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{read_garbage, v1_prefix};
+	use crate::io::shared_tcp_stream::SharedTcpStream;
+	use message::types::Version;
+	use message::Message;
+	use network::Network;
+
+	#[test]
+	fn test_v1_prefix() {
+		let magic = Network::Mainnet.magic();
+		let version = Version::default();
+		let message = Message::new(magic, version.version(), &version).unwrap();
+		assert_eq!(&message.as_ref()[..16], &v1_prefix(magic)[..]);
+	}
+
+	#[tokio::test]
+	async fn test_read_garbage() {
+		let terminator = [0xaa; 16];
+		let mut data = vec![1, 2, 3];
+		data.extend_from_slice(&terminator);
+		data.push(4);
+		let stream = SharedTcpStream::new(data.into());
+		assert_eq!(read_garbage(&stream, &terminator).await.unwrap(), vec![1, 2, 3]);
+
+		let stream = SharedTcpStream::new(vec![0u8; 5000].into());
+		assert!(read_garbage(&stream, &terminator).await.is_err());
+	}
+}
@@ -0,0 +1,310 @@
+//! BIP324 v2 transport encryption: key derivation, packet encryption and short message ids.
+//! https://github.com/bitcoin/bips/blob/master/bip-0324.mediawiki
+
+use crate::bytes::Bytes;
+use crypto::{hkdf_sha256_expand, hkdf_sha256_extract, tagged_hash, FSChaCha20, FSChaCha20Poly1305, POLY1305_TAG_LEN};
+use keys::{EllSwiftKeyPair, EllSwiftPublic};
+use message::{Command, Error as MessageError};
+use network::Magic;
+
+/// Length of the ElligatorSwift encoded public key
+pub const ELLSWIFT_LEN: usize = 64;
+/// Length of the garbage terminator
+pub const GARBAGE_TERMINATOR_LEN: usize = 16;
+/// Maximum length of the garbage sent before the garbage terminator
+pub const MAX_GARBAGE_LEN: usize = 4095;
+/// Length of the encrypted contents length
+pub const LENGTH_LEN: usize = 3;
+/// Length of the packet header
+pub const HEADER_LEN: usize = 1;
+/// Header bit, marking decoy packets
+const IGNORE_BIT: u8 = 0x80;
+/// Maximum length of the packet contents: message type and 4MB long payload
+pub const MAX_CONTENTS_LEN: usize = 1 + 12 + 4_000_000;
+
+/// Message types, encoded as single byte. Index in the array plus 1 is the id.
+const SHORT_IDS: [&str; 28] = [
+	"addr",
+	"block",
+	"blocktxn",
+	"cmpctblock",
+	"feefilter",
+	"filteradd",
+	"filterclear",
+	"filterload",
+	"getblocks",
+	"getblocktxn",
+	"getdata",
+	"getheaders",
+	"headers",
+	"inv",
+	"mempool",
+	"merkleblock",
+	"notfound",
+	"ping",
+	"pong",
+	"sendcmpct",
+	"tx",
+	"getcfilters",
+	"cfilter",
+	"getcfheaders",
+	"cfheaders",
+	"getcfcheckpt",
+	"cfcheckpt",
+	"addrv2",
+];
+
+/// Encrypts outgoing packets
+pub struct V2Sender {
+	length: FSChaCha20,
+	packet: FSChaCha20Poly1305,
+}
+
+impl V2Sender {
+	/// Encrypts packet contents. Packets with `ignore` flag are decoys and are dropped by the receiver.
+	pub fn encrypt(&mut self, contents: &[u8], aad: &[u8], ignore: bool) -> Vec<u8> {
+		let mut plaintext = Vec::with_capacity(HEADER_LEN + contents.len());
+		plaintext.push(if ignore { IGNORE_BIT } else { 0 });
+		plaintext.extend_from_slice(contents);
+
+		let mut packet = (contents.len() as u32).to_le_bytes()[..LENGTH_LEN].to_vec();
+		self.length.crypt(&mut packet);
+		packet.extend(self.packet.encrypt(aad, &plaintext));
+		packet
+	}
+}
+
+/// Decrypts incoming packets
+pub struct V2Receiver {
+	length: FSChaCha20,
+	packet: FSChaCha20Poly1305,
+}
+
+impl V2Receiver {
+	/// Decrypts length of the contents and returns the number of bytes remaining in the packet
+	pub fn decrypt_length(&mut self, mut length: [u8; LENGTH_LEN]) -> Result<usize, MessageError> {
+		self.length.crypt(&mut length);
+		let contents_len = u32::from_le_bytes([length[0], length[1], length[2], 0]) as usize;
+		if contents_len > MAX_CONTENTS_LEN {
+			return Err(MessageError::Deserialize);
+		}
+		Ok(HEADER_LEN + contents_len + POLY1305_TAG_LEN)
+	}
+
+	/// Authenticates and decrypts the rest of the packet. Returns None for decoy packets.
+	pub fn decrypt(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Option<Vec<u8>>, MessageError> {
+		let mut plaintext = self.packet.decrypt(aad, ciphertext).ok_or(MessageError::InvalidChecksum)?;
+		if plaintext[0] & IGNORE_BIT != 0 {
+			return Ok(None);
+		}
+		Ok(Some(plaintext.split_off(HEADER_LEN)))
+	}
+}
+
+/// Ciphers and secrets of the v2 session, derived from the ECDH shared secret
+pub struct V2Session {
+	pub sender: V2Sender,
+	pub receiver: V2Receiver,
+	pub send_garbage_terminator: [u8; GARBAGE_TERMINATOR_LEN],
+	pub recv_garbage_terminator: [u8; GARBAGE_TERMINATOR_LEN],
+	pub session_id: [u8; 32],
+}
+
+impl V2Session {
+	pub fn new(ours: &EllSwiftKeyPair, theirs: &EllSwiftPublic, initiator: bool, magic: Magic) -> Result<Self, keys::Error> {
+		let ecdh_x = ours.ecdh_x(theirs)?;
+		let (initiator_key, responder_key) = if initiator {
+			(ours.public(), theirs)
+		} else {
+			(theirs, ours.public())
+		};
+		let mut preimage = Vec::with_capacity(ELLSWIFT_LEN * 2 + 32);
+		preimage.extend_from_slice(initiator_key);
+		preimage.extend_from_slice(responder_key);
+		preimage.extend_from_slice(&ecdh_x);
+		let shared_secret = tagged_hash("bip324_ellswift_xonly_ecdh", &preimage);
+
+		let mut salt = b"bitcoin_v2_shared_secret".to_vec();
+		salt.extend_from_slice(&magic.to_le_bytes());
+		let prk = hkdf_sha256_extract(&salt, &shared_secret[..]);
+		let expand = |label: &str| {
+			let mut key = [0u8; 32];
+			key.copy_from_slice(&hkdf_sha256_expand(&prk, label.as_bytes(), 32));
+			key
+		};
+
+		let initiator_ciphers = (
+			FSChaCha20::new(expand("initiator_L")),
+			FSChaCha20Poly1305::new(expand("initiator_P")),
+		);
+		let responder_ciphers = (
+			FSChaCha20::new(expand("responder_L")),
+			FSChaCha20Poly1305::new(expand("responder_P")),
+		);
+		let garbage_terminators = expand("garbage_terminators");
+		let mut initiator_terminator = [0u8; GARBAGE_TERMINATOR_LEN];
+		initiator_terminator.copy_from_slice(&garbage_terminators[..GARBAGE_TERMINATOR_LEN]);
+		let mut responder_terminator = [0u8; GARBAGE_TERMINATOR_LEN];
+		responder_terminator.copy_from_slice(&garbage_terminators[GARBAGE_TERMINATOR_LEN..]);
+
+		let (send, recv, send_garbage_terminator, recv_garbage_terminator) = if initiator {
+			(initiator_ciphers, responder_ciphers, initiator_terminator, responder_terminator)
+		} else {
+			(responder_ciphers, initiator_ciphers, responder_terminator, initiator_terminator)
+		};
+
+		Ok(V2Session {
+			sender: V2Sender {
+				length: send.0,
+				packet: send.1,
+			},
+			receiver: V2Receiver {
+				length: recv.0,
+				packet: recv.1,
+			},
+			send_garbage_terminator,
+			recv_garbage_terminator,
+			session_id: expand("session_id"),
+		})
+	}
+}
+
+/// Encodes message type and payload as the packet contents
+pub fn encode_contents(command: &Command, payload: &[u8]) -> Vec<u8> {
+	let mut contents = Vec::with_capacity(1 + 12 + payload.len());
+	match SHORT_IDS.iter().position(|name| command == name) {
+		Some(index) => contents.push(index as u8 + 1),
+		None => {
+			contents.push(0);
+			contents.extend_from_slice(&command[..]);
+		}
+	}
+	contents.extend_from_slice(payload);
+	contents
+}
+
+/// Decodes message type and payload from the packet contents
+pub fn decode_contents(contents: &[u8]) -> Result<(Command, Bytes), MessageError> {
+	match contents.first() {
+		Some(0) if contents.len() >= 13 => Ok((Command::from(&contents[1..13]), contents[13..].to_vec().into())),
+		Some(&id) if id != 0 && (id as usize) <= SHORT_IDS.len() => {
+			let command = SHORT_IDS[id as usize - 1].parse()?;
+			Ok((command, contents[1..].to_vec().into()))
+		}
+		_ => Err(MessageError::InvalidCommand),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode_contents, encode_contents, V2Session, SHORT_IDS};
+	use crypto::{FromHex, ToHex};
+	use keys::{EllSwiftKeyPair, EllSwiftPublic};
+	use message::{Command, Error as MessageError};
+	use network::Network;
+
+	#[test]
+	fn test_short_message_ids() {
+		let contents = encode_contents(&"ping".into(), &[1, 2, 3]);
+		assert_eq!(contents, vec![18, 1, 2, 3]);
+		assert_eq!(decode_contents(&contents).unwrap(), ("ping".into(), vec![1, 2, 3].into()));
+
+		let contents = encode_contents(&"version".into(), &[4]);
+		assert_eq!(contents, b"\x00version\x00\x00\x00\x00\x00\x04".to_vec());
+		assert_eq!(decode_contents(&contents).unwrap(), ("version".into(), vec![4].into()));
+
+		for (index, name) in SHORT_IDS.iter().enumerate() {
+			let command: Command = name.parse().unwrap();
+			assert_eq!(encode_contents(&command, &[])[0] as usize, index + 1);
+		}
+		assert_eq!(decode_contents(&[29]), Err(MessageError::InvalidCommand));
+		assert_eq!(decode_contents(&[0, 1, 2]), Err(MessageError::InvalidCommand));
+		assert_eq!(decode_contents(&[]), Err(MessageError::InvalidCommand));
+	}
+
+	#[test]
+	fn test_v2_session() {
+		let magic = Network::Mainnet.magic();
+		let initiator_key = EllSwiftKeyPair::generate();
+		let responder_key = EllSwiftKeyPair::generate();
+		let mut initiator = V2Session::new(&initiator_key, responder_key.public(), true, magic).unwrap();
+		let mut responder = V2Session::new(&responder_key, initiator_key.public(), false, magic).unwrap();
+		assert_eq!(initiator.session_id, responder.session_id);
+		assert_eq!(initiator.send_garbage_terminator, responder.recv_garbage_terminator);
+		assert_eq!(initiator.recv_garbage_terminator, responder.send_garbage_terminator);
+		assert_ne!(initiator.send_garbage_terminator, initiator.recv_garbage_terminator);
+
+		for (contents, aad, ignore) in [(&b""[..], &b"garbage"[..], false), (b"decoy", b"", true), (b"ping", b"", false)].iter() {
+			let packet = initiator.sender.encrypt(contents, aad, *ignore);
+			let mut length = [0u8; 3];
+			length.copy_from_slice(&packet[..3]);
+			assert_eq!(responder.receiver.decrypt_length(length).unwrap(), packet.len() - 3);
+			let decrypted = responder.receiver.decrypt(aad, &packet[3..]).unwrap();
+			assert_eq!(decrypted, if *ignore { None } else { Some(contents.to_vec()) });
+		}
+
+		// tampered packet fails authentication
+		let mut packet = responder.sender.encrypt(b"pong", b"", false);
+		packet[5] ^= 1;
+		let mut length = [0u8; 3];
+		length.copy_from_slice(&packet[..3]);
+		initiator.receiver.decrypt_length(length).unwrap();
+		assert_eq!(initiator.receiver.decrypt(b"", &packet[3..]), Err(MessageError::InvalidChecksum));
+	}
+
+	/// Row in the format of https://github.com/bitcoin/bips/blob/master/bip-0324/packet_encoding_test_vectors.csv,
+	/// limited to the columns which are observable through `V2Session`
+	struct PacketVector {
+		in_priv_ours: &'static str,
+		in_ellswift_ours: &'static str,
+		in_ellswift_theirs: &'static str,
+		in_initiating: bool,
+		in_idx: usize,
+		in_contents: &'static str,
+		in_multiply: usize,
+		in_aad: &'static str,
+		in_ignore: bool,
+		out_session_id: &'static str,
+		out_ciphertext: &'static str,
+		out_ciphertext_endswith: &'static str,
+	}
+
+	/// Rows from https://github.com/bitcoin/bips/blob/master/bip-0324/packet_encoding_test_vectors.csv
+	const PACKET_VECTORS: [PacketVector; 1] = [PacketVector {
+		in_priv_ours: "61062ea5071d800bbfd59e2e8b53d47d194b095ae5a4df04936b49772ef0d4d7",
+		in_ellswift_ours: "ec0adff257bbfe500c188c80b4fdd640f6b45a482bbc15fc7cef5931deff0aa186f6eb9bba7b85dc4dcc28b28722de1e3d9108b985e2967045668f66098e475b",
+		in_ellswift_theirs: "a4a94dfce69b4a2a0a099313d10f9f7e7d649d60501c9e1d274c300e0d89aafaffffffffffffffffffffffffffffffffffffffffffffffffffffffff8faf88d5",
+		in_initiating: true,
+		in_idx: 1,
+		in_contents: "8e",
+		in_multiply: 1,
+		in_aad: "",
+		in_ignore: false,
+		out_session_id: "ce72dffb015da62b0d0f5474cab8bc72605225b0cee3f62312ec680ec5f41ba5",
+		out_ciphertext: "7530d2a18720162ac09c25329a60d75adf36eda3c3",
+		out_ciphertext_endswith: "",
+	}];
+
+	#[test]
+	fn test_packet_encoding_vectors() {
+		let from_hex = |hex: &str| -> Vec<u8> { FromHex::from_hex(hex).unwrap() };
+		for vector in PACKET_VECTORS.iter() {
+			let ours = EllSwiftPublic::from_slice(&from_hex(vector.in_ellswift_ours)).unwrap();
+			let ours = EllSwiftKeyPair::from_parts(&from_hex(vector.in_priv_ours), ours).unwrap();
+			let theirs = EllSwiftPublic::from_slice(&from_hex(vector.in_ellswift_theirs)).unwrap();
+			let mut session = V2Session::new(&ours, &theirs, vector.in_initiating, Network::Mainnet.magic()).unwrap();
+			assert_eq!(session.session_id[..].to_hex(), vector.out_session_id);
+
+			for _ in 0..vector.in_idx {
+				session.sender.encrypt(&[], &[], false);
+			}
+			let contents = from_hex(vector.in_contents).repeat(vector.in_multiply);
+			let ciphertext = session.sender.encrypt(&contents, &from_hex(vector.in_aad), vector.in_ignore);
+			if vector.out_ciphertext.is_empty() {
+				assert!(ciphertext.to_hex().ends_with(vector.out_ciphertext_endswith));
+			} else {
+				assert_eq!(ciphertext.to_hex(), vector.out_ciphertext);
+			}
+		}
+	}
+}
@@ -6,6 +6,7 @@ extern crate log;
 extern crate csv;
 
 extern crate bitcrypto as crypto;
+extern crate keys;
 extern crate message;
 extern crate network;
 extern crate primitives;
@@ -25,9 +26,9 @@ pub use crate::p2p::{Context, P2P};
 pub use config::Config;
 pub use io::read_any_message;
 pub use net::Config as NetConfig;
-pub use net::{connect, Connection};
+pub use net::{connect, Connection, NetTotals, Proxy, Resolver, SystemResolver, TorControl, UploadTarget};
 pub use protocol::{
 	InboundSyncConnection, InboundSyncConnectionRef, InboundSyncConnectionState, InboundSyncConnectionStateRef, LocalSyncNode,
 	LocalSyncNodeRef, OutboundSyncConnection, OutboundSyncConnectionRef,
 };
-pub use util::{Direction, InternetProtocol, NodeTableError, PeerId, PeerInfo};
+pub use util::{BanEntry, Direction, InternetProtocol, NodeTableError, PeerId, PeerInfo, Subnet, DEFAULT_BAN_TIME};
@@ -1,4 +1,4 @@
-use crate::io::{accept_handshake, Error, SharedTcpStream};
+use crate::io::{accept_handshake, accept_handshake_v2, Error, SharedTcpStream, Transport};
 use crate::net::{Config, Connection};
 use std::net;
 use std::time::Duration;
@@ -7,22 +7,42 @@ use tokio::time::timeout;
 
 pub async fn accept_connection<'a>(stream: TcpStream, config: &Config, address: net::SocketAddr) -> Result<Connection, Error> {
 	let shared_stream: SharedTcpStream = stream.into();
+	let address = address.into();
 	let handshake = async {
-		let handshake_result = accept_handshake(
-			&shared_stream,
-			config.network.magic(),
-			config.version(&address),
-			config.protocol_minimum,
-		)
-		.await?;
+		let magic = config.network.magic();
+		let (handshake_result, transport) = if config.v2_transport {
+			accept_handshake_v2(
+				&shared_stream,
+				magic,
+				config.version(&address),
+				config.protocol_minimum,
+				config.txrcncl_salt(),
+			)
+			.await?
+		} else {
+			let handshake_result = accept_handshake(
+				&shared_stream,
+				magic,
+				config.version(&address),
+				config.protocol_minimum,
+				config.txrcncl_salt(),
+			)
+			.await?;
+			(handshake_result, Transport::V1)
+		};
 
 		Ok(Connection {
 			stream: shared_stream,
+			transport,
 			services: handshake_result.version.services(),
 			version: handshake_result.negotiated_version,
 			version_message: handshake_result.version,
-			magic: config.network.magic(),
+			magic,
 			address,
+			addrv2: handshake_result.addrv2,
+			wtxid_relay: handshake_result.wtxid_relay,
+			local_txrcncl_salt: handshake_result.local_txrcncl_salt,
+			txrcncl_salt: handshake_result.txrcncl_salt,
 		})
 	};
 
@@ -0,0 +1,182 @@
+//! Node-wide bandwidth accounting. Upload is optionally limited by the daily target: once the target is close to
+//! being exhausted, historical blocks are not served anymore, so that the budget is left for the new blocks and transactions.
+
+/// Upload target is measured over this timeframe.
+pub const UPLOAD_TARGET_TIMEFRAME: u64 = 24 * 60 * 60;
+/// Max size of the serialized block.
+const MAX_BLOCK_SERIALIZED_SIZE: u64 = 4_000_000;
+/// Average time between blocks.
+const BLOCK_INTERVAL: u64 = 10 * 60;
+
+/// Upload target state within the current cycle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadTarget {
+	/// Max number of bytes we are going to send during single cycle.
+	pub target: u64,
+	/// Is target reached?
+	pub target_reached: bool,
+	/// Are we still serving historical blocks?
+	pub serve_historical_blocks: bool,
+	/// Bytes left in the current cycle.
+	pub bytes_left_in_cycle: u64,
+	/// Seconds left in the current cycle.
+	pub time_left_in_cycle: u64,
+}
+
+/// Total network traffic of the node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetTotals {
+	/// Total bytes received.
+	pub total_recv: u64,
+	/// Total bytes sent.
+	pub total_sent: u64,
+	/// Upload target, if it is set.
+	pub upload_target: Option<UploadTarget>,
+}
+
+/// Node-wide traffic counters.
+#[derive(Debug, Default)]
+pub struct Bandwidth {
+	total_recv: u64,
+	total_sent: u64,
+	/// Max number of bytes to send during the `UPLOAD_TARGET_TIMEFRAME`.
+	max_upload_target: Option<u64>,
+	/// Time when the current cycle has started.
+	cycle_start: u64,
+	/// Bytes sent during the current cycle.
+	sent_in_cycle: u64,
+}
+
+impl Bandwidth {
+	pub fn new(max_upload_target: Option<u64>) -> Self {
+		Bandwidth {
+			max_upload_target: max_upload_target.filter(|target| *target != 0),
+			..Default::default()
+		}
+	}
+
+	pub fn report_recv(&mut self, bytes: usize) {
+		self.total_recv += bytes as u64;
+	}
+
+	pub fn report_send(&mut self, bytes: usize, now: u64) {
+		self.total_sent += bytes as u64;
+
+		if self.max_upload_target.is_some() {
+			if now >= self.cycle_start + UPLOAD_TARGET_TIMEFRAME {
+				self.cycle_start = now;
+				self.sent_in_cycle = 0;
+			}
+			self.sent_in_cycle += bytes as u64;
+		}
+	}
+
+	/// Returns true if the upload target is reached. When `historical` is true, the buffer for serving
+	/// the new blocks during the rest of the cycle is also taken into account.
+	pub fn is_upload_target_reached(&self, historical: bool, now: u64) -> bool {
+		let target = match self.max_upload_target {
+			Some(target) => target,
+			None => return false,
+		};
+
+		let sent_in_cycle = self.sent_in_cycle(now);
+		if historical {
+			let buffer = self.time_left_in_cycle(now) / BLOCK_INTERVAL * MAX_BLOCK_SERIALIZED_SIZE;
+			buffer >= target || sent_in_cycle >= target - buffer
+		} else {
+			sent_in_cycle >= target
+		}
+	}
+
+	pub fn totals(&self, now: u64) -> NetTotals {
+		NetTotals {
+			total_recv: self.total_recv,
+			total_sent: self.total_sent,
+			upload_target: self.max_upload_target.map(|target| UploadTarget {
+				target,
+				target_reached: self.is_upload_target_reached(false, now),
+				serve_historical_blocks: !self.is_upload_target_reached(true, now),
+				bytes_left_in_cycle: target.saturating_sub(self.sent_in_cycle(now)),
+				time_left_in_cycle: self.time_left_in_cycle(now),
+			}),
+		}
+	}
+
+	fn sent_in_cycle(&self, now: u64) -> u64 {
+		if now >= self.cycle_start + UPLOAD_TARGET_TIMEFRAME {
+			0
+		} else {
+			self.sent_in_cycle
+		}
+	}
+
+	fn time_left_in_cycle(&self, now: u64) -> u64 {
+		(self.cycle_start + UPLOAD_TARGET_TIMEFRAME).saturating_sub(now)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Bandwidth, NetTotals, UploadTarget, UPLOAD_TARGET_TIMEFRAME};
+
+	const NOW: u64 = 1_600_000_000;
+	const MB: u64 = 1_000_000;
+
+	#[test]
+	fn totals_without_upload_target() {
+		let mut bandwidth = Bandwidth::new(None);
+		bandwidth.report_recv(100);
+		bandwidth.report_send(200, NOW);
+		bandwidth.report_send(300, NOW);
+		assert!(!bandwidth.is_upload_target_reached(true, NOW));
+		assert_eq!(
+			bandwidth.totals(NOW),
+			NetTotals {
+				total_recv: 100,
+				total_sent: 500,
+				upload_target: None,
+			}
+		);
+	}
+
+	#[test]
+	fn historical_blocks_are_limited_before_target_is_reached() {
+		let mut bandwidth = Bandwidth::new(Some(1000 * MB));
+		bandwidth.report_send(100 * MB as usize, NOW);
+		assert!(!bandwidth.is_upload_target_reached(true, NOW));
+
+		// half of the cycle has passed => 72 blocks are expected during the rest of the cycle => 288MB buffer
+		let now = NOW + UPLOAD_TARGET_TIMEFRAME / 2;
+		bandwidth.report_send(650 * MB as usize, now);
+		assert!(bandwidth.is_upload_target_reached(true, now));
+		assert!(!bandwidth.is_upload_target_reached(false, now));
+		assert_eq!(
+			bandwidth.totals(now).upload_target,
+			Some(UploadTarget {
+				target: 1000 * MB,
+				target_reached: false,
+				serve_historical_blocks: false,
+				bytes_left_in_cycle: 250 * MB,
+				time_left_in_cycle: UPLOAD_TARGET_TIMEFRAME / 2,
+			})
+		);
+
+		bandwidth.report_send(250 * MB as usize, now);
+		assert!(bandwidth.is_upload_target_reached(false, now));
+	}
+
+	#[test]
+	fn upload_target_is_reset_after_cycle() {
+		let mut bandwidth = Bandwidth::new(Some(MB));
+		bandwidth.report_send(MB as usize, NOW);
+		assert!(bandwidth.is_upload_target_reached(false, NOW));
+
+		let now = NOW + UPLOAD_TARGET_TIMEFRAME;
+		assert!(!bandwidth.is_upload_target_reached(false, now));
+		bandwidth.report_send(100, now);
+		let upload_target = bandwidth.totals(now).upload_target.unwrap();
+		assert_eq!(upload_target.bytes_left_in_cycle, MB - 100);
+		assert_eq!(upload_target.time_left_in_cycle, UPLOAD_TARGET_TIMEFRAME);
+		assert_eq!(bandwidth.totals(now).total_sent, MB + 100);
+	}
+}
@@ -1,19 +1,22 @@
 use crate::bytes::Bytes;
-use crate::io::{read_any_message, Error, SharedTcpStream};
+use crate::io::{Error, SharedTcpStream, Transport};
+use crate::net::Flow;
 use crate::session::Session;
 use crate::PeerInfo;
 use message::Command;
 
 pub struct Channel {
 	stream: SharedTcpStream,
+	transport: Transport,
 	peer_info: PeerInfo,
 	session: Session,
 }
 
 impl Channel {
-	pub fn new(stream: SharedTcpStream, peer_info: PeerInfo, session: Session) -> Self {
+	pub fn new(stream: SharedTcpStream, transport: Transport, peer_info: PeerInfo, session: Session) -> Self {
 		Channel {
 			stream,
+			transport,
 			peer_info,
 			session,
 		}
@@ -23,11 +26,11 @@ impl Channel {
 	where
 		T: AsRef<[u8]>,
 	{
-		self.stream.write_all(message.as_ref()).await
+		self.transport.write_message(&self.stream, message.as_ref()).await
 	}
 
 	pub async fn read_message(&self) -> Result<(Command, Bytes), Error> {
-		read_any_message(&self.stream, self.peer_info.magic).await
+		self.transport.read_message(&self.stream, self.peer_info.magic).await
 	}
 
 	pub async fn shutdown(&self) {
@@ -43,6 +46,18 @@ impl Channel {
 		self.peer_info.clone()
 	}
 
+	/// Returns peer info along with the up-to-date traffic and transactions reconciliation statistics
+	pub fn peer_info_with_stats(&self) -> PeerInfo {
+		let mut peer_info = self.peer_info.clone();
+		let stats = self.session.stats().lock();
+		peer_info.txreconciliation = stats.txreconciliation;
+		peer_info.bytes_sent = stats.total_send;
+		peer_info.bytes_recv = stats.total_recv;
+		peer_info.bytes_sent_per_msg = stats.bytes_per_msg(Flow::Send);
+		peer_info.bytes_recv_per_msg = stats.bytes_per_msg(Flow::Receive);
+		peer_info
+	}
+
 	pub fn session(&self) -> &Session {
 		&self.session
 	}
@@ -1,5 +1,5 @@
 use crate::util::nonce::{NonceGenerator, RandomNonce};
-use message::common::{NetAddress, Services};
+use message::common::{NetAddress, NetworkAddress, NodeAddress, Services};
 use message::types::version::{Version, V0, V106, V70001};
 use network::Network;
 use primitives::time::{RealTime, Time};
@@ -15,20 +15,67 @@ pub struct Config {
 	pub user_agent: String,
 	pub start_height: u32,
 	pub relay: bool,
+	/// Use BIP324 encrypted transport, falling back to v1 if the peer does not support it.
+	pub v2_transport: bool,
+	/// Announce transactions using the set reconciliation (BIP330) to the peers, which are supporting it.
+	pub txreconciliation: bool,
+	/// SOCKS5 proxy used for all outbound connections.
+	pub proxy: Option<Proxy>,
+	/// SOCKS5 proxy used for connections to Tor onion services. Defaults to `proxy`.
+	pub onion_proxy: Option<Proxy>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proxy {
+	/// Address of the SOCKS5 proxy.
+	pub address: SocketAddr,
+	/// Use random credentials for every connection, so that Tor uses different circuit for each of them.
+	pub randomize_credentials: bool,
 }
 
 impl Config {
-	pub fn version(&self, to: &SocketAddr) -> Version {
+	/// Returns proxy, which should be used to connect to the address.
+	pub fn proxy_for(&self, address: &NetworkAddress) -> Option<&Proxy> {
+		match *address {
+			NetworkAddress::IpV4(_) | NetworkAddress::IpV6(_) => self.proxy.as_ref(),
+			NetworkAddress::TorV3(_) => self.onion_proxy.as_ref().or(self.proxy.as_ref()),
+			NetworkAddress::I2P(_) | NetworkAddress::Cjdns(_) => None,
+		}
+	}
+
+	/// Returns random salt for the `sendtxrcncl` message, if transactions reconciliation is enabled.
+	pub fn txrcncl_salt(&self) -> Option<u64> {
+		if self.txreconciliation && self.relay {
+			Some(rand::random())
+		} else {
+			None
+		}
+	}
+
+	/// Returns true if onion services are reachable through the proxy.
+	pub fn is_onion_reachable(&self) -> bool {
+		self.onion_proxy.is_some() || self.proxy.is_some()
+	}
+
+	pub fn version(&self, to: &NodeAddress) -> Version {
+		// addresses of the other networks can not be represented in the version message
+		let receiver = match to.address.ip() {
+			Some(ip) if to.address.is_addr_v1_compatible() => NetAddress {
+				services: self.services,
+				address: ip.into(),
+				port: to.port.into(),
+			},
+			_ => NetAddress {
+				services: self.services,
+				..Default::default()
+			},
+		};
 		Version::V70001(
 			V0 {
 				version: self.protocol_version,
 				services: self.services,
 				timestamp: RealTime.now().as_secs(),
-				receiver: NetAddress {
-					services: self.services,
-					address: to.ip().into(),
-					port: to.port().into(),
-				},
+				receiver,
 			},
 			V106 {
 				from: NetAddress {
@@ -1,23 +1,83 @@
-use crate::io::{handshake, Error, SharedTcpStream};
+use crate::io::{handshake, handshake_v2, Credentials, Error, HandshakeResult, SharedTcpStream, Transport};
 use crate::net::{Config, Connection};
-use std::net::SocketAddr;
+use message::common::{NodeAddress, Services};
+use std::io;
 use std::time::Duration;
 use tokio::time::timeout;
 
-pub async fn connect<'a>(address: &SocketAddr, config: &Config) -> Result<Connection, Error> {
-	let stream = SharedTcpStream::connect(address).await?;
-	let connect = async {
-		let handshake = handshake(&stream, config.network.magic(), config.version(address), config.protocol_minimum).await?;
+/// Connects to the node. V2 transport is only tried if the node advertises `NODE_P2P_V2` service.
+/// Services of unknown (manually added) nodes are not known => v2 transport is tried with fallback to v1.
+pub async fn connect<'a>(address: &NodeAddress, services: Option<Services>, config: &Config) -> Result<Connection, Error> {
+	if config.v2_transport && services.is_none_or(|services| services.p2p_v2()) {
+		let stream = open_stream(address, config).await?;
+		let connect = handshake_v2(
+			&stream,
+			config.network.magic(),
+			config.version(address),
+			config.protocol_minimum,
+			config.txrcncl_salt(),
+		);
+		match timeout(Duration::new(5, 0), connect).await {
+			Ok(Ok((handshake, transport))) => return Ok(connection(stream, transport, handshake, address, config)),
+			// peer does not support v2 transport, reconnect and use v1
+			Ok(Err(err)) => trace!("V2 handshake with {} failed with {}", address, err),
+			Err(_) => trace!("V2 handshake with {} timed out", address),
+		}
+		stream.shutdown().await;
+	}
 
-		Ok(Connection {
-			stream,
-			services: handshake.version.services(),
-			version: handshake.negotiated_version,
-			version_message: handshake.version,
-			magic: config.network.magic(),
-			address: *address,
-		})
+	let stream = open_stream(address, config).await?;
+	let connect = async {
+		let handshake = handshake(
+			&stream,
+			config.network.magic(),
+			config.version(address),
+			config.protocol_minimum,
+			config.txrcncl_salt(),
+		)
+		.await?;
+		Ok(connection(stream, Transport::V1, handshake, address, config))
 	};
 
 	timeout(Duration::new(5, 0), connect).await?
 }
+
+/// Opens tcp stream to the address, either directly or through the configured proxy
+async fn open_stream(address: &NodeAddress, config: &Config) -> Result<SharedTcpStream, io::Error> {
+	if let Some(proxy) = config.proxy_for(&address.address) {
+		// every connection gets its own credentials, so that Tor isolates it from the others
+		let credentials = if proxy.randomize_credentials {
+			Some(Credentials::random())
+		} else {
+			None
+		};
+		return SharedTcpStream::connect_socks5(&proxy.address, address, credentials.as_ref()).await;
+	}
+
+	match address.socket_addr() {
+		Some(socket) => SharedTcpStream::connect(&socket).await,
+		None => Err(io::Error::other(format!("No proxy to reach {}", address))),
+	}
+}
+
+fn connection(
+	stream: SharedTcpStream,
+	transport: Transport,
+	handshake: HandshakeResult,
+	address: &NodeAddress,
+	config: &Config,
+) -> Connection {
+	Connection {
+		stream,
+		transport,
+		services: handshake.version.services(),
+		version: handshake.negotiated_version,
+		version_message: handshake.version,
+		magic: config.network.magic(),
+		address: *address,
+		addrv2: handshake.addrv2,
+		wtxid_relay: handshake.wtxid_relay,
+		local_txrcncl_salt: handshake.local_txrcncl_salt,
+		txrcncl_salt: handshake.txrcncl_salt,
+	}
+}
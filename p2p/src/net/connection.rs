@@ -1,14 +1,22 @@
-use crate::io::SharedTcpStream;
-use message::common::Services;
+use crate::io::{SharedTcpStream, Transport};
+use message::common::{NodeAddress, Services};
 use message::types;
 use network::Magic;
-use std::net;
 
 pub struct Connection {
 	pub stream: SharedTcpStream,
+	pub transport: Transport,
 	pub version: u32,
 	pub version_message: types::Version,
 	pub magic: Magic,
 	pub services: Services,
-	pub address: net::SocketAddr,
+	pub address: NodeAddress,
+	/// Peer signalled support of the `addrv2` message during the handshake
+	pub addrv2: bool,
+	/// Peer signalled support of the wtxid-based transaction relay during the handshake
+	pub wtxid_relay: bool,
+	/// Salt, which we have sent in the `sendtxrcncl` message during the handshake
+	pub local_txrcncl_salt: Option<u64>,
+	/// Salt of the peer, received in the `sendtxrcncl` message during the handshake
+	pub txrcncl_salt: Option<u64>,
 }
@@ -1,25 +1,31 @@
 use std::cmp;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-/// Counts number of open inbound and outbound connections.
+/// Counts number of open inbound, outbound and block-relay-only outbound connections.
 pub struct ConnectionCounter {
 	/// Current number of inbound connections.
 	current_inbound_connections: AtomicUsize,
 	/// Current number of outbound connections.
 	current_outbound_connections: AtomicUsize,
+	/// Current number of block-relay-only outbound connections.
+	current_block_relay_connections: AtomicUsize,
 	/// Maximum number of inbound connections.
 	max_inbound_connections: u32,
 	/// Maximum number of outbound connections.
 	max_outbound_connections: u32,
+	/// Maximum number of block-relay-only outbound connections.
+	max_block_relay_connections: u32,
 }
 
 impl ConnectionCounter {
-	pub fn new(max_inbound_connections: u32, max_outbound_connections: u32) -> Self {
+	pub fn new(max_inbound_connections: u32, max_outbound_connections: u32, max_block_relay_connections: u32) -> Self {
 		ConnectionCounter {
 			current_inbound_connections: AtomicUsize::new(0),
 			current_outbound_connections: AtomicUsize::new(0),
+			current_block_relay_connections: AtomicUsize::new(0),
 			max_inbound_connections,
 			max_outbound_connections,
+			max_block_relay_connections,
 		}
 	}
 
@@ -45,6 +51,17 @@ impl ConnectionCounter {
 		self.current_outbound_connections.fetch_sub(1, Ordering::AcqRel);
 	}
 
+	/// Increases block-relay-only connections counter by 1.
+	pub fn note_new_block_relay_connection(&self) {
+		self.current_block_relay_connections.fetch_add(1, Ordering::AcqRel);
+	}
+
+	/// Decreases block-relay-only connections counter by 1.
+	/// If it underflows, it means, that there is a logic error.
+	pub fn note_close_block_relay_connection(&self) {
+		self.current_block_relay_connections.fetch_sub(1, Ordering::AcqRel);
+	}
+
 	/// Returns number of inbound connections needed to reach the maximum
 	pub fn inbound_connections_needed(&self) -> u32 {
 		let ic = self.inbound_connections();
@@ -57,6 +74,12 @@ impl ConnectionCounter {
 		oc.1 - cmp::min(oc.0, oc.1)
 	}
 
+	/// Returns number of block-relay-only connections needed to reach the maximum
+	pub fn block_relay_connections_needed(&self) -> u32 {
+		let bc = self.block_relay_connections();
+		bc.1 - cmp::min(bc.0, bc.1)
+	}
+
 	/// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
 	pub fn inbound_connections(&self) -> (u32, u32) {
 		let current = self.current_inbound_connections.load(Ordering::Acquire) as u32;
@@ -68,6 +91,12 @@ impl ConnectionCounter {
 		let current = self.current_outbound_connections.load(Ordering::Acquire) as u32;
 		(current, self.max_outbound_connections)
 	}
+
+	/// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
+	pub fn block_relay_connections(&self) -> (u32, u32) {
+		let current = self.current_block_relay_connections.load(Ordering::Acquire) as u32;
+		(current, self.max_block_relay_connections)
+	}
 }
 
 #[cfg(test)]
@@ -76,7 +105,7 @@ mod tests {
 
 	#[test]
 	fn test_inbound_connection_counter() {
-		let cc = ConnectionCounter::new(5, 10);
+		let cc = ConnectionCounter::new(5, 10, 0);
 		assert_eq!(cc.inbound_connections_needed(), 5);
 		assert_eq!(cc.inbound_connections(), (0, 5));
 		cc.note_new_inbound_connection();
@@ -98,7 +127,7 @@ mod tests {
 
 	#[test]
 	fn test_outbound_connection_counter() {
-		let cc = ConnectionCounter::new(0, 4);
+		let cc = ConnectionCounter::new(0, 4, 0);
 		assert_eq!(cc.outbound_connections_needed(), 4);
 		assert_eq!(cc.outbound_connections(), (0, 4));
 		cc.note_new_outbound_connection();
@@ -109,4 +138,17 @@ mod tests {
 		assert_eq!(cc.outbound_connections_needed(), 3);
 		assert_eq!(cc.outbound_connections(), (1, 4));
 	}
+
+	#[test]
+	fn test_block_relay_connection_counter() {
+		let cc = ConnectionCounter::new(0, 4, 2);
+		cc.note_new_outbound_connection();
+		cc.note_new_block_relay_connection();
+		assert_eq!(cc.outbound_connections_needed(), 3);
+		assert_eq!(cc.block_relay_connections_needed(), 1);
+		assert_eq!(cc.block_relay_connections(), (1, 2));
+		cc.note_close_block_relay_connection();
+		assert_eq!(cc.block_relay_connections_needed(), 2);
+		assert_eq!(cc.outbound_connections(), (1, 4));
+	}
 }
@@ -2,11 +2,12 @@ use crate::net::{Channel, Connection};
 use crate::p2p::Context;
 use crate::session::SessionFactory;
 use crate::{Direction, PeerId, PeerInfo};
+use message::common::NodeAddress;
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet};
+use std::mem;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::{mem, net};
 
 const SYNCHRONOUS_RESPONSES: bool = true;
 
@@ -30,13 +31,13 @@ impl Connections {
 	}
 
 	/// Returns addresses of all active channels (nonblocking).
-	pub fn addresses(&self) -> HashSet<net::SocketAddr> {
+	pub fn addresses(&self) -> HashSet<NodeAddress> {
 		self.channels().values().map(|channel| channel.peer_info().address).collect()
 	}
 
 	/// Returns info on every peer
 	pub fn info(&self) -> Vec<PeerInfo> {
-		self.channels().values().map(|channel| channel.peer_info()).collect()
+		self.channels().values().map(|channel| channel.peer_info_with_stats()).collect()
 	}
 
 	/// Returns number of connections.
@@ -46,7 +47,7 @@ impl Connections {
 
 	/// Stores new channel.
 	/// Returns a shared pointer to it.
-	pub fn store<T>(&self, context: Arc<Context>, connection: Connection, direction: Direction) -> Arc<Channel>
+	pub fn store<T>(&self, context: Arc<Context>, connection: Connection, direction: Direction, block_relay_only: bool) -> Arc<Channel>
 	where
 		T: SessionFactory,
 	{
@@ -60,10 +61,20 @@ impl Connections {
 			version: connection.version,
 			version_message: connection.version_message,
 			magic: connection.magic,
+			addrv2: connection.addrv2,
+			wtxid_relay: connection.wtxid_relay,
+			local_txrcncl_salt: connection.local_txrcncl_salt,
+			txrcncl_salt: connection.txrcncl_salt,
+			txreconciliation: None,
+			block_relay_only,
+			bytes_sent: 0,
+			bytes_recv: 0,
+			bytes_sent_per_msg: Default::default(),
+			bytes_recv_per_msg: Default::default(),
 		};
 
 		let session = T::new_session(context, peer_info.clone(), SYNCHRONOUS_RESPONSES);
-		let channel = Arc::new(Channel::new(connection.stream, peer_info, session));
+		let channel = Arc::new(Channel::new(connection.stream, connection.transport, peer_info, session));
 		self.channels.write().insert(id, channel.clone());
 		channel
 	}
@@ -0,0 +1,129 @@
+//! DNS seeds, which are queried for the addresses of the reliable nodes when there are no good addresses known.
+//! Seeds support filtering by the service bits, encoded in the `x<services>.` subdomain.
+
+use message::common::Services;
+use message::types::AddressV2Entry;
+use rand::Rng;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+
+/// Seeds do not report when the node was seen, so the addresses are given the random time in this range.
+const MIN_SEED_AGE: u64 = 3 * 24 * 60 * 60;
+const MAX_SEED_AGE: u64 = 7 * 24 * 60 * 60;
+
+/// Resolves host names to the socket addresses.
+pub trait Resolver: Send + Sync {
+	fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, io::Error>;
+}
+
+/// Blocking resolver of the operating system.
+#[derive(Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+	fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, io::Error> {
+		Ok((host, port).to_socket_addrs()?.collect())
+	}
+}
+
+/// Services of the nodes, which are requested from the seeds.
+pub fn seed_services() -> Services {
+	Services::default().with_network(true).with_witness(true)
+}
+
+/// Returns host name of the seed, which resolves only to the nodes with given services.
+fn filtered_host(seed: &str, services: Services) -> String {
+	format!("x{:x}.{}", u64::from(services), seed)
+}
+
+/// Queries the seed for the addresses of the full nodes supporting segwit.
+/// Falls back to the unfiltered query if the seed does not support filtering.
+pub fn query_seed(resolver: &dyn Resolver, seed: &str, port: u16, now: u64) -> Vec<AddressV2Entry> {
+	let services = seed_services();
+	let (addresses, services) = match resolver.resolve(&filtered_host(seed, services), port) {
+		Ok(ref addresses) if !addresses.is_empty() => (addresses.clone(), services),
+		_ => match resolver.resolve(seed, port) {
+			Ok(addresses) => (addresses, Services::default()),
+			Err(err) => {
+				warn!(target: "p2p", "Failed to resolve DNS seed {}: {}", seed, err);
+				return vec![];
+			}
+		},
+	};
+
+	let mut rng = rand::thread_rng();
+	addresses
+		.into_iter()
+		.map(|address| AddressV2Entry {
+			timestamp: now.saturating_sub(rng.gen_range(MIN_SEED_AGE..MAX_SEED_AGE)) as u32,
+			services,
+			address: address.ip().into(),
+			port: address.port(),
+		})
+		.collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::{query_seed, seed_services, Resolver, MAX_SEED_AGE, MIN_SEED_AGE};
+	use message::common::Services;
+	use std::collections::HashMap;
+	use std::io;
+	use std::net::{IpAddr, SocketAddr};
+
+	const NOW: u64 = 1_600_000_000;
+
+	/// Resolves only the hosts it was created with.
+	pub struct StubResolver(pub HashMap<&'static str, Vec<&'static str>>);
+
+	impl Resolver for StubResolver {
+		fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, io::Error> {
+			match self.0.get(host) {
+				Some(ips) => Ok(ips.iter().map(|ip| SocketAddr::new(ip.parse().unwrap(), port)).collect()),
+				None => Err(io::ErrorKind::NotFound.into()),
+			}
+		}
+	}
+
+	fn ips(entries: &[message::types::AddressV2Entry]) -> Vec<IpAddr> {
+		entries.iter().map(|entry| entry.address.ip().unwrap()).collect()
+	}
+
+	#[test]
+	fn test_query_filtered_seed() {
+		let resolver = StubResolver(
+			vec![
+				("x9.seed.example.com", vec!["1.2.3.4", "2001:db8::1"]),
+				("seed.example.com", vec!["5.6.7.8"]),
+			]
+			.into_iter()
+			.collect(),
+		);
+		let entries = query_seed(&resolver, "seed.example.com", 8333, NOW);
+		assert_eq!(
+			ips(&entries),
+			vec!["1.2.3.4".parse::<IpAddr>().unwrap(), "2001:db8::1".parse().unwrap()]
+		);
+		for entry in entries {
+			assert_eq!(entry.port, 8333);
+			assert_eq!(entry.services, seed_services());
+			let age = NOW - entry.timestamp as u64;
+			assert!((MIN_SEED_AGE..MAX_SEED_AGE).contains(&age));
+		}
+	}
+
+	#[test]
+	fn test_query_unfiltered_seed() {
+		let resolver = StubResolver(
+			vec![("x9.seed.example.com", vec![]), ("seed.example.com", vec!["5.6.7.8"])]
+				.into_iter()
+				.collect(),
+		);
+		let entries = query_seed(&resolver, "seed.example.com", 18333, NOW);
+		assert_eq!(ips(&entries), vec!["5.6.7.8".parse::<IpAddr>().unwrap()]);
+		assert_eq!(entries[0].services, Services::default());
+		assert_eq!(entries[0].port, 18333);
+
+		assert!(query_seed(&resolver, "other.example.com", 8333, NOW).is_empty());
+	}
+}
@@ -0,0 +1,141 @@
+//! Selection of the inbound peer, which is disconnected to make room for the new one.
+//! Peers which are hard for the attacker to imitate are protected, so that he can not
+//! take over all our inbound slots just by opening many connections.
+
+use crate::PeerId;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Number of peers protected by their network group.
+const PROTECT_BY_NETGROUP: usize = 4;
+/// Number of peers protected by the lowest ping.
+const PROTECT_BY_PING: usize = 8;
+/// Number of peers protected by recently relayed transactions.
+const PROTECT_BY_TX: usize = 4;
+/// Number of peers, which do not relay transactions, protected by recently relayed blocks.
+const PROTECT_BY_BLOCK_RELAY_ONLY: usize = 8;
+/// Number of peers protected by recently relayed blocks.
+const PROTECT_BY_BLOCK: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct EvictionCandidate {
+	/// Peers are numbered in order of connection, so the lower id means longer connected peer.
+	pub id: PeerId,
+	/// Keyed network group of the peer.
+	pub netgroup: u64,
+	/// Lowest observed ping time.
+	pub min_ping: Option<Duration>,
+	/// Time of the last received block.
+	pub last_block: Duration,
+	/// Time of the last received transaction.
+	pub last_tx: Duration,
+	/// Peer asked us to relay transactions.
+	pub relay_txs: bool,
+}
+
+/// Removes up to `count` candidates with the highest key from the eviction candidates.
+/// If keys are equal, longer connected peers are protected.
+fn protect<K, F>(candidates: &mut Vec<EvictionCandidate>, count: usize, key: F)
+where
+	K: Ord,
+	F: Fn(&EvictionCandidate) -> K,
+{
+	candidates.sort_by_key(|candidate| (key(candidate), Reverse(candidate.id)));
+	let len = candidates.len().saturating_sub(count);
+	candidates.truncate(len);
+}
+
+/// Returns inbound peer which should be disconnected, or None if all of them are protected.
+pub fn select_peer_to_evict(mut candidates: Vec<EvictionCandidate>) -> Option<PeerId> {
+	// deterministically random, attacker can not choose network group which is protected
+	protect(&mut candidates, PROTECT_BY_NETGROUP, |candidate| candidate.netgroup);
+	protect(&mut candidates, PROTECT_BY_PING, |candidate| {
+		Reverse(candidate.min_ping.unwrap_or(Duration::MAX))
+	});
+	protect(&mut candidates, PROTECT_BY_TX, |candidate| candidate.last_tx);
+
+	// protect peers, which deliberately do not relay transactions, but provide us with blocks
+	let mut block_relay_only: Vec<_> = candidates.iter().filter(|candidate| !candidate.relay_txs).cloned().collect();
+	block_relay_only.sort_by_key(|candidate| Reverse((candidate.last_block, Reverse(candidate.id))));
+	let protected: Vec<_> = block_relay_only
+		.into_iter()
+		.take(PROTECT_BY_BLOCK_RELAY_ONLY)
+		.map(|candidate| candidate.id)
+		.collect();
+	candidates.retain(|candidate| !protected.contains(&candidate.id));
+
+	protect(&mut candidates, PROTECT_BY_BLOCK, |candidate| candidate.last_block);
+
+	// protect half of the remaining peers, which were connected for the longest time
+	let half = candidates.len() / 2;
+	protect(&mut candidates, half, |candidate| Reverse(candidate.id));
+
+	// disconnect the youngest peer of the network group with most connections
+	let mut netgroups: HashMap<u64, Vec<PeerId>> = HashMap::new();
+	for candidate in candidates {
+		netgroups.entry(candidate.netgroup).or_default().push(candidate.id);
+	}
+	netgroups
+		.into_values()
+		.map(|peers| (peers.len(), peers.into_iter().max().expect("groups are never empty; qed")))
+		.max()
+		.map(|(_, youngest)| youngest)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{select_peer_to_evict, EvictionCandidate};
+	use std::time::Duration;
+
+	fn candidate(id: usize, netgroup: u64) -> EvictionCandidate {
+		EvictionCandidate {
+			id,
+			netgroup,
+			min_ping: None,
+			last_block: Duration::default(),
+			last_tx: Duration::default(),
+			relay_txs: true,
+		}
+	}
+
+	#[test]
+	fn test_nothing_to_evict() {
+		assert_eq!(select_peer_to_evict(vec![]), None);
+		// all peers are protected by their network groups
+		let candidates = (0..4).map(|id| candidate(id, id as u64)).collect();
+		assert_eq!(select_peer_to_evict(candidates), None);
+	}
+
+	#[test]
+	fn test_evict_youngest_of_largest_netgroup() {
+		// peers 0..4 are protected by the network group, 4..12 by ping
+		let mut candidates: Vec<_> = (0..4).map(|id| candidate(id, 100 + id as u64)).collect();
+		candidates.extend((4..12).map(|id| EvictionCandidate {
+			min_ping: Some(Duration::from_millis(id as u64)),
+			..candidate(id, 0)
+		}));
+		// 12..20 are the oldest of the remaining peers, so they are protected by tx and block relay,
+		// and then half of the remaining ones is protected by longevity
+		candidates.extend((12..32).map(|id| candidate(id, 1)));
+		candidates.extend((32..40).map(|id| candidate(id, 2)));
+		assert_eq!(select_peer_to_evict(candidates.clone()), Some(39));
+
+		// peers relaying transactions and blocks are protected
+		candidates[39].last_tx = Duration::from_secs(1);
+		assert_eq!(select_peer_to_evict(candidates.clone()), Some(38));
+		candidates[38].last_block = Duration::from_secs(1);
+		assert_eq!(select_peer_to_evict(candidates), Some(37));
+	}
+
+	#[test]
+	fn test_protect_block_relay_only() {
+		let mut candidates: Vec<_> = (0..30).map(|id| candidate(id, 0)).collect();
+		for candidate in candidates.iter_mut().skip(20) {
+			candidate.relay_txs = false;
+			candidate.last_block = Duration::from_secs(candidate.id as u64);
+		}
+		// the youngest peers relay only blocks, so the youngest one of the other peers is evicted
+		assert_eq!(select_peer_to_evict(candidates), Some(19));
+	}
+}
@@ -1,19 +1,29 @@
 mod accept_connection;
+mod bandwidth;
 mod channel;
 mod config;
 mod connect;
 mod connection;
 mod connection_counter;
 mod connections;
+mod dns_seed;
+mod eviction;
 mod peer_context;
 mod stats;
+mod tor_control;
 
 pub use self::accept_connection::accept_connection;
+pub use self::bandwidth::{Bandwidth, NetTotals, UploadTarget};
 pub use self::channel::Channel;
-pub use self::config::Config;
+pub use self::config::{Config, Proxy};
 pub use self::connect::connect;
 pub use self::connection::Connection;
 pub use self::connection_counter::ConnectionCounter;
 pub use self::connections::Connections;
+#[cfg(test)]
+pub use self::dns_seed::tests::StubResolver;
+pub use self::dns_seed::{query_seed, Resolver, SystemResolver};
+pub use self::eviction::{select_peer_to_evict, EvictionCandidate};
 pub use self::peer_context::PeerContext;
-pub use self::stats::PeerStats;
+pub use self::stats::{Flow, PeerStats, ReconciliationStats};
+pub use self::tor_control::{TorControl, TorControlConnection};
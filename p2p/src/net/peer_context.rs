@@ -24,11 +24,15 @@ impl PeerContext {
 		}
 	}
 
+	/// Serializes response, which is queued until the preceding responses are sent.
 	fn to_message<T>(&self, payload: &T) -> Message<T>
 	where
 		T: Payload,
 	{
-		Message::new(self.info.magic, self.info.version, payload).expect("failed to create outgoing message")
+		let message = Message::new(self.info.magic, self.info.version, payload).expect("failed to create outgoing message");
+		self.stats.lock().report_send(T::command().into(), message.len());
+		self.context.report_send(message.len());
+		message
 	}
 
 	fn send_awaiting(&self, sync: &mut ConfigurableSynchronizer, queue: &mut ResponseQueue, start_id: u32) {
@@ -1,9 +1,9 @@
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::time::Instant;
 
 use crate::util::interval::{Interval, RealInterval};
-use message::types::{Ping, Pong};
+use message::types::{Block, CompactBlock, Ping, Pong, ReconcilDiff, Tx};
 use message::{Command, Payload};
 use primitives::time::{RealTime, Time};
 use tokio::time::Duration;
@@ -46,6 +46,17 @@ impl RunningAverage {
 	}
 }
 
+/// Transactions reconciliation (BIP330) statistics of the peer.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct ReconciliationStats {
+	/// Number of finished reconciliation rounds
+	pub rounds: u64,
+	/// Number of rounds, which have failed to find the difference of the sets
+	pub failures: u64,
+	/// Number of transactions, requested by the short ids
+	pub requested: u64,
+}
+
 pub enum Flow {
 	Receive,
 	Send,
@@ -56,6 +67,11 @@ pub struct PeerStats<T: Interval = RealInterval> {
 	pub last_send: Duration,
 	pub last_recv: Duration,
 
+	/// Time of the last received block.
+	pub last_block: Duration,
+	/// Time of the last received transaction.
+	pub last_tx: Duration,
+
 	pub total_send: u64,
 	pub total_recv: u64,
 
@@ -65,9 +81,15 @@ pub struct PeerStats<T: Interval = RealInterval> {
 	send_avg: HashMap<Command, RunningAverage>,
 	recv_avg: HashMap<Command, RunningAverage>,
 
+	send_per_msg: HashMap<Command, u64>,
+	recv_per_msg: HashMap<Command, u64>,
+
 	last_ping: Option<Instant>,
 	ping_count: u64,
 
+	/// Set when the transactions are reconciled with the peer.
+	pub txreconciliation: Option<ReconciliationStats>,
+
 	interval: T,
 }
 
@@ -87,6 +109,8 @@ impl<I: Interval> PeerStats<I> {
 			self.report_ping_send();
 		}
 
+		*self.send_per_msg.entry(command).or_insert(0) += bytes as u64;
+
 		match self.send_avg.entry(command) {
 			Entry::Occupied(mut avg) => {
 				avg.get_mut().add(bytes);
@@ -122,8 +146,14 @@ impl<I: Interval> PeerStats<I> {
 
 		if command == Pong::command() {
 			self.report_pong_recv();
+		} else if command == Block::command() || command == CompactBlock::command() {
+			self.last_block = self.last_recv;
+		} else if command == Tx::command() {
+			self.last_tx = self.last_recv;
 		}
 
+		*self.recv_per_msg.entry(command).or_insert(0) += bytes as u64;
+
 		match self.recv_avg.entry(command) {
 			Entry::Occupied(mut avg) => {
 				avg.get_mut().add(bytes);
@@ -134,6 +164,25 @@ impl<I: Interval> PeerStats<I> {
 		}
 	}
 
+	/// Record the reconciliation round result, which has been sent to or received from the peer.
+	pub fn report_reconcildiff(&mut self, reconcildiff: &ReconcilDiff) {
+		let stats = self.txreconciliation.get_or_insert_with(Default::default);
+		stats.rounds += 1;
+		if !reconcildiff.success {
+			stats.failures += 1;
+		}
+		stats.requested += reconcildiff.ask_shortids.len() as u64;
+	}
+
+	/// Total bytes sent or received, by message command.
+	pub fn bytes_per_msg(&self, dir: Flow) -> BTreeMap<String, u64> {
+		let per_msg = match dir {
+			Flow::Receive => &self.recv_per_msg,
+			Flow::Send => &self.send_per_msg,
+		};
+		per_msg.iter().map(|(command, bytes)| (command.to_string(), *bytes)).collect()
+	}
+
 	pub fn avg<T>(&self, dir: Flow, cmd: T) -> u64
 	where
 		T: Into<Command>,
@@ -148,8 +197,9 @@ impl<I: Interval> PeerStats<I> {
 #[cfg(test)]
 mod tests {
 
-	use super::{Flow, PeerStats, RunningAverage};
+	use super::{Flow, PeerStats, ReconciliationStats, RunningAverage};
 	use crate::util::interval::{FixedIntervalSpawner, RealInterval};
+	use message::types::ReconcilDiff;
 
 	#[test]
 	fn avg() {
@@ -196,4 +246,57 @@ mod tests {
 
 		assert_eq!(stats.avg(Flow::Receive, "inv"), 2500);
 	}
+
+	#[test]
+	fn last_block_and_tx() {
+		let mut stats = PeerStats::<RealInterval>::default();
+		stats.report_recv("inv".into(), 100);
+		assert_eq!(stats.last_block, Default::default());
+		assert_eq!(stats.last_tx, Default::default());
+
+		stats.report_recv("cmpctblock".into(), 100);
+		assert_eq!(stats.last_block, stats.last_recv);
+		stats.report_recv("tx".into(), 100);
+		assert_eq!(stats.last_tx, stats.last_recv);
+	}
+
+	#[test]
+	fn bytes_per_msg() {
+		let mut stats = PeerStats::<RealInterval>::default();
+		stats.report_send("inv".into(), 200);
+		stats.report_send("inv".into(), 300);
+		stats.report_send("tx".into(), 100);
+		stats.report_recv("getdata".into(), 50);
+
+		assert_eq!(
+			stats.bytes_per_msg(Flow::Send).into_iter().collect::<Vec<_>>(),
+			vec![("inv".to_owned(), 500), ("tx".to_owned(), 100)]
+		);
+		assert_eq!(
+			stats.bytes_per_msg(Flow::Receive).into_iter().collect::<Vec<_>>(),
+			vec![("getdata".to_owned(), 50)]
+		);
+	}
+
+	#[test]
+	fn reconciliation_rounds() {
+		let mut stats = PeerStats::<RealInterval>::default();
+		assert_eq!(stats.txreconciliation, None);
+		stats.report_reconcildiff(&ReconcilDiff {
+			success: true,
+			ask_shortids: vec![1, 2],
+		});
+		stats.report_reconcildiff(&ReconcilDiff {
+			success: false,
+			ask_shortids: vec![],
+		});
+		assert_eq!(
+			stats.txreconciliation,
+			Some(ReconciliationStats {
+				rounds: 2,
+				failures: 1,
+				requested: 2,
+			})
+		);
+	}
 }
@@ -0,0 +1,231 @@
+//! Client of the Tor control protocol, used to create ephemeral onion service.
+//! https://spec.torproject.org/control-spec/
+
+use std::net::SocketAddr;
+use std::{fs, io};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+const REPLY_OK: &str = "250";
+
+/// Tor control port settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorControl {
+	/// Address of the Tor control port.
+	pub address: SocketAddr,
+	/// Password of the control port. Cookie authentication is used if it's not set.
+	pub password: Option<String>,
+}
+
+fn error(message: String) -> io::Error {
+	io::Error::other(format!("Tor control error: {}", message))
+}
+
+/// Authenticated connection to the Tor control port.
+/// Ephemeral onion services created with it exist until the connection is closed.
+pub struct TorControlConnection {
+	stream: BufReader<TcpStream>,
+}
+
+impl TorControlConnection {
+	/// Connects to the control port and authenticates using either password, or the method announced by Tor.
+	pub async fn connect(config: &TorControl) -> Result<Self, io::Error> {
+		let stream = TcpStream::connect(config.address).await?;
+		let mut connection = TorControlConnection {
+			stream: BufReader::new(stream),
+		};
+
+		let command = match config.password {
+			Some(ref password) => format!("AUTHENTICATE {}", quote(password)),
+			None => {
+				let info = connection.command("PROTOCOLINFO 1").await?;
+				auth_command(&info)?
+			}
+		};
+		connection.command(&command).await?;
+		Ok(connection)
+	}
+
+	/// Creates new onion service, which forwards `virtual_port` to the `target`. Returns the service id.
+	pub async fn add_onion(&mut self, virtual_port: u16, target: &SocketAddr) -> Result<String, io::Error> {
+		let command = format!("ADD_ONION NEW:ED25519-V3 Flags=DiscardPK Port={},{}", virtual_port, target);
+		let reply = self.command(&command).await?;
+		reply
+			.iter()
+			.find_map(|line| line.strip_prefix("ServiceID="))
+			.map(ToOwned::to_owned)
+			.ok_or_else(|| error("missing service id".into()))
+	}
+
+	/// Waits until Tor closes the connection.
+	pub async fn wait_closed(mut self) -> Result<(), io::Error> {
+		let mut line = String::new();
+		while self.stream.read_line(&mut line).await? != 0 {
+			line.clear();
+		}
+		Ok(())
+	}
+
+	/// Sends the command and reads the reply. Returns lines of the successful reply without the status code.
+	async fn command(&mut self, command: &str) -> Result<Vec<String>, io::Error> {
+		self.stream.get_mut().write_all(format!("{}\r\n", command).as_bytes()).await?;
+
+		let mut lines = Vec::new();
+		loop {
+			let mut line = String::new();
+			if self.stream.read_line(&mut line).await? == 0 {
+				return Err(io::ErrorKind::UnexpectedEof.into());
+			}
+			let line = line.trim_end();
+			if line.len() < 4 || !line.is_char_boundary(4) {
+				return Err(error(format!("invalid reply '{}'", line)));
+			}
+
+			let (status, separator, text) = (&line[..3], &line[3..4], &line[4..]);
+			if status != REPLY_OK {
+				return Err(error(format!("{} {}", status, text)));
+			}
+			lines.push(text.to_owned());
+			match separator {
+				" " => return Ok(lines),
+				"-" => (),
+				// data reply continues until the line with single dot
+				"+" => loop {
+					let mut data = String::new();
+					if self.stream.read_line(&mut data).await? == 0 {
+						return Err(io::ErrorKind::UnexpectedEof.into());
+					}
+					if data.trim_end() == "." {
+						break;
+					}
+				},
+				_ => return Err(error(format!("invalid reply '{}'", line))),
+			}
+		}
+	}
+}
+
+/// Chooses authentication method from the PROTOCOLINFO reply.
+fn auth_command(info: &[String]) -> Result<String, io::Error> {
+	let auth = info
+		.iter()
+		.find_map(|line| line.strip_prefix("AUTH METHODS="))
+		.ok_or_else(|| error("missing authentication methods".into()))?;
+	let mut parts = auth.splitn(2, ' ');
+	let methods: Vec<&str> = parts.next().unwrap_or_default().split(',').collect();
+
+	if methods.contains(&"NULL") {
+		return Ok("AUTHENTICATE".into());
+	}
+
+	if methods.contains(&"COOKIE") {
+		let cookie_file = parts
+			.next()
+			.and_then(|rest| rest.strip_prefix("COOKIEFILE="))
+			.map(|path| path.trim_matches('"'))
+			.ok_or_else(|| error("missing cookie file".into()))?;
+		let cookie = fs::read(cookie_file)?;
+		let cookie: String = cookie.iter().map(|byte| format!("{:02x}", byte)).collect();
+		return Ok(format!("AUTHENTICATE {}", cookie));
+	}
+
+	Err(error(format!("unsupported authentication methods {}", methods.join(","))))
+}
+
+/// Quotes the string, escaping special characters.
+fn quote(value: &str) -> String {
+	format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{auth_command, quote, TorControl, TorControlConnection};
+	use std::{env, fs};
+	use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+	use tokio::net::TcpListener;
+
+	const SERVICE_ID: &str = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd";
+
+	/// Minimal Tor control port, which answers the commands with given replies.
+	/// Returns received commands once the client disconnects.
+	async fn control_port(listener: TcpListener, replies: Vec<&'static str>) -> Vec<String> {
+		let (stream, _) = listener.accept().await.unwrap();
+		let mut stream = BufReader::new(stream);
+		let mut commands = Vec::new();
+		for reply in replies {
+			let mut command = String::new();
+			stream.read_line(&mut command).await.unwrap();
+			commands.push(command.trim_end().to_owned());
+			stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+		}
+		commands
+	}
+
+	#[tokio::test]
+	async fn test_add_onion() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let config = TorControl {
+			address: listener.local_addr().unwrap(),
+			password: Some("pass\"word".into()),
+		};
+		let server = tokio::spawn(control_port(
+			listener,
+			vec![
+				"250 OK\r\n",
+				"250-ServiceID=pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd\r\n250 OK\r\n",
+			],
+		));
+
+		let mut connection = TorControlConnection::connect(&config).await.unwrap();
+		let service_id = connection.add_onion(8333, &"127.0.0.1:8334".parse().unwrap()).await.unwrap();
+		assert_eq!(service_id, SERVICE_ID);
+		connection.wait_closed().await.unwrap();
+
+		assert_eq!(
+			server.await.unwrap(),
+			vec![
+				"AUTHENTICATE \"pass\\\"word\"".to_owned(),
+				"ADD_ONION NEW:ED25519-V3 Flags=DiscardPK Port=8333,127.0.0.1:8334".to_owned(),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_authentication_failed() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let config = TorControl {
+			address: listener.local_addr().unwrap(),
+			password: None,
+		};
+		tokio::spawn(control_port(
+			listener,
+			vec![
+				"250-PROTOCOLINFO 1\r\n250-AUTH METHODS=NULL\r\n250-VERSION Tor=\"0.4.5.6\"\r\n250 OK\r\n",
+				"515 Authentication failed\r\n",
+			],
+		));
+
+		let err = TorControlConnection::connect(&config).await.err().unwrap();
+		assert_eq!(err.to_string(), "Tor control error: 515 Authentication failed");
+	}
+
+	#[test]
+	fn test_auth_command() {
+		let cookie_file = env::temp_dir().join("bitcoin-rs-test-control-auth-cookie");
+		fs::write(&cookie_file, [0xab, 0x01]).unwrap();
+		let cookie = vec![format!("AUTH METHODS=COOKIE,SAFECOOKIE COOKIEFILE=\"{}\"", cookie_file.display())];
+		assert_eq!(auth_command(&cookie).unwrap(), "AUTHENTICATE ab01");
+		fs::remove_file(cookie_file).unwrap();
+
+		let null = vec!["AUTH METHODS=NULL".to_owned()];
+		assert_eq!(auth_command(&null).unwrap(), "AUTHENTICATE");
+
+		let password = vec!["AUTH METHODS=HASHEDPASSWORD".to_owned()];
+		assert!(auth_command(&password).is_err());
+	}
+
+	#[test]
+	fn test_quote() {
+		assert_eq!(quote("a\\b\"c"), "\"a\\\\b\\\"c\"");
+	}
+}
@@ -1,14 +1,22 @@
 use crate::io::Error;
-use crate::net::{accept_connection, connect, Channel, ConnectionCounter, Connections};
-use crate::session::{NormalSessionFactory, SeednodeSessionFactory, SessionFactory};
-use crate::util::{Node, NodeTable};
-use crate::{Config, Direction, InboundSyncConnectionRef, LocalSyncNodeRef, NetConfig, NodeTableError, OutboundSyncConnectionRef, PeerId};
-use message::common::Services;
-use message::types::addr::AddressEntry;
+use crate::net::{
+	accept_connection, connect, query_seed, select_peer_to_evict, Bandwidth, Channel, ConnectionCounter, Connections, EvictionCandidate,
+	NetTotals, Resolver, TorControlConnection,
+};
+use crate::session::{BlockRelaySessionFactory, FeelerSessionFactory, NormalSessionFactory, SeednodeSessionFactory, SessionFactory};
+use crate::util::{AddressManager, Asmap, BanEntry, BanList, NetGroups, Node, Subnet, DEFAULT_BAN_TIME};
+use crate::{
+	Config, Direction, InboundSyncConnectionRef, InternetProtocol, LocalSyncNodeRef, NetConfig, NodeTableError, OutboundSyncConnectionRef,
+	PeerId,
+};
+use message::common::{NodeAddress, Services};
+use message::types::AddressV2Entry;
 use message::{Message, Payload};
 use network::Network;
 use parking_lot::{Mutex, RwLock};
+use primitives::time::{RealTime, Time};
 use rand::seq::SliceRandom;
+use std::collections::HashSet;
 use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -27,25 +35,55 @@ pub struct Context {
 	connections: Connections,
 	/// Connection counter.
 	connection_counter: ConnectionCounter,
-	/// Node Table.
-	node_table: RwLock<NodeTable>,
+	/// Known addresses of the nodes.
+	address_manager: RwLock<AddressManager>,
+	/// Banned subnets.
+	ban_list: RwLock<BanList>,
 	/// Local synchronization node.
 	local_sync_node: LocalSyncNodeRef,
 	/// Node table path.
 	config: Config,
+	/// Address of our onion service, if it was created.
+	local_onion_address: RwLock<Option<NodeAddress>>,
+	/// Network groups of the peers.
+	netgroups: NetGroups,
+	/// Resolver of the DNS seeds.
+	resolver: Arc<dyn Resolver>,
+	/// Network traffic of the node.
+	bandwidth: Mutex<Bandwidth>,
 }
 
 impl Context {
-	/// Creates new context with reference to local sync node.
-	pub fn new(runtime_handle: Handle, local_sync_node: LocalSyncNodeRef, config: Config) -> Result<Self, Box<dyn error::Error>> {
+	/// Creates new context with reference to local sync node. DNS seeds are queried with the given resolver.
+	pub fn new(
+		runtime_handle: Handle,
+		local_sync_node: LocalSyncNodeRef,
+		config: Config,
+		resolver: Arc<dyn Resolver>,
+	) -> Result<Self, Box<dyn error::Error>> {
+		let asmap = match config.asmap_path {
+			Some(ref path) => Some(Asmap::from_file(path)?),
+			None => None,
+		};
+		let netgroups = NetGroups::new(asmap);
+		let address_manager = AddressManager::from_file(config.preferable_services, netgroups.clone(), config.node_table_path.clone())?;
 		let context = Context {
 			runtime_handle,
 			shutdown_flag: Arc::new(Mutex::new(false)),
 			connections: Default::default(),
-			connection_counter: ConnectionCounter::new(config.inbound_connections, config.outbound_connections),
-			node_table: RwLock::new(NodeTable::from_file(config.preferable_services, config.node_table_path.clone())?),
+			connection_counter: ConnectionCounter::new(
+				config.inbound_connections,
+				config.outbound_connections,
+				config.block_relay_connections,
+			),
+			address_manager: RwLock::new(address_manager),
+			ban_list: RwLock::new(BanList::from_file(config.ban_list_path.clone())?),
+			bandwidth: Mutex::new(Bandwidth::new(config.max_upload_target)),
 			local_sync_node,
 			config,
+			local_onion_address: RwLock::new(None),
+			netgroups,
+			resolver,
 		};
 
 		Ok(context)
@@ -59,6 +97,39 @@ impl Context {
 		self.config.connection.protocol_version
 	}
 
+	pub fn internet_protocol(&self) -> InternetProtocol {
+		self.config.internet_protocol
+	}
+
+	pub fn net_config(&self) -> &NetConfig {
+		&self.config.connection
+	}
+
+	/// Records bytes sent to the peer.
+	pub fn report_send(&self, bytes: usize) {
+		self.bandwidth.lock().report_send(bytes, RealTime.now().as_secs());
+	}
+
+	/// Records bytes received from the peer.
+	pub fn report_recv(&self, bytes: usize) {
+		self.bandwidth.lock().report_recv(bytes);
+	}
+
+	/// Returns total network traffic of the node.
+	pub fn net_totals(&self) -> NetTotals {
+		self.bandwidth.lock().totals(RealTime.now().as_secs())
+	}
+
+	/// Returns true if historical blocks should not be served, because the upload target is nearly reached.
+	pub fn is_historical_block_serving_limited(&self) -> bool {
+		self.bandwidth.lock().is_upload_target_reached(true, RealTime.now().as_secs())
+	}
+
+	/// Returns address of our onion service, if it was created.
+	pub fn local_onion_address(&self) -> Option<NodeAddress> {
+		*self.local_onion_address.read()
+	}
+
 	/// Spawns a future using thread pool and schedules execution of it with event loop handle.
 	pub fn spawn<F>(&self, f: F)
 	where
@@ -80,33 +151,152 @@ impl Context {
 		});
 	}
 
-	/// Returns addresses of recently active nodes. Sorted and limited to 1000.
+	/// Returns random addresses of known nodes, which are relayed to other nodes. Limited to 1000.
 	pub fn node_table_entries(&self) -> Vec<Node> {
-		self.node_table.read().recently_active_nodes(self.config.internet_protocol)
+		self.address_manager.read().recently_active_nodes(self.config.internet_protocol)
 	}
 
-	/// Updates node table.
-	pub fn update_node_table(&self, nodes: Vec<AddressEntry>) {
+	/// Updates node table with addresses announced by the nodes themselves.
+	pub fn update_node_table(&self, nodes: Vec<AddressV2Entry>) {
 		trace!("Updating node table with {} entries", nodes.len());
-		self.node_table.write().insert_many(nodes);
+		let mut address_manager = self.address_manager.write();
+		for node in nodes {
+			let source = node.address;
+			address_manager.add_many(vec![node], &source);
+		}
+	}
+
+	/// Updates node table with addresses relayed by the given node.
+	pub fn update_node_table_from(&self, source: &NodeAddress, nodes: Vec<AddressV2Entry>) {
+		trace!("Updating node table with {} entries from {}", nodes.len(), source);
+		self.address_manager.write().add_many(nodes, &source.address);
 	}
 
 	/// Penalize node.
-	pub fn penalize_node(&self, addr: &SocketAddr) {
+	pub fn penalize_node(&self, addr: &NodeAddress) {
 		trace!("Penalizing node {}", addr);
-		self.node_table.write().note_failure(addr);
+		self.address_manager.write().attempt(addr);
 	}
 
 	/// Adds node to table.
-	pub fn add_node(&self, addr: SocketAddr) -> Result<(), NodeTableError> {
+	pub fn add_node(&self, addr: NodeAddress) -> Result<(), NodeTableError> {
 		trace!("Adding node {} to node table", &addr);
-		self.node_table.write().add(addr, self.config.connection.services)
+		self.address_manager.write().add_manual(addr, self.config.connection.services)
 	}
 
 	/// Removes node from table.
-	pub fn remove_node(&self, addr: SocketAddr) -> Result<(), NodeTableError> {
+	pub fn remove_node(&self, addr: NodeAddress) -> Result<(), NodeTableError> {
 		trace!("Removing node {} from node table", &addr);
-		self.node_table.write().remove(&addr)
+		self.address_manager.write().remove(&addr)
+	}
+
+	/// Returns true if the address belongs to the banned subnet.
+	pub fn is_banned(&self, addr: &NodeAddress) -> bool {
+		self.ban_list.read().is_banned(&addr.address)
+	}
+
+	/// Bans the subnet until given time and disconnects peers from it.
+	/// Returns false if the subnet was already banned for at least that long.
+	pub fn ban(&self, subnet: Subnet, until: u64) -> bool {
+		trace!("Banning {}", subnet);
+		if !self.ban_list.write().ban_until(subnet, until) {
+			return false;
+		}
+		self.on_banned(subnet);
+		true
+	}
+
+	/// Bans the peer address for the default ban time.
+	/// Local peers are only penalized, so that we do not ban our own Tor proxy.
+	pub fn ban_node(&self, addr: &NodeAddress) {
+		match addr.address.ip() {
+			Some(ip) if !ip.is_loopback() => {
+				trace!("Banning node {}", addr);
+				if self.ban_list.write().ban(ip.into(), DEFAULT_BAN_TIME) {
+					self.on_banned(ip.into());
+				}
+			}
+			_ => self.penalize_node(addr),
+		}
+	}
+
+	fn on_banned(&self, subnet: Subnet) {
+		if let Err(_err) = self.ban_list.read().save_to_file() {
+			error!("Saving ban list to disk failed");
+		}
+		for info in self.connections.info() {
+			if info.address.address.ip().is_some_and(|ip| subnet.contains(&ip)) {
+				self.close_channel(info.id);
+			}
+		}
+	}
+
+	/// Removes the ban. Returns false if the subnet was not banned.
+	pub fn unban(&self, subnet: &Subnet) -> bool {
+		trace!("Unbanning {}", subnet);
+		let mut ban_list = self.ban_list.write();
+		if !ban_list.unban(subnet) {
+			return false;
+		}
+		if let Err(_err) = ban_list.save_to_file() {
+			error!("Saving ban list to disk failed");
+		}
+		true
+	}
+
+	/// Removes all bans.
+	pub fn clear_banned(&self) {
+		trace!("Clearing ban list");
+		let mut ban_list = self.ban_list.write();
+		ban_list.clear();
+		if let Err(_err) = ban_list.save_to_file() {
+			error!("Saving ban list to disk failed");
+		}
+	}
+
+	/// Returns active bans.
+	pub fn banned(&self) -> Vec<BanEntry> {
+		self.ban_list.read().entries()
+	}
+
+	/// Finds the initial peers. DNS seeds are queried if none of the known addresses is good.
+	/// Hardcoded seed nodes are used if DNS seeding is disabled or has not returned any address.
+	async fn bootstrap(context: Arc<Context>) {
+		if context.config.dns_seed && context.address_manager.read().is_stale() && Context::dns_seed(context.clone()).await != 0 {
+			return;
+		}
+
+		let seeds: Vec<SocketAddr> = match context.config.connection.network {
+			Network::Mainnet => seeds_main(),
+			Network::Testnet => seeds_test(),
+			_ => vec![],
+		};
+
+		for seed in seeds.choose_multiple(&mut rand::thread_rng(), 5) {
+			Context::connect::<SeednodeSessionFactory>(context.clone(), (*seed).into());
+		}
+	}
+
+	/// Queries DNS seeds of the network and adds returned addresses to the node table.
+	/// Returns number of the received addresses.
+	pub async fn dns_seed(context: Arc<Context>) -> usize {
+		let network = context.config.connection.network;
+		let now = RealTime.now().as_secs();
+		let mut count = 0;
+		for seed in network.dns_seeds() {
+			let resolver = context.resolver.clone();
+			let entries = match tokio::task::spawn_blocking(move || query_seed(&*resolver, seed, network.port(), now)).await {
+				Ok(entries) => entries,
+				Err(_) => continue,
+			};
+			info!(target: "p2p", "DNS seed {} returned {} addresses", seed, entries.len());
+			// all addresses returned by the seed share the same source, so that single seed can not fill the whole table
+			if let Some(source) = entries.first().map(|entry| NodeAddress::new(entry.address, entry.port)) {
+				count += entries.len();
+				context.update_node_table_from(&source, entries);
+			}
+		}
+		count
 	}
 
 	/// Every 10 seconds check if we have reached maximum number of outbound connections.
@@ -132,39 +322,87 @@ impl Context {
 		}
 
 		let needed = context.connection_counter.outbound_connections_needed() as usize;
-		if needed != 0 {
+		let block_relay_needed = context.connection_counter.block_relay_connections_needed() as usize;
+		if needed + block_relay_needed != 0 {
 			let used_addresses = context.connections.addresses();
-			let peers = context.node_table.read().nodes_with_services(
+			// connect to at most one peer from every network group
+			let mut used_groups: HashSet<_> = context
+				.connections
+				.info()
+				.into_iter()
+				.filter(|info| info.direction == Direction::Outbound)
+				.map(|info| context.netgroups.group(&info.address.address))
+				.collect();
+			let peers = context.address_manager.read().nodes_with_services(
 				&Services::default(),
 				context.config.internet_protocol,
+				context.config.connection.is_onion_reachable(),
 				&used_addresses,
-				needed,
+				|address| !context.is_banned(address) && used_groups.insert(context.netgroups.group(&address.address)),
+				needed + block_relay_needed,
 			);
-			let addresses = peers.into_iter().map(|peer| peer.address()).collect::<Vec<_>>();
+			let mut addresses = peers.into_iter().map(|peer| peer.address());
 
-			trace!("Creating {} more outbound connections", addresses.len());
-			for address in addresses {
+			trace!("Creating {} more outbound connections", needed);
+			for address in addresses.by_ref().take(needed) {
 				Context::connect::<NormalSessionFactory>(context.clone(), address);
 			}
+
+			trace!("Creating {} more block-relay-only connections", block_relay_needed);
+			for address in addresses {
+				Context::connect_block_relay(context.clone(), address);
+			}
 		}
 
-		if let Err(_err) = context.node_table.read().save_to_file() {
+		// test tried address, which collides with the new one
+		let collision = {
+			let mut address_manager = context.address_manager.write();
+			address_manager.resolve_collisions();
+			address_manager.select_tried_collision()
+		};
+		if let Some(address) = collision {
+			if !context.connections.addresses().contains(&address) {
+				trace!("Testing {} before evicting it from the tried table", address);
+				Context::connect::<FeelerSessionFactory>(context.clone(), address);
+			}
+		}
+
+		if let Err(_err) = context.address_manager.read().save_to_file() {
 			error!("Saving node table to disk failed");
 		}
 	}
 
 	/// Connect to socket.
-	async fn connect_future<T>(context: Arc<Context>, socket: net::SocketAddr)
+	async fn connect_future<T>(context: Arc<Context>, socket: NodeAddress, block_relay_only: bool)
 	where
 		T: SessionFactory,
 	{
+		if context.is_banned(&socket) {
+			trace!("Refusing to connect to banned {}", socket);
+			context.note_close_connection(Direction::Outbound, block_relay_only);
+			return;
+		}
+
 		trace!("Trying to connect to: {}", socket);
-		match connect(&socket, &context.config.connection).await {
+		let services = context.address_manager.read().services(&socket);
+		let result = if block_relay_only {
+			// ask the peer not to send us transactions
+			let config = NetConfig {
+				relay: false,
+				..context.config.connection.clone()
+			};
+			connect(&socket, services, &config).await
+		} else {
+			connect(&socket, services, &context.config.connection).await
+		};
+		match result {
 			Ok(connection) => {
 				// successful handshake
 				trace!("Connected to {}", connection.address);
-				context.node_table.write().insert(connection.address, connection.services);
-				let channel = context.connections.store::<T>(context.clone(), connection, Direction::Outbound);
+				context.address_manager.write().good(&connection.address, connection.services);
+				let channel = context
+					.connections
+					.store::<T>(context.clone(), connection, Direction::Outbound, block_relay_only);
 
 				// initialize session and then start reading messages
 				channel.session().initialize();
@@ -178,47 +416,57 @@ impl Context {
 				// protocol error
 				trace!("Handshake with {} failed with {}", socket, err);
 				// TODO: close socket
-				context.node_table.write().note_failure(&socket);
-				context.connection_counter.note_close_outbound_connection();
+				context.address_manager.write().attempt(&socket);
+				context.note_close_connection(Direction::Outbound, block_relay_only);
 			}
 			Err(Error::Timeout) => {
 				// connection time out
 				trace!("Handshake with {} timed out", socket);
 				// TODO: close socket
-				context.node_table.write().note_failure(&socket);
-				context.connection_counter.note_close_outbound_connection();
+				context.address_manager.write().attempt(&socket);
+				context.note_close_connection(Direction::Outbound, block_relay_only);
 			}
 			Err(Error::IO(err)) => {
 				// network error
 				trace!("Failed to connect to {} with {}", socket, err);
-				context.node_table.write().note_failure(&socket);
-				context.connection_counter.note_close_outbound_connection();
+				context.address_manager.write().attempt(&socket);
+				context.note_close_connection(Direction::Outbound, block_relay_only);
 			}
 		}
 	}
 
 	/// Connect to socket using given context.
-	pub fn connect<T>(context: Arc<Context>, socket: net::SocketAddr)
+	pub fn connect<T>(context: Arc<Context>, socket: NodeAddress)
 	where
 		T: SessionFactory + 'static,
 	{
 		context.connection_counter.note_new_outbound_connection();
-		context.runtime_handle.spawn(Context::connect_future::<T>(context.clone(), socket));
+		context
+			.runtime_handle
+			.spawn(Context::connect_future::<T>(context.clone(), socket, false));
 	}
 
-	pub fn connect_normal(context: Arc<Context>, socket: net::SocketAddr) {
+	pub fn connect_normal(context: Arc<Context>, socket: NodeAddress) {
 		Self::connect::<NormalSessionFactory>(context, socket)
 	}
 
+	/// Opens outbound connection, which relays neither transactions nor addresses.
+	/// Such connections are hard to detect for the attacker, which makes eclipsing us harder.
+	pub fn connect_block_relay(context: Arc<Context>, socket: NodeAddress) {
+		context.connection_counter.note_new_block_relay_connection();
+		context
+			.runtime_handle
+			.spawn(Context::connect_future::<BlockRelaySessionFactory>(context.clone(), socket, true));
+	}
+
 	pub async fn accept_connection_future(context: Arc<Context>, stream: TcpStream, socket: net::SocketAddr, config: NetConfig) {
 		match accept_connection(stream, &config, socket).await {
 			Ok(connection) => {
 				// successful handshake
 				trace!("Accepted connection from {}", connection.address);
-				context.node_table.write().insert(connection.address, connection.services);
 				let channel = context
 					.connections
-					.store::<NormalSessionFactory>(context.clone(), connection, Direction::Inbound);
+					.store::<NormalSessionFactory>(context.clone(), connection, Direction::Inbound, false);
 
 				// initialize session and then start reading messages
 				channel.session().initialize();
@@ -228,20 +476,17 @@ impl Context {
 				// protocol error
 				trace!("Accepting handshake from {} failed with error: {}", socket, err);
 				// TODO: close socket
-				context.node_table.write().note_failure(&socket);
 				context.connection_counter.note_close_inbound_connection();
 			}
 			Err(Error::Timeout) => {
 				// connection time out
 				trace!("Accepting handshake from {} timed out", socket);
 				// TODO: close socket
-				context.node_table.write().note_failure(&socket);
 				context.connection_counter.note_close_inbound_connection();
 			}
 			_ => {
 				// network error
 				trace!("Accepting handshake from {} failed with network error", socket);
-				context.node_table.write().note_failure(&socket);
 				context.connection_counter.note_close_inbound_connection();
 			}
 		}
@@ -252,6 +497,49 @@ impl Context {
 		tokio::spawn(Context::accept_connection_future(context.clone(), stream, socket, config));
 	}
 
+	/// Creates ephemeral onion service, which forwards incoming Tor connections to our tcp server.
+	/// The service exists as long as the connection to the Tor control port is open.
+	pub async fn publish_onion_service(context: Arc<Context>) {
+		let tor_control = match context.config.tor_control {
+			Some(ref tor_control) => tor_control.clone(),
+			None => return,
+		};
+
+		let mut connection = match TorControlConnection::connect(&tor_control).await {
+			Ok(connection) => connection,
+			Err(err) => {
+				error!("Connecting to Tor control port {} failed with {}", tor_control.address, err);
+				return;
+			}
+		};
+
+		let config = &context.config.connection;
+		let target = net::SocketAddr::new(net::Ipv4Addr::LOCALHOST.into(), config.local_address.port());
+		let port = config.network.port();
+		match connection.add_onion(port, &target).await {
+			Ok(service_id) => match format!("{}.onion:{}", service_id, port).parse::<NodeAddress>() {
+				Ok(address) => {
+					info!("Onion service {} created", address);
+					*context.local_onion_address.write() = Some(address);
+				}
+				Err(_) => {
+					error!("Tor returned invalid onion service id {}", service_id);
+					return;
+				}
+			},
+			Err(err) => {
+				error!("Creating onion service failed with {}", err);
+				return;
+			}
+		}
+
+		if let Err(err) = connection.wait_closed().await {
+			error!("Connection to Tor control port failed with {}", err);
+		}
+		warn!("Onion service is no longer available");
+		*context.local_onion_address.write() = None;
+	}
+
 	/// Starts tcp server and listens for incoming connections.
 	pub async fn listen(context: Arc<Context>, config: NetConfig) {
 		trace!("Starting tcp server");
@@ -259,11 +547,20 @@ impl Context {
 		while let Some(stream) = server.next().await {
 			match stream {
 				Ok(stream) => {
+					let socket = match stream.peer_addr() {
+						Ok(socket) => socket,
+						Err(_) => continue,
+					};
+					if context.is_banned(&socket.into()) {
+						trace!("Refusing connection from banned {}", socket);
+						let _ = stream.shutdown(net::Shutdown::Both);
+						continue;
+					}
 					// because we acquire atomic value twice,
 					// it may happen that accept slightly more connections than we need
 					// we don't mind
-					if context.connection_counter.inbound_connections_needed() > 0 {
-						Context::accept_connection(context.clone(), stream, config.local_address, config.clone());
+					if context.connection_counter.inbound_connections_needed() > 0 || context.evict_inbound_peer() {
+						Context::accept_connection(context.clone(), stream, socket, config.clone());
 					} else {
 						// ignore result
 						let _ = stream.shutdown(net::Shutdown::Both);
@@ -284,7 +581,7 @@ impl Context {
 				// handle message and read the next one
 				match channel.session().on_message(command, payload) {
 					Ok(_) => {
-						context.node_table.write().note_used(&channel.peer_info().address);
+						context.address_manager.write().connected(&channel.peer_info().address);
 						Ok(())
 					}
 					Err(err) => {
@@ -314,6 +611,7 @@ impl Context {
 				let message = Message::with_flags(info.magic, info.version, &payload, serialization_flags)
 					.expect("failed to create outgoing message");
 				channel.session().stats().lock().report_send(T::command().into(), message.len());
+				context.report_send(message.len());
 				Context::send(context, channel, message).await
 			}
 			None => {
@@ -361,10 +659,7 @@ impl Context {
 			channel.session().on_close();
 			trace!("Disconnecting from {}", info.address);
 			tokio::spawn(async move { channel.shutdown().await });
-			match info.direction {
-				Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
-				Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
-			}
+			self.note_close_connection(info.direction, info.block_relay_only);
 		}
 	}
 
@@ -375,11 +670,48 @@ impl Context {
 			channel.session().on_close();
 			trace!("Disconnecting from {} caused by {}", info.address, error);
 			tokio::spawn(async move { channel.shutdown().await });
-			self.node_table.write().note_failure(&info.address);
-			match info.direction {
-				Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
-				Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
+			self.address_manager.write().attempt(&info.address);
+			self.note_close_connection(info.direction, info.block_relay_only);
+		}
+	}
+
+	fn note_close_connection(&self, direction: Direction, block_relay_only: bool) {
+		match direction {
+			Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
+			Direction::Outbound if block_relay_only => self.connection_counter.note_close_block_relay_connection(),
+			Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
+		}
+	}
+
+	/// Disconnects inbound peer to make room for the new one.
+	/// Returns false if all inbound peers are protected from the eviction.
+	fn evict_inbound_peer(&self) -> bool {
+		let candidates = self
+			.connections
+			.channels()
+			.values()
+			.filter(|channel| channel.peer_info().direction == Direction::Inbound)
+			.map(|channel| {
+				let info = channel.peer_info();
+				let stats = channel.session().stats().lock();
+				EvictionCandidate {
+					id: info.id,
+					netgroup: self.netgroups.keyed(&self.netgroups.group(&info.address.address)),
+					min_ping: stats.min_ping.map(Duration::from_secs_f64),
+					last_block: stats.last_block,
+					last_tx: stats.last_tx,
+					relay_txs: info.version_message.relay_transactions(),
+				}
+			})
+			.collect();
+
+		match select_peer_to_evict(candidates) {
+			Some(id) => {
+				trace!("Evicting inbound peer#{} to make room for the new one", id);
+				self.close_channel(id);
+				true
 			}
+			None => false,
 		}
 	}
 
@@ -398,7 +730,7 @@ impl Context {
 	}
 
 	pub fn nodes(&self) -> Vec<Node> {
-		self.node_table.read().nodes()
+		self.address_manager.read().nodes()
 	}
 }
 
@@ -429,27 +761,21 @@ impl P2P {
 			self.connect::<NormalSessionFactory>(*peer);
 		}
 
-		if self.context.config.seed.is_some() {
-			Context::connect::<SeednodeSessionFactory>(self.context.clone(), self.context.config.seed.unwrap());
-		} else {
-			let seeds: Vec<SocketAddr> = match self.context.config.connection.network {
-				Network::Mainnet => seeds_main(),
-				Network::Testnet => seeds_test(),
-				_ => vec![],
-			};
-
-			for seed in seeds.choose_multiple(&mut rand::thread_rng(), 5) {
-				Context::connect::<SeednodeSessionFactory>(self.context.clone(), *seed);
+		match self.context.config.seed {
+			Some(seed) => Context::connect::<SeednodeSessionFactory>(self.context.clone(), seed.into()),
+			None => {
+				tokio::spawn(Context::bootstrap(self.context.clone()));
 			}
 		}
 
 		tokio::spawn(Context::autoconnect(self.context.clone()));
+		tokio::spawn(Context::publish_onion_service(self.context.clone()));
 
 		Context::listen(self.context.clone(), self.context.config.connection.clone()).await
 	}
 
 	/// Attempts to connect to the specified node
-	pub fn connect<T>(&self, addr: net::SocketAddr)
+	pub fn connect<T>(&self, addr: NodeAddress)
 	where
 		T: SessionFactory + 'static,
 	{
@@ -460,3 +786,96 @@ impl P2P {
 		&self.context
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{Context, P2P};
+	use crate::net::StubResolver;
+	use crate::util::InternetProtocol;
+	use crate::{Config, InboundSyncConnectionRef, LocalSyncNode, NetConfig, OutboundSyncConnectionRef};
+	use message::common::Services;
+	use network::Network;
+	use std::net::IpAddr;
+	use std::sync::Arc;
+	use std::{env, fs};
+	use tokio::runtime::Handle;
+
+	struct DummyLocalSyncNode;
+
+	impl LocalSyncNode for DummyLocalSyncNode {
+		fn create_sync_session(&self, _height: i32, _services: Services, _outbound: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
+			unreachable!("no connections are established in tests")
+		}
+	}
+
+	fn p2p(name: &str, dns_seed: bool) -> P2P {
+		let path = env::temp_dir().join(name).to_str().unwrap().to_owned();
+		let _ = fs::remove_dir_all(&path);
+		fs::create_dir_all(&path).unwrap();
+
+		let config = Config {
+			inbound_connections: 0,
+			outbound_connections: 8,
+			block_relay_connections: 0,
+			connection: NetConfig {
+				protocol_version: 70016,
+				protocol_minimum: 70001,
+				network: Network::Testnet,
+				local_address: "127.0.0.1:18333".parse().unwrap(),
+				services: Services::default(),
+				user_agent: "test".into(),
+				start_height: 0,
+				relay: true,
+				v2_transport: false,
+				txreconciliation: false,
+				proxy: None,
+				onion_proxy: None,
+			},
+			peers: vec![],
+			seed: None,
+			dns_seed,
+			node_table_path: path.clone(),
+			ban_list_path: path,
+			preferable_services: Services::default(),
+			internet_protocol: InternetProtocol::default(),
+			asmap_path: None,
+			tor_control: None,
+			max_upload_target: None,
+		};
+		let resolver = StubResolver(
+			vec![("testnet-seed.bitcoin.jonasschnelli.ch", vec!["1.2.3.4", "5.6.7.8"])]
+				.into_iter()
+				.collect(),
+		);
+		let context = Context::new(Handle::current(), Box::new(DummyLocalSyncNode), config, Arc::new(resolver)).unwrap();
+		P2P::new(Arc::new(context))
+	}
+
+	fn node_ips(p2p: &P2P) -> Vec<IpAddr> {
+		let mut ips: Vec<_> = p2p
+			.context()
+			.nodes()
+			.iter()
+			.filter_map(|node| node.address().address.ip())
+			.collect();
+		ips.sort();
+		ips
+	}
+
+	#[tokio::test]
+	async fn test_bootstrap_queries_dns_seeds() {
+		let p2p = p2p("bitcoin-rs-test-bootstrap-dns-seed", true);
+		Context::bootstrap(p2p.context().clone()).await;
+		assert_eq!(
+			node_ips(&p2p),
+			vec!["1.2.3.4".parse::<IpAddr>().unwrap(), "5.6.7.8".parse().unwrap()]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_bootstrap_without_dns_seeds() {
+		let p2p = p2p("bitcoin-rs-test-bootstrap-no-dns-seed", false);
+		Context::bootstrap(p2p.context().clone()).await;
+		assert!(node_ips(&p2p).is_empty());
+	}
+}
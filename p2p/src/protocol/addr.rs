@@ -3,7 +3,7 @@ use crate::io::Error;
 use crate::net::PeerContext;
 use crate::protocol::Protocol;
 use crate::Direction;
-use message::types::{Addr, GetAddr};
+use message::types::{Addr, AddrV2, AddressV2Entry, GetAddr, SendAddrV2};
 use message::{deserialize_payload, Command, Payload};
 use std::sync::Arc;
 use std::time::Duration;
@@ -13,13 +13,30 @@ pub struct AddrProtocol {
 	context: Arc<PeerContext>,
 	/// True if this is a connection to the seednode && we should disconnect after receiving addr message
 	is_seed_node_connection: bool,
+	/// True if peer signalled support of the addrv2 message
+	addrv2: bool,
 }
 
 impl AddrProtocol {
 	pub fn new(context: Arc<PeerContext>, is_seed_node_connection: bool) -> Self {
+		let addrv2 = context.info().addrv2;
 		AddrProtocol {
 			context,
 			is_seed_node_connection,
+			addrv2,
+		}
+	}
+
+	fn on_addresses(&self, addresses: Vec<AddressV2Entry>) {
+		let nodes_len = addresses.len();
+		self.context
+			.global()
+			.update_node_table_from(&self.context.info().address, addresses);
+		// seednodes are currently responding with two addr messages:
+		// 1) addr message with single address - seednode itself
+		// 2) addr message with 1000 addresses (seednode node_table contents)
+		if self.is_seed_node_connection && nodes_len > 1 {
+			self.context.close();
 		}
 	}
 }
@@ -36,26 +53,28 @@ impl Protocol for AddrProtocol {
 		// meanwhile seednodes, surprisingly, send addr message even before they are asked for it
 		if command == &GetAddr::command() {
 			let _: GetAddr = deserialize_payload(payload, self.context.info().version)?;
-			let entries = self.context.global().node_table_entries().into_iter().map(Into::into).collect();
-			let addr = Addr::new(entries);
-			self.context.send_response_inline(addr);
+			let entries = self.context.global().node_table_entries().into_iter().map(AddressV2Entry::from);
+			if self.addrv2 {
+				self.context.send_response_inline(AddrV2::new(entries.collect()));
+			} else {
+				// addresses of the networks other than ipv4 and ipv6 are relayed only to the peers supporting addrv2
+				let addr = Addr::new(entries.filter_map(|entry| entry.to_v1()).collect());
+				self.context.send_response_inline(addr);
+			}
+		} else if command == &SendAddrV2::command() {
+			let _: SendAddrV2 = deserialize_payload(payload, self.context.info().version)?;
+			self.addrv2 = true;
 		} else if command == &Addr::command() {
 			let addr: Addr = deserialize_payload(payload, self.context.info().version)?;
 			match addr {
 				Addr::V0(_) => {
 					unreachable!("This version of protocol is not supported!");
 				}
-				Addr::V31402(addr) => {
-					let nodes_len = addr.addresses.len();
-					self.context.global().update_node_table(addr.addresses);
-					// seednodes are currently responding with two addr messages:
-					// 1) addr message with single address - seednode itself
-					// 2) addr message with 1000 addresses (seednode node_table contents)
-					if self.is_seed_node_connection && nodes_len > 1 {
-						self.context.close();
-					}
-				}
+				Addr::V31402(addr) => self.on_addresses(addr.addresses.into_iter().map(Into::into).collect()),
 			}
+		} else if command == &AddrV2::command() {
+			let addr: AddrV2 = deserialize_payload(payload, self.context.info().version)?;
+			self.on_addresses(addr.addresses);
 		}
 		Ok(())
 	}
@@ -81,7 +100,7 @@ impl Protocol for SeednodeProtocol {
 	fn on_message(&mut self, command: &Command, _payload: &Bytes) -> Result<(), Error> {
 		// Seed nodes send addr message more than once with different addresses.
 		// We can't disconnect after first read. Let's delay it by 60 seconds.
-		if !self.disconnecting && command == &Addr::command() {
+		if !self.disconnecting && (command == &Addr::command() || command == &AddrV2::command()) {
 			self.disconnecting = true;
 			let context = self.context.global().clone();
 			let peer = self.context.info().id;
@@ -92,3 +111,26 @@ impl Protocol for SeednodeProtocol {
 		Ok(())
 	}
 }
+
+/// Protocol of the feeler connection, which only checks that the node is reachable
+/// and disconnects right after the handshake.
+pub struct FeelerProtocol {
+	/// Context
+	context: Arc<PeerContext>,
+}
+
+impl FeelerProtocol {
+	pub fn new(context: Arc<PeerContext>) -> Self {
+		FeelerProtocol { context }
+	}
+}
+
+impl Protocol for FeelerProtocol {
+	fn initialize(&mut self) {
+		self.context.close();
+	}
+
+	fn on_message(&mut self, _command: &Command, _payload: &Bytes) -> Result<(), Error> {
+		Ok(())
+	}
+}
@@ -4,7 +4,7 @@ mod sync;
 
 use message::common::Command;
 
-pub use self::addr::{AddrProtocol, SeednodeProtocol};
+pub use self::addr::{AddrProtocol, FeelerProtocol, SeednodeProtocol};
 pub use self::ping::PingProtocol;
 pub use self::sync::{
 	InboundSyncConnection, InboundSyncConnectionRef, InboundSyncConnectionState, InboundSyncConnectionStateRef, LocalSyncNode,
@@ -1,7 +1,9 @@
 use crate::bytes::Bytes;
-use crate::io::Error;
+use crate::io::{Error, TXRECONCILIATION_VERSION, WTXID_RELAY_VERSION};
 use crate::net::PeerContext;
 use crate::protocol::Protocol;
+use crate::util::Direction;
+use message::common::InventoryType;
 use message::{deserialize_payload, types, Command, Payload, Services};
 use ser::SERIALIZE_TRANSACTION_WITNESS;
 use std::sync::Arc;
@@ -41,7 +43,15 @@ pub trait InboundSyncConnection: Send + Sync {
 	fn on_compact_block(&self, message: types::CompactBlock);
 	fn on_get_block_txn(&self, message: types::GetBlockTxn);
 	fn on_block_txn(&self, message: types::BlockTxn);
+	fn on_get_cfilters(&self, message: types::GetCFilters);
+	fn on_get_cfheaders(&self, message: types::GetCFHeaders);
+	fn on_get_cfcheckpt(&self, message: types::GetCFCheckpt);
 	fn on_notfound(&self, message: types::NotFound);
+	fn on_wtxid_relay(&self, message: types::WtxidRelay);
+	fn on_sendtxrcncl(&self, message: types::SendTxRcncl, local_salt: u64, is_initiator: bool);
+	fn on_reqrecon(&self, message: types::ReqRecon);
+	fn on_sketch(&self, message: types::Sketch);
+	fn on_reconcildiff(&self, message: types::ReconcilDiff);
 }
 
 pub trait OutboundSyncConnection: Send + Sync {
@@ -66,9 +76,17 @@ pub trait OutboundSyncConnection: Send + Sync {
 	fn send_compact_block(&self, message: types::CompactBlock);
 	fn send_get_block_txn(&self, message: types::GetBlockTxn);
 	fn send_block_txn(&self, message: types::BlockTxn);
+	fn send_cfilter(&self, message: types::CFilter);
+	fn send_cfheaders(&self, message: types::CFHeaders);
+	fn send_cfcheckpt(&self, message: types::CFCheckpt);
 	fn send_notfound(&self, message: types::NotFound);
+	fn send_reqrecon(&self, message: types::ReqRecon);
+	fn send_sketch(&self, message: types::Sketch);
+	fn send_reconcildiff(&self, message: types::ReconcilDiff);
+	fn is_historical_block_serving_limited(&self) -> bool;
 	fn ignored(&self, id: u32);
 	fn close(&self);
+	fn ban(&self);
 }
 
 struct OutboundSync {
@@ -166,10 +184,39 @@ impl OutboundSyncConnection for OutboundSync {
 		self.context.send_request(message);
 	}
 
+	fn send_cfilter(&self, message: types::CFilter) {
+		self.context.send_request(message);
+	}
+
+	fn send_cfheaders(&self, message: types::CFHeaders) {
+		self.context.send_request(message);
+	}
+
+	fn send_cfcheckpt(&self, message: types::CFCheckpt) {
+		self.context.send_request(message);
+	}
+
 	fn send_notfound(&self, message: types::NotFound) {
 		self.context.send_request(message);
 	}
 
+	fn send_reqrecon(&self, message: types::ReqRecon) {
+		self.context.send_request(message);
+	}
+
+	fn send_sketch(&self, message: types::Sketch) {
+		self.context.send_request(message);
+	}
+
+	fn send_reconcildiff(&self, message: types::ReconcilDiff) {
+		self.context.stats().lock().report_reconcildiff(&message);
+		self.context.send_request(message);
+	}
+
+	fn is_historical_block_serving_limited(&self) -> bool {
+		self.context.global().is_historical_block_serving_limited()
+	}
+
 	fn ignored(&self, id: u32) {
 		self.context.ignore_response(id);
 	}
@@ -178,12 +225,19 @@ impl OutboundSyncConnection for OutboundSync {
 		self.context.global().penalize_node(&self.context.info().address);
 		self.context.close()
 	}
+
+	fn ban(&self) {
+		self.context.global().ban_node(&self.context.info().address);
+		self.context.close()
+	}
 }
 
 pub struct SyncProtocol {
 	inbound_connection: InboundSyncConnectionRef,
 	context: Arc<PeerContext>,
 	state: InboundSyncConnectionStateRef,
+	/// Transactions are announced using their witness hashes
+	wtxid_relay: bool,
 }
 
 impl SyncProtocol {
@@ -197,15 +251,52 @@ impl SyncProtocol {
 			inbound_connection,
 			context,
 			state,
+			wtxid_relay: false,
 		}
 	}
+
+	/// Start reconciling transactions with the peer, if both sides have agreed to it
+	fn register_txreconciliation(&self, message: types::SendTxRcncl, is_initiator: bool) {
+		let info = self.context.info();
+		let local_salt = match info.local_txrcncl_salt {
+			Some(local_salt) if self.wtxid_relay && !info.block_relay_only => local_salt,
+			_ => return,
+		};
+
+		self.context.stats().lock().txreconciliation = Some(Default::default());
+		self.inbound_connection.on_sendtxrcncl(message, local_salt, is_initiator);
+	}
+
+	/// Reconciliation messages are only accepted from the peers, we have registered reconciliation with
+	fn is_txreconciliation_registered(&self) -> bool {
+		!self.state.synchronizing() && self.context.stats().lock().txreconciliation.is_some()
+	}
 }
 
 impl Protocol for SyncProtocol {
 	fn initialize(&mut self) {
 		let info = self.context.info();
+		let mut version = info.version_message.clone();
+		if info.block_relay_only {
+			// transactions are never announced to the block-relay-only peers
+			if let types::Version::V70001(_, _, ref mut v70001) = version {
+				v70001.relay = false;
+			}
+		}
+		if info.wtxid_relay {
+			self.wtxid_relay = true;
+			self.inbound_connection.on_wtxid_relay(types::WtxidRelay);
+		}
+		// outbound peer has sent its salt during the handshake => we are requesting reconciliations
+		if let Some(salt) = info.txrcncl_salt {
+			let message = types::SendTxRcncl {
+				version: TXRECONCILIATION_VERSION,
+				salt,
+			};
+			self.register_txreconciliation(message, true);
+		}
 		self.inbound_connection
-			.start_sync_session(format!("{}/{}", info.address, info.user_agent), info.version_message.clone());
+			.start_sync_session(format!("{}/{}", info.address, info.user_agent), version);
 	}
 
 	fn on_message(&mut self, command: &Command, payload: &Bytes) -> Result<(), Error> {
@@ -219,7 +310,15 @@ impl Protocol for SyncProtocol {
 				return Ok(());
 			}
 
-			let message: types::Inv = deserialize_payload(payload, version)?;
+			let mut message: types::Inv = deserialize_payload(payload, version)?;
+			if self.context.info().block_relay_only {
+				message.inventory.retain(|item| {
+					!matches!(
+						item.inv_type,
+						InventoryType::MessageTx | InventoryType::MessageWtx | InventoryType::MessageWitnessTx
+					)
+				});
+			}
 			self.inbound_connection.on_inventory(message);
 		} else if command == &types::GetData::command() {
 			if self.state.synchronizing() {
@@ -248,7 +347,7 @@ impl Protocol for SyncProtocol {
 			// we ignore all transactions while synchronizing, as memory pool contains
 			// only verified transactions && we can not verify on-top transactions while
 			// we are not on the top
-			if self.state.synchronizing() {
+			if self.state.synchronizing() || self.context.info().block_relay_only {
 				return Ok(());
 			}
 
@@ -301,9 +400,68 @@ impl Protocol for SyncProtocol {
 		} else if command == &types::BlockTxn::command() {
 			let message: types::BlockTxn = deserialize_payload(payload, version)?;
 			self.inbound_connection.on_block_txn(message);
+		} else if command == &types::GetCFilters::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFilters = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfilters(message);
+		} else if command == &types::GetCFHeaders::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFHeaders = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfheaders(message);
+		} else if command == &types::GetCFCheckpt::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFCheckpt = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfcheckpt(message);
 		} else if command == &types::NotFound::command() {
 			let message: types::NotFound = deserialize_payload(payload, version)?;
 			self.inbound_connection.on_notfound(message);
+		} else if command == &types::WtxidRelay::command() {
+			// inbound peers signal wtxid relay support after the handshake
+			if self.context.info().version_message.version() >= WTXID_RELAY_VERSION {
+				let message: types::WtxidRelay = deserialize_payload(payload, version)?;
+				self.wtxid_relay = true;
+				self.inbound_connection.on_wtxid_relay(message);
+			}
+		} else if command == &types::SendTxRcncl::command() {
+			// inbound peers are sending their salt after the handshake => they are requesting reconciliations
+			let message: types::SendTxRcncl = deserialize_payload(payload, version)?;
+			if self.context.info().direction == Direction::Inbound
+				&& message.version >= TXRECONCILIATION_VERSION
+				&& self.context.stats().lock().txreconciliation.is_none()
+			{
+				self.register_txreconciliation(message, false);
+			}
+		} else if command == &types::ReqRecon::command() {
+			if !self.is_txreconciliation_registered() {
+				return Ok(());
+			}
+
+			let message: types::ReqRecon = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_reqrecon(message);
+		} else if command == &types::Sketch::command() {
+			if !self.is_txreconciliation_registered() {
+				return Ok(());
+			}
+
+			let message: types::Sketch = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_sketch(message);
+		} else if command == &types::ReconcilDiff::command() {
+			if !self.is_txreconciliation_registered() {
+				return Ok(());
+			}
+
+			let message: types::ReconcilDiff = deserialize_payload(payload, version)?;
+			self.context.stats().lock().report_reconcildiff(&message);
+			self.inbound_connection.on_reconcildiff(message);
 		}
 		Ok(())
 	}
@@ -2,7 +2,7 @@ use crate::bytes::Bytes;
 use crate::io::Error;
 use crate::net::{PeerContext, PeerStats};
 use crate::p2p::Context;
-use crate::protocol::{AddrProtocol, PingProtocol, Protocol, SeednodeProtocol, SyncProtocol};
+use crate::protocol::{AddrProtocol, FeelerProtocol, PingProtocol, Protocol, SeednodeProtocol, SyncProtocol};
 use crate::util::PeerInfo;
 use message::Command;
 use parking_lot::Mutex;
@@ -36,6 +36,29 @@ impl SessionFactory for NormalSessionFactory {
 	}
 }
 
+/// Session of the block-relay-only connection, which does not exchange addresses.
+pub struct BlockRelaySessionFactory;
+
+impl SessionFactory for BlockRelaySessionFactory {
+	fn new_session(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Session {
+		let peer_context = Arc::new(PeerContext::new(context, info, synchronous));
+		let ping = PingProtocol::new(peer_context.clone()).boxed();
+		let sync = SyncProtocol::new(peer_context.clone()).boxed();
+		Session::new(peer_context, vec![ping, sync])
+	}
+}
+
+/// Session of the short-lived connection, which tests that the node is reachable.
+pub struct FeelerSessionFactory;
+
+impl SessionFactory for FeelerSessionFactory {
+	fn new_session(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Session {
+		let peer_context = Arc::new(PeerContext::new(context, info, synchronous));
+		let feeler = FeelerProtocol::new(peer_context.clone()).boxed();
+		Session::new(peer_context, vec![feeler])
+	}
+}
+
 pub struct Session {
 	peer_context: Arc<PeerContext>,
 	protocols: Mutex<Vec<Box<dyn Protocol>>>,
@@ -63,6 +86,7 @@ impl Session {
 
 	pub fn on_message(&self, command: Command, payload: Bytes) -> Result<(), Error> {
 		self.stats().lock().report_recv(command.clone(), payload.len());
+		self.peer_context.global().report_recv(payload.len());
 
 		self.protocols
 			.lock()
@@ -0,0 +1,1136 @@
+//! Address manager, which keeps addresses of the nodes in two bucketed tables.
+//! Addresses we have not connected to yet are stored in the `new` table, bucketed by the network group
+//! of the address and of the peer which told us about it, so that single peer can fill only a few buckets.
+//! Addresses we have successfully connected to are moved to the `tried` table, bucketed by their own
+//! network group. Bucket positions depend on the secret key, so the attacker can not choose them.
+//! https://github.com/bitcoin/bitcoin/blob/master/src/addrman.h
+
+use crate::util::{InternetProtocol, NetGroups};
+use crypto::{dhash256, sha256};
+use message::common::{NetworkAddress, NodeAddress, Services};
+use message::types::AddressV2Entry;
+use primitives::bytes::Bytes;
+use primitives::time::{RealTime, Time};
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::Rng;
+use ser::{Reader, Stream};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::{fs, io, net};
+
+const ADDRESSES_FILE: &str = "addresses.dat";
+/// Node table of the older versions, imported if there is no addresses file yet.
+const LEGACY_NODES_FILE: &str = "nodes.csv";
+const FILE_VERSION: u8 = 1;
+
+const TRIED_BUCKET_COUNT: u64 = 256;
+const NEW_BUCKET_COUNT: u64 = 1024;
+const BUCKET_SIZE: u64 = 64;
+/// Number of tried buckets, over which addresses from the single network group are spread.
+const TRIED_BUCKETS_PER_GROUP: u64 = 8;
+/// Number of new buckets, over which addresses from the single source network group are spread.
+const NEW_BUCKETS_PER_SOURCE_GROUP: u64 = 64;
+/// Maximal number of new buckets, the address can be in.
+const NEW_BUCKETS_PER_ADDRESS: u32 = 8;
+/// Addresses not seen for this long are terrible.
+const HORIZON: u64 = 30 * 24 * 60 * 60;
+/// Addresses which failed this many times without any success are terrible.
+const RETRIES: u32 = 3;
+/// Addresses which failed this many times in a row ...
+const MAX_FAILURES: u32 = 10;
+/// ... and have not succeeded for this long are terrible.
+const MIN_FAIL: u64 = 7 * 24 * 60 * 60;
+/// Tried address, which succeeded in this time, is not replaced by the colliding one.
+const REPLACEMENT: u64 = 4 * 60 * 60;
+/// Colliding address replaces the tried one, if it was not tested in this time.
+const TEST_WINDOW: u64 = 40 * 60;
+/// Maximal number of addresses waiting for the test of the tried address they collide with.
+const MAX_TRIED_COLLISIONS: usize = 10;
+/// Penalty applied to the time of the address, which was relayed by the other node.
+const TIME_PENALTY: u64 = 2 * 60 * 60;
+/// Maximal number of addresses returned in response to `getaddr` ...
+const GETADDR_MAX: usize = 1000;
+/// ... and maximal percentage of all known addresses.
+const GETADDR_MAX_PCT: usize = 23;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Node {
+	/// Node address.
+	addr: NodeAddress,
+	/// Timestamp of last interaction with a node, or the time advertised by other nodes.
+	time: u64,
+	/// Services supported by the node.
+	services: Services,
+	/// Is preferable node?
+	is_preferable: bool,
+	/// Address of the node, which told us about this one.
+	source: NetworkAddress,
+	/// Time of the last connection attempt.
+	last_try: u64,
+	/// Time of the last successful connection.
+	last_success: u64,
+	/// Connection attempts since the last successful one.
+	attempts: u32,
+	/// Number of new buckets referencing this node.
+	ref_count: u32,
+	/// Is node in the tried table?
+	in_tried: bool,
+}
+
+impl Node {
+	pub fn address(&self) -> NodeAddress {
+		self.addr
+	}
+
+	/// Returns true if the address is not worth keeping.
+	fn is_terrible(&self, now: u64) -> bool {
+		// never remove things tried in the last minute
+		if self.last_try != 0 && self.last_try >= now.saturating_sub(60) {
+			return false;
+		}
+		// came from the future
+		if self.time > now + 10 * 60 {
+			return true;
+		}
+		// not seen in recent history
+		if self.time == 0 || now.saturating_sub(self.time) > HORIZON {
+			return true;
+		}
+		// tried several times and never succeeded
+		if self.last_success == 0 && self.attempts >= RETRIES {
+			return true;
+		}
+		// many successive failures in the last week
+		now.saturating_sub(self.last_success) > MIN_FAIL && self.attempts >= MAX_FAILURES
+	}
+
+	/// Returns relative chance, the address is selected for the connection.
+	fn chance(&self, now: u64) -> f64 {
+		let mut chance = 1.0;
+		// deprioritize very recent attempts
+		if now.saturating_sub(self.last_try) < 10 * 60 {
+			chance *= 0.01;
+		}
+		// deprioritize 66% after each failed attempt, but at most 1/28th
+		chance *= 0.66f64.powi(self.attempts.min(8) as i32);
+		if !self.is_preferable {
+			chance *= 0.5;
+		}
+		chance
+	}
+}
+
+impl From<Node> for AddressV2Entry {
+	fn from(node: Node) -> Self {
+		AddressV2Entry {
+			timestamp: node.time as u32,
+			services: node.services,
+			address: node.addr.address,
+			port: node.addr.port,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum NodeTableError {
+	AddressAlreadyAdded,
+	NoAddressInTable,
+}
+
+type NodeId = u64;
+type Slot = (u64, u64);
+
+#[derive(Default)]
+pub struct AddressManager<T = RealTime>
+where
+	T: Time,
+{
+	/// Time source.
+	time: T,
+	/// Preferable services.
+	preferable_services: Services,
+	/// Secret key, randomizing bucket positions.
+	key: [u8; 32],
+	/// Network groups of the addresses.
+	groups: NetGroups,
+	/// Nodes by id.
+	nodes: HashMap<NodeId, Node>,
+	/// Node ids by address.
+	by_addr: HashMap<NodeAddress, NodeId>,
+	/// Id of the next inserted node.
+	next_id: NodeId,
+	/// Occupied slots of the new table.
+	new_table: HashMap<Slot, NodeId>,
+	/// Occupied slots of the tried table.
+	tried_table: HashMap<Slot, NodeId>,
+	/// New nodes, which collide with the tried ones and wait until the old node is tested.
+	collisions: BTreeSet<NodeId>,
+	/// Where addresses are stored.
+	path: String,
+}
+
+impl AddressManager {
+	/// Opens a file and loads addresses from it. If there is no such file,
+	/// addresses are imported from the node table of the previous versions.
+	pub fn from_file(preferable_services: Services, groups: NetGroups, path: String) -> Result<Self, io::Error> {
+		let file_path = path.clone() + "/" + ADDRESSES_FILE;
+		let mut manager = match fs::read(&file_path) {
+			Ok(bytes) => Self::load(preferable_services, groups, &bytes)?,
+			Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+				let mut manager = Self::new(preferable_services, groups);
+				if let Ok(file) = fs::File::open(path + "/" + LEGACY_NODES_FILE) {
+					manager.import_legacy(file);
+				}
+				manager
+			}
+			Err(err) => return Err(err),
+		};
+		manager.path = file_path;
+		Ok(manager)
+	}
+
+	/// Saves addresses to file
+	pub fn save_to_file(&self) -> Result<(), io::Error> {
+		// write to the temporary file first, so that crash does not leave us with a partial file
+		let tmp_path = self.path.clone() + ".new";
+		fs::write(&tmp_path, self.save())?;
+		fs::rename(tmp_path, &self.path)
+	}
+}
+
+impl<T> AddressManager<T>
+where
+	T: Time + Default,
+{
+	/// Creates empty address manager with random key.
+	pub fn new(preferable_services: Services, groups: NetGroups) -> Self {
+		AddressManager {
+			preferable_services,
+			key: rand::thread_rng().gen(),
+			groups,
+			..Default::default()
+		}
+	}
+
+	/// Loads addresses from the binary source.
+	pub fn load(preferable_services: Services, groups: NetGroups, bytes: &[u8]) -> Result<Self, io::Error> {
+		let err = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Load addresses error: {}", message));
+
+		if bytes.len() < 32 {
+			return Err(err("file is too short"));
+		}
+		let (data, checksum) = bytes.split_at(bytes.len() - 32);
+		if dhash256(data)[0..32] != *checksum {
+			return Err(err("invalid checksum"));
+		}
+
+		let mut reader = Reader::new(data);
+		let version: u8 = reader.read().map_err(|_| err("invalid version"))?;
+		if version != FILE_VERSION {
+			return Err(err("unsupported version"));
+		}
+
+		let mut manager = AddressManager {
+			preferable_services,
+			groups,
+			..Default::default()
+		};
+		reader.read_slice(&mut manager.key).map_err(|_| err("invalid key"))?;
+
+		let nodes = read_nodes(&mut reader, preferable_services).map_err(|_| err("invalid node"))?;
+		if !reader.is_finished() {
+			return Err(err("unexpected data"));
+		}
+
+		// restore tried nodes first, so that they are not pushed out by the new ones
+		let (tried, new): (Vec<_>, Vec<_>) = nodes.into_iter().partition(|node| node.in_tried);
+		for mut node in tried {
+			let slot = manager.tried_slot(&node.addr);
+			if manager.tried_table.contains_key(&slot) {
+				node.in_tried = false;
+				manager.insert_new(node, false);
+			} else {
+				let id = manager.create(node);
+				manager.tried_table.insert(slot, id);
+			}
+		}
+		for node in new {
+			manager.insert_new(node, false);
+		}
+
+		Ok(manager)
+	}
+}
+
+impl<T> AddressManager<T>
+where
+	T: Time,
+{
+	/// Adds address to the new table. Returns false if the address was not added, or there was nothing to update.
+	/// Address of the node, which relayed it, is used to choose the buckets.
+	pub fn add(&mut self, entry: AddressV2Entry, source: &NetworkAddress) -> bool {
+		let now = self.time.now().as_secs();
+		let addr = NodeAddress::new(entry.address, entry.port);
+		// addresses relayed by the other nodes are considered a bit older
+		let penalty = if entry.address == *source { 0 } else { TIME_PENALTY };
+		let time = u64::from(entry.timestamp).saturating_sub(penalty);
+
+		let node = match self.by_addr.get(&addr) {
+			Some(id) => {
+				let node = self.nodes.get_mut(id).expect("every address has a node; qed");
+				// update time of the node periodically
+				let update_interval = if now.saturating_sub(node.time) < 24 * 60 * 60 {
+					60 * 60
+				} else {
+					24 * 60 * 60
+				};
+				if node.time + update_interval < time {
+					node.time = time;
+				}
+				node.services = Services::from(u64::from(node.services) | u64::from(entry.services));
+				node.is_preferable = node.services.includes(&self.preferable_services);
+
+				// there is nothing new
+				if u64::from(entry.timestamp) <= node.time || node.in_tried || node.ref_count == NEW_BUCKETS_PER_ADDRESS {
+					return false;
+				}
+				// every next bucket is twice as hard to get into
+				if node.ref_count > 0 && rand::thread_rng().gen_range(0..(1u64 << node.ref_count)) != 0 {
+					return false;
+				}
+				let mut node = node.clone();
+				node.source = *source;
+				node
+			}
+			None => Node {
+				addr,
+				time,
+				services: entry.services,
+				is_preferable: entry.services.includes(&self.preferable_services),
+				source: *source,
+				last_try: 0,
+				last_success: 0,
+				attempts: 0,
+				ref_count: 0,
+				in_tried: false,
+			},
+		};
+
+		self.insert_new(node, false)
+	}
+
+	/// Adds many addresses relayed by the single node.
+	/// Used in `addr` and `addrv2` request handlers.
+	pub fn add_many(&mut self, entries: Vec<AddressV2Entry>, source: &NetworkAddress) {
+		let now = self.time.now().as_secs();
+		for mut entry in entries {
+			// addresses with nonsense time are considered 5 days old
+			if entry.timestamp <= 100_000_000 || u64::from(entry.timestamp) > now + 10 * 60 {
+				entry.timestamp = now.saturating_sub(5 * 24 * 60 * 60) as u32;
+			}
+			self.add(entry, source);
+		}
+	}
+
+	/// Adds address requested by the user. It replaces any address occupying its bucket position.
+	pub fn add_manual(&mut self, addr: NodeAddress, services: Services) -> Result<(), NodeTableError> {
+		if self.exists(&addr) {
+			return Err(NodeTableError::AddressAlreadyAdded);
+		}
+
+		let node = Node {
+			addr,
+			time: self.time.now().as_secs(),
+			services,
+			is_preferable: services.includes(&self.preferable_services),
+			source: addr.address,
+			last_try: 0,
+			last_success: 0,
+			attempts: 0,
+			ref_count: 0,
+			in_tried: false,
+		};
+		self.insert_new(node, true);
+		Ok(())
+	}
+
+	pub fn exists(&self, addr: &NodeAddress) -> bool {
+		self.by_addr.contains_key(addr)
+	}
+
+	/// Services, advertised by the node with given address.
+	pub fn services(&self, addr: &NodeAddress) -> Option<Services> {
+		self.by_addr.get(addr).map(|id| self.nodes[id].services)
+	}
+
+	/// Removes the address from both tables.
+	pub fn remove(&mut self, addr: &NodeAddress) -> Result<(), NodeTableError> {
+		let id = *self.by_addr.get(addr).ok_or(NodeTableError::NoAddressInTable)?;
+		if self.nodes[&id].in_tried {
+			let slot = self.tried_slot(addr);
+			self.tried_table.remove(&slot);
+		} else {
+			self.remove_from_new_buckets(id);
+		}
+		self.collisions.remove(&id);
+		self.delete(id);
+		Ok(())
+	}
+
+	/// Marks the address as the one we have successfully connected to and moves it to the tried table.
+	/// If its position in the tried table is occupied, the move waits until the occupant is tested.
+	pub fn good(&mut self, addr: &NodeAddress, services: Services) {
+		let now = self.time.now().as_secs();
+		if !self.exists(addr) {
+			let entry = AddressV2Entry {
+				timestamp: now as u32,
+				services,
+				address: addr.address,
+				port: addr.port,
+			};
+			self.add(entry, &addr.address);
+		}
+
+		let id = match self.by_addr.get(addr) {
+			Some(id) => *id,
+			None => return,
+		};
+		let node = self.nodes.get_mut(&id).expect("every address has a node; qed");
+		node.last_success = now;
+		node.last_try = now;
+		node.attempts = 0;
+		node.services = services;
+		node.is_preferable = services.includes(&self.preferable_services);
+		if node.in_tried {
+			return;
+		}
+
+		let slot = self.tried_slot(addr);
+		match self.tried_table.get(&slot) {
+			Some(_) => {
+				// test-before-evict
+				if self.collisions.len() < MAX_TRIED_COLLISIONS {
+					self.collisions.insert(id);
+				}
+			}
+			None => self.make_tried(id),
+		}
+	}
+
+	/// Notes connection attempt, which did not succeed.
+	pub fn attempt(&mut self, addr: &NodeAddress) {
+		let now = self.time.now().as_secs();
+		let nodes = &mut self.nodes;
+		if let Some(node) = self.by_addr.get(addr).and_then(|id| nodes.get_mut(id)) {
+			node.last_try = now;
+			node.attempts += 1;
+		}
+	}
+
+	/// Marks address as recently used.
+	pub fn connected(&mut self, addr: &NodeAddress) {
+		let now = self.time.now().as_secs();
+		let nodes = &mut self.nodes;
+		if let Some(node) = self.by_addr.get(addr).and_then(|id| nodes.get_mut(id)) {
+			// the time is relayed to other nodes, so update it only every 20 minutes
+			if now.saturating_sub(node.time) > 20 * 60 {
+				node.time = now;
+			}
+		}
+	}
+
+	/// Moves colliding addresses to the tried table, if the occupant of their position failed the test,
+	/// or if it was not tested in time.
+	pub fn resolve_collisions(&mut self) {
+		let now = self.time.now().as_secs();
+		for id in self.collisions.clone() {
+			let node = match self.nodes.get(&id) {
+				Some(node) if !node.in_tried => node,
+				_ => {
+					self.collisions.remove(&id);
+					continue;
+				}
+			};
+
+			let resolved = match self.tried_table.get(&self.tried_slot(&node.addr)) {
+				None => true,
+				Some(old_id) => {
+					let old = &self.nodes[old_id];
+					if now.saturating_sub(old.last_success) < REPLACEMENT {
+						// the old node is still good, forget the colliding one
+						self.collisions.remove(&id);
+						false
+					} else if now.saturating_sub(old.last_try) < REPLACEMENT {
+						// the old node was tried recently and failed
+						now.saturating_sub(old.last_try) > 60
+					} else {
+						// the old node was not tested in time
+						now.saturating_sub(node.last_success) > TEST_WINDOW
+					}
+				}
+			};
+
+			if resolved {
+				self.make_tried(id);
+				self.collisions.remove(&id);
+			}
+		}
+	}
+
+	/// Returns address from the tried table, which collides with some new address and should be tested.
+	pub fn select_tried_collision(&self) -> Option<NodeAddress> {
+		let id = self.collisions.iter().choose(&mut rand::thread_rng())?;
+		let node = self.nodes.get(id)?;
+		let old_id = self.tried_table.get(&self.tried_slot(&node.addr))?;
+		Some(self.nodes[old_id].addr)
+	}
+
+	/// Randomly selects nodes with desired services, which we can connect to.
+	/// Tried and new tables are used with the same probability and addresses
+	/// which failed recently are less likely to be selected.
+	/// Onion services are returned only if they are reachable through the proxy.
+	/// `accept` is called for every selected node and may be used to reject nodes from the same network group.
+	pub fn nodes_with_services<F>(
+		&self,
+		services: &Services,
+		protocol: InternetProtocol,
+		onion: bool,
+		except: &HashSet<NodeAddress>,
+		mut accept: F,
+		limit: usize,
+	) -> Vec<Node>
+	where
+		F: FnMut(&NodeAddress) -> bool,
+	{
+		let now = self.time.now().as_secs();
+		let (mut tried, mut new): (Vec<&Node>, Vec<&Node>) = self
+			.nodes
+			.values()
+			.filter(|node| protocol.is_reachable(&node.addr, onion))
+			.filter(|node| node.services.includes(services))
+			.filter(|node| !is_excepted(&node.addr, except))
+			.partition(|node| node.in_tried);
+
+		let mut rng = rand::thread_rng();
+		let mut result = Vec::new();
+		let mut chance_factor = 1.0;
+		while result.len() < limit && !(tried.is_empty() && new.is_empty()) {
+			let table = if new.is_empty() || (!tried.is_empty() && rng.gen_bool(0.5)) {
+				&mut tried
+			} else {
+				&mut new
+			};
+			let index = rng.gen_range(0..table.len());
+			if rng.gen::<f64>() < chance_factor * table[index].chance(now) {
+				let node = table.swap_remove(index);
+				if accept(&node.addr) {
+					result.push(node.clone());
+				}
+				chance_factor = 1.0;
+			} else {
+				chance_factor *= 1.2;
+			}
+		}
+		result
+	}
+
+	/// Returnes all nodes
+	pub fn nodes(&self) -> Vec<Node> {
+		self.nodes.values().cloned().collect()
+	}
+
+	/// Returns random nodes, which are relayed in response to `getaddr`.
+	/// Only limited part of all addresses is returned, so that the peer can not learn all of them at once.
+	pub fn recently_active_nodes(&self, protocol: InternetProtocol) -> Vec<Node> {
+		let now = self.time.now().as_secs();
+		let max = GETADDR_MAX.min(self.nodes.len() * GETADDR_MAX_PCT / 100);
+		let mut nodes: Vec<Node> = self
+			.nodes
+			.values()
+			.filter(|node| protocol.is_allowed(&node.addr))
+			.filter(|node| !node.is_terrible(now))
+			.cloned()
+			.collect();
+		nodes.shuffle(&mut rand::thread_rng());
+		nodes.truncate(max);
+		nodes
+	}
+
+	/// Returns true if there are no addresses worth connecting to, so that new ones should be requested from the DNS seeds.
+	pub fn is_stale(&self) -> bool {
+		let now = self.time.now().as_secs();
+		self.nodes.values().all(|node| node.is_terrible(now))
+	}
+
+	/// Save addresses in the binary format.
+	pub fn save(&self) -> Bytes {
+		let mut stream = Stream::new();
+		stream.append(&FILE_VERSION).append_slice(&self.key);
+		stream.append(&ser::CompactInteger::from(self.nodes.len()));
+		for node in self.nodes.values() {
+			append_address(&mut stream, &node.addr.address);
+			stream.append(&node.addr.port).append(&u64::from(node.services)).append(&node.time);
+			append_address(&mut stream, &node.source);
+			stream
+				.append(&node.last_try)
+				.append(&node.last_success)
+				.append(&node.attempts)
+				.append(&node.in_tried);
+		}
+
+		let mut data: Vec<u8> = stream.out().into();
+		let checksum = dhash256(&data);
+		data.extend_from_slice(&checksum[0..32]);
+		data.into()
+	}
+
+	/// Imports addresses from the csv node table of the previous versions.
+	fn import_legacy<R>(&mut self, read: R)
+	where
+		R: io::Read,
+	{
+		let mut rdr = csv::ReaderBuilder::new().has_headers(false).delimiter(b' ').from_reader(read);
+		for row in rdr.deserialize() {
+			let (addr, time, services, _failures): (String, u64, u64, u32) = match row {
+				Ok(row) => row,
+				Err(_) => continue,
+			};
+			let addr: NodeAddress = match addr.parse() {
+				Ok(addr) => addr,
+				Err(_) => continue,
+			};
+			let entry = AddressV2Entry {
+				timestamp: time as u32,
+				services: services.into(),
+				address: addr.address,
+				port: addr.port,
+			};
+			self.add(entry, &addr.address);
+		}
+	}
+
+	/// Inserts the node into the new table. If its position is taken by the terrible address, or by the address
+	/// which is also in other buckets, that address is removed. Forced insert always removes the occupant.
+	fn insert_new(&mut self, node: Node, force: bool) -> bool {
+		let now = self.time.now().as_secs();
+		let slot = self.new_slot(&node.addr, &node.source);
+		let id = match self.by_addr.get(&node.addr) {
+			Some(id) => *id,
+			None => self.create(node),
+		};
+
+		let insert = match self.new_table.get(&slot) {
+			Some(other) if *other == id => return false,
+			Some(other) => {
+				let other = &self.nodes[other];
+				force || other.is_terrible(now) || (other.ref_count > 1 && self.nodes[&id].ref_count == 0)
+			}
+			None => true,
+		};
+
+		if insert {
+			self.clear_new(slot);
+			self.new_table.insert(slot, id);
+			self.nodes.get_mut(&id).expect("node was just created; qed").ref_count += 1;
+		} else if self.nodes[&id].ref_count == 0 {
+			self.delete(id);
+		}
+		insert
+	}
+
+	/// Moves the node from the new table to the tried one. The node occupying its position is moved back to the new table.
+	fn make_tried(&mut self, id: NodeId) {
+		self.remove_from_new_buckets(id);
+
+		let addr = self.nodes[&id].addr;
+		let slot = self.tried_slot(&addr);
+		if let Some(old_id) = self.tried_table.remove(&slot) {
+			let old = self.nodes.get_mut(&old_id).expect("tried table references existing nodes; qed");
+			old.in_tried = false;
+			let (old_addr, old_source) = (old.addr, old.source);
+			let new_slot = self.new_slot(&old_addr, &old_source);
+			self.clear_new(new_slot);
+			self.new_table.insert(new_slot, old_id);
+			self.nodes
+				.get_mut(&old_id)
+				.expect("tried table references existing nodes; qed")
+				.ref_count = 1;
+		}
+
+		self.tried_table.insert(slot, id);
+		let node = self.nodes.get_mut(&id).expect("node exists; qed");
+		node.in_tried = true;
+		node.ref_count = 0;
+	}
+
+	/// Removes the node from the new slot. Node, which is not referenced anymore, is deleted.
+	fn clear_new(&mut self, slot: Slot) {
+		if let Some(id) = self.new_table.remove(&slot) {
+			let node = self.nodes.get_mut(&id).expect("new table references existing nodes; qed");
+			node.ref_count -= 1;
+			if node.ref_count == 0 {
+				self.collisions.remove(&id);
+				self.delete(id);
+			}
+		}
+	}
+
+	fn remove_from_new_buckets(&mut self, id: NodeId) {
+		let addr = self.nodes[&id].addr;
+		for bucket in 0..NEW_BUCKET_COUNT {
+			if self.nodes[&id].ref_count == 0 {
+				break;
+			}
+			let slot = (bucket, self.bucket_position(true, bucket, &addr));
+			if self.new_table.get(&slot) == Some(&id) {
+				self.new_table.remove(&slot);
+				self.nodes.get_mut(&id).expect("node exists; qed").ref_count -= 1;
+			}
+		}
+	}
+
+	fn create(&mut self, node: Node) -> NodeId {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.by_addr.insert(node.addr, id);
+		self.nodes.insert(id, node);
+		id
+	}
+
+	fn delete(&mut self, id: NodeId) {
+		if let Some(node) = self.nodes.remove(&id) {
+			self.by_addr.remove(&node.addr);
+		}
+	}
+
+	fn hash(&self, data: &[&[u8]]) -> u64 {
+		let mut input = self.key.to_vec();
+		for data in data {
+			input.extend_from_slice(data);
+		}
+		let hash = sha256(&input);
+		let mut bytes = [0u8; 8];
+		bytes.copy_from_slice(&hash[0..8]);
+		u64::from_le_bytes(bytes)
+	}
+
+	fn tried_slot(&self, addr: &NodeAddress) -> Slot {
+		let group = self.groups.group(&addr.address);
+		let group_bucket = self.hash(&[&address_key(addr)]) % TRIED_BUCKETS_PER_GROUP;
+		let bucket = self.hash(&[group.as_ref(), &group_bucket.to_le_bytes()]) % TRIED_BUCKET_COUNT;
+		(bucket, self.bucket_position(false, bucket, addr))
+	}
+
+	fn new_slot(&self, addr: &NodeAddress, source: &NetworkAddress) -> Slot {
+		let group = self.groups.group(&addr.address);
+		let source_group = self.groups.group(source);
+		let group_bucket = self.hash(&[group.as_ref(), source_group.as_ref()]) % NEW_BUCKETS_PER_SOURCE_GROUP;
+		let bucket = self.hash(&[source_group.as_ref(), &group_bucket.to_le_bytes()]) % NEW_BUCKET_COUNT;
+		(bucket, self.bucket_position(true, bucket, addr))
+	}
+
+	fn bucket_position(&self, new: bool, bucket: u64, addr: &NodeAddress) -> u64 {
+		let table = if new { b"N" } else { b"K" };
+		self.hash(&[table, &bucket.to_le_bytes(), &address_key(addr)]) % BUCKET_SIZE
+	}
+}
+
+fn address_key(addr: &NodeAddress) -> Vec<u8> {
+	let mut key = vec![addr.address.network_id()];
+	key.extend_from_slice(&addr.address.to_bytes());
+	key.extend_from_slice(&addr.port.to_be_bytes());
+	key
+}
+
+/// Returns true if the address, or its ipv4/ipv6 counterpart is in the `except` set.
+fn is_excepted(addr: &NodeAddress, except: &HashSet<NodeAddress>) -> bool {
+	except.contains(addr)
+		|| match addr.socket_addr() {
+			Some(net::SocketAddr::V4(v4)) => {
+				except.contains(&net::SocketAddr::V6(net::SocketAddrV6::new(v4.ip().to_ipv6_compatible(), v4.port(), 0, 0)).into())
+			}
+			Some(net::SocketAddr::V6(v6)) => v6
+				.ip()
+				.to_ipv4()
+				.is_some_and(|v4| except.contains(&net::SocketAddr::V4(net::SocketAddrV4::new(v4, v6.port())).into())),
+			None => false,
+		}
+}
+
+fn append_address(stream: &mut Stream, address: &NetworkAddress) {
+	stream.append(&address.network_id()).append(&Bytes::from(address.to_bytes()));
+}
+
+fn read_address(reader: &mut Reader<&[u8]>) -> Result<NetworkAddress, ser::Error> {
+	let network: u8 = reader.read()?;
+	let bytes: Bytes = reader.read()?;
+	NetworkAddress::from_network_bytes(network, &bytes)?.ok_or(ser::Error::MalformedData)
+}
+
+fn read_nodes(reader: &mut Reader<&[u8]>, preferable_services: Services) -> Result<Vec<Node>, ser::Error> {
+	let count: usize = reader.read::<ser::CompactInteger>()?.into();
+	let mut nodes = Vec::new();
+	for _ in 0..count {
+		let address = read_address(reader)?;
+		let port = reader.read()?;
+		let services = reader.read::<u64>()?.into();
+		let time = reader.read()?;
+		let source = read_address(reader)?;
+		nodes.push(Node {
+			addr: NodeAddress::new(address, port),
+			time,
+			services,
+			is_preferable: Services::includes(&services, &preferable_services),
+			source,
+			last_try: reader.read()?,
+			last_success: reader.read()?,
+			attempts: reader.read()?,
+			ref_count: 0,
+			in_tried: reader.read()?,
+		});
+	}
+	Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{AddressManager, NodeTableError, BUCKET_SIZE};
+	use crate::util::NetGroups;
+	use crate::InternetProtocol;
+	use message::common::{NetworkAddress, NodeAddress, Services};
+	use message::types::AddressV2Entry;
+	use primitives::time::Time;
+	use std::cell::Cell;
+	use std::collections::HashSet;
+	use std::time::Duration;
+
+	const NOW: u64 = 1_600_000_000;
+
+	#[derive(Default)]
+	struct TestTime(Cell<u64>);
+
+	impl Time for TestTime {
+		fn now(&self) -> Duration {
+			Duration::from_secs(NOW + self.0.get())
+		}
+	}
+
+	fn manager() -> AddressManager<TestTime> {
+		let mut manager = AddressManager::new(Services::default(), NetGroups::default());
+		// fixed key, so that bucket positions do not change between runs
+		manager.key = [7; 32];
+		manager
+	}
+
+	fn advance(manager: &AddressManager<TestTime>, secs: u64) {
+		manager.time.0.set(manager.time.0.get() + secs);
+	}
+
+	fn entry(addr: &str, timestamp: u64) -> AddressV2Entry {
+		let addr: NodeAddress = addr.parse().unwrap();
+		AddressV2Entry {
+			timestamp: timestamp as u32,
+			services: Services::default(),
+			address: addr.address,
+			port: addr.port,
+		}
+	}
+
+	fn source(ip: &str) -> NetworkAddress {
+		ip.parse::<std::net::IpAddr>().unwrap().into()
+	}
+
+	fn select(manager: &AddressManager<TestTime>, protocol: InternetProtocol) -> HashSet<NodeAddress> {
+		manager
+			.nodes_with_services(&Services::default(), protocol, false, &HashSet::new(), |_| true, 100)
+			.into_iter()
+			.map(|node| node.address())
+			.collect()
+	}
+
+	/// Returns address from the same /16 group, which takes the same position in the table.
+	fn colliding<F>(addr: &str, slot: F) -> String
+	where
+		F: Fn(&NodeAddress) -> (u64, u64),
+	{
+		let expected = slot(&addr.parse().unwrap());
+		(0..=u16::MAX)
+			.map(|i| format!("1.2.{}.{}:8333", i >> 8, i & 0xff))
+			.find(|other| other != addr && slot(&other.parse().unwrap()) == expected)
+			.unwrap()
+	}
+
+	#[test]
+	fn test_add_and_select() {
+		let mut manager = manager();
+		let addresses = ["1.2.0.1:8333", "1.3.0.1:8333", "[2001:db8::1]:8333"];
+		for addr in &addresses {
+			assert!(manager.add(entry(addr, NOW), &source("5.5.5.5")));
+		}
+		// nothing new
+		assert!(!manager.add(entry("1.2.0.1:8333", NOW), &source("5.5.5.5")));
+
+		let expected: HashSet<NodeAddress> = addresses.iter().map(|addr| addr.parse().unwrap()).collect();
+		assert_eq!(select(&manager, InternetProtocol::default()), expected);
+		assert_eq!(select(&manager, InternetProtocol::IpV6).len(), 1);
+		assert_eq!(manager.nodes().len(), 3);
+
+		// relayed addresses are considered a bit older
+		let node = &manager.nodes[&manager.by_addr[&"1.2.0.1:8333".parse().unwrap()]];
+		assert_eq!(node.time, NOW - 2 * 60 * 60);
+	}
+
+	#[test]
+	fn test_diverse_nodes() {
+		let mut manager = manager();
+		for addr in &["1.2.0.1:8333", "1.2.0.2:8333", "1.3.0.1:8333"] {
+			manager.add(entry(addr, NOW), &source("5.5.5.5"));
+		}
+		let groups = NetGroups::default();
+		let mut used_groups = HashSet::new();
+		let nodes = manager.nodes_with_services(
+			&Services::default(),
+			InternetProtocol::default(),
+			false,
+			&HashSet::new(),
+			|address| used_groups.insert(groups.group(&address.address)),
+			3,
+		);
+		let addresses: HashSet<String> = nodes.into_iter().map(|node| node.address().to_string()).collect();
+		assert_eq!(addresses.len(), 2);
+		assert!(addresses.contains("1.3.0.1:8333"));
+	}
+
+	#[test]
+	fn test_single_source_fills_limited_buckets() {
+		let mut manager = manager();
+		// addresses from the single network group relayed by the single peer share one bucket
+		for i in 0..1000u32 {
+			manager.add(entry(&format!("1.2.{}.{}:8333", i >> 8, i & 0xff), NOW), &source("5.5.5.5"));
+		}
+		assert!(manager.nodes.len() as u64 <= BUCKET_SIZE);
+
+		// addresses from many network groups are spread over at most 64 buckets
+		for i in 0..5000u32 {
+			manager.add(entry(&format!("{}.{}.1.1:8333", 20 + i / 256, i % 256), NOW), &source("5.5.5.5"));
+		}
+		let buckets: HashSet<u64> = manager.new_table.keys().map(|slot| slot.0).collect();
+		assert!(buckets.len() <= 64);
+	}
+
+	#[test]
+	fn test_replace_terrible_address() {
+		let mut manager = manager();
+		let first = "1.2.0.1:8333";
+		let src = source("5.5.5.5");
+		let second = colliding(first, |addr| manager.new_slot(addr, &src));
+		let third = colliding(&second, |addr| manager.new_slot(addr, &src));
+
+		// address not seen for more than 30 days is terrible and replaced by the colliding one
+		manager.add(entry(first, NOW - 31 * 24 * 60 * 60), &src);
+		assert!(manager.add(entry(&second, NOW), &src));
+		assert!(!manager.exists(&first.parse().unwrap()));
+
+		// but the good one is kept
+		assert!(!manager.add(entry(&third, NOW), &src));
+		assert!(manager.exists(&second.parse().unwrap()));
+		assert!(!manager.exists(&third.parse().unwrap()));
+	}
+
+	#[test]
+	fn test_is_terrible() {
+		let mut manager = manager();
+		manager.add(entry("1.2.0.1:8333", NOW), &source("1.2.0.1"));
+		let addr: NodeAddress = "1.2.0.1:8333".parse().unwrap();
+		let now = NOW;
+
+		let mut node = manager.nodes[&manager.by_addr[&addr]].clone();
+		assert!(!node.is_terrible(now));
+		node.time = now + 11 * 60;
+		assert!(node.is_terrible(now));
+		node.time = now;
+		node.attempts = 3;
+		assert!(node.is_terrible(now));
+		// but not the one tried in the last minute
+		node.last_try = now - 30;
+		assert!(!node.is_terrible(now));
+		node.last_try = now - 61;
+		node.attempts = 0;
+		node.time = now - 31 * 24 * 60 * 60;
+		assert!(node.is_terrible(now));
+	}
+
+	#[test]
+	fn test_good_moves_to_tried() {
+		let mut manager = manager();
+		let addr: NodeAddress = "1.2.0.1:8333".parse().unwrap();
+		manager.add(entry("1.2.0.1:8333", NOW), &source("5.5.5.5"));
+		manager.good(&addr, Services::default().with_network(true));
+
+		let node = &manager.nodes[&manager.by_addr[&addr]];
+		assert!(node.in_tried);
+		assert_eq!(node.last_success, NOW);
+		assert!(node.services.includes(&Services::default().with_network(true)));
+		assert!(manager.new_table.is_empty());
+		assert_eq!(manager.tried_table.len(), 1);
+
+		// unknown address is added and then moved to tried
+		manager.good(&"1.3.0.1:8333".parse().unwrap(), Services::default());
+		assert_eq!(manager.tried_table.len(), 2);
+	}
+
+	#[test]
+	fn test_tried_collision() {
+		let mut manager = manager();
+		let old: NodeAddress = "1.2.0.1:8333".parse().unwrap();
+		let new: NodeAddress = colliding("1.2.0.1:8333", |addr| manager.tried_slot(addr)).parse().unwrap();
+		manager.good(&old, Services::default());
+		advance(&manager, 5 * 60 * 60);
+
+		// the new address waits until the old one is tested
+		manager.good(&new, Services::default());
+		assert_eq!(manager.select_tried_collision(), Some(old));
+		manager.resolve_collisions();
+		assert!(!manager.nodes[&manager.by_addr[&new]].in_tried);
+
+		// the old one is still reachable, so the new one gives up
+		manager.good(&old, Services::default());
+		manager.resolve_collisions();
+		assert_eq!(manager.select_tried_collision(), None);
+		assert!(manager.nodes[&manager.by_addr[&old]].in_tried);
+		assert!(!manager.nodes[&manager.by_addr[&new]].in_tried);
+
+		// the old one failed the test, so it's replaced
+		advance(&manager, 5 * 60 * 60);
+		manager.good(&new, Services::default());
+		manager.attempt(&old);
+		advance(&manager, 61);
+		manager.resolve_collisions();
+		assert_eq!(manager.select_tried_collision(), None);
+		assert!(!manager.nodes[&manager.by_addr[&old]].in_tried);
+		assert!(manager.nodes[&manager.by_addr[&new]].in_tried);
+	}
+
+	#[test]
+	fn test_recently_active_nodes() {
+		let mut manager = manager();
+		for i in 0..100u32 {
+			manager.add(entry(&format!("{}.{}.1.1:8333", 20 + i / 256, i % 256), NOW), &source("5.5.5.5"));
+		}
+		// only part of the addresses is relayed
+		let count = manager.nodes.len();
+		assert_eq!(manager.recently_active_nodes(InternetProtocol::default()).len(), count * 23 / 100);
+	}
+
+	#[test]
+	fn test_is_stale() {
+		let mut manager = manager();
+		assert!(manager.is_stale());
+		manager.add(entry("1.2.0.1:8333", NOW), &source("5.5.5.5"));
+		assert!(!manager.is_stale());
+		// not seen for more than a month
+		advance(&manager, 31 * 24 * 60 * 60);
+		assert!(manager.is_stale());
+	}
+
+	#[test]
+	fn test_network_addresses() {
+		let onion = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion:8333";
+		let mut manager = manager();
+		let entries = vec![
+			entry("1.2.0.1:8333", NOW),
+			entry(onion, NOW),
+			entry("aaaqeayeaudaocajbifqydiob4ibceqtcqkrmfyydenbwha5dypq.b32.i2p:0", NOW),
+			entry("[fc00::1]:8333", NOW),
+		];
+		manager.add_many(entries, &source("5.5.5.5"));
+		assert_eq!(manager.nodes().len(), 4);
+
+		// only nodes reachable over ip are returned for connecting
+		let nodes = select(&manager, InternetProtocol::default());
+		assert_eq!(nodes, vec!["1.2.0.1:8333".parse().unwrap()].into_iter().collect());
+		let nodes = select(&manager, InternetProtocol::Cjdns);
+		assert_eq!(nodes, vec!["[fc00::1]:8333".parse().unwrap()].into_iter().collect());
+		// and onion services are returned only if they are reachable through the proxy
+		let nodes = manager.nodes_with_services(&Services::default(), InternetProtocol::Tor, true, &HashSet::new(), |_| true, 10);
+		assert_eq!(nodes.len(), 1);
+		assert_eq!(nodes[0].address().to_string(), onion);
+	}
+
+	#[test]
+	fn test_add_many_fixes_time() {
+		let mut manager = manager();
+		let src = source("5.5.5.5");
+		manager.add_many(vec![entry("1.2.0.1:8333", NOW + 3600), entry("1.3.0.1:8333", 0)], &src);
+		// addresses from the future or with nonsense time are considered 5 days old
+		for node in manager.nodes() {
+			assert_eq!(node.time, NOW - 5 * 24 * 60 * 60 - 2 * 60 * 60);
+		}
+	}
+
+	#[test]
+	fn test_add_manual_and_remove() {
+		let mut manager = manager();
+		let addr: NodeAddress = "127.0.0.1:8001".parse().unwrap();
+		assert!(manager.add_manual(addr, Services::default().with_p2p_v2(true)).is_ok());
+		assert_eq!(manager.services(&addr), Some(Services::default().with_p2p_v2(true)));
+		assert!(matches!(
+			manager.add_manual(addr, Services::default()),
+			Err(NodeTableError::AddressAlreadyAdded)
+		));
+
+		assert!(manager.remove(&addr).is_ok());
+		assert!(matches!(manager.remove(&addr), Err(NodeTableError::NoAddressInTable)));
+		assert!(manager.nodes.is_empty());
+		assert!(manager.by_addr.is_empty());
+		assert!(manager.new_table.is_empty());
+		assert_eq!(manager.services(&addr), None);
+
+		manager.good(&addr, Services::default());
+		assert!(manager.remove(&addr).is_ok());
+		assert!(manager.tried_table.is_empty());
+	}
+
+	#[test]
+	fn test_save_and_load() {
+		let mut manager = manager();
+		let src = source("5.5.5.5");
+		manager.add(entry("1.2.0.1:8333", NOW), &src);
+		manager.add(entry("1.3.0.1:8333", NOW), &src);
+		manager.add(
+			entry("pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion:8333", NOW),
+			&src,
+		);
+		manager.good(&"1.3.0.1:8333".parse().unwrap(), Services::default());
+		manager.attempt(&"1.2.0.1:8333".parse().unwrap());
+
+		let saved = manager.save();
+		let loaded = AddressManager::<TestTime>::load(Services::default(), NetGroups::default(), &saved).unwrap();
+		assert_eq!(loaded.key, manager.key);
+		let mut nodes = manager.nodes();
+		let mut loaded_nodes = loaded.nodes();
+		nodes.sort_by_key(|node| node.address());
+		loaded_nodes.sort_by_key(|node| node.address());
+		assert_eq!(nodes, loaded_nodes);
+		assert_eq!(loaded.new_table.len(), 2);
+		assert_eq!(loaded.tried_table.len(), 1);
+
+		let mut corrupted = saved.to_vec();
+		corrupted[40] ^= 1;
+		assert!(AddressManager::<TestTime>::load(Services::default(), NetGroups::default(), &corrupted).is_err());
+		assert!(AddressManager::<TestTime>::load(Services::default(), NetGroups::default(), &[]).is_err());
+	}
+
+	#[test]
+	fn test_import_legacy() {
+		let mut manager = manager();
+		let csv = format!("1.2.0.1:8333 {} 1 0\ninvalid 0 0 0\n[2001:db8::1]:8333 {} 0 2\n", NOW, NOW);
+		manager.import_legacy(csv.as_bytes());
+		let addresses: HashSet<String> = manager.nodes().into_iter().map(|node| node.address().to_string()).collect();
+		assert_eq!(
+			addresses,
+			vec!["1.2.0.1:8333".to_owned(), "[2001:db8::1]:8333".to_owned()]
+				.into_iter()
+				.collect()
+		);
+	}
+}
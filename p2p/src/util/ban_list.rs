@@ -0,0 +1,346 @@
+use message::common::NetworkAddress;
+use primitives::time::{RealTime, Time};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use std::{fmt, fs, io, str};
+
+const BANS_FILE: &str = "banlist.csv";
+
+/// Default duration of the ban.
+pub const DEFAULT_BAN_TIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Range of ip addresses, defined by the network address and prefix length.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Subnet {
+	network: IpAddr,
+	prefix: u8,
+}
+
+/// Returns ipv4 address for the ipv4-mapped ipv6 address, so that both forms are banned together.
+fn canonical(ip: IpAddr) -> IpAddr {
+	match ip {
+		IpAddr::V6(ip) => ip.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(IpAddr::V6(ip)),
+		ip => ip,
+	}
+}
+
+fn mask(ip: IpAddr, prefix: u8) -> IpAddr {
+	match ip {
+		IpAddr::V4(ip) => {
+			let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+			IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+		}
+		IpAddr::V6(ip) => {
+			let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+			IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+		}
+	}
+}
+
+fn max_prefix(ip: &IpAddr) -> u8 {
+	match *ip {
+		IpAddr::V4(_) => 32,
+		IpAddr::V6(_) => 128,
+	}
+}
+
+impl Subnet {
+	/// Creates subnet, ignoring the host bits of the address. Returns None if the prefix is too long.
+	pub fn new(ip: IpAddr, prefix: u8) -> Option<Self> {
+		let ip = canonical(ip);
+		if prefix > max_prefix(&ip) {
+			return None;
+		}
+		Some(Subnet {
+			network: mask(ip, prefix),
+			prefix,
+		})
+	}
+
+	/// Returns true if the ip address belongs to this subnet.
+	pub fn contains(&self, ip: &IpAddr) -> bool {
+		let ip = canonical(*ip);
+		max_prefix(&ip) == max_prefix(&self.network) && mask(ip, self.prefix) == self.network
+	}
+}
+
+impl From<IpAddr> for Subnet {
+	fn from(ip: IpAddr) -> Self {
+		let ip = canonical(ip);
+		Subnet {
+			network: ip,
+			prefix: max_prefix(&ip),
+		}
+	}
+}
+
+impl str::FromStr for Subnet {
+	type Err = &'static str;
+
+	/// Parses single ip address, or subnet in the `address/prefix` format.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.splitn(2, '/');
+		let ip: IpAddr = parts.next().unwrap_or_default().parse().map_err(|_| "Invalid ip address")?;
+		match parts.next() {
+			Some(prefix) => {
+				let prefix: u8 = prefix.parse().map_err(|_| "Invalid prefix length")?;
+				// prefix of the ipv4-mapped address counts the mapping bits too
+				let prefix = match ip {
+					IpAddr::V6(v6) if v6.to_ipv4_mapped().is_some() => prefix.checked_sub(96).ok_or("Invalid prefix length")?,
+					_ => prefix,
+				};
+				Subnet::new(ip, prefix).ok_or("Invalid prefix length")
+			}
+			None => Ok(ip.into()),
+		}
+	}
+}
+
+impl fmt::Display for Subnet {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}/{}", self.network, self.prefix)
+	}
+}
+
+/// Banned subnet.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BanEntry {
+	pub subnet: Subnet,
+	/// Time, when the ban was created (seconds since epoch).
+	pub created: u64,
+	/// Time, when the ban expires (seconds since epoch).
+	pub until: u64,
+}
+
+/// Subnets, connections to which are refused until the ban expires.
+#[derive(Default, Debug)]
+pub struct BanList<T = RealTime>
+where
+	T: Time,
+{
+	/// Bans by subnet.
+	bans: BTreeMap<Subnet, BanEntry>,
+	/// Ban list file path.
+	path: String,
+	/// Time source.
+	time: T,
+}
+
+impl BanList {
+	/// Opens a file and loads ban list from it.
+	pub fn from_file(path: String) -> Result<Self, io::Error> {
+		let file_path = path + "/" + BANS_FILE;
+		let mut ban_list = fs::OpenOptions::new()
+			.create(true)
+			.truncate(false)
+			.read(true)
+			// without opening for write, mac os returns os error 22
+			.write(true)
+			.open(&file_path)
+			.and_then(Self::load)?;
+		ban_list.path = file_path;
+		Ok(ban_list)
+	}
+
+	/// Saves ban list to file
+	pub fn save_to_file(&self) -> Result<(), io::Error> {
+		fs::File::create(&self.path).and_then(|file| self.save(file))
+	}
+}
+
+impl<T> BanList<T>
+where
+	T: Time,
+{
+	/// Bans the subnet until given time. Returns false if it was already banned for at least that long.
+	pub fn ban_until(&mut self, subnet: Subnet, until: u64) -> bool {
+		let now = self.time.now().as_secs();
+		self.insert(subnet, now, until)
+	}
+
+	/// Bans the subnet for given time. Returns false if it was already banned for at least that long.
+	pub fn ban(&mut self, subnet: Subnet, duration: Duration) -> bool {
+		let now = self.time.now().as_secs();
+		self.insert(subnet, now, now.saturating_add(duration.as_secs()))
+	}
+
+	fn insert(&mut self, subnet: Subnet, created: u64, until: u64) -> bool {
+		match self.bans.get(&subnet) {
+			Some(entry) if entry.until >= until && entry.until > created => false,
+			_ => {
+				self.bans.insert(subnet, BanEntry { subnet, created, until });
+				true
+			}
+		}
+	}
+
+	/// Removes the ban. Returns false if the subnet was not banned.
+	pub fn unban(&mut self, subnet: &Subnet) -> bool {
+		self.bans.remove(subnet).is_some()
+	}
+
+	/// Removes all bans.
+	pub fn clear(&mut self) {
+		self.bans.clear();
+	}
+
+	/// Returns true if the address belongs to the subnet with active ban.
+	pub fn is_banned(&self, address: &NetworkAddress) -> bool {
+		let now = self.time.now().as_secs();
+		match address.ip() {
+			Some(ip) => self.bans.values().any(|entry| entry.until > now && entry.subnet.contains(&ip)),
+			None => false,
+		}
+	}
+
+	/// Removes expired bans.
+	pub fn sweep(&mut self) {
+		let now = self.time.now().as_secs();
+		self.bans.retain(|_, entry| entry.until > now);
+	}
+
+	/// Returns active bans, sorted by subnet.
+	pub fn entries(&self) -> Vec<BanEntry> {
+		let now = self.time.now().as_secs();
+		self.bans.values().filter(|entry| entry.until > now).cloned().collect()
+	}
+
+	/// Save ban list in csv format.
+	pub fn save<W>(&self, write: W) -> Result<(), io::Error>
+	where
+		W: io::Write,
+	{
+		let mut writer = csv::WriterBuilder::new().delimiter(b' ').from_writer(write);
+
+		let err = || io::Error::other("Write csv error");
+
+		for entry in self.entries() {
+			let record = (entry.subnet.to_string(), entry.created, entry.until);
+			writer.serialize(record).map_err(|_| err())?;
+		}
+
+		Ok(())
+	}
+
+	/// Loads ban list from a csv source. Expired bans are skipped.
+	pub fn load<R>(read: R) -> Result<Self, io::Error>
+	where
+		R: io::Read,
+		T: Default,
+	{
+		let mut rdr = csv::ReaderBuilder::new().has_headers(false).delimiter(b' ').from_reader(read);
+
+		let mut ban_list = BanList::default();
+
+		let err = || io::Error::other("Load csv error");
+
+		for row in rdr.deserialize() {
+			let (subnet, created, until): (String, u64, u64) = row.map_err(|_| err())?;
+			let subnet = subnet.parse().map_err(|_| err())?;
+			ban_list.bans.insert(subnet, BanEntry { subnet, created, until });
+		}
+
+		ban_list.sweep();
+		Ok(ban_list)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BanEntry, BanList, Subnet};
+	use message::common::NetworkAddress;
+	use std::net::IpAddr;
+	use std::time::Duration;
+	use test_data::time::IncrementalTime;
+
+	fn address(s: &str) -> NetworkAddress {
+		s.parse::<IpAddr>().unwrap().into()
+	}
+
+	#[test]
+	fn test_subnet_parse() {
+		assert_eq!("10.0.0.1".parse::<Subnet>().unwrap().to_string(), "10.0.0.1/32");
+		assert_eq!("10.0.3.1/16".parse::<Subnet>().unwrap().to_string(), "10.0.0.0/16");
+		assert_eq!("::ffff:10.0.3.1/112".parse::<Subnet>().unwrap().to_string(), "10.0.0.0/16");
+		assert_eq!("2001:db8::1/32".parse::<Subnet>().unwrap().to_string(), "2001:db8::/32");
+		assert_eq!("0.0.0.0/0".parse::<Subnet>().unwrap().to_string(), "0.0.0.0/0");
+		assert!("10.0.0.1/33".parse::<Subnet>().is_err());
+		assert!("10.0.0.1/a".parse::<Subnet>().is_err());
+		assert!("10.0.0/8".parse::<Subnet>().is_err());
+	}
+
+	#[test]
+	fn test_subnet_contains() {
+		let subnet: Subnet = "10.0.0.0/16".parse().unwrap();
+		assert!(subnet.contains(&"10.0.255.1".parse().unwrap()));
+		assert!(subnet.contains(&"::ffff:10.0.0.1".parse().unwrap()));
+		assert!(!subnet.contains(&"10.1.0.1".parse().unwrap()));
+		assert!(!subnet.contains(&"::a00:1".parse().unwrap()));
+		let all: Subnet = "::/0".parse().unwrap();
+		assert!(all.contains(&"2001:db8::1".parse().unwrap()));
+		assert!(!all.contains(&"10.0.0.1".parse().unwrap()));
+	}
+
+	#[test]
+	fn test_ban_list_ban_and_expire() {
+		let mut ban_list = BanList::<IncrementalTime>::default();
+		assert!(ban_list.ban("10.0.0.0/8".parse().unwrap(), Duration::from_secs(5)));
+		// shorter ban does not replace the longer one
+		assert!(!ban_list.ban("10.0.0.0/8".parse().unwrap(), Duration::from_secs(2)));
+		assert!(ban_list.is_banned(&address("10.1.2.3")));
+		assert!(!ban_list.is_banned(&address("11.1.2.3")));
+		assert!(ban_list.is_banned(&address("10.1.2.3")));
+		// the time is advanced by every call
+		assert!(!ban_list.is_banned(&address("10.1.2.3")));
+		assert!(ban_list.entries().is_empty());
+	}
+
+	#[test]
+	fn test_ban_list_unban_and_clear() {
+		let mut ban_list = BanList::<IncrementalTime>::default();
+		let first: Subnet = "10.0.0.1".parse().unwrap();
+		let second: Subnet = "2001:db8::/32".parse().unwrap();
+		ban_list.ban(first, Duration::from_secs(100));
+		ban_list.ban(second, Duration::from_secs(100));
+		assert!(ban_list.unban(&first));
+		assert!(!ban_list.unban(&first));
+		assert!(!ban_list.is_banned(&address("10.0.0.1")));
+		assert!(ban_list.is_banned(&address("2001:db8::1")));
+		ban_list.clear();
+		assert!(!ban_list.is_banned(&address("2001:db8::1")));
+	}
+
+	#[test]
+	fn test_ban_list_save_and_load() {
+		let mut ban_list = BanList::<IncrementalTime>::default();
+		ban_list.ban_until("10.0.0.0/8".parse().unwrap(), u64::MAX);
+		ban_list.ban_until("2001:db8::1".parse().unwrap(), u64::MAX);
+		// already expired
+		ban_list.ban_until("10.0.0.1".parse().unwrap(), 0);
+
+		let mut s = vec![];
+		ban_list.save(&mut s).unwrap();
+		assert_eq!(
+			String::from_utf8(s.clone()).unwrap(),
+			"10.0.0.0/8 0 18446744073709551615\n2001:db8::1/128 1 18446744073709551615\n".to_string()
+		);
+
+		let loaded = BanList::<IncrementalTime>::load(s.as_slice()).unwrap();
+		assert_eq!(
+			loaded.entries(),
+			vec![
+				BanEntry {
+					subnet: "10.0.0.0/8".parse().unwrap(),
+					created: 0,
+					until: u64::MAX,
+				},
+				BanEntry {
+					subnet: "2001:db8::1".parse().unwrap(),
+					created: 1,
+					until: u64::MAX,
+				},
+			]
+		);
+	}
+}
@@ -1,10 +1,14 @@
-use std::{net, str};
+use message::common::{NetworkAddress, NodeAddress};
+use std::str;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum InternetProtocol {
 	Any,
 	IpV4,
 	IpV6,
+	Tor,
+	I2P,
+	Cjdns,
 }
 
 impl Default for InternetProtocol {
@@ -20,30 +24,44 @@ impl str::FromStr for InternetProtocol {
 		match s {
 			"ipv4" => Ok(InternetProtocol::IpV4),
 			"ipv6" => Ok(InternetProtocol::IpV6),
+			"onion" => Ok(InternetProtocol::Tor),
+			"i2p" => Ok(InternetProtocol::I2P),
+			"cjdns" => Ok(InternetProtocol::Cjdns),
 			_ => Err("Invalid internet protocol"),
 		}
 	}
 }
 
 impl InternetProtocol {
-	pub fn is_allowed(&self, addr: &net::SocketAddr) -> bool {
+	pub fn is_allowed(&self, addr: &NodeAddress) -> bool {
 		match *self {
 			InternetProtocol::Any => true,
-			InternetProtocol::IpV4 => match *addr {
-				net::SocketAddr::V4(_) => true,
-				_ => false,
-			},
-			InternetProtocol::IpV6 => match *addr {
-				net::SocketAddr::V6(_) => true,
-				_ => false,
-			},
+			InternetProtocol::IpV4 => matches!(addr.address, NetworkAddress::IpV4(_)),
+			InternetProtocol::IpV6 => matches!(addr.address, NetworkAddress::IpV6(_)),
+			InternetProtocol::Tor => matches!(addr.address, NetworkAddress::TorV3(_)),
+			InternetProtocol::I2P => matches!(addr.address, NetworkAddress::I2P(_)),
+			InternetProtocol::Cjdns => matches!(addr.address, NetworkAddress::Cjdns(_)),
 		}
 	}
+
+	/// Returns true if we can open connection to the address.
+	/// Tor nodes are reachable only through the proxy, I2P nodes are not supported,
+	/// and CJDNS nodes are reachable only if the CJDNS network was explicitly selected.
+	pub fn is_reachable(&self, addr: &NodeAddress, onion: bool) -> bool {
+		self.is_allowed(addr)
+			&& match addr.address {
+				NetworkAddress::IpV4(_) | NetworkAddress::IpV6(_) => true,
+				NetworkAddress::Cjdns(_) => *self == InternetProtocol::Cjdns,
+				NetworkAddress::TorV3(_) => onion,
+				NetworkAddress::I2P(_) => false,
+			}
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::InternetProtocol;
+	use message::common::NodeAddress;
 
 	#[test]
 	fn test_default_internet_protocol() {
@@ -54,6 +72,30 @@ mod tests {
 	fn test_parsing_internet_protocol() {
 		assert_eq!(InternetProtocol::IpV4, "ipv4".parse().unwrap());
 		assert_eq!(InternetProtocol::IpV6, "ipv6".parse().unwrap());
+		assert_eq!(InternetProtocol::Tor, "onion".parse().unwrap());
+		assert_eq!(InternetProtocol::I2P, "i2p".parse().unwrap());
+		assert_eq!(InternetProtocol::Cjdns, "cjdns".parse().unwrap());
 		assert!("sa".parse::<InternetProtocol>().is_err());
 	}
+
+	#[test]
+	fn test_internet_protocol_filters() {
+		let ipv4: NodeAddress = "127.0.0.1:8333".parse().unwrap();
+		let cjdns: NodeAddress = "[fc00::1]:8333".parse().unwrap();
+		let onion: NodeAddress = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion:8333"
+			.parse()
+			.unwrap();
+
+		assert!(InternetProtocol::Any.is_allowed(&onion));
+		assert!(InternetProtocol::Tor.is_allowed(&onion));
+		assert!(!InternetProtocol::IpV4.is_allowed(&onion));
+		assert!(!InternetProtocol::IpV6.is_allowed(&cjdns));
+
+		assert!(InternetProtocol::Any.is_reachable(&ipv4, false));
+		assert!(!InternetProtocol::Any.is_reachable(&cjdns, false));
+		assert!(InternetProtocol::Cjdns.is_reachable(&cjdns, false));
+		assert!(!InternetProtocol::Tor.is_reachable(&onion, false));
+		assert!(InternetProtocol::Tor.is_reachable(&onion, true));
+		assert!(!InternetProtocol::IpV4.is_reachable(&onion, true));
+	}
 }
@@ -1,13 +1,17 @@
+mod address_manager;
+mod ban_list;
 mod internet_protocol;
 pub mod interval;
-mod node_table;
+mod netgroup;
 pub mod nonce;
 mod peer;
 mod response_queue;
 mod synchronizer;
 
+pub use self::address_manager::{AddressManager, Node, NodeTableError};
+pub use self::ban_list::{BanEntry, BanList, Subnet, DEFAULT_BAN_TIME};
 pub use self::internet_protocol::InternetProtocol;
-pub use self::node_table::{Node, NodeTable, NodeTableError};
+pub use self::netgroup::{Asmap, NetGroups};
 pub use self::peer::{Direction, PeerId, PeerInfo};
 pub use self::response_queue::{ResponseQueue, Responses};
 pub use self::synchronizer::{ConfigurableSynchronizer, Synchronizer};
@@ -0,0 +1,368 @@
+//! Network groups of the addresses, used to diversify connections,
+//! so that attacker controlling single network range can not occupy all of them.
+
+use message::common::NetworkAddress;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::{fs, io};
+
+// network classes, as numbered by bitcoin core
+const NET_UNROUTABLE: u8 = 0;
+const NET_IPV4: u8 = 1;
+const NET_IPV6: u8 = 2;
+const NET_ONION: u8 = 3;
+const NET_I2P: u8 = 4;
+const NET_CJDNS: u8 = 5;
+const NET_LOCAL: u8 = 255;
+
+/// Group of addresses, which are likely to be controlled by the same operator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NetGroup(Vec<u8>);
+
+impl AsRef<[u8]> for NetGroup {
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+/// Assigns addresses to the network groups, either by the prefix of the address
+/// or by the autonomous system it belongs to, if asmap is loaded.
+#[derive(Default, Clone)]
+pub struct NetGroups {
+	asmap: Option<Asmap>,
+	key: RandomState,
+}
+
+impl NetGroups {
+	pub fn new(asmap: Option<Asmap>) -> Self {
+		NetGroups {
+			asmap,
+			key: RandomState::new(),
+		}
+	}
+
+	/// Returns network group of the address.
+	pub fn group(&self, address: &NetworkAddress) -> NetGroup {
+		let (class, prefix_bits, bytes) = match *address {
+			NetworkAddress::IpV4(ip) => return self.ipv4_group(ip),
+			NetworkAddress::IpV6(ip) => match linked_ipv4(&ip) {
+				Some(ipv4) => return self.ipv4_group(ipv4),
+				None if ip.is_loopback() => return NetGroup(vec![NET_LOCAL]),
+				None if !is_routable_ipv6(&ip) => return NetGroup(vec![NET_UNROUTABLE]),
+				None => {
+					if let Some(asn) = self.asn(&ip) {
+						return asn_group(asn);
+					}
+					// addresses of the Hurricane Electric tunnel broker are grouped by /36
+					let bits = if ip.segments()[..2] == [0x2001, 0x0470] { 36 } else { 32 };
+					(NET_IPV6, bits, ip.octets().to_vec())
+				}
+			},
+			NetworkAddress::TorV3(key) => (NET_ONION, 4, key.to_vec()),
+			NetworkAddress::I2P(hash) => (NET_I2P, 4, hash.to_vec()),
+			NetworkAddress::Cjdns(ip) => (NET_CJDNS, 12, ip.octets().to_vec()),
+		};
+
+		let mut group = vec![class];
+		group.extend_from_slice(&bytes[..prefix_bits / 8]);
+		if prefix_bits % 8 != 0 {
+			// remaining bits are set to 1, so that /36 group differs from /32 one
+			group.push(bytes[prefix_bits / 8] | ((1 << (8 - prefix_bits % 8)) - 1));
+		}
+		NetGroup(group)
+	}
+
+	/// Returns random, but stable for the lifetime of the node, value of the group.
+	/// Used to order peers by their groups without letting the attacker choose a favourable position.
+	pub fn keyed(&self, group: &NetGroup) -> u64 {
+		self.key.hash_one(group)
+	}
+
+	fn ipv4_group(&self, ip: Ipv4Addr) -> NetGroup {
+		if ip.is_loopback() {
+			return NetGroup(vec![NET_LOCAL]);
+		}
+		if !is_routable_ipv4(&ip) {
+			return NetGroup(vec![NET_UNROUTABLE]);
+		}
+		if let Some(asn) = self.asn(&ip.to_ipv6_mapped()) {
+			return asn_group(asn);
+		}
+		let octets = ip.octets();
+		NetGroup(vec![NET_IPV4, octets[0], octets[1]])
+	}
+
+	fn asn(&self, ip: &Ipv6Addr) -> Option<u32> {
+		self.asmap.as_ref().map(|asmap| asmap.asn(ip)).filter(|asn| *asn != 0)
+	}
+}
+
+fn asn_group(asn: u32) -> NetGroup {
+	let mut group = vec![NET_IPV6];
+	group.extend_from_slice(&asn.to_le_bytes());
+	NetGroup(group)
+}
+
+/// Returns IPv4 address embedded into the IPv4-mapped, 6to4 or Teredo IPv6 address.
+fn linked_ipv4(ip: &Ipv6Addr) -> Option<Ipv4Addr> {
+	let octets = ip.octets();
+	match ip.segments() {
+		[0, 0, 0, 0, 0, 0xffff, _, _] => Some(Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15])),
+		[0x2002, ..] => Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5])),
+		[0x2001, 0, ..] => Some(Ipv4Addr::new(!octets[12], !octets[13], !octets[14], !octets[15])),
+		_ => None,
+	}
+}
+
+fn is_routable_ipv4(ip: &Ipv4Addr) -> bool {
+	let octets = ip.octets();
+	!(ip.is_unspecified()
+		|| ip.is_private()
+		|| ip.is_link_local()
+		|| ip.is_broadcast()
+		|| ip.is_documentation()
+		// shared address space, RFC6598
+		|| (octets[0] == 100 && octets[1] & 0xc0 == 64)
+		// benchmarking, RFC2544
+		|| (octets[0] == 198 && octets[1] & 0xfe == 18))
+}
+
+fn is_routable_ipv6(ip: &Ipv6Addr) -> bool {
+	let segments = ip.segments();
+	!(ip.is_unspecified()
+		// unique local, RFC4193
+		|| segments[0] & 0xfe00 == 0xfc00
+		// link local, RFC4862
+		|| segments[0] & 0xffc0 == 0xfe80
+		// documentation, RFC3849
+		|| segments[..2] == [0x2001, 0x0db8])
+}
+
+/// Mapping of the IP addresses to the autonomous system numbers, in the format used by bitcoin core.
+/// The file is a program, interpreted bit by bit against the bits of the address.
+/// https://github.com/sipa/asmap
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asmap {
+	bits: Vec<bool>,
+}
+
+const INVALID: u32 = 0xffff_ffff;
+const TYPE_BIT_SIZES: [u8; 3] = [0, 0, 1];
+const ASN_BIT_SIZES: [u8; 10] = [15, 16, 17, 18, 19, 20, 21, 22, 23, 24];
+const MATCH_BIT_SIZES: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+const JUMP_BIT_SIZES: [u8; 26] = [
+	5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30,
+];
+
+const RETURN: u32 = 0;
+const JUMP: u32 = 1;
+const MATCH: u32 = 2;
+const DEFAULT: u32 = 3;
+
+impl Asmap {
+	pub fn from_bytes(bytes: &[u8]) -> Self {
+		let bits = bytes
+			.iter()
+			.flat_map(|byte| (0..8).map(move |bit| (byte >> bit) & 1 == 1))
+			.collect();
+		Asmap { bits }
+	}
+
+	pub fn from_file(path: &str) -> Result<Self, io::Error> {
+		fs::read(path).map(|bytes| Asmap::from_bytes(&bytes))
+	}
+
+	/// Returns autonomous system number of the address, or 0 if it is unknown.
+	pub fn asn(&self, ip: &Ipv6Addr) -> u32 {
+		let ip: Vec<bool> = ip
+			.octets()
+			.iter()
+			.flat_map(|byte| (0..8).rev().map(move |bit| (byte >> bit) & 1 == 1))
+			.collect();
+		self.interpret(&ip)
+	}
+
+	fn interpret(&self, ip: &[bool]) -> u32 {
+		let mut pos = 0;
+		let mut ip = ip.iter();
+		let mut default_asn = 0;
+		while pos < self.bits.len() {
+			match self.decode(&mut pos, 0, &TYPE_BIT_SIZES) {
+				RETURN => {
+					let asn = self.decode(&mut pos, 1, &ASN_BIT_SIZES);
+					return if asn == INVALID { 0 } else { asn };
+				}
+				JUMP => {
+					let jump = self.decode(&mut pos, 17, &JUMP_BIT_SIZES) as usize;
+					if jump == INVALID as usize || jump >= self.bits.len() - pos {
+						return 0;
+					}
+					match ip.next() {
+						Some(true) => pos += jump,
+						Some(false) => (),
+						None => return 0,
+					}
+				}
+				MATCH => {
+					let pattern = self.decode(&mut pos, 2, &MATCH_BIT_SIZES);
+					if pattern == INVALID {
+						return 0;
+					}
+					// pattern bits follow the highest set bit
+					let len = 31 - pattern.leading_zeros();
+					for bit in (0..len).rev() {
+						match ip.next() {
+							Some(&value) if value == ((pattern >> bit) & 1 == 1) => (),
+							Some(_) => return default_asn,
+							None => return 0,
+						}
+					}
+				}
+				DEFAULT => {
+					default_asn = self.decode(&mut pos, 1, &ASN_BIT_SIZES);
+					if default_asn == INVALID {
+						return 0;
+					}
+				}
+				_ => return 0,
+			}
+		}
+		0
+	}
+
+	/// Decodes variable length integer. Every set bit selects next, larger class of values,
+	/// and the first unset bit is followed by the position of the value within the class.
+	fn decode(&self, pos: &mut usize, min: u32, bit_sizes: &[u8]) -> u32 {
+		let mut value = min;
+		for (i, &size) in bit_sizes.iter().enumerate() {
+			let is_last = i + 1 == bit_sizes.len();
+			let next_class = if is_last {
+				false
+			} else {
+				match self.bits.get(*pos) {
+					Some(&bit) => {
+						*pos += 1;
+						bit
+					}
+					None => return INVALID,
+				}
+			};
+
+			if next_class {
+				value += 1 << size;
+			} else {
+				for bit in (0..size).rev() {
+					match self.bits.get(*pos) {
+						Some(&set) => {
+							*pos += 1;
+							value += (set as u32) << bit;
+						}
+						None => return INVALID,
+					}
+				}
+				return value;
+			}
+		}
+		INVALID
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Asmap, NetGroup, NetGroups, ASN_BIT_SIZES, JUMP_BIT_SIZES, MATCH_BIT_SIZES, TYPE_BIT_SIZES};
+
+	fn group(groups: &NetGroups, address: &str) -> NetGroup {
+		groups.group(&address.parse().unwrap())
+	}
+
+	#[test]
+	fn test_net_groups() {
+		let groups = NetGroups::default();
+		assert_eq!(group(&groups, "1.2.3.4"), NetGroup(vec![1, 1, 2]));
+		assert_eq!(group(&groups, "1.2.3.4"), group(&groups, "1.2.200.1"));
+		assert_ne!(group(&groups, "1.2.3.4"), group(&groups, "1.3.3.4"));
+		// 6to4 and Teredo addresses are grouped by the embedded IPv4 address
+		assert_eq!(group(&groups, "2002:102:304::1"), NetGroup(vec![1, 1, 2]));
+		assert_eq!(group(&groups, "2001:0:9d38:953c:10ef:ee5b:fefd:fcfb"), NetGroup(vec![1, 1, 2]));
+		assert_eq!(group(&groups, "127.0.0.1"), NetGroup(vec![255]));
+		assert_eq!(group(&groups, "192.168.1.1"), NetGroup(vec![0]));
+		assert_eq!(group(&groups, "2a01:4f8:1:2::1"), NetGroup(vec![2, 0x2a, 0x01, 0x04, 0xf8]));
+		assert_eq!(group(&groups, "2001:470:abcd::1"), NetGroup(vec![2, 0x20, 0x01, 0x04, 0x70, 0xaf]));
+		assert_eq!(group(&groups, "fc01:2345::1"), NetGroup(vec![5, 0xfc, 0x0f]));
+		assert_eq!(
+			group(&groups, "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion"),
+			NetGroup(vec![3, 0x7f])
+		);
+	}
+
+	#[test]
+	fn test_keyed_net_groups() {
+		let groups = NetGroups::default();
+		let group = group(&groups, "1.2.3.4");
+		assert_eq!(groups.keyed(&group), groups.keyed(&group.clone()));
+	}
+
+	/// Encodes the value, so that it can be decoded with `Asmap::decode`
+	fn encode(bits: &mut Vec<bool>, value: u32, min: u32, bit_sizes: &[u8]) {
+		let mut value = value - min;
+		for (i, &size) in bit_sizes.iter().enumerate() {
+			let is_last = i + 1 == bit_sizes.len();
+			if value >= 1 << size {
+				assert!(!is_last);
+				bits.push(true);
+				value -= 1 << size;
+			} else {
+				if !is_last {
+					bits.push(false);
+				}
+				bits.extend((0..size).rev().map(|bit| (value >> bit) & 1 == 1));
+				return;
+			}
+		}
+	}
+
+	#[test]
+	fn test_asmap() {
+		// if the first bit is set, return 200 if the next two bits are 01 and 100 otherwise,
+		// if the first bit is not set, return 300
+		let mut bits = Vec::new();
+		encode(&mut bits, 3, 0, &TYPE_BIT_SIZES);
+		encode(&mut bits, 100, 1, &ASN_BIT_SIZES);
+
+		let mut not_set = Vec::new();
+		encode(&mut not_set, 0, 0, &TYPE_BIT_SIZES);
+		encode(&mut not_set, 300, 1, &ASN_BIT_SIZES);
+
+		encode(&mut bits, 1, 0, &TYPE_BIT_SIZES);
+		encode(&mut bits, not_set.len() as u32, 17, &JUMP_BIT_SIZES);
+		bits.extend(not_set);
+		encode(&mut bits, 2, 0, &TYPE_BIT_SIZES);
+		encode(&mut bits, 0b101, 2, &MATCH_BIT_SIZES);
+		encode(&mut bits, 0, 0, &TYPE_BIT_SIZES);
+		encode(&mut bits, 200, 1, &ASN_BIT_SIZES);
+
+		let asmap = Asmap { bits };
+		assert_eq!(asmap.interpret(&[false, true, true]), 300);
+		assert_eq!(asmap.interpret(&[true, false, true]), 200);
+		assert_eq!(asmap.interpret(&[true, true, true]), 100);
+		// program requires more bits
+		assert_eq!(asmap.interpret(&[true]), 0);
+	}
+
+	#[test]
+	fn test_asmap_net_groups() {
+		let asmap = Asmap::from_bytes(&[]);
+		assert_eq!(asmap.asn(&"::ffff:1.2.3.4".parse().unwrap()), 0);
+
+		// single RETURN instruction, which maps all addresses to AS 1
+		let mut bits = Vec::new();
+		encode(&mut bits, 0, 0, &TYPE_BIT_SIZES);
+		encode(&mut bits, 1, 1, &ASN_BIT_SIZES);
+		let groups = NetGroups::new(Some(Asmap { bits }));
+		assert_eq!(group(&groups, "1.2.3.4"), NetGroup(vec![2, 1, 0, 0, 0]));
+		assert_eq!(group(&groups, "2a01:4f8:1:2::1"), NetGroup(vec![2, 1, 0, 0, 0]));
+		// unroutable addresses are not mapped
+		assert_eq!(group(&groups, "10.0.0.1"), NetGroup(vec![0]));
+	}
+}
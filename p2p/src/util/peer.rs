@@ -1,6 +1,8 @@
+use crate::net::ReconciliationStats;
+use message::common::NodeAddress;
 use message::types;
 use network::Magic;
-use std::net::SocketAddr;
+use std::collections::BTreeMap;
 
 pub type PeerId = usize;
 
@@ -13,10 +15,30 @@ pub enum Direction {
 #[derive(Debug, PartialEq, Clone)]
 pub struct PeerInfo {
 	pub id: PeerId,
-	pub address: SocketAddr,
+	pub address: NodeAddress,
 	pub user_agent: String,
 	pub direction: Direction,
 	pub version: u32,
 	pub version_message: types::Version,
 	pub magic: Magic,
+	/// Peer signalled support of the `addrv2` message
+	pub addrv2: bool,
+	/// Peer signalled support of the wtxid-based transaction relay during the handshake
+	pub wtxid_relay: bool,
+	/// Salt, which we have sent in the `sendtxrcncl` message during the handshake
+	pub local_txrcncl_salt: Option<u64>,
+	/// Salt of the peer, received in the `sendtxrcncl` message during the handshake
+	pub txrcncl_salt: Option<u64>,
+	/// Transactions reconciliation statistics, if transactions are reconciled with the peer
+	pub txreconciliation: Option<ReconciliationStats>,
+	/// Outbound connection, which relays neither transactions nor addresses
+	pub block_relay_only: bool,
+	/// Total bytes sent to the peer
+	pub bytes_sent: u64,
+	/// Total bytes received from the peer
+	pub bytes_recv: u64,
+	/// Bytes sent to the peer, by message command
+	pub bytes_sent_per_msg: BTreeMap<String, u64>,
+	/// Bytes received from the peer, by message command
+	pub bytes_recv_per_msg: BTreeMap<String, u64>,
 }
@@ -1,10 +1,10 @@
 use bitcrypto::SHA256D;
 use jsonrpc_core_client::futures::Future;
 use jsonrpc_core_client::{RpcChannel, RpcResult, TypedClient};
-use keys::AddressHash;
+use keys::Address;
 use rpc_server::v1::types::{
-	AddNodeOperation, BlockchainInfo, ChainTxStats, GetBlockResponse, GetTxOutResponse, GetTxOutSetInfoResponse, MemoryInfo, NetworkInfo,
-	NodeInfo, Peer,
+	AddNodeOperation, BannedNode, BlockchainInfo, ChainTxStats, GetBlockResponse, GetTxOutResponse, GetTxOutSetInfoResponse, MemoryInfo,
+	NetworkInfo, NodeInfo, Peer, SetBanOperation,
 };
 use std::net::{IpAddr, SocketAddr};
 
@@ -78,11 +78,11 @@ impl RpcClient {
 	pub fn generate_to_address(
 		&self,
 		n_blocks: u32,
-		address: AddressHash,
+		address: Address,
 		max_tries: Option<usize>,
 	) -> impl Future<Output = RpcResult<Vec<SHA256D>>> {
 		self.0
-			.call_method("generatetoaddress", "Vec<SHA256D>", (n_blocks, address, max_tries))
+			.call_method("generatetoaddress", "Vec<SHA256D>", (n_blocks, address.to_string(), max_tries))
 	}
 
 	/// Network
@@ -105,4 +105,26 @@ impl RpcClient {
 	pub fn peer_info(&self) -> impl Future<Output = RpcResult<Vec<Peer>>> {
 		self.0.call_method("getpeerinfo", "Vec<Peer>", ())
 	}
+
+	pub fn set_ban(
+		&self,
+		subnet: String,
+		operation: SetBanOperation,
+		bantime: Option<u64>,
+		absolute: Option<bool>,
+	) -> impl Future<Output = RpcResult<()>> {
+		self.0.call_method("setban", "()", (subnet, operation, bantime, absolute))
+	}
+
+	pub fn list_banned(&self) -> impl Future<Output = RpcResult<Vec<BannedNode>>> {
+		self.0.call_method("listbanned", "Vec<BannedNode>", ())
+	}
+
+	pub fn clear_banned(&self) -> impl Future<Output = RpcResult<()>> {
+		self.0.call_method("clearbanned", "()", ())
+	}
+
+	pub fn disconnect_node(&self, address: Option<String>, node_id: Option<usize>) -> impl Future<Output = RpcResult<()>> {
+		self.0.call_method("disconnectnode", "()", (address, node_id))
+	}
 }
@@ -9,6 +9,7 @@ extern crate jsonrpc_core;
 extern crate jsonrpc_derive;
 extern crate jsonrpc_http_server;
 extern crate keys;
+extern crate message;
 extern crate miner;
 extern crate network;
 extern crate p2p;
@@ -18,6 +19,7 @@ extern crate serialization as ser;
 extern crate storage;
 extern crate sync;
 extern crate verification;
+extern crate wallet;
 
 pub mod rpc_apis;
 pub mod rpc_server;
@@ -12,6 +12,8 @@ pub enum Api {
 	Miner,
 	BlockChain,
 	Network,
+	AddressIndex,
+	Wallet,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -22,9 +24,18 @@ pub enum ApiSet {
 impl Default for ApiSet {
 	fn default() -> Self {
 		ApiSet::List(
-			vec![Api::Control, Api::Generate, Api::Raw, Api::Miner, Api::BlockChain, Api::Network]
-				.into_iter()
-				.collect(),
+			vec![
+				Api::Control,
+				Api::Generate,
+				Api::Raw,
+				Api::Miner,
+				Api::BlockChain,
+				Api::Network,
+				Api::AddressIndex,
+				Api::Wallet,
+			]
+			.into_iter()
+			.collect(),
 		)
 	}
 }
@@ -40,6 +51,8 @@ impl FromStr for Api {
 			"miner" => Ok(Api::Miner),
 			"blockchain" => Ok(Api::BlockChain),
 			"network" => Ok(Api::Network),
+			"address" => Ok(Api::AddressIndex),
+			"wallet" => Ok(Api::Wallet),
 			api => Err(format!("Unknown api: {}", api)),
 		}
 	}
@@ -58,7 +71,9 @@ pub fn setup_rpc(mut handler: MetaIoHandler<()>, apis: ApiSet, deps: Dependencie
 		match api {
 			Api::Control => handler
 				.extend_with(ControlClient::new(ControlClientCore::new(deps.memory.clone(), deps.shutdown_signal.clone())).to_delegate()),
-			Api::Generate => handler.extend_with(GenerateClient::new(GenerateClientCore::new(deps.local_sync_node.clone())).to_delegate()),
+			Api::Generate => {
+				handler.extend_with(GenerateClient::new(GenerateClientCore::new(deps.network, deps.local_sync_node.clone())).to_delegate())
+			}
 			Api::Raw => handler.extend_with(
 				RawClient::new(RawClientCore::new(deps.network, deps.local_sync_node.clone(), deps.storage.clone())).to_delegate(),
 			),
@@ -72,6 +87,16 @@ pub fn setup_rpc(mut handler: MetaIoHandler<()>, apis: ApiSet, deps: Dependencie
 				.to_delegate(),
 			),
 			Api::Network => handler.extend_with(NetworkClient::new(NetworkClientCore::new(deps.p2p_context.clone())).to_delegate()),
+			Api::AddressIndex => {
+				handler.extend_with(AddressIndexClient::new(AddressIndexClientCore::new(deps.storage.clone())).to_delegate())
+			}
+			// wallet methods are only available when wallet is enabled
+			Api::Wallet => {
+				if let Some(ref wallet) = deps.wallet {
+					handler
+						.extend_with(WalletClient::new(WalletClientCore::new(wallet.clone(), deps.local_sync_node.clone())).to_delegate())
+				}
+			}
 		}
 	}
 
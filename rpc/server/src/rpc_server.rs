@@ -10,6 +10,7 @@ use std::sync::Arc;
 use storage;
 use sync;
 use tokio::sync::Notify;
+use wallet::Wallet;
 
 pub struct Dependencies {
 	pub network: Network,
@@ -18,6 +19,8 @@ pub struct Dependencies {
 	pub p2p_context: Arc<p2p::Context>,
 	pub memory: Arc<Memory>,
 	pub shutdown_signal: Arc<Notify>,
+	/// Wallet, if enabled
+	pub wallet: Option<Arc<Wallet>>,
 }
 
 #[derive(Default, Debug, PartialEq)]
@@ -4,12 +4,18 @@ mod codes {
 	// NOTE [ToDr] Codes from [-32099, -32000]
 	pub const UNKNOWN: i64 = -32000;
 	pub const EXECUTION_ERROR: i64 = -32015;
+	pub const INSUFFICIENT_FUNDS: i64 = -32093;
+	pub const BLOCK_FILTER_INDEX_DISABLED: i64 = -32094;
+	pub const ADDRESS_INDEX_DISABLED: i64 = -32095;
 	pub const TRANSACTION_NOT_FOUND: i64 = -32096;
 	pub const TRANSACTION_OUTPUT_NOT_FOUND: i64 = -32097;
 	pub const TRANSACTION_OF_SIDE_BRANCH: i64 = -32098;
 	pub const BLOCK_NOT_FOUND: i64 = -32099;
 	pub const NODE_ALREADY_ADDED: i64 = -32150;
 	pub const NODE_NOT_ADDED: i64 = -32151;
+	pub const NODE_ALREADY_BANNED: i64 = -32152;
+	pub const NODE_NOT_BANNED: i64 = -32153;
+	pub const NODE_NOT_CONNECTED: i64 = -32154;
 }
 
 use jsonrpc_core::{Error, ErrorCode, Value};
@@ -86,6 +92,30 @@ pub fn transaction_of_side_branch<T: fmt::Debug>(data: T) -> Error {
 	}
 }
 
+pub fn address_index_disabled() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::ADDRESS_INDEX_DISABLED),
+		message: "Address index is disabled. Restart node with --address-index".into(),
+		data: None,
+	}
+}
+
+pub fn block_filter_index_disabled() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::BLOCK_FILTER_INDEX_DISABLED),
+		message: "Block filter index is disabled. Restart node with --block-filters".into(),
+		data: None,
+	}
+}
+
+pub fn insufficient_funds() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::INSUFFICIENT_FUNDS),
+		message: "Insufficient funds".into(),
+		data: None,
+	}
+}
+
 pub fn node_already_added() -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::NODE_ALREADY_ADDED),
@@ -102,6 +132,30 @@ pub fn node_not_added() -> Error {
 	}
 }
 
+pub fn node_already_banned() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::NODE_ALREADY_BANNED),
+		message: "Subnet already banned".into(),
+		data: None,
+	}
+}
+
+pub fn node_not_banned() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::NODE_NOT_BANNED),
+		message: "Subnet not banned".into(),
+		data: None,
+	}
+}
+
+pub fn node_not_connected() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::NODE_NOT_CONNECTED),
+		message: "Node not found in connected nodes".into(),
+		data: None,
+	}
+}
+
 pub fn unknown() -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::UNKNOWN),
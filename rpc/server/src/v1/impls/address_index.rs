@@ -0,0 +1,252 @@
+use crate::v1::helpers::errors::{address_index_disabled, execution, invalid_params};
+use crate::v1::traits::AddressIndex;
+use crate::v1::types::{AddressBalance, AddressUtxo, AddressesRequest};
+use bitcrypto::{sha256, SHA256D};
+use chain::OutPoint;
+use global_script::Builder as ScriptBuilder;
+use jsonrpc_core::Error;
+use keys::Address;
+use primitives::bytes::Bytes;
+use std::collections::HashSet;
+use storage::{self, ScriptHashEntry};
+
+pub struct AddressIndexClient<T: AddressIndexClientCoreApi> {
+	core: T,
+}
+
+pub trait AddressIndexClientCoreApi: Send + Sync + 'static {
+	fn script_history(&self, script_pubkey: &[u8]) -> Result<Vec<ScriptHashEntry>, storage::Error>;
+}
+
+pub struct AddressIndexClientCore {
+	storage: storage::SharedStore,
+}
+
+impl AddressIndexClientCore {
+	pub fn new(storage: storage::SharedStore) -> Self {
+		AddressIndexClientCore { storage }
+	}
+}
+
+impl AddressIndexClientCoreApi for AddressIndexClientCore {
+	fn script_history(&self, script_pubkey: &[u8]) -> Result<Vec<ScriptHashEntry>, storage::Error> {
+		self.storage.script_hash_history(&sha256(script_pubkey))
+	}
+}
+
+fn script_pubkey(address: &Address) -> Result<Bytes, Error> {
+	ScriptBuilder::build_address(address)
+		.map(|script| script.to_bytes())
+		.map_err(|err| invalid_params("addresses", err))
+}
+
+impl<T> AddressIndexClient<T>
+where
+	T: AddressIndexClientCoreApi,
+{
+	pub fn new(core: T) -> Self {
+		AddressIndexClient { core }
+	}
+
+	fn address_history(&self, address: &Address) -> Result<Vec<ScriptHashEntry>, Error> {
+		self.core.script_history(&script_pubkey(address)?).map_err(|err| match err {
+			storage::Error::AddressIndexDisabled => address_index_disabled(),
+			err => execution(err),
+		})
+	}
+}
+
+impl<T> AddressIndex for AddressIndexClient<T>
+where
+	T: AddressIndexClientCoreApi,
+{
+	fn address_tx_ids(&self, request: AddressesRequest) -> Result<Vec<SHA256D>, Error> {
+		let mut entries = Vec::new();
+		for address in &request.addresses {
+			entries.extend(self.address_history(address)?);
+		}
+		entries.sort_by_key(|entry| entry.height);
+
+		let mut known_tx_ids = HashSet::new();
+		Ok(entries
+			.into_iter()
+			.map(|entry| entry.tx_hash)
+			.filter(|tx_hash| known_tx_ids.insert(*tx_hash))
+			.collect())
+	}
+
+	fn address_utxos(&self, request: AddressesRequest) -> Result<Vec<AddressUtxo>, Error> {
+		let mut utxos = Vec::new();
+		for address in request.addresses {
+			let history = self.address_history(&address)?;
+			let spent: HashSet<&OutPoint> = history
+				.iter()
+				.filter(|entry| entry.is_spending)
+				.map(|entry| &entry.outpoint)
+				.collect();
+			let script_pubkey = script_pubkey(&address)?;
+			utxos.extend(
+				history
+					.iter()
+					.filter(|entry| !entry.is_spending && !spent.contains(&entry.outpoint))
+					.map(|entry| AddressUtxo {
+						address: address.clone(),
+						txid: entry.outpoint.hash,
+						output_index: entry.outpoint.index,
+						script: script_pubkey.clone().into(),
+						satoshis: entry.value,
+						height: entry.height,
+					}),
+			);
+		}
+		utxos.sort_by_key(|utxo| utxo.height);
+		Ok(utxos)
+	}
+
+	fn address_balance(&self, request: AddressesRequest) -> Result<AddressBalance, Error> {
+		let (mut received, mut spent) = (0u64, 0u64);
+		for address in &request.addresses {
+			for entry in self.address_history(address)? {
+				if entry.is_spending {
+					spent = spent.saturating_add(entry.value);
+				} else {
+					received = received.saturating_add(entry.value);
+				}
+			}
+		}
+		// outputs, funded before the index has been enabled, may be spent after
+		Ok(AddressBalance {
+			balance: received.saturating_sub(spent),
+			received,
+		})
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	extern crate test_data;
+
+	use super::*;
+	use chain::IndexedBlock;
+	use db::BlockChainDatabase;
+	use jsonrpc_core::IoHandler;
+	use std::sync::Arc;
+	use storage::AddressIndexProvider;
+	use test_data::script_hash_entry;
+
+	const ADDRESS: &'static str = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+
+	#[derive(Default)]
+	struct SuccessAddressIndexClientCore;
+	#[derive(Default)]
+	struct ErrorAddressIndexClientCore;
+	#[derive(Default)]
+	struct PartialAddressIndexClientCore;
+
+	impl AddressIndexClientCoreApi for SuccessAddressIndexClientCore {
+		fn script_history(&self, _script_pubkey: &[u8]) -> Result<Vec<ScriptHashEntry>, storage::Error> {
+			Ok(vec![
+				script_hash_entry(1, 1, 1, 10, false),
+				script_hash_entry(2, 2, 2, 20, false),
+				script_hash_entry(3, 3, 1, 10, true),
+			])
+		}
+	}
+
+	impl AddressIndexClientCoreApi for PartialAddressIndexClientCore {
+		fn script_history(&self, _script_pubkey: &[u8]) -> Result<Vec<ScriptHashEntry>, storage::Error> {
+			// output has been funded before the index has been enabled
+			Ok(vec![script_hash_entry(3, 3, 1, 10, true)])
+		}
+	}
+
+	impl AddressIndexClientCoreApi for ErrorAddressIndexClientCore {
+		fn script_history(&self, _script_pubkey: &[u8]) -> Result<Vec<ScriptHashEntry>, storage::Error> {
+			Err(storage::Error::AddressIndexDisabled)
+		}
+	}
+
+	fn request(method: &str) -> String {
+		format!(
+			r#"{{"jsonrpc": "2.0", "method": "{}", "params": [{{"addresses": ["{}"]}}], "id": 1}}"#,
+			method, ADDRESS
+		)
+	}
+
+	fn handler<T: AddressIndexClientCoreApi>(core: T) -> IoHandler {
+		let mut handler = IoHandler::new();
+		handler.extend_with(AddressIndexClient::new(core).to_delegate());
+		handler
+	}
+
+	#[test]
+	fn address_tx_ids_success() {
+		let sample = handler(SuccessAddressIndexClientCore::default())
+			.handle_request_sync(&request("getaddresstxids"))
+			.unwrap();
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":["0101010101010101010101010101010101010101010101010101010101010101","0202020202020202020202020202020202020202020202020202020202020202","0303030303030303030303030303030303030303030303030303030303030303"],"id":1}"#
+		);
+	}
+
+	#[test]
+	fn address_utxos_success() {
+		let sample = handler(SuccessAddressIndexClientCore::default())
+			.handle_request_sync(&request("getaddressutxos"))
+			.unwrap();
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":[{"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","height":2,"outputIndex":0,"satoshis":20,"script":"76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac","txid":"0202020202020202020202020202020202020202020202020202020202020202"}],"id":1}"#
+		);
+	}
+
+	#[test]
+	fn address_balance_success() {
+		let sample = handler(SuccessAddressIndexClientCore::default())
+			.handle_request_sync(&request("getaddressbalance"))
+			.unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"balance":20,"received":30},"id":1}"#);
+	}
+
+	#[test]
+	fn address_balance_with_unindexed_funding() {
+		let sample = handler(PartialAddressIndexClientCore::default())
+			.handle_request_sync(&request("getaddressbalance"))
+			.unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"balance":0,"received":0},"id":1}"#);
+	}
+
+	#[test]
+	fn address_balance_index_disabled() {
+		let sample = handler(ErrorAddressIndexClientCore::default())
+			.handle_request_sync(&request("getaddressbalance"))
+			.unwrap();
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32095,"message":"Address index is disabled. Restart node with --address-index"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn address_history_from_storage() {
+		let address: Address = ADDRESS.into();
+		let script_pubkey = ScriptBuilder::build_address(&address).unwrap().to_bytes();
+		let genesis: IndexedBlock = test_data::genesis().into();
+		#[rustfmt::skip]
+		let block: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(50).script_pubkey_bytes(script_pubkey.clone()).build().build()
+			.merkled_header().parent(*genesis.hash()).build()
+			.build()
+			.into();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis]).with_address_index(true));
+		storage.insert(block.clone()).unwrap();
+		storage.canonize(block.hash()).unwrap();
+
+		let core = AddressIndexClientCore::new(storage.clone());
+		let history = core.script_history(&script_pubkey).unwrap();
+		assert_eq!(history, storage.script_hash_history(&sha256(&script_pubkey)).unwrap());
+		assert_eq!(history.len(), 1);
+		assert_eq!(history[0].tx_hash, block.transactions[0].hash);
+	}
+}
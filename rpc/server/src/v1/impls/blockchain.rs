@@ -1,10 +1,11 @@
 use crate::v1::helpers::errors::{
-	block_at_height_not_found, block_not_found, transaction_not_found, transaction_of_side_branch, transaction_output_not_found, unknown,
+	block_at_height_not_found, block_filter_index_disabled, block_not_found, execution, invalid_params, transaction_not_found,
+	transaction_of_side_branch, transaction_output_not_found, unknown,
 };
 use crate::v1::traits::BlockChain;
-use crate::v1::types::ChainTxStats;
 use crate::v1::types::U256;
 use crate::v1::types::{BlockchainInfo, GetTxOutSetInfoResponse};
+use crate::v1::types::{ChainTxStats, GetBlockFilterResponse};
 use crate::v1::types::{GetBlockResponse, RawBlock, VerboseBlock};
 use crate::v1::types::{GetTxOutResponse, TransactionOutputScript};
 use bitcrypto::SHA256D;
@@ -40,6 +41,7 @@ pub trait BlockChainClientCoreApi: Send + Sync + 'static {
 	fn verbose_block(&self, hash: SHA256D) -> Option<VerboseBlock>;
 	fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error>;
 	fn chain_tx_stats(&self, nblocks: Option<usize>, blockhash: Option<String>) -> Result<ChainTxStats, Error>;
+	fn block_filter(&self, hash: SHA256D) -> Result<GetBlockFilterResponse, Error>;
 }
 
 pub struct BlockChainClientCore {
@@ -190,11 +192,12 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 					.map(|a| Address {
 						network: match self.network {
 							Network::Mainnet => keys::Network::Mainnet,
-							// there's no correct choices for Regtests && Other networks
+							Network::Regtest => keys::Network::Regtest,
+							// there's no correct choices for Other networks
 							// => let's just make Testnet key
 							_ => keys::Network::Testnet,
 						},
-						hash: a.hash,
+						payload: a.payload,
 						kind: a.kind,
 					})
 					.collect(),
@@ -248,6 +251,25 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 			txrate: 0,
 		})
 	}
+
+	fn block_filter(&self, hash: SHA256D) -> Result<GetBlockFilterResponse, Error> {
+		let map_err = |err| match err {
+			storage::Error::BlockFilterIndexDisabled => block_filter_index_disabled(),
+			err => execution(err),
+		};
+		if self.storage.block_number(&hash).is_none() {
+			return Err(block_not_found(hash));
+		}
+		let filter = self.storage.block_filter(&hash).map_err(map_err)?;
+		let header = self.storage.block_filter_header(&hash).map_err(map_err)?;
+		match (filter, header) {
+			(Some(filter), Some(header)) => Ok(GetBlockFilterResponse {
+				filter: filter.content.into(),
+				header,
+			}),
+			_ => Err(block_not_found(hash)),
+		}
+	}
 }
 
 impl<T> BlockChainClient<T>
@@ -348,6 +370,13 @@ where
 	fn chain_tx_stats(&self, nblocks: Option<usize>, blockhash: Option<String>) -> Result<ChainTxStats, Error> {
 		self.core.chain_tx_stats(nblocks, blockhash)
 	}
+
+	fn block_filter(&self, hash: SHA256D, filter_type: Option<String>) -> Result<GetBlockFilterResponse, Error> {
+		match filter_type.as_deref() {
+			None | Some("basic") => self.core.block_filter(hash),
+			Some(filter_type) => Err(invalid_params("filtertype", filter_type)),
+		}
+	}
 }
 
 #[cfg(test)]
@@ -476,6 +505,13 @@ pub mod tests {
 		fn chain_tx_stats(&self, _nblocks: Option<usize>, _blockhash: Option<String>) -> Result<ChainTxStats, Error> {
 			Ok(ChainTxStats::default())
 		}
+
+		fn block_filter(&self, _hash: SHA256D) -> Result<GetBlockFilterResponse, Error> {
+			Ok(GetBlockFilterResponse {
+				filter: Bytes::new(vec![0x01, 0x7f, 0xa8, 0x80]),
+				header: SHA256D::default(),
+			})
+		}
 	}
 
 	impl BlockChainClientCoreApi for ErrorBlockChainClientCore {
@@ -535,6 +571,10 @@ pub mod tests {
 			Err(block_not_found(nblocks))
 		}
 
+		fn block_filter(&self, hash: SHA256D) -> Result<GetBlockFilterResponse, Error> {
+			Err(block_not_found(hash))
+		}
+
 		fn block_meta(&self, _hash: SHA256D) -> Option<BlockMeta> {
 			None
 		}
@@ -939,4 +979,87 @@ pub mod tests {
 			r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a"},"id":1}"#
 		);
 	}
+
+	#[test]
+	fn block_filter_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockfilter",
+				"params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", "basic"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"filter":"017fa880","header":"0000000000000000000000000000000000000000000000000000000000000000"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn block_filter_unknown_filter_type() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockfilter",
+				"params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", "extended"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: filtertype","data":"\"extended\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn block_filter_contents() {
+		let storage = BlockChainDatabase::transient(&test_data::genesis().into())
+			.unwrap()
+			.with_block_filters(true);
+		storage.build_missing_block_filters(1).unwrap();
+		storage.insert(test_data::block_h1().into()).unwrap();
+		storage.canonize(&test_data::block_h1().hash()).unwrap();
+		let core = BlockChainClientCore::new(Network::Mainnet, Arc::new(storage), None);
+
+		let filter = chain::BlockFilter::basic(&test_data::block_h1().into(), vec![]);
+		let genesis_filter = chain::BlockFilter::basic(&test_data::genesis().into(), vec![]);
+		assert_eq!(
+			core.block_filter(test_data::block_h1().hash()).unwrap(),
+			GetBlockFilterResponse {
+				filter: filter.content.clone().into(),
+				header: filter.header(&genesis_filter.header(&SHA256D::default())),
+			}
+		);
+		assert_eq!(
+			core.block_filter(SHA256D::default()).unwrap_err(),
+			block_not_found(SHA256D::default())
+		);
+	}
+
+	#[test]
+	fn block_filter_index_is_disabled() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let core = BlockChainClientCore::new(Network::Mainnet, storage, None);
+		assert_eq!(
+			core.block_filter(test_data::genesis().hash()).unwrap_err(),
+			block_filter_index_disabled()
+		);
+	}
 }
@@ -1,8 +1,9 @@
 use crate::v1::helpers::errors::invalid_params;
 use crate::v1::traits::Generate;
-use bitcrypto::{FromStr, SHA256D};
+use bitcrypto::SHA256D;
 use jsonrpc_core::Error;
-use keys::AddressHash;
+use keys::Address;
+use network::Network;
 use tokio::time::Duration;
 
 pub struct GenerateClient<T: GenerateClientCoreApi> {
@@ -10,30 +11,41 @@ pub struct GenerateClient<T: GenerateClientCoreApi> {
 }
 
 pub trait GenerateClientCoreApi: Send + Sync + 'static {
-	fn generate_to_address(&self, n_blocks: u32, address: Option<AddressHash>, max_tries: usize) -> Vec<SHA256D>;
+	fn generate_to_address(&self, n_blocks: u32, address: Option<Address>, max_tries: usize) -> Vec<SHA256D>;
+	/// Network of the accepted addresses
+	fn address_network(&self) -> keys::Network;
 }
 
 pub struct GenerateClientCore {
+	network: Network,
 	local_sync_node: sync::LocalNodeRef,
 }
 
 impl GenerateClientCore {
-	pub fn new(local_sync_node: sync::LocalNodeRef) -> Self {
-		GenerateClientCore { local_sync_node }
+	pub fn new(network: Network, local_sync_node: sync::LocalNodeRef) -> Self {
+		GenerateClientCore { network, local_sync_node }
 	}
 }
 
 impl GenerateClientCoreApi for GenerateClientCore {
-	fn generate_to_address(&self, n_blocks: u32, address: Option<AddressHash>, max_tries: usize) -> Vec<SHA256D> {
+	fn generate_to_address(&self, n_blocks: u32, address: Option<Address>, max_tries: usize) -> Vec<SHA256D> {
 		let mut block_hashes = vec![];
 		for _ in 0..n_blocks {
-			if let Some(hash) = self.local_sync_node.generate_block(address, max_tries) {
+			if let Some(hash) = self.local_sync_node.generate_block(address.clone(), max_tries) {
 				block_hashes.push(hash);
 			}
 			std::thread::sleep(Duration::from_secs(1));
 		}
 		block_hashes
 	}
+
+	fn address_network(&self) -> keys::Network {
+		match self.network {
+			Network::Mainnet => keys::Network::Mainnet,
+			Network::Regtest => keys::Network::Regtest,
+			_ => keys::Network::Testnet,
+		}
+	}
 }
 
 impl<T> GenerateClient<T>
@@ -54,9 +66,61 @@ where
 	}
 
 	fn generate_to_address(&self, n_blocks: u32, address: String, max_tries: Option<usize>) -> Result<Vec<SHA256D>, Error> {
-		let address_hash = AddressHash::from_str(&address).map_err(|e| invalid_params("address", e.to_string()))?;
-		Ok(self
-			.core
-			.generate_to_address(n_blocks, Some(address_hash), max_tries.unwrap_or(1000000)))
+		let address: Address = address.parse().map_err(|e: keys::Error| invalid_params("address", e.to_string()))?;
+		if address.network != self.core.address_network() {
+			return Err(invalid_params("address", "Address is for other network"));
+		}
+		Ok(self.core.generate_to_address(n_blocks, Some(address), max_tries.unwrap_or(1000000)))
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use jsonrpc_core::IoHandler;
+
+	#[derive(Default)]
+	struct SuccessGenerateClientCore;
+
+	impl GenerateClientCoreApi for SuccessGenerateClientCore {
+		fn generate_to_address(&self, n_blocks: u32, _address: Option<Address>, _max_tries: usize) -> Vec<SHA256D> {
+			(0..n_blocks).map(|_| SHA256D::default()).collect()
+		}
+
+		fn address_network(&self) -> keys::Network {
+			keys::Network::Regtest
+		}
+	}
+
+	fn handler() -> IoHandler {
+		let mut handler = IoHandler::new();
+		handler.extend_with(GenerateClient::new(SuccessGenerateClientCore::default()).to_delegate());
+		handler
+	}
+
+	#[test]
+	fn generate_to_address_success() {
+		let sample = handler()
+			.handle_request_sync(
+				r#"{"jsonrpc": "2.0", "method": "generatetoaddress", "params": [1, "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"], "id": 1}"#,
+			)
+			.unwrap();
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":["0000000000000000000000000000000000000000000000000000000000000000"],"id":1}"#
+		);
+	}
+
+	#[test]
+	fn generate_to_address_other_network() {
+		let sample = handler()
+			.handle_request_sync(
+				r#"{"jsonrpc": "2.0", "method": "generatetoaddress", "params": [1, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"], "id": 1}"#,
+			)
+			.unwrap();
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: address","data":"\"Address is for other network\""},"id":1}"#
+		);
 	}
 }
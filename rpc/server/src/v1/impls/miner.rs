@@ -1,8 +1,10 @@
+use crate::v1::helpers::errors::{execution, transaction_not_found};
 use crate::v1::traits::Miner;
-use crate::v1::types::{BlockTemplate, BlockTemplateRequest, MempoolInfo};
+use crate::v1::types::{BlockTemplate, BlockTemplateRequest, MempoolEntry, MempoolInfo, MempoolRelatives};
+use bitcrypto::SHA256D;
 use jsonrpc_core::Error;
 use miner;
-use miner::MemoryPoolInformation;
+use miner::{MemoryPoolEntryInformation, MemoryPoolInformation};
 use sync;
 
 pub struct MinerClient<T: MinerClientCoreApi> {
@@ -12,6 +14,10 @@ pub struct MinerClient<T: MinerClientCoreApi> {
 pub trait MinerClientCoreApi: Send + Sync + 'static {
 	fn get_block_template(&self) -> miner::BlockTemplate;
 	fn get_mempool_info(&self) -> miner::MemoryPoolInformation;
+	fn get_mempool_entry(&self, hash: &SHA256D) -> Option<miner::MemoryPoolEntryInformation>;
+	fn get_mempool_ancestors(&self, hash: &SHA256D) -> Option<Vec<miner::MemoryPoolEntryInformation>>;
+	fn get_mempool_descendants(&self, hash: &SHA256D) -> Option<Vec<miner::MemoryPoolEntryInformation>>;
+	fn save_mempool(&self) -> Result<(), String>;
 }
 
 pub struct MinerClientCore {
@@ -32,6 +38,30 @@ impl MinerClientCoreApi for MinerClientCore {
 	fn get_mempool_info(&self) -> MemoryPoolInformation {
 		self.local_sync_node.information().chain.transactions
 	}
+
+	fn get_mempool_entry(&self, hash: &SHA256D) -> Option<MemoryPoolEntryInformation> {
+		self.local_sync_node.memory_pool().read().entry_information(hash)
+	}
+
+	fn get_mempool_ancestors(&self, hash: &SHA256D) -> Option<Vec<MemoryPoolEntryInformation>> {
+		let memory_pool = self.local_sync_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		memory_pool
+			.ancestors(hash)
+			.map(|ancestors| ancestors.iter().filter_map(|hash| memory_pool.entry_information(hash)).collect())
+	}
+
+	fn get_mempool_descendants(&self, hash: &SHA256D) -> Option<Vec<MemoryPoolEntryInformation>> {
+		let memory_pool = self.local_sync_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		memory_pool
+			.descendants(hash)
+			.map(|descendants| descendants.iter().filter_map(|hash| memory_pool.entry_information(hash)).collect())
+	}
+
+	fn save_mempool(&self) -> Result<(), String> {
+		self.local_sync_node.save_memory_pool().map(|_| ())
+	}
 }
 
 impl<T> MinerClient<T>
@@ -54,6 +84,31 @@ where
 	fn mempool_info(&self) -> Result<MempoolInfo, Error> {
 		Ok(self.core.get_mempool_info().into())
 	}
+
+	fn mempool_entry(&self, hash: SHA256D) -> Result<MempoolEntry, Error> {
+		self.core
+			.get_mempool_entry(&hash)
+			.map(Into::into)
+			.ok_or_else(|| transaction_not_found(hash))
+	}
+
+	fn mempool_ancestors(&self, hash: SHA256D, verbose: Option<bool>) -> Result<MempoolRelatives, Error> {
+		self.core
+			.get_mempool_ancestors(&hash)
+			.map(|ancestors| MempoolRelatives::new(ancestors, verbose.unwrap_or_default()))
+			.ok_or_else(|| transaction_not_found(hash))
+	}
+
+	fn mempool_descendants(&self, hash: SHA256D, verbose: Option<bool>) -> Result<MempoolRelatives, Error> {
+		self.core
+			.get_mempool_descendants(&hash)
+			.map(|descendants| MempoolRelatives::new(descendants, verbose.unwrap_or_default()))
+			.ok_or_else(|| transaction_not_found(hash))
+	}
+
+	fn save_mempool(&self) -> Result<(), Error> {
+		self.core.save_mempool().map_err(execution)
+	}
 }
 
 #[cfg(test)]
@@ -90,7 +145,70 @@ pub mod tests {
 			MemoryPoolInformation {
 				transactions_count: 500,
 				transactions_size_in_bytes: 50000,
+				memory_usage: 120000,
+				max_memory_usage: 300000000,
+				minimum_fee_rate: 2000,
+			}
+		}
+
+		fn get_mempool_entry(&self, hash: &SHA256D) -> Option<MemoryPoolEntryInformation> {
+			if *hash != SHA256D::from_str("0100000000000000000000000000000000000000000000000000000000000000").unwrap() {
+				return None;
+			}
+
+			Some(MemoryPoolEntryInformation {
+				hash: *hash,
+				witness_hash: SHA256D::from_str("0200000000000000000000000000000000000000000000000000000000000000").unwrap(),
+				size: 200,
+				weight: 701,
+				time: 1_600_000_000,
+				fee: 10_000,
+				modified_fee: 15_000,
+				ancestors_count: 1,
+				ancestors_size: 350,
+				ancestors_modified_fee: 20_000,
+				package_count: 2,
+				package_size: 400,
+				package_modified_fee: 25_000,
+				depends: vec![SHA256D::from_str("0300000000000000000000000000000000000000000000000000000000000000").unwrap()],
+				bip125_replaceable: true,
+			})
+		}
+
+		fn get_mempool_ancestors(&self, hash: &SHA256D) -> Option<Vec<MemoryPoolEntryInformation>> {
+			if *hash != SHA256D::from_str("0100000000000000000000000000000000000000000000000000000000000000").unwrap() {
+				return None;
+			}
+
+			Some(vec![MemoryPoolEntryInformation {
+				hash: SHA256D::from_str("0300000000000000000000000000000000000000000000000000000000000000").unwrap(),
+				witness_hash: SHA256D::from_str("0300000000000000000000000000000000000000000000000000000000000000").unwrap(),
+				size: 150,
+				weight: 600,
+				time: 1_599_999_000,
+				fee: 5_000,
+				modified_fee: 5_000,
+				ancestors_count: 0,
+				ancestors_size: 150,
+				ancestors_modified_fee: 5_000,
+				package_count: 2,
+				package_size: 350,
+				package_modified_fee: 20_000,
+				depends: vec![],
+				bip125_replaceable: false,
+			}])
+		}
+
+		fn get_mempool_descendants(&self, hash: &SHA256D) -> Option<Vec<MemoryPoolEntryInformation>> {
+			if *hash != SHA256D::from_str("0100000000000000000000000000000000000000000000000000000000000000").unwrap() {
+				return None;
 			}
+
+			Some(vec![])
+		}
+
+		fn save_mempool(&self) -> Result<(), String> {
+			Ok(())
 		}
 	}
 
@@ -140,7 +258,162 @@ pub mod tests {
 
 		assert_eq!(
 			&sample,
-			r#"{"jsonrpc":"2.0","result":{"bytes":0,"maxmempool":0,"mempoolminfee":0,"minrelaytxfee":0,"size":500,"usage":50000},"id":1}"#
+			r#"{"jsonrpc":"2.0","result":{"bytes":50000,"maxmempool":300000000,"mempoolminfee":0.00002,"minrelaytxfee":0,"size":500,"usage":120000},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn getmempoolentry_accepted() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempoolentry",
+				"params": ["0100000000000000000000000000000000000000000000000000000000000000"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"ancestorcount":2,"ancestorsize":350,"bip125-replaceable":true,"depends":["0300000000000000000000000000000000000000000000000000000000000000"],"descendantcount":2,"descendantsize":400,"fees":{"ancestor":0.0002,"base":0.0001,"descendant":0.00025,"modified":0.00015},"time":1600000000,"vsize":176,"wtxid":"0200000000000000000000000000000000000000000000000000000000000000"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn getmempoolentry_rejected() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempoolentry",
+				"params": ["0400000000000000000000000000000000000000000000000000000000000000"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32096,"message":"Transaction with given hash is not found","data":"0400000000000000000000000000000000000000000000000000000000000000"},"id":1}"#
 		);
 	}
+
+	#[test]
+	fn getmempoolancestors_accepted() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempoolancestors",
+				"params": ["0100000000000000000000000000000000000000000000000000000000000000"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":["0300000000000000000000000000000000000000000000000000000000000000"],"id":1}"#
+		);
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempoolancestors",
+				"params": ["0100000000000000000000000000000000000000000000000000000000000000", true],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","result":{"0300000000000000000000000000000000000000000000000000000000000000":{"ancestorcount":1,"ancestorsize":150,"bip125-replaceable":false,"depends":[],"descendantcount":2,"descendantsize":350,"fees":{"ancestor":0.00005,"base":0.00005,"descendant":0.0002,"modified":0.00005},"time":1599999000,"vsize":150,"wtxid":"0300000000000000000000000000000000000000000000000000000000000000"}},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn getmempooldescendants_accepted() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempooldescendants",
+				"params": ["0100000000000000000000000000000000000000000000000000000000000000"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":[],"id":1}"#);
+	}
+
+	#[test]
+	fn getmempooldescendants_rejected() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempooldescendants",
+				"params": ["0400000000000000000000000000000000000000000000000000000000000000"],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			&sample,
+			r#"{"jsonrpc":"2.0","error":{"code":-32096,"message":"Transaction with given hash is not found","data":"0400000000000000000000000000000000000000000000000000000000000000"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn savemempool_accepted() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "savemempool",
+				"params": [],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
 }
@@ -1,13 +1,17 @@
+mod address_index;
 mod blockchain;
 mod control;
 mod generate;
 mod miner;
 mod network;
 mod raw;
+mod wallet;
 
+pub use self::address_index::{AddressIndexClient, AddressIndexClientCore};
 pub use self::blockchain::{BlockChainClient, BlockChainClientCore};
 pub use self::control::{ControlClient, ControlClientCore};
 pub use self::generate::{GenerateClient, GenerateClientCore};
 pub use self::miner::{MinerClient, MinerClientCore};
 pub use self::network::{NetworkClient, NetworkClientCore};
 pub use self::raw::{RawClient, RawClientCore};
+pub use self::wallet::{WalletClient, WalletClientCore};
@@ -1,21 +1,33 @@
 use crate::v1::helpers::errors;
 use crate::v1::traits::Network as NetworkRpc;
-use crate::v1::types::{AddNodeOperation, NodeInfo};
-use crate::v1::types::{NetworkInfo, Peer};
+use crate::v1::types::{AddNodeOperation, BannedNode, NodeInfo, SetBanOperation};
+use crate::v1::types::{NetTotals, NetworkInfo, NetworkInfoAddress, NetworkInfoNetwork, Peer};
 use jsonrpc_core::Error;
-use p2p;
-use std::net::{IpAddr, SocketAddr};
+use message::common::NodeAddress;
+use p2p::{self, BanEntry, InternetProtocol, PeerId, Subnet};
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub trait NetworkApi: Send + Sync + 'static {
-	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError>;
-	fn remove_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError>;
-	fn connect(&self, socket_addr: SocketAddr);
+	fn add_node(&self, node_addr: NodeAddress) -> Result<(), p2p::NodeTableError>;
+	fn remove_node(&self, node_addr: NodeAddress) -> Result<(), p2p::NodeTableError>;
+	fn connect(&self, node_addr: NodeAddress);
 	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError>;
 	fn nodes_info(&self) -> Vec<NodeInfo>;
 	fn peers_info(&self) -> Vec<Peer>;
 	fn connection_count(&self) -> usize;
 	fn network_info(&self) -> NetworkInfo;
+	fn net_totals(&self) -> p2p::NetTotals;
+	fn ban(&self, subnet: Subnet, until: u64) -> bool;
+	fn unban(&self, subnet: &Subnet) -> bool;
+	fn banned(&self) -> Vec<BanEntry>;
+	fn clear_banned(&self);
+	fn disconnect(&self, node_addr: Option<NodeAddress>, id: Option<PeerId>) -> bool;
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
 }
 
 impl<T> NetworkRpc for NetworkClient<T>
@@ -29,7 +41,7 @@ where
 	fn add_node(&self, node: String, operation: AddNodeOperation) -> Result<(), Error> {
 		let addr = node
 			.parse()
-			.map_err(|_| errors::invalid_params("node", "Invalid socket address format, should be ip:port (127.0.0.1:8008)"))?;
+			.map_err(|_| errors::invalid_params("node", "Invalid node address format, should be host:port (127.0.0.1:8008)"))?;
 		match operation {
 			AddNodeOperation::Add => self.api.add_node(addr).map_err(|_| errors::node_already_added()),
 			AddNodeOperation::Remove => self.api.remove_node(addr).map_err(|_| errors::node_not_added()),
@@ -60,6 +72,83 @@ where
 	fn peer_info(&self) -> Result<Vec<Peer>, Error> {
 		Ok(self.api.peers_info())
 	}
+
+	fn net_totals(&self) -> Result<NetTotals, Error> {
+		let timemillis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+		Ok(NetTotals::new(self.api.net_totals(), timemillis))
+	}
+
+	fn set_ban(&self, subnet: String, operation: SetBanOperation, bantime: Option<u64>, absolute: Option<bool>) -> Result<(), Error> {
+		let subnet: Subnet = subnet
+			.parse()
+			.map_err(|_| errors::invalid_params("subnet", "Invalid subnet format, should be ip address or subnet (192.168.0.0/24)"))?;
+		match operation {
+			SetBanOperation::Add => {
+				let until = match (bantime, absolute.unwrap_or(false)) {
+					(Some(until), true) => until,
+					// zero means the default ban time
+					(Some(bantime), false) if bantime != 0 => now().saturating_add(bantime),
+					_ => now() + p2p::DEFAULT_BAN_TIME.as_secs(),
+				};
+				if self.api.ban(subnet, until) {
+					Ok(())
+				} else {
+					Err(errors::node_already_banned())
+				}
+			}
+			SetBanOperation::Remove => {
+				if self.api.unban(&subnet) {
+					Ok(())
+				} else {
+					Err(errors::node_not_banned())
+				}
+			}
+		}
+	}
+
+	fn list_banned(&self) -> Result<Vec<BannedNode>, Error> {
+		let now = now();
+		Ok(self
+			.api
+			.banned()
+			.into_iter()
+			.map(|entry| BannedNode {
+				address: entry.subnet.to_string(),
+				ban_created: entry.created,
+				banned_until: entry.until,
+				ban_duration: entry.until.saturating_sub(entry.created),
+				time_remaining: entry.until.saturating_sub(now),
+			})
+			.collect())
+	}
+
+	fn clear_banned(&self) -> Result<(), Error> {
+		self.api.clear_banned();
+		Ok(())
+	}
+
+	fn disconnect_node(&self, address: Option<String>, node_id: Option<usize>) -> Result<(), Error> {
+		let address = match address.filter(|address| !address.is_empty()) {
+			Some(address) => Some(
+				address
+					.parse()
+					.map_err(|_| errors::invalid_params("address", "Invalid node address format, should be host:port (127.0.0.1:8008)"))?,
+			),
+			None => None,
+		};
+		if address.is_some() == node_id.is_some() {
+			return Err(errors::invalid_params(
+				"address",
+				"Only one of address and node id should be provided",
+			));
+		}
+
+		if self.api.disconnect(address, node_id) {
+			Ok(())
+		} else {
+			Err(errors::node_not_connected())
+		}
+	}
 }
 
 pub struct NetworkClient<T: NetworkApi> {
@@ -86,16 +175,16 @@ impl NetworkClientCore {
 }
 
 impl NetworkApi for NetworkClientCore {
-	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError> {
-		self.p2p.add_node(socket_addr)
+	fn add_node(&self, node_addr: NodeAddress) -> Result<(), p2p::NodeTableError> {
+		self.p2p.add_node(node_addr)
 	}
 
-	fn remove_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError> {
-		self.p2p.remove_node(socket_addr)
+	fn remove_node(&self, node_addr: NodeAddress) -> Result<(), p2p::NodeTableError> {
+		self.p2p.remove_node(node_addr)
 	}
 
-	fn connect(&self, socket_addr: SocketAddr) {
-		p2p::Context::connect_normal(self.p2p.clone(), socket_addr);
+	fn connect(&self, node_addr: NodeAddress) {
+		p2p::Context::connect_normal(self.p2p.clone(), node_addr);
 	}
 
 	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError> {
@@ -103,7 +192,7 @@ impl NetworkApi for NetworkClientCore {
 			.p2p
 			.nodes()
 			.iter()
-			.find(|n| n.address().ip() == node_addr)
+			.find(|n| n.address().address.ip() == Some(node_addr))
 			.cloned()
 			.ok_or(p2p::NodeTableError::NoAddressInTable)?;
 
@@ -148,14 +237,49 @@ impl NetworkApi for NetworkClientCore {
 	}
 
 	fn network_info(&self) -> NetworkInfo {
+		let protocol = self.p2p.internet_protocol();
+		let config = self.p2p.net_config();
+		let networks = vec![
+			("ipv4", InternetProtocol::IpV4, config.proxy.as_ref()),
+			("ipv6", InternetProtocol::IpV6, config.proxy.as_ref()),
+			(
+				"onion",
+				InternetProtocol::Tor,
+				config.onion_proxy.as_ref().or(config.proxy.as_ref()),
+			),
+		]
+		.into_iter()
+		.map(|(name, network, proxy)| {
+			let limited = protocol != InternetProtocol::Any && protocol != network;
+			NetworkInfoNetwork {
+				name: name.to_string(),
+				limited,
+				// onion services are reachable only through the proxy
+				reachable: !limited && (network != InternetProtocol::Tor || proxy.is_some()),
+				proxy: proxy.map(|proxy| proxy.address.to_string()).unwrap_or_default(),
+				proxy_randomize_credentials: proxy.is_some_and(|proxy| proxy.randomize_credentials),
+			}
+		})
+		.collect();
+		let localaddresses = self
+			.p2p
+			.local_onion_address()
+			.into_iter()
+			.map(|address| NetworkInfoAddress {
+				address: address.address.to_string(),
+				port: address.port as usize,
+				score: 0,
+			})
+			.collect();
+
 		NetworkInfo {
 			connections: self.p2p.connections().count(),
 			incrementalfee: 0,
-			localaddresses: Vec::new(),
+			localaddresses,
 			localrelay: false,
 			localservices: "".to_string(),
 			networkactive: true,
-			networks: Vec::new(),
+			networks,
 			protocolversion: self.p2p.get_version() as usize,
 			relayfee: 0,
 			version: 0,
@@ -164,4 +288,215 @@ impl NetworkApi for NetworkClientCore {
 			warnings: "".to_string(),
 		}
 	}
+
+	fn net_totals(&self) -> p2p::NetTotals {
+		self.p2p.net_totals()
+	}
+
+	fn ban(&self, subnet: Subnet, until: u64) -> bool {
+		self.p2p.ban(subnet, until)
+	}
+
+	fn unban(&self, subnet: &Subnet) -> bool {
+		self.p2p.unban(subnet)
+	}
+
+	fn banned(&self) -> Vec<BanEntry> {
+		self.p2p.banned()
+	}
+
+	fn clear_banned(&self) {
+		self.p2p.clear_banned()
+	}
+
+	fn disconnect(&self, node_addr: Option<NodeAddress>, id: Option<PeerId>) -> bool {
+		let peer = self
+			.p2p
+			.connections()
+			.info()
+			.into_iter()
+			.find(|info| Some(info.address) == node_addr || Some(info.id) == id);
+		match peer {
+			Some(info) => {
+				self.p2p.close_channel(info.id);
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use jsonrpc_core::IoHandler;
+	use std::sync::Mutex;
+
+	#[derive(Default)]
+	struct TestNetworkClientCore {
+		bans: Mutex<Vec<BanEntry>>,
+	}
+
+	impl NetworkApi for Arc<TestNetworkClientCore> {
+		fn add_node(&self, _node_addr: NodeAddress) -> Result<(), p2p::NodeTableError> {
+			Ok(())
+		}
+		fn remove_node(&self, _node_addr: NodeAddress) -> Result<(), p2p::NodeTableError> {
+			Ok(())
+		}
+		fn connect(&self, _node_addr: NodeAddress) {}
+		fn node_info(&self, _node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError> {
+			Err(p2p::NodeTableError::NoAddressInTable)
+		}
+		fn nodes_info(&self) -> Vec<NodeInfo> {
+			vec![]
+		}
+		fn peers_info(&self) -> Vec<Peer> {
+			vec![]
+		}
+		fn connection_count(&self) -> usize {
+			0
+		}
+		fn network_info(&self) -> NetworkInfo {
+			NetworkInfo::default()
+		}
+		fn net_totals(&self) -> p2p::NetTotals {
+			p2p::NetTotals {
+				total_recv: 1000,
+				total_sent: 2000,
+				upload_target: Some(p2p::UploadTarget {
+					target: 5000,
+					target_reached: false,
+					serve_historical_blocks: false,
+					bytes_left_in_cycle: 3000,
+					time_left_in_cycle: 600,
+				}),
+			}
+		}
+		fn ban(&self, subnet: Subnet, until: u64) -> bool {
+			let mut bans = self.bans.lock().unwrap();
+			if bans.iter().any(|entry| entry.subnet == subnet) {
+				return false;
+			}
+			bans.push(BanEntry {
+				subnet,
+				created: 1000,
+				until,
+			});
+			true
+		}
+		fn unban(&self, subnet: &Subnet) -> bool {
+			let mut bans = self.bans.lock().unwrap();
+			let len = bans.len();
+			bans.retain(|entry| entry.subnet != *subnet);
+			bans.len() != len
+		}
+		fn banned(&self) -> Vec<BanEntry> {
+			self.bans.lock().unwrap().clone()
+		}
+		fn clear_banned(&self) {
+			self.bans.lock().unwrap().clear()
+		}
+		fn disconnect(&self, node_addr: Option<NodeAddress>, id: Option<PeerId>) -> bool {
+			node_addr == Some("127.0.0.1:8333".parse().unwrap()) || id == Some(1)
+		}
+	}
+
+	fn handler(core: Arc<TestNetworkClientCore>) -> IoHandler {
+		let client = NetworkClient::new(core);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+		handler
+	}
+
+	fn request(handler: &IoHandler, method: &str, params: &str) -> String {
+		let request = format!(r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#, method, params);
+		handler.handle_request_sync(&request).unwrap()
+	}
+
+	#[test]
+	fn test_set_ban() {
+		let core = Arc::new(TestNetworkClientCore::default());
+		let handler = handler(core.clone());
+
+		assert_eq!(
+			request(&handler, "setban", r#"["10.0.3.1/16", "add", 2000, true]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			request(&handler, "setban", r#"["10.0.0.0/16", "add"]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32152,"message":"Subnet already banned"},"id":1}"#
+		);
+		assert_eq!(
+			request(&handler, "listbanned", "[]"),
+			r#"{"jsonrpc":"2.0","result":[{"address":"10.0.0.0/16","ban_created":1000,"ban_duration":1000,"banned_until":2000,"time_remaining":0}],"id":1}"#
+		);
+
+		assert_eq!(
+			request(&handler, "setban", r#"["10.0.0.1", "add", 100]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		let until = core.banned()[1].until;
+		assert!(until >= now() + 99 && until <= now() + 100);
+
+		assert_eq!(
+			request(&handler, "setban", r#"["10.0.0.0/16", "remove"]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			request(&handler, "setban", r#"["10.0.0.0/16", "remove"]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32153,"message":"Subnet not banned"},"id":1}"#
+		);
+		assert_eq!(request(&handler, "clearbanned", "[]"), r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+		assert!(core.banned().is_empty());
+
+		assert_eq!(
+			request(&handler, "setban", r#"["10.0.0.1/33", "add"]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: subnet","data":"\"Invalid subnet format, should be ip address or subnet (192.168.0.0/24)\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn test_disconnect_node() {
+		let handler = handler(Default::default());
+
+		assert_eq!(
+			request(&handler, "disconnectnode", r#"["127.0.0.1:8333"]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			request(&handler, "disconnectnode", r#"["", 1]"#),
+			r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+		);
+		assert_eq!(
+			request(&handler, "disconnectnode", "[null, 2]"),
+			r#"{"jsonrpc":"2.0","error":{"code":-32154,"message":"Node not found in connected nodes"},"id":1}"#
+		);
+		assert_eq!(
+			request(&handler, "disconnectnode", r#"["127.0.0.1:8333", 1]"#),
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: address","data":"\"Only one of address and node id should be provided\""},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn test_net_totals() {
+		let handler = handler(Arc::new(TestNetworkClientCore::default()));
+
+		let response: serde_json::Value = serde_json::from_str(&request(&handler, "getnettotals", "[]")).unwrap();
+		let result = &response["result"];
+		assert_eq!(result["totalbytesrecv"], 1000);
+		assert_eq!(result["totalbytessent"], 2000);
+		assert!(result["timemillis"].as_u64().unwrap() >= now() * 1000);
+		assert_eq!(
+			result["uploadtarget"],
+			serde_json::json!({
+				"timeframe": 86400,
+				"target": 5000,
+				"target_reached": false,
+				"serve_historical_blocks": false,
+				"bytes_left_in_cycle": 3000,
+				"time_left_in_cycle": 600,
+			})
+		);
+	}
 }
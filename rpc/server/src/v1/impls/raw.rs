@@ -1,26 +1,37 @@
 use crate::v1::helpers::errors::{execution, invalid_params, transaction_not_found, transaction_of_side_branch};
 use crate::v1::traits::Raw;
 use crate::v1::types::{
-	GetRawTransactionResponse, RawTransaction, SignedTransactionInput, SignedTransactionOutput, Transaction, TransactionInput,
-	TransactionInputScript, TransactionOutput, TransactionOutputScript, TransactionOutputs,
+	AnalyzePsbtResponse, AnalyzedPsbtInput, DecodePsbtResponse, DecodedPsbtInput, DecodedPsbtOutput, DescriptorRange, FinalizePsbtResponse,
+	GetDescriptorInfoResponse, GetRawTransactionResponse, PsbtKeyOrigin, PsbtRole, PsbtScript, PsbtUtxo, RawTransaction,
+	SignedTransactionInput, SignedTransactionOutput, Transaction, TransactionInput, TransactionInputScript, TransactionOutput,
+	TransactionOutputScript, TransactionOutputs,
 };
-use bitcrypto::SHA256D;
-use chain::{IndexedTransaction as GlobalIndexedTransaction, Transaction as GlobalTransaction};
-use global_script::Script;
+use bitcrypto::{ToHex, SHA256D};
+use chain::constants::SATOSHIS_IN_COIN;
+use chain::{
+	IndexedTransaction as GlobalIndexedTransaction, KeySource, OutPoint, Psbt, PsbtError, Transaction as GlobalTransaction,
+	TransactionOutput as GlobalTransactionOutput,
+};
+use global_script::{descriptor_checksum, finalize_psbt, finalize_psbt_input, Descriptor, Script};
 use jsonrpc_core::Error;
-use keys::Address;
+use keys::{Address, ChildNumber, DerivationPath, HARDENED_OFFSET};
 use network::{ConsensusParams, Network};
 use primitives::bytes::Bytes as GlobalBytes;
-use ser::{deserialize, serialize, Reader, Serializable, SERIALIZE_TRANSACTION_WITNESS};
-use storage;
+use ser::{deserialize, serialize, serialize_with_flags, Reader, Serializable, SERIALIZE_TRANSACTION_WITNESS};
+use std::collections::BTreeMap;
+use storage::{self, DuplexTransactionOutputProvider, TransactionOutputProvider};
 use sync;
 
+/// Maximal number of addresses, derived by the single `deriveaddresses` call
+const MAX_DERIVED_ADDRESSES: u32 = 1_000_000;
+
 pub struct RawClient<T: RawClientCoreApi> {
 	core: T,
 }
 
 pub trait RawClientCoreApi: Send + Sync + 'static {
 	fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<SHA256D, String>;
+	fn accept_package(&self, package: Vec<GlobalTransaction>) -> Result<Vec<SHA256D>, String>;
 	fn create_raw_transaction(
 		&self,
 		inputs: Vec<TransactionInput>,
@@ -29,6 +40,10 @@ pub trait RawClientCoreApi: Send + Sync + 'static {
 	) -> Result<GlobalTransaction, String>;
 	fn get_raw_transaction(&self, hash: SHA256D, verbose: bool) -> Result<GetRawTransactionResponse, Error>;
 	fn transaction_to_verbose_transaction(&self, transaction: GlobalIndexedTransaction) -> Transaction;
+	/// Unspent output from the UTXO set or from the memory pool
+	fn unspent_transaction_output(&self, outpoint: &OutPoint) -> Option<GlobalTransactionOutput>;
+	/// Network of derived addresses
+	fn address_network(&self) -> keys::Network;
 }
 
 pub struct RawClientCore {
@@ -76,32 +91,29 @@ impl RawClientCore {
 			.collect();
 
 		// prepare outputs
-		let outputs: Vec<_> = outputs
+		let outputs = outputs
 			.outputs
 			.into_iter()
 			.map(|output| match output {
 				TransactionOutput::Address(with_address) => {
 					let amount_in_satoshis = (with_address.amount * (chain::constants::SATOSHIS_IN_COIN as f64)) as u64;
-					let script = match with_address.address.kind {
-						keys::Type::P2PKH => ScriptBuilder::build_p2pkh(&with_address.address.hash),
-						keys::Type::P2SH => ScriptBuilder::build_p2sh(&with_address.address.hash),
-					};
+					let script = ScriptBuilder::build_address(&with_address.address).map_err(|err| err.to_string())?;
 
-					chain::TransactionOutput {
+					Ok(chain::TransactionOutput {
 						value: amount_in_satoshis,
 						script_pubkey: script.to_bytes(),
-					}
+					})
 				}
 				TransactionOutput::ScriptData(with_script_data) => {
 					let script = ScriptBuilder::default().return_bytes(&*with_script_data.script_data).into_script();
 
-					chain::TransactionOutput {
+					Ok(chain::TransactionOutput {
 						value: 0,
 						script_pubkey: script.to_bytes(),
-					}
+					})
 				}
 			})
-			.collect();
+			.collect::<Result<Vec<_>, String>>()?;
 
 		// now construct && serialize transaction
 		let transaction = GlobalTransaction {
@@ -121,6 +133,11 @@ impl RawClientCoreApi for RawClientCore {
 			.accept_transaction(GlobalIndexedTransaction::from_raw(transaction))
 	}
 
+	fn accept_package(&self, package: Vec<GlobalTransaction>) -> Result<Vec<SHA256D>, String> {
+		self.local_sync_node
+			.accept_package(package.into_iter().map(GlobalIndexedTransaction::from_raw).collect())
+	}
+
 	fn create_raw_transaction(
 		&self,
 		inputs: Vec<TransactionInput>,
@@ -223,10 +240,11 @@ impl RawClientCoreApi for RawClientCore {
 						addresses: script_addresses
 							.into_iter()
 							.map(|address| Address {
-								hash: address.hash,
+								payload: address.payload,
 								kind: address.kind,
 								network: match self.network {
 									Network::Mainnet => keys::Network::Mainnet,
+									Network::Regtest => keys::Network::Regtest,
 									_ => keys::Network::Testnet,
 								},
 							})
@@ -256,6 +274,24 @@ impl RawClientCoreApi for RawClientCore {
 			blocktime: None,
 		}
 	}
+
+	fn unspent_transaction_output(&self, outpoint: &OutPoint) -> Option<GlobalTransactionOutput> {
+		let memory_pool = self.local_sync_node.memory_pool();
+		let memory_pool = memory_pool.read();
+		let provider = DuplexTransactionOutputProvider::new(self.storage.as_transaction_output_provider(), &*memory_pool);
+		if provider.is_spent(outpoint) {
+			return None;
+		}
+		provider.transaction_output(outpoint, usize::MAX)
+	}
+
+	fn address_network(&self) -> keys::Network {
+		match self.network {
+			Network::Mainnet => keys::Network::Mainnet,
+			Network::Regtest => keys::Network::Regtest,
+			_ => keys::Network::Testnet,
+		}
+	}
 }
 
 impl<T> RawClient<T>
@@ -280,6 +316,17 @@ where
 			.map_err(|e| execution(e))
 	}
 
+	fn submit_package(&self, raw_transactions: Vec<RawTransaction>) -> Result<Vec<SHA256D>, Error> {
+		let package = raw_transactions
+			.into_iter()
+			.map(|raw_transaction| {
+				let raw_transaction_data: Vec<u8> = raw_transaction.into();
+				deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("rawtxs", e))
+			})
+			.collect::<Result<Vec<GlobalTransaction>, Error>>()?;
+		self.core.accept_package(package).map_err(execution)
+	}
+
 	fn create_raw_transaction(
 		&self,
 		inputs: Vec<TransactionInput>,
@@ -313,6 +360,264 @@ where
 		let global_hash: SHA256D = hash.clone().into();
 		self.core.get_raw_transaction(global_hash, verbose.unwrap_or_default())
 	}
+
+	fn create_psbt(
+		&self,
+		inputs: Vec<TransactionInput>,
+		outputs: TransactionOutputs,
+		lock_time: Option<u32>,
+		psbt_version: Option<u32>,
+	) -> Result<String, Error> {
+		let transaction = self
+			.core
+			.create_raw_transaction(inputs, outputs, lock_time)
+			.map_err(|e| execution(e))?;
+		let psbt = Psbt::from_unsigned_transaction(transaction, psbt_version.unwrap_or_default()).map_err(|e| match e {
+			PsbtError::UnsupportedVersion(_) => invalid_params("psbt_version", e),
+			_ => execution(e),
+		})?;
+		Ok(psbt.to_string())
+	}
+
+	fn decode_psbt(&self, psbt: String) -> Result<DecodePsbtResponse, Error> {
+		let psbt = parse_psbt("psbt", &psbt)?;
+		let transaction = psbt.unsigned_transaction().map_err(|e| invalid_params("psbt", e))?;
+		let inputs = psbt
+			.inputs
+			.iter()
+			.map(|input| DecodedPsbtInput {
+				non_witness_utxo: input.non_witness_utxo.clone().map(|transaction| {
+					self.core
+						.transaction_to_verbose_transaction(GlobalIndexedTransaction::from_raw(transaction))
+				}),
+				witness_utxo: input.witness_utxo.as_ref().map(|output| PsbtUtxo {
+					amount: to_btc(output.value),
+					script_pubkey: psbt_script(&output.script_pubkey),
+				}),
+				partial_signatures: input
+					.partial_sigs
+					.iter()
+					.map(|(public, signature)| (public[..].to_hex(), signature.clone().into()))
+					.collect(),
+				sighash: input.sighash_type.map(sighash_name),
+				redeem_script: input.redeem_script.as_ref().map(psbt_script),
+				witness_script: input.witness_script.as_ref().map(psbt_script),
+				bip32_derivs: key_origins(&input.bip32_derivation),
+				final_script_sig: input.final_script_sig.as_ref().map(|script_sig| TransactionInputScript {
+					asm: Script::from(script_sig.clone()).to_string(),
+					hex: script_sig.clone().into(),
+				}),
+				final_script_witness: input
+					.final_script_witness
+					.as_ref()
+					.map(|witness| witness.iter().map(|item| item.clone().into()).collect()),
+				unknown: unknown_pairs(&input.unknown),
+			})
+			.collect();
+		let outputs = psbt
+			.outputs
+			.iter()
+			.map(|output| DecodedPsbtOutput {
+				redeem_script: output.redeem_script.as_ref().map(psbt_script),
+				witness_script: output.witness_script.as_ref().map(psbt_script),
+				bip32_derivs: key_origins(&output.bip32_derivation),
+				unknown: unknown_pairs(&output.unknown),
+			})
+			.collect();
+
+		Ok(DecodePsbtResponse {
+			tx: self
+				.core
+				.transaction_to_verbose_transaction(GlobalIndexedTransaction::from_raw(transaction)),
+			psbt_version: psbt.version,
+			unknown: unknown_pairs(&psbt.unknown),
+			inputs,
+			outputs,
+			fee: psbt.fee().map(to_btc),
+		})
+	}
+
+	fn combine_psbt(&self, txs: Vec<String>) -> Result<String, Error> {
+		let mut psbts = txs.iter().map(|psbt| parse_psbt("txs", psbt));
+		let mut combined = psbts.next().ok_or_else(|| invalid_params("txs", "empty array"))??;
+		for psbt in psbts {
+			combined.combine(psbt?).map_err(|e| invalid_params("txs", e))?;
+		}
+		Ok(combined.to_string())
+	}
+
+	fn finalize_psbt(&self, psbt: String, extract: Option<bool>) -> Result<FinalizePsbtResponse, Error> {
+		let mut psbt = parse_psbt("psbt", &psbt)?;
+		let complete = finalize_psbt(&mut psbt);
+		if complete && extract.unwrap_or(true) {
+			let transaction = psbt.extract().map_err(|e| invalid_params("psbt", e))?;
+			Ok(FinalizePsbtResponse {
+				psbt: None,
+				hex: Some(serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).into()),
+				complete,
+			})
+		} else {
+			Ok(FinalizePsbtResponse {
+				psbt: Some(psbt.to_string()),
+				hex: None,
+				complete,
+			})
+		}
+	}
+
+	fn analyze_psbt(&self, psbt: String) -> Result<AnalyzePsbtResponse, Error> {
+		let psbt = parse_psbt("psbt", &psbt)?;
+		let inputs: Vec<_> = (0..psbt.inputs.len()).map(|index| analyze_psbt_input(&psbt, index)).collect();
+		let next = inputs.iter().map(|input| input.next).min().unwrap_or(PsbtRole::Extractor);
+		if !inputs.iter().all(|input| input.has_utxo) {
+			return Ok(AnalyzePsbtResponse {
+				inputs,
+				fee: None,
+				next,
+				error: None,
+			});
+		}
+
+		match psbt.fee() {
+			Some(fee) => Ok(AnalyzePsbtResponse {
+				inputs,
+				fee: Some(to_btc(fee)),
+				next,
+				error: None,
+			}),
+			None => Ok(AnalyzePsbtResponse {
+				inputs,
+				fee: None,
+				next: PsbtRole::Creator,
+				error: Some("Input amounts are less than output amounts".into()),
+			}),
+		}
+	}
+
+	fn utxo_update_psbt(&self, psbt: String) -> Result<String, Error> {
+		let mut psbt = parse_psbt("psbt", &psbt)?;
+		for input in &mut psbt.inputs {
+			if input.non_witness_utxo.is_some() || input.witness_utxo.is_some() {
+				continue;
+			}
+			let output = match self.core.unspent_transaction_output(&input.previous_output) {
+				Some(output) => output,
+				None => continue,
+			};
+			// non-segwit inputs need the whole spent transaction, which is not provided by the UTXO set
+			let script_pubkey: Script = output.script_pubkey.clone().into();
+			let redeem_script: Option<Script> = input.redeem_script.clone().map(Into::into);
+			let is_segwit = script_pubkey.parse_witness_program().is_some()
+				|| (script_pubkey.is_pay_to_script_hash() && redeem_script.is_some_and(|script| script.parse_witness_program().is_some()));
+			if is_segwit {
+				input.witness_utxo = Some(output);
+			}
+		}
+		Ok(psbt.to_string())
+	}
+
+	fn get_descriptor_info(&self, descriptor: String) -> Result<GetDescriptorInfoResponse, Error> {
+		let parsed = Descriptor::parse(&descriptor, false).map_err(|e| invalid_params("descriptor", e))?;
+		let body = descriptor.split('#').next().unwrap_or_default();
+		Ok(GetDescriptorInfoResponse {
+			descriptor: parsed.to_string(),
+			checksum: descriptor_checksum(body).map_err(|e| invalid_params("descriptor", e))?,
+			isrange: parsed.is_range(),
+			issolvable: parsed.is_solvable(),
+			hasprivatekeys: parsed.has_private_keys(),
+		})
+	}
+
+	fn derive_addresses(&self, descriptor: String, range: Option<DescriptorRange>) -> Result<Vec<String>, Error> {
+		let descriptor = Descriptor::parse(&descriptor, true).map_err(|e| invalid_params("descriptor", e))?;
+		let (begin, end) = match (descriptor.is_range(), range) {
+			(true, None) => return Err(invalid_params("range", "Range must be specified for a ranged descriptor")),
+			(false, Some(_)) => return Err(invalid_params("range", "Range should not be specified for an un-ranged descriptor")),
+			(false, None) => (0, 0),
+			(true, Some(range)) => (range.begin(), range.end()),
+		};
+		if begin > end || end >= HARDENED_OFFSET {
+			return Err(invalid_params("range", "Invalid range"));
+		}
+		if end - begin >= MAX_DERIVED_ADDRESSES {
+			return Err(invalid_params("range", "Range is too large"));
+		}
+
+		let network = self.core.address_network();
+		(begin..=end)
+			.map(|index| descriptor.address(network, index).map(|address| address.to_string()))
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(execution)
+	}
+}
+
+fn parse_psbt(param: &str, psbt: &str) -> Result<Psbt, Error> {
+	psbt.parse().map_err(|e| invalid_params(param, e))
+}
+
+fn to_btc(satoshis: u64) -> f64 {
+	satoshis as f64 / SATOSHIS_IN_COIN as f64
+}
+
+fn psbt_script(script: &GlobalBytes) -> PsbtScript {
+	let script_bytes = script.clone();
+	let script: Script = script_bytes.clone().into();
+	PsbtScript {
+		asm: script.to_string(),
+		hex: script_bytes.into(),
+		script_type: script.script_type().into(),
+	}
+}
+
+fn sighash_name(sighash_type: u32) -> String {
+	let base = match sighash_type & 0x1f {
+		1 => "ALL",
+		2 => "NONE",
+		3 => "SINGLE",
+		_ => return sighash_type.to_string(),
+	};
+	if sighash_type & !0x1f == 0x80 {
+		format!("{}|ANYONECANPAY", base)
+	} else if sighash_type & !0x1f == 0 {
+		base.into()
+	} else {
+		sighash_type.to_string()
+	}
+}
+
+fn key_origins(origins: &BTreeMap<GlobalBytes, KeySource>) -> Vec<PsbtKeyOrigin> {
+	origins
+		.iter()
+		.map(|(public, source)| PsbtKeyOrigin {
+			pubkey: public.clone().into(),
+			master_fingerprint: source.fingerprint.to_vec().into(),
+			path: DerivationPath::new(source.path.iter().map(|index| ChildNumber::from(*index)).collect()).to_string(),
+		})
+		.collect()
+}
+
+fn unknown_pairs(pairs: &BTreeMap<GlobalBytes, GlobalBytes>) -> BTreeMap<String, crate::v1::types::Bytes> {
+	pairs.iter().map(|(key, value)| (key[..].to_hex(), value.clone().into())).collect()
+}
+
+/// Role, which has to process the input next: updater adds missing outputs and scripts, signer adds
+/// missing signatures, finalizer builds final scripts and extractor builds the network transaction.
+fn analyze_psbt_input(psbt: &Psbt, index: usize) -> AnalyzedPsbtInput {
+	let input = &psbt.inputs[index];
+	let has_utxo = input.utxo().is_some();
+	let is_final = input.is_finalized();
+	let next = if is_final {
+		PsbtRole::Extractor
+	} else if !has_utxo {
+		PsbtRole::Updater
+	} else {
+		match finalize_psbt_input(&mut psbt.clone(), index) {
+			Ok(true) => PsbtRole::Finalizer,
+			Ok(false) => PsbtRole::Signer,
+			Err(_) => PsbtRole::Updater,
+		}
+	};
+	AnalyzedPsbtInput { has_utxo, is_final, next }
 }
 
 #[cfg(test)]
@@ -322,10 +627,13 @@ pub mod tests {
 	use crate::v1::types::{
 		Bytes, ScriptType, SignedTransactionInput, Transaction, TransactionInput, TransactionInputScript, TransactionOutputs,
 	};
+	use bitcrypto::FromHex;
 	use bitcrypto::{FromStr, SHA256D};
 	use chain::Transaction as GlobalTransaction;
+	use global_script::{sign_psbt_input, Builder as GlobalScriptBuilder};
 	use jsonrpc_core::IoHandler;
-	use keys::Address;
+	use keys::{Address, KeyPair};
+	use serde_json::Value;
 
 	#[derive(Default)]
 	struct SuccessRawClientCore;
@@ -338,6 +646,10 @@ pub mod tests {
 			Ok(transaction.hash())
 		}
 
+		fn accept_package(&self, package: Vec<GlobalTransaction>) -> Result<Vec<SHA256D>, String> {
+			Ok(package.iter().map(GlobalTransaction::hash).collect())
+		}
+
 		fn create_raw_transaction(
 			&self,
 			_inputs: Vec<TransactionInput>,
@@ -454,6 +766,17 @@ pub mod tests {
 				blocktime: None,
 			}
 		}
+
+		fn unspent_transaction_output(&self, _outpoint: &OutPoint) -> Option<GlobalTransactionOutput> {
+			Some(GlobalTransactionOutput {
+				value: 100_000,
+				script_pubkey: "0014d85c2b71d0060b09c9886aeb815e50991dda124d".into(),
+			})
+		}
+
+		fn address_network(&self) -> keys::Network {
+			keys::Network::Mainnet
+		}
 	}
 
 	impl RawClientCoreApi for ErrorRawClientCore {
@@ -461,6 +784,10 @@ pub mod tests {
 			Err("error".to_owned())
 		}
 
+		fn accept_package(&self, _package: Vec<GlobalTransaction>) -> Result<Vec<SHA256D>, String> {
+			Err("error".to_owned())
+		}
+
 		fn create_raw_transaction(
 			&self,
 			_inputs: Vec<TransactionInput>,
@@ -529,6 +856,14 @@ pub mod tests {
 				blocktime: None,
 			}
 		}
+
+		fn unspent_transaction_output(&self, _outpoint: &OutPoint) -> Option<GlobalTransactionOutput> {
+			None
+		}
+
+		fn address_network(&self) -> keys::Network {
+			keys::Network::Testnet
+		}
 	}
 
 	#[test]
@@ -581,6 +916,54 @@ pub mod tests {
 		);
 	}
 
+	#[test]
+	fn submitpackage_accepted() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitpackage",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000", "0100000001ad9d38823d95f31dc6c0cb0724c11a3cf5a466ca4147254a10cd94aade6eb5b3230000006b483045022100b7683165c3ecd57b0c44bf6a0fb258dc08c328458321c8fadc2b9348d4e66bd502204fd164c58d1a949a4d39bb380f8f05c9f6b3e9417f06bf72e5c068428ca3578601210391c35ac5ee7cf82c5015229dcff89507f83f9b8c952b8fecfa469066c1cb44ccffffffff0170f30500000000001976a914801da3cb2ed9e44540f4b982bde07cd3fbae264288ac00000000"]],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","result":["34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","4dbbc65cf8eff9a04752bf493232e0b82488308f72f2afb497f36bbddada500c"],"id":1}"#,
+			&sample
+		);
+	}
+
+	#[test]
+	fn submitpackage_rejected() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler
+			.handle_request_sync(
+				&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitpackage",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#),
+			)
+			.unwrap();
+
+		assert_eq!(
+			r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"error\""},"id":1}"#,
+			&sample
+		);
+	}
+
 	#[test]
 	fn createrawtransaction_success() {
 		let client = RawClient::new(SuccessRawClientCore::default());
@@ -745,4 +1128,325 @@ pub mod tests {
 			&sample
 		);
 	}
+
+	#[test]
+	fn create_raw_transaction_to_witness_addresses() {
+		let inputs: Vec<TransactionInput> =
+			serde_json::from_str(r#"[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}]"#).unwrap();
+		let outputs: TransactionOutputs = serde_json::from_str(
+			r#"{"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4":0.01,"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0":0.02}"#,
+		)
+		.unwrap();
+
+		let transaction = RawClientCore::do_create_raw_transaction(inputs, outputs, None).unwrap();
+		assert_eq!(transaction.outputs.len(), 2);
+		assert_eq!(
+			transaction.outputs[0].script_pubkey,
+			"0014751e76e8199196d454941c45d1b3a323f1433bd6".into()
+		);
+		assert_eq!(
+			transaction.outputs[1].script_pubkey,
+			"512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".into()
+		);
+	}
+
+	/// PSBT, spending p2wpkh output of the test key
+	fn test_psbt() -> (Psbt, KeyPair) {
+		let keypair = KeyPair::from_private("L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy".into()).unwrap();
+		let inputs: Vec<TransactionInput> =
+			serde_json::from_str(r#"[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}]"#).unwrap();
+		let outputs: TransactionOutputs = serde_json::from_str(r#"{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}"#).unwrap();
+		let transaction = RawClientCore::do_create_raw_transaction(inputs, outputs, None).unwrap();
+		let mut psbt = Psbt::from_unsigned_transaction(transaction, 0).unwrap();
+		psbt.inputs[0].witness_utxo = Some(GlobalTransactionOutput {
+			value: 1_100_000,
+			script_pubkey: GlobalScriptBuilder::build_p2wpkh(&keypair.public().address_hash()).to_bytes(),
+		});
+		(psbt, keypair)
+	}
+
+	fn handle<T: RawClientCoreApi>(core: T, method: &str, params: &str) -> Value {
+		let client = RawClient::new(core);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let request = format!(r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#, method, params);
+		let response = handler.handle_request_sync(&request).unwrap();
+		serde_json::from_str(&response).unwrap()
+	}
+
+	#[test]
+	fn create_psbt_from_created_transaction() {
+		let (mut psbt, _) = test_psbt();
+		psbt.inputs[0].witness_utxo = None;
+		assert_eq!(psbt.to_string(), "cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA");
+	}
+
+	#[test]
+	fn createpsbt_error() {
+		let params = r#"[[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}]"#;
+		let response = handle(ErrorRawClientCore::default(), "createpsbt", params);
+		assert_eq!(response["error"]["code"], -32015);
+
+		let params = r#"[[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01},0,1]"#;
+		let response = handle(SuccessRawClientCore::default(), "createpsbt", params);
+		assert_eq!(
+			response["error"],
+			serde_json::json!({"code": -32602, "message": "Invalid parameter: psbt_version", "data": "UnsupportedVersion(1)"})
+		);
+	}
+
+	#[test]
+	fn decodepsbt_success() {
+		let (mut psbt, _) = test_psbt();
+		psbt.inputs[0].sighash_type = Some(0x81);
+		psbt.inputs[0].bip32_derivation.insert(
+			"0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".into(),
+			KeySource {
+				fingerprint: [0xd9, 0x0c, 0x6a, 0x4f],
+				path: vec![0x8000_0054, 0, 1],
+			},
+		);
+		psbt.unknown.insert("f001".into(), "beef".into());
+
+		let response = handle(SuccessRawClientCore::default(), "decodepsbt", &format!(r#"["{}"]"#, psbt));
+		let result = &response["result"];
+		assert_eq!(result["psbt_version"], 0);
+		assert_eq!(result["fee"], 0.001);
+		assert_eq!(result["unknown"], serde_json::json!({"f001": "beef"}));
+		assert_eq!(result["outputs"], serde_json::json!([{}]));
+		assert_eq!(
+			result["inputs"],
+			serde_json::json!([{
+				"witness_utxo": {
+					"amount": 0.011,
+					"scriptPubKey": {
+						"asm": "OP_0 0x\nOP_PUSHBYTES_20 0x47862fe165e6121af80d5dde1ecb478ed170565b\n",
+						"hex": "001447862fe165e6121af80d5dde1ecb478ed170565b",
+						"type": "witness_v0_keyhash",
+					},
+				},
+				"sighash": "ALL|ANYONECANPAY",
+				"bip32_derivs": [{
+					"pubkey": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+					"master_fingerprint": "d90c6a4f",
+					"path": "m/84'/0/1",
+				}],
+			}])
+		);
+	}
+
+	#[test]
+	fn decodepsbt_invalid() {
+		let response = handle(SuccessRawClientCore::default(), "decodepsbt", r#"["cHNidP8"]"#);
+		assert_eq!(
+			response["error"],
+			serde_json::json!({"code": -32602, "message": "Invalid parameter: psbt", "data": "InvalidBase64"})
+		);
+	}
+
+	#[test]
+	fn combinepsbt_success() {
+		let (psbt, keypair) = test_psbt();
+		let mut signed = psbt.clone();
+		assert_eq!(sign_psbt_input(&mut signed, 0, &keypair), Ok(true));
+		let mut derived = psbt.clone();
+		derived.outputs[0]
+			.bip32_derivation
+			.insert(keypair.public().to_vec().into(), KeySource::default());
+
+		let response = handle(
+			SuccessRawClientCore::default(),
+			"combinepsbt",
+			&format!(r#"[["{}", "{}"]]"#, signed, derived),
+		);
+		let mut combined = signed.clone();
+		combined.combine(derived).unwrap();
+		assert_eq!(response["result"], combined.to_string());
+
+		let (mut other, _) = test_psbt();
+		other.inputs[0].sequence = 0;
+		let response = handle(
+			SuccessRawClientCore::default(),
+			"combinepsbt",
+			&format!(r#"[["{}", "{}"]]"#, psbt, other),
+		);
+		assert_eq!(response["error"]["data"], "MismatchedTransaction");
+		let response = handle(SuccessRawClientCore::default(), "combinepsbt", "[[]]");
+		assert_eq!(response["error"]["message"], "Invalid parameter: txs");
+	}
+
+	#[test]
+	fn finalizepsbt_success() {
+		let (mut psbt, keypair) = test_psbt();
+		let response = handle(SuccessRawClientCore::default(), "finalizepsbt", &format!(r#"["{}"]"#, psbt));
+		assert_eq!(response["result"], serde_json::json!({"psbt": psbt.to_string(), "complete": false}));
+
+		sign_psbt_input(&mut psbt, 0, &keypair).unwrap();
+		let response = handle(SuccessRawClientCore::default(), "finalizepsbt", &format!(r#"["{}"]"#, psbt));
+		assert_eq!(response["result"]["complete"], true);
+		let hex: Vec<u8> = FromHex::from_hex(response["result"]["hex"].as_str().unwrap()).unwrap();
+		let transaction: GlobalTransaction = deserialize(&*hex).unwrap();
+		assert_eq!(transaction.inputs[0].script_witness.len(), 2);
+		assert_eq!(transaction.inputs[0].script_witness[1], keypair.public().to_vec().into());
+
+		let response = handle(SuccessRawClientCore::default(), "finalizepsbt", &format!(r#"["{}", false]"#, psbt));
+		let finalized: Psbt = response["result"]["psbt"].as_str().unwrap().parse().unwrap();
+		assert_eq!(response["result"]["complete"], true);
+		assert_eq!(finalized.extract().unwrap(), transaction);
+	}
+
+	#[test]
+	fn analyzepsbt_success() {
+		let (mut psbt, keypair) = test_psbt();
+		let mut without_utxo = psbt.clone();
+		without_utxo.inputs[0].witness_utxo = None;
+		let response = handle(SuccessRawClientCore::default(), "analyzepsbt", &format!(r#"["{}"]"#, without_utxo));
+		assert_eq!(
+			response["result"],
+			serde_json::json!({"inputs": [{"has_utxo": false, "is_final": false, "next": "updater"}], "next": "updater"})
+		);
+
+		let response = handle(SuccessRawClientCore::default(), "analyzepsbt", &format!(r#"["{}"]"#, psbt));
+		assert_eq!(
+			response["result"],
+			serde_json::json!({"inputs": [{"has_utxo": true, "is_final": false, "next": "signer"}], "fee": 0.001, "next": "signer"})
+		);
+
+		sign_psbt_input(&mut psbt, 0, &keypair).unwrap();
+		let response = handle(SuccessRawClientCore::default(), "analyzepsbt", &format!(r#"["{}"]"#, psbt));
+		assert_eq!(response["result"]["next"], "finalizer");
+
+		psbt.outputs[0].value = 2_000_000;
+		let response = handle(SuccessRawClientCore::default(), "analyzepsbt", &format!(r#"["{}"]"#, psbt));
+		assert_eq!(response["result"]["next"], "creator");
+		assert_eq!(response["result"]["error"], "Input amounts are less than output amounts");
+	}
+
+	#[test]
+	fn utxoupdatepsbt_success() {
+		let (mut psbt, _) = test_psbt();
+		psbt.inputs[0].witness_utxo = None;
+
+		let response = handle(ErrorRawClientCore::default(), "utxoupdatepsbt", &format!(r#"["{}"]"#, psbt));
+		assert_eq!(response["result"], psbt.to_string());
+
+		let response = handle(SuccessRawClientCore::default(), "utxoupdatepsbt", &format!(r#"["{}"]"#, psbt));
+		let updated: Psbt = response["result"].as_str().unwrap().parse().unwrap();
+		assert_eq!(
+			updated.inputs[0].witness_utxo,
+			Some(GlobalTransactionOutput {
+				value: 100_000,
+				script_pubkey: "0014d85c2b71d0060b09c9886aeb815e50991dda124d".into(),
+			})
+		);
+	}
+
+	#[test]
+	fn getdescriptorinfo_success() {
+		let descriptor = "wpkh([d34db33f/84h/0h/0h]L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1)";
+		let response = handle(
+			SuccessRawClientCore::default(),
+			"getdescriptorinfo",
+			&format!(r#"["{}"]"#, descriptor),
+		);
+		let public = "wpkh([d34db33f/84'/0'/0']03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)";
+		assert_eq!(
+			response["result"],
+			serde_json::json!({
+				"descriptor": format!("{}#{}", public, global_script::descriptor_checksum(public).unwrap()),
+				"checksum": global_script::descriptor_checksum(descriptor).unwrap(),
+				"isrange": false,
+				"issolvable": true,
+				"hasprivatekeys": true,
+			})
+		);
+
+		let response = handle(
+			SuccessRawClientCore::default(),
+			"getdescriptorinfo",
+			r#"["wpkh(xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8/0/*)#wvk84d79"]"#,
+		);
+		assert_eq!(response["result"]["checksum"], "wvk84d79");
+		assert_eq!(response["result"]["isrange"], true);
+		assert_eq!(response["result"]["hasprivatekeys"], false);
+	}
+
+	#[test]
+	fn getdescriptorinfo_invalid() {
+		let response = handle(SuccessRawClientCore::default(), "getdescriptorinfo", r#"["wpkh(02f9)"]"#);
+		assert_eq!(
+			response["error"],
+			serde_json::json!({"code": -32602, "message": "Invalid parameter: descriptor", "data": "InvalidKey"})
+		);
+		let response = handle(
+			SuccessRawClientCore::default(),
+			"getdescriptorinfo",
+			r#"["raw(deadbeef)#89f8spxn"]"#,
+		);
+		assert_eq!(response["error"]["data"], "InvalidChecksum");
+	}
+
+	#[test]
+	fn deriveaddresses_success() {
+		let descriptor = "wpkh(xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8/0/*)#wvk84d79";
+		let xpub: keys::ExtendedPublic =
+			"xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8".into();
+		let address = |network, index| {
+			let public = xpub
+				.derive_path(&DerivationPath::new(vec![ChildNumber::Normal(0), ChildNumber::Normal(index)]))
+				.unwrap()
+				.public();
+			Address {
+				kind: keys::Type::P2WPKH,
+				network,
+				payload: public.address_hash().into(),
+			}
+			.to_string()
+		};
+
+		let response = handle(
+			SuccessRawClientCore::default(),
+			"deriveaddresses",
+			&format!(r#"["{}", [1, 2]]"#, descriptor),
+		);
+		assert_eq!(
+			response["result"],
+			serde_json::json!([address(keys::Network::Mainnet, 1), address(keys::Network::Mainnet, 2)])
+		);
+		let response = handle(
+			ErrorRawClientCore::default(),
+			"deriveaddresses",
+			&format!(r#"["{}", 0]"#, descriptor),
+		);
+		assert_eq!(response["result"], serde_json::json!([address(keys::Network::Testnet, 0)]));
+
+		let response = handle(
+			SuccessRawClientCore::default(),
+			"deriveaddresses",
+			r#"["pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)#8fhd9pwu"]"#,
+		);
+		assert_eq!(response["result"], serde_json::json!(["1cMh228HTCiwS8ZsaakH8A8wze1JR5ZsP"]));
+	}
+
+	#[test]
+	fn deriveaddresses_error() {
+		let ranged =
+			"wpkh(xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8/0/*)";
+		let response = handle(SuccessRawClientCore::default(), "deriveaddresses", &format!(r#"["{}", 1]"#, ranged));
+		assert_eq!(response["error"]["data"], "MissingChecksum");
+
+		let error = |params: String| handle(SuccessRawClientCore::default(), "deriveaddresses", &params)["error"]["data"].clone();
+		assert_eq!(
+			error(format!(r#"["{}#wvk84d79"]"#, ranged)),
+			"\"Range must be specified for a ranged descriptor\""
+		);
+		assert_eq!(error(format!(r#"["{}#wvk84d79", [2, 1]]"#, ranged)), "\"Invalid range\"");
+		assert_eq!(error(format!(r#"["{}#wvk84d79", [0, 1000000]]"#, ranged)), "\"Range is too large\"");
+		assert_eq!(
+			error(r#"["raw(deadbeef)#89f8spxm", 1]"#.into()),
+			"\"Range should not be specified for an un-ranged descriptor\""
+		);
+		assert_eq!(error(r#"["raw(deadbeef)#89f8spxm"]"#.into()), "NoAddress");
+	}
 }
@@ -0,0 +1,375 @@
+use crate::v1::helpers::errors::{execution, insufficient_funds, invalid_params};
+use crate::v1::traits::Wallet;
+use crate::v1::types::{WalletTransactionCategory, WalletTransactionEntry, WalletUnspent};
+use bitcrypto::SHA256D;
+use chain::constants::SATOSHIS_IN_COIN;
+use chain::IndexedTransaction;
+use jsonrpc_core::Error;
+use keys::Address;
+use std::sync::Arc;
+use sync;
+use wallet::{self as global_wallet, Category, HistoryEntry, OutputType, WalletUtxo, DEFAULT_FEE_RATE};
+
+pub struct WalletClient<T: WalletClientCoreApi> {
+	core: T,
+}
+
+pub trait WalletClientCoreApi: Send + Sync + 'static {
+	fn new_address(&self, output_type: OutputType) -> Result<Address, global_wallet::Error>;
+	fn balance(&self, min_conf: u32) -> u64;
+	fn unspent(&self, min_conf: u32, max_conf: u32) -> Vec<WalletUtxo>;
+	fn history(&self) -> Vec<HistoryEntry>;
+	fn send_to_address(&self, address: &Address, amount: u64) -> Result<SHA256D, Error>;
+}
+
+pub struct WalletClientCore {
+	wallet: Arc<global_wallet::Wallet>,
+	local_sync_node: sync::LocalNodeRef,
+}
+
+impl WalletClientCore {
+	pub fn new(wallet: Arc<global_wallet::Wallet>, local_sync_node: sync::LocalNodeRef) -> Self {
+		WalletClientCore { wallet, local_sync_node }
+	}
+}
+
+impl WalletClientCoreApi for WalletClientCore {
+	fn new_address(&self, output_type: OutputType) -> Result<Address, global_wallet::Error> {
+		self.wallet.new_address(output_type)
+	}
+
+	fn balance(&self, min_conf: u32) -> u64 {
+		self.wallet.balance(min_conf)
+	}
+
+	fn unspent(&self, min_conf: u32, max_conf: u32) -> Vec<WalletUtxo> {
+		self.wallet.unspent(min_conf, max_conf)
+	}
+
+	fn history(&self) -> Vec<HistoryEntry> {
+		self.wallet.history()
+	}
+
+	fn send_to_address(&self, address: &Address, amount: u64) -> Result<SHA256D, Error> {
+		let transaction = self
+			.wallet
+			.create_transaction(address, amount, DEFAULT_FEE_RATE)
+			.map_err(wallet_error)?;
+		let hash = self
+			.local_sync_node
+			.accept_transaction(IndexedTransaction::from_raw(transaction.clone()))
+			.map_err(execution)?;
+		self.wallet.add_transaction(transaction).map_err(wallet_error)?;
+		Ok(hash)
+	}
+}
+
+impl<T> WalletClient<T>
+where
+	T: WalletClientCoreApi,
+{
+	pub fn new(core: T) -> Self {
+		WalletClient { core }
+	}
+}
+
+impl<T> Wallet for WalletClient<T>
+where
+	T: WalletClientCoreApi,
+{
+	fn new_address(&self, _label: Option<String>, address_type: Option<String>) -> Result<String, Error> {
+		let output_type = match address_type {
+			Some(address_type) => address_type.parse().map_err(wallet_error)?,
+			None => OutputType::Bech32,
+		};
+		self.core
+			.new_address(output_type)
+			.map(|address| address.to_string())
+			.map_err(wallet_error)
+	}
+
+	fn balance(&self, _dummy: Option<String>, minconf: Option<u32>) -> Result<f64, Error> {
+		Ok(to_btc(self.core.balance(minconf.unwrap_or(0))))
+	}
+
+	fn unspent(&self, minconf: Option<u32>, maxconf: Option<u32>) -> Result<Vec<WalletUnspent>, Error> {
+		Ok(self
+			.core
+			.unspent(minconf.unwrap_or(1), maxconf.unwrap_or(9_999_999))
+			.into_iter()
+			.map(|utxo| WalletUnspent {
+				txid: utxo.outpoint.hash,
+				vout: utxo.outpoint.index,
+				address: utxo.address,
+				script_pubkey: utxo.output.script_pubkey.into(),
+				amount: to_btc(utxo.output.value),
+				confirmations: utxo.confirmations,
+				desc: utxo.descriptor.to_string(),
+				spendable: true,
+			})
+			.collect())
+	}
+
+	fn transactions(
+		&self,
+		_label: Option<String>,
+		count: Option<usize>,
+		skip: Option<usize>,
+	) -> Result<Vec<WalletTransactionEntry>, Error> {
+		// `count` most recent entries, after skipping `skip` most recent entries, starting from the oldest one
+		let history = self.core.history();
+		let end = history.len().saturating_sub(skip.unwrap_or(0));
+		let start = end.saturating_sub(count.unwrap_or(10));
+		Ok(history[start..end]
+			.iter()
+			.map(|entry| WalletTransactionEntry {
+				address: entry.address.as_ref().map(ToString::to_string),
+				category: match entry.category {
+					Category::Send => WalletTransactionCategory::Send,
+					Category::Receive => WalletTransactionCategory::Receive,
+					Category::Generate => WalletTransactionCategory::Generate,
+					Category::Immature => WalletTransactionCategory::Immature,
+				},
+				amount: entry.amount as f64 / SATOSHIS_IN_COIN as f64,
+				vout: entry.vout,
+				fee: entry.fee.map(|fee| fee as f64 / SATOSHIS_IN_COIN as f64),
+				confirmations: entry.confirmations,
+				blockhash: entry.block.map(|block| block.hash),
+				blockheight: entry.block.map(|block| block.height),
+				txid: entry.txid,
+				time: entry.time,
+			})
+			.collect())
+	}
+
+	fn send_to_address(&self, address: String, amount: f64) -> Result<SHA256D, Error> {
+		let address: Address = address.parse().map_err(|err| invalid_params("address", err))?;
+		if !amount.is_finite() || amount <= 0.0 {
+			return Err(invalid_params("amount", amount));
+		}
+		let amount = (amount * SATOSHIS_IN_COIN as f64).round() as u64;
+		self.core.send_to_address(&address, amount)
+	}
+}
+
+fn to_btc(satoshis: u64) -> f64 {
+	satoshis as f64 / SATOSHIS_IN_COIN as f64
+}
+
+fn wallet_error(err: global_wallet::Error) -> Error {
+	match err {
+		global_wallet::Error::UnknownAddressType(_) => invalid_params("address_type", err),
+		global_wallet::Error::InvalidNetwork => invalid_params("address", err),
+		global_wallet::Error::AmountTooSmall => invalid_params("amount", err),
+		global_wallet::Error::InsufficientFunds => insufficient_funds(),
+		err => execution(err),
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use bitcrypto::Hash;
+	use chain::{OutPoint, TransactionOutput};
+	use jsonrpc_core::IoHandler;
+	use keys::{KeyPair, Network, Private};
+	use wallet::{BlockTip, Descriptor};
+
+	#[derive(Default)]
+	struct SuccessWalletClientCore;
+	#[derive(Default)]
+	struct ErrorWalletClientCore;
+
+	fn descriptor() -> Descriptor {
+		let mut secret = [0u8; 32];
+		secret[31] = 1;
+		let private = Private {
+			network: Network::Mainnet,
+			secret,
+			compressed: true,
+		};
+		Descriptor::new(OutputType::Legacy, KeyPair::from_private(private).unwrap().public().clone()).unwrap()
+	}
+
+	fn history_entry(txid: u8, category: Category, amount: i64, fee: Option<i64>) -> HistoryEntry {
+		HistoryEntry {
+			txid: SHA256D::from_inner([txid; 32]),
+			vout: 0,
+			address: Some(descriptor().address(Network::Mainnet)),
+			category,
+			amount,
+			fee,
+			confirmations: 1,
+			block: Some(BlockTip {
+				hash: SHA256D::from_inner([txid; 32]),
+				height: txid as u32,
+			}),
+			time: 100,
+		}
+	}
+
+	impl WalletClientCoreApi for SuccessWalletClientCore {
+		fn new_address(&self, output_type: OutputType) -> Result<Address, global_wallet::Error> {
+			Ok(Descriptor::new(output_type, descriptor().public().clone())
+				.unwrap()
+				.address(Network::Mainnet))
+		}
+
+		fn balance(&self, min_conf: u32) -> u64 {
+			150_000_000 + min_conf as u64
+		}
+
+		fn unspent(&self, _min_conf: u32, _max_conf: u32) -> Vec<WalletUtxo> {
+			let descriptor = descriptor();
+			vec![WalletUtxo {
+				outpoint: OutPoint {
+					hash: SHA256D::from_inner([1; 32]),
+					index: 2,
+				},
+				output: TransactionOutput {
+					value: 10_000,
+					script_pubkey: descriptor.script_pubkey().to_bytes(),
+				},
+				address: descriptor.address(Network::Mainnet),
+				descriptor,
+				confirmations: 3,
+			}]
+		}
+
+		fn history(&self) -> Vec<HistoryEntry> {
+			vec![
+				history_entry(1, Category::Receive, 100_000, None),
+				history_entry(2, Category::Send, -50_000, Some(-1_000)),
+				history_entry(3, Category::Generate, 5_000_000_000, None),
+			]
+		}
+
+		fn send_to_address(&self, _address: &Address, amount: u64) -> Result<SHA256D, Error> {
+			assert_eq!(amount, 10_000_000);
+			Ok(SHA256D::from_inner([5; 32]))
+		}
+	}
+
+	impl WalletClientCoreApi for ErrorWalletClientCore {
+		fn new_address(&self, _output_type: OutputType) -> Result<Address, global_wallet::Error> {
+			Err(global_wallet::Error::Database("error".into()))
+		}
+
+		fn balance(&self, _min_conf: u32) -> u64 {
+			0
+		}
+
+		fn unspent(&self, _min_conf: u32, _max_conf: u32) -> Vec<WalletUtxo> {
+			vec![]
+		}
+
+		fn history(&self) -> Vec<HistoryEntry> {
+			vec![]
+		}
+
+		fn send_to_address(&self, _address: &Address, _amount: u64) -> Result<SHA256D, Error> {
+			Err(wallet_error(global_wallet::Error::InsufficientFunds))
+		}
+	}
+
+	fn handler<T: WalletClientCoreApi>(core: T) -> IoHandler {
+		let mut handler = IoHandler::new();
+		handler.extend_with(WalletClient::new(core).to_delegate());
+		handler
+	}
+
+	fn request(method: &str, params: &str) -> String {
+		format!(r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#, method, params)
+	}
+
+	#[test]
+	fn new_address_success() {
+		let handler = handler(SuccessWalletClientCore::default());
+		assert_eq!(
+			handler.handle_request_sync(&request("getnewaddress", "[]")).unwrap(),
+			r#"{"jsonrpc":"2.0","result":"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4","id":1}"#
+		);
+		assert_eq!(
+			handler.handle_request_sync(&request("getnewaddress", r#"["", "legacy"]"#)).unwrap(),
+			r#"{"jsonrpc":"2.0","result":"1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH","id":1}"#
+		);
+		assert_eq!(
+			handler
+				.handle_request_sync(&request("getnewaddress", r#"["", "unknown"]"#))
+				.unwrap(),
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: address_type","data":"UnknownAddressType(\"unknown\")"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn new_address_error() {
+		assert_eq!(
+			handler(ErrorWalletClientCore::default())
+				.handle_request_sync(&request("getnewaddress", "[]"))
+				.unwrap(),
+			r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"Database(\"error\")"},"id":1}"#
+		);
+	}
+
+	#[test]
+	fn balance_success() {
+		assert_eq!(
+			handler(SuccessWalletClientCore::default())
+				.handle_request_sync(&request("getbalance", r#"["*", 1]"#))
+				.unwrap(),
+			r#"{"jsonrpc":"2.0","result":1.50000001,"id":1}"#
+		);
+	}
+
+	#[test]
+	fn unspent_success() {
+		assert_eq!(
+			handler(SuccessWalletClientCore::default())
+				.handle_request_sync(&request("listunspent", "[]"))
+				.unwrap(),
+			r#"{"jsonrpc":"2.0","result":[{"address":"1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH","amount":0.0001,"confirmations":3,"desc":"pkh(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)#e48zzw02","scriptPubKey":"76a914751e76e8199196d454941c45d1b3a323f1433bd688ac","spendable":true,"txid":"0101010101010101010101010101010101010101010101010101010101010101","vout":2}],"id":1}"#
+		);
+	}
+
+	#[test]
+	fn transactions_success() {
+		let handler = handler(SuccessWalletClientCore::default());
+		assert_eq!(
+			handler.handle_request_sync(&request("listtransactions", r#"["*", 1, 1]"#)).unwrap(),
+			r#"{"jsonrpc":"2.0","result":[{"address":"1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH","amount":-0.0005,"blockhash":"0202020202020202020202020202020202020202020202020202020202020202","blockheight":2,"category":"send","confirmations":1,"fee":-0.00001,"time":100,"txid":"0202020202020202020202020202020202020202020202020202020202020202","vout":0}],"id":1}"#
+		);
+		assert_eq!(
+			handler
+				.handle_request_sync(&request("listtransactions", r#"["*", 10, 3]"#))
+				.unwrap(),
+			r#"{"jsonrpc":"2.0","result":[],"id":1}"#
+		);
+	}
+
+	#[test]
+	fn send_to_address_success() {
+		assert_eq!(
+			handler(SuccessWalletClientCore::default())
+				.handle_request_sync(&request("sendtoaddress", r#"["1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH", 0.1]"#))
+				.unwrap(),
+			r#"{"jsonrpc":"2.0","result":"0505050505050505050505050505050505050505050505050505050505050505","id":1}"#
+		);
+	}
+
+	#[test]
+	fn send_to_address_error() {
+		let handler = handler(ErrorWalletClientCore::default());
+		assert_eq!(
+			handler
+				.handle_request_sync(&request("sendtoaddress", r#"["1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH", 0.1]"#))
+				.unwrap(),
+			r#"{"jsonrpc":"2.0","error":{"code":-32093,"message":"Insufficient funds"},"id":1}"#
+		);
+		assert_eq!(
+			handler
+				.handle_request_sync(&request("sendtoaddress", r#"["1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH", -1]"#))
+				.unwrap(),
+			r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid parameter: amount","data":"-1.0"},"id":1}"#
+		);
+	}
+}
@@ -0,0 +1,21 @@
+use crate::v1::types::{AddressBalance, AddressUtxo, AddressesRequest};
+use bitcrypto::SHA256D;
+use jsonrpc_core::Error;
+use jsonrpc_derive::rpc;
+
+/// bitcoin-rs address index interface. Requires node to be started with `--address-index`.
+#[rpc(server)]
+pub trait AddressIndex {
+	/// Returns hashes of all canon chain transactions, funding or spending given addresses.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddresstxids", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getaddresstxids")]
+	fn address_tx_ids(&self, request: AddressesRequest) -> Result<Vec<SHA256D>, Error>;
+	/// Returns all unspent canon chain outputs of given addresses.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddressutxos", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getaddressutxos")]
+	fn address_utxos(&self, request: AddressesRequest) -> Result<Vec<AddressUtxo>, Error>;
+	/// Returns current balance and total received amount of given addresses.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddressbalance", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getaddressbalance")]
+	fn address_balance(&self, request: AddressesRequest) -> Result<AddressBalance, Error>;
+}
@@ -1,7 +1,7 @@
 use crate::v1::types::GetBlockResponse;
 use crate::v1::types::GetTxOutResponse;
 use crate::v1::types::GetTxOutSetInfoResponse;
-use crate::v1::types::{BlockchainInfo, ChainTxStats};
+use crate::v1::types::{BlockchainInfo, ChainTxStats, GetBlockFilterResponse};
 use bitcrypto::SHA256D;
 use jsonrpc_core::Error;
 use jsonrpc_derive::rpc;
@@ -45,4 +45,8 @@ pub trait BlockChain {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getchaintxstats", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getchaintxstats")]
 	fn chain_tx_stats(&self, nblocks: Option<usize>, blockhash: Option<String>) -> Result<ChainTxStats, Error>;
+	/// Get BIP158 filter of the canon block.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockfilter", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", "basic"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getblockfilter")]
+	fn block_filter(&self, hash: SHA256D, filter_type: Option<String>) -> Result<GetBlockFilterResponse, Error>;
 }
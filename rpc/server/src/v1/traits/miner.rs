@@ -1,4 +1,5 @@
-use crate::v1::types::{BlockTemplate, BlockTemplateRequest, MempoolInfo};
+use crate::v1::types::{BlockTemplate, BlockTemplateRequest, MempoolEntry, MempoolInfo, MempoolRelatives};
+use bitcrypto::SHA256D;
 use jsonrpc_core::Error;
 use jsonrpc_derive::rpc;
 
@@ -13,4 +14,20 @@ pub trait Miner {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempoolinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getmempoolinfo")]
 	fn mempool_info(&self) -> Result<MempoolInfo, Error>;
+	/// Get memory pool data for given transaction
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempoolentry", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getmempoolentry")]
+	fn mempool_entry(&self, hash: SHA256D) -> Result<MempoolEntry, Error>;
+	/// Get all in-pool ancestors of given memory pool transaction
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempoolancestors", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getmempoolancestors")]
+	fn mempool_ancestors(&self, hash: SHA256D, verbose: Option<bool>) -> Result<MempoolRelatives, Error>;
+	/// Get all in-pool descendants of given memory pool transaction
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempooldescendants", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getmempooldescendants")]
+	fn mempool_descendants(&self, hash: SHA256D, verbose: Option<bool>) -> Result<MempoolRelatives, Error>;
+	/// Save memory pool to the dump file, which is loaded on startup
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "savemempool", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "savemempool")]
+	fn save_mempool(&self) -> Result<(), Error>;
 }
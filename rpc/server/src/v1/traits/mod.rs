@@ -1,13 +1,17 @@
+mod address_index;
 mod blockchain;
 mod control;
 mod generate;
 mod miner;
 mod network;
 mod raw;
+mod wallet;
 
+pub use self::address_index::AddressIndex;
 pub use self::blockchain::BlockChain;
 pub use self::control::Control;
 pub use self::generate::Generate;
 pub use self::miner::Miner;
 pub use self::network::Network;
 pub use self::raw::Raw;
+pub use self::wallet::Wallet;
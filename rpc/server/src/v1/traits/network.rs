@@ -1,4 +1,4 @@
-use crate::v1::types::{AddNodeOperation, NetworkInfo, NodeInfo, Peer};
+use crate::v1::types::{AddNodeOperation, BannedNode, NetTotals, NetworkInfo, NodeInfo, Peer, SetBanOperation};
 use jsonrpc_core::Error;
 use jsonrpc_derive::rpc;
 
@@ -28,4 +28,27 @@ pub trait Network {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getpeerinfo", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getpeerinfo")]
 	fn peer_info(&self) -> Result<Vec<Peer>, Error>;
+	/// Returns information about network traffic and the upload target.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getnettotals", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getnettotals")]
+	fn net_totals(&self) -> Result<NetTotals, Error>;
+	/// Adds or removes subnet from the ban list. Ban time is in seconds, or a timestamp if `absolute` is true.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "setban", "params": ["192.168.0.6", "add", 86400] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "setban", "params": ["192.168.0.0/24", "add"] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "setban", "params": ["192.168.0.0/24", "remove"] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "setban")]
+	fn set_ban(&self, subnet: String, operation: SetBanOperation, bantime: Option<u64>, absolute: Option<bool>) -> Result<(), Error>;
+	/// Returns all banned subnets.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "listbanned", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "listbanned")]
+	fn list_banned(&self) -> Result<Vec<BannedNode>, Error>;
+	/// Removes all bans.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "clearbanned", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "clearbanned")]
+	fn clear_banned(&self) -> Result<(), Error>;
+	/// Disconnects from the peer with given address or id.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "disconnectnode", "params": ["192.168.0.6:8333"] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "disconnectnode", "params": ["", 1] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "disconnectnode")]
+	fn disconnect_node(&self, address: Option<String>, node_id: Option<usize>) -> Result<(), Error>;
 }
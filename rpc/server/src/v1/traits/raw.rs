@@ -1,3 +1,8 @@
+use crate::v1::types::AnalyzePsbtResponse;
+use crate::v1::types::DecodePsbtResponse;
+use crate::v1::types::DescriptorRange;
+use crate::v1::types::FinalizePsbtResponse;
+use crate::v1::types::GetDescriptorInfoResponse;
 use crate::v1::types::GetRawTransactionResponse;
 use crate::v1::types::RawTransaction;
 use crate::v1::types::Transaction;
@@ -14,6 +19,11 @@ pub trait Raw {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "sendrawtransaction", "params": ["01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "sendrawtransaction")]
 	fn send_raw_transaction(&self, raw_transaction: RawTransaction) -> Result<SHA256D, Error>;
+	/// Adds package of transactions (topologically sorted parents + their child) to the memory pool && relays it to the peers.
+	/// Package is evaluated as a whole, so that the child could pay for its parents.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "submitpackage", "params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "submitpackage")]
+	fn submit_package(&self, raw_transactions: Vec<RawTransaction>) -> Result<Vec<SHA256D>, Error>;
 	/// Create a transaction spending the given inputs and creating new outputs.
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "createrawtransaction", "params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "createrawtransaction")]
@@ -31,4 +41,42 @@ pub trait Raw {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getrawtransaction", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getrawtransaction")]
 	fn get_raw_transaction(&self, hash: SHA256D, verbose: Option<bool>) -> Result<GetRawTransactionResponse, Error>;
+	/// Create a transaction in the PSBT format (version 0 or 2), spending the given inputs and creating new outputs.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "createpsbt", "params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "createpsbt")]
+	fn create_psbt(
+		&self,
+		inputs: Vec<TransactionInput>,
+		outputs: TransactionOutputs,
+		lock_time: Option<u32>,
+		psbt_version: Option<u32>,
+	) -> Result<String, Error>;
+	/// Return an object representing the base64-encoded PSBT.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "decodepsbt", "params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "decodepsbt")]
+	fn decode_psbt(&self, psbt: String) -> Result<DecodePsbtResponse, Error>;
+	/// Combine multiple PSBTs, describing the same transaction, into one.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "combinepsbt", "params": [["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA", "cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "combinepsbt")]
+	fn combine_psbt(&self, txs: Vec<String>) -> Result<String, Error>;
+	/// Finalize inputs of the PSBT. If all inputs are finalized, the network transaction is extracted (unless `extract` is false).
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "finalizepsbt", "params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "finalizepsbt")]
+	fn finalize_psbt(&self, psbt: String, extract: Option<bool>) -> Result<FinalizePsbtResponse, Error>;
+	/// Analyze the PSBT: which inputs are missing data and which role should process the PSBT next.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "analyzepsbt", "params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "analyzepsbt")]
+	fn analyze_psbt(&self, psbt: String) -> Result<AnalyzePsbtResponse, Error>;
+	/// Add spent outputs of segwit inputs to the PSBT, using the UTXO set and the memory pool.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "utxoupdatepsbt", "params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "utxoupdatepsbt")]
+	fn utxo_update_psbt(&self, psbt: String) -> Result<String, Error>;
+	/// Analyze output script descriptor, returning its canonical form and checksum.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getdescriptorinfo", "params": ["wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getdescriptorinfo")]
+	fn get_descriptor_info(&self, descriptor: String) -> Result<GetDescriptorInfoResponse, Error>;
+	/// Derive addresses of the output script descriptor with checksum. Range is required for ranged descriptors.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "deriveaddresses", "params": ["wpkh(xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8/0/*)#wvk84d79", [0, 2]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "deriveaddresses")]
+	fn derive_addresses(&self, descriptor: String, range: Option<DescriptorRange>) -> Result<Vec<String>, Error>;
 }
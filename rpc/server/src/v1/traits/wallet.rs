@@ -0,0 +1,29 @@
+use crate::v1::types::{WalletTransactionEntry, WalletUnspent};
+use bitcrypto::SHA256D;
+use jsonrpc_core::Error;
+use jsonrpc_derive::rpc;
+
+/// bitcoin-rs wallet interface. Requires node to be started with `--wallet`.
+#[rpc(server)]
+pub trait Wallet {
+	/// Generates new receiving address. Address type is one of `legacy`, `p2sh-segwit`, `bech32` (default) or `bech32m`.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getnewaddress", "params": ["", "bech32"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getnewaddress")]
+	fn new_address(&self, label: Option<String>, address_type: Option<String>) -> Result<String, Error>;
+	/// Returns total amount of spendable wallet outputs (in BTC) with at least `minconf` confirmations.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getbalance", "params": ["*", 1], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getbalance")]
+	fn balance(&self, dummy: Option<String>, minconf: Option<u32>) -> Result<f64, Error>;
+	/// Returns spendable wallet outputs with between `minconf` and `maxconf` confirmations.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listunspent", "params": [1, 9999999], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "listunspent")]
+	fn unspent(&self, minconf: Option<u32>, maxconf: Option<u32>) -> Result<Vec<WalletUnspent>, Error>;
+	/// Returns up to `count` most recent wallet transactions entries, skipping first `skip` of them.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listtransactions", "params": ["*", 10, 0], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "listtransactions")]
+	fn transactions(&self, label: Option<String>, count: Option<usize>, skip: Option<usize>) -> Result<Vec<WalletTransactionEntry>, Error>;
+	/// Sends amount (in BTC) to the given address. Returns hash of the transaction.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "sendtoaddress", "params": ["tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", 0.1], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "sendtoaddress")]
+	fn send_to_address(&self, address: String, amount: f64) -> Result<SHA256D, Error>;
+}
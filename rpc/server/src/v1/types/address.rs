@@ -56,9 +56,9 @@ pub mod vec {
 	where
 		D: Deserializer<'a>,
 	{
-		<Vec<&'a str> as Deserialize>::deserialize(deserializer)?
+		<Vec<String> as Deserialize>::deserialize(deserializer)?
 			.into_iter()
-			.map(|value| AddressVisitor::default().visit_str(value))
+			.map(|value| AddressVisitor::default().visit_str(&value))
 			.collect()
 	}
 }
@@ -98,4 +98,12 @@ mod tests {
 			test
 		);
 	}
+
+	#[test]
+	fn witness_address_serialize_deserialize() {
+		let json = r#"{"address":"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"}"#;
+		let test = TestStruct::new("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".into());
+		assert_eq!(serde_json::to_string(&test).unwrap(), json);
+		assert_eq!(serde_json::from_str::<TestStruct>(json).unwrap(), test);
+	}
 }
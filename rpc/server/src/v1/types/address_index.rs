@@ -0,0 +1,72 @@
+use super::bytes::Bytes;
+use crate::v1::types;
+use bitcrypto::SHA256D;
+use keys::Address;
+
+/// Addresses, passed to the address index requests
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressesRequest {
+	/// List of base58 or bech32 addresses
+	#[serde(with = "types::address::vec")]
+	pub addresses: Vec<Address>,
+}
+
+/// Unspent output, locked by one of requested addresses
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressUtxo {
+	/// Address the output is locked by
+	#[serde(with = "types::address")]
+	pub address: Address,
+	/// Hash of the transaction, containing the output
+	pub txid: SHA256D,
+	/// Index of the output in the transaction
+	#[serde(rename = "outputIndex")]
+	pub output_index: u32,
+	/// Hex-encoded output script
+	pub script: Bytes,
+	/// Output value in satoshis
+	pub satoshis: u64,
+	/// Height of the block, containing the transaction
+	pub height: u32,
+}
+
+/// Balance of requested addresses
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressBalance {
+	/// Current balance in satoshis
+	pub balance: u64,
+	/// Total received amount in satoshis
+	pub received: u64,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json;
+
+	#[test]
+	fn addresses_request_deserialize() {
+		assert_eq!(
+			serde_json::from_str::<AddressesRequest>(r#"{"addresses":["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}"#).unwrap(),
+			AddressesRequest {
+				addresses: vec!["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into()],
+			}
+		);
+	}
+
+	#[test]
+	fn address_utxo_serialize() {
+		let utxo = AddressUtxo {
+			address: "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into(),
+			txid: SHA256D::default(),
+			output_index: 1,
+			script: Bytes::new(vec![0x51]),
+			satoshis: 100,
+			height: 7,
+		};
+		assert_eq!(
+			serde_json::to_string(&utxo).unwrap(),
+			r#"{"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","txid":"0000000000000000000000000000000000000000000000000000000000000000","outputIndex":1,"script":"51","satoshis":100,"height":7}"#
+		);
+	}
+}
@@ -0,0 +1,11 @@
+use super::bytes::Bytes;
+use bitcrypto::SHA256D;
+
+/// BIP158 filter of the block
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetBlockFilterResponse {
+	/// Hex-encoded filter data
+	pub filter: Bytes,
+	/// Hex-encoded filter header
+	pub header: SHA256D,
+}
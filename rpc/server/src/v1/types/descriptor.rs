@@ -0,0 +1,52 @@
+/// Return value of `getdescriptorinfo` method
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetDescriptorInfoResponse {
+	/// Descriptor in the canonical form with public keys and checksum
+	pub descriptor: String,
+	/// Checksum of the input descriptor
+	pub checksum: String,
+	/// Descriptor has wildcard keys
+	pub isrange: bool,
+	/// All information, required to spend the outputs (except for private keys), is known
+	pub issolvable: bool,
+	/// Descriptor has private keys
+	pub hasprivatekeys: bool,
+}
+
+/// Range of derived indexes: either end (starting from 0) or [begin, end], both are inclusive
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(untagged)]
+pub enum DescriptorRange {
+	End(u32),
+	BeginEnd([u32; 2]),
+}
+
+impl DescriptorRange {
+	pub fn begin(&self) -> u32 {
+		match *self {
+			DescriptorRange::End(_) => 0,
+			DescriptorRange::BeginEnd([begin, _]) => begin,
+		}
+	}
+
+	pub fn end(&self) -> u32 {
+		match *self {
+			DescriptorRange::End(end) | DescriptorRange::BeginEnd([_, end]) => end,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::DescriptorRange;
+	use serde_json;
+
+	#[test]
+	fn descriptor_range_deserialize() {
+		let range: DescriptorRange = serde_json::from_str("5").unwrap();
+		assert_eq!((range.begin(), range.end()), (0, 5));
+		let range: DescriptorRange = serde_json::from_str("[2, 7]").unwrap();
+		assert_eq!((range.begin(), range.end()), (2, 7));
+		assert!(serde_json::from_str::<DescriptorRange>("[2]").is_err());
+	}
+}
@@ -1,10 +1,20 @@
-use miner::MemoryPoolInformation;
+use bitcrypto::SHA256D;
+use miner::{MemoryPoolEntryInformation, MemoryPoolInformation};
+use network::ConsensusParams;
+use std::collections::BTreeMap;
+
+/// Satoshis in single BTC
+const SATOSHIS_IN_COIN: f64 = 100_000_000f64;
 
 impl From<MemoryPoolInformation> for MempoolInfo {
 	fn from(info: MemoryPoolInformation) -> Self {
 		MempoolInfo {
 			size: info.transactions_count,
-			usage: info.transactions_size_in_bytes,
+			bytes: info.transactions_size_in_bytes,
+			usage: info.memory_usage,
+			maxmempool: info.max_memory_usage,
+			// satoshis per 1000 bytes => BTC/kB
+			mempoolminfee: info.minimum_fee_rate as f64 / SATOSHIS_IN_COIN,
 			..Default::default()
 		}
 	}
@@ -21,7 +31,86 @@ pub struct MempoolInfo {
 	// Maximum memory usage for the mempool
 	maxmempool: usize,
 	// Minimum fee rate in BTC/kB for tx to be accepted. Is the maximum of minrelaytxfee and minimum mempool fee
-	mempoolminfee: usize,
+	mempoolminfee: f64,
 	// Current minimum relay fee for transactions
 	minrelaytxfee: usize,
 }
+
+impl From<MemoryPoolEntryInformation> for MempoolEntry {
+	fn from(info: MemoryPoolEntryInformation) -> Self {
+		MempoolEntry {
+			vsize: info.weight.div_ceil(ConsensusParams::witness_scale_factor()),
+			time: info.time,
+			descendantcount: info.package_count,
+			descendantsize: info.package_size,
+			ancestorcount: info.ancestors_count + 1,
+			ancestorsize: info.ancestors_size,
+			wtxid: info.witness_hash,
+			fees: MempoolEntryFees {
+				base: info.fee as f64 / SATOSHIS_IN_COIN,
+				modified: info.modified_fee as f64 / SATOSHIS_IN_COIN,
+				descendant: info.package_modified_fee as f64 / SATOSHIS_IN_COIN,
+				ancestor: info.ancestors_modified_fee as f64 / SATOSHIS_IN_COIN,
+			},
+			depends: info.depends,
+			bip125_replaceable: info.bip125_replaceable,
+		}
+	}
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MempoolEntry {
+	// Virtual transaction size as defined in BIP141
+	vsize: usize,
+	// Time when transaction has entered the pool, in seconds since epoch
+	time: u64,
+	// Number of in-pool descendants, including this transaction
+	descendantcount: usize,
+	// Size of in-pool descendants, including this transaction
+	descendantsize: usize,
+	// Number of in-pool ancestors, including this transaction
+	ancestorcount: usize,
+	// Size of in-pool ancestors, including this transaction
+	ancestorsize: usize,
+	// Transaction witness hash
+	wtxid: SHA256D,
+	// Transaction fees in BTC
+	fees: MempoolEntryFees,
+	// Unconfirmed transactions, used as inputs for this transaction
+	depends: Vec<SHA256D>,
+	// Could this transaction be replaced due to BIP125 (replace-by-fee)
+	#[serde(rename = "bip125-replaceable")]
+	bip125_replaceable: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MempoolEntryFees {
+	// Transaction fee
+	base: f64,
+	// Transaction fee, adjusted for prioritisation
+	modified: f64,
+	// Modified fees of in-pool descendants, including this transaction
+	descendant: f64,
+	// Modified fees of in-pool ancestors, including this transaction
+	ancestor: f64,
+}
+
+/// Response to getmempoolancestors and getmempooldescendants RPC requests
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum MempoolRelatives {
+	/// When asking for short response
+	Hashes(Vec<SHA256D>),
+	/// When asking for verbose response
+	Verbose(BTreeMap<SHA256D, MempoolEntry>),
+}
+
+impl MempoolRelatives {
+	pub fn new(relatives: Vec<MemoryPoolEntryInformation>, verbose: bool) -> Self {
+		if verbose {
+			MempoolRelatives::Verbose(relatives.into_iter().map(|info| (info.hash, info.into())).collect())
+		} else {
+			MempoolRelatives::Hashes(relatives.into_iter().map(|info| info.hash).collect())
+		}
+	}
+}
@@ -1,36 +1,50 @@
 pub mod address;
+mod address_index;
 mod block;
+mod block_filter;
 mod block_template;
 mod block_template_request;
 mod blockchain_info;
 mod bytes;
 mod chain_tx_stats;
+mod descriptor;
 mod get_block_response;
 mod get_tx_out_response;
 mod get_tx_out_set_info_response;
 mod memory_info;
 mod mempool_info;
+mod net_totals;
 mod network_info;
 mod nodes;
 mod peer;
+mod psbt;
 mod script;
 mod transaction;
 mod uint;
+mod wallet;
 
+pub use self::address_index::{AddressBalance, AddressUtxo, AddressesRequest};
 pub use self::block::RawBlock;
+pub use self::block_filter::GetBlockFilterResponse;
 pub use self::block_template::{BlockTemplate, BlockTemplateTransaction};
 pub use self::block_template_request::{BlockTemplateRequest, BlockTemplateRequestMode};
 pub use self::blockchain_info::BlockchainInfo;
 pub use self::bytes::Bytes;
 pub use self::chain_tx_stats::ChainTxStats;
+pub use self::descriptor::{DescriptorRange, GetDescriptorInfoResponse};
 pub use self::get_block_response::{GetBlockResponse, VerboseBlock};
 pub use self::get_tx_out_response::GetTxOutResponse;
 pub use self::get_tx_out_set_info_response::GetTxOutSetInfoResponse;
 pub use self::memory_info::MemoryInfo;
-pub use self::mempool_info::MempoolInfo;
-pub use self::network_info::NetworkInfo;
-pub use self::nodes::{AddNodeOperation, NodeInfo};
+pub use self::mempool_info::{MempoolEntry, MempoolEntryFees, MempoolInfo, MempoolRelatives};
+pub use self::net_totals::{NetTotals, UploadTarget};
+pub use self::network_info::{Address as NetworkInfoAddress, Network as NetworkInfoNetwork, NetworkInfo};
+pub use self::nodes::{AddNodeOperation, BannedNode, NodeInfo, SetBanOperation};
 pub use self::peer::Peer;
+pub use self::psbt::{
+	AnalyzePsbtResponse, AnalyzedPsbtInput, DecodePsbtResponse, DecodedPsbtInput, DecodedPsbtOutput, FinalizePsbtResponse, PsbtKeyOrigin,
+	PsbtRole, PsbtScript, PsbtUtxo,
+};
 pub use self::script::ScriptType;
 pub use self::transaction::{
 	GetRawTransactionResponse, RawTransaction, SignedTransactionInput, SignedTransactionOutput, Transaction, TransactionInput,
@@ -38,3 +52,4 @@ pub use self::transaction::{
 	TransactionOutputs,
 };
 pub use self::uint::U256;
+pub use self::wallet::{WalletTransactionCategory, WalletTransactionEntry, WalletUnspent};
@@ -0,0 +1,57 @@
+use p2p::NetTotals as P2PNetTotals;
+
+/// Upload target timeframe in seconds
+const UPLOAD_TARGET_TIMEFRAME: u64 = 24 * 60 * 60;
+
+impl NetTotals {
+	pub fn new(totals: P2PNetTotals, timemillis: u64) -> Self {
+		NetTotals {
+			totalbytesrecv: totals.total_recv,
+			totalbytessent: totals.total_sent,
+			timemillis,
+			uploadtarget: match totals.upload_target {
+				Some(upload_target) => UploadTarget {
+					timeframe: UPLOAD_TARGET_TIMEFRAME,
+					target: upload_target.target,
+					target_reached: upload_target.target_reached,
+					serve_historical_blocks: upload_target.serve_historical_blocks,
+					bytes_left_in_cycle: upload_target.bytes_left_in_cycle,
+					time_left_in_cycle: upload_target.time_left_in_cycle,
+				},
+				None => UploadTarget {
+					timeframe: UPLOAD_TARGET_TIMEFRAME,
+					serve_historical_blocks: true,
+					..Default::default()
+				},
+			},
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct NetTotals {
+	// Total bytes received
+	pub totalbytesrecv: u64,
+	// Total bytes sent
+	pub totalbytessent: u64,
+	// Current UNIX time in milliseconds
+	pub timemillis: u64,
+	// Upload target state
+	pub uploadtarget: UploadTarget,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct UploadTarget {
+	// Length of the measuring timeframe in seconds
+	pub timeframe: u64,
+	// Target in bytes, zero if there is no target
+	pub target: u64,
+	// True if target is reached
+	pub target_reached: bool,
+	// True if serving historical blocks
+	pub serve_historical_blocks: bool,
+	// Bytes left in current time cycle
+	pub bytes_left_in_cycle: u64,
+	// Seconds left in current time cycle
+	pub time_left_in_cycle: u64,
+}
@@ -11,6 +11,14 @@ pub enum AddNodeOperation {
 	OneTry,
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum SetBanOperation {
+	#[serde(rename = "add")]
+	Add,
+	#[serde(rename = "remove")]
+	Remove,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct NodeInfoAddress {
 	address: String,
@@ -43,3 +51,17 @@ pub enum NodeInfoAddressConnectionType {
 	#[serde(rename = "outbound")]
 	Outbound,
 }
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BannedNode {
+	/// Banned subnet
+	pub address: String,
+	/// Time, when the ban was created (seconds since epoch)
+	pub ban_created: u64,
+	/// Time, when the ban expires (seconds since epoch)
+	pub banned_until: u64,
+	/// Duration of the ban in seconds
+	pub ban_duration: u64,
+	/// Seconds until the ban expires
+	pub time_remaining: u64,
+}
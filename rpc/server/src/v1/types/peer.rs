@@ -1,21 +1,41 @@
 use p2p::{Direction, PeerInfo};
+use std::collections::BTreeMap;
 
 impl From<&PeerInfo> for Peer {
 	fn from(info: &PeerInfo) -> Self {
 		Peer {
 			id: info.id,
 			addr: info.address.to_string(),
+			relaytxes: info.version_message.relay_transactions() && !info.block_relay_only,
 			inbound: match info.direction {
 				Direction::Inbound => true,
 				Direction::Outbound => false,
 			},
+			connection_type: match info.direction {
+				Direction::Inbound => "inbound",
+				Direction::Outbound if info.block_relay_only => "block-relay-only",
+				Direction::Outbound => "outbound-full-relay",
+			}
+			.to_string(),
 			subver: info.user_agent.clone(),
 			version: info.version,
+			txreconciliation: info.txreconciliation.is_some(),
+			reconciliation_rounds: info.txreconciliation.map(|stats| stats.rounds).unwrap_or_default(),
+			reconciliation_failures: info.txreconciliation.map(|stats| stats.failures).unwrap_or_default(),
+			reconciliation_requested: info.txreconciliation.map(|stats| stats.requested).unwrap_or_default(),
+			bytessent: info.bytes_sent,
+			bytesrecv: info.bytes_recv,
+			bytessent_per_msg: bytes_per_msg(&info.bytes_sent_per_msg),
+			bytesrecv_per_msg: bytes_per_msg(&info.bytes_recv_per_msg),
 			..Default::default()
 		}
 	}
 }
 
+fn bytes_per_msg(per_msg: &BTreeMap<String, u64>) -> Vec<BytesPerMessage> {
+	per_msg.iter().map(|(msg, n)| BytesPerMessage { msg: msg.clone(), n: *n }).collect()
+}
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct Peer {
 	// Peer index
@@ -35,9 +55,9 @@ pub struct Peer {
 	// The time in seconds since epoch (Jan 1 1970 GMT) of the last receive
 	pub lastrecv: u32,
 	// The total bytes sent
-	pub bytessent: u32,
+	pub bytessent: u64,
 	// The total bytes received
-	pub bytesrecv: u32,
+	pub bytesrecv: u64,
 	// The connection time in seconds since epoch (Jan 1 1970 GMT)
 	pub conntime: u32,
 	// The time offset in seconds
@@ -54,6 +74,8 @@ pub struct Peer {
 	pub subver: String,
 	// Inbound (true) or Outbound (false)
 	pub inbound: bool,
+	// Type of the connection: inbound, outbound-full-relay or block-relay-only
+	pub connection_type: String,
 	// Whether connection was due to addnode/-connect or if it was an automatic/inbound connection
 	pub addnode: bool,
 	// The starting height (block) of the peer
@@ -70,6 +92,14 @@ pub struct Peer {
 	pub minfeefilter: u32,
 	// The minimum fee rate for transactions this peer accepts
 	pub whitelisted: bool,
+	// Whether transactions are announced to the peer using BIP330 set reconciliation
+	pub txreconciliation: bool,
+	// Number of finished transactions reconciliation rounds
+	pub reconciliation_rounds: u64,
+	// Number of reconciliation rounds, which have failed to find the difference
+	pub reconciliation_failures: u64,
+	// Number of transactions, requested by their short ids during reconciliation
+	pub reconciliation_requested: u64,
 	// Bytes sent per message. If message is not in list bytes sent is zero.
 	pub bytessent_per_msg: Vec<BytesPerMessage>,
 	// Bytes received per message. If message is not in list bytes sent is zero.
@@ -79,5 +109,5 @@ pub struct Peer {
 #[derive(Serialize, Deserialize, Default)]
 pub struct BytesPerMessage {
 	pub msg: String, // Message type
-	pub n: u32,      // Number of bytes sent
+	pub n: u64,      // Number of bytes sent
 }
@@ -0,0 +1,149 @@
+use super::bytes::Bytes;
+use super::script::ScriptType;
+use super::transaction::{RawTransaction, Transaction, TransactionInputScript};
+use std::collections::BTreeMap;
+
+/// Script with its type
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtScript {
+	/// Script code
+	pub asm: String,
+	/// Script hex
+	pub hex: Bytes,
+	/// Type of script
+	#[serde(rename = "type")]
+	pub script_type: ScriptType,
+}
+
+/// Output, spent by the PSBT input
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtUtxo {
+	/// Output value in BTC
+	pub amount: f64,
+	/// Output script
+	#[serde(rename = "scriptPubKey")]
+	pub script_pubkey: PsbtScript,
+}
+
+/// Origin of the public key
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtKeyOrigin {
+	/// Hex-encoded public key
+	pub pubkey: Bytes,
+	/// Fingerprint of the master key
+	pub master_fingerprint: Bytes,
+	/// Derivation path from the master key
+	pub path: String,
+}
+
+/// Decoded PSBT input
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DecodedPsbtInput {
+	/// Transaction, spent by the input
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub non_witness_utxo: Option<Transaction>,
+	/// Output, spent by the input
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witness_utxo: Option<PsbtUtxo>,
+	/// Hex-encoded signatures, keyed by hex-encoded public keys
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub partial_signatures: BTreeMap<String, Bytes>,
+	/// Signature hash type
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sighash: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redeem_script: Option<PsbtScript>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witness_script: Option<PsbtScript>,
+	/// Origins of public keys
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub bip32_derivs: Vec<PsbtKeyOrigin>,
+	#[serde(rename = "final_scriptSig", skip_serializing_if = "Option::is_none")]
+	pub final_script_sig: Option<TransactionInputScript>,
+	#[serde(rename = "final_scriptwitness", skip_serializing_if = "Option::is_none")]
+	pub final_script_witness: Option<Vec<Bytes>>,
+	/// Hex-encoded unknown values, keyed by hex-encoded keys
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub unknown: BTreeMap<String, Bytes>,
+}
+
+/// Decoded PSBT output
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DecodedPsbtOutput {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redeem_script: Option<PsbtScript>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witness_script: Option<PsbtScript>,
+	/// Origins of public keys
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub bip32_derivs: Vec<PsbtKeyOrigin>,
+	/// Hex-encoded unknown values, keyed by hex-encoded keys
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub unknown: BTreeMap<String, Bytes>,
+}
+
+/// Return value of `decodepsbt` method
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DecodePsbtResponse {
+	/// Unsigned transaction
+	pub tx: Transaction,
+	/// Version of the PSBT
+	pub psbt_version: u32,
+	/// Hex-encoded unknown global values, keyed by hex-encoded keys
+	#[serde(default)]
+	pub unknown: BTreeMap<String, Bytes>,
+	pub inputs: Vec<DecodedPsbtInput>,
+	pub outputs: Vec<DecodedPsbtOutput>,
+	/// Transaction fee in BTC, if all spent outputs are known
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<f64>,
+}
+
+/// Return value of `finalizepsbt` method
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FinalizePsbtResponse {
+	/// Base64-encoded PSBT, if transaction is not extracted
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub psbt: Option<String>,
+	/// Extracted transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub hex: Option<RawTransaction>,
+	/// All inputs are finalized
+	pub complete: bool,
+}
+
+/// Role, which has to process the PSBT next
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PsbtRole {
+	Creator,
+	Updater,
+	Signer,
+	Finalizer,
+	Extractor,
+}
+
+/// Analyzed PSBT input
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AnalyzedPsbtInput {
+	/// Output, spent by the input, is known
+	pub has_utxo: bool,
+	/// Input is finalized
+	pub is_final: bool,
+	/// Role, which has to process the input next
+	pub next: PsbtRole,
+}
+
+/// Return value of `analyzepsbt` method
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AnalyzePsbtResponse {
+	pub inputs: Vec<AnalyzedPsbtInput>,
+	/// Transaction fee in BTC, if all spent outputs are known
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<f64>,
+	/// Role, which has to process the PSBT next
+	pub next: PsbtRole,
+	/// Error, making the PSBT invalid
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
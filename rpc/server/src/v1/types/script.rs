@@ -13,6 +13,7 @@ pub enum ScriptType {
 	NullData,
 	WitnessScript,
 	WitnessKey,
+	WitnessTaproot,
 }
 
 impl From<GlobalScriptType> for ScriptType {
@@ -26,6 +27,7 @@ impl From<GlobalScriptType> for ScriptType {
 			GlobalScriptType::NullData => ScriptType::NullData,
 			GlobalScriptType::WitnessScript => ScriptType::WitnessScript,
 			GlobalScriptType::WitnessKey => ScriptType::WitnessKey,
+			GlobalScriptType::WitnessTaproot => ScriptType::WitnessTaproot,
 		}
 	}
 }
@@ -44,6 +46,7 @@ impl Serialize for ScriptType {
 			ScriptType::NullData => "nulldata".serialize(serializer),
 			ScriptType::WitnessScript => "witness_v0_scripthash".serialize(serializer),
 			ScriptType::WitnessKey => "witness_v0_keyhash".serialize(serializer),
+			ScriptType::WitnessTaproot => "witness_v1_taproot".serialize(serializer),
 		}
 	}
 }
@@ -77,6 +80,7 @@ impl<'a> Deserialize<'a> for ScriptType {
 					"nulldata" => Ok(ScriptType::NullData),
 					"witness_v0_scripthash" => Ok(ScriptType::WitnessScript),
 					"witness_v0_keyhash" => Ok(ScriptType::WitnessKey),
+					"witness_v1_taproot" => Ok(ScriptType::WitnessTaproot),
 					_ => Err(E::invalid_value(Unexpected::Str(value), &self)),
 				}
 			}
@@ -0,0 +1,91 @@
+use super::bytes::Bytes;
+use crate::v1::types;
+use bitcrypto::SHA256D;
+use keys::Address;
+
+/// Unspent output, owned by the wallet
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct WalletUnspent {
+	/// Hash of the transaction, containing the output
+	pub txid: SHA256D,
+	/// Index of the output in the transaction
+	pub vout: u32,
+	/// Address the output is locked by
+	#[serde(with = "types::address")]
+	pub address: Address,
+	/// Hex-encoded output script
+	#[serde(rename = "scriptPubKey")]
+	pub script_pubkey: Bytes,
+	/// Output value in BTC
+	pub amount: f64,
+	/// Number of confirmations
+	pub confirmations: u32,
+	/// Output descriptor of the wallet key
+	pub desc: String,
+	/// Wallet holds the key to spend this output
+	pub spendable: bool,
+}
+
+/// Category of the wallet transaction entry
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WalletTransactionCategory {
+	Send,
+	Receive,
+	Generate,
+	Immature,
+}
+
+/// Entry of the wallet transactions history
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct WalletTransactionEntry {
+	/// Address of the output
+	pub address: Option<String>,
+	/// Category of the entry
+	pub category: WalletTransactionCategory,
+	/// Amount in BTC. Negative for the `send` category
+	pub amount: f64,
+	/// Index of the output in the transaction
+	pub vout: u32,
+	/// Fee of the transaction in BTC. Only present (and negative) for the `send` category
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<f64>,
+	/// Number of confirmations
+	pub confirmations: u32,
+	/// Hash of the block, containing the transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blockhash: Option<SHA256D>,
+	/// Height of the block, containing the transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blockheight: Option<u32>,
+	/// Hash of the transaction
+	pub txid: SHA256D,
+	/// Time of the block, containing the transaction, or time the transaction has been added to the wallet
+	pub time: u32,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json;
+
+	#[test]
+	fn wallet_transaction_entry_serialize() {
+		let entry = WalletTransactionEntry {
+			address: Some("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into()),
+			category: WalletTransactionCategory::Send,
+			amount: -0.5,
+			vout: 1,
+			fee: Some(-0.0001),
+			confirmations: 0,
+			blockhash: None,
+			blockheight: None,
+			txid: SHA256D::default(),
+			time: 100,
+		};
+		assert_eq!(
+			serde_json::to_string(&entry).unwrap(),
+			r#"{"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","category":"send","amount":-0.5,"vout":1,"fee":-0.0001,"confirmations":0,"txid":"0000000000000000000000000000000000000000000000000000000000000000","time":100}"#
+		);
+	}
+}
@@ -2,7 +2,7 @@
 
 use crate::bytes::Bytes;
 use crate::{Num, Opcode, Script};
-use keys::AddressHash;
+use keys::{Address, AddressHash, Payload, Type};
 
 /// Script builder
 #[derive(Default)]
@@ -31,6 +31,49 @@ impl Builder {
 			.into_script()
 	}
 
+	/// Builds p2wpkh script pubkey
+	pub fn build_p2wpkh(address: &AddressHash) -> Script {
+		Builder::default().push_opcode(Opcode::OP_0).push_bytes(address).into_script()
+	}
+
+	/// Builds p2wsh script pubkey
+	pub fn build_p2wsh(program: &[u8; 32]) -> Script {
+		Builder::default().push_opcode(Opcode::OP_0).push_bytes(program).into_script()
+	}
+
+	/// Builds p2tr script pubkey
+	pub fn build_p2tr(output_key: &[u8; 32]) -> Script {
+		Builder::default().push_opcode(Opcode::OP_1).push_bytes(output_key).into_script()
+	}
+
+	/// Builds script pubkey paying to witness program of given version
+	pub fn build_witness_program(version: u8, program: &[u8]) -> Script {
+		let version = match version {
+			0 => Opcode::OP_0,
+			version => Opcode::from_u8(Opcode::OP_1 as u8 + version - 1).expect("witness version is lower than 17; qed"),
+		};
+		Builder::default().push_opcode(version).push_bytes(program).into_script()
+	}
+
+	/// Builds script pubkey paying to given address.
+	/// Fails if address payload does not match address type, see `Address::is_valid`.
+	pub fn build_address(address: &Address) -> Result<Script, keys::Error> {
+		if !address.is_valid() {
+			return Err(keys::Error::InvalidAddress);
+		}
+
+		let script = match (address.kind, &address.payload) {
+			(Type::P2PKH, Payload::Hash(hash)) => Builder::build_p2pkh(hash),
+			(Type::P2SH, Payload::Hash(hash)) => Builder::build_p2sh(hash),
+			(Type::P2WPKH, Payload::Hash(hash)) => Builder::build_p2wpkh(hash),
+			(Type::P2WSH, Payload::WitnessProgram(program)) => Builder::build_p2wsh(program),
+			(Type::P2TR, Payload::WitnessProgram(program)) => Builder::build_p2tr(program),
+			(Type::WitnessUnknown(version), Payload::UnknownWitnessProgram(program)) => Builder::build_witness_program(version, program),
+			_ => return Err(keys::Error::InvalidAddress),
+		};
+		Ok(script)
+	}
+
 	/// Builds op_return script
 	pub fn build_nulldata(bytes: &[u8]) -> Script {
 		Builder::default().push_opcode(Opcode::OP_RETURN).push_bytes(bytes).into_script()
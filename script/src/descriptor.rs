@@ -0,0 +1,1077 @@
+//! Output script descriptors: language, describing sets of output scripts.
+//! https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki
+//!
+//! Supported script expressions are `pk`, `pkh`, `wpkh`, `sh`, `wsh`, `multi`, `sortedmulti` (BIP381-383),
+//! `tr` with optional script tree (BIP386), `addr` and `raw` (BIP385). Keys are either hex-encoded public keys,
+//! WIF private keys or extended keys with derivation path, optionally ending with a wildcard (`/*`).
+
+use crate::bytes::Bytes;
+use crate::interpreter::{compute_tapleaf_hash, compute_taproot_tweak, TAPROOT_LEAF_TAPSCRIPT};
+use crate::script::{MAX_PUBKEYS_PER_MULTISIG, MAX_SCRIPT_ELEMENT_SIZE};
+use crate::{Builder, Num, Opcode, Script, ScriptType};
+use bitcrypto::{dhash160, sha256, tagged_hash, FromHex, Hash, ToHex, SHA256};
+use keys::{
+	Address, ChildNumber, DerivationPath, ExtendedKeyFormat, ExtendedPrivate, ExtendedPublic, Fingerprint, KeyPair, Network, Private,
+	Public, XOnlyPublic,
+};
+use std::fmt;
+use std::str::FromStr;
+
+/// Characters, allowed in descriptors. Position of the character is used by the checksum.
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+/// Characters of the checksum
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+/// Number of characters in the checksum
+const CHECKSUM_LENGTH: usize = 8;
+/// Bare multisig is limited to 3 keys by the standardness rules
+const MAX_BARE_MULTISIG_KEYS: usize = 3;
+/// Maximal depth of the taproot script tree (BIP341)
+const MAX_TAPROOT_TREE_DEPTH: usize = 128;
+
+#[derive(Debug, PartialEq)]
+pub enum DescriptorError {
+	/// Character is not allowed in descriptors
+	InvalidCharacter(char),
+	InvalidChecksum,
+	MissingChecksum,
+	/// Unbalanced brackets or unexpected number of arguments
+	InvalidSyntax,
+	UnknownFunction(String),
+	/// Function is not allowed at this place, e.g. `wpkh` inside of `wsh`
+	UnexpectedFunction(String),
+	InvalidKey,
+	InvalidKeyOrigin,
+	InvalidDerivationPath,
+	/// Uncompressed keys are not allowed in segwit scripts
+	UncompressedKey,
+	/// Multisig threshold is zero or greater than the number of keys
+	InvalidThreshold,
+	TooManyKeys,
+	/// P2SH redeem script is larger than 520 bytes
+	ScriptSize,
+	InvalidAddress,
+	InvalidHex,
+	/// Hardened child can't be derived from the public key
+	HardenedDerivation,
+	/// Described script has no address form
+	NoAddress,
+	Key(keys::Error),
+}
+
+impl fmt::Display for DescriptorError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DescriptorError::InvalidCharacter(c) => write!(f, "Invalid character {:?}", c),
+			DescriptorError::InvalidChecksum => "Invalid checksum".fmt(f),
+			DescriptorError::MissingChecksum => "Missing checksum".fmt(f),
+			DescriptorError::InvalidSyntax => "Invalid syntax".fmt(f),
+			DescriptorError::UnknownFunction(ref name) => write!(f, "Unknown function {}", name),
+			DescriptorError::UnexpectedFunction(ref name) => write!(f, "Function {} is not allowed here", name),
+			DescriptorError::InvalidKey => "Invalid key".fmt(f),
+			DescriptorError::InvalidKeyOrigin => "Invalid key origin".fmt(f),
+			DescriptorError::InvalidDerivationPath => "Invalid derivation path".fmt(f),
+			DescriptorError::UncompressedKey => "Uncompressed keys are not allowed".fmt(f),
+			DescriptorError::InvalidThreshold => "Invalid multisig threshold".fmt(f),
+			DescriptorError::TooManyKeys => "Too many keys".fmt(f),
+			DescriptorError::ScriptSize => "P2SH script is too large".fmt(f),
+			DescriptorError::InvalidAddress => "Invalid address".fmt(f),
+			DescriptorError::InvalidHex => "Invalid hex".fmt(f),
+			DescriptorError::HardenedDerivation => "Hardened derivation from public key".fmt(f),
+			DescriptorError::NoAddress => "Descriptor has no address".fmt(f),
+			DescriptorError::Key(ref e) => e.fmt(f),
+		}
+	}
+}
+
+impl std::error::Error for DescriptorError {}
+
+impl From<keys::Error> for DescriptorError {
+	fn from(e: keys::Error) -> Self {
+		DescriptorError::Key(e)
+	}
+}
+
+/// Place of the expression, which limits allowed functions and keys
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Context {
+	Top,
+	P2sh,
+	/// Witness v0 script, compressed keys only
+	P2wsh,
+	/// Taproot internal key or tapscript leaf, x-only keys are allowed
+	Tapscript,
+}
+
+/// Derivation step after the last explicit child
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Wildcard {
+	None,
+	Normal,
+	Hardened,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExtendedKey {
+	Public(ExtendedPublic),
+	Private(ExtendedPrivate),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+	/// Public key, optionally with its private key. X-only keys are displayed without the parity byte.
+	Single {
+		public: Public,
+		private: Option<Private>,
+		x_only: bool,
+	},
+	Extended {
+		key: ExtendedKey,
+		path: Vec<ChildNumber>,
+		wildcard: Wildcard,
+	},
+}
+
+/// Key expression with optional origin `[fingerprint/path]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescriptorKey {
+	origin: Option<(Fingerprint, DerivationPath)>,
+	key: Key,
+}
+
+impl DescriptorKey {
+	/// Single public key without origin. X-only key is displayed without the parity byte.
+	pub fn from_public(public: Public, x_only: bool) -> Self {
+		DescriptorKey {
+			origin: None,
+			key: Key::Single {
+				public,
+				private: None,
+				x_only,
+			},
+		}
+	}
+
+	/// Fingerprint of the master key and derivation path from it
+	pub fn origin(&self) -> Option<&(Fingerprint, DerivationPath)> {
+		self.origin.as_ref()
+	}
+
+	/// Key ends with the wildcard, so it describes different keys at different indexes
+	pub fn is_range(&self) -> bool {
+		match self.key {
+			Key::Extended { wildcard, .. } => wildcard != Wildcard::None,
+			Key::Single { .. } => false,
+		}
+	}
+
+	pub fn has_private_key(&self) -> bool {
+		match self.key {
+			Key::Single { ref private, .. } => private.is_some(),
+			Key::Extended { ref key, .. } => matches!(*key, ExtendedKey::Private(_)),
+		}
+	}
+
+	/// Derives public key at the index. Index is ignored if the key is not ranged.
+	pub fn derive(&self, index: u32) -> Result<Public, DescriptorError> {
+		let (key, path, wildcard) = match self.key {
+			Key::Single { ref public, .. } => return Ok(public.clone()),
+			Key::Extended {
+				ref key,
+				ref path,
+				wildcard,
+			} => (key, path, wildcard),
+		};
+
+		let mut children = path.clone();
+		match wildcard {
+			Wildcard::None => (),
+			Wildcard::Normal => children.push(ChildNumber::normal(index)?),
+			Wildcard::Hardened => children.push(ChildNumber::hardened(index)?),
+		}
+		let path = DerivationPath::new(children);
+		match *key {
+			ExtendedKey::Private(ref key) => Ok(key.derive_path(&path)?.extended_public().public()),
+			ExtendedKey::Public(_) if path.children().iter().any(ChildNumber::is_hardened) => Err(DescriptorError::HardenedDerivation),
+			ExtendedKey::Public(ref key) => Ok(key.derive_path(&path)?.public()),
+		}
+	}
+
+	fn derive_x_only(&self, index: u32) -> Result<XOnlyPublic, DescriptorError> {
+		Ok(self.derive(index)?.x_only()?)
+	}
+
+	fn is_compressed(&self) -> bool {
+		match self.key {
+			Key::Single { ref public, .. } => matches!(*public, Public::Compressed(_)),
+			Key::Extended { .. } => true,
+		}
+	}
+}
+
+impl fmt::Display for DescriptorKey {
+	/// Displays public form of the key
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if let Some((ref fingerprint, ref path)) = self.origin {
+			write!(f, "[{}", fingerprint.to_hex())?;
+			for child in path.children() {
+				write!(f, "/{}", child)?;
+			}
+			"]".fmt(f)?;
+		}
+
+		match self.key {
+			Key::Single { ref public, x_only, .. } if x_only => public[1..].to_hex().fmt(f),
+			Key::Single { ref public, .. } => public.fmt(f),
+			Key::Extended {
+				ref key,
+				ref path,
+				wildcard,
+			} => {
+				match *key {
+					ExtendedKey::Public(ref key) => key.fmt(f)?,
+					ExtendedKey::Private(ref key) => key.extended_public().fmt(f)?,
+				}
+				for child in path {
+					write!(f, "/{}", child)?;
+				}
+				match wildcard {
+					Wildcard::None => Ok(()),
+					Wildcard::Normal => "/*".fmt(f),
+					Wildcard::Hardened => "/*'".fmt(f),
+				}
+			}
+		}
+	}
+}
+
+/// Taproot script tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum TapTree {
+	Leaf(Box<Descriptor>),
+	Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+	/// Merkle root of the tree at the index
+	fn hash(&self, index: u32) -> Result<SHA256, DescriptorError> {
+		match *self {
+			TapTree::Leaf(ref leaf) => Ok(compute_tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &leaf.tapscript(index)?)),
+			TapTree::Branch(ref left, ref right) => {
+				let (left, right) = (left.hash(index)?, right.hash(index)?);
+				// branch children are sorted to make the tree commitment independent of the order
+				let (first, second) = if left[..] < right[..] { (left, right) } else { (right, left) };
+				let mut branch = first.to_vec();
+				branch.extend_from_slice(&second[..]);
+				Ok(tagged_hash("TapBranch", &branch))
+			}
+		}
+	}
+
+	fn keys<'a>(&'a self, keys: &mut Vec<&'a DescriptorKey>) {
+		match *self {
+			TapTree::Leaf(ref leaf) => leaf.collect_keys(keys),
+			TapTree::Branch(ref left, ref right) => {
+				left.keys(keys);
+				right.keys(keys);
+			}
+		}
+	}
+
+	fn body(&self) -> String {
+		match *self {
+			TapTree::Leaf(ref leaf) => leaf.body(),
+			TapTree::Branch(ref left, ref right) => format!("{{{},{}}}", left.body(), right.body()),
+		}
+	}
+}
+
+/// Parsed output script descriptor
+#[derive(Debug, Clone, PartialEq)]
+pub enum Descriptor {
+	/// `pk(KEY)`: P2PK script
+	Pk(DescriptorKey),
+	/// `pkh(KEY)`: P2PKH script
+	Pkh(DescriptorKey),
+	/// `wpkh(KEY)`: P2WPKH script
+	Wpkh(DescriptorKey),
+	/// `sh(SCRIPT)`: P2SH script, wrapping inner script
+	Sh(Box<Descriptor>),
+	/// `wsh(SCRIPT)`: P2WSH script, wrapping inner script
+	Wsh(Box<Descriptor>),
+	/// `multi(k,KEY,...)` and `sortedmulti(k,KEY,...)`: k-of-n multisig script
+	Multi {
+		threshold: usize,
+		keys: Vec<DescriptorKey>,
+		sorted: bool,
+	},
+	/// `tr(KEY)` and `tr(KEY,TREE)`: P2TR script with internal key and optional script tree
+	Tr {
+		internal_key: DescriptorKey,
+		tree: Option<TapTree>,
+	},
+	/// `addr(ADDR)`: script, paying to the address
+	Addr(Address),
+	/// `raw(HEX)`: script as is
+	Raw(Bytes),
+}
+
+impl Descriptor {
+	/// Parses descriptor, checking its checksum (if present)
+	pub fn parse(s: &str, require_checksum: bool) -> Result<Self, DescriptorError> {
+		let (body, checksum) = match s.find('#') {
+			Some(position) => (&s[..position], Some(&s[position + 1..])),
+			None => (s, None),
+		};
+
+		// also makes sure that the descriptor consists of allowed characters
+		let expected_checksum = descriptor_checksum(body)?;
+		match checksum {
+			Some(checksum) if checksum != expected_checksum => Err(DescriptorError::InvalidChecksum),
+			None if require_checksum => Err(DescriptorError::MissingChecksum),
+			_ => parse_script(body, Context::Top),
+		}
+	}
+
+	/// Some of keys end with the wildcard, so the descriptor describes different scripts at different indexes
+	pub fn is_range(&self) -> bool {
+		self.keys().iter().any(|key| key.is_range())
+	}
+
+	/// All information, required to spend the described outputs (except for private keys) is known
+	pub fn is_solvable(&self) -> bool {
+		!matches!(*self, Descriptor::Addr(_) | Descriptor::Raw(_))
+	}
+
+	pub fn has_private_keys(&self) -> bool {
+		self.keys().iter().any(|key| key.has_private_key())
+	}
+
+	/// All key expressions of the descriptor
+	pub fn keys(&self) -> Vec<&DescriptorKey> {
+		let mut keys = Vec::new();
+		self.collect_keys(&mut keys);
+		keys
+	}
+
+	/// Derives script pubkey at the index. Index is ignored if the descriptor is not ranged.
+	pub fn script_pubkey(&self, index: u32) -> Result<Script, DescriptorError> {
+		let script = match *self {
+			Descriptor::Pk(ref key) => Builder::default()
+				.push_data(&key.derive(index)?)
+				.push_opcode(Opcode::OP_CHECKSIG)
+				.into_script(),
+			Descriptor::Pkh(ref key) => Builder::build_p2pkh(&key.derive(index)?.address_hash()),
+			Descriptor::Wpkh(ref key) => Builder::build_p2wpkh(&key.derive(index)?.address_hash()),
+			Descriptor::Sh(ref inner) => Builder::build_p2sh(&dhash160(&inner.script_pubkey(index)?)),
+			Descriptor::Wsh(ref inner) => Builder::build_p2wsh(&sha256(&inner.script_pubkey(index)?).into_inner()),
+			Descriptor::Multi {
+				threshold,
+				ref keys,
+				sorted,
+			} => {
+				let mut publics = keys.iter().map(|key| key.derive(index)).collect::<Result<Vec<_>, _>>()?;
+				if sorted {
+					publics.sort_by(|a, b| a[..].cmp(&b[..]));
+				}
+				let builder = publics
+					.iter()
+					.fold(push_small_number(Builder::default(), threshold), |builder, public| {
+						builder.push_data(public)
+					});
+				push_small_number(builder, publics.len())
+					.push_opcode(Opcode::OP_CHECKMULTISIG)
+					.into_script()
+			}
+			Descriptor::Tr {
+				ref internal_key,
+				ref tree,
+			} => {
+				let internal_key = internal_key.derive_x_only(index)?;
+				let merkle_root = match *tree {
+					Some(ref tree) => Some(tree.hash(index)?),
+					None => None,
+				};
+				let (output_key, _) = internal_key.tweak_add(&compute_taproot_tweak(&internal_key, merkle_root.as_ref())[..])?;
+				let mut program = [0u8; 32];
+				program.copy_from_slice(&output_key);
+				Builder::build_p2tr(&program)
+			}
+			Descriptor::Addr(ref address) => Builder::build_address(address)?,
+			Descriptor::Raw(ref bytes) => Script::new(bytes.clone()),
+		};
+		Ok(script)
+	}
+
+	/// Derives address at the index. Bare `pk` and `multi` descriptors (and non-standard raw scripts) have no address.
+	pub fn address(&self, network: Network, index: u32) -> Result<Address, DescriptorError> {
+		let script = self.script_pubkey(index)?;
+		match script.script_type() {
+			ScriptType::PubKeyHash
+			| ScriptType::ScriptHash
+			| ScriptType::WitnessKey
+			| ScriptType::WitnessScript
+			| ScriptType::WitnessTaproot => (),
+			_ => return Err(DescriptorError::NoAddress),
+		}
+
+		let destination = script.extract_destinations()?.pop().ok_or(DescriptorError::NoAddress)?;
+		Ok(Address {
+			kind: destination.kind,
+			network,
+			payload: destination.payload,
+		})
+	}
+
+	/// Script of the tapscript leaf
+	fn tapscript(&self, index: u32) -> Result<Script, DescriptorError> {
+		match *self {
+			Descriptor::Pk(ref key) => Ok(Builder::default()
+				.push_bytes(&key.derive_x_only(index)?)
+				.push_opcode(Opcode::OP_CHECKSIG)
+				.into_script()),
+			Descriptor::Pkh(ref key) => Ok(Builder::build_p2pkh(&dhash160(&key.derive_x_only(index)?))),
+			_ => unreachable!("only pk and pkh are parsed in tapscript context; qed"),
+		}
+	}
+
+	fn collect_keys<'a>(&'a self, keys: &mut Vec<&'a DescriptorKey>) {
+		match *self {
+			Descriptor::Pk(ref key) | Descriptor::Pkh(ref key) | Descriptor::Wpkh(ref key) => keys.push(key),
+			Descriptor::Sh(ref inner) | Descriptor::Wsh(ref inner) => inner.collect_keys(keys),
+			Descriptor::Multi { keys: ref multi_keys, .. } => keys.extend(multi_keys),
+			Descriptor::Tr {
+				ref internal_key,
+				ref tree,
+			} => {
+				keys.push(internal_key);
+				if let Some(ref tree) = *tree {
+					tree.keys(keys);
+				}
+			}
+			Descriptor::Addr(_) | Descriptor::Raw(_) => (),
+		}
+	}
+
+	/// Public form of the descriptor without checksum
+	fn body(&self) -> String {
+		match *self {
+			Descriptor::Pk(ref key) => format!("pk({})", key),
+			Descriptor::Pkh(ref key) => format!("pkh({})", key),
+			Descriptor::Wpkh(ref key) => format!("wpkh({})", key),
+			Descriptor::Sh(ref inner) => format!("sh({})", inner.body()),
+			Descriptor::Wsh(ref inner) => format!("wsh({})", inner.body()),
+			Descriptor::Multi {
+				threshold,
+				ref keys,
+				sorted,
+			} => {
+				let name = if sorted { "sortedmulti" } else { "multi" };
+				let keys = keys.iter().map(|key| format!(",{}", key)).collect::<String>();
+				format!("{}({}{})", name, threshold, keys)
+			}
+			Descriptor::Tr {
+				ref internal_key,
+				tree: None,
+			} => format!("tr({})", internal_key),
+			Descriptor::Tr {
+				ref internal_key,
+				tree: Some(ref tree),
+			} => format!("tr({},{})", internal_key, tree.body()),
+			Descriptor::Addr(ref address) => format!("addr({})", address),
+			Descriptor::Raw(ref bytes) => format!("raw({})", bytes[..].to_hex()),
+		}
+	}
+}
+
+impl fmt::Display for Descriptor {
+	/// Displays public form of the descriptor with checksum
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let body = self.body();
+		let checksum = descriptor_checksum(&body).expect("descriptor is displayed using allowed characters; qed");
+		write!(f, "{}#{}", body, checksum)
+	}
+}
+
+impl FromStr for Descriptor {
+	type Err = DescriptorError;
+
+	/// Parses descriptor with optional checksum
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Descriptor::parse(s, false)
+	}
+}
+
+impl From<&'static str> for Descriptor {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+/// Computes 8 characters long checksum of the descriptor (without `#` and checksum)
+pub fn descriptor_checksum(descriptor: &str) -> Result<String, DescriptorError> {
+	fn polymod(c: u64, value: u64) -> u64 {
+		let c0 = c >> 35;
+		let mut c = ((c & 0x7_ffff_ffff) << 5) ^ value;
+		for (bit, generator) in [0xf5_dee5_1989, 0xa9_fdca_3312, 0x1b_ab10_e32d, 0x37_06b1_677a, 0x64_4d62_6ffd]
+			.iter()
+			.enumerate()
+		{
+			if c0 & (1 << bit) != 0 {
+				c ^= generator;
+			}
+		}
+		c
+	}
+
+	let mut c = 1;
+	// every character is split into 5 low bits and class of the character, three classes are packed together
+	let mut classes = 0;
+	let mut classes_count = 0;
+	for ch in descriptor.chars() {
+		let position = INPUT_CHARSET.find(ch).ok_or(DescriptorError::InvalidCharacter(ch))? as u64;
+		c = polymod(c, position & 31);
+		classes = classes * 3 + (position >> 5);
+		classes_count += 1;
+		if classes_count == 3 {
+			c = polymod(c, classes);
+			classes = 0;
+			classes_count = 0;
+		}
+	}
+	if classes_count > 0 {
+		c = polymod(c, classes);
+	}
+	for _ in 0..CHECKSUM_LENGTH {
+		c = polymod(c, 0);
+	}
+	c ^= 1;
+
+	Ok((0..CHECKSUM_LENGTH)
+		.map(|i| CHECKSUM_CHARSET[((c >> (5 * (7 - i))) & 31) as usize] as char)
+		.collect())
+}
+
+fn push_small_number(builder: Builder, number: usize) -> Builder {
+	match number {
+		1..=16 => builder.push_opcode(Opcode::from_u8(Opcode::OP_1 as u8 + number as u8 - 1).expect("OP_1..OP_16 are valid opcodes; qed")),
+		_ => builder.push_num(Num::from(number)),
+	}
+}
+
+/// Splits `name(arguments)` expression
+fn split_function(s: &str) -> Result<(&str, &str), DescriptorError> {
+	let open = s.find('(').ok_or(DescriptorError::InvalidSyntax)?;
+	if !s.ends_with(')') {
+		return Err(DescriptorError::InvalidSyntax);
+	}
+	Ok((&s[..open], &s[open + 1..s.len() - 1]))
+}
+
+/// Splits arguments by top-level commas
+fn split_arguments(s: &str) -> Result<Vec<&str>, DescriptorError> {
+	let mut arguments = Vec::new();
+	let mut depth = 0usize;
+	let mut start = 0;
+	for (position, ch) in s.char_indices() {
+		match ch {
+			'(' | '[' | '{' => depth += 1,
+			')' | ']' | '}' => depth = depth.checked_sub(1).ok_or(DescriptorError::InvalidSyntax)?,
+			',' if depth == 0 => {
+				arguments.push(&s[start..position]);
+				start = position + 1;
+			}
+			_ => (),
+		}
+	}
+	if depth != 0 {
+		return Err(DescriptorError::InvalidSyntax);
+	}
+	arguments.push(&s[start..]);
+	Ok(arguments)
+}
+
+fn single_argument(s: &str) -> Result<&str, DescriptorError> {
+	match split_arguments(s)?.as_slice() {
+		[argument] => Ok(argument),
+		_ => Err(DescriptorError::InvalidSyntax),
+	}
+}
+
+fn parse_script(s: &str, context: Context) -> Result<Descriptor, DescriptorError> {
+	let (name, arguments) = split_function(s)?;
+	match name {
+		"pk" => Ok(Descriptor::Pk(parse_key(single_argument(arguments)?, context)?)),
+		"pkh" => Ok(Descriptor::Pkh(parse_key(single_argument(arguments)?, context)?)),
+		"wpkh" if context == Context::Top || context == Context::P2sh => {
+			Ok(Descriptor::Wpkh(parse_key(single_argument(arguments)?, Context::P2wsh)?))
+		}
+		"sh" if context == Context::Top => Ok(Descriptor::Sh(Box::new(parse_script(single_argument(arguments)?, Context::P2sh)?))),
+		"wsh" if context == Context::Top || context == Context::P2sh => Ok(Descriptor::Wsh(Box::new(parse_script(
+			single_argument(arguments)?,
+			Context::P2wsh,
+		)?))),
+		"multi" | "sortedmulti" if context != Context::Tapscript => parse_multi(arguments, context, name == "sortedmulti"),
+		"tr" if context == Context::Top => {
+			let arguments = split_arguments(arguments)?;
+			let tree = match arguments.len() {
+				1 => None,
+				2 => Some(parse_tree(arguments[1], 0)?),
+				_ => return Err(DescriptorError::InvalidSyntax),
+			};
+			Ok(Descriptor::Tr {
+				internal_key: parse_key(arguments[0], Context::Tapscript)?,
+				tree,
+			})
+		}
+		"addr" if context == Context::Top => single_argument(arguments)?
+			.parse()
+			.map(Descriptor::Addr)
+			.map_err(|_| DescriptorError::InvalidAddress),
+		"raw" if context == Context::Top => {
+			let bytes: Vec<u8> = FromHex::from_hex(single_argument(arguments)?).map_err(|_| DescriptorError::InvalidHex)?;
+			Ok(Descriptor::Raw(bytes.into()))
+		}
+		"wpkh" | "sh" | "wsh" | "multi" | "sortedmulti" | "tr" | "addr" | "raw" => Err(DescriptorError::UnexpectedFunction(name.into())),
+		_ => Err(DescriptorError::UnknownFunction(name.into())),
+	}
+}
+
+fn parse_multi(arguments: &str, context: Context, sorted: bool) -> Result<Descriptor, DescriptorError> {
+	let arguments = split_arguments(arguments)?;
+	// usize::from_str accepts leading `+`
+	if !arguments[0].bytes().all(|b| b.is_ascii_digit()) {
+		return Err(DescriptorError::InvalidThreshold);
+	}
+	let threshold: usize = arguments[0].parse().map_err(|_| DescriptorError::InvalidThreshold)?;
+	let keys = arguments[1..]
+		.iter()
+		.map(|key| parse_key(key, context))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let max_keys = match context {
+		Context::Top => MAX_BARE_MULTISIG_KEYS,
+		_ => MAX_PUBKEYS_PER_MULTISIG,
+	};
+	if keys.len() > max_keys {
+		return Err(DescriptorError::TooManyKeys);
+	}
+	if threshold == 0 || threshold > keys.len() {
+		return Err(DescriptorError::InvalidThreshold);
+	}
+	if context == Context::P2sh {
+		// threshold, keys count and OP_CHECKMULTISIG take 1 byte each
+		let script_size = 3 + keys.iter().map(|key| if key.is_compressed() { 34 } else { 66 }).sum::<usize>();
+		if script_size > MAX_SCRIPT_ELEMENT_SIZE {
+			return Err(DescriptorError::ScriptSize);
+		}
+	}
+
+	Ok(Descriptor::Multi { threshold, keys, sorted })
+}
+
+fn parse_tree(s: &str, depth: usize) -> Result<TapTree, DescriptorError> {
+	if depth > MAX_TAPROOT_TREE_DEPTH {
+		return Err(DescriptorError::InvalidSyntax);
+	}
+
+	match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+		Some(branch) => match split_arguments(branch)?.as_slice() {
+			[left, right] => Ok(TapTree::Branch(
+				Box::new(parse_tree(left, depth + 1)?),
+				Box::new(parse_tree(right, depth + 1)?),
+			)),
+			_ => Err(DescriptorError::InvalidSyntax),
+		},
+		None => {
+			let leaf = parse_script(s, Context::Tapscript)?;
+			match leaf {
+				Descriptor::Pk(_) | Descriptor::Pkh(_) => Ok(TapTree::Leaf(Box::new(leaf))),
+				_ => Err(DescriptorError::InvalidSyntax),
+			}
+		}
+	}
+}
+
+fn parse_child(s: &str) -> Result<ChildNumber, DescriptorError> {
+	s.parse().map_err(|_| DescriptorError::InvalidDerivationPath)
+}
+
+fn parse_key(s: &str, context: Context) -> Result<DescriptorKey, DescriptorError> {
+	let (origin, s) = match s.strip_prefix('[') {
+		Some(s) => {
+			let end = s.find(']').ok_or(DescriptorError::InvalidKeyOrigin)?;
+			let mut parts = s[..end].split('/');
+			let fingerprint: Vec<u8> = parts
+				.next()
+				.and_then(|fingerprint| FromHex::from_hex(fingerprint).ok())
+				.filter(|fingerprint: &Vec<u8>| fingerprint.len() == 4)
+				.ok_or(DescriptorError::InvalidKeyOrigin)?;
+			let path = parts.map(parse_child).collect::<Result<Vec<_>, _>>()?;
+			let mut origin = Fingerprint::default();
+			origin.copy_from_slice(&fingerprint);
+			(Some((origin, DerivationPath::new(path))), &s[end + 1..])
+		}
+		None => (None, s),
+	};
+
+	let mut parts = s.split('/');
+	let key = parts.next().unwrap_or_default();
+	let mut path = Vec::new();
+	let mut wildcard = Wildcard::None;
+	for part in parts {
+		if wildcard != Wildcard::None {
+			return Err(DescriptorError::InvalidDerivationPath);
+		}
+		match part {
+			"*" => wildcard = Wildcard::Normal,
+			"*'" | "*h" => wildcard = Wildcard::Hardened,
+			_ => path.push(parse_child(part)?),
+		}
+	}
+
+	let has_path = !path.is_empty() || wildcard != Wildcard::None;
+	let key = if let Ok(bytes) = Vec::<u8>::from_hex(key) {
+		let (public, x_only) = match bytes.len() {
+			32 if context == Context::Tapscript => {
+				let x_only = XOnlyPublic::from_slice(&bytes)?;
+				let mut public = vec![2u8];
+				public.extend_from_slice(&x_only);
+				(Public::from_slice(&public)?, true)
+			}
+			33 | 65 => {
+				let public = Public::from_slice(&bytes)?;
+				// makes sure that the point is on the curve
+				public.x_only()?;
+				(public, false)
+			}
+			_ => return Err(DescriptorError::InvalidKey),
+		};
+		Key::Single {
+			public,
+			private: None,
+			x_only,
+		}
+	} else if let Ok(extended) = key.parse::<ExtendedPublic>() {
+		if extended.format() != ExtendedKeyFormat::Standard {
+			return Err(DescriptorError::InvalidKey);
+		}
+		Key::Extended {
+			key: ExtendedKey::Public(extended),
+			path,
+			wildcard,
+		}
+	} else if let Ok(extended) = key.parse::<ExtendedPrivate>() {
+		if extended.format() != ExtendedKeyFormat::Standard {
+			return Err(DescriptorError::InvalidKey);
+		}
+		Key::Extended {
+			key: ExtendedKey::Private(extended),
+			path,
+			wildcard,
+		}
+	} else if let Ok(private) = key.parse::<Private>() {
+		let public = KeyPair::from_private(private.clone())?.public().clone();
+		Key::Single {
+			public,
+			private: Some(private),
+			x_only: context == Context::Tapscript,
+		}
+	} else {
+		return Err(DescriptorError::InvalidKey);
+	};
+
+	if let Key::Single { .. } = key {
+		if has_path {
+			return Err(DescriptorError::InvalidDerivationPath);
+		}
+	}
+
+	let key = DescriptorKey { origin, key };
+	if !key.is_compressed() && (context == Context::P2wsh || context == Context::Tapscript) {
+		return Err(DescriptorError::UncompressedKey);
+	}
+	Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{descriptor_checksum, Descriptor, DescriptorError, TapTree};
+	use crate::interpreter::{compute_tapleaf_hash, verify_taproot_commitment, TAPROOT_LEAF_TAPSCRIPT};
+	use bitcrypto::{FromHex, ToHex};
+	use keys::{ChildNumber, DerivationPath, ExtendedPrivate, ExtendedPublic, Network};
+
+	const XPRV: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+	const XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+
+	fn script_hex(descriptor: &str) -> String {
+		Descriptor::parse(descriptor, false).unwrap().script_pubkey(0).unwrap()[..].to_hex()
+	}
+
+	/// Test vectors from https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki#test-vectors
+	#[test]
+	fn test_descriptor_checksum() {
+		assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+		assert_eq!(descriptor_checksum("addr(mkmZxiEcEd8ZqjQWVZuC6so5dFMKEFpN2j)").unwrap(), "02wpgw69");
+		assert_eq!(
+			descriptor_checksum("raw(deadbeef)\u{e9}"),
+			Err(DescriptorError::InvalidCharacter('\u{e9}'))
+		);
+
+		assert!(Descriptor::parse("raw(deadbeef)#89f8spxm", true).is_ok());
+		assert_eq!(Descriptor::parse("raw(deadbeef)", true), Err(DescriptorError::MissingChecksum));
+		assert_eq!(Descriptor::parse("raw(deadbeef)#", false), Err(DescriptorError::InvalidChecksum));
+		assert_eq!(
+			Descriptor::parse("raw(deadbeef)#89f8spxmx", false),
+			Err(DescriptorError::InvalidChecksum)
+		);
+		assert_eq!(
+			Descriptor::parse("raw(deadbeef)#89f8spxn", false),
+			Err(DescriptorError::InvalidChecksum)
+		);
+		assert_eq!(
+			Descriptor::parse("raw(deedbeef)#89f8spxm", false),
+			Err(DescriptorError::InvalidChecksum)
+		);
+		assert_eq!(Descriptor::from("raw(deadbeef)").to_string(), "raw(deadbeef)#89f8spxm");
+	}
+
+	/// Test vectors from BIP381-383
+	#[test]
+	fn test_key_descriptors() {
+		let pk = "2103a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bdac";
+		assert_eq!(script_hex("pk(L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1)"), pk);
+		assert_eq!(
+			script_hex("pk(03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)"),
+			pk
+		);
+		assert_eq!(
+			script_hex("pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)"),
+			"76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac"
+		);
+		assert_eq!(
+			script_hex("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)"),
+			"00147dd65592d0ab2fe0d0257d571abf032cd9db93dc"
+		);
+		assert_eq!(
+			script_hex("sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))"),
+			"a914cc6ffbc0bf31af759451068f90ba7a0272b6b33287"
+		);
+		assert_eq!(
+			script_hex("multi(1,L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1,5KYZdUEo39z3FPrtuX2QbbwGnNP5zTd7yyr2SC1j299sBCnWjss)"),
+			"512103a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd4104a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd5b8dec5235a0fa8722476c7709c02559e3aa73aa03918ba2d492eea75abea23552ae"
+		);
+
+		let descriptor = Descriptor::from("pk(L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1)");
+		assert!(descriptor.has_private_keys());
+		assert!(!descriptor.is_range());
+		assert_eq!(
+			descriptor.to_string(),
+			Descriptor::from("pk(03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)").to_string()
+		);
+		assert_eq!(descriptor.address(Network::Mainnet, 0), Err(DescriptorError::NoAddress));
+	}
+
+	#[test]
+	fn test_sortedmulti_descriptor() {
+		let keys = [
+			"03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+			"02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+		];
+		let multi = script_hex(&format!("wsh(multi(2,{},{}))", keys[1], keys[0]));
+		assert_eq!(script_hex(&format!("wsh(sortedmulti(2,{},{}))", keys[0], keys[1])), multi);
+		assert_ne!(script_hex(&format!("wsh(multi(2,{},{}))", keys[0], keys[1])), multi);
+		assert_eq!(
+			script_hex(&format!("multi(2,{},{})", keys[1], keys[0])),
+			format!("5221{}21{}52ae", keys[1], keys[0])
+		);
+	}
+
+	#[test]
+	fn test_extended_key_descriptors() {
+		let master = ExtendedPrivate::from(XPRV);
+		let child = |path: &str| {
+			master
+				.derive_path(&path.parse::<DerivationPath>().unwrap())
+				.unwrap()
+				.extended_public()
+				.public()
+		};
+
+		let descriptor = Descriptor::parse(&format!("wpkh([d34db33f/84'/0'/0']{}/1/*)", XPUB), false).unwrap();
+		assert!(descriptor.is_range());
+		assert!(!descriptor.has_private_keys());
+		assert!(descriptor.is_solvable());
+		for index in 0..3 {
+			let expected = child(&format!("m/1/{}", index)).address_hash();
+			assert_eq!(descriptor.script_pubkey(index).unwrap(), crate::Builder::build_p2wpkh(&expected));
+		}
+		assert_eq!(
+			descriptor.keys()[0].origin().unwrap().1,
+			DerivationPath::new(vec![ChildNumber::Hardened(84), ChildNumber::Hardened(0), ChildNumber::Hardened(0)])
+		);
+
+		// private keys are replaced with public keys and hardened markers are normalized
+		let private = Descriptor::parse(&format!("pkh([d34db33f/44h/0h/0h]{}/0h/*')", XPRV), false).unwrap();
+		assert!(private.has_private_keys());
+		let public = format!("pkh([d34db33f/44'/0'/0']{}/0'/*')", XPUB);
+		assert_eq!(private.to_string(), format!("{}#{}", public, descriptor_checksum(&public).unwrap()));
+		assert_eq!(
+			private.script_pubkey(5).unwrap(),
+			crate::Builder::build_p2pkh(&child("m/0'/5'").address_hash())
+		);
+
+		// hardened steps can't be derived from the public form
+		let public = Descriptor::parse(&public, false).unwrap();
+		assert_eq!(public.script_pubkey(5), Err(DescriptorError::HardenedDerivation));
+		assert_eq!(
+			Descriptor::parse(&format!("pkh({}/*/1)", XPUB), false),
+			Err(DescriptorError::InvalidDerivationPath)
+		);
+		assert_eq!(
+			ExtendedPublic::from(XPUB).derive_path(&"m/1/0".into()).unwrap().public(),
+			child("m/1/0")
+		);
+	}
+
+	/// Test vector from https://github.com/bitcoin/bips/blob/master/bip-0386.mediawiki#test-vectors
+	/// and first receiving address from https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+	#[test]
+	fn test_taproot_descriptors() {
+		assert_eq!(
+			script_hex("tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)"),
+			"512077aab6e066f8a7419c5ab714c12c67d25007ed55a43cadcacb4d7a970a093f11"
+		);
+		assert_eq!(
+			script_hex("tr(L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1)"),
+			"512077aab6e066f8a7419c5ab714c12c67d25007ed55a43cadcacb4d7a970a093f11"
+		);
+		let descriptor = Descriptor::from("tr(cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115)");
+		assert_eq!(
+			descriptor.address(Network::Mainnet, 0).unwrap().to_string(),
+			"bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+		);
+		assert_eq!(
+			Descriptor::parse("pkh(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)", false),
+			Err(DescriptorError::InvalidKey)
+		);
+	}
+
+	#[test]
+	fn test_taproot_script_tree() {
+		let internal = "a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd";
+		let left = "pk(669b8afcec803a0d323e9a17f3ea8e68e8abe5a278020a929adbec52421adbd0)";
+		let right = "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)";
+		let descriptor = Descriptor::parse(&format!("tr({},{{{},{}}})", internal, left, right), false).unwrap();
+		let (left_script, right_script) = match descriptor {
+			Descriptor::Tr {
+				tree: Some(TapTree::Branch(ref left, ref right)),
+				..
+			} => match (&**left, &**right) {
+				(TapTree::Leaf(left), TapTree::Leaf(right)) => (left.tapscript(0).unwrap(), right.tapscript(0).unwrap()),
+				_ => panic!("both branches are leaves"),
+			},
+			_ => panic!("descriptor has script tree"),
+		};
+		assert_eq!(
+			left_script[..].to_hex(),
+			"20669b8afcec803a0d323e9a17f3ea8e68e8abe5a278020a929adbec52421adbd0ac"
+		);
+
+		// the output key commits to the left leaf with the right leaf hash as the merkle path
+		let script_pubkey = descriptor.script_pubkey(0).unwrap();
+		let leaf_hash = compute_tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &left_script);
+		let sibling_hash = compute_tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &right_script);
+		let committed = [0u8, 1].iter().any(|parity| {
+			let mut control = vec![TAPROOT_LEAF_TAPSCRIPT | parity];
+			control.extend_from_slice(&Vec::<u8>::from_hex(internal).unwrap());
+			control.extend_from_slice(&sibling_hash[..]);
+			verify_taproot_commitment(&control, &script_pubkey[2..], &leaf_hash)
+		});
+		assert!(committed);
+
+		assert_eq!(
+			descriptor.to_string(),
+			format!(
+				"tr({},{{{},{}}})#{}",
+				internal,
+				left,
+				right,
+				descriptor_checksum(&format!("tr({},{{{},{}}})", internal, left, right)).unwrap()
+			)
+		);
+	}
+
+	#[test]
+	fn test_nested_descriptors() {
+		let key = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+		let descriptor = Descriptor::parse(&format!("sh(wsh(pkh({})))", key), false).unwrap();
+		let inner = Descriptor::parse(&format!("wsh(pkh({}))", key), false).unwrap();
+		assert_eq!(
+			descriptor.script_pubkey(0).unwrap(),
+			crate::Builder::build_p2sh(&bitcrypto::dhash160(&inner.script_pubkey(0).unwrap()))
+		);
+		assert_eq!(
+			descriptor.address(Network::Mainnet, 0).unwrap().to_string().chars().next(),
+			Some('3')
+		);
+		assert!(inner.address(Network::Mainnet, 0).unwrap().to_string().starts_with("bc1q"));
+
+		let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+		let descriptor = Descriptor::parse(&format!("addr({})", address), false).unwrap();
+		assert!(!descriptor.is_solvable());
+		assert_eq!(descriptor.address(Network::Mainnet, 0).unwrap().to_string(), address);
+		assert_eq!(
+			Descriptor::from("raw(76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac)")
+				.address(Network::Mainnet, 0)
+				.unwrap()
+				.to_string(),
+			"1cMh228HTCiwS8ZsaakH8A8wze1JR5ZsP"
+		);
+	}
+
+	#[test]
+	fn test_invalid_descriptors() {
+		let key = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+		let uncompressed = "5KYZdUEo39z3FPrtuX2QbbwGnNP5zTd7yyr2SC1j299sBCnWjss";
+		let parse = |s: String| Descriptor::parse(&s, false).err();
+		assert_eq!(parse(format!("pk({}", key)), Some(DescriptorError::InvalidSyntax));
+		assert_eq!(parse(format!("pk({},{})", key, key)), Some(DescriptorError::InvalidSyntax));
+		assert_eq!(parse(format!("foo({})", key)), Some(DescriptorError::UnknownFunction("foo".into())));
+		assert_eq!(
+			parse(format!("wsh(wpkh({}))", key)),
+			Some(DescriptorError::UnexpectedFunction("wpkh".into()))
+		);
+		assert_eq!(
+			parse(format!("sh(sh(pk({})))", key)),
+			Some(DescriptorError::UnexpectedFunction("sh".into()))
+		);
+		assert_eq!(
+			parse(format!("tr({},multi(1,{}))", key, key)),
+			Some(DescriptorError::UnexpectedFunction("multi".into()))
+		);
+		assert_eq!(parse(format!("wpkh({})", uncompressed)), Some(DescriptorError::UncompressedKey));
+		assert_eq!(parse(format!("wsh(pk({}))", uncompressed)), Some(DescriptorError::UncompressedKey));
+		assert_eq!(parse(format!("multi(0,{})", key)), Some(DescriptorError::InvalidThreshold));
+		assert_eq!(parse(format!("multi(2,{})", key)), Some(DescriptorError::InvalidThreshold));
+		assert_eq!(parse(format!("multi(+1,{})", key)), Some(DescriptorError::InvalidThreshold));
+		assert_eq!(
+			parse(format!("multi(1,{},{},{},{})", key, key, key, key)),
+			Some(DescriptorError::TooManyKeys)
+		);
+		assert_eq!(
+			parse(format!("sh(multi(1{}))", format!(",{}", uncompressed).repeat(8))),
+			Some(DescriptorError::ScriptSize)
+		);
+		assert_eq!(
+			parse(format!("pk([d34db33f/44']{}/0)", key)),
+			Some(DescriptorError::InvalidDerivationPath)
+		);
+		assert_eq!(parse(format!("pk([d34db3/44']{})", key)), Some(DescriptorError::InvalidKeyOrigin));
+		assert_eq!(parse("pk(03a34b)".into()), Some(DescriptorError::InvalidKey));
+		assert_eq!(
+			parse("addr(1cMh228HTCiwS8ZsaakH8A8wze1JR5Zs)".into()),
+			Some(DescriptorError::InvalidAddress)
+		);
+		assert_eq!(parse("raw(0)".into()), Some(DescriptorError::InvalidHex));
+	}
+}
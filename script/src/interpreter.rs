@@ -1,12 +1,40 @@
 use crate::bytes::Bytes;
 use crate::script::MAX_SCRIPT_ELEMENT_SIZE;
-use crate::sign::{Sighash, SignatureVersion};
+use crate::sign::{ScriptExecutionData, Sighash, SignatureVersion};
 use crate::{script, Builder, Error, Num, Opcode, Script, ScriptWitness, SignatureChecker, Stack, VerificationFlags};
 use chain::constants::SEQUENCE_LOCKTIME_DISABLE_FLAG;
-use crypto::{dhash160, dhash256, ripemd160, sha1, sha256, Hash, SHA256};
-use keys::{Public, Signature};
+use crypto::{dhash160, dhash256, ripemd160, sha1, sha256, tagged_hash, Hash, SHA256};
+use keys::{Public, SchnorrSignature, Signature, XOnlyPublic};
+use ser::{serialized_list_size, Stream};
 use std::{cmp, mem};
 
+/// Maximum number of elements on the stack and altstack combined
+const MAX_STACK_SIZE: usize = 1000;
+
+/// First byte of the last witness stack element, which marks it as an annex (BIP341)
+const ANNEX_TAG: u8 = 0x50;
+
+/// Leaf version of tapscript (BIP342)
+pub(crate) const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+
+/// Mask to extract leaf version from the first byte of the control block
+const TAPROOT_LEAF_MASK: u8 = 0xfe;
+
+/// Size of control block without any merkle path nodes: leaf version and internal key
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+
+/// Size of single merkle path node in the control block
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+
+/// Maximal depth of taproot script tree
+const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+/// Validation weight consumed by each executed signature check in tapscript
+const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+
+/// Validation weight available in addition to the witness size
+const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
 /// Helper function.
 fn check_signature(
 	checker: &dyn SignatureChecker,
@@ -27,6 +55,63 @@ fn check_signature(
 	}
 }
 
+/// Checks BIP340 signature, optionally followed by hash type byte.
+fn check_schnorr_signature(
+	checker: &dyn SignatureChecker,
+	signature: &[u8],
+	public: &[u8],
+	version: SignatureVersion,
+	execdata: &ScriptExecutionData,
+) -> Result<(), Error> {
+	let (signature, sighashtype) = match signature.len() {
+		64 => (signature, 0),
+		// SIGHASH_DEFAULT must not be explicitly specified
+		65 if signature[64] == 0 => return Err(Error::SchnorrSigHashtype),
+		65 => (&signature[..64], signature[64] as u32),
+		_ => return Err(Error::SchnorrSigSize),
+	};
+
+	let public = XOnlyPublic::from_slice(public).map_err(|_| Error::SchnorrSig)?;
+	let signature = SchnorrSignature::from_slice(signature).map_err(|_| Error::SchnorrSig)?;
+	checker.check_schnorr_signature(&signature, &public, sighashtype, version, execdata)
+}
+
+/// Signature check of OP_CHECKSIG, OP_CHECKSIGVERIFY and OP_CHECKSIGADD in tapscript (BIP342).
+fn eval_checksig_tapscript(
+	checker: &dyn SignatureChecker,
+	signature: &[u8],
+	public: &[u8],
+	flags: &VerificationFlags,
+	version: SignatureVersion,
+	execdata: &mut ScriptExecutionData,
+) -> Result<bool, Error> {
+	// empty signature is the only way to fail signature check without failing the script
+	let success = !signature.is_empty();
+	if success {
+		execdata.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+		if execdata.validation_weight_left < 0 {
+			return Err(Error::TapscriptValidationWeight);
+		}
+	}
+
+	match public.len() {
+		0 => return Err(Error::PubkeyType),
+		32 => {
+			if success {
+				check_schnorr_signature(checker, signature, public, version, execdata)?;
+			}
+		}
+		// unknown public key types are reserved for upgrades
+		_ => {
+			if flags.verify_discourage_upgradable_pubkeytype {
+				return Err(Error::DiscourageUpgradablePubkeyType);
+			}
+		}
+	}
+
+	Ok(success)
+}
+
 fn is_public_key(v: &[u8]) -> bool {
 	match v.len() {
 		33 if v[0] == 2 || v[0] == 3 => true,
@@ -259,7 +344,7 @@ pub fn verify_script(
 
 			had_witness = true;
 			verify_cleanstack = false;
-			if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+			if !verify_witness_program(witness, witness_version, witness_program, flags, checker, false)? {
 				return Err(Error::EvalFalse);
 			}
 		}
@@ -293,7 +378,7 @@ pub fn verify_script(
 
 				had_witness = true;
 				verify_cleanstack = false;
-				if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+				if !verify_witness_program(witness, witness_version, witness_program, flags, checker, true)? {
 					return Err(Error::EvalFalse);
 				}
 			}
@@ -331,7 +416,16 @@ fn verify_witness_program(
 	witness_program: &[u8],
 	flags: &VerificationFlags,
 	checker: &dyn SignatureChecker,
+	is_p2sh: bool,
 ) -> Result<bool, Error> {
+	if witness_version == 1 && witness_program.len() == 32 && !is_p2sh {
+		if !flags.verify_taproot {
+			return Ok(true);
+		}
+
+		return verify_taproot_program(witness, witness_program, flags, checker);
+	}
+
 	if witness_version != 0 {
 		if flags.verify_discourage_upgradable_witness_program {
 			return Err(Error::DiscourageUpgradableWitnessProgram);
@@ -376,11 +470,112 @@ fn verify_witness_program(
 		_ => return Err(Error::WitnessProgramWrongLength),
 	};
 
+	let mut execdata = ScriptExecutionData::default();
+	execute_witness_script(
+		&mut stack,
+		&script_pubkey,
+		flags,
+		checker,
+		SignatureVersion::WitnessV0,
+		&mut execdata,
+	)
+}
+
+/// Verifies spending of witness v1 program (BIP341)
+fn verify_taproot_program(
+	witness: &ScriptWitness,
+	witness_program: &[u8],
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+) -> Result<bool, Error> {
+	let mut witness_stack = witness.clone();
+	if witness_stack.is_empty() {
+		return Err(Error::WitnessProgramWitnessEmpty);
+	}
+
+	let mut execdata = ScriptExecutionData::default();
+	if witness_stack.len() >= 2 && witness_stack.last().and_then(|annex| annex.first()) == Some(&ANNEX_TAG) {
+		let annex = witness_stack.pop().expect("witness_stack.len() >= 2; qed");
+		let mut stream = Stream::default();
+		stream.append(&annex);
+		execdata.annex_hash = Some(sha256(&stream.out()));
+	}
+
+	if witness_stack.len() == 1 {
+		// key path spending
+		check_schnorr_signature(checker, &witness_stack[0], witness_program, SignatureVersion::Taproot, &execdata)?;
+		return Ok(true);
+	}
+
+	// script path spending
+	let control = witness_stack.pop().expect("witness_stack.len() >= 2; qed");
+	let script = witness_stack.pop().expect("witness_stack.len() >= 2; qed");
+	if control.len() < TAPROOT_CONTROL_BASE_SIZE
+		|| control.len() > TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_MAX_NODE_COUNT * TAPROOT_CONTROL_NODE_SIZE
+		|| !(control.len() - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+	{
+		return Err(Error::TaprootWrongControlSize);
+	}
+
+	let leaf_version = control[0] & TAPROOT_LEAF_MASK;
+	let tapleaf_hash = compute_tapleaf_hash(leaf_version, &script);
+	if !verify_taproot_commitment(&control, witness_program, &tapleaf_hash) {
+		return Err(Error::WitnessProgramMismatch);
+	}
+	execdata.tapleaf_hash = Some(tapleaf_hash);
+
+	if leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+		// unknown leaf versions are reserved for upgrades
+		if flags.verify_discourage_upgradable_taproot_version {
+			return Err(Error::DiscourageUpgradableTaprootVersion);
+		}
+
+		return Ok(true);
+	}
+
+	let script = Script::new(script);
+	// OP_SUCCESSx processing overrides everything, including stack element size limits
+	let mut pc = 0;
+	while pc < script.len() {
+		if Opcode::is_success(script[pc]) {
+			if flags.verify_discourage_op_success {
+				return Err(Error::DiscourageOpSuccess);
+			}
+
+			return Ok(true);
+		}
+
+		match script.get_instruction(pc) {
+			Ok(instruction) => pc += instruction.step,
+			// OP_INVALIDOPCODE is the only opcode which is neither known nor OP_SUCCESSx
+			Err(Error::BadOpcode) if Opcode::from_u8(script[pc]).is_none() => pc += 1,
+			Err(err) => return Err(err),
+		}
+	}
+
+	if witness_stack.len() > MAX_STACK_SIZE {
+		return Err(Error::StackSize);
+	}
+
+	execdata.validation_weight_left = serialized_list_size::<Bytes, Bytes>(witness) as i64 + VALIDATION_WEIGHT_OFFSET;
+	let mut stack = witness_stack.into();
+	execute_witness_script(&mut stack, &script, flags, checker, SignatureVersion::Tapscript, &mut execdata)
+}
+
+/// Executes witness script, which must leave exactly one element on the stack
+fn execute_witness_script(
+	stack: &mut Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	execdata: &mut ScriptExecutionData,
+) -> Result<bool, Error> {
 	if stack.iter().any(|s| s.len() > MAX_SCRIPT_ELEMENT_SIZE) {
 		return Err(Error::PushSize);
 	}
 
-	if !eval_script(&mut stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0)? {
+	if !eval_script_with_execdata(stack, script, flags, checker, version, execdata)? {
 		return Ok(false);
 	}
 
@@ -396,8 +591,49 @@ fn verify_witness_program(
 	Ok(success)
 }
 
+pub(crate) fn compute_tapleaf_hash(leaf_version: u8, script: &[u8]) -> SHA256 {
+	let mut stream = Stream::default();
+	stream.append(&leaf_version);
+	stream.append_list(script);
+	tagged_hash("TapLeaf", &stream.out())
+}
+
+/// Checks that the witness program commits to the executed leaf, using merkle path from the control block
+pub(crate) fn verify_taproot_commitment(control: &[u8], witness_program: &[u8], tapleaf_hash: &SHA256) -> bool {
+	let (internal, output) = match (XOnlyPublic::from_slice(&control[1..33]), XOnlyPublic::from_slice(witness_program)) {
+		(Ok(internal), Ok(output)) => (internal, output),
+		_ => return false,
+	};
+
+	let mut node_hash = *tapleaf_hash;
+	for node in control[TAPROOT_CONTROL_BASE_SIZE..].chunks(TAPROOT_CONTROL_NODE_SIZE) {
+		// branch children are sorted to make the tree commitment independent of the order
+		let mut branch = Vec::with_capacity(64);
+		if &node_hash[..] < node {
+			branch.extend_from_slice(&node_hash[..]);
+			branch.extend_from_slice(node);
+		} else {
+			branch.extend_from_slice(node);
+			branch.extend_from_slice(&node_hash[..]);
+		}
+		node_hash = tagged_hash("TapBranch", &branch);
+	}
+
+	let tweak = compute_taproot_tweak(&internal, Some(&node_hash));
+	internal.is_tweaked(&output, control[0] & 1 == 1, tweak.into_inner())
+}
+
+/// Computes tweak of the taproot internal key, committing to the script tree with given merkle root (BIP341).
+/// Key without script path (BIP86) commits to the empty script tree.
+pub fn compute_taproot_tweak(internal_key: &XOnlyPublic, merkle_root: Option<&SHA256>) -> SHA256 {
+	let mut tweak = internal_key.to_vec();
+	if let Some(merkle_root) = merkle_root {
+		tweak.extend_from_slice(&merkle_root[..]);
+	}
+	tagged_hash("TapTweak", &tweak)
+}
+
 /// Evaluautes the script
-#[cfg_attr(feature = "cargo-clippy", allow(match_same_arms))]
 pub fn eval_script(
 	stack: &mut Stack<Bytes>,
 	script: &Script,
@@ -405,17 +641,35 @@ pub fn eval_script(
 	checker: &dyn SignatureChecker,
 	version: SignatureVersion,
 ) -> Result<bool, Error> {
-	if script.len() > script::MAX_SCRIPT_SIZE {
+	eval_script_with_execdata(stack, script, flags, checker, version, &mut ScriptExecutionData::default())
+}
+
+/// Evaluautes the script, updating taproot execution data (executed OP_CODESEPARATOR and validation weight)
+#[cfg_attr(feature = "cargo-clippy", allow(match_same_arms))]
+fn eval_script_with_execdata(
+	stack: &mut Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	execdata: &mut ScriptExecutionData,
+) -> Result<bool, Error> {
+	// tapscript has no script size and opcodes count limits
+	let is_tapscript = version == SignatureVersion::Tapscript;
+	if !is_tapscript && script.len() > script::MAX_SCRIPT_SIZE {
 		return Err(Error::ScriptSize);
 	}
 
 	let mut pc = 0;
 	let mut op_count = 0;
+	let mut opcode_pos = 0u32;
 	let mut begincode = 0;
 	let mut exec_stack = Vec::<bool>::new();
 	let mut altstack = Stack::<Bytes>::new();
 
 	while pc < script.len() {
+		let current_opcode_pos = opcode_pos;
+		opcode_pos += 1;
 		let executing = exec_stack.iter().all(|x| *x);
 		let instruction = match script.get_instruction(pc) {
 			Ok(i) => i,
@@ -437,7 +691,7 @@ pub fn eval_script(
 			}
 		}
 
-		if opcode.is_countable() {
+		if !is_tapscript && opcode.is_countable() {
 			op_count += 1;
 			if op_count > script::MAX_OPS_PER_SCRIPT {
 				return Err(Error::OpCount);
@@ -749,7 +1003,11 @@ pub fn eval_script(
 			Opcode::OP_IF | Opcode::OP_NOTIF => {
 				let mut exec_value = false;
 				if executing {
-					exec_value = cast_to_bool(&stack.pop().map_err(|_| Error::UnbalancedConditional)?);
+					let value = stack.pop().map_err(|_| Error::UnbalancedConditional)?;
+					if is_tapscript && (value.len() > 1 || (value.len() == 1 && value[0] != 1)) {
+						return Err(Error::TapscriptMinimalIf);
+					}
+					exec_value = cast_to_bool(&value);
 					if opcode == Opcode::OP_NOTIF {
 						exec_value = !exec_value;
 					}
@@ -998,23 +1256,28 @@ pub fn eval_script(
 			}
 			Opcode::OP_CODESEPARATOR => {
 				begincode = pc;
+				execdata.codeseparator_pos = current_opcode_pos;
 			}
 			Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY => {
 				let pubkey = stack.pop()?;
 				let signature = stack.pop()?;
-				let mut subscript = script.subscript(begincode);
-				match version {
-					SignatureVersion::WitnessV0 => (),
-					SignatureVersion::Base => {
-						let signature_script = Builder::default().push_data(&*signature).into_script();
-						subscript = subscript.find_and_delete(&*signature_script);
-					}
-				}
+				let success = match version {
+					SignatureVersion::Base | SignatureVersion::WitnessV0 => {
+						let mut subscript = script.subscript(begincode);
+						if version == SignatureVersion::Base {
+							let signature_script = Builder::default().push_data(&*signature).into_script();
+							subscript = subscript.find_and_delete(&*signature_script);
+						}
 
-				check_signature_encoding(&signature, flags)?;
-				check_pubkey_encoding(&pubkey, flags)?;
+						check_signature_encoding(&signature, flags)?;
+						check_pubkey_encoding(&pubkey, flags)?;
 
-				let success = check_signature(checker, &signature, &pubkey, &subscript, version);
+						check_signature(checker, &signature, &pubkey, &subscript, version)
+					}
+					SignatureVersion::Taproot | SignatureVersion::Tapscript => {
+						eval_checksig_tapscript(checker, &signature, &pubkey, flags, version, execdata)?
+					}
+				};
 				match opcode {
 					Opcode::OP_CHECKSIG => {
 						if success {
@@ -1029,7 +1292,26 @@ pub fn eval_script(
 					_ => {}
 				}
 			}
+			Opcode::OP_CHECKSIGADD => {
+				if !is_tapscript {
+					return Err(Error::BadOpcode);
+				}
+
+				if stack.len() < 3 {
+					return Err(Error::InvalidStackOperation);
+				}
+
+				let pubkey = stack.pop()?;
+				let n = Num::from_slice(&stack.pop()?, flags.verify_minimaldata, 4)?;
+				let signature = stack.pop()?;
+				let success = eval_checksig_tapscript(checker, &signature, &pubkey, flags, version, execdata)?;
+				stack.push((n + Num::from(success)).to_bytes());
+			}
 			Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY => {
+				if is_tapscript {
+					return Err(Error::TapscriptCheckMultisig);
+				}
+
 				let keys_count = Num::from_slice(&stack.pop()?, flags.verify_minimaldata, 4)?;
 				if keys_count < 0.into() || keys_count > script::MAX_PUBKEYS_PER_MULTISIG.into() {
 					return Err(Error::PubkeyCount);
@@ -1050,7 +1332,7 @@ pub fn eval_script(
 
 				for signature in &sigs {
 					match version {
-						SignatureVersion::WitnessV0 => (),
+						SignatureVersion::WitnessV0 | SignatureVersion::Taproot | SignatureVersion::Tapscript => (),
 						SignatureVersion::Base => {
 							let signature_script = Builder::default().push_data(&*signature).into_script();
 							subscript = subscript.find_and_delete(&*signature_script);
@@ -1105,7 +1387,7 @@ pub fn eval_script(
 			}
 		}
 
-		if stack.len() + altstack.len() > 1000 {
+		if stack.len() + altstack.len() > MAX_STACK_SIZE {
 			return Err(Error::StackSize);
 		}
 	}
@@ -1124,10 +1406,10 @@ pub fn eval_script(
 
 #[cfg(test)]
 mod tests {
-	use super::{eval_script, is_public_key, verify_script};
+	use super::{compute_tapleaf_hash, compute_taproot_tweak, eval_script, is_public_key, verify_script, TAPROOT_LEAF_TAPSCRIPT};
 	use crate::bytes::Bytes;
 	use crate::script::MAX_SCRIPT_ELEMENT_SIZE;
-	use crate::sign::SignatureVersion;
+	use crate::sign::{ScriptExecutionData, SignatureVersion};
 	use crate::{
 		Builder, Error, NoopSignatureChecker, Num, Opcode, Script, ScriptWitness, Stack, TransactionInputSigner,
 		TransactionSignatureChecker, VerificationFlags,
@@ -1996,6 +2278,7 @@ mod tests {
 			signer,
 			input_index: 0,
 			input_amount: 0,
+			spent_outputs: vec![],
 		};
 		let input: Script = "47304402202cb265bf10707bf49346c3515dd3d16fc454618c58ec0a0ff448a676c54ff71302206c6624d762a1fcef4618284ead8f08678ac05b13c84235f1654e6ad168233e8201410414e301b2328f17442c0b8310d787bf3d8a404cfbd0704f135b6ad4b2d3ee751310f981926e53a6e8c39bd7d3fefd576c543cce493cbac06388f2651d1aacbfcd".into();
 		let output: Script = "76a914df3bd30160e6c6145baaf2c88a8844c13a00d1d588ac".into();
@@ -2015,6 +2298,7 @@ mod tests {
 			signer,
 			input_index: 0,
 			input_amount: 0,
+			spent_outputs: vec![],
 		};
 		let input: Script = "00483045022100deeb1f13b5927b5e32d877f3c42a4b028e2e0ce5010fdb4e7f7b5e2921c1dcd2022068631cb285e8c1be9f061d2968a18c3163b780656f30a049effee640e80d9bff01483045022100ee80e164622c64507d243bd949217d666d8b16486e153ac6a1f8e04c351b71a502203691bef46236ca2b4f5e60a82a853a33d6712d6a1e7bf9a65e575aeb7328db8c014cc9524104a882d414e478039cd5b52a92ffb13dd5e6bd4515497439dffd691a0f12af9575fa349b5694ed3155b136f09e63975a1700c9f4d4df849323dac06cf3bd6458cd41046ce31db9bdd543e72fe3039a1f1c047dab87037c36a669ff90e28da1848f640de68c2fe913d363a51154a0c62d7adea1b822d05035077418267b1a1379790187410411ffd36c70776538d079fbae117dc38effafb33304af83ce4894589747aee1ef992f63280567f52f5ba870678b4ab4ff6c8ea600bd217870a8b4f1f09f3a8e8353ae".into();
 		let output: Script = "a9141a8b0026343166625c7475f01e48b5ede8c0252e87".into();
@@ -2034,6 +2318,7 @@ mod tests {
 			signer,
 			input_index: 0,
 			input_amount: 0,
+			spent_outputs: vec![],
 		};
 		let input: Script = "483045022052ffc1929a2d8bd365c6a2a4e3421711b4b1e1b8781698ca9075807b4227abcb0221009984107ddb9e3813782b095d0d84361ed4c76e5edaf6561d252ae162c2341cfb01".into();
 		let output: Script =
@@ -2055,6 +2340,7 @@ mod tests {
 			signer,
 			input_index: 0,
 			input_amount: 0,
+			spent_outputs: vec![],
 		};
 		let input: Script = "4b3048022200002b83d59c1d23c08efd82ee0662fec23309c3adbcbd1f0b8695378db4b14e736602220000334a96676e58b1bb01784cb7c556dd8ce1c220171904da22e18fe1e7d1510db5014104d0fe07ff74c9ef5b00fed1104fad43ecf72dbab9e60733e4f56eacf24b20cf3b8cd945bcabcc73ba0158bf9ce769d43e94bd58c5c7e331a188922b3fe9ca1f5a".into();
 		let output: Script = "76a9147a2a3b481ca80c4ba7939c54d9278e50189d94f988ac".into();
@@ -2074,6 +2360,7 @@ mod tests {
 			signer,
 			input_index: 1,
 			input_amount: 0,
+			spent_outputs: vec![],
 		};
 		let input: Script = "004730440220276d6dad3defa37b5f81add3992d510d2f44a317fd85e04f93a1e2daea64660202200f862a0da684249322ceb8ed842fb8c859c0cb94c81e1c5308b4868157a428ee01ab51210232abdc893e7f0631364d7fd01cb33d24da45329a00357b3a7886211ab414d55a51ae".into();
 		let output: Script = "142a9bc5447d664c1d0141392a842d23dba45c4f13b175".into();
@@ -2100,6 +2387,7 @@ mod tests {
 			signer,
 			input_index: 0,
 			input_amount: 0,
+			spent_outputs: vec![],
 		};
 		let input: Script = "483045022100d92e4b61452d91a473a43cde4b469a472467c0ba0cbd5ebba0834e4f4762810402204802b76b7783db57ac1f61d2992799810e173e91055938750815b6d8a675902e014f".into();
 		let output: Script = "76009f69905160a56b210378d430274f8c5ec1321338151e9f27f4c676a008bdf8638d07c0b6be9ab35c71ad6c".into();
@@ -2154,6 +2442,7 @@ mod tests {
 			signer,
 			input_index: 1,
 			input_amount: 0,
+			spent_outputs: vec![],
 		};
 		let input: Script = "00483045022015BD0139BCCCF990A6AF6EC5C1C52ED8222E03A0D51C334DF139968525D2FCD20221009F9EFE325476EB64C3958E4713E9EEFE49BF1D820ED58D2112721B134E2A1A53034930460221008431BDFA72BC67F9D41FE72E94C88FB8F359FFA30B33C72C121C5A877D922E1002210089EF5FC22DD8BFC6BF9FFDB01A9862D27687D424D1FEFBAB9E9C7176844A187A014C9052483045022015BD0139BCCCF990A6AF6EC5C1C52ED8222E03A0D51C334DF139968525D2FCD20221009F9EFE325476EB64C3958E4713E9EEFE49BF1D820ED58D2112721B134E2A1A5303210378D430274F8C5EC1321338151E9F27F4C676A008BDF8638D07C0B6BE9AB35C71210378D430274F8C5EC1321338151E9F27F4C676A008BDF8638D07C0B6BE9AB35C7153AE".into();
 		let output: Script = "A914D8DACDADB7462AE15CD906F1878706D0DA8660E687".into();
@@ -2210,6 +2499,7 @@ mod tests {
 		let checker = TransactionSignatureChecker {
 			input_index: 0,
 			input_amount: amount,
+			spent_outputs: vec![],
 			signer: tx2.into(),
 		};
 
@@ -2663,6 +2953,7 @@ mod tests {
 		let checker = TransactionSignatureChecker {
 			input_index: index,
 			input_amount: amount,
+			spent_outputs: vec![],
 			signer: tx.clone().into(),
 		};
 
@@ -4036,6 +4327,7 @@ mod tests {
 			signer,
 			input_index: 0,
 			input_amount: 1000000,
+			spent_outputs: vec![],
 		};
 		let input: Script = "473044022057e887c4cb773a6ec513b285dde1209ee4213209c21bb9da9e284ffe7477979302201aba367cf84bf2c6ccfd1b18d2bec0d705e2acacfeb42324cdc0fe63fbe2524a01483045022100e3f2e5e2a0b6bb75f2a506d7b190d8ba48b1e9108dd4fc4a740fbc921d0067a3022070fccd6eec2415d6d75f7aa3d0604988ee84d856db2acde4cc01d9c43f0237a301".into();
 		let output: Script = "5253877c5121027fe085933328a89d0ad069071dee3bd4c908fddc852032356a318324c9ab0f6c210321e7c9eea060c099747ddcf741e9498a2b90fe8f362e2c85370722df0f88d1782102a5bc779306b40927648e73e144d430dc1b7c0730f6a3ab5bbd130374d8fe4a5a53af2102a70faff961b367875336396076a72293bf3adaa084404f8a5cbec23f41645b87ac".into();
@@ -4045,4 +4337,378 @@ mod tests {
 			Ok(())
 		);
 	}
+
+	/// Private key 1, which public key is the generator point
+	const TAPROOT_PRIVATE: &'static str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+
+	fn taproot_checker(script_pubkey: &Script) -> TransactionSignatureChecker {
+		use chain::{OutPoint, TransactionInput, TransactionOutput};
+
+		let spent_output = TransactionOutput {
+			value: 1000,
+			script_pubkey: script_pubkey.to_bytes(),
+		};
+		let tx = Transaction {
+			version: 2,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint {
+					hash: Default::default(),
+					index: 0,
+				},
+				script_sig: Bytes::default(),
+				sequence: 0xffffffff,
+				script_witness: vec![],
+			}],
+			outputs: vec![TransactionOutput {
+				value: 900,
+				script_pubkey: Builder::default().into_bytes(),
+			}],
+			lock_time: 0,
+		};
+
+		TransactionSignatureChecker {
+			input_index: 0,
+			input_amount: spent_output.value,
+			spent_outputs: vec![spent_output],
+			signer: tx.into(),
+		}
+	}
+
+	fn taproot_sign(
+		checker: &TransactionSignatureChecker,
+		sighashtype: u8,
+		version: SignatureVersion,
+		execdata: &ScriptExecutionData,
+	) -> Bytes {
+		let private: keys::Private = TAPROOT_PRIVATE.into();
+		let hash = checker
+			.signer
+			.signature_hash_schnorr(0, &checker.spent_outputs, sighashtype as u32, version, execdata)
+			.unwrap();
+		let mut signature = private.sign_schnorr(&hash, &[0; 32]).unwrap().to_vec();
+		if sighashtype != 0 {
+			signature.push(sighashtype);
+		}
+		signature.into()
+	}
+
+	fn taproot_x_only() -> keys::XOnlyPublic {
+		let keypair = keys::KeyPair::from_private(TAPROOT_PRIVATE.into()).unwrap();
+		keypair.public().x_only().unwrap()
+	}
+
+	fn taproot_flags() -> VerificationFlags {
+		VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true)
+			.verify_taproot(true)
+	}
+
+	fn witness_v1_script(output_key: &[u8]) -> Script {
+		Builder::default().push_opcode(Opcode::OP_1).push_data(output_key).into_script()
+	}
+
+	/// Builds P2TR output with single tapscript leaf, returns (script_pubkey, control block)
+	fn tapscript_output(leaf_script: &Script) -> (Script, Bytes) {
+		use crypto::Hash;
+
+		let internal = taproot_x_only();
+		let leaf_hash = compute_tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, leaf_script);
+		let tweak = compute_taproot_tweak(&internal, Some(&leaf_hash));
+		let (output_key, parity) = internal.tweak_add(&tweak.into_inner()).unwrap();
+
+		let mut control = vec![TAPROOT_LEAF_TAPSCRIPT | parity as u8];
+		control.extend_from_slice(&internal);
+		(witness_v1_script(&output_key), control.into())
+	}
+
+	fn run_tapscript_test(leaf_script: &Script, witness: Vec<Bytes>, flags: VerificationFlags) -> Result<(), Error> {
+		let (script_pubkey, control) = tapscript_output(leaf_script);
+		let checker = taproot_checker(&script_pubkey);
+		let mut witness = witness;
+		witness.push(leaf_script.to_bytes());
+		witness.push(control);
+		verify_script(
+			&Script::new(Bytes::default()),
+			&script_pubkey,
+			&witness,
+			&flags,
+			&checker,
+			SignatureVersion::Base,
+		)
+	}
+
+	fn tapscript_signature(leaf_script: &Script) -> Bytes {
+		let (script_pubkey, _) = tapscript_output(leaf_script);
+		let checker = taproot_checker(&script_pubkey);
+		let execdata = ScriptExecutionData {
+			tapleaf_hash: Some(compute_tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, leaf_script)),
+			..Default::default()
+		};
+		taproot_sign(&checker, 0, SignatureVersion::Tapscript, &execdata)
+	}
+
+	#[test]
+	fn taproot_key_path_spend() {
+		let script_pubkey = witness_v1_script(&taproot_x_only());
+		let checker = taproot_checker(&script_pubkey);
+		let script_sig = Script::new(Bytes::default());
+		let execdata = ScriptExecutionData::default();
+		let flags = taproot_flags();
+
+		let signature = taproot_sign(&checker, 0, SignatureVersion::Taproot, &execdata);
+		let result = verify_script(
+			&script_sig,
+			&script_pubkey,
+			&vec![signature.clone()],
+			&flags,
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Ok(()));
+
+		let signature_all = taproot_sign(&checker, 0x01, SignatureVersion::Taproot, &execdata);
+		let result = verify_script(
+			&script_sig,
+			&script_pubkey,
+			&vec![signature_all],
+			&flags,
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Ok(()));
+
+		let mut explicit_default = signature.to_vec();
+		explicit_default.push(0);
+		let result = verify_script(
+			&script_sig,
+			&script_pubkey,
+			&vec![explicit_default.into()],
+			&flags,
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Err(Error::SchnorrSigHashtype));
+
+		let mut invalid = signature.to_vec();
+		invalid[10] ^= 1;
+		let result = verify_script(
+			&script_sig,
+			&script_pubkey,
+			&vec![invalid.into()],
+			&flags,
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Err(Error::SchnorrSig));
+
+		let result = verify_script(
+			&script_sig,
+			&script_pubkey,
+			&vec![vec![0; 63].into()],
+			&flags,
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Err(Error::SchnorrSigSize));
+	}
+
+	#[test]
+	fn taproot_key_path_spend_with_annex() {
+		let script_pubkey = witness_v1_script(&taproot_x_only());
+		let checker = taproot_checker(&script_pubkey);
+		let annex: Bytes = vec![0x50, 0x01, 0x02].into();
+		let mut stream = ser::Stream::default();
+		stream.append(&annex);
+		let execdata = ScriptExecutionData {
+			annex_hash: Some(crypto::sha256(&stream.out())),
+			..Default::default()
+		};
+		let signature = taproot_sign(&checker, 0, SignatureVersion::Taproot, &execdata);
+		let no_annex_signature = taproot_sign(&checker, 0, SignatureVersion::Taproot, &ScriptExecutionData::default());
+		let script_sig = Script::new(Bytes::default());
+
+		let result = verify_script(
+			&script_sig,
+			&script_pubkey,
+			&vec![signature, annex.clone()],
+			&taproot_flags(),
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Ok(()));
+		let result = verify_script(
+			&script_sig,
+			&script_pubkey,
+			&vec![no_annex_signature, annex],
+			&taproot_flags(),
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Err(Error::SchnorrSig));
+	}
+
+	#[test]
+	fn taproot_inactive_is_anyone_can_spend() {
+		let script_pubkey = witness_v1_script(&taproot_x_only());
+		let checker = taproot_checker(&script_pubkey);
+		let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true);
+		let result = verify_script(
+			&Script::new(Bytes::default()),
+			&script_pubkey,
+			&vec![vec![0; 64].into()],
+			&flags,
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Ok(()));
+	}
+
+	#[test]
+	fn tapscript_checksig() {
+		let leaf_script = Builder::default()
+			.push_data(&taproot_x_only())
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script();
+		let signature = tapscript_signature(&leaf_script);
+		assert_eq!(run_tapscript_test(&leaf_script, vec![signature], taproot_flags()), Ok(()));
+		assert_eq!(
+			run_tapscript_test(&leaf_script, vec![Bytes::default()], taproot_flags()),
+			Err(Error::EvalFalse)
+		);
+	}
+
+	#[test]
+	fn tapscript_checksigadd() {
+		let leaf_script = Builder::default()
+			.push_data(&taproot_x_only())
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.push_data(&taproot_x_only())
+			.push_opcode(Opcode::OP_CHECKSIGADD)
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_NUMEQUAL)
+			.into_script();
+		let signature = tapscript_signature(&leaf_script);
+		assert_eq!(
+			run_tapscript_test(&leaf_script, vec![signature.clone(), signature.clone()], taproot_flags()),
+			Ok(())
+		);
+		assert_eq!(
+			run_tapscript_test(&leaf_script, vec![Bytes::default(), signature], taproot_flags()),
+			Err(Error::EvalFalse)
+		);
+	}
+
+	#[test]
+	fn tapscript_checksigadd_is_bad_opcode_in_legacy_script() {
+		let script = Builder::default().push_opcode(Opcode::OP_CHECKSIGADD).into_script();
+		basic_test(&script, Err(Error::BadOpcode), Stack::default());
+	}
+
+	#[test]
+	fn tapscript_wrong_commitment() {
+		let leaf_script = Builder::default().push_opcode(Opcode::OP_1).into_script();
+		let (script_pubkey, mut control) = tapscript_output(&leaf_script);
+		control[0] ^= 1;
+		let checker = taproot_checker(&script_pubkey);
+		let witness = vec![leaf_script.to_bytes(), control];
+		let result = verify_script(
+			&Script::new(Bytes::default()),
+			&script_pubkey,
+			&witness,
+			&taproot_flags(),
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Err(Error::WitnessProgramMismatch));
+	}
+
+	#[test]
+	fn tapscript_wrong_control_size() {
+		let leaf_script = Builder::default().push_opcode(Opcode::OP_1).into_script();
+		let (script_pubkey, control) = tapscript_output(&leaf_script);
+		let checker = taproot_checker(&script_pubkey);
+		let witness = vec![leaf_script.to_bytes(), control[..32].to_vec().into()];
+		let result = verify_script(
+			&Script::new(Bytes::default()),
+			&script_pubkey,
+			&witness,
+			&taproot_flags(),
+			&checker,
+			SignatureVersion::Base,
+		);
+		assert_eq!(result, Err(Error::TaprootWrongControlSize));
+	}
+
+	#[test]
+	fn tapscript_op_success() {
+		// OP_RETURN would fail the script, but OP_SUCCESS80 is found first
+		let leaf_script: Script = vec![Opcode::OP_RETURN as u8, Opcode::OP_RESERVED as u8].into();
+		assert_eq!(run_tapscript_test(&leaf_script, vec![], taproot_flags()), Ok(()));
+		assert_eq!(
+			run_tapscript_test(&leaf_script, vec![], taproot_flags().verify_discourage_op_success(true)),
+			Err(Error::DiscourageOpSuccess)
+		);
+	}
+
+	#[test]
+	fn tapscript_checkmultisig_disabled() {
+		let leaf_script = Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_script();
+		assert_eq!(
+			run_tapscript_test(&leaf_script, vec![], taproot_flags()),
+			Err(Error::TapscriptCheckMultisig)
+		);
+	}
+
+	#[test]
+	fn tapscript_minimal_if() {
+		let leaf_script = Builder::default()
+			.push_opcode(Opcode::OP_IF)
+			.push_opcode(Opcode::OP_1)
+			.push_opcode(Opcode::OP_ENDIF)
+			.into_script();
+		assert_eq!(run_tapscript_test(&leaf_script, vec![vec![1].into()], taproot_flags()), Ok(()));
+		assert_eq!(
+			run_tapscript_test(&leaf_script, vec![vec![2].into()], taproot_flags()),
+			Err(Error::TapscriptMinimalIf)
+		);
+	}
+
+	#[test]
+	fn tapscript_unknown_pubkey_type() {
+		let leaf_script = Builder::default()
+			.push_data(&[1; 33])
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script();
+		assert_eq!(run_tapscript_test(&leaf_script, vec![vec![1].into()], taproot_flags()), Ok(()));
+		assert_eq!(
+			run_tapscript_test(
+				&leaf_script,
+				vec![vec![1].into()],
+				taproot_flags().verify_discourage_upgradable_pubkeytype(true)
+			),
+			Err(Error::DiscourageUpgradablePubkeyType)
+		);
+	}
+
+	#[test]
+	fn tapscript_validation_weight() {
+		// every checked signature costs 50 units of weight, while budget is witness size + 50
+		let mut builder = Builder::default();
+		for _ in 0..10 {
+			builder = builder
+				.push_opcode(Opcode::OP_DUP)
+				.push_data(&[1; 33])
+				.push_opcode(Opcode::OP_CHECKSIGVERIFY);
+		}
+		let leaf_script = builder.into_script();
+		assert_eq!(
+			run_tapscript_test(&leaf_script, vec![vec![1].into()], taproot_flags()),
+			Err(Error::TapscriptValidationWeight)
+		);
+	}
 }
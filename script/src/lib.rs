@@ -6,11 +6,13 @@ extern crate primitives;
 extern crate serialization as ser;
 
 mod builder;
+mod descriptor;
 mod error;
 mod flags;
 mod interpreter;
 mod num;
 mod opcode;
+mod psbt;
 mod script;
 mod sign;
 mod stack;
@@ -19,12 +21,14 @@ mod verify;
 pub use primitives::{bytes, hash};
 
 pub use self::builder::Builder;
+pub use self::descriptor::{descriptor_checksum, Descriptor, DescriptorError, DescriptorKey, TapTree};
 pub use self::error::Error;
 pub use self::flags::VerificationFlags;
-pub use self::interpreter::{eval_script, verify_script};
+pub use self::interpreter::{compute_taproot_tweak, eval_script, verify_script};
 pub use self::num::Num;
 pub use self::opcode::Opcode;
+pub use self::psbt::{finalize_psbt, finalize_psbt_input, sign_psbt_input};
 pub use self::script::{is_witness_commitment_script, Script, ScriptAddress, ScriptType, ScriptWitness};
-pub use self::sign::{SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
+pub use self::sign::{ScriptExecutionData, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
 pub use self::stack::Stack;
 pub use self::verify::{NoopSignatureChecker, SignatureChecker, TransactionSignatureChecker};
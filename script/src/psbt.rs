@@ -0,0 +1,309 @@
+//! Signing and finalizing of partially signed transactions
+
+use crate::bytes::Bytes;
+use crate::chain::{Psbt, PsbtError, PsbtInput};
+use crate::keys::{AddressHash, KeyPair};
+use crate::{Builder, Script, SignatureVersion, TransactionInputSigner};
+use bitcrypto::{dhash160, sha256, Hash};
+use std::collections::BTreeMap;
+
+/// Signature hash type, used if the input doesn't specify one
+const SIGHASH_ALL: u32 = 1;
+
+/// Signs the input, adding the signature to its partial signatures. Legacy, P2SH-wrapped segwit
+/// and native segwit v0 inputs are supported.
+///
+/// Returns false if the key doesn't take part in spending of the input or if the input is already finalized.
+pub fn sign_psbt_input(psbt: &mut Psbt, input_index: usize, keypair: &KeyPair) -> Result<bool, PsbtError> {
+	let signer = TransactionInputSigner::from(psbt.unsigned_transaction()?);
+	let input = psbt.inputs.get_mut(input_index).ok_or(PsbtError::InputOutOfRange(input_index))?;
+	if input.is_finalized() {
+		return Ok(false);
+	}
+
+	let utxo = input.utxo().ok_or(PsbtError::MissingUtxo(input_index))?;
+	let (script_code, sigversion) = script_code(input, utxo.script_pubkey.into(), input_index)?;
+	let public: &[u8] = keypair.public();
+	if !contains(&script_code, public) && !contains(&script_code, &dhash160(public)[..]) {
+		return Ok(false);
+	}
+
+	let sighash = input.sighash_type.unwrap_or(SIGHASH_ALL);
+	let hash = signer
+		.signature_hash(input_index, utxo.value, &script_code, sigversion, sighash)
+		.ok_or(PsbtError::UnsupportedScript(input_index))?;
+	let mut signature: Vec<u8> = keypair.private().sign(&hash).map_err(|_| PsbtError::Signing)?.into();
+	signature.push(sighash as u8);
+	input.partial_sigs.insert(public.into(), signature.into());
+	Ok(true)
+}
+
+/// Builds final script sig and witness of the input from its partial signatures. Data, which is not
+/// required by the extractor, is removed from the finalized input.
+///
+/// Returns false if there are not enough signatures to spend the input.
+pub fn finalize_psbt_input(psbt: &mut Psbt, input_index: usize) -> Result<bool, PsbtError> {
+	let input = psbt.inputs.get_mut(input_index).ok_or(PsbtError::InputOutOfRange(input_index))?;
+	if input.is_finalized() {
+		return Ok(true);
+	}
+
+	let utxo = input.utxo().ok_or(PsbtError::MissingUtxo(input_index))?;
+	let script_pubkey: Script = utxo.script_pubkey.into();
+	let is_pay_to_script_hash = script_pubkey.is_pay_to_script_hash();
+	let (script_code, sigversion) = script_code(input, script_pubkey, input_index)?;
+	let mut stack = match satisfy(&script_code, &input.partial_sigs) {
+		Some(stack) => stack,
+		None => return Ok(false),
+	};
+
+	let redeem_script = if is_pay_to_script_hash { input.redeem_script.clone() } else { None };
+	match sigversion {
+		SignatureVersion::WitnessV0 => {
+			if let Some(ref witness_script) = input.witness_script {
+				if script_code.to_bytes() == *witness_script {
+					stack.push(witness_script.clone());
+				}
+			}
+			input.final_script_sig = redeem_script.map(|script| Builder::default().push_data(&script).into_bytes());
+			input.final_script_witness = Some(stack);
+		}
+		_ => {
+			let builder = stack
+				.iter()
+				.chain(redeem_script.iter())
+				.fold(Builder::default(), |builder, item| builder.push_data(item));
+			input.final_script_sig = Some(builder.into_bytes());
+		}
+	}
+
+	input.partial_sigs.clear();
+	input.sighash_type = None;
+	input.redeem_script = None;
+	input.witness_script = None;
+	input.bip32_derivation.clear();
+	Ok(true)
+}
+
+/// Finalizes all inputs of the PSBT. Inputs, which can't be finalized, are left unchanged.
+/// Returns true if all inputs are finalized.
+pub fn finalize_psbt(psbt: &mut Psbt) -> bool {
+	let mut is_finalized = true;
+	for input_index in 0..psbt.inputs.len() {
+		is_finalized &= finalize_psbt_input(psbt, input_index).unwrap_or(false);
+	}
+	is_finalized
+}
+
+/// Script, committed to by the input signatures, and the signature version
+fn script_code(input: &PsbtInput, script_pubkey: Script, input_index: usize) -> Result<(Script, SignatureVersion), PsbtError> {
+	let script: Script = if script_pubkey.is_pay_to_script_hash() {
+		let redeem_script = input.redeem_script.as_ref().ok_or(PsbtError::MissingField("redeem script"))?;
+		if dhash160(redeem_script)[..] != script_pubkey[2..22] {
+			return Err(PsbtError::ScriptMismatch(input_index));
+		}
+		redeem_script.clone().into()
+	} else {
+		script_pubkey
+	};
+
+	if script.is_pay_to_witness_key_hash() {
+		let hash = AddressHash::from_slice(&script[2..22]).expect("witness key hash is 20 bytes long; qed");
+		Ok((Builder::build_p2pkh(&hash), SignatureVersion::WitnessV0))
+	} else if script.is_pay_to_witness_script_hash() {
+		let witness_script = input.witness_script.as_ref().ok_or(PsbtError::MissingField("witness script"))?;
+		if sha256(witness_script)[..] != script[2..34] {
+			return Err(PsbtError::ScriptMismatch(input_index));
+		}
+		Ok((witness_script.clone().into(), SignatureVersion::WitnessV0))
+	} else if script.parse_witness_program().is_some() {
+		Err(PsbtError::UnsupportedScript(input_index))
+	} else {
+		Ok((script, SignatureVersion::Base))
+	}
+}
+
+/// Stack items, satisfying pay-to-pubkey, pay-to-pubkey-hash or multisig script
+fn satisfy(script: &Script, signatures: &BTreeMap<Bytes, Bytes>) -> Option<Vec<Bytes>> {
+	if script.is_pay_to_public_key_hash() {
+		signatures
+			.iter()
+			.find(|(public, _)| dhash160(public)[..] == script[3..23])
+			.map(|(public, signature)| vec![signature.clone(), public.clone()])
+	} else if script.is_pay_to_public_key() {
+		let public: Bytes = script[1..script.len() - 1].into();
+		signatures.get(&public).map(|signature| vec![signature.clone()])
+	} else if script.is_multisig_script() {
+		let required = script.num_signatures_required() as usize;
+		// OP_CHECKMULTISIG pops one extra element
+		let mut stack = vec![Bytes::new()];
+		stack.extend(
+			script
+				.iter()
+				.filter_map(|instruction| instruction.ok()?.data)
+				.filter_map(|public| signatures.get(&Bytes::from(public)).cloned())
+				.take(required),
+		);
+		if stack.len() == required + 1 {
+			Some(stack)
+		} else {
+			None
+		}
+	} else {
+		None
+	}
+}
+
+fn contains(script: &[u8], data: &[u8]) -> bool {
+	script.windows(data.len()).any(|window| window == data)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{finalize_psbt, sign_psbt_input};
+	use crate::{verify_script, Builder, Opcode, Script, SignatureVersion, TransactionSignatureChecker, VerificationFlags};
+	use bitcrypto::{dhash160, sha256, FromStr, SHA256D};
+	use chain::{OutPoint, Psbt, PsbtError, Transaction, TransactionInput, TransactionOutput};
+	use keys::{KeyPair, Private};
+
+	fn keypair(private: &'static str) -> KeyPair {
+		KeyPair::from_private(Private::from(private)).unwrap()
+	}
+
+	fn psbt(spent: &[TransactionOutput]) -> Psbt {
+		let transaction = Transaction {
+			version: 2,
+			inputs: (0..spent.len())
+				.map(|index| TransactionInput {
+					previous_output: OutPoint {
+						hash: SHA256D::from_str("75ddabb27b8845f5247975c8a5ba7c6f336c4570708ebe230caf6db5217ae858").unwrap(),
+						index: index as u32,
+					},
+					sequence: 0xffff_ffff,
+					..Default::default()
+				})
+				.collect(),
+			outputs: vec![TransactionOutput {
+				value: 10_000,
+				script_pubkey: "0014d85c2b71d0060b09c9886aeb815e50991dda124d".into(),
+			}],
+			lock_time: 0,
+		};
+		let mut psbt = Psbt::from_unsigned_transaction(transaction, 0).unwrap();
+		for (input, output) in psbt.inputs.iter_mut().zip(spent) {
+			input.witness_utxo = Some(output.clone());
+		}
+		psbt
+	}
+
+	fn verify(transaction: &Transaction, spent: &[TransactionOutput]) {
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_strictenc(true)
+			.verify_dersig(true)
+			.verify_witness(true);
+		for (index, (input, output)) in transaction.inputs.iter().zip(spent).enumerate() {
+			let checker = TransactionSignatureChecker {
+				signer: transaction.clone().into(),
+				input_index: index,
+				input_amount: output.value,
+				spent_outputs: spent.to_vec(),
+			};
+			let script_sig: Script = input.script_sig.clone().into();
+			let script_pubkey: Script = output.script_pubkey.clone().into();
+			verify_script(
+				&script_sig,
+				&script_pubkey,
+				&input.script_witness,
+				&flags,
+				&checker,
+				SignatureVersion::Base,
+			)
+			.unwrap();
+		}
+	}
+
+	#[test]
+	fn test_sign_and_finalize_single_key_inputs() {
+		let key = keypair("L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy");
+		let hash = key.public().address_hash();
+		let p2wpkh = Builder::build_p2wpkh(&hash);
+		let spent = vec![
+			TransactionOutput {
+				value: 20_000,
+				script_pubkey: Builder::build_p2pkh(&hash).to_bytes(),
+			},
+			TransactionOutput {
+				value: 30_000,
+				script_pubkey: p2wpkh.to_bytes(),
+			},
+			TransactionOutput {
+				value: 40_000,
+				script_pubkey: Builder::build_p2sh(&dhash160(&p2wpkh)).to_bytes(),
+			},
+		];
+		let mut psbt = psbt(&spent);
+		assert_eq!(sign_psbt_input(&mut psbt, 2, &key), Err(PsbtError::MissingField("redeem script")));
+		psbt.inputs[2].redeem_script = Some(p2wpkh.to_bytes());
+
+		let other = keypair("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn");
+		for index in 0..spent.len() {
+			assert_eq!(sign_psbt_input(&mut psbt, index, &other), Ok(false));
+			assert_eq!(sign_psbt_input(&mut psbt, index, &key), Ok(true));
+		}
+		assert_eq!(sign_psbt_input(&mut psbt, 3, &key), Err(PsbtError::InputOutOfRange(3)));
+
+		assert!(finalize_psbt(&mut psbt));
+		assert!(psbt.inputs[0].final_script_witness.is_none());
+		assert!(psbt.inputs[1].final_script_sig.is_none());
+		assert!(psbt.inputs.iter().all(|input| input.partial_sigs.is_empty()));
+		let transaction = psbt.extract().unwrap();
+		verify(&transaction, &spent);
+	}
+
+	#[test]
+	fn test_sign_and_finalize_multisig_inputs() {
+		let keys = [
+			keypair("L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy"),
+			keypair("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn"),
+			keypair("L3p8oAcQTtuokSCRHQ7i4MhjWc9zornvpJLfmg62sYpLRJF9woSu"),
+		];
+		let multisig = keys
+			.iter()
+			.fold(Builder::default().push_opcode(Opcode::OP_2), |builder, key| {
+				builder.push_data(key.public())
+			})
+			.push_opcode(Opcode::OP_3)
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_script();
+		let mut program = [0u8; 32];
+		program.copy_from_slice(&sha256(&multisig)[..]);
+		let spent = vec![
+			TransactionOutput {
+				value: 20_000,
+				script_pubkey: Builder::build_p2sh(&dhash160(&multisig)).to_bytes(),
+			},
+			TransactionOutput {
+				value: 30_000,
+				script_pubkey: Builder::build_p2wsh(&program).to_bytes(),
+			},
+		];
+		let mut psbt = psbt(&spent);
+		psbt.inputs[0].redeem_script = Some(multisig.to_bytes());
+		psbt.inputs[1].witness_script = Some(multisig.to_bytes());
+
+		// signers are working on their own copies of the PSBT
+		let mut first = psbt.clone();
+		let mut second = psbt.clone();
+		for index in 0..spent.len() {
+			assert_eq!(sign_psbt_input(&mut first, index, &keys[2]), Ok(true));
+			assert_eq!(sign_psbt_input(&mut second, index, &keys[0]), Ok(true));
+		}
+		assert!(!finalize_psbt(&mut first.clone()));
+
+		first.combine(second).unwrap();
+		assert!(finalize_psbt(&mut first));
+		let transaction = first.extract().unwrap();
+		verify(&transaction, &spent);
+	}
+}
@@ -3,7 +3,8 @@
 use crate::bytes::Bytes;
 use crate::{Error, Opcode};
 use crypto::Hash;
-use keys::{self, AddressHash, Public};
+use keys::{self, AddressHash, Payload, Public};
+use std::convert::TryInto;
 use std::{fmt, ops};
 
 /// Maximum number of bytes pushable to the stack
@@ -29,6 +30,7 @@ pub enum ScriptType {
 	NullData,
 	WitnessScript,
 	WitnessKey,
+	WitnessTaproot,
 }
 
 /// Address from Script
@@ -36,8 +38,8 @@ pub enum ScriptType {
 pub struct ScriptAddress {
 	/// The type of the address.
 	pub kind: keys::Type,
-	/// Public key hash.
-	pub hash: AddressHash,
+	/// Public key hash, script hash or witness program.
+	pub payload: Payload,
 }
 
 impl ScriptAddress {
@@ -45,7 +47,7 @@ impl ScriptAddress {
 	pub fn new_p2pkh(hash: AddressHash) -> Self {
 		ScriptAddress {
 			kind: keys::Type::P2PKH,
-			hash,
+			payload: hash.into(),
 		}
 	}
 
@@ -53,7 +55,40 @@ impl ScriptAddress {
 	pub fn new_p2sh(hash: AddressHash) -> Self {
 		ScriptAddress {
 			kind: keys::Type::P2SH,
-			hash,
+			payload: hash.into(),
+		}
+	}
+
+	/// Creates P2WPKH-type ScriptAddress
+	pub fn new_p2wpkh(hash: AddressHash) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2WPKH,
+			payload: hash.into(),
+		}
+	}
+
+	/// Creates P2WSH-type ScriptAddress
+	pub fn new_p2wsh(program: [u8; 32]) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2WSH,
+			payload: Payload::WitnessProgram(program),
+		}
+	}
+
+	/// Creates P2TR-type ScriptAddress
+	pub fn new_p2tr(program: [u8; 32]) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2TR,
+			payload: Payload::WitnessProgram(program),
+		}
+	}
+}
+
+impl From<keys::Address> for ScriptAddress {
+	fn from(address: keys::Address) -> Self {
+		ScriptAddress {
+			kind: address.kind,
+			payload: address.payload,
 		}
 	}
 }
@@ -160,6 +195,11 @@ impl Script {
 		self.data.len() == 34 && self.data[0] == Opcode::OP_0 as u8 && self.data[1] == Opcode::OP_PUSHBYTES_32 as u8
 	}
 
+	/// Extra-fast test for pay-to-taproot scripts.
+	pub fn is_pay_to_taproot(&self) -> bool {
+		self.data.len() == 34 && self.data[0] == Opcode::OP_1 as u8 && self.data[1] == Opcode::OP_PUSHBYTES_32 as u8
+	}
+
 	/// Extra-fast test for multisig scripts.
 	pub fn is_multisig_script(&self) -> bool {
 		if self.data.len() < 3 {
@@ -344,6 +384,8 @@ impl Script {
 			ScriptType::WitnessKey
 		} else if self.is_pay_to_witness_script_hash() {
 			ScriptType::WitnessScript
+		} else if self.is_pay_to_taproot() {
+			ScriptType::WitnessTaproot
 		} else {
 			ScriptType::NonStandard
 		}
@@ -429,12 +471,15 @@ impl Script {
 				Ok(addresses)
 			}
 			ScriptType::NullData => Ok(vec![]),
-			ScriptType::WitnessScript => {
-				Ok(vec![]) // TODO
-			}
-			ScriptType::WitnessKey => {
-				Ok(vec![]) // TODO
-			}
+			ScriptType::WitnessScript => Ok(vec![ScriptAddress::new_p2wsh(
+				self.data[2..34].try_into().expect("checked by script_type(); qed"),
+			)]),
+			ScriptType::WitnessKey => Ok(vec![ScriptAddress::new_p2wpkh(
+				AddressHash::from_slice(&self.data[2..22]).map_err(|_| keys::Error::InvalidAddress)?,
+			)]),
+			ScriptType::WitnessTaproot => Ok(vec![ScriptAddress::new_p2tr(
+				self.data[2..34].try_into().expect("checked by script_type(); qed"),
+			)]),
 		}
 	}
 
@@ -576,7 +621,7 @@ pub fn is_witness_commitment_script(script: &[u8]) -> bool {
 mod tests {
 	use super::{Script, ScriptAddress, ScriptType, MAX_SCRIPT_ELEMENT_SIZE};
 	use crate::{Builder, Opcode};
-	use keys::{Address, Public};
+	use keys::{Address, Payload, Public, Type};
 
 	#[test]
 	fn test_is_pay_to_script_hash() {
@@ -738,7 +783,7 @@ OP_ADD
 
 	#[test]
 	fn test_extract_destinations_pub_key_hash() {
-		let address = Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash;
+		let address = *Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").payload.hash().unwrap();
 		let script = Builder::build_p2pkh(&address);
 		assert_eq!(script.script_type(), ScriptType::PubKeyHash);
 		assert_eq!(script.extract_destinations(), Ok(vec![ScriptAddress::new_p2pkh(address),]));
@@ -746,12 +791,70 @@ OP_ADD
 
 	#[test]
 	fn test_extract_destinations_script_hash() {
-		let address = Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash;
+		let address = *Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").payload.hash().unwrap();
 		let script = Builder::build_p2sh(&address);
 		assert_eq!(script.script_type(), ScriptType::ScriptHash);
 		assert_eq!(script.extract_destinations(), Ok(vec![ScriptAddress::new_p2sh(address),]));
 	}
 
+	#[test]
+	fn test_extract_destinations_witness_key_hash() {
+		let address = Address::from("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+		let script = Builder::build_address(&address).unwrap();
+		assert_eq!(script, "0014751e76e8199196d454941c45d1b3a323f1433bd6".into());
+		assert_eq!(script.script_type(), ScriptType::WitnessKey);
+		assert_eq!(script.extract_destinations(), Ok(vec![address.into()]));
+	}
+
+	#[test]
+	fn test_extract_destinations_witness_script_hash() {
+		let address = Address::from("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7");
+		let script = Builder::build_address(&address).unwrap();
+		assert_eq!(
+			script,
+			"00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262".into()
+		);
+		assert_eq!(script.script_type(), ScriptType::WitnessScript);
+		assert_eq!(script.extract_destinations(), Ok(vec![address.into()]));
+	}
+
+	#[test]
+	fn test_extract_destinations_taproot() {
+		let address = Address::from("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0");
+		let script = Builder::build_address(&address).unwrap();
+		assert_eq!(
+			script,
+			"512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".into()
+		);
+		assert_eq!(script.script_type(), ScriptType::WitnessTaproot);
+		assert_eq!(script.extract_destinations(), Ok(vec![address.into()]));
+	}
+
+	#[test]
+	fn test_build_unknown_witness_program() {
+		let address = Address::from("BC1SW50QGDZ25J");
+		let script = Builder::build_address(&address).unwrap();
+		assert_eq!(script, "6002751e".into());
+		assert_eq!(script.parse_witness_program(), Some((16, &[0x75, 0x1e][..])));
+
+		let address = Address::from("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs");
+		assert_eq!(
+			Builder::build_address(&address).unwrap(),
+			"5210751e76e8199196d454941c45d1b3a323".into()
+		);
+	}
+
+	#[test]
+	fn test_build_invalid_address() {
+		let mut address = Address::from("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+		address.kind = Type::P2WSH;
+		assert_eq!(Builder::build_address(&address), Err(keys::Error::InvalidAddress));
+
+		address.kind = Type::WitnessUnknown(17);
+		address.payload = Payload::UnknownWitnessProgram(vec![0; 20]);
+		assert_eq!(Builder::build_address(&address), Err(keys::Error::InvalidAddress));
+	}
+
 	#[test]
 	fn test_extract_destinations_multisig() {
 		let pubkey1_bytes = [0; 33];
@@ -4,7 +4,7 @@ use crate::bytes::Bytes;
 use crate::chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
 use crate::keys::KeyPair;
 use crate::{Builder, Script};
-use bitcrypto::{dhash256, Hash, SHA256D};
+use bitcrypto::{dhash256, sha256, tagged_hash, Hash, SHA256, SHA256D};
 use ser::Stream;
 
 const ONE: [u8; 32] = [
@@ -15,6 +15,34 @@ const ONE: [u8; 32] = [
 pub enum SignatureVersion {
 	Base,
 	WitnessV0,
+	/// Witness v1 key path spending (BIP341)
+	Taproot,
+	/// Witness v1 script path spending (BIP342)
+	Tapscript,
+}
+
+/// Data about the currently executed taproot spend, which is committed to by signatures
+#[derive(Debug, Clone)]
+pub struct ScriptExecutionData {
+	/// Hash of the executed tapleaf, `None` for key path spends
+	pub tapleaf_hash: Option<SHA256>,
+	/// Opcode position of the last executed OP_CODESEPARATOR (0xffffffff if there was none)
+	pub codeseparator_pos: u32,
+	/// Hash of the annex, if it is present
+	pub annex_hash: Option<SHA256>,
+	/// How much signature validation weight is still available to the tapscript
+	pub validation_weight_left: i64,
+}
+
+impl Default for ScriptExecutionData {
+	fn default() -> Self {
+		ScriptExecutionData {
+			tapleaf_hash: None,
+			codeseparator_pos: 0xffff_ffff,
+			annex_hash: None,
+			validation_weight_left: 0,
+		}
+	}
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -117,6 +145,9 @@ impl From<Transaction> for TransactionInputSigner {
 }
 
 impl TransactionInputSigner {
+	/// Legacy and segwit v0 signature hash.
+	///
+	/// Returns None for taproot signature versions, which commit to all spent outputs (see `signature_hash_schnorr`).
 	pub fn signature_hash(
 		&self,
 		input_index: usize,
@@ -124,16 +155,115 @@ impl TransactionInputSigner {
 		script_pubkey: &Script,
 		sigversion: SignatureVersion,
 		sighashtype: u32,
-	) -> SHA256D {
+	) -> Option<SHA256D> {
 		let sighash = Sighash::from_u32(sighashtype);
 		match sigversion {
-			SignatureVersion::Base => self.signature_hash_original(input_index, script_pubkey, sighashtype, sighash),
-			SignatureVersion::WitnessV0 => self.signature_hash_witness0(input_index, input_amount, script_pubkey, sighashtype, sighash),
+			SignatureVersion::Base => Some(self.signature_hash_original(input_index, script_pubkey, sighashtype, sighash)),
+			SignatureVersion::WitnessV0 => {
+				Some(self.signature_hash_witness0(input_index, input_amount, script_pubkey, sighashtype, sighash))
+			}
+			SignatureVersion::Taproot | SignatureVersion::Tapscript => None,
+		}
+	}
+
+	/// BIP341 signature hash.
+	///
+	/// spent_outputs - outputs spent by all inputs of the transaction, in the same order
+	///
+	/// Returns None if the hash type is invalid, or if SIGHASH_SINGLE is used without corresponding output.
+	pub fn signature_hash_schnorr(
+		&self,
+		input_index: usize,
+		spent_outputs: &[TransactionOutput],
+		sighashtype: u32,
+		sigversion: SignatureVersion,
+		execdata: &ScriptExecutionData,
+	) -> Option<SHA256> {
+		let ext_flag: u8 = match sigversion {
+			SignatureVersion::Taproot => 0,
+			SignatureVersion::Tapscript => 1,
+			SignatureVersion::Base | SignatureVersion::WitnessV0 => return None,
+		};
+
+		// SIGHASH_DEFAULT (0) commits to the same data as SIGHASH_ALL
+		match sighashtype {
+			0x00 | 0x01 | 0x02 | 0x03 | 0x81 | 0x82 | 0x83 => (),
+			_ => return None,
+		}
+
+		if input_index >= self.inputs.len() || spent_outputs.len() != self.inputs.len() {
+			return None;
+		}
+
+		let sighash = Sighash::from_u32(sighashtype);
+		let mut stream = Stream::default();
+		// epoch
+		stream.append(&0u8);
+		stream.append(&(sighashtype as u8));
+		stream.append(&self.version);
+		stream.append(&self.lock_time);
+
+		if !sighash.anyone_can_pay {
+			let mut prevouts = Stream::default();
+			let mut amounts = Stream::default();
+			let mut script_pubkeys = Stream::default();
+			let mut sequences = Stream::default();
+			for (input, spent_output) in self.inputs.iter().zip(spent_outputs) {
+				prevouts.append(&input.previous_output);
+				amounts.append(&spent_output.value);
+				script_pubkeys.append(&spent_output.script_pubkey);
+				sequences.append(&input.sequence);
+			}
+			stream.append_slice(&sha256(&prevouts.out())[..]);
+			stream.append_slice(&sha256(&amounts.out())[..]);
+			stream.append_slice(&sha256(&script_pubkeys.out())[..]);
+			stream.append_slice(&sha256(&sequences.out())[..]);
+		}
+
+		if sighash.base == SighashBase::All {
+			let mut outputs = Stream::default();
+			for output in &self.outputs {
+				outputs.append(output);
+			}
+			stream.append_slice(&sha256(&outputs.out())[..]);
+		}
+
+		let spend_type = (ext_flag << 1) + execdata.annex_hash.is_some() as u8;
+		stream.append(&spend_type);
+		if sighash.anyone_can_pay {
+			let input = &self.inputs[input_index];
+			stream.append(&input.previous_output);
+			stream.append(&spent_outputs[input_index]);
+			stream.append(&input.sequence);
+		} else {
+			stream.append(&(input_index as u32));
+		}
+
+		if let Some(ref annex_hash) = execdata.annex_hash {
+			stream.append_slice(&annex_hash[..]);
+		}
+
+		if sighash.base == SighashBase::Single {
+			let output = self.outputs.get(input_index)?;
+			let mut single = Stream::default();
+			single.append(output);
+			stream.append_slice(&sha256(&single.out())[..]);
 		}
+
+		if sigversion == SignatureVersion::Tapscript {
+			stream.append_slice(&execdata.tapleaf_hash.as_ref()?[..]);
+			// key_version
+			stream.append(&0u8);
+			stream.append(&execdata.codeseparator_pos);
+		}
+
+		Some(tagged_hash("TapSighash", &stream.out()))
 	}
 
 	/// input_index - index of input to sign
 	/// script_pubkey - script_pubkey of input's previous_output pubkey
+	///
+	/// Returns None for taproot signature versions.
 	pub fn signed_input(
 		&self,
 		keypair: &KeyPair,
@@ -142,8 +272,8 @@ impl TransactionInputSigner {
 		script_pubkey: &Script,
 		sigversion: SignatureVersion,
 		sighash: u32,
-	) -> TransactionInput {
-		let hash = self.signature_hash(input_index, input_amount, script_pubkey, sigversion, sighash);
+	) -> Option<TransactionInput> {
+		let hash = self.signature_hash(input_index, input_amount, script_pubkey, sigversion, sighash)?;
 
 		let mut signature: Vec<u8> = keypair.private().sign(&hash).unwrap().into();
 		signature.push(sighash as u8);
@@ -153,12 +283,12 @@ impl TransactionInputSigner {
 			.into_script();
 
 		let unsigned_input = &self.inputs[input_index];
-		TransactionInput {
+		Some(TransactionInput {
 			previous_output: unsigned_input.previous_output.clone(),
 			sequence: unsigned_input.sequence,
 			script_sig: script_sig.to_bytes(),
 			script_witness: vec![],
-		}
+		})
 	}
 
 	pub fn signature_hash_original(&self, input_index: usize, script_pubkey: &Script, sighashtype: u32, sighash: Sighash) -> SHA256D {
@@ -306,10 +436,10 @@ fn compute_hash_outputs(sighash: Sighash, input_index: usize, outputs: &[Transac
 
 #[cfg(test)]
 mod tests {
-	use super::{Sighash, SighashBase, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
+	use super::{ScriptExecutionData, Sighash, SighashBase, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
 	use crate::bytes::Bytes;
 	use crate::script::Script;
-	use bitcrypto::{FromHex, FromInnerHex, SHA256D};
+	use bitcrypto::{FromHex, FromInnerHex, FromStr, SHA256, SHA256D};
 	use chain::{OutPoint, Transaction, TransactionOutput};
 	use keys::{Address, KeyPair, Private};
 
@@ -331,7 +461,7 @@ mod tests {
 		// this is irrelevant
 		let kp = KeyPair::from_private(private).unwrap();
 		assert_eq!(kp.address(), from);
-		assert_eq!(&current_output[3..23], &*to.hash);
+		assert_eq!(&current_output[3..23], &*to.payload);
 
 		let unsigned_input = UnsignedTransactionInput {
 			sequence: 0xffff_ffff,
@@ -353,7 +483,9 @@ mod tests {
 			outputs: vec![output],
 		};
 
-		let hash = input_signer.signature_hash(0, 0, &previous_output, SignatureVersion::Base, SighashBase::All.into());
+		let hash = input_signer
+			.signature_hash(0, 0, &previous_output, SignatureVersion::Base, SighashBase::All.into())
+			.unwrap();
 		assert_eq!(hash, expected_signature_hash);
 	}
 
@@ -875,6 +1007,50 @@ mod tests {
 		run_test_sighash("fea256ce01272d125e577c0a09570a71366898280dda279b021000db1325f27edda41a53460100000002ab53c752c21c013c2b3a01000000000000000000", "65", 0, 1145543262, "076b9f844f6ae429de228a2c337c704df1652c292b6c6494882190638dad9efd");
 	}
 
+	// keyPathSpending rows of https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+	#[test]
+	fn test_signature_hash_schnorr_bip341() {
+		let tx: Transaction = "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d".into();
+		let spent_outputs: Vec<TransactionOutput> = vec![
+			(420000000, "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"),
+			(462000000, "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"),
+			(294000000, "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac"),
+			(504000000, "5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e"),
+			(630000000, "512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605"),
+			(378000000, "00147dd65592d0ab2fe0d0257d571abf032cd9db93dc"),
+			(672000000, "512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831"),
+			(546000000, "5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5"),
+			(588000000, "512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220"),
+		]
+		.into_iter()
+		.map(|(value, script_pubkey)| TransactionOutput {
+			value,
+			script_pubkey: script_pubkey.into(),
+		})
+		.collect();
+		let signer: TransactionInputSigner = tx.into();
+		let execdata = ScriptExecutionData::default();
+
+		let vectors = [
+			(0, 0x03, "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555"),
+			(3, 0x01, "bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669"),
+			(6, 0x02, "15f25c298eb5cdc7eb1d638dd2d45c97c4c59dcaec6679cfc16ad84f30876b85"),
+			(7, 0x82, "cd292de50313804dabe4685e83f923d2969577191a3e1d2882220dca88cbeb10"),
+			(8, 0x81, "cccb739eca6c13a8a89e6e5cd317ffe55669bbda23f2fd37b0f18755e008edd2"),
+		];
+		for (input_index, sighashtype, expected) in vectors.iter() {
+			let hash = signer.signature_hash_schnorr(*input_index, &spent_outputs, *sighashtype, SignatureVersion::Taproot, &execdata);
+			assert_eq!(hash, Some(SHA256::from_str(expected).unwrap()));
+		}
+
+		// taproot signatures commit to all spent outputs => legacy signature hash is not defined
+		let script_pubkey: Script = spent_outputs[0].script_pubkey.clone().into();
+		assert_eq!(
+			signer.signature_hash(0, spent_outputs[0].value, &script_pubkey, SignatureVersion::Taproot, 0),
+			None
+		);
+	}
+
 	#[test]
 	fn test_sighash_from_u32() {
 		assert!(!Sighash::is_defined(0xFFFFFF82));
@@ -1,9 +1,10 @@
-use crate::sign::SignatureVersion;
-use crate::{Num, Script, TransactionInputSigner};
+use crate::sign::{ScriptExecutionData, SignatureVersion};
+use crate::{Error, Num, Script, TransactionInputSigner};
 use chain::constants::{
 	LOCKTIME_THRESHOLD, SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG,
 };
-use keys::{Message, Public, Signature};
+use chain::TransactionOutput;
+use keys::{Message, Public, SchnorrSignature, Signature, XOnlyPublic};
 
 /// Checks transaction signature
 pub trait SignatureChecker {
@@ -18,6 +19,15 @@ pub trait SignatureChecker {
 		version: SignatureVersion,
 	) -> bool;
 
+	fn check_schnorr_signature(
+		&self,
+		signature: &SchnorrSignature,
+		public: &XOnlyPublic,
+		sighashtype: u32,
+		version: SignatureVersion,
+		execdata: &ScriptExecutionData,
+	) -> Result<(), Error>;
+
 	fn check_lock_time(&self, lock_time: Num) -> bool;
 
 	fn check_sequence(&self, sequence: Num) -> bool;
@@ -34,6 +44,17 @@ impl SignatureChecker for NoopSignatureChecker {
 		false
 	}
 
+	fn check_schnorr_signature(
+		&self,
+		_: &SchnorrSignature,
+		_: &XOnlyPublic,
+		_: u32,
+		_: SignatureVersion,
+		_: &ScriptExecutionData,
+	) -> Result<(), Error> {
+		Err(Error::SchnorrSig)
+	}
+
 	fn check_lock_time(&self, _: Num) -> bool {
 		false
 	}
@@ -48,6 +69,8 @@ pub struct TransactionSignatureChecker {
 	pub signer: TransactionInputSigner,
 	pub input_index: usize,
 	pub input_amount: u64,
+	/// Outputs spent by all transaction inputs. Required to verify taproot signatures
+	pub spent_outputs: Vec<TransactionOutput>,
 }
 
 impl SignatureChecker for TransactionSignatureChecker {
@@ -63,10 +86,31 @@ impl SignatureChecker for TransactionSignatureChecker {
 		sighashtype: u32,
 		version: SignatureVersion,
 	) -> bool {
+		match self
+			.signer
+			.signature_hash(self.input_index, self.input_amount, script_code, version, sighashtype)
+		{
+			Some(hash) => self.verify_signature(signature, public, &hash),
+			None => false,
+		}
+	}
+
+	fn check_schnorr_signature(
+		&self,
+		signature: &SchnorrSignature,
+		public: &XOnlyPublic,
+		sighashtype: u32,
+		version: SignatureVersion,
+		execdata: &ScriptExecutionData,
+	) -> Result<(), Error> {
 		let hash = self
 			.signer
-			.signature_hash(self.input_index, self.input_amount, script_code, version, sighashtype);
-		self.verify_signature(signature, public, &hash)
+			.signature_hash_schnorr(self.input_index, &self.spent_outputs, sighashtype, version, execdata)
+			.ok_or(Error::SchnorrSigHashtype)?;
+		match public.verify(&hash, signature) {
+			Ok(true) => Ok(()),
+			_ => Err(Error::SchnorrSig),
+		}
 	}
 
 	fn check_lock_time(&self, lock_time: Num) -> bool {
@@ -17,7 +17,6 @@ pub fn impl_deserializable(ast: &DeriveInput) -> TokenStream {
 
 	let name = &ast.ident;
 
-	let dummy_const = format_ident!("_IMPL_DESERIALIZABLE_FOR_{}", name);
 	let impl_block = quote! {
 		impl serialization::Deserializable for #name {
 			fn deserialize<T>(reader: &mut serialization::Reader<T>) -> Result<Self, serialization::Error> where T: io::Read {
@@ -31,8 +30,8 @@ pub fn impl_deserializable(ast: &DeriveInput) -> TokenStream {
 	};
 
 	quote! {
-		#[allow(non_upper_case_globals, unused_attributes, unused_qualifications)]
-		const #dummy_const: () = {
+		#[allow(unused_attributes, unused_qualifications)]
+		const _: () = {
 			extern crate serialization;
 			use std::io;
 			#impl_block
@@ -1,5 +1,4 @@
 extern crate syn;
-#[macro_use]
 extern crate quote;
 
 mod de;
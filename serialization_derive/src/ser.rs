@@ -19,8 +19,6 @@ pub fn impl_serializable(ast: &DeriveInput) -> TokenStream {
 
 	let name = &ast.ident;
 
-	let dummy_const = format_ident!("_IMPL_SERIALIZABLE_FOR_{}", name);
-
 	let impl_block = quote! {
 		impl serialization::Serializable for #name {
 			fn serialize(&self, stream: &mut serialization::Stream) {
@@ -34,8 +32,8 @@ pub fn impl_serializable(ast: &DeriveInput) -> TokenStream {
 	};
 
 	quote! {
-		#[allow(non_upper_case_globals, unused_attributes, unused_qualifications)]
-		const #dummy_const: () = {
+		#[allow(unused_attributes, unused_qualifications)]
+		const _: () = {
 			extern crate serialization;
 			#impl_block
 		};
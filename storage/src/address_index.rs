@@ -0,0 +1,25 @@
+use crate::Error;
+use bitcrypto::{SHA256, SHA256D};
+use chain::OutPoint;
+
+/// Single funding or spending of an output, locked by some script.
+#[derive(Debug, Clone, PartialEq, Serializable, Deserializable)]
+pub struct ScriptHashEntry {
+	/// Height of the canon block, containing the transaction
+	pub height: u32,
+	/// Hash of the funding (or spending) transaction
+	pub tx_hash: SHA256D,
+	/// Funded (or spent) output
+	pub outpoint: OutPoint,
+	/// Value of the output
+	pub value: u64,
+	/// True if transaction spends the output, false if it creates it
+	pub is_spending: bool,
+}
+
+/// Optional index of canon chain transactions by `sha256(script_pubkey)`.
+pub trait AddressIndexProvider {
+	/// Returns all fundings and spendings of outputs with given script hash, oldest first.
+	/// Fails with `Error::AddressIndexDisabled` if index is not maintained by the storage.
+	fn script_hash_history(&self, script_hash: &SHA256) -> Result<Vec<ScriptHashEntry>, Error>;
+}
@@ -0,0 +1,14 @@
+use crate::Error;
+use bitcrypto::SHA256D;
+use chain::BlockFilter;
+
+/// Optional index of BIP158 basic filters of canon chain blocks.
+pub trait BlockFilterProvider {
+	/// Returns basic filter of the canon block.
+	/// Fails with `Error::BlockFilterIndexDisabled` if filters are not maintained by the storage.
+	fn block_filter(&self, block_hash: &SHA256D) -> Result<Option<BlockFilter>, Error>;
+
+	/// Returns header of the basic filter of the canon block.
+	/// Fails with `Error::BlockFilterIndexDisabled` if filters are not maintained by the storage.
+	fn block_filter_header(&self, block_hash: &SHA256D) -> Result<Option<SHA256D>, Error>;
+}
@@ -8,6 +8,8 @@ pub enum Error {
 	UnknownParent,
 	AncientFork,
 	InconsistentData,
+	AddressIndexDisabled,
+	BlockFilterIndexDisabled,
 }
 
 impl fmt::Display for Error {
@@ -19,6 +21,8 @@ impl fmt::Display for Error {
 			Error::UnknownParent => write!(f, "Block parent is unknown"),
 			Error::AncientFork => write!(f, "Fork is too long to proceed"),
 			Error::InconsistentData => write!(f, "Database inconsistency detected"),
+			Error::AddressIndexDisabled => write!(f, "Address index is disabled"),
+			Error::BlockFilterIndexDisabled => write!(f, "Block filter index is disabled"),
 		}
 	}
 }
@@ -10,8 +10,10 @@ extern crate serialization as ser;
 #[macro_use]
 extern crate serialization_derive;
 
+mod address_index;
 mod block_ancestors;
 mod block_chain;
+mod block_filter;
 mod block_height;
 mod block_impls;
 mod block_iterator;
@@ -27,8 +29,10 @@ mod transaction_provider;
 
 pub use primitives::{bytes, hash};
 
+pub use address_index::{AddressIndexProvider, ScriptHashEntry};
 pub use block_ancestors::BlockAncestors;
 pub use block_chain::{BlockChain, ForkChain, Forkable};
+pub use block_filter::BlockFilterProvider;
 pub use block_height::BlockHeight;
 pub use block_iterator::BlockIterator;
 pub use block_meta::BlockMeta;
@@ -1,6 +1,7 @@
 use crate::BlockHeight;
 use crate::{
-	BlockChain, BlockHeaderProvider, BlockProvider, Forkable, TransactionMetaProvider, TransactionOutputProvider, TransactionProvider,
+	AddressIndexProvider, BlockChain, BlockFilterProvider, BlockHeaderProvider, BlockProvider, Forkable, TransactionMetaProvider,
+	TransactionOutputProvider, TransactionProvider,
 };
 use chain::IndexedBlockHeader;
 use std::sync::Arc;
@@ -25,7 +26,15 @@ pub trait Store: AsSubstore {
 }
 
 /// Allows casting Arc<Store> to reference to any substore type
-pub trait AsSubstore: BlockChain + BlockProvider + TransactionProvider + TransactionMetaProvider + TransactionOutputProvider {
+pub trait AsSubstore:
+	BlockChain
+	+ BlockProvider
+	+ TransactionProvider
+	+ TransactionMetaProvider
+	+ TransactionOutputProvider
+	+ AddressIndexProvider
+	+ BlockFilterProvider
+{
 	fn as_block_provider(&self) -> &dyn BlockProvider;
 
 	fn as_block_header_provider(&self) -> &dyn BlockHeaderProvider;
@@ -35,11 +44,21 @@ pub trait AsSubstore: BlockChain + BlockProvider + TransactionProvider + Transac
 	fn as_transaction_output_provider(&self) -> &dyn TransactionOutputProvider;
 
 	fn as_transaction_meta_provider(&self) -> &dyn TransactionMetaProvider;
+
+	fn as_address_index_provider(&self) -> &dyn AddressIndexProvider;
+
+	fn as_block_filter_provider(&self) -> &dyn BlockFilterProvider;
 }
 
 impl<T> AsSubstore for T
 where
-	T: BlockChain + BlockProvider + TransactionProvider + TransactionMetaProvider + TransactionOutputProvider,
+	T: BlockChain
+		+ BlockProvider
+		+ TransactionProvider
+		+ TransactionMetaProvider
+		+ TransactionOutputProvider
+		+ AddressIndexProvider
+		+ BlockFilterProvider,
 {
 	fn as_block_provider(&self) -> &dyn BlockProvider {
 		&*self
@@ -60,6 +79,14 @@ where
 	fn as_transaction_meta_provider(&self) -> &dyn TransactionMetaProvider {
 		&*self
 	}
+
+	fn as_address_index_provider(&self) -> &dyn AddressIndexProvider {
+		&*self
+	}
+
+	fn as_block_filter_provider(&self) -> &dyn BlockFilterProvider {
+		&*self
+	}
 }
 
 pub type SharedStore = Arc<dyn CanonStore + Send + Sync>;
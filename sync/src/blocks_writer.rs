@@ -135,6 +135,10 @@ impl TransactionVerificationSink for BlocksWriterSink {
 		unreachable!("not intended to verify transactions")
 	}
 
+	fn on_package_verification_success(&self, _package: Vec<chain::IndexedTransaction>) {
+		unreachable!("not intended to verify transactions")
+	}
+
 	fn on_transaction_verification_error(&self, _err: &str, _hash: &SHA256D) {
 		unreachable!("not intended to verify transactions")
 	}
@@ -1,4 +1,5 @@
 use crate::chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
+use crate::message::common::InventoryType;
 use crate::message::types;
 use crate::p2p::{InboundSyncConnection, InboundSyncConnectionRef, InboundSyncConnectionStateRef};
 use crate::types::{LocalNodeRef, PeerIndex, PeersRef, RequestId};
@@ -54,6 +55,16 @@ impl InboundSyncConnection for InboundConnection {
 			return;
 		}
 
+		// peer knows transactions it announces, so they are never announced back
+		for item in &message.inventory {
+			if matches!(
+				item.inv_type,
+				InventoryType::MessageTx | InventoryType::MessageWtx | InventoryType::MessageWitnessTx
+			) {
+				self.peers.hash_known_as(self.peer_index, item.hash, KnownHashType::Transaction);
+			}
+		}
+
 		self.node.on_inventory(self.peer_index, message);
 	}
 
@@ -86,6 +97,10 @@ impl InboundSyncConnection for InboundConnection {
 		let tx = IndexedTransaction::from_raw(message.transaction);
 		self.peers
 			.hash_known_as(self.peer_index, tx.hash.clone(), KnownHashType::Transaction);
+		if tx.raw.has_witness() {
+			self.peers
+				.hash_known_as(self.peer_index, tx.raw.witness_hash(), KnownHashType::Transaction);
+		}
 		self.node.on_transaction(self.peer_index, tx);
 	}
 
@@ -183,9 +198,41 @@ impl InboundSyncConnection for InboundConnection {
 		self.node.on_block_txn(self.peer_index, message);
 	}
 
+	fn on_get_cfilters(&self, message: types::GetCFilters) {
+		self.node.on_get_cfilters(self.peer_index, message);
+	}
+
+	fn on_get_cfheaders(&self, message: types::GetCFHeaders) {
+		self.node.on_get_cfheaders(self.peer_index, message);
+	}
+
+	fn on_get_cfcheckpt(&self, message: types::GetCFCheckpt) {
+		self.node.on_get_cfcheckpt(self.peer_index, message);
+	}
+
 	fn on_notfound(&self, message: types::NotFound) {
 		self.node.on_notfound(self.peer_index, message);
 	}
+
+	fn on_wtxid_relay(&self, message: types::WtxidRelay) {
+		self.node.on_wtxid_relay(self.peer_index, message);
+	}
+
+	fn on_sendtxrcncl(&self, message: types::SendTxRcncl, local_salt: u64, is_initiator: bool) {
+		self.node.on_sendtxrcncl(self.peer_index, message, local_salt, is_initiator);
+	}
+
+	fn on_reqrecon(&self, message: types::ReqRecon) {
+		self.node.on_reqrecon(self.peer_index, message);
+	}
+
+	fn on_sketch(&self, message: types::Sketch) {
+		self.node.on_sketch(self.peer_index, message);
+	}
+
+	fn on_reconcildiff(&self, message: types::ReconcilDiff) {
+		self.node.on_reconcildiff(self.peer_index, message);
+	}
 }
 
 #[cfg(test)]
@@ -195,16 +242,19 @@ pub mod tests {
 	use p2p::OutboundSyncConnection;
 	use parking_lot::Mutex;
 	use std::collections::HashMap;
+	use std::sync::atomic::{AtomicBool, Ordering};
 	use std::sync::Arc;
 
 	pub struct DummyOutboundSyncConnection {
 		pub messages: Mutex<HashMap<String, usize>>,
+		pub historical_block_serving_limited: AtomicBool,
 	}
 
 	impl DummyOutboundSyncConnection {
 		pub fn new() -> Arc<DummyOutboundSyncConnection> {
 			Arc::new(DummyOutboundSyncConnection {
 				messages: Mutex::new(HashMap::new()),
+				historical_block_serving_limited: AtomicBool::new(false),
 			})
 		}
 	}
@@ -273,10 +323,32 @@ pub mod tests {
 		fn send_block_txn(&self, _message: types::BlockTxn) {
 			*self.messages.lock().entry("blocktxn".to_owned()).or_insert(0) += 1;
 		}
+		fn send_cfilter(&self, _message: types::CFilter) {
+			*self.messages.lock().entry("cfilter".to_owned()).or_insert(0) += 1;
+		}
+		fn send_cfheaders(&self, _message: types::CFHeaders) {
+			*self.messages.lock().entry("cfheaders".to_owned()).or_insert(0) += 1;
+		}
+		fn send_cfcheckpt(&self, _message: types::CFCheckpt) {
+			*self.messages.lock().entry("cfcheckpt".to_owned()).or_insert(0) += 1;
+		}
 		fn send_notfound(&self, _message: types::NotFound) {
 			*self.messages.lock().entry("notfound".to_owned()).or_insert(0) += 1;
 		}
+		fn send_reqrecon(&self, _message: types::ReqRecon) {
+			*self.messages.lock().entry("reqrecon".to_owned()).or_insert(0) += 1;
+		}
+		fn send_sketch(&self, _message: types::Sketch) {
+			*self.messages.lock().entry("sketch".to_owned()).or_insert(0) += 1;
+		}
+		fn send_reconcildiff(&self, _message: types::ReconcilDiff) {
+			*self.messages.lock().entry("reconcildiff".to_owned()).or_insert(0) += 1;
+		}
+		fn is_historical_block_serving_limited(&self) -> bool {
+			self.historical_block_serving_limited.load(Ordering::SeqCst)
+		}
 		fn ignored(&self, _id: RequestId) {}
 		fn close(&self) {}
+		fn ban(&self) {}
 	}
 }
@@ -36,12 +36,14 @@ mod types;
 mod utils;
 
 pub use types::LocalNodeRef;
+pub use types::MemoryPoolRef;
 pub use types::PeersRef;
 
 use bitcrypto::SHA256D;
 use message::Services;
 use network::{ConsensusParams, Network};
 use parking_lot::RwLock;
+use std::path::PathBuf;
 use std::sync::Arc;
 use utils::AverageSpeedMeter;
 use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
@@ -70,6 +72,33 @@ pub struct VerificationParameters {
 	pub verification_edge: SHA256D,
 }
 
+#[derive(Debug, Clone)]
+/// Memory pool parameters.
+pub struct MemoryPoolParameters {
+	/// Max memory usage of the memory pool, in bytes.
+	pub max_memory_usage: usize,
+	/// Transactions are expired after staying in the memory pool for this time, in seconds.
+	pub expiry: u64,
+	/// Allow replacement of transactions that do not signal replaceability (BIP125).
+	pub full_rbf: bool,
+	/// Limits on in-pool ancestors and descendants of single transaction.
+	pub package_limits: miner::MemoryPoolPackageLimits,
+	/// Memory pool is saved to this file on shutdown and loaded from it on startup. Not persisted if `None`.
+	pub dump_path: Option<PathBuf>,
+}
+
+impl Default for MemoryPoolParameters {
+	fn default() -> Self {
+		MemoryPoolParameters {
+			max_memory_usage: miner::DEFAULT_MEMORY_POOL_MAX_MEMORY_USAGE,
+			expiry: miner::DEFAULT_MEMORY_POOL_EXPIRY,
+			full_rbf: false,
+			package_limits: Default::default(),
+			dump_path: None,
+		}
+	}
+}
+
 /// Synchronization events listener
 pub trait SyncListener: Send + 'static {
 	/// Called when node switches to synchronization state
@@ -100,6 +129,7 @@ pub fn create_local_sync_node(
 	db: storage::SharedStore,
 	peers: PeersRef,
 	verification_params: VerificationParameters,
+	memory_pool_params: MemoryPoolParameters,
 ) -> LocalNodeRef {
 	use local_node::LocalNode as SyncNode;
 	use miner::MemoryPool;
@@ -118,6 +148,10 @@ pub fn create_local_sync_node(
 		close_connection_on_bad_block: network != Network::Regtest,
 	};
 	let mut memory_pool = MemoryPool::new();
+	memory_pool.set_max_memory_usage(memory_pool_params.max_memory_usage);
+	memory_pool.set_expiry(memory_pool_params.expiry);
+	memory_pool.set_full_rbf(memory_pool_params.full_rbf);
+	memory_pool.set_package_limits(memory_pool_params.package_limits);
 	if network == Network::Regtest {
 		// during regtests, peer is providing us with zero fee transactions => we shouldn't ignore these
 		memory_pool.accept_zero_fee_transactions();
@@ -147,7 +181,7 @@ pub fn create_local_sync_node(
 	);
 	let verifier_sink = Arc::new(CoreVerificationSink::new(sync_client_core.clone()));
 	let verifier = AsyncVerifier::new(chain_verifier, db.clone(), memory_pool.clone(), verifier_sink, verification_params);
-	let sync_client = SynchronizationClient::new(sync_state.clone(), sync_client_core, verifier);
+	let sync_client = SynchronizationClient::new(sync_client_core, verifier);
 	Arc::new(SyncNode::new(
 		consensus,
 		db,
@@ -156,6 +190,7 @@ pub fn create_local_sync_node(
 		sync_state,
 		sync_client,
 		sync_server,
+		memory_pool_params.dump_path,
 	))
 }
 
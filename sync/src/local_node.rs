@@ -6,16 +6,19 @@ use crate::synchronization_verifier::TransactionVerificationSink;
 use crate::types::{
 	ClientRef, MemoryPoolRef, PeerIndex, PeersRef, RequestId, ServerRef, StorageRef, SyncListenerRef, SynchronizationStateRef,
 };
+use crate::utils::Reconciliation;
 use bitcrypto::SHA256D;
 use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
 use futures::future::lazy;
-use keys::AddressHash;
+use keys::Address;
 use message::types;
 use miner::block_template::BlockTemplate;
-use miner::{mine_block, BlockAssembler};
+use miner::{mine_block, BlockAssembler, MemoryPoolDump};
 use network::ConsensusParams;
 use parking_lot::{Condvar, Mutex};
 use primitives::time::{RealTime, Time};
+use std::io;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 /// Local synchronization node
@@ -34,6 +37,8 @@ pub struct LocalNode<U: Server, V: Client> {
 	client: ClientRef<V>,
 	/// Synchronization server
 	server: ServerRef<U>,
+	/// Memory pool dump file path
+	memory_pool_dump_path: Option<PathBuf>,
 }
 
 /// Transaction accept verification sink
@@ -62,6 +67,7 @@ where
 		state: SynchronizationStateRef,
 		client: ClientRef<V>,
 		server: ServerRef<U>,
+		memory_pool_dump_path: Option<PathBuf>,
 	) -> Self {
 		LocalNode {
 			consensus,
@@ -71,11 +77,18 @@ where
 			state,
 			client,
 			server,
+			memory_pool_dump_path,
 		}
 	}
 
 	pub fn shutdown(&self) {
 		self.client.shutdown();
+		if self.memory_pool_dump_path.is_some() {
+			match self.save_memory_pool() {
+				Ok(count) => info!("Saved {} memory pool transactions", count),
+				Err(err) => warn!(target: "sync", "Failed to save memory pool: {}", err),
+			}
+		}
 	}
 
 	/// Return shared reference to synchronization state.
@@ -83,6 +96,11 @@ where
 		self.state.clone()
 	}
 
+	/// Return shared reference to the memory pool.
+	pub fn memory_pool(&self) -> MemoryPoolRef {
+		self.memory_pool.clone()
+	}
+
 	pub fn information(&self) -> Information {
 		self.client.information()
 	}
@@ -185,6 +203,39 @@ where
 		self.server.execute(ServerTask::GetBlockTxn(peer_index, message));
 	}
 
+	/// When peer asks us for filters of canon blocks
+	pub fn on_get_cfilters(&self, peer_index: PeerIndex, message: types::GetCFilters) {
+		if self.state.synchronizing() {
+			trace!(target: "sync", "Ignored `getcfilters` message from peer#{}", peer_index);
+			return;
+		}
+
+		trace!(target: "sync", "Got `getcfilters` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFilters(peer_index, message));
+	}
+
+	/// When peer asks us for filter headers of canon blocks
+	pub fn on_get_cfheaders(&self, peer_index: PeerIndex, message: types::GetCFHeaders) {
+		if self.state.synchronizing() {
+			trace!(target: "sync", "Ignored `getcfheaders` message from peer#{}", peer_index);
+			return;
+		}
+
+		trace!(target: "sync", "Got `getcfheaders` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFHeaders(peer_index, message));
+	}
+
+	/// When peer asks us for filter headers checkpoints
+	pub fn on_get_cfcheckpt(&self, peer_index: PeerIndex, message: types::GetCFCheckpt) {
+		if self.state.synchronizing() {
+			trace!(target: "sync", "Ignored `getcfcheckpt` message from peer#{}", peer_index);
+			return;
+		}
+
+		trace!(target: "sync", "Got `getcfcheckpt` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFCheckpt(peer_index, message));
+	}
+
 	/// When peer sets bloom filter for connection
 	pub fn on_filterload(&self, peer_index: PeerIndex, message: types::FilterLoad) {
 		trace!(target: "sync", "Got `filterload` message from peer#{}", peer_index);
@@ -209,6 +260,56 @@ where
 		self.peers.set_fee_filter(peer_index, message);
 	}
 
+	/// When peer asks us to announce transactions using their witness hashes
+	pub fn on_wtxid_relay(&self, peer_index: PeerIndex, _message: types::WtxidRelay) {
+		trace!(target: "sync", "Got `wtxidrelay` message from peer#{}", peer_index);
+		self.peers.set_wtxid_relay(peer_index);
+	}
+
+	/// When peer has agreed to reconcile transactions with us
+	pub fn on_sendtxrcncl(&self, peer_index: PeerIndex, message: types::SendTxRcncl, local_salt: u64, is_initiator: bool) {
+		trace!(target: "sync", "Got `sendtxrcncl` message from peer#{}", peer_index);
+		if !self.peers.is_wtxid_relay(peer_index) {
+			return;
+		}
+
+		self.peers
+			.set_reconciliation(peer_index, Reconciliation::new(is_initiator, local_salt, message.salt));
+	}
+
+	/// When peer asks us for the sketch of transactions we are going to announce
+	pub fn on_reqrecon(&self, peer_index: PeerIndex, message: types::ReqRecon) {
+		if self.state.synchronizing() {
+			trace!(target: "sync", "Ignored `reqrecon` message from peer#{}", peer_index);
+			return;
+		}
+
+		trace!(target: "sync", "Got `reqrecon` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::ReqRecon(peer_index, message));
+	}
+
+	/// When peer responds to our reconciliation request
+	pub fn on_sketch(&self, peer_index: PeerIndex, message: types::Sketch) {
+		if self.state.synchronizing() {
+			trace!(target: "sync", "Ignored `sketch` message from peer#{}", peer_index);
+			return;
+		}
+
+		trace!(target: "sync", "Got `sketch` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::Sketch(peer_index, message));
+	}
+
+	/// When peer finishes reconciliation round
+	pub fn on_reconcildiff(&self, peer_index: PeerIndex, message: types::ReconcilDiff) {
+		if self.state.synchronizing() {
+			trace!(target: "sync", "Ignored `reconcildiff` message from peer#{}", peer_index);
+			return;
+		}
+
+		trace!(target: "sync", "Got `reconcildiff` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::ReconcilDiff(peer_index, message));
+	}
+
 	/// When peer asks us to announce new blocks using headers message
 	pub fn on_sendheaders(&self, peer_index: PeerIndex, _message: types::SendHeaders) {
 		trace!(target: "sync", "Got `sendheaders` message from peer#{}", peer_index);
@@ -220,14 +321,10 @@ where
 	pub fn on_send_compact(&self, peer_index: PeerIndex, message: types::SendCompact) {
 		trace!(target: "sync", "Got `sendcmpct` message from peer#{}", peer_index);
 
-		// The second integer SHALL be interpreted as a little-endian version number. Nodes sending a sendcmpct message MUST currently set this value to 1.
-		// TODO: version 2 supports segregated witness transactions
-		if message.second != 1 {
-			return;
-		}
-
+		// The second integer SHALL be interpreted as a little-endian version number.
+		// We are only building compact blocks of version 1.
 		// Upon receipt of a "sendcmpct" message with the first and second integers set to 1, the node SHOULD announce new blocks by sending a cmpctblock message.
-		if message.first {
+		if message.second == 1 && message.first {
 			self.peers
 				.set_block_announcement_type(peer_index, BlockAnnouncementType::SendCompactBlock);
 		}
@@ -236,6 +333,9 @@ where
 		// Upon receipt of a "sendcmpct" message with the first integer set to 0, the node SHOULD NOT announce new blocks by sending a cmpctblock message,
 		// but SHOULD announce new blocks by sending invs or headers, as defined by BIP130.
 		// => work as before
+
+		// version 2 supports segregated witness transactions => we could ask peer for compact blocks
+		self.client.on_send_compact(peer_index, message);
 	}
 
 	/// When peer sents us a merkle block
@@ -246,17 +346,15 @@ where
 	}
 
 	/// When peer sents us a compact block
-	pub fn on_compact_block(&self, peer_index: PeerIndex, _message: types::CompactBlock) {
+	pub fn on_compact_block(&self, peer_index: PeerIndex, message: types::CompactBlock) {
 		trace!(target: "sync", "Got `cmpctblock` message from peer#{}", peer_index);
-		// we never ask compact block from peers => misbehaving
-		self.peers.misbehaving(peer_index, "Got unrequested 'cmpctblock' message");
+		self.client.on_compact_block(peer_index, message);
 	}
 
 	/// When peer sents us specific transactions for specific block
-	pub fn on_block_txn(&self, peer_index: PeerIndex, _message: types::BlockTxn) {
+	pub fn on_block_txn(&self, peer_index: PeerIndex, message: types::BlockTxn) {
 		trace!(target: "sync", "Got `blocktxn` message from peer#{}", peer_index);
-		// we never ask for this => misbehaving
-		self.peers.misbehaving(peer_index, "Got unrequested 'blocktxn' message");
+		self.client.on_block_txn(peer_index, message);
 	}
 
 	/// Verify new block
@@ -277,6 +375,69 @@ where
 		sink_data.wait()
 	}
 
+	/// Verify and then schedule new package of transactions.
+	/// Returns hashes of all package transactions if package has been accepted.
+	pub fn accept_package(&self, package: Vec<IndexedTransaction>) -> Result<Vec<SHA256D>, String> {
+		let hashes = package.iter().map(|tx| tx.hash).collect();
+		let sink_data = Arc::new(TransactionAcceptSinkData::default());
+		let sink = TransactionAcceptSink::new(sink_data.clone()).boxed();
+		self.client.accept_package(package, sink)?;
+		sink_data.wait().map(|_| hashes)
+	}
+
+	/// Save memory pool transactions (with their entry times and virtual fees) to the dump file.
+	/// Returns number of saved transactions.
+	pub fn save_memory_pool(&self) -> Result<usize, String> {
+		let path = self
+			.memory_pool_dump_path
+			.as_ref()
+			.ok_or_else(|| "Memory pool persistence is disabled".to_owned())?;
+		let dump = self.memory_pool.read().dump();
+		dump.write_to_file(path).map_err(|err| err.to_string())?;
+		Ok(dump.transactions.len())
+	}
+
+	/// Load memory pool transactions from the dump file. Every transaction is verified again (even while
+	/// synchronizing), so that transactions which are no longer valid at the current best block are dropped.
+	/// Loaded transactions are not relayed. Returns number of loaded transactions.
+	pub fn load_memory_pool(&self) -> Result<usize, String> {
+		let path = match self.memory_pool_dump_path {
+			Some(ref path) => path,
+			None => return Ok(0),
+		};
+		let dump = match MemoryPoolDump::read_from_file(path) {
+			Ok(dump) => dump,
+			Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+			Err(err) => return Err(err.to_string()),
+		};
+
+		let mut loaded = 0usize;
+		for dumped in dump.transactions {
+			// virtual fee must be known before acceptance, since transaction may not pay enough on its own
+			let transaction: IndexedTransaction = dumped.transaction.into();
+			let hash = transaction.hash;
+			self.memory_pool.write().set_virtual_fee(&hash, dumped.virtual_fee);
+
+			let sink_data = Arc::new(TransactionAcceptSinkData::default());
+			let sink = TransactionAcceptSink::new(sink_data.clone()).boxed();
+			if let Err(err) = self.client.load_transaction(transaction, sink).and_then(|_| sink_data.wait()) {
+				trace!(target: "sync", "Dropped memory pool transaction on load: {}", err);
+				self.memory_pool.write().set_virtual_fee(&hash, 0);
+				continue;
+			}
+
+			let mut memory_pool = self.memory_pool.write();
+			if memory_pool.contains(&hash) {
+				memory_pool.set_entry_time(&hash, dumped.time);
+				loaded += 1;
+			}
+		}
+
+		// entry times are restored => some transactions could be expired now
+		let expired = self.memory_pool.write().expire(RealTime.now().as_secs());
+		Ok(loaded.saturating_sub(expired.len()))
+	}
+
 	/// Get block template for mining
 	pub fn get_block_template(&self) -> BlockTemplate {
 		let block_assembler = BlockAssembler {
@@ -287,7 +448,7 @@ where
 		block_assembler.create_new_block(&self.storage, memory_pool, RealTime.now().as_secs() as u32, &self.consensus)
 	}
 
-	pub fn generate_block(&self, to_address: Option<AddressHash>, max_tries: usize) -> Option<SHA256D> {
+	pub fn generate_block(&self, to_address: Option<Address>, max_tries: usize) -> Option<SHA256D> {
 		let block_template = self.get_block_template();
 		if let Some(block) = mine_block(block_template, to_address, max_tries) {
 			let block_hash = block.hash().clone();
@@ -334,6 +495,12 @@ impl TransactionVerificationSink for TransactionAcceptSink {
 		self.data.waiter.notify_all();
 	}
 
+	fn on_package_verification_success(&self, package: Vec<IndexedTransaction>) {
+		let child = package.last().expect("packages are never empty");
+		*self.data.result.lock() = Some(Ok(child.hash));
+		self.data.waiter.notify_all();
+	}
+
 	fn on_transaction_verification_error(&self, err: &str, _hash: &SHA256D) {
 		*self.data.result.lock() = Some(Err(err.to_owned()));
 		self.data.waiter.notify_all();
@@ -361,12 +528,14 @@ pub mod tests {
 	use db::BlockChainDatabase;
 	use message::common::{InventoryType, InventoryVector};
 	use message::types;
-	use miner::MemoryPool;
+	use miner::{DumpedTransaction, MemoryPool, MemoryPoolDump};
 	use network::{ConsensusParams, Network};
 	use parking_lot::RwLock;
 	use primitives::bytes::Bytes;
+	use primitives::time::{RealTime, Time};
 	use std::iter::repeat;
 	use std::sync::Arc;
+	use std::{env, fs};
 	use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 
 	pub fn default_filterload() -> types::FilterLoad {
@@ -415,7 +584,7 @@ pub mod tests {
 			None => DummyVerifier::default(),
 		};
 		verifier.set_sink(Arc::new(CoreVerificationSink::new(client_core.clone())));
-		let client = SynchronizationClient::new(sync_state.clone(), client_core, verifier);
+		let client = SynchronizationClient::new(client_core, verifier);
 		let local_node = LocalNode::new(
 			ConsensusParams::new(Network::Mainnet),
 			storage,
@@ -424,6 +593,7 @@ pub mod tests {
 			sync_state,
 			client,
 			server.clone(),
+			None,
 		);
 		(executor, server, local_node)
 	}
@@ -497,4 +667,65 @@ pub mod tests {
 
 		assert_eq!(executor.take_tasks(), vec![]);
 	}
+
+	#[test]
+	fn local_node_saves_and_loads_memory_pool() {
+		let dump_path = env::temp_dir().join("bitcoin-rs-test-mempool.dat");
+		let genesis = test_data::genesis();
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(1)
+			.add_input(&genesis.transactions[0], 0)
+			.into();
+		let transaction_hash = transaction.hash();
+
+		let (_, _, mut local_node) = create_local_node(None);
+		local_node.memory_pool_dump_path = Some(dump_path.clone());
+		assert_eq!(local_node.accept_transaction(transaction.into()), Ok(transaction_hash));
+		local_node.memory_pool().write().set_virtual_fee(&transaction_hash, 100);
+		let entry_information = local_node.memory_pool().read().entry_information(&transaction_hash);
+		assert_eq!(local_node.save_memory_pool(), Ok(1));
+
+		// transaction is accepted again with the same entry time and virtual fee
+		let (_, _, mut local_node) = create_local_node(None);
+		local_node.memory_pool_dump_path = Some(dump_path.clone());
+		assert_eq!(local_node.load_memory_pool(), Ok(1));
+		assert_eq!(
+			local_node.memory_pool().read().entry_information(&transaction_hash),
+			entry_information
+		);
+
+		fs::remove_file(dump_path).unwrap();
+	}
+
+	#[test]
+	fn local_node_loads_memory_pool_with_virtual_fee_and_without_relay() {
+		let dump_path = env::temp_dir().join("bitcoin-rs-test-mempool-virtual-fee.dat");
+		let genesis = test_data::genesis();
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(5_000_000_000)
+			.add_input(&genesis.transactions[0], 0)
+			.into();
+		let transaction_hash = transaction.hash();
+
+		// zero fee transaction is only accepted because of its virtual fee
+		let dump = MemoryPoolDump {
+			transactions: vec![DumpedTransaction {
+				transaction,
+				time: RealTime.now().as_secs(),
+				virtual_fee: 1000,
+			}],
+		};
+		dump.write_to_file(&dump_path).unwrap();
+
+		let (executor, _, mut local_node) = create_local_node(None);
+		local_node.on_connect(0, "test".into(), types::Version::default());
+		executor.take_tasks();
+		local_node.memory_pool_dump_path = Some(dump_path.clone());
+		assert_eq!(local_node.load_memory_pool(), Ok(1));
+
+		let entry_information = local_node.memory_pool().read().entry_information(&transaction_hash).unwrap();
+		assert_eq!(entry_information.fee, 0);
+		assert_eq!(entry_information.modified_fee, 1000);
+		assert_eq!(executor.take_tasks(), vec![]);
+
+		fs::remove_file(dump_path).unwrap();
+	}
 }
@@ -8,6 +8,7 @@ use primitives::bytes::Bytes;
 use std::collections::{HashSet, VecDeque};
 use std::fmt;
 use storage;
+use verification::{PackageError, TransactionError};
 
 /// Index of 'verifying' queue
 const VERIFYING_QUEUE: usize = 0;
@@ -549,6 +550,14 @@ impl Chain {
 		TransactionState::Unknown
 	}
 
+	/// Get state of transaction with given witness hash. Only memory pool is indexed by witness hashes.
+	pub fn witness_transaction_state(&self, witness_hash: &SHA256D) -> TransactionState {
+		match self.memory_pool.read().hash_by_witness_hash(witness_hash) {
+			Some(_) => TransactionState::InMemory,
+			None => TransactionState::Unknown,
+		}
+	}
+
 	/// Get transactions hashes with given state
 	pub fn transactions_hashes_with_state(&self, state: TransactionState) -> Vec<SHA256D> {
 		match state {
@@ -610,17 +619,46 @@ impl Chain {
 		})
 	}
 
-	/// Insert transaction to memory pool
-	pub fn insert_verified_transaction(&mut self, transaction: IndexedTransaction) {
+	/// Insert transaction to memory pool. Returns transactions, which have been expired or evicted from the pool
+	/// (along with the error if transaction has been rejected)
+	pub fn insert_verified_transaction(
+		&mut self,
+		transaction: IndexedTransaction,
+	) -> Result<Vec<IndexedTransaction>, (TransactionError, Vec<IndexedTransaction>)> {
 		// we have verified transaction, but possibly this transaction replaces
 		// existing transaction from memory pool
 		// => remove previous transactions before
 		let mut memory_pool = self.memory_pool.write();
+		let mut replaced = Vec::new();
 		for input in &transaction.raw.inputs {
-			memory_pool.remove_by_prevout(&input.previous_output);
+			replaced.extend(memory_pool.remove_by_prevout(&input.previous_output).unwrap_or_default());
 		}
 		// now insert transaction itself
-		memory_pool.insert_verified(transaction, &FeeCalculator(self.storage.as_transaction_output_provider()));
+		let fee_calculator = FeeCalculator(self.storage.as_transaction_output_provider());
+		memory_pool
+			.insert_verified(transaction, &fee_calculator)
+			.map_err(|(err, mut removed)| {
+				// transaction has been rejected => restore transactions it was going to replace
+				for transaction in replaced {
+					match memory_pool.insert_verified(transaction.clone(), &fee_calculator) {
+						Ok(evicted) => removed.extend(evicted),
+						Err((_, evicted)) => {
+							removed.extend(evicted);
+							removed.push(transaction);
+						}
+					}
+				}
+				(err, removed)
+			})
+	}
+
+	/// Insert verified package of transactions into memory pool. Returns transactions, which have been expired or evicted from the pool
+	pub fn insert_verified_package(&mut self, package: Vec<IndexedTransaction>) -> Result<Vec<IndexedTransaction>, PackageError> {
+		// package transactions are never replacing memory pool transactions
+		// => no need to remove conflicting transactions first
+		self.memory_pool
+			.write()
+			.insert_verified_package(package, &FeeCalculator(self.storage.as_transaction_output_provider()))
 	}
 
 	/// Calculate block locator hashes for hash queue
@@ -757,9 +795,10 @@ mod tests {
 	use bitcrypto::SHA256D;
 	use chain::{IndexedBlockHeader, Transaction};
 	use db::BlockChainDatabase;
-	use miner::MemoryPool;
+	use miner::{MemoryPool, MemoryPoolPackageLimits};
 	use parking_lot::RwLock;
 	use std::sync::Arc;
+	use verification::TransactionError;
 
 	#[test]
 	fn chain_empty() {
@@ -990,7 +1029,7 @@ mod tests {
 		let tx1_hash = tx1.hash();
 		let tx2_hash = tx2.hash();
 		chain.verify_transaction(tx1.into());
-		chain.insert_verified_transaction(tx2.into());
+		chain.insert_verified_transaction(tx2.into()).unwrap();
 
 		assert_eq!(
 			chain.transaction_state(&genesis_block.transactions[0].hash()),
@@ -1001,6 +1040,37 @@ mod tests {
 		assert_eq!(chain.transaction_state(&tx2_hash), TransactionState::InMemory);
 	}
 
+	#[test]
+	fn chain_rejected_transaction_does_not_remove_conflicts() {
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
+			test_data::genesis().into(),
+			test_data::block_h1().into(),
+		]));
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		let mut chain = Chain::new(db, memory_pool.clone());
+		let tx0: Transaction = test_data::TransactionBuilder::with_input(&test_data::block_h1().transactions[0], 0).into();
+		let tx1: Transaction = test_data::TransactionBuilder::with_input(&test_data::genesis().transactions[0], 0).into();
+		let tx2: Transaction = test_data::TransactionBuilder::with_input(&test_data::genesis().transactions[0], 0)
+			.add_input(&tx0, 0)
+			.into();
+		let tx1_hash = tx1.hash();
+		chain.insert_verified_transaction(tx0.into()).unwrap();
+		chain.insert_verified_transaction(tx1.into()).unwrap();
+
+		// tx2 replaces tx1, but is rejected, because it has too many in-pool ancestors
+		memory_pool.write().set_package_limits(MemoryPoolPackageLimits {
+			ancestors_count: 1,
+			..Default::default()
+		});
+		let (err, removed) = chain.insert_verified_transaction(tx2.into()).unwrap_err();
+		assert_eq!(err, TransactionError::TooManyAncestors(1));
+		assert!(removed.is_empty());
+
+		// => tx1 is still in the memory pool
+		assert_eq!(chain.transaction_state(&tx1_hash), TransactionState::InMemory);
+		assert_eq!(chain.information().transactions.transactions_count, 2);
+	}
+
 	#[test]
 	fn chain_block_transaction_is_removed_from_on_block_insert() {
 		#[rustfmt::skip]
@@ -1026,7 +1096,7 @@ mod tests {
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
 		chain.verify_transaction(tx1.into());
-		chain.insert_verified_transaction(tx2.into());
+		chain.insert_verified_transaction(tx2.into()).unwrap();
 
 		// only one transaction is in the memory pool
 		assert_eq!(chain.information().transactions.transactions_count, 1);
@@ -1091,10 +1161,10 @@ mod tests {
 			test_data::block_h1().into(),
 		]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
-		chain.insert_verified_transaction(test_chain.at(0).into());
-		chain.insert_verified_transaction(test_chain.at(1).into());
-		chain.insert_verified_transaction(test_chain.at(2).into());
-		chain.insert_verified_transaction(test_chain.at(3).into());
+		chain.insert_verified_transaction(test_chain.at(0).into()).unwrap();
+		chain.insert_verified_transaction(test_chain.at(1).into()).unwrap();
+		chain.insert_verified_transaction(test_chain.at(2).into()).unwrap();
+		chain.insert_verified_transaction(test_chain.at(3).into()).unwrap();
 
 		let chain_transactions = chain.transactions_hashes_with_state(TransactionState::InMemory);
 		assert!(chain_transactions.contains(&test_chain.at(0).hash()));
@@ -1126,7 +1196,7 @@ mod tests {
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
 		chain.verify_transaction(tx1.into());
-		chain.insert_verified_transaction(tx2.into());
+		chain.insert_verified_transaction(tx2.into()).unwrap();
 
 		// no reorg
 		let result = chain.insert_best_block(b1.into()).expect("no error");
@@ -1190,11 +1260,11 @@ mod tests {
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
 
-		chain.insert_verified_transaction(tx1.into());
-		chain.insert_verified_transaction(tx2.into());
-		chain.insert_verified_transaction(tx3.into());
-		chain.insert_verified_transaction(tx4.into());
-		chain.insert_verified_transaction(tx5.into());
+		chain.insert_verified_transaction(tx1.into()).unwrap();
+		chain.insert_verified_transaction(tx2.into()).unwrap();
+		chain.insert_verified_transaction(tx3.into()).unwrap();
+		chain.insert_verified_transaction(tx4.into()).unwrap();
+		chain.insert_verified_transaction(tx5.into()).unwrap();
 
 		assert_eq!(
 			chain.insert_best_block(b0.clone().into()).expect("block rejected"),
@@ -1246,7 +1316,7 @@ mod tests {
 		// insert tx2 to memory pool
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
-		chain.insert_verified_transaction(tx2.clone().into());
+		chain.insert_verified_transaction(tx2.clone().into()).unwrap();
 		// insert verified block with tx1
 		chain.insert_best_block(b0.into()).expect("no error");
 		// => tx2 is removed from memory pool, but tx3 remains
@@ -1274,10 +1344,10 @@ mod tests {
 
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
-		chain.insert_verified_transaction(data_chain.at(0).into());
-		chain.insert_verified_transaction(data_chain.at(1).into());
+		chain.insert_verified_transaction(data_chain.at(0).into()).unwrap();
+		chain.insert_verified_transaction(data_chain.at(1).into()).unwrap();
 		assert_eq!(chain.information().transactions.transactions_count, 2);
-		chain.insert_verified_transaction(data_chain.at(2).into());
+		chain.insert_verified_transaction(data_chain.at(2).into()).unwrap();
 		assert_eq!(chain.information().transactions.transactions_count, 2); // tx was replaced
 	}
 }
@@ -1,10 +1,11 @@
 use crate::synchronization_client_core::{ClientCore, Information, SynchronizationClientCore};
 use crate::synchronization_executor::TaskExecutor;
-use crate::synchronization_verifier::{TransactionVerificationSink, Verifier};
-use crate::types::{ClientCoreRef, PeerIndex, SyncListenerRef, SynchronizationStateRef, UnitFuture};
+use crate::synchronization_verifier::{TransactionVerificationSink, VerificationTask, Verifier};
+use crate::types::{ClientCoreRef, PeerIndex, SyncListenerRef, UnitFuture};
 use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
 use message::types;
 use parking_lot::Mutex;
+use std::collections::VecDeque;
 use std::sync::Arc;
 
 #[cfg_attr(feature = "cargo-clippy", allow(doc_markdown))]
@@ -129,9 +130,14 @@ pub trait Client: Send + Sync + 'static {
 	fn on_block(&self, peer_index: PeerIndex, block: IndexedBlock);
 	fn on_transaction(&self, peer_index: PeerIndex, transaction: IndexedTransaction);
 	fn on_notfound(&self, peer_index: PeerIndex, message: types::NotFound);
+	fn on_send_compact(&self, peer_index: PeerIndex, message: types::SendCompact);
+	fn on_compact_block(&self, peer_index: PeerIndex, message: types::CompactBlock);
+	fn on_block_txn(&self, peer_index: PeerIndex, message: types::BlockTxn);
 	fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: UnitFuture);
 	fn accept_block(&self, block: IndexedBlock);
 	fn accept_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String>;
+	fn accept_package(&self, package: Vec<IndexedTransaction>, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String>;
+	fn load_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String>;
 	fn install_sync_listener(&self, listener: SyncListenerRef);
 	fn shutdown(&self);
 }
@@ -140,8 +146,6 @@ pub trait Client: Send + Sync + 'static {
 pub struct SynchronizationClient<T: TaskExecutor, U: Verifier> {
 	/// Verification mutex
 	verification_lock: Mutex<()>,
-	/// Shared client state
-	shared_state: SynchronizationStateRef,
 	/// Client core
 	core: ClientCoreRef<SynchronizationClientCore<T>>,
 	/// Verifier
@@ -223,15 +227,8 @@ where
 		// on-time => this transaction + all dependent orphaned should be verified
 		let transactions_to_verify = self.core.lock().on_transaction(peer_index, transaction);
 
-		if let Some(mut transactions_to_verify) = transactions_to_verify {
-			// it is not actual height of block this transaction will be included to
-			// => it possibly will be invalid if included in later blocks
-			// => mined block can be rejected
-			// => we should verify blocks we mine
-			let next_block_height = self.shared_state.best_storage_block_height() + 1;
-			while let Some(tx) = transactions_to_verify.pop_front() {
-				self.verifier.verify_transaction(next_block_height, tx);
-			}
+		if let Some(transactions_to_verify) = transactions_to_verify {
+			self.verify_transactions(transactions_to_verify);
 		}
 	}
 
@@ -239,17 +236,44 @@ where
 		self.core.lock().on_notfound(peer_index, message);
 	}
 
+	fn on_send_compact(&self, peer_index: PeerIndex, message: types::SendCompact) {
+		self.core.lock().on_send_compact(peer_index, message);
+	}
+
+	fn on_compact_block(&self, peer_index: PeerIndex, message: types::CompactBlock) {
+		// reconstructed block is processed as if it was received in `block` message
+		let block = self.core.lock().on_compact_block(peer_index, message);
+		if let Some(block) = block {
+			self.on_block(peer_index, block);
+		}
+	}
+
+	fn on_block_txn(&self, peer_index: PeerIndex, message: types::BlockTxn) {
+		let block = self.core.lock().on_block_txn(peer_index, message);
+		if let Some(block) = block {
+			self.on_block(peer_index, block);
+		}
+	}
+
 	fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: UnitFuture) {
 		self.core.lock().after_peer_nearly_blocks_verified(peer_index, future);
 	}
 
 	fn accept_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String> {
-		let mut transactions_to_verify = self.core.lock().accept_transaction(transaction, sink)?;
+		let transactions_to_verify = self.core.lock().accept_transaction(transaction, sink)?;
+		self.verify_transactions(transactions_to_verify);
+		Ok(())
+	}
 
-		let next_block_height = self.shared_state.best_storage_block_height() + 1;
-		while let Some(tx) = transactions_to_verify.pop_front() {
-			self.verifier.verify_transaction(next_block_height, tx);
-		}
+	fn accept_package(&self, package: Vec<IndexedTransaction>, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String> {
+		let package_to_verify = self.core.lock().accept_package(package, sink)?;
+		self.verify_transactions(vec![package_to_verify].into());
+		Ok(())
+	}
+
+	fn load_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String> {
+		let transactions_to_verify = self.core.lock().load_transaction(transaction, sink)?;
+		self.verify_transactions(transactions_to_verify);
 		Ok(())
 	}
 
@@ -268,12 +292,25 @@ where
 	U: Verifier,
 {
 	/// Create new synchronization client
-	pub fn new(shared_state: SynchronizationStateRef, core: ClientCoreRef<SynchronizationClientCore<T>>, verifier: U) -> Arc<Self> {
+	pub fn new(core: ClientCoreRef<SynchronizationClientCore<T>>, verifier: U) -> Arc<Self> {
 		Arc::new(SynchronizationClient {
 			verification_lock: Mutex::new(()),
-			shared_state,
 			core,
 			verifier,
 		})
 	}
+
+	/// Schedule transactions && packages verification.
+	/// Verification is performed at the height of the next block. It is not actual height of block
+	/// this transaction will be included to => it possibly will be invalid if included in later blocks
+	/// => mined block can be rejected => we should verify blocks we mine
+	fn verify_transactions(&self, mut tasks: VecDeque<VerificationTask>) {
+		while let Some(task) = tasks.pop_front() {
+			match task {
+				VerificationTask::VerifyTransaction(height, transaction) => self.verifier.verify_transaction(height, transaction),
+				VerificationTask::VerifyPackage(height, package) => self.verifier.verify_package(height, package),
+				_ => unreachable!("only transactions verification tasks are created by the core"),
+			}
+		}
+	}
 }
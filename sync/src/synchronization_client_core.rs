@@ -8,11 +8,14 @@ use crate::synchronization_verifier::{BlockVerificationSink, TransactionVerifica
 use crate::types::{
 	AverageSpeedMeterRef, BlockHeight, ClientCoreRef, PeerIndex, PeersRef, SyncListenerRef, SynchronizationStateRef, UnitFuture,
 };
-use crate::utils::{AverageSpeedMeter, HashPosition, MessageBlockHeadersProvider, OrphanBlocksPool, OrphanTransactionsPool};
+use crate::utils::{
+	AverageSpeedMeter, CompactBlockReconstruction, HashPosition, MessageBlockHeadersProvider, OrphanBlocksPool, OrphanTransactionsPool,
+	PartialCompactBlock,
+};
 use crate::verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 use bitcrypto::SHA256D;
 use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
-use message::common::{InventoryType, InventoryVector};
+use message::common::{BlockTransactionsRequest, InventoryType, InventoryVector};
 use message::types;
 use miner::transaction_fee_rate;
 use parking_lot::Mutex;
@@ -44,6 +47,14 @@ const MIN_BLOCK_DUPLICATION_INTERVAL_S: Duration = Duration::from_secs(10);
 const MAX_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 4;
 /// Minimal number of blocks in duplicate requests.
 const MIN_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 8;
+/// Compact blocks version we are supporting (version 2 uses wtxid in short ids).
+const COMPACT_BLOCKS_VERSION: u64 = 2;
+/// Maximal number of peers which are asked to announce new blocks with `cmpctblock` message.
+const MAX_HIGH_BANDWIDTH_COMPACT_BLOCKS_PEERS: usize = 3;
+/// Time to wait for `blocktxn` message before partial compact block is forgotten.
+const PARTIAL_COMPACT_BLOCK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximal number of partial compact blocks, waiting for `blocktxn` message from single peer.
+const MAX_PARTIAL_COMPACT_BLOCKS_PER_PEER: usize = 2;
 
 /// Information on current synchronization state.
 #[derive(Debug)]
@@ -64,18 +75,31 @@ pub struct Information {
 pub trait ClientCore {
 	fn on_connect(&mut self, peer_index: PeerIndex);
 	fn on_disconnect(&mut self, peer_index: PeerIndex);
-	fn on_inventory(&self, peer_index: PeerIndex, message: types::Inv);
+	fn on_inventory(&mut self, peer_index: PeerIndex, message: types::Inv);
 	fn on_headers(&mut self, peer_index: PeerIndex, message: Vec<IndexedBlockHeader>);
 	fn on_block(&mut self, peer_index: PeerIndex, block: IndexedBlock) -> Option<VecDeque<IndexedBlock>>;
-	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<IndexedTransaction>>;
+	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<VerificationTask>>;
 	fn on_notfound(&mut self, peer_index: PeerIndex, message: types::NotFound);
+	fn on_send_compact(&mut self, peer_index: PeerIndex, message: types::SendCompact);
+	fn on_compact_block(&mut self, peer_index: PeerIndex, message: types::CompactBlock) -> Option<IndexedBlock>;
+	fn on_block_txn(&mut self, peer_index: PeerIndex, message: types::BlockTxn) -> Option<IndexedBlock>;
 	fn after_peer_nearly_blocks_verified(&mut self, peer_index: PeerIndex, future: UnitFuture);
 	fn accept_block(&mut self, block: IndexedBlock) -> Option<VecDeque<IndexedBlock>>;
 	fn accept_transaction(
 		&mut self,
 		transaction: IndexedTransaction,
 		sink: Box<dyn TransactionVerificationSink>,
-	) -> Result<VecDeque<IndexedTransaction>, String>;
+	) -> Result<VecDeque<VerificationTask>, String>;
+	fn accept_package(
+		&mut self,
+		package: Vec<IndexedTransaction>,
+		sink: Box<dyn TransactionVerificationSink>,
+	) -> Result<VerificationTask, String>;
+	fn load_transaction(
+		&mut self,
+		transaction: IndexedTransaction,
+		sink: Box<dyn TransactionVerificationSink>,
+	) -> Result<VecDeque<VerificationTask>, String>;
 	fn install_sync_listener(&mut self, listener: SyncListenerRef);
 	fn execute_synchronization_tasks(&mut self, forced_blocks_requests: Option<Vec<SHA256D>>, final_blocks_requests: Option<Vec<SHA256D>>);
 	fn try_switch_to_saturated_state(&mut self) -> bool;
@@ -126,10 +150,16 @@ pub struct SynchronizationClientCore<T: TaskExecutor> {
 	sync_speed_meter: AverageSpeedMeter,
 	/// Configuration
 	config: Config,
-	/// Synchronization events listener
-	listener: Option<SyncListenerRef>,
+	/// Synchronization events listeners
+	listeners: Vec<SyncListenerRef>,
 	/// Time of last duplicated blocks request.
 	last_dup_time: Option<Instant>,
+	/// Peers which are supporting compact blocks of our version
+	compact_blocks_peers: HashSet<PeerIndex>,
+	/// Peers which are asked to announce new blocks with `cmpctblock` message (oldest first)
+	high_bandwidth_compact_blocks_peers: VecDeque<PeerIndex>,
+	/// Compact blocks, waiting for `blocktxn` message from peer (since given time)
+	partial_compact_blocks: HashMap<SHA256D, (PeerIndex, Instant, PartialCompactBlock)>,
 }
 
 /// Verification sink for synchronization client core
@@ -165,7 +195,6 @@ pub struct BlocksRequestLimits {
 
 /// Transaction append error
 enum AppendTransactionError {
-	Synchronizing,
 	Orphan(HashSet<SHA256D>),
 }
 
@@ -216,16 +245,28 @@ where
 		// not useful until peer responds with headers message
 		self.peers_tasks.unuseful_peer(peer_index);
 		self.peers_tasks.on_headers_requested(peer_index);
+		// announce compact blocks support (low-bandwidth mode until peer proves to be fast)
+		self.executor.execute(Task::SendCompact(
+			peer_index,
+			types::SendCompact {
+				first: false,
+				second: COMPACT_BLOCKS_VERSION,
+			},
+		));
 	}
 
 	fn on_disconnect(&mut self, peer_index: PeerIndex) {
 		// sync tasks from this peers must be executed by other peers
 		let peer_tasks = self.peers_tasks.reset_blocks_tasks(peer_index);
 		self.peers_tasks.disconnect(peer_index);
+		// forget compact blocks state
+		self.compact_blocks_peers.remove(&peer_index);
+		self.high_bandwidth_compact_blocks_peers.retain(|p| *p != peer_index);
+		self.partial_compact_blocks.retain(|_, (p, _, _)| *p != peer_index);
 		self.execute_synchronization_tasks(Some(peer_tasks), None);
 	}
 
-	fn on_inventory(&self, peer_index: PeerIndex, message: types::Inv) {
+	fn on_inventory(&mut self, peer_index: PeerIndex, message: types::Inv) {
 		// else ask for all unknown transactions and blocks
 		let unknown_inventory: Vec<_> = message
 			.inventory
@@ -237,6 +278,11 @@ where
 						self.chain.transaction_state(&item.hash) == TransactionState::Unknown
 							&& !self.orphaned_transactions_pool.contains(&item.hash)
 					}
+					// check that transaction is unknown to us && is not yet requested from other peer
+					InventoryType::MessageWtx => {
+						self.chain.witness_transaction_state(&item.hash) == TransactionState::Unknown
+							&& !self.peers_tasks.is_transaction_requested(&item.hash)
+					}
 					// check that block is unknown to us
 					InventoryType::MessageBlock | InventoryType::MessageWitnessBlock => match self.chain.block_state(&item.hash) {
 						BlockState::Unknown => !self.orphaned_blocks_pool.contains_unknown_block(&item.hash),
@@ -260,14 +306,14 @@ where
 					}
 				}
 			})
-			// we are not synchronizing => we shall not ask for witness
+			// always ask for witness; ask for compact block when we are not synchronizing && peer supports it
 			.map(|item| match item.inv_type {
 				InventoryType::MessageTx => InventoryVector {
 					inv_type: InventoryType::MessageWitnessTx,
 					hash: item.hash,
 				},
-				InventoryType::MessageBlock => InventoryVector {
-					inv_type: InventoryType::MessageWitnessBlock,
+				InventoryType::MessageBlock | InventoryType::MessageWitnessBlock => InventoryVector {
+					inv_type: self.block_inventory_type(peer_index, &item.hash),
 					hash: item.hash,
 				},
 				_ => item,
@@ -280,6 +326,16 @@ where
 			return;
 		}
 
+		// remember transactions, requested by witness hash, so that we do not ask other peers for them
+		let requested_transactions: Vec<_> = unknown_inventory
+			.iter()
+			.filter(|item| item.inv_type == InventoryType::MessageWtx)
+			.map(|item| item.hash)
+			.collect();
+		if !requested_transactions.is_empty() {
+			self.peers_tasks.on_transactions_requested(peer_index, &requested_transactions);
+		}
+
 		// ask for unknown items
 		let message = types::GetData::with_inventory(unknown_inventory);
 		self.executor.execute(Task::GetData(peer_index, message));
@@ -393,6 +449,8 @@ where
 	fn on_block(&mut self, peer_index: PeerIndex, block: IndexedBlock) -> Option<VecDeque<IndexedBlock>> {
 		// update peers to select next tasks
 		self.peers_tasks.on_block_received(peer_index, &block.header.hash);
+		// we do not need missing transactions of this block anymore
+		self.partial_compact_blocks.remove(&block.header.hash);
 
 		// prepare list of blocks to verify + make all required changes to the chain
 		let mut result: Option<VecDeque<IndexedBlock>> = None;
@@ -560,7 +618,10 @@ where
 		result
 	}
 
-	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<IndexedTransaction>> {
+	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<VerificationTask>> {
+		self.peers_tasks
+			.on_transaction_response(peer_index, &transaction.raw.witness_hash());
+
 		// check if this transaction is already known
 		if self.orphaned_transactions_pool.contains(&transaction.hash)
 			|| self.chain.transaction_state(&transaction.hash) != TransactionState::Unknown
@@ -573,6 +634,11 @@ where
 
 	/// When peer has no blocks
 	fn on_notfound(&mut self, peer_index: PeerIndex, message: types::NotFound) {
+		// transactions, requested by witness hash, could now be requested from other peers
+		for item in message.inventory.iter().filter(|item| item.inv_type == InventoryType::MessageWtx) {
+			self.peers_tasks.on_transaction_response(peer_index, &item.hash);
+		}
+
 		let notfound_blocks: HashSet<_> = message
 			.inventory
 			.into_iter()
@@ -608,6 +674,69 @@ where
 		}
 	}
 
+	fn on_send_compact(&mut self, peer_index: PeerIndex, message: types::SendCompact) {
+		if message.second == COMPACT_BLOCKS_VERSION {
+			self.compact_blocks_peers.insert(peer_index);
+		}
+	}
+
+	fn on_compact_block(&mut self, peer_index: PeerIndex, message: types::CompactBlock) -> Option<IndexedBlock> {
+		// we have announced that we only support compact blocks version 2
+		if !self.compact_blocks_peers.contains(&peer_index) {
+			self.peers.misbehaving(peer_index, "Got unrequested 'cmpctblock' message");
+			return None;
+		}
+
+		let block_hash = message.header.header.hash();
+		match self.chain.block_state(&block_hash) {
+			BlockState::Verifying | BlockState::Stored => {
+				// we have already processed this block before
+				self.peers_tasks.useful_peer(peer_index);
+				return None;
+			}
+			BlockState::DeadEnd if self.config.close_connection_on_bad_block => {
+				self.peers
+					.misbehaving(peer_index, &format!("Provided dead-end compact block {}", block_hash));
+				return None;
+			}
+			_ => (),
+		}
+
+		// we are already waiting for missing transactions of this block
+		// => ignore this message, unless the peer has failed to respond in time
+		if let Some((_, requested_at, _)) = self.partial_compact_blocks.get(&block_hash) {
+			if requested_at.elapsed() < PARTIAL_COMPACT_BLOCK_TIMEOUT {
+				return None;
+			}
+			self.partial_compact_blocks.remove(&block_hash);
+		}
+
+		let reconstruction = PartialCompactBlock::reconstruct(message.header, &self.chain.memory_pool().read());
+		self.on_compact_block_reconstruction(peer_index, block_hash, reconstruction)
+	}
+
+	fn on_block_txn(&mut self, peer_index: PeerIndex, message: types::BlockTxn) -> Option<IndexedBlock> {
+		let block_hash = message.request.blockhash;
+		let partial_block = match self.partial_compact_blocks.entry(block_hash) {
+			Entry::Occupied(entry) if entry.get().0 == peer_index => entry.remove().2,
+			_ => {
+				self.peers.misbehaving(peer_index, "Got unrequested 'blocktxn' message");
+				return None;
+			}
+		};
+
+		match partial_block.into_block(message.request.transactions) {
+			CompactBlockReconstruction::Incomplete(_) => {
+				self.peers.misbehaving(
+					peer_index,
+					&format!("Provided not enough transactions of compact block {}", block_hash),
+				);
+				None
+			}
+			reconstruction => self.on_compact_block_reconstruction(peer_index, block_hash, reconstruction),
+		}
+	}
+
 	/// Execute after last block from this peer in NearlySaturated state is verified.
 	/// If there are no verifying blocks from this peer or we are not in the NearlySaturated state => execute immediately.
 	fn after_peer_nearly_blocks_verified(&mut self, peer_index: PeerIndex, future: UnitFuture) {
@@ -630,22 +759,74 @@ where
 		&mut self,
 		transaction: IndexedTransaction,
 		sink: Box<dyn TransactionVerificationSink>,
-	) -> Result<VecDeque<IndexedTransaction>, String> {
+	) -> Result<VecDeque<VerificationTask>, String> {
+		// if we are in synchronization state, we will ignore this transaction
+		if self.state.is_synchronizing() {
+			return Err("Cannot append transaction as node is not yet fully synchronized".to_owned());
+		}
+
 		let hash = transaction.hash;
 		match self.try_append_transaction(transaction, true) {
 			Err(AppendTransactionError::Orphan(_)) => Err("Cannot append transaction as its inputs are unknown".to_owned()),
-			Err(AppendTransactionError::Synchronizing) => Err("Cannot append transaction as node is not yet fully synchronized".to_owned()),
-			Ok(transactions) => {
+			Ok(tasks) => {
 				self.verifying_transactions_sinks.insert(hash, sink);
-				Ok(transactions)
+				Ok(tasks)
+			}
+		}
+	}
+
+	fn accept_package(
+		&mut self,
+		package: Vec<IndexedTransaction>,
+		sink: Box<dyn TransactionVerificationSink>,
+	) -> Result<VerificationTask, String> {
+		// if we are in synchronization state, we will ignore this package
+		if self.state.is_synchronizing() {
+			return Err("Cannot append package as node is not yet fully synchronized".to_owned());
+		}
+
+		// all package inputs must be either known, or spend outputs of other package transactions
+		let child_hash = match package.last() {
+			Some(child) => child.hash,
+			None => return Err("Cannot append empty package".to_owned()),
+		};
+		let package_hashes: HashSet<SHA256D> = package.iter().map(|tx| tx.hash).collect();
+		let has_unknown_inputs = package.iter().flat_map(|tx| tx.raw.inputs.iter()).any(|input| {
+			!package_hashes.contains(&input.previous_output.hash)
+				&& self.chain.transaction_state(&input.previous_output.hash) == TransactionState::Unknown
+		});
+		if has_unknown_inputs {
+			return Err("Cannot append package as its inputs are unknown".to_owned());
+		}
+
+		// remember that we are verifying these transactions
+		for tx in &package {
+			self.chain.verify_transaction(tx.clone());
+		}
+		self.verifying_transactions_sinks.insert(child_hash, sink);
+
+		let next_block_height = self.shared_state.best_storage_block_height() + 1;
+		Ok(VerificationTask::VerifyPackage(next_block_height, package))
+	}
+
+	fn load_transaction(
+		&mut self,
+		transaction: IndexedTransaction,
+		sink: Box<dyn TransactionVerificationSink>,
+	) -> Result<VecDeque<VerificationTask>, String> {
+		// transactions from the memory pool dump are accepted even while synchronizing and they are not relayed
+		let hash = transaction.hash;
+		match self.try_append_transaction(transaction, false) {
+			Err(AppendTransactionError::Orphan(_)) => Err("Cannot load transaction as its inputs are unknown".to_owned()),
+			Ok(tasks) => {
+				self.verifying_transactions_sinks.insert(hash, sink);
+				Ok(tasks)
 			}
 		}
 	}
 
 	fn install_sync_listener(&mut self, listener: SyncListenerRef) {
-		// currently single, single-setup listener is supported
-		assert!(self.listener.is_none());
-		self.listener = Some(listener);
+		self.listeners.push(listener);
 	}
 
 	/// Schedule new synchronization tasks, if any.
@@ -868,6 +1049,11 @@ where
 		self.core.lock().on_transaction_verification_success(transaction)
 	}
 
+	/// Process successful package verification
+	fn on_package_verification_success(&self, package: Vec<IndexedTransaction>) {
+		self.core.lock().on_package_verification_success(package)
+	}
+
 	/// Process failed transaction verification
 	fn on_transaction_verification_error(&self, err: &str, hash: &SHA256D) {
 		self.core.lock().on_transaction_verification_error(err, hash)
@@ -907,8 +1093,11 @@ where
 			block_speed_meter,
 			sync_speed_meter: AverageSpeedMeter::with_inspect_items(SYNC_SPEED_BLOCKS_TO_INSPECT),
 			config,
-			listener: None,
+			listeners: Vec::new(),
 			last_dup_time: None,
+			compact_blocks_peers: HashSet::new(),
+			high_bandwidth_compact_blocks_peers: VecDeque::new(),
+			partial_compact_blocks: HashMap::new(),
 		}));
 
 		{
@@ -1006,6 +1195,39 @@ where
 		}
 	}
 
+	/// Forget partial compact blocks, which peers have not completed with `blocktxn` in time.
+	/// Every forgotten block is requested as full block from the same peer.
+	pub fn manage_partial_compact_blocks(&mut self) {
+		let expired: Vec<_> = self
+			.partial_compact_blocks
+			.iter()
+			.filter(|(_, (_, requested_at, _))| requested_at.elapsed() >= PARTIAL_COMPACT_BLOCK_TIMEOUT)
+			.map(|(block_hash, (peer_index, _, _))| (*block_hash, *peer_index))
+			.collect();
+		for (block_hash, peer_index) in expired {
+			trace!(target: "sync", "Peer#{} has not provided transactions of compact block {} in time. Requesting full block", peer_index, block_hash);
+			self.partial_compact_blocks.remove(&block_hash);
+			self.request_full_block(peer_index, block_hash);
+		}
+	}
+
+	/// Start new transactions reconciliation round with every peer, we are the reconciliation initiator for.
+	/// Rounds, which peers have not responded to in time, are abandoned and sketched transactions are announced.
+	pub fn request_transactions_reconciliation(&mut self) {
+		for peer_index in self.peers.enumerate() {
+			let expired = self.peers.expire_reconciliation(peer_index);
+			if !expired.is_empty() {
+				trace!(target: "sync", "Reconciliation with peer#{} has timed out, announcing {} transactions", peer_index, expired.len());
+				let inventory = expired.into_iter().map(InventoryVector::wtx).collect();
+				self.executor
+					.execute(Task::Inventory(peer_index, types::Inv::with_inventory(inventory)));
+			}
+			if let Some(reqrecon) = self.peers.request_reconciliation(peer_index) {
+				self.executor.execute(Task::ReqRecon(peer_index, reqrecon));
+			}
+		}
+	}
+
 	/// Verify and select unknown headers for scheduling
 	fn verify_headers(
 		&mut self,
@@ -1079,14 +1301,18 @@ where
 		_peer_index: Option<PeerIndex>,
 		transaction: IndexedTransaction,
 		relay: bool,
-	) -> Option<VecDeque<IndexedTransaction>> {
+	) -> Option<VecDeque<VerificationTask>> {
+		// if we are in synchronization state, we will ignore this message
+		if self.state.is_synchronizing() {
+			return None;
+		}
+
 		match self.try_append_transaction(transaction.clone(), relay) {
 			Err(AppendTransactionError::Orphan(unknown_parents)) => {
 				self.orphaned_transactions_pool.insert(transaction, unknown_parents);
 				None
 			}
-			Err(AppendTransactionError::Synchronizing) => None,
-			Ok(transactions) => Some(transactions),
+			Ok(tasks) => Some(tasks),
 		}
 	}
 
@@ -1094,13 +1320,8 @@ where
 		&mut self,
 		transaction: IndexedTransaction,
 		relay: bool,
-	) -> Result<VecDeque<IndexedTransaction>, AppendTransactionError> {
-		// if we are in synchronization state, we will ignore this message
-		if self.state.is_synchronizing() {
-			return Err(AppendTransactionError::Synchronizing);
-		}
-
-		// else => verify transaction + it's orphans and then add to the memory pool
+	) -> Result<VecDeque<VerificationTask>, AppendTransactionError> {
+		// verify transaction + it's orphans and then add to the memory pool
 		// if any parent transaction is unknown => we have orphan transaction => remember in orphan pool
 		let unknown_parents: HashSet<SHA256D> = transaction
 			.raw
@@ -1114,17 +1335,30 @@ where
 		}
 
 		// else verify && insert this transaction && all dependent orphans
-		let mut transactions: VecDeque<IndexedTransaction> = VecDeque::new();
-		transactions.extend(self.orphaned_transactions_pool.remove_transactions_for_parent(&transaction.hash));
-		transactions.push_front(transaction);
+		// direct children of this transaction are verified in packages with it => they could pay for it
+		// => if there are such children, the transaction itself is only verified as a part of these packages
+		let mut packages: VecDeque<Vec<IndexedTransaction>> =
+			self.orphaned_transactions_pool.remove_packages_for_parent(&transaction).into();
+		if !packages.iter().any(|package| package.len() > 1) {
+			packages.push_front(vec![transaction]);
+		}
 		// remember that we are verifying these transactions
-		for tx in &transactions {
-			if !relay {
-				self.do_not_relay.insert(tx.hash.clone());
+		let next_block_height = self.shared_state.best_storage_block_height() + 1;
+		let mut tasks: VecDeque<VerificationTask> = VecDeque::new();
+		for mut package in packages {
+			for tx in &package {
+				if !relay {
+					self.do_not_relay.insert(tx.hash.clone());
+				}
+				self.chain.verify_transaction(tx.clone());
 			}
-			self.chain.verify_transaction((*tx).clone());
+
+			tasks.push_back(match package.len() {
+				1 => VerificationTask::VerifyTransaction(next_block_height, package.remove(0)),
+				_ => VerificationTask::VerifyPackage(next_block_height, package),
+			});
 		}
-		Ok(transactions)
+		Ok(tasks)
 	}
 
 	fn prepare_blocks_requests_tasks(
@@ -1162,14 +1396,14 @@ where
 			self.peers_tasks.on_blocks_requested(peer, &chunk_hashes);
 
 			// request blocks. If block is believed to have witness - ask for witness
+			// single new block is requested as compact block if peer supports it
+			let inv_type = if chunk_hashes.len() == 1 {
+				self.block_inventory_type(peer, &chunk_hashes[0])
+			} else {
+				InventoryType::MessageWitnessBlock
+			};
 			let getdata = types::GetData {
-				inventory: chunk_hashes
-					.into_iter()
-					.map(|h| InventoryVector {
-						inv_type: InventoryType::MessageWitnessBlock,
-						hash: h,
-					})
-					.collect(),
+				inventory: chunk_hashes.into_iter().map(|h| InventoryVector { inv_type, hash: h }).collect(),
 			};
 			tasks.push(Task::GetData(peer, getdata));
 		}
@@ -1178,13 +1412,134 @@ where
 		tasks
 	}
 
+	/// Request full (witness) block from the peer.
+	fn request_full_block(&self, peer_index: PeerIndex, block_hash: SHA256D) {
+		let inventory = vec![InventoryVector {
+			inv_type: InventoryType::MessageWitnessBlock,
+			hash: block_hash,
+		}];
+		self.executor
+			.execute(Task::GetData(peer_index, types::GetData::with_inventory(inventory)));
+	}
+
+	/// Inventory type to use when requesting new block from the peer.
+	/// Block, which is already partially received as compact block, is requested as full block.
+	fn block_inventory_type(&self, peer_index: PeerIndex, block_hash: &SHA256D) -> InventoryType {
+		if !self.state.is_synchronizing()
+			&& self.compact_blocks_peers.contains(&peer_index)
+			&& !self.partial_compact_blocks.contains_key(block_hash)
+		{
+			InventoryType::MessageCompactBlock
+		} else {
+			InventoryType::MessageWitnessBlock
+		}
+	}
+
+	/// Process result of compact block reconstruction
+	fn on_compact_block_reconstruction(
+		&mut self,
+		peer_index: PeerIndex,
+		block_hash: SHA256D,
+		reconstruction: CompactBlockReconstruction,
+	) -> Option<IndexedBlock> {
+		match reconstruction {
+			CompactBlockReconstruction::Complete(block) => {
+				trace!(target: "sync", "Reconstructed compact block {} from peer#{}", block_hash, peer_index);
+				self.select_high_bandwidth_compact_blocks_peer(peer_index);
+				Some(block)
+			}
+			CompactBlockReconstruction::Incomplete(partial_block) => {
+				// do not keep state for blocks with invalid header
+				let header = partial_block.header();
+				if self.verify_headers {
+					let headers_provider = MessageBlockHeadersProvider::new(&self.chain, self.chain.best_block_header().number);
+					if let Err(error) = self
+						.chain_verifier
+						.verify_block_header(&headers_provider, &header.hash, &header.raw)
+					{
+						self.peers.misbehaving(
+							peer_index,
+							&format!("Error verifying header of compact block {}: {:?}", block_hash, error),
+						);
+						return None;
+					}
+				}
+
+				// blocks with unknown parent and blocks above the per-peer limit are requested as full blocks
+				let parent_state = self.chain.block_state(&header.raw.previous_header_hash);
+				let peer_partial_blocks = self.partial_compact_blocks.values().filter(|(p, _, _)| *p == peer_index).count();
+				if parent_state == BlockState::Unknown || parent_state == BlockState::DeadEnd {
+					trace!(target: "sync", "Parent of compact block {} from peer#{} is unknown. Requesting full block", block_hash, peer_index);
+					self.request_full_block(peer_index, block_hash);
+					return None;
+				}
+				if peer_partial_blocks >= MAX_PARTIAL_COMPACT_BLOCKS_PER_PEER {
+					trace!(target: "sync", "Too many partial compact blocks from peer#{}. Requesting full block {}", peer_index, block_hash);
+					self.request_full_block(peer_index, block_hash);
+					return None;
+				}
+
+				let request = BlockTransactionsRequest {
+					blockhash: block_hash,
+					indexes: partial_block.missing_indexes(),
+				};
+				trace!(target: "sync", "Compact block {} from peer#{} is missing {} transactions", block_hash, peer_index, request.indexes.len());
+				self.partial_compact_blocks
+					.insert(block_hash, (peer_index, Instant::now(), partial_block));
+				self.executor.execute(Task::GetBlockTxn(peer_index, types::GetBlockTxn { request }));
+				None
+			}
+			CompactBlockReconstruction::Collision => {
+				trace!(target: "sync", "Failed to reconstruct compact block {} from peer#{}. Requesting full block", block_hash, peer_index);
+				self.request_full_block(peer_index, block_hash);
+				None
+			}
+			CompactBlockReconstruction::Invalid(reason) => {
+				self.peers
+					.misbehaving(peer_index, &format!("Provided invalid compact block {}: {}", block_hash, reason));
+				None
+			}
+		}
+	}
+
+	/// Ask peer, which has provided us with compact block, to announce new blocks using `cmpctblock` message
+	fn select_high_bandwidth_compact_blocks_peer(&mut self, peer_index: PeerIndex) {
+		if self.high_bandwidth_compact_blocks_peers.contains(&peer_index) {
+			return;
+		}
+
+		self.high_bandwidth_compact_blocks_peers.push_back(peer_index);
+		self.executor.execute(Task::SendCompact(
+			peer_index,
+			types::SendCompact {
+				first: true,
+				second: COMPACT_BLOCKS_VERSION,
+			},
+		));
+
+		// switch the oldest high-bandwidth peer back to the low-bandwidth mode
+		if self.high_bandwidth_compact_blocks_peers.len() > MAX_HIGH_BANDWIDTH_COMPACT_BLOCKS_PEERS {
+			let oldest_peer_index = self
+				.high_bandwidth_compact_blocks_peers
+				.pop_front()
+				.expect("len > MAX_HIGH_BANDWIDTH_COMPACT_BLOCKS_PEERS; qed");
+			self.executor.execute(Task::SendCompact(
+				oldest_peer_index,
+				types::SendCompact {
+					first: false,
+					second: COMPACT_BLOCKS_VERSION,
+				},
+			));
+		}
+	}
+
 	/// Switch to synchronization state
 	fn switch_to_synchronization_state(&mut self) {
 		if self.state.is_synchronizing() {
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(true);
 		}
 
@@ -1198,7 +1553,7 @@ where
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(false);
 		}
 
@@ -1212,7 +1567,7 @@ where
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(false);
 		}
 
@@ -1274,9 +1629,9 @@ where
 				self.shared_state
 					.update_best_storage_block_height(self.chain.best_storage_block().number);
 
-				// notify listener
+				// notify listeners
 				if let Some(best_block_hash) = insert_result.canonized_blocks_hashes.last() {
-					if let Some(ref listener) = self.listener {
+					for listener in &self.listeners {
 						listener.best_storage_block_inserted(best_block_hash);
 					}
 				}
@@ -1298,13 +1653,9 @@ where
 
 				// deal with block transactions
 				let mut verification_tasks: Vec<VerificationTask> = Vec::with_capacity(insert_result.transactions_to_reverify.len());
-				let next_block_height = self.chain.best_block().number + 1;
 				for tx in insert_result.transactions_to_reverify {
 					// do not relay resurrected transactions again
-					if let Some(tx_orphans) = self.process_peer_transaction(None, tx.into(), false) {
-						let tx_tasks = tx_orphans
-							.into_iter()
-							.map(|tx| VerificationTask::VerifyTransaction(next_block_height, tx));
+					if let Some(tx_tasks) = self.process_peer_transaction(None, tx.into(), false) {
 						verification_tasks.extend(tx_tasks);
 					};
 				}
@@ -1358,7 +1709,21 @@ where
 		}
 
 		// transaction was in verification queue => insert to memory pool
-		self.chain.insert_verified_transaction(transaction.clone());
+		// transaction may still be rejected here, i.e. if memory pool min fee has been raised during verification
+		match self.chain.insert_verified_transaction(transaction.clone()) {
+			Ok(removed) => {
+				if !removed.is_empty() {
+					trace!(target: "sync", "{} transactions removed from memory pool on insertion of {}", removed.len(), transaction.hash);
+				}
+			}
+			Err((err, removed)) => {
+				if !removed.is_empty() {
+					trace!(target: "sync", "{} transactions removed from memory pool on rejection of {}", removed.len(), transaction.hash);
+				}
+				self.on_transaction_rejected(&format!("{:?}", err), &transaction.hash);
+				return;
+			}
+		}
 
 		// calculate transaction fee rate
 		let transaction_fee_rate = transaction_fee_rate(&self.chain, &transaction.raw);
@@ -1375,6 +1740,73 @@ where
 		}
 	}
 
+	fn on_package_verification_success(&mut self, package: Vec<IndexedTransaction>) {
+		// errors are reported for the child transaction, which is the last transaction of the package
+		let child_hash = match package.last() {
+			Some(child) => child.hash,
+			None => return,
+		};
+
+		// remove flags
+		let needs_relay: HashSet<SHA256D> = package
+			.iter()
+			.filter(|tx| !self.do_not_relay.remove(&tx.hash))
+			.map(|tx| tx.hash)
+			.collect();
+
+		// remove package transactions from verification queue
+		// if child is not in the queue => it was removed due to error or reorganization
+		for parent in &package[..package.len() - 1] {
+			self.chain.forget_verifying_transaction(&parent.hash);
+		}
+		if !self.chain.forget_verifying_transaction(&child_hash) {
+			return;
+		}
+
+		// package was in verification queue => insert to memory pool
+		let new_transactions: Vec<IndexedTransaction> = package
+			.iter()
+			.filter(|tx| self.chain.transaction_state(&tx.hash) != TransactionState::InMemory)
+			.cloned()
+			.collect();
+		match self.chain.insert_verified_package(package.clone()) {
+			Ok(removed) => {
+				if !removed.is_empty() {
+					trace!(target: "sync", "{} transactions removed from memory pool on insertion of package {}", removed.len(), child_hash);
+				}
+			}
+			Err(err) => {
+				let err = format!("{:?}", err);
+				for parent in new_transactions.iter().filter(|tx| tx.hash != child_hash) {
+					self.on_transaction_rejected(&err, &parent.hash);
+				}
+				self.on_transaction_verification_error(&err, &child_hash);
+				return;
+			}
+		}
+
+		// relay package transactions, which were not in the memory pool before
+		for transaction in &new_transactions {
+			if needs_relay.contains(&transaction.hash) {
+				let transaction_fee_rate = transaction_fee_rate(&self.chain, &transaction.raw);
+				self.executor
+					.execute(Task::RelayNewTransaction(transaction.clone(), transaction_fee_rate));
+			}
+		}
+
+		// call verification futures of parents, which were only verified in this package, if any
+		for parent in new_transactions.iter().filter(|tx| tx.hash != child_hash) {
+			if let Some(future_sink) = self.verifying_transactions_sinks.remove(&parent.hash) {
+				future_sink.on_transaction_verification_success(parent.clone());
+			}
+		}
+
+		// call verification future, if any
+		if let Some(future_sink) = self.verifying_transactions_sinks.remove(&child_hash) {
+			future_sink.on_package_verification_success(package);
+		}
+	}
+
 	fn on_transaction_verification_error(&mut self, err: &str, hash: &SHA256D) {
 		warn!(target: "sync", "Transaction {} verification failed with error {:?}", hash, err);
 
@@ -1390,6 +1822,17 @@ where
 		}
 	}
 
+	/// Verified transaction has not been accepted to the memory pool. Its children are not forgotten,
+	/// because they may still be accepted in package with it.
+	fn on_transaction_rejected(&mut self, err: &str, hash: &SHA256D) {
+		debug!(target: "sync", "Transaction {} is rejected by memory pool with error {:?}", hash, err);
+
+		// call verification future, if any
+		if let Some(future_sink) = self.verifying_transactions_sinks.remove(hash) {
+			future_sink.on_transaction_verification_error(err, hash);
+		}
+	}
+
 	/// Execute futures, which were waiting for this block verification
 	fn awake_waiting_threads(&mut self, hash: &SHA256D) {
 		// find a peer, which has supplied us with this block
@@ -1436,26 +1879,30 @@ pub mod tests {
 	extern crate test_data;
 
 	use super::super::SyncListener;
-	use super::{ClientCore, Config, CoreVerificationSink, SynchronizationClientCore};
+	use super::{
+		ClientCore, Config, CoreVerificationSink, SynchronizationClientCore, MAX_PARTIAL_COMPACT_BLOCKS_PER_PEER,
+		PARTIAL_COMPACT_BLOCK_TIMEOUT,
+	};
 	use crate::inbound_connection::tests::DummyOutboundSyncConnection;
-	use crate::synchronization_chain::Chain;
+	use crate::synchronization_chain::{Chain, TransactionState};
 	use crate::synchronization_client::{Client, SynchronizationClient};
 	use crate::synchronization_executor::tests::DummyTaskExecutor;
 	use crate::synchronization_executor::Task;
 	use crate::synchronization_peers::PeersImpl;
 	use crate::synchronization_verifier::tests::DummyVerifier;
 	use crate::types::{ClientCoreRef, PeerIndex, StorageRef, SynchronizationStateRef};
-	use crate::utils::{AverageSpeedMeter, SynchronizationState};
+	use crate::utils::{build_compact_block, AverageSpeedMeter, SynchronizationState};
 	use crate::BLOCKS_SPEED_BLOCKS_TO_INSPECT;
 	use bitcrypto::{FromStr, SHA256D};
 	use chain::{Block, Transaction};
 	use db::BlockChainDatabase;
-	use message::common::InventoryVector;
+	use message::common::{self, InventoryType, InventoryVector};
 	use message::{types, Services};
-	use miner::MemoryPool;
+	use miner::{MemoryPool, NonZeroFeeCalculator};
 	use network::{ConsensusParams, Network};
 	use parking_lot::{Mutex, RwLock};
 	use std::sync::Arc;
+	use std::time::Instant;
 	use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 
 	#[derive(Default)]
@@ -1525,7 +1972,7 @@ pub mod tests {
 		verifier.set_memory_pool(memory_pool);
 		verifier.set_verifier(chain_verifier);
 
-		let client = SynchronizationClient::new(sync_state, client_core.clone(), verifier);
+		let client = SynchronizationClient::new(client_core.clone(), verifier);
 		(executor, client_core, client)
 	}
 
@@ -1550,14 +1997,52 @@ pub mod tests {
 		)
 	}
 
+	fn send_compact(peer_index: PeerIndex, high_bandwidth: bool) -> Task {
+		Task::SendCompact(
+			peer_index,
+			types::SendCompact {
+				first: high_bandwidth,
+				second: 2,
+			},
+		)
+	}
+
+	fn compact_block(block: &Block) -> types::CompactBlock {
+		// test transactions have no witness => short ids computed from txid are valid for version 2
+		let prefilled = vec![0].into_iter().collect();
+		types::CompactBlock {
+			header: build_compact_block(&block.clone().into(), prefilled),
+		}
+	}
+
+	fn block_with_transactions() -> Block {
+		let genesis_coinbase_hash = test_data::genesis().transactions[0].hash();
+		#[rustfmt::skip]
+		let block = test_data::block_builder()
+			.transaction().coinbase()
+				.output().value(10).build()
+				.build()
+			.transaction()
+				.input().hash(genesis_coinbase_hash).index(0).build()
+				.output().value(20).build()
+				.build()
+			.transaction()
+				.input().hash(genesis_coinbase_hash).index(1).build()
+				.output().value(30).build()
+				.build()
+			.merkled_header().parent(test_data::genesis().hash()).build()
+			.build();
+		block
+	}
+
 	#[test]
 	fn synchronization_request_inventory_on_sync_start() {
 		let (executor, _, sync) = create_sync(None, None);
 		// start sync session
 		sync.on_connect(0);
-		// => ask for inventory
+		// => ask for inventory && announce compact blocks support
 		let tasks = executor.take_tasks();
-		assert_eq!(tasks, vec![request_block_headers_genesis(0)]);
+		assert_eq!(tasks, vec![request_block_headers_genesis(0), send_compact(0, false)]);
 	}
 
 	#[test]
@@ -2253,6 +2738,25 @@ pub mod tests {
 		);
 	}
 
+	#[test]
+	fn wtx_announced_by_several_peers_is_requested_once() {
+		let (executor, _, sync) = create_sync(None, None);
+
+		let wtx = InventoryVector::wtx(SHA256D::default());
+		sync.on_inventory(0, types::Inv::with_inventory(vec![wtx.clone()]));
+		sync.on_inventory(1, types::Inv::with_inventory(vec![wtx.clone()]));
+
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks, vec![Task::GetData(0, types::GetData::with_inventory(vec![wtx.clone()]))]);
+
+		// after peer#0 has responded with notfound, transaction is requested from peer#1
+		sync.on_notfound(0, types::NotFound::with_inventory(vec![wtx.clone()]));
+		sync.on_inventory(1, types::Inv::with_inventory(vec![wtx.clone()]));
+
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks, vec![Task::GetData(1, types::GetData::with_inventory(vec![wtx]))]);
+	}
+
 	#[test]
 	fn known_transaction_is_not_requested() {
 		let (executor, _, sync) = create_sync(None, None);
@@ -2337,6 +2841,60 @@ pub mod tests {
 		assert_eq!(core.lock().information().orphaned_transactions, 0);
 	}
 
+	#[test]
+	fn orphaned_transaction_pays_for_its_parent() {
+		let input_tx = test_data::genesis().transactions[0].clone();
+		let chain = &mut test_data::ChainBuilder::new();
+		test_data::TransactionBuilder::with_input(&input_tx, 0)
+			.set_output(5_000_000_000)
+			.store(chain) // zero fee: t0
+			.set_input(&chain.at(0), 0)
+			.set_output(4_000_000_000)
+			.store(chain); // t0 -> t1
+
+		let (_, core, sync) = create_sync(None, None);
+
+		// zero fee transaction is not accepted on its own
+		sync.on_transaction(1, chain.at(0).into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 0);
+
+		sync.on_transaction(1, chain.at(1).into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 0);
+		assert_eq!(core.lock().information().orphaned_transactions, 1);
+
+		// but it is accepted in package with its child
+		sync.on_transaction(1, chain.at(0).into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 2);
+		assert_eq!(core.lock().information().orphaned_transactions, 0);
+	}
+
+	#[test]
+	fn parent_is_accepted_when_its_package_with_orphan_is_invalid() {
+		let input_tx = test_data::genesis().transactions[0].clone();
+		let chain = &mut test_data::ChainBuilder::new();
+		test_data::TransactionBuilder::with_input(&input_tx, 0)
+			.set_output(4_000_000_000)
+			.store(chain) // t0
+			.set_input(&chain.at(0), 0)
+			.set_output(3_000_000_000)
+			.store(chain); // t0 -> t1
+
+		let mut dummy_verifier = DummyVerifier::default();
+		dummy_verifier.error_when_verifying(chain.at(1).hash(), "simulated");
+
+		let (_, core, sync) = create_sync(None, Some(dummy_verifier));
+
+		sync.on_transaction(1, chain.at(1).into());
+		assert_eq!(core.lock().information().orphaned_transactions, 1);
+
+		// parent is only verified in package with its child, but it is valid on its own
+		sync.on_transaction(1, chain.at(0).into());
+		let mut core = core.lock();
+		assert_eq!(core.information().orphaned_transactions, 0);
+		assert_eq!(core.chain().transaction_state(&chain.at(0).hash()), TransactionState::InMemory);
+		assert_eq!(core.chain().transaction_state(&chain.at(1).hash()), TransactionState::Unknown);
+	}
+
 	#[test]
 	// https://github.com/ethcore/parity-bitcoin/issues/121
 	fn when_previous_block_verification_failed_fork_is_not_requested() {
@@ -2459,7 +3017,8 @@ pub mod tests {
 
 	#[test]
 	fn relay_new_transaction_when_in_saturated_state() {
-		let (executor, _, sync) = create_sync(None, None);
+		let (executor, core, sync) = create_sync(None, None);
+		core.lock().chain().memory_pool().write().accept_zero_fee_transactions();
 
 		let tx: Transaction = test_data::TransactionBuilder::with_output(20).into();
 
@@ -2472,6 +3031,22 @@ pub mod tests {
 		assert_eq!(tasks, vec![Task::RelayNewTransaction(tx.into(), 0)]);
 	}
 
+	#[test]
+	fn transaction_rejected_by_memory_pool_is_not_relayed() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		// zero fee transaction passes verification, but is not accepted to the memory pool
+		let tx: Transaction = test_data::TransactionBuilder::with_output(20).into();
+
+		sync.on_connect(1);
+		executor.take_tasks();
+
+		sync.on_transaction(2, tx.clone().into());
+
+		assert_eq!(executor.take_tasks(), vec![]);
+		assert!(!core.lock().chain().memory_pool().read().contains(&tx.hash()));
+	}
+
 	#[test]
 	fn receive_same_unknown_block_twice() {
 		let (_, _, sync) = create_sync(None, None);
@@ -2793,4 +3368,226 @@ pub mod tests {
 		assert_eq!(data.lock().is_synchronizing, false);
 		assert_eq!(data.lock().best_blocks.len(), 3);
 	}
+
+	#[test]
+	fn synchronization_requests_compact_block_from_compact_blocks_peer() {
+		let (executor, _, sync) = create_sync(None, None);
+		let block1: Block = test_data::block_h1();
+
+		sync.on_send_compact(1, types::SendCompact { first: false, second: 2 });
+		sync.on_headers(1, vec![block1.block_header.clone().into()]);
+
+		let tasks = executor.take_tasks();
+		assert_eq!(
+			tasks,
+			vec![
+				request_block_headers_genesis_and(1, vec![block1.hash()]),
+				Task::GetData(
+					1,
+					types::GetData::with_inventory(vec![InventoryVector {
+						inv_type: InventoryType::MessageCompactBlock,
+						hash: block1.hash(),
+					}])
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn synchronization_reconstructs_compact_block_from_memory_pool() {
+		let (executor, core, sync) = create_sync(None, None);
+		let block = block_with_transactions();
+		{
+			let memory_pool = core.lock().chain().memory_pool();
+			let mut memory_pool = memory_pool.write();
+			memory_pool
+				.insert_verified(block.transactions[1].clone().into(), &NonZeroFeeCalculator)
+				.unwrap();
+			memory_pool
+				.insert_verified(block.transactions[2].clone().into(), &NonZeroFeeCalculator)
+				.unwrap();
+		}
+
+		sync.on_send_compact(1, types::SendCompact { first: false, second: 2 });
+		sync.on_compact_block(1, compact_block(&block));
+
+		assert_eq!(core.lock().information().chain.stored, 2);
+		assert!(executor.take_tasks().contains(&send_compact(1, true)));
+	}
+
+	#[test]
+	fn synchronization_requests_missing_compact_block_transactions() {
+		let (executor, core, sync) = create_sync(None, None);
+		let block = block_with_transactions();
+		{
+			let memory_pool = core.lock().chain().memory_pool();
+			memory_pool
+				.write()
+				.insert_verified(block.transactions[2].clone().into(), &NonZeroFeeCalculator)
+				.unwrap();
+		}
+
+		sync.on_send_compact(1, types::SendCompact { first: false, second: 2 });
+		sync.on_compact_block(1, compact_block(&block));
+
+		// => missing transaction is requested
+		assert_eq!(
+			executor.take_tasks(),
+			vec![Task::GetBlockTxn(
+				1,
+				types::GetBlockTxn {
+					request: common::BlockTransactionsRequest {
+						blockhash: block.hash(),
+						indexes: vec![1],
+					},
+				}
+			)]
+		);
+		assert_eq!(core.lock().information().chain.stored, 1);
+
+		// => block is reconstructed when missing transaction is received
+		sync.on_block_txn(
+			1,
+			types::BlockTxn {
+				request: common::BlockTransactions {
+					blockhash: block.hash(),
+					transactions: vec![block.transactions[1].clone()],
+				},
+			},
+		);
+		assert_eq!(core.lock().information().chain.stored, 2);
+		assert!(executor.take_tasks().contains(&send_compact(1, true)));
+	}
+
+	#[test]
+	fn synchronization_requests_full_block_when_compact_block_is_pending() {
+		let (executor, core, sync) = create_sync(None, None);
+		let block = block_with_transactions();
+
+		sync.on_send_compact(1, types::SendCompact { first: false, second: 2 });
+		sync.on_send_compact(2, types::SendCompact { first: false, second: 2 });
+		sync.on_compact_block(1, compact_block(&block));
+		executor.take_tasks();
+
+		// => block, which is waiting for 'blocktxn' from peer#1, is requested as full block from peer#2
+		sync.on_inventory(2, types::Inv::with_inventory(vec![InventoryVector::witness_block(block.hash())]));
+		assert_eq!(executor.take_tasks(), vec![request_blocks(2, vec![block.hash()])]);
+		assert_eq!(core.lock().information().chain.stored, 1);
+	}
+
+	#[test]
+	fn synchronization_requests_full_block_when_blocktxn_is_not_received_in_time() {
+		let (executor, core, sync) = create_sync(None, None);
+		let block = block_with_transactions();
+
+		sync.on_send_compact(1, types::SendCompact { first: false, second: 2 });
+		sync.on_compact_block(1, compact_block(&block));
+		executor.take_tasks();
+
+		// => nothing happens while peer still has time to respond
+		core.lock().manage_partial_compact_blocks();
+		assert_eq!(executor.take_tasks(), vec![]);
+
+		// => partial block is forgotten && requested as full block when timeout has passed
+		{
+			let mut core = core.lock();
+			core.partial_compact_blocks.get_mut(&block.hash()).unwrap().1 = Instant::now() - PARTIAL_COMPACT_BLOCK_TIMEOUT;
+			core.manage_partial_compact_blocks();
+			assert!(core.partial_compact_blocks.is_empty());
+		}
+		assert_eq!(executor.take_tasks(), vec![request_blocks(1, vec![block.hash()])]);
+	}
+
+	#[test]
+	fn synchronization_requests_full_block_when_compact_block_parent_is_unknown() {
+		let (executor, core, sync) = create_sync(None, None);
+		#[rustfmt::skip]
+		let block: Block = test_data::block_builder()
+			.transaction().coinbase().build()
+			.transaction().coinbase().output().value(10).build().build()
+			.merkled_header().parent(test_data::block_h1().hash()).build()
+			.build();
+
+		sync.on_send_compact(1, types::SendCompact { first: false, second: 2 });
+		sync.on_compact_block(1, compact_block(&block));
+
+		assert_eq!(executor.take_tasks(), vec![request_blocks(1, vec![block.hash()])]);
+		assert!(core.lock().partial_compact_blocks.is_empty());
+	}
+
+	#[test]
+	fn synchronization_limits_partial_compact_blocks_per_peer() {
+		let (executor, core, sync) = create_sync(None, None);
+		let blocks: Vec<Block> = (0..MAX_PARTIAL_COMPACT_BLOCKS_PER_PEER + 1)
+			.map(|value| {
+				#[rustfmt::skip]
+				let block = test_data::block_builder()
+					.transaction().coinbase().output().value(value as u64).build().build()
+					.transaction().coinbase().output().value(10).build().build()
+					.merkled_header().parent(test_data::genesis().hash()).build()
+					.build();
+				block
+			})
+			.collect();
+
+		sync.on_send_compact(1, types::SendCompact { first: false, second: 2 });
+		for block in &blocks {
+			sync.on_compact_block(1, compact_block(block));
+		}
+
+		// => block above the limit is requested as full block
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks.len(), MAX_PARTIAL_COMPACT_BLOCKS_PER_PEER + 1);
+		assert_eq!(
+			tasks.last(),
+			Some(&request_blocks(1, vec![blocks[MAX_PARTIAL_COMPACT_BLOCKS_PER_PEER].hash()]))
+		);
+		assert_eq!(core.lock().partial_compact_blocks.len(), MAX_PARTIAL_COMPACT_BLOCKS_PER_PEER);
+	}
+
+	#[test]
+	fn synchronization_requests_full_block_on_compact_block_collision() {
+		let (executor, core, sync) = create_sync(None, None);
+		let block = block_with_transactions();
+		let mut compact_block = compact_block(&block);
+		compact_block.header.short_ids[1] = compact_block.header.short_ids[0];
+
+		sync.on_send_compact(1, types::SendCompact { first: false, second: 2 });
+		sync.on_compact_block(1, compact_block);
+
+		assert_eq!(executor.take_tasks(), vec![request_blocks(1, vec![block.hash()])]);
+		assert_eq!(core.lock().information().chain.stored, 1);
+	}
+
+	#[test]
+	fn synchronization_ignores_compact_block_from_peer_without_compact_blocks_support() {
+		let (executor, core, sync) = create_sync(None, None);
+		let block = block_with_transactions();
+
+		sync.on_send_compact(1, types::SendCompact { first: true, second: 1 });
+		sync.on_compact_block(1, compact_block(&block));
+
+		assert_eq!(executor.take_tasks(), vec![]);
+		assert_eq!(core.lock().information().chain.stored, 1);
+	}
+
+	#[test]
+	fn synchronization_selects_at_most_three_high_bandwidth_compact_blocks_peers() {
+		let (executor, core, _) = create_sync(None, None);
+		let mut core = core.lock();
+		for peer_index in 0..4 {
+			core.select_high_bandwidth_compact_blocks_peer(peer_index);
+		}
+
+		assert_eq!(
+			executor.take_tasks(),
+			vec![
+				send_compact(0, true),
+				send_compact(1, true),
+				send_compact(2, true),
+				send_compact(3, true),
+				send_compact(0, false),
+			]
+		);
+	}
 }
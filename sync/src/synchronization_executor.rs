@@ -23,6 +23,10 @@ pub enum Task {
 	GetHeaders(PeerIndex, types::GetHeaders),
 	/// Get memory pool
 	MemoryPool(PeerIndex),
+	/// Request missing compact block transactions
+	GetBlockTxn(PeerIndex, types::GetBlockTxn),
+	/// Announce compact blocks support
+	SendCompact(PeerIndex, types::SendCompact),
 	/// Send block
 	Block(PeerIndex, IndexedBlock),
 	/// Send merkleblock
@@ -37,10 +41,22 @@ pub enum Task {
 	WitnessTransaction(PeerIndex, IndexedTransaction),
 	/// Send block transactions
 	BlockTxn(PeerIndex, types::BlockTxn),
+	/// Send block filter
+	CFilter(PeerIndex, types::CFilter),
+	/// Send block filter headers
+	CFHeaders(PeerIndex, types::CFHeaders),
+	/// Send block filter headers checkpoints
+	CFCheckpt(PeerIndex, types::CFCheckpt),
 	/// Send notfound
 	NotFound(PeerIndex, types::NotFound),
 	/// Send inventory
 	Inventory(PeerIndex, types::Inv),
+	/// Request transactions reconciliation
+	ReqRecon(PeerIndex, types::ReqRecon),
+	/// Send sketch of the reconciliation set
+	Sketch(PeerIndex, types::Sketch),
+	/// Send reconciliation result
+	ReconcilDiff(PeerIndex, types::ReconcilDiff),
 	/// Send headers
 	Headers(PeerIndex, types::Headers, Option<RequestId>),
 	/// Relay new block to peers
@@ -91,6 +107,20 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
+	fn execute_get_block_txn(&self, peer_index: PeerIndex, getblocktxn: types::GetBlockTxn) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Querying {} missing transactions of compact block {} from peer#{}", getblocktxn.request.indexes.len(), getblocktxn.request.blockhash, peer_index);
+			connection.send_get_block_txn(getblocktxn);
+		}
+	}
+
+	fn execute_send_compact(&self, peer_index: PeerIndex, sendcmpct: types::SendCompact) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending sendcmpct (high-bandwidth: {}) to peer#{}", sendcmpct.first, peer_index);
+			connection.send_send_compact(sendcmpct);
+		}
+	}
+
 	fn execute_block(&self, peer_index: PeerIndex, block: IndexedBlock) {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			trace!(target: "sync", "Sending block {} to peer#{}", block.hash(), peer_index);
@@ -144,6 +174,10 @@ impl LocalSynchronizationTaskExecutor {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			trace!(target: "sync", "Sending witness transaction {} to peer#{}", transaction.hash, peer_index);
 			self.peers.hash_known_as(peer_index, transaction.hash, KnownHashType::Transaction);
+			if transaction.raw.has_witness() {
+				self.peers
+					.hash_known_as(peer_index, transaction.raw.witness_hash(), KnownHashType::Transaction);
+			}
 			let transaction = types::Tx {
 				transaction: transaction.raw,
 			};
@@ -158,6 +192,27 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
+	fn execute_cfilter(&self, peer_index: PeerIndex, cfilter: types::CFilter) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfilter of block {} to peer#{}", cfilter.block_hash, peer_index);
+			connection.send_cfilter(cfilter);
+		}
+	}
+
+	fn execute_cfheaders(&self, peer_index: PeerIndex, cfheaders: types::CFHeaders) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfheaders to peer#{} with {} items", peer_index, cfheaders.filter_hashes.len());
+			connection.send_cfheaders(cfheaders);
+		}
+	}
+
+	fn execute_cfcheckpt(&self, peer_index: PeerIndex, cfcheckpt: types::CFCheckpt) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfcheckpt to peer#{} with {} items", peer_index, cfcheckpt.filter_headers.len());
+			connection.send_cfcheckpt(cfcheckpt);
+		}
+	}
+
 	fn execute_notfound(&self, peer_index: PeerIndex, notfound: types::NotFound) {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			trace!(target: "sync", "Sending notfound to peer#{} with {} items", peer_index, notfound.inventory.len());
@@ -172,6 +227,27 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
+	fn execute_reqrecon(&self, peer_index: PeerIndex, reqrecon: types::ReqRecon) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Requesting reconciliation of {} transactions from peer#{}", reqrecon.set_size, peer_index);
+			connection.send_reqrecon(reqrecon);
+		}
+	}
+
+	fn execute_sketch(&self, peer_index: PeerIndex, sketch: types::Sketch) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending sketch to peer#{}", peer_index);
+			connection.send_sketch(sketch);
+		}
+	}
+
+	fn execute_reconcildiff(&self, peer_index: PeerIndex, reconcildiff: types::ReconcilDiff) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending reconcildiff to peer#{} with {} short ids", peer_index, reconcildiff.ask_shortids.len());
+			connection.send_reconcildiff(reconcildiff);
+		}
+	}
+
 	fn execute_headers(&self, peer_index: PeerIndex, headers: types::Headers, request_id: Option<RequestId>) {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			trace!(target: "sync", "Sending headers to peer#{} with {} items", peer_index, headers.headers.len());
@@ -204,10 +280,22 @@ impl LocalSynchronizationTaskExecutor {
 	fn execute_relay_transaction(&self, transaction: IndexedTransaction, fee_rate: u64) {
 		for peer_index in self.peers.enumerate() {
 			match self.peers.filter_transaction(peer_index, &transaction, Some(fee_rate)) {
-				TransactionAnnouncementType::SendInventory => self.execute_inventory(
-					peer_index,
-					types::Inv::with_inventory(vec![InventoryVector::tx(transaction.hash.clone())]),
-				),
+				TransactionAnnouncementType::SendInventory => {
+					// peers, which negotiated wtxid relay, are expecting witness hashes
+					let item = if self.peers.is_wtxid_relay(peer_index) {
+						InventoryVector::wtx(transaction.raw.witness_hash())
+					} else {
+						InventoryVector::tx(transaction.hash.clone())
+					};
+					self.execute_inventory(peer_index, types::Inv::with_inventory(vec![item]))
+				}
+				TransactionAnnouncementType::Reconcile => {
+					// transaction is announced after the reconciliation round, unless the set is full
+					let witness_hash = transaction.raw.witness_hash();
+					if !self.peers.add_reconciliation_transaction(peer_index, witness_hash) {
+						self.execute_inventory(peer_index, types::Inv::with_inventory(vec![InventoryVector::wtx(witness_hash)]))
+					}
+				}
 				TransactionAnnouncementType::DoNotAnnounce => (),
 			}
 		}
@@ -221,6 +309,8 @@ impl TaskExecutor for LocalSynchronizationTaskExecutor {
 			Task::GetData(peer_index, getdata) => self.execute_getdata(peer_index, getdata),
 			Task::GetHeaders(peer_index, getheaders) => self.execute_getheaders(peer_index, getheaders),
 			Task::MemoryPool(peer_index) => self.execute_memorypool(peer_index),
+			Task::GetBlockTxn(peer_index, getblocktxn) => self.execute_get_block_txn(peer_index, getblocktxn),
+			Task::SendCompact(peer_index, sendcmpct) => self.execute_send_compact(peer_index, sendcmpct),
 			Task::Block(peer_index, block) => self.execute_block(peer_index, block),
 			Task::MerkleBlock(peer_index, hash, block) => self.execute_merkleblock(peer_index, hash, block),
 			Task::CompactBlock(peer_index, hash, block) => self.execute_compact_block(peer_index, hash, block),
@@ -228,8 +318,14 @@ impl TaskExecutor for LocalSynchronizationTaskExecutor {
 			Task::Transaction(peer_index, transaction) => self.execute_transaction(peer_index, transaction),
 			Task::WitnessTransaction(peer_index, transaction) => self.execute_witness_transaction(peer_index, transaction),
 			Task::BlockTxn(peer_index, blocktxn) => self.execute_block_txn(peer_index, blocktxn),
+			Task::CFilter(peer_index, cfilter) => self.execute_cfilter(peer_index, cfilter),
+			Task::CFHeaders(peer_index, cfheaders) => self.execute_cfheaders(peer_index, cfheaders),
+			Task::CFCheckpt(peer_index, cfcheckpt) => self.execute_cfcheckpt(peer_index, cfcheckpt),
 			Task::NotFound(peer_index, notfound) => self.execute_notfound(peer_index, notfound),
 			Task::Inventory(peer_index, inventory) => self.execute_inventory(peer_index, inventory),
+			Task::ReqRecon(peer_index, reqrecon) => self.execute_reqrecon(peer_index, reqrecon),
+			Task::Sketch(peer_index, sketch) => self.execute_sketch(peer_index, sketch),
+			Task::ReconcilDiff(peer_index, reconcildiff) => self.execute_reconcildiff(peer_index, reconcildiff),
 			Task::Headers(peer_index, headers, request_id) => self.execute_headers(peer_index, headers, request_id),
 			Task::RelayNewBlock(block) => self.execute_relay_block(block),
 			Task::RelayNewTransaction(transaction, fee_rate) => self.execute_relay_transaction(transaction, fee_rate),
@@ -244,7 +340,8 @@ pub mod tests {
 	use super::*;
 	use crate::inbound_connection::tests::DummyOutboundSyncConnection;
 	use crate::local_node::tests::{default_filterload, make_filteradd};
-	use crate::synchronization_peers::{BlockAnnouncementType, PeersContainer, PeersFilters, PeersImpl, PeersOptions};
+	use crate::synchronization_peers::{BlockAnnouncementType, PeersContainer, PeersFilters, PeersImpl, PeersOptions, PeersReconciliation};
+	use crate::utils::Reconciliation;
 	use chain::Transaction;
 	use message::{types, Services};
 	use parking_lot::{Condvar, Mutex};
@@ -379,6 +476,25 @@ pub mod tests {
 		assert_eq!(*c4.messages.lock().entry("inventory".to_owned()).or_insert(0), 2);
 	}
 
+	#[test]
+	fn relay_new_transaction_with_reconciliation() {
+		let peers = Arc::new(PeersImpl::default());
+		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+
+		let c1 = DummyOutboundSyncConnection::new();
+		peers.insert(1, Services::default(), c1.clone());
+		peers.set_wtxid_relay(1);
+		peers.set_reconciliation(1, Reconciliation::new(true, 1, 2));
+		let c2 = DummyOutboundSyncConnection::new();
+		peers.insert(2, Services::default(), c2.clone());
+
+		// transaction is added to the reconciliation set instead of being announced
+		executor.execute(Task::RelayNewTransaction(test_data::genesis().transactions[0].clone().into(), 0));
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
+		assert_eq!(*c2.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
+		assert_eq!(peers.request_reconciliation(1), Some(types::ReqRecon { set_size: 1, q: 8191 }));
+	}
+
 	#[test]
 	fn relay_new_transaction_with_feefilter() {
 		let peers = Arc::new(PeersImpl::default());
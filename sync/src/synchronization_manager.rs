@@ -89,6 +89,8 @@ impl ManagementWorker {
 			let mut core = core.lock();
 			// trace synchronization state
 			core.print_synchronization_information();
+			// forget compact blocks, which peers have not completed in time
+			core.manage_partial_compact_blocks();
 			// execute management tasks if not saturated
 			if core.state().is_synchronizing() || core.state().is_nearly_saturated() {
 				let (blocks_to_request, blocks_to_forget) =
@@ -110,6 +112,8 @@ impl ManagementWorker {
 				manage_synchronization_peers_headers(&peers_config, core.peers(), core.peers_tasks());
 				manage_orphaned_transactions(&orphan_config, core.orphaned_transactions_pool());
 			} else {
+				core.request_transactions_reconciliation();
+
 				// only remove orphaned blocks when not in synchronization state
 				if let Some(orphans_to_remove) = manage_unknown_orphaned_blocks(&unknown_config, core.orphaned_blocks_pool()) {
 					for orphan_to_remove in orphans_to_remove {
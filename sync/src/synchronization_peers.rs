@@ -1,11 +1,12 @@
 use crate::types::PeerIndex;
-use crate::utils::{ConnectionFilter, KnownHashType};
+use crate::utils::{ConnectionFilter, KnownHashType, Reconciliation, ReconciliationResult};
 use bitcrypto::SHA256D;
 use chain::{IndexedBlock, IndexedTransaction};
 use message::{types, Services};
 use p2p::OutboundSyncConnectionRef;
 use parking_lot::RwLock;
 use std::collections::HashMap;
+use std::time::Instant;
 
 /// Block announcement type
 #[derive(Debug, Clone, Copy)]
@@ -25,6 +26,8 @@ pub enum BlockAnnouncementType {
 pub enum TransactionAnnouncementType {
 	/// Send inventory message with transaction hash [default behavior]
 	SendInventory,
+	/// Add transaction to the reconciliation set of the peer
+	Reconcile,
 	/// Do not announce transactions at all
 	DoNotAnnounce,
 }
@@ -39,7 +42,7 @@ pub struct MerkleBlockArtefacts {
 }
 
 /// Connected peers
-pub trait Peers: Send + Sync + PeersContainer + PeersFilters + PeersOptions {
+pub trait Peers: Send + Sync + PeersContainer + PeersFilters + PeersOptions + PeersReconciliation {
 	/// Require peers services.
 	fn require_peer_services(&self, services: Services);
 	/// Get peer connection
@@ -56,7 +59,7 @@ pub trait PeersContainer {
 	fn remove(&self, peer_index: PeerIndex);
 	/// Close and remove peer connection due to misbehaving
 	fn misbehaving(&self, peer_index: PeerIndex, reason: &str);
-	/// Close and remove peer connection due to detected DOS attempt
+	/// Ban peer and remove its connection due to detected DOS attempt
 	fn dos(&self, peer_index: PeerIndex, reason: &str);
 }
 
@@ -97,6 +100,28 @@ pub trait PeersOptions {
 	fn set_block_announcement_type(&self, peer_index: PeerIndex, announcement_type: BlockAnnouncementType);
 	/// Set up new transaction announcement type for the connection
 	fn set_transaction_announcement_type(&self, peer_index: PeerIndex, announcement_type: TransactionAnnouncementType);
+	/// Announce transactions to the connection using their witness hashes
+	fn set_wtxid_relay(&self, peer_index: PeerIndex);
+	/// Is peer using witness hashes to announce transactions?
+	fn is_wtxid_relay(&self, peer_index: PeerIndex) -> bool;
+}
+
+/// Transactions reconciliation (BIP330) with peers
+pub trait PeersReconciliation {
+	/// Start reconciling transactions with the peer
+	fn set_reconciliation(&self, peer_index: PeerIndex, reconciliation: Reconciliation);
+	/// Add transaction to the peer reconciliation set. Returns false if transaction should be announced right now
+	fn add_reconciliation_transaction(&self, peer_index: PeerIndex, witness_hash: SHA256D) -> bool;
+	/// Start new reconciliation round, if we are the initiator
+	fn request_reconciliation(&self, peer_index: PeerIndex) -> Option<types::ReqRecon>;
+	/// Build sketch of the peer reconciliation set
+	fn reconciliation_sketch(&self, peer_index: PeerIndex, request: &types::ReqRecon) -> Option<types::Sketch>;
+	/// Find the difference between the peer reconciliation set and the sketch of the peer set
+	fn reconcile(&self, peer_index: PeerIndex, sketch: &types::Sketch) -> Option<ReconciliationResult>;
+	/// Finish reconciliation round. Returns witness hashes of transactions to announce
+	fn finish_reconciliation(&self, peer_index: PeerIndex, reconcildiff: &types::ReconcilDiff) -> Option<Vec<SHA256D>>;
+	/// Abandon reconciliation round, which peer has not responded to. Returns witness hashes of transactions to announce
+	fn expire_reconciliation(&self, peer_index: PeerIndex) -> Vec<SHA256D>;
 }
 
 /// Single connected peer data
@@ -111,6 +136,10 @@ struct Peer {
 	pub block_announcement_type: BlockAnnouncementType,
 	/// Transaction announcement type
 	pub transaction_announcement_type: TransactionAnnouncementType,
+	/// Transactions are announced using their witness hashes
+	pub wtxid_relay: bool,
+	/// Transactions reconciliation state
+	pub reconciliation: Option<Reconciliation>,
 }
 
 /// Default implementation of connected peers container
@@ -129,6 +158,8 @@ impl Peer {
 			filter: ConnectionFilter::default(),
 			block_announcement_type: BlockAnnouncementType::SendInventory,
 			transaction_announcement_type: TransactionAnnouncementType::SendInventory,
+			wtxid_relay: false,
+			reconciliation: None,
 		}
 	}
 }
@@ -182,8 +213,8 @@ impl PeersContainer for PeersImpl {
 
 	fn dos(&self, peer_index: PeerIndex, reason: &str) {
 		if let Some(peer) = self.peers.write().remove(&peer_index) {
-			warn!(target: "sync", "Disconnecting from peer#{} due to DoS: {}", peer_index, reason);
-			peer.connection.close();
+			warn!(target: "sync", "Banning peer#{} due to DoS: {}", peer_index, reason);
+			peer.connection.ban();
 		}
 	}
 }
@@ -287,4 +318,73 @@ impl PeersOptions for PeersImpl {
 			peer.transaction_announcement_type = announcement_type;
 		}
 	}
+
+	fn set_wtxid_relay(&self, peer_index: PeerIndex) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			peer.wtxid_relay = true;
+		}
+	}
+
+	fn is_wtxid_relay(&self, peer_index: PeerIndex) -> bool {
+		self.peers.read().get(&peer_index).map(|peer| peer.wtxid_relay).unwrap_or_default()
+	}
+}
+
+impl PeersReconciliation for PeersImpl {
+	fn set_reconciliation(&self, peer_index: PeerIndex, reconciliation: Reconciliation) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			peer.reconciliation = Some(reconciliation);
+			peer.transaction_announcement_type = TransactionAnnouncementType::Reconcile;
+		}
+	}
+
+	fn add_reconciliation_transaction(&self, peer_index: PeerIndex, witness_hash: SHA256D) -> bool {
+		self.peers
+			.write()
+			.get_mut(&peer_index)
+			.and_then(|peer| peer.reconciliation.as_mut())
+			.map(|reconciliation| reconciliation.add_transaction(witness_hash))
+			.unwrap_or_default()
+	}
+
+	fn request_reconciliation(&self, peer_index: PeerIndex) -> Option<types::ReqRecon> {
+		self.peers
+			.write()
+			.get_mut(&peer_index)
+			.and_then(|peer| peer.reconciliation.as_mut())
+			.and_then(|reconciliation| reconciliation.request())
+	}
+
+	fn reconciliation_sketch(&self, peer_index: PeerIndex, request: &types::ReqRecon) -> Option<types::Sketch> {
+		self.peers
+			.write()
+			.get_mut(&peer_index)
+			.and_then(|peer| peer.reconciliation.as_mut())
+			.and_then(|reconciliation| reconciliation.sketch(request))
+	}
+
+	fn reconcile(&self, peer_index: PeerIndex, sketch: &types::Sketch) -> Option<ReconciliationResult> {
+		self.peers
+			.write()
+			.get_mut(&peer_index)
+			.and_then(|peer| peer.reconciliation.as_mut())
+			.and_then(|reconciliation| reconciliation.reconcile(sketch))
+	}
+
+	fn finish_reconciliation(&self, peer_index: PeerIndex, reconcildiff: &types::ReconcilDiff) -> Option<Vec<SHA256D>> {
+		self.peers
+			.write()
+			.get_mut(&peer_index)
+			.and_then(|peer| peer.reconciliation.as_mut())
+			.and_then(|reconciliation| reconciliation.finish(reconcildiff))
+	}
+
+	fn expire_reconciliation(&self, peer_index: PeerIndex) -> Vec<SHA256D> {
+		self.peers
+			.write()
+			.get_mut(&peer_index)
+			.and_then(|peer| peer.reconciliation.as_mut())
+			.map(|reconciliation| reconciliation.expire(Instant::now()))
+			.unwrap_or_default()
+	}
 }
@@ -5,7 +5,7 @@ use linked_hash_map::LinkedHashMap;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Max peer failures # before excluding from sync process
 const MAX_PEER_FAILURES: usize = 4;
@@ -13,6 +13,8 @@ const MAX_PEER_FAILURES: usize = 4;
 const MAX_BLOCKS_FAILURES: usize = 6;
 /// Number of blocks to inspect while calculating average response time
 const BLOCKS_TO_INSPECT: usize = 32;
+/// Time after which transaction could be requested from another peer
+const TRANSACTION_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// Information on synchronization peers
 pub struct Information {
@@ -45,6 +47,8 @@ pub struct PeersTasks {
 	stats: HashMap<PeerIndex, PeerStats>,
 	/// Blocks statistics
 	blocks_stats: HashMap<SHA256D, BlockStats>,
+	/// Pending transactions requests (by witness hash) sent to peers
+	transactions_requests: HashMap<SHA256D, (PeerIndex, Instant)>,
 }
 
 /// Pending headers request
@@ -194,6 +198,7 @@ impl PeersTasks {
 		self.headers_requests.remove(&peer_index);
 		self.blocks_requests.remove(&peer_index);
 		self.stats.remove(&peer_index);
+		self.transactions_requests.retain(|_, (peer, _)| *peer != peer_index);
 	}
 
 	/// Block is received from peer.
@@ -336,6 +341,30 @@ impl PeersTasks {
 			.unwrap_or_default()
 	}
 
+	/// Transactions have been requested from peer by their witness hashes.
+	pub fn on_transactions_requested(&mut self, peer_index: PeerIndex, witness_hashes: &[SHA256D]) {
+		let now = Instant::now();
+		self.transactions_requests
+			.retain(|_, (_, timestamp)| now.duration_since(*timestamp) < TRANSACTION_REQUEST_TIMEOUT);
+		self.transactions_requests
+			.extend(witness_hashes.iter().map(|hash| (*hash, (peer_index, now))));
+	}
+
+	/// Is transaction with given witness hash requested from some peer?
+	pub fn is_transaction_requested(&self, witness_hash: &SHA256D) -> bool {
+		self.transactions_requests
+			.get(witness_hash)
+			.map(|(_, timestamp)| timestamp.elapsed() < TRANSACTION_REQUEST_TIMEOUT)
+			.unwrap_or(false)
+	}
+
+	/// Transaction has been received or peer has responded with notfound.
+	pub fn on_transaction_response(&mut self, peer_index: PeerIndex, witness_hash: &SHA256D) {
+		if self.transactions_requests.get(witness_hash).map(|(peer, _)| *peer) == Some(peer_index) {
+			self.transactions_requests.remove(witness_hash);
+		}
+	}
+
 	/// Reset all peers state to the unuseful
 	pub fn reset(&mut self) {
 		self.unuseful.clear();
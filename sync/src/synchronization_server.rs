@@ -2,7 +2,7 @@ use crate::synchronization_executor::{Task, TaskExecutor};
 use crate::types::{BlockHeight, ExecutorRef, MemoryPoolRef, PeerIndex, PeersRef, RequestId, StorageRef};
 use crate::utils::KnownHashType;
 use bitcrypto::SHA256D;
-use chain::IndexedTransaction;
+use chain::{IndexedBlock, IndexedTransaction, BASIC_FILTER_TYPE};
 use message::{common, types};
 use parking_lot::{Condvar, Mutex};
 use std::collections::hash_map::Entry;
@@ -11,6 +11,9 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 
+/// Blocks, which are older than the best block by this number of seconds, are considered historical
+const HISTORICAL_BLOCK_AGE: u32 = 7 * 24 * 60 * 60;
+
 /// Synchronization server task
 #[derive(Debug, PartialEq)]
 pub enum ServerTask {
@@ -26,6 +29,18 @@ pub enum ServerTask {
 	Mempool(PeerIndex),
 	/// Serve 'getblocktxn' request
 	GetBlockTxn(PeerIndex, types::GetBlockTxn),
+	/// Serve 'getcfilters' request
+	GetCFilters(PeerIndex, types::GetCFilters),
+	/// Serve 'getcfheaders' request
+	GetCFHeaders(PeerIndex, types::GetCFHeaders),
+	/// Serve 'getcfcheckpt' request
+	GetCFCheckpt(PeerIndex, types::GetCFCheckpt),
+	/// Serve 'reqrecon' request
+	ReqRecon(PeerIndex, types::ReqRecon),
+	/// Reconcile transactions using 'sketch' response
+	Sketch(PeerIndex, types::Sketch),
+	/// Finish reconciliation round
+	ReconcilDiff(PeerIndex, types::ReconcilDiff),
 }
 
 /// Synchronization server
@@ -84,7 +99,13 @@ impl ServerTask {
 			| ServerTask::GetBlocks(peer_index, _)
 			| ServerTask::GetHeaders(peer_index, _, _)
 			| ServerTask::Mempool(peer_index)
-			| ServerTask::GetBlockTxn(peer_index, _) => peer_index,
+			| ServerTask::GetBlockTxn(peer_index, _)
+			| ServerTask::GetCFilters(peer_index, _)
+			| ServerTask::GetCFHeaders(peer_index, _)
+			| ServerTask::GetCFCheckpt(peer_index, _)
+			| ServerTask::ReqRecon(peer_index, _)
+			| ServerTask::Sketch(peer_index, _)
+			| ServerTask::ReconcilDiff(peer_index, _) => peer_index,
 		}
 	}
 }
@@ -247,6 +268,12 @@ where
 			ServerTask::GetHeaders(peer_index, message, request_id) => self.serve_get_headers(peer_index, message, request_id),
 			ServerTask::Mempool(peer_index) => self.serve_mempool(peer_index),
 			ServerTask::GetBlockTxn(peer_index, message) => self.serve_get_block_txn(peer_index, message),
+			ServerTask::GetCFilters(peer_index, message) => self.serve_get_cfilters(peer_index, message),
+			ServerTask::GetCFHeaders(peer_index, message) => self.serve_get_cfheaders(peer_index, message),
+			ServerTask::GetCFCheckpt(peer_index, message) => self.serve_get_cfcheckpt(peer_index, message),
+			ServerTask::ReqRecon(peer_index, message) => self.serve_reqrecon(peer_index, message),
+			ServerTask::Sketch(peer_index, message) => self.serve_sketch(peer_index, message),
+			ServerTask::ReconcilDiff(peer_index, message) => self.serve_reconcildiff(peer_index, message),
 		}
 
 		None
@@ -300,8 +327,23 @@ where
 					notfound.inventory.push(next_item);
 				}
 			}
+			common::InventoryType::MessageWtx => {
+				// only transaction from memory pool can be requested
+				let memory_pool = self.memory_pool.read();
+				let transaction = memory_pool.hash_by_witness_hash(&next_item.hash).and_then(|hash| {
+					memory_pool
+						.read_by_hash(&hash)
+						.map(|transaction| IndexedTransaction::new(hash, transaction.clone()))
+				});
+				if let Some(transaction) = transaction {
+					trace!(target: "sync", "'getblocks' response to peer#{} is ready with wtx {}", peer_index, next_item.hash);
+					self.executor.execute(Task::WitnessTransaction(peer_index, transaction));
+				} else {
+					notfound.inventory.push(next_item);
+				}
+			}
 			common::InventoryType::MessageBlock => {
-				if let Some(block) = self.storage.block(next_item.hash.clone().into()) {
+				if let Some(block) = self.served_block(peer_index, &next_item.hash) {
 					trace!(target: "sync", "'getblocks' response to peer#{} is ready with block {}", peer_index, next_item.hash);
 					self.executor.execute(Task::Block(peer_index, block));
 				} else {
@@ -309,7 +351,7 @@ where
 				}
 			}
 			common::InventoryType::MessageFilteredBlock => {
-				if let Some(block) = self.storage.block(next_item.hash.clone().into()) {
+				if let Some(block) = self.served_block(peer_index, &next_item.hash) {
 					let message_artefacts = self.peers.build_merkle_block(peer_index, &block);
 					if let Some(message_artefacts) = message_artefacts {
 						// send merkleblock first
@@ -330,7 +372,7 @@ where
 				}
 			}
 			common::InventoryType::MessageCompactBlock => {
-				if let Some(block) = self.storage.block(next_item.hash.clone().into()) {
+				if let Some(block) = self.served_block(peer_index, &next_item.hash) {
 					let message = self.peers.build_compact_block(peer_index, &block);
 					if let Some(message) = message {
 						trace!(target: "sync", "'getblocks' response to peer#{} is ready with compactblock {}", peer_index, next_item.hash);
@@ -341,7 +383,7 @@ where
 				}
 			}
 			common::InventoryType::MessageWitnessBlock => {
-				if let Some(block) = self.storage.block(next_item.hash.clone().into()) {
+				if let Some(block) = self.served_block(peer_index, &next_item.hash) {
 					trace!(target: "sync", "'getblocks' response to peer#{} is ready with witness-block {}", peer_index, next_item.hash);
 					self.executor.execute(Task::WitnessBlock(peer_index, block.into()));
 				} else {
@@ -354,6 +396,23 @@ where
 		Some(ServerTask::ReversedGetData(peer_index, message, notfound))
 	}
 
+	/// Reads block, requested by the peer. Historical blocks are not served once the upload target is nearly reached.
+	fn served_block(&self, peer_index: PeerIndex, hash: &SHA256D) -> Option<IndexedBlock> {
+		let block = self.storage.block((*hash).into())?;
+		let best_block_time = self.storage.best_header().raw.time;
+		if best_block_time.saturating_sub(block.header.raw.time) > HISTORICAL_BLOCK_AGE
+			&& self
+				.peers
+				.connection(peer_index)
+				.map_or(false, |connection| connection.is_historical_block_serving_limited())
+		{
+			trace!(target: "sync", "Not serving historical block {} to peer#{}: upload target is reached", hash, peer_index);
+			return None;
+		}
+
+		Some(block)
+	}
+
 	fn serve_get_blocks(&self, peer_index: PeerIndex, message: types::GetBlocks) {
 		if let Some(block_height) = self.locate_best_common_block(&message.hash_stop, &message.block_locator_hashes) {
 			let inventory: Vec<_> = (block_height + 1..block_height + 1 + (types::GETBLOCKS_MAX_RESPONSE_HASHES as BlockHeight))
@@ -400,13 +459,21 @@ where
 	}
 
 	fn serve_mempool(&self, peer_index: PeerIndex) {
-		let inventory: Vec<_> = self
-			.memory_pool
-			.read()
-			.get_transactions_ids()
-			.into_iter()
-			.map(common::InventoryVector::tx)
-			.collect();
+		let inventory: Vec<_> = if self.peers.is_wtxid_relay(peer_index) {
+			self.memory_pool
+				.read()
+				.get_transactions_witness_ids()
+				.into_iter()
+				.map(common::InventoryVector::wtx)
+				.collect()
+		} else {
+			self.memory_pool
+				.read()
+				.get_transactions_ids()
+				.into_iter()
+				.map(common::InventoryVector::tx)
+				.collect()
+		};
 		// empty inventory messages are invalid according to regtests, while empty headers messages are valid
 		if !inventory.is_empty() {
 			trace!(target: "sync", "'mempool' response to peer#{} is ready with {} transactions", peer_index, inventory.len());
@@ -497,6 +564,222 @@ where
 		));
 	}
 
+	fn serve_get_cfilters(&self, peer_index: PeerIndex, message: types::GetCFilters) {
+		let block_hashes = match self.locate_filtered_blocks(
+			peer_index,
+			"getcfilters",
+			message.filter_type,
+			message.start_height,
+			&message.stop_hash,
+			types::GETCFILTERS_MAX_RESPONSE_FILTERS,
+		) {
+			Some(stop_height) => self.canon_block_hashes(message.start_height, stop_height),
+			None => return,
+		};
+
+		let mut filters = Vec::with_capacity(block_hashes.len());
+		for block_hash in block_hashes {
+			match self.storage.block_filter(&block_hash) {
+				Ok(Some(filter)) => filters.push((block_hash, filter)),
+				Ok(None) | Err(_) => {
+					warn!(target: "sync", "'getcfilters' request from peer#{} is ignored as we have failed to find filter of block {} in storage", peer_index, block_hash);
+					return;
+				}
+			}
+		}
+
+		trace!(target: "sync", "'getcfilters' response to peer#{} is ready with {} filters", peer_index, filters.len());
+		for (block_hash, filter) in filters {
+			self.executor.execute(Task::CFilter(
+				peer_index,
+				types::CFilter {
+					filter_type: message.filter_type,
+					block_hash,
+					filter: filter.content,
+				},
+			));
+		}
+	}
+
+	fn serve_get_cfheaders(&self, peer_index: PeerIndex, message: types::GetCFHeaders) {
+		let block_hashes = match self.locate_filtered_blocks(
+			peer_index,
+			"getcfheaders",
+			message.filter_type,
+			message.start_height,
+			&message.stop_hash,
+			types::GETCFHEADERS_MAX_RESPONSE_HEADERS,
+		) {
+			Some(stop_height) => self.canon_block_hashes(message.start_height, stop_height),
+			None => return,
+		};
+
+		let previous_filter_header = match message.start_height {
+			0 => Some(SHA256D::default()),
+			start_height => self
+				.storage
+				.block_hash(start_height - 1)
+				.and_then(|block_hash| self.storage.block_filter_header(&block_hash).ok().and_then(|header| header)),
+		};
+		let filter_hashes: Option<Vec<_>> = block_hashes
+			.iter()
+			.map(|block_hash| {
+				self.storage
+					.block_filter(block_hash)
+					.ok()
+					.and_then(|filter| filter)
+					.map(|filter| filter.hash())
+			})
+			.collect();
+		let (previous_filter_header, filter_hashes) = match (previous_filter_header, filter_hashes) {
+			(Some(previous_filter_header), Some(filter_hashes)) => (previous_filter_header, filter_hashes),
+			_ => {
+				warn!(target: "sync", "'getcfheaders' request from peer#{} is ignored as we have failed to find filters in storage", peer_index);
+				return;
+			}
+		};
+
+		trace!(target: "sync", "'getcfheaders' response to peer#{} is ready with {} hashes", peer_index, filter_hashes.len());
+		self.executor.execute(Task::CFHeaders(
+			peer_index,
+			types::CFHeaders {
+				filter_type: message.filter_type,
+				stop_hash: message.stop_hash,
+				previous_filter_header,
+				filter_hashes,
+			},
+		));
+	}
+
+	fn serve_get_cfcheckpt(&self, peer_index: PeerIndex, message: types::GetCFCheckpt) {
+		let stop_height = match self.locate_filtered_blocks(
+			peer_index,
+			"getcfcheckpt",
+			message.filter_type,
+			0,
+			&message.stop_hash,
+			u32::MAX,
+		) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let filter_headers: Option<Vec<_>> = (1..stop_height / types::CFCHECKPT_INTERVAL + 1)
+			.map(|checkpoint| self.storage.block_hash(checkpoint * types::CFCHECKPT_INTERVAL))
+			.map(|block_hash| {
+				block_hash.and_then(|block_hash| self.storage.block_filter_header(&block_hash).ok().and_then(|header| header))
+			})
+			.collect();
+		let filter_headers = match filter_headers {
+			Some(filter_headers) => filter_headers,
+			None => {
+				warn!(target: "sync", "'getcfcheckpt' request from peer#{} is ignored as we have failed to find filters in storage", peer_index);
+				return;
+			}
+		};
+
+		trace!(target: "sync", "'getcfcheckpt' response to peer#{} is ready with {} headers", peer_index, filter_headers.len());
+		self.executor.execute(Task::CFCheckpt(
+			peer_index,
+			types::CFCheckpt {
+				filter_type: message.filter_type,
+				stop_hash: message.stop_hash,
+				filter_headers,
+			},
+		));
+	}
+
+	fn serve_reqrecon(&self, peer_index: PeerIndex, message: types::ReqRecon) {
+		match self.peers.reconciliation_sketch(peer_index, &message) {
+			Some(sketch) => {
+				trace!(target: "sync", "'reqrecon' response to peer#{} is ready with {} bytes sketch", peer_index, sketch.skdata.len());
+				self.executor.execute(Task::Sketch(peer_index, sketch));
+			}
+			None => self.peers.misbehaving(peer_index, "Got unexpected 'reqrecon' message"),
+		}
+	}
+
+	fn serve_sketch(&self, peer_index: PeerIndex, message: types::Sketch) {
+		match self.peers.reconcile(peer_index, &message) {
+			Some(result) => {
+				trace!(target: "sync", "Reconciled transactions with peer#{}: success {}, {} announced, {} requested", peer_index, result.reconcildiff.success, result.announce.len(), result.reconcildiff.ask_shortids.len());
+				self.executor.execute(Task::ReconcilDiff(peer_index, result.reconcildiff));
+				self.announce_reconciled_transactions(peer_index, result.announce);
+			}
+			None => self.peers.misbehaving(peer_index, "Got unexpected 'sketch' message"),
+		}
+	}
+
+	fn serve_reconcildiff(&self, peer_index: PeerIndex, message: types::ReconcilDiff) {
+		match self.peers.finish_reconciliation(peer_index, &message) {
+			Some(announce) => self.announce_reconciled_transactions(peer_index, announce),
+			None => self.peers.misbehaving(peer_index, "Got unexpected 'reconcildiff' message"),
+		}
+	}
+
+	/// Announce transactions, which are missing from the peer set after reconciliation
+	fn announce_reconciled_transactions(&self, peer_index: PeerIndex, witness_hashes: Vec<SHA256D>) {
+		if witness_hashes.is_empty() {
+			return;
+		}
+
+		let inventory = witness_hashes.into_iter().map(common::InventoryVector::wtx).collect();
+		self.executor
+			.execute(Task::Inventory(peer_index, types::Inv::with_inventory(inventory)));
+	}
+
+	/// Returns height of the `stop_hash` block if filters request is valid
+	fn locate_filtered_blocks(
+		&self,
+		peer_index: PeerIndex,
+		command: &str,
+		filter_type: u8,
+		start_height: BlockHeight,
+		stop_hash: &SHA256D,
+		max_blocks: u32,
+	) -> Option<BlockHeight> {
+		if filter_type != BASIC_FILTER_TYPE {
+			self.peers.misbehaving(
+				peer_index,
+				&format!("Got '{}' message with unsupported filter type {}", command, filter_type),
+			);
+			return None;
+		}
+		if let Err(storage::Error::BlockFilterIndexDisabled) = self.storage.block_filter_header(stop_hash) {
+			self.peers
+				.misbehaving(peer_index, &format!("Got '{}' message when block filters are not served", command));
+			return None;
+		}
+
+		let stop_height = match self.storage.block_number(stop_hash) {
+			Some(stop_height) => stop_height,
+			None => {
+				trace!(target: "sync", "'{}' request from peer#{} is ignored as {} is not a canon block", command, peer_index, stop_hash);
+				return None;
+			}
+		};
+		if start_height > stop_height || stop_height - start_height >= max_blocks {
+			self.peers.misbehaving(
+				peer_index,
+				&format!(
+					"Got '{}' message with invalid range: start height {}, stop height {}",
+					command, start_height, stop_height
+				),
+			);
+			return None;
+		}
+
+		Some(stop_height)
+	}
+
+	fn canon_block_hashes(&self, start_height: BlockHeight, stop_height: BlockHeight) -> Vec<SHA256D> {
+		(start_height..stop_height + 1)
+			.map(|block_height| self.storage.block_hash(block_height))
+			.take_while(Option::is_some)
+			.map(Option::unwrap)
+			.collect()
+	}
+
 	fn locate_best_common_block(&self, hash_stop: &SHA256D, locator: &[SHA256D]) -> Option<BlockHeight> {
 		for block_hash in locator.iter().chain(&[hash_stop.clone()]) {
 			if let Some(block_number) = self.storage.block_number(block_hash) {
@@ -536,15 +819,16 @@ pub mod tests {
 	use crate::synchronization_executor::Task;
 	use crate::synchronization_peers::{PeersContainer, PeersFilters, PeersImpl};
 	use crate::types::{ExecutorRef, MemoryPoolRef, PeerIndex, PeersRef, StorageRef};
-	use crate::utils::KnownHashType;
+	use crate::utils::{KnownHashType, Reconciliation};
 	use bitcrypto::SHA256D;
-	use chain::Transaction;
+	use chain::{BlockFilter, Transaction};
 	use db::BlockChainDatabase;
 	use message::common::{self, InventoryType, InventoryVector, Services};
 	use message::types;
 	use miner::{MemoryPool, NonZeroFeeCalculator};
 	use parking_lot::{Mutex, RwLock};
 	use std::mem::replace;
+	use std::sync::atomic::Ordering;
 	use std::sync::Arc;
 
 	pub struct DummyServer {
@@ -580,6 +864,21 @@ pub mod tests {
 		(storage, memory_pool, executor, peers, server)
 	}
 
+	fn create_synchronization_server_with_block_filters() -> (ExecutorRef<DummyTaskExecutor>, PeersRef, ServerImpl) {
+		let peers = Arc::new(PeersImpl::default());
+		let storage = BlockChainDatabase::transient(&test_data::genesis().into())
+			.unwrap()
+			.with_block_filters(true);
+		storage.build_missing_block_filters(1).unwrap();
+		storage.insert(test_data::block_h1().into()).unwrap();
+		storage.canonize(&test_data::block_h1().hash()).unwrap();
+		let storage = Arc::new(storage);
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		let executor = DummyTaskExecutor::new();
+		let server = ServerImpl::new(peers.clone(), storage, memory_pool, executor.clone());
+		(executor, peers, server)
+	}
+
 	#[test]
 	fn server_getdata_responds_notfound_when_block_not_found() {
 		let (_, _, executor, _, server) = create_synchronization_server();
@@ -691,6 +990,52 @@ pub mod tests {
 		assert_eq!(tasks, vec![Task::Headers(0, types::Headers::with_headers(headers), Some(dummy_id))]);
 	}
 
+	#[test]
+	fn server_responds_to_reconciliation_requests() {
+		let (_, _, executor, peers, server) = create_synchronization_server();
+		let transactions: Vec<SHA256D> = vec![
+			test_data::TransactionBuilder::with_output(10).into(),
+			test_data::TransactionBuilder::with_output(20).into(),
+		]
+		.into_iter()
+		.map(|transaction: Transaction| transaction.witness_hash())
+		.collect();
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		peers.set_reconciliation(0, Reconciliation::new(false, 1, 2));
+		for transaction in &transactions {
+			assert!(peers.add_reconciliation_transaction(0, *transaction));
+		}
+
+		// when peer requests reconciliation
+		server.execute(ServerTask::ReqRecon(0, types::ReqRecon { set_size: 0, q: 0 }));
+		// => respond with sketch
+		let tasks = DummyTaskExecutor::wait_tasks(executor.clone());
+		assert!(matches!(tasks[..], [Task::Sketch(0, _)]));
+
+		// when peer fails to reconcile sets
+		server.execute(ServerTask::ReconcilDiff(
+			0,
+			types::ReconcilDiff {
+				success: false,
+				ask_shortids: vec![],
+			},
+		));
+		// => announce all transactions
+		let mut tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(tasks.len(), 1);
+		match tasks.pop() {
+			Some(Task::Inventory(0, inv)) => {
+				let mut announced: Vec<_> = inv.inventory.iter().map(|item| item.hash).collect();
+				announced.sort();
+				let mut expected = transactions.clone();
+				expected.sort();
+				assert_eq!(announced, expected);
+				assert!(inv.inventory.iter().all(|item| item.inv_type == InventoryType::MessageWtx));
+			}
+			task => panic!("unexpected task: {:?}", task),
+		}
+	}
+
 	#[test]
 	fn server_mempool_do_not_responds_inventory_when_empty_memory_pool() {
 		let (_, _, executor, _, server) = create_synchronization_server();
@@ -707,7 +1052,10 @@ pub mod tests {
 		// when memory pool is non-empty
 		let transaction = Transaction::default();
 		let transaction_hash = transaction.hash();
-		memory_pool.write().insert_verified(transaction.into(), &NonZeroFeeCalculator);
+		memory_pool
+			.write()
+			.insert_verified(transaction.into(), &NonZeroFeeCalculator)
+			.unwrap();
 		// when asking for memory pool transactions ids
 		server.execute(ServerTask::Mempool(0));
 		// => respond with inventory
@@ -778,6 +1126,122 @@ pub mod tests {
 		assert!(!peers.enumerate().contains(&0));
 	}
 
+	#[test]
+	fn server_get_cfilters_responds_filters() {
+		let (executor, _, server) = create_synchronization_server_with_block_filters();
+		server.execute(ServerTask::GetCFilters(
+			0,
+			types::GetCFilters {
+				filter_type: 0,
+				start_height: 0,
+				stop_hash: test_data::block_h1().hash(),
+			},
+		));
+
+		// all filters are sent by separate tasks
+		use std::thread;
+		use std::time::Duration;
+		thread::park_timeout(Duration::from_millis(100)); // TODO: get rid of timeout
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		let filter = |block: chain::Block| types::CFilter {
+			filter_type: 0,
+			block_hash: block.hash(),
+			filter: BlockFilter::basic(&block.into(), vec![]).content,
+		};
+		assert_eq!(
+			tasks,
+			vec![
+				Task::CFilter(0, filter(test_data::genesis())),
+				Task::CFilter(0, filter(test_data::block_h1()))
+			]
+		);
+	}
+
+	#[test]
+	fn server_get_cfheaders_responds_filter_hashes() {
+		let (executor, _, server) = create_synchronization_server_with_block_filters();
+		server.execute(ServerTask::GetCFHeaders(
+			0,
+			types::GetCFHeaders {
+				filter_type: 0,
+				start_height: 1,
+				stop_hash: test_data::block_h1().hash(),
+			},
+		));
+
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		let genesis_filter = BlockFilter::basic(&test_data::genesis().into(), vec![]);
+		let filter = BlockFilter::basic(&test_data::block_h1().into(), vec![]);
+		assert_eq!(
+			tasks,
+			vec![Task::CFHeaders(
+				0,
+				types::CFHeaders {
+					filter_type: 0,
+					stop_hash: test_data::block_h1().hash(),
+					previous_filter_header: genesis_filter.header(&SHA256D::default()),
+					filter_hashes: vec![filter.hash()],
+				}
+			)]
+		);
+	}
+
+	#[test]
+	fn server_get_cfcheckpt_responds_checkpoints() {
+		let (executor, _, server) = create_synchronization_server_with_block_filters();
+		server.execute(ServerTask::GetCFCheckpt(
+			0,
+			types::GetCFCheckpt {
+				filter_type: 0,
+				stop_hash: test_data::block_h1().hash(),
+			},
+		));
+
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(
+			tasks,
+			vec![Task::CFCheckpt(
+				0,
+				types::CFCheckpt {
+					filter_type: 0,
+					stop_hash: test_data::block_h1().hash(),
+					filter_headers: vec![],
+				}
+			)]
+		);
+	}
+
+	#[test]
+	fn server_get_cfilters_disconnects_when_bad_request() {
+		let (_, peers, server) = create_synchronization_server_with_block_filters();
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+
+		// unsupported filter type
+		server.execute(ServerTask::GetCFilters(
+			0,
+			types::GetCFilters {
+				filter_type: 1,
+				start_height: 0,
+				stop_hash: test_data::block_h1().hash(),
+			},
+		));
+		// start height is above stop height
+		server.execute(ServerTask::GetCFilters(
+			1,
+			types::GetCFilters {
+				filter_type: 0,
+				start_height: 2,
+				stop_hash: test_data::block_h1().hash(),
+			},
+		));
+
+		use std::thread;
+		use std::time::Duration;
+		thread::park_timeout(Duration::from_millis(100)); // TODO: get rid of timeout
+		assert!(peers.enumerate().is_empty());
+	}
+
 	#[test]
 	fn server_getdata_responds_notfound_when_transaction_is_inaccessible() {
 		let (_, _, executor, _, server) = create_synchronization_server();
@@ -807,7 +1271,8 @@ pub mod tests {
 		{
 			memory_pool
 				.write()
-				.insert_verified(tx_verified.clone().into(), &NonZeroFeeCalculator);
+				.insert_verified(tx_verified.clone().into(), &NonZeroFeeCalculator)
+				.unwrap();
 		}
 		// when asking for known in-memory transaction
 		let inventory = vec![InventoryVector {
@@ -873,6 +1338,53 @@ pub mod tests {
 		assert_eq!(tasks, vec![Task::Headers(0, types::Headers::with_headers(headers), Some(dummy_id))]);
 	}
 
+	#[test]
+	fn server_does_not_serve_historical_blocks_when_upload_target_is_reached() {
+		let peers = Arc::new(PeersImpl::default());
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		let sync_executor = DummyTaskExecutor::new();
+		let executor = ServerTaskExecutor::new(peers.clone(), storage.clone(), memory_pool, sync_executor.clone());
+
+		// genesis is more than week older than b1
+		let genesis = test_data::genesis();
+		#[rustfmt::skip]
+		let b1 = test_data::block_builder()
+			.header().parent(genesis.hash()).time(genesis.block_header.time + 8 * 24 * 60 * 60).build()
+			.transaction().coinbase().build()
+			.build();
+		storage.insert(b1.clone().into()).expect("no error");
+		storage.canonize(&b1.hash()).unwrap();
+
+		let connection = DummyOutboundSyncConnection::new();
+		connection.historical_block_serving_limited.store(true, Ordering::SeqCst);
+		peers.insert(0, Services::default(), connection.clone());
+
+		let getdata = || types::GetData::with_inventory(vec![InventoryVector::block(genesis.hash()), InventoryVector::block(b1.hash())]);
+		let mut loop_task = ServerTask::GetData(0, getdata());
+		while let Some(new_task) = executor.execute(loop_task) {
+			loop_task = new_task;
+		}
+		assert_eq!(
+			sync_executor.take_tasks(),
+			vec![
+				Task::Block(0, b1.clone().into()),
+				Task::NotFound(0, types::NotFound::with_inventory(vec![InventoryVector::block(genesis.hash())])),
+			]
+		);
+
+		// all blocks are served when the target is not reached
+		connection.historical_block_serving_limited.store(false, Ordering::SeqCst);
+		let mut loop_task = ServerTask::GetData(0, getdata());
+		while let Some(new_task) = executor.execute(loop_task) {
+			loop_task = new_task;
+		}
+		assert_eq!(
+			sync_executor.take_tasks(),
+			vec![Task::Block(0, genesis.into()), Task::Block(0, b1.into())]
+		);
+	}
+
 	#[test]
 	fn server_serves_merkleblock() {
 		let peers = Arc::new(PeersImpl::default());
@@ -6,13 +6,14 @@ use chain::{IndexedBlock, IndexedTransaction};
 use network::ConsensusParams;
 use parking_lot::Mutex;
 use primitives::time::{RealTime, Time};
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 use std::thread;
 use verification::{
-	BackwardsCompatibleChainVerifier as ChainVerifier, Error as VerificationError, VerificationLevel, Verify as VerificationVerify,
+	BackwardsCompatibleChainVerifier as ChainVerifier, Error as VerificationError, PackageError, PackageVerifier, VerificationLevel,
+	Verify as VerificationVerify,
 };
 
 /// Block verification events sink
@@ -27,6 +28,8 @@ pub trait BlockVerificationSink: Send + Sync + 'static {
 pub trait TransactionVerificationSink: Send + Sync + 'static {
 	/// When transaction verification has completed successfully.
 	fn on_transaction_verification_success(&self, transaction: IndexedTransaction);
+	/// When package verification has completed successfully.
+	fn on_package_verification_success(&self, package: Vec<IndexedTransaction>);
 	/// When transaction verification has failed.
 	fn on_transaction_verification_error(&self, err: &str, hash: &SHA256D);
 }
@@ -41,6 +44,8 @@ pub enum VerificationTask {
 	VerifyBlock(IndexedBlock),
 	/// Verify single transaction
 	VerifyTransaction(BlockHeight, IndexedTransaction),
+	/// Verify package of transactions (parents + child)
+	VerifyPackage(BlockHeight, Vec<IndexedTransaction>),
 	/// Stop verification thread
 	Stop,
 }
@@ -51,6 +56,8 @@ pub trait Verifier: Send + Sync + 'static {
 	fn verify_block(&self, block: IndexedBlock);
 	/// Verify transaction
 	fn verify_transaction(&self, height: BlockHeight, transaction: IndexedTransaction);
+	/// Verify package of transactions
+	fn verify_package(&self, height: BlockHeight, package: Vec<IndexedTransaction>);
 }
 
 /// Asynchronous synchronization verifier
@@ -98,6 +105,39 @@ impl ChainVerifierWrapper {
 
 		self.verifier.verify(verification_level, block)
 	}
+
+	/// Verify package of memory pool transactions.
+	/// Package transactions, which are already in the memory pool, are not verified again.
+	pub fn verify_package(
+		&self,
+		storage: &StorageRef,
+		memory_pool: &MemoryPoolRef,
+		height: BlockHeight,
+		package: &[IndexedTransaction],
+	) -> Result<(), PackageError> {
+		PackageVerifier::new(package).check()?;
+
+		let time: u32 = RealTime.now().as_secs() as u32;
+		for (index, transaction) in package.iter().enumerate() {
+			if memory_pool.read().contains(&transaction.hash) {
+				continue;
+			}
+
+			let package_error = |err| PackageError::Transaction(transaction.hash, err);
+			let tx_output_provider = MemoryPoolTransactionOutputProvider::for_package_transaction(
+				storage.clone(),
+				memory_pool,
+				&package[..index],
+				&transaction.raw,
+			)
+			.map_err(package_error)?;
+			self.verifier
+				.verify_mempool_transaction(storage.as_block_header_provider(), &tx_output_provider, height, time, transaction)
+				.map_err(package_error)?;
+		}
+
+		Ok(())
+	}
 }
 
 impl VerificationTask {
@@ -202,12 +242,49 @@ impl AsyncVerifier {
 						}
 					};
 				}
+				VerificationTask::VerifyPackage(height, package) => match verifier.verify_package(storage, memory_pool, height, &package) {
+					Ok(_) => sink.on_package_verification_success(package),
+					Err(e) => AsyncVerifier::on_package_verification_error(sink, memory_pool, package, e),
+				},
 				VerificationTask::Stop => return false,
 			}
 		}
 
 		true
 	}
+
+	/// Report package verification error for every package transaction, which is not in the memory pool.
+	/// Transactions, which precede the invalid one and do not spend outputs of other package transactions,
+	/// are valid on their own => they are reported as verified, so that they still could be accepted individually.
+	fn on_package_verification_error<T: VerificationSink>(
+		sink: &Arc<T>,
+		memory_pool: &MemoryPoolRef,
+		package: Vec<IndexedTransaction>,
+		err: PackageError,
+	) {
+		let valid_transactions = match err {
+			PackageError::Transaction(ref hash, _) => package.iter().position(|tx| tx.hash == *hash).unwrap_or(0),
+			_ => 0,
+		};
+		let package_hashes: HashSet<SHA256D> = package.iter().map(|tx| tx.hash).collect();
+		let err = format!("{:?}", err);
+		for (index, transaction) in package.into_iter().enumerate() {
+			if memory_pool.read().contains(&transaction.hash) {
+				continue;
+			}
+
+			let spends_package_transaction = transaction
+				.raw
+				.inputs
+				.iter()
+				.any(|input| package_hashes.contains(&input.previous_output.hash));
+			if index < valid_transactions && !spends_package_transaction {
+				sink.on_transaction_verification_success(transaction);
+			} else {
+				sink.on_transaction_verification_error(&err, &transaction.hash);
+			}
+		}
+	}
 }
 
 impl Drop for AsyncVerifier {
@@ -239,6 +316,14 @@ impl Verifier for AsyncVerifier {
 			.send(VerificationTask::VerifyTransaction(height, transaction))
 			.expect("Verification thread have the same lifetime as `AsyncVerifier`");
 	}
+
+	/// Verify package of transactions
+	fn verify_package(&self, height: BlockHeight, package: Vec<IndexedTransaction>) {
+		self.verification_work_sender
+			.lock()
+			.send(VerificationTask::VerifyPackage(height, package))
+			.expect("Verification thread have the same lifetime as `AsyncVerifier`");
+	}
 }
 
 /// Synchronous synchronization verifier
@@ -282,6 +367,15 @@ where
 	fn verify_transaction(&self, _height: BlockHeight, _transaction: IndexedTransaction) {
 		unimplemented!() // sync verifier is currently only used for blocks verification
 	}
+
+	/// Verify package of transactions
+	fn verify_package(&self, _height: BlockHeight, package: Vec<IndexedTransaction>) {
+		// sync verifier is currently only used for blocks verification => there's no memory pool to accept the package to
+		for transaction in &package {
+			self.sink
+				.on_transaction_verification_error("Packages are not supported by the sync verifier", &transaction.hash);
+		}
+	}
 }
 
 #[cfg(test)]
@@ -393,6 +487,38 @@ pub mod tests {
 				None => panic!("call set_sink"),
 			}
 		}
+
+		fn verify_package(&self, _height: BlockHeight, package: Vec<IndexedTransaction>) {
+			let child_hash = package.last().expect("packages are never empty").hash;
+			match self.sink {
+				Some(ref sink) => match self.errors.get(&child_hash) {
+					Some(err) => {
+						// parents are valid on their own
+						for transaction in package {
+							match transaction.hash == child_hash {
+								true => sink.on_transaction_verification_error(&err, &child_hash),
+								false => sink.on_transaction_verification_success(transaction),
+							}
+						}
+					}
+					None => {
+						if self.actual_checks.contains(&child_hash) {
+							let next_block_height = self.storage.as_ref().unwrap().best_block().number + 1;
+							AsyncVerifier::execute_single_task(
+								sink,
+								self.storage.as_ref().unwrap(),
+								self.memory_pool.as_ref().unwrap(),
+								self.verifier.as_ref().unwrap(),
+								VerificationTask::VerifyPackage(next_block_height, package),
+							);
+						} else {
+							sink.on_package_verification_success(package);
+						}
+					}
+				},
+				None => panic!("call set_sink"),
+			}
+		}
 	}
 
 	#[test]
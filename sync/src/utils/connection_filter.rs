@@ -34,8 +34,10 @@ impl ConnectionFilter {
 	}
 
 	/// Check if transaction should be sent to this connection && optionally update filter
+	/// Peer may know the transaction either by its hash, or by its witness hash.
 	pub fn filter_transaction(&self, transaction: &IndexedTransaction, transaction_fee_rate: Option<u64>) -> bool {
 		self.known_hash_filter.filter_transaction(&transaction.hash)
+			&& (!transaction.raw.has_witness() || self.known_hash_filter.filter_transaction(&transaction.raw.witness_hash()))
 			&& self.fee_rate_filter.filter_transaction(transaction_fee_rate)
 			&& self.bloom_filter.filter_transaction(transaction)
 	}
@@ -171,6 +173,16 @@ pub mod tests {
 		assert!(filter.filter_transaction(&test_data::block_h2().transactions[0].clone().into(), None));
 	}
 
+	#[test]
+	fn filter_rejects_transaction_known_by_witness_hash() {
+		let mut filter = ConnectionFilter::default();
+		let mut tx: IndexedTransaction = test_data::block_h1().transactions[0].clone().into();
+		tx.raw.inputs[0].script_witness = vec![vec![0; 32].into()];
+		filter.hash_known_as(tx.raw.witness_hash(), KnownHashType::Transaction);
+		assert!(!filter.filter_transaction(&tx, None));
+		assert!(filter.filter_transaction(&test_data::block_h2().transactions[0].clone().into(), None));
+	}
+
 	#[test]
 	fn filter_rejects_transaction_feerate() {
 		let mut filter = ConnectionFilter::default();
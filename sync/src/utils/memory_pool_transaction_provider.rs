@@ -1,20 +1,20 @@
 use super::super::types::{MemoryPoolRef, StorageRef};
-use chain::{OutPoint, Transaction, TransactionOutput};
-use miner::{DoubleSpendCheckResult, HashedOutPoint, NonFinalDoubleSpendSet};
+use chain::{IndexedTransaction, OutPoint, Transaction, TransactionOutput};
+use miner::{transaction_fee, DoubleSpendCheckResult, HashedOutPoint, ReplaceableDoubleSpendSet};
 use std::collections::HashMap;
 use storage::TransactionOutputProvider;
 use verification::TransactionError;
 
 /// Transaction output observer, which looks into both storage && into memory pool.
-/// It also allows to replace non-final transactions in the memory pool.
+/// It also allows to replace replaceable (BIP125) transactions in the memory pool.
 pub struct MemoryPoolTransactionOutputProvider {
 	/// Storage provider
 	storage_provider: StorageRef,
 	/// Transaction inputs from memory pool transactions
 	mempool_inputs: HashMap<HashedOutPoint, Option<TransactionOutput>>,
 	/// Previous outputs, for which we should return 'Not spent' value.
-	/// These are used when new version of transaction is received.
-	nonfinal_spends: Option<NonFinalDoubleSpendSet>,
+	/// These are used when replacement transaction is received.
+	replaceable_spends: Option<ReplaceableDoubleSpendSet>,
 }
 
 impl MemoryPoolTransactionOutputProvider {
@@ -23,40 +23,79 @@ impl MemoryPoolTransactionOutputProvider {
 		// we have to check if there are another in-mempool transactions which spent same outputs here
 		let memory_pool = memory_pool.read();
 		let check_result = memory_pool.check_double_spend(transaction);
-		match check_result {
-			// input of transaction is already spent by another final transaction from memory pool
-			DoubleSpendCheckResult::DoubleSpend(_, hash, index) => Err(TransactionError::UsingSpentOutput(hash, index)),
+		let replaceable_spends = match check_result {
+			// input of transaction is already spent by another non-replaceable transaction from memory pool
+			DoubleSpendCheckResult::DoubleSpend(_, hash, index) => return Err(TransactionError::UsingSpentOutput(hash, index)),
 			// there are no transactions, which are spending same inputs in memory pool
-			DoubleSpendCheckResult::NoDoubleSpend => Ok(MemoryPoolTransactionOutputProvider {
-				storage_provider: storage,
-				mempool_inputs: transaction
-					.inputs
-					.iter()
-					.map(|input| {
-						(
-							input.previous_output.clone().into(),
-							memory_pool.transaction_output(&input.previous_output, usize::max_value()),
-						)
-					})
-					.collect(),
-				nonfinal_spends: None,
-			}),
-			// there are non-final transactions, which are spending same inputs in memory pool
-			DoubleSpendCheckResult::NonFinalDoubleSpend(nonfinal_spends) => Ok(MemoryPoolTransactionOutputProvider {
-				storage_provider: storage,
-				mempool_inputs: transaction
-					.inputs
-					.iter()
-					.map(|input| {
-						(
-							input.previous_output.clone().into(),
-							memory_pool.transaction_output(&input.previous_output, usize::max_value()),
-						)
-					})
-					.collect(),
-				nonfinal_spends: Some(nonfinal_spends),
-			}),
+			DoubleSpendCheckResult::NoDoubleSpend => None,
+			// there are replaceable transactions, which are spending same inputs in memory pool
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(replaceable_spends) => Some(replaceable_spends),
+		};
+
+		let provider = MemoryPoolTransactionOutputProvider {
+			storage_provider: storage,
+			mempool_inputs: transaction
+				.inputs
+				.iter()
+				.map(|input| {
+					(
+						input.previous_output.clone().into(),
+						memory_pool.transaction_output(&input.previous_output, usize::max_value()),
+					)
+				})
+				.collect(),
+			replaceable_spends,
+		};
+
+		match provider.replaceable_spends {
+			// replacement transaction must also pay enough to evict replaced transactions
+			// and must not exceed package limits once they are evicted
+			Some(ref replaceable_spends) => {
+				let fee = transaction_fee(&provider, transaction);
+				memory_pool.check_replacement(transaction, fee, replaceable_spends)?;
+			}
+			None => memory_pool.check_package_limits(transaction)?,
 		}
+
+		Ok(provider)
+	}
+
+	/// Create new provider for verifying given transaction of the package.
+	/// Outputs of preceding package transactions are treated as memory pool transactions outputs.
+	/// Package transactions are not allowed to replace memory pool transactions.
+	pub fn for_package_transaction(
+		storage: StorageRef,
+		memory_pool: &MemoryPoolRef,
+		package: &[IndexedTransaction],
+		transaction: &Transaction,
+	) -> Result<Self, TransactionError> {
+		let memory_pool = memory_pool.read();
+		if let Some(input) = transaction.inputs.iter().find(|input| memory_pool.is_spent(&input.previous_output)) {
+			return Err(TransactionError::UsingSpentOutput(
+				input.previous_output.hash,
+				input.previous_output.index,
+			));
+		}
+
+		// package limits are checked when package is inserted to the memory pool
+		Ok(MemoryPoolTransactionOutputProvider {
+			storage_provider: storage,
+			mempool_inputs: transaction
+				.inputs
+				.iter()
+				.map(|input| {
+					let prevout = &input.previous_output;
+					let output = memory_pool.transaction_output(prevout, usize::MAX).or_else(|| {
+						package
+							.iter()
+							.find(|tx| tx.hash == prevout.hash)
+							.and_then(|tx| tx.raw.outputs.get(prevout.index as usize).cloned())
+					});
+					(prevout.clone().into(), output)
+				})
+				.collect(),
+			replaceable_spends: None,
+		})
 	}
 }
 
@@ -65,9 +104,9 @@ impl TransactionOutputProvider for MemoryPoolTransactionOutputProvider {
 		let hashed_prevout: HashedOutPoint = prevout.clone().into();
 
 		// check if that is output of some transaction, which is vitually removed from memory pool
-		if let Some(ref nonfinal_spends) = self.nonfinal_spends {
-			if nonfinal_spends.dependent_spends.contains(&hashed_prevout) {
-				// transaction is trying to replace some nonfinal transaction
+		if let Some(ref replaceable_spends) = self.replaceable_spends {
+			if replaceable_spends.dependent_spends.contains(&hashed_prevout) {
+				// transaction is trying to replace some replaceable transaction
 				// + it is also depends on this transaction
 				// => this is definitely an error
 				return None;
@@ -86,9 +125,9 @@ impl TransactionOutputProvider for MemoryPoolTransactionOutputProvider {
 	}
 
 	fn is_spent(&self, prevout: &OutPoint) -> bool {
-		// check if this output is spent by some non-final mempool transaction
-		if let Some(ref nonfinal_spends) = self.nonfinal_spends {
-			if nonfinal_spends.double_spends.contains(&prevout.clone().into()) {
+		// check if this output is spent by some replaceable mempool transaction
+		if let Some(ref replaceable_spends) = self.replaceable_spends {
+			if replaceable_spends.double_spends.contains(&prevout.clone().into()) {
 				return false;
 			}
 		}
@@ -106,22 +145,23 @@ mod tests {
 	use super::MemoryPoolTransactionOutputProvider;
 	use chain::OutPoint;
 	use db::BlockChainDatabase;
-	use miner::{MemoryPool, NonZeroFeeCalculator};
+	use miner::{MemoryPool, MemoryPoolPackageLimits, NonZeroFeeCalculator};
 	use parking_lot::RwLock;
 	use std::sync::Arc;
 	use storage::TransactionOutputProvider;
+	use verification::TransactionError;
 
 	#[test]
-	fn when_transaction_depends_on_removed_nonfinal_transaction() {
+	fn when_transaction_depends_on_removed_replaceable_transaction() {
 		let dchain = &mut test_data::ChainBuilder::new();
 
-		test_data::TransactionBuilder::with_output(10)
+		test_data::TransactionBuilder::with_output(1_000_000_000)
 			.store(dchain) // t0
 			.reset()
 			.set_input(&dchain.at(0), 0)
 			.add_output(20)
 			.lock()
-			.store(dchain) // nonfinal: t0[0] -> t1
+			.store(dchain) // replaceable: t0[0] -> t1
 			.reset()
 			.set_input(&dchain.at(1), 0)
 			.add_output(30)
@@ -134,13 +174,22 @@ mod tests {
 		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
 		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
 		{
-			memory_pool.write().insert_verified(dchain.at(0).into(), &NonZeroFeeCalculator);
-			memory_pool.write().insert_verified(dchain.at(1).into(), &NonZeroFeeCalculator);
-			memory_pool.write().insert_verified(dchain.at(2).into(), &NonZeroFeeCalculator);
+			memory_pool
+				.write()
+				.insert_verified(dchain.at(0).into(), &NonZeroFeeCalculator)
+				.unwrap();
+			memory_pool
+				.write()
+				.insert_verified(dchain.at(1).into(), &NonZeroFeeCalculator)
+				.unwrap();
+			memory_pool
+				.write()
+				.insert_verified(dchain.at(2).into(), &NonZeroFeeCalculator)
+				.unwrap();
 		}
 
 		// when inserting t3:
-		// check that is_spent(t0[0]) == Some(false) (as it is spent by nonfinal t1)
+		// check that is_spent(t0[0]) == Some(false) (as it is spent by replaceable t1)
 		// check that is_spent(t1[0]) == None (as t1 is virtually removed)
 		// check that is_spent(t2[0]) == None (as t2 is virtually removed)
 		// check that previous_transaction_output(t0[0]) = Some(_)
@@ -201,4 +250,126 @@ mod tests {
 			None
 		);
 	}
+
+	#[test]
+	fn when_replacement_transaction_pays_insufficient_fee() {
+		let dchain = &mut test_data::ChainBuilder::new();
+
+		test_data::TransactionBuilder::with_output(10)
+			.store(dchain) // t0
+			.reset()
+			.set_input(&dchain.at(0), 0)
+			.add_output(5)
+			.lock()
+			.store(dchain) // replaceable: t0[0] -> t1
+			.reset()
+			.set_input(&dchain.at(0), 0)
+			.add_output(4)
+			.store(dchain); // replacement, paying less than t1: t0[0] -> t2
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		{
+			memory_pool
+				.write()
+				.insert_verified(dchain.at(0).into(), &NonZeroFeeCalculator)
+				.unwrap();
+			memory_pool
+				.write()
+				.insert_verified(dchain.at(1).into(), &NonZeroFeeCalculator)
+				.unwrap();
+		}
+
+		assert_eq!(
+			MemoryPoolTransactionOutputProvider::for_transaction(storage, &memory_pool, &dchain.at(2)).err(),
+			Some(TransactionError::ReplacementFeeRate(dchain.at(1).hash()))
+		);
+	}
+
+	#[test]
+	fn when_replacement_transaction_exceeds_package_limits() {
+		let dchain = &mut test_data::ChainBuilder::new();
+
+		test_data::TransactionBuilder::with_output(1_000_000_000)
+			.store(dchain) // t0
+			.reset()
+			.set_input(&dchain.at(0), 0)
+			.add_output(20)
+			.lock()
+			.store(dchain) // replaceable: t0[0] -> t1
+			.reset()
+			.set_input(&dchain.at(0), 0)
+			.add_output(40)
+			.store(dchain); // replacement: t0[0] -> t2
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		{
+			let mut memory_pool = memory_pool.write();
+			memory_pool.insert_verified(dchain.at(0).into(), &NonZeroFeeCalculator).unwrap();
+			memory_pool.insert_verified(dchain.at(1).into(), &NonZeroFeeCalculator).unwrap();
+			memory_pool.set_package_limits(MemoryPoolPackageLimits {
+				ancestors_count: 1,
+				..Default::default()
+			});
+		}
+
+		assert_eq!(
+			MemoryPoolTransactionOutputProvider::for_transaction(storage, &memory_pool, &dchain.at(2)).err(),
+			Some(TransactionError::TooManyAncestors(1))
+		);
+	}
+
+	#[test]
+	fn when_package_transaction_spends_preceding_package_transaction() {
+		let dchain = &mut test_data::ChainBuilder::new();
+
+		test_data::TransactionBuilder::with_output(1_000)
+			.store(dchain) // t0
+			.reset()
+			.set_input(&dchain.at(0), 0)
+			.add_output(20)
+			.store(dchain) // t0[0] -> t1
+			.reset()
+			.set_input(&dchain.at(1), 0)
+			.add_output(10)
+			.store(dchain) // t0[0] -> t1[0] -> t2
+			.reset()
+			.set_input(&dchain.at(0), 0)
+			.add_output(30)
+			.store(dchain); // conflicting: t0[0] -> t3
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		memory_pool
+			.write()
+			.insert_verified(dchain.at(0).into(), &NonZeroFeeCalculator)
+			.unwrap();
+
+		// t1 output is visible to t2, verified as a part of t1 + t2 package
+		let package = vec![dchain.at(1).into(), dchain.at(2).into()];
+		let provider =
+			MemoryPoolTransactionOutputProvider::for_package_transaction(storage.clone(), &memory_pool, &package[..1], &dchain.at(2))
+				.unwrap();
+		assert_eq!(
+			provider.transaction_output(
+				&OutPoint {
+					hash: dchain.at(1).hash(),
+					index: 0,
+				},
+				0
+			),
+			Some(dchain.at(1).outputs[0].clone())
+		);
+
+		// package transactions are not allowed to replace memory pool transactions
+		memory_pool
+			.write()
+			.insert_verified(dchain.at(1).into(), &NonZeroFeeCalculator)
+			.unwrap();
+		assert_eq!(
+			MemoryPoolTransactionOutputProvider::for_package_transaction(storage, &memory_pool, &[], &dchain.at(3)).err(),
+			Some(TransactionError::UsingSpentOutput(dchain.at(0).hash(), 0))
+		);
+	}
 }
@@ -0,0 +1,365 @@
+//! Set sketches over GF(2^32), compatible with the minisketch library, which is used by BIP330.
+//! Sketch of capacity `c` holds the odd power sums of the elements. Sketches of two sets are combined with xor,
+//! after which up to `c` elements of the symmetric difference of these sets could be recovered.
+
+use byteorder::{ByteOrder, LittleEndian};
+use rand::random;
+
+/// Irreducible polynomial, defining the field: x^32 + x^7 + x^3 + x^2 + 1
+const FIELD_MODULUS: u64 = (1 << 32) | 0x8d;
+/// Number of random trace polynomials to try before giving up on the polynomial split
+const MAX_SPLIT_ATTEMPTS: usize = 64;
+
+/// Sketch of the set of non-zero 32-bit elements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sketch {
+	/// Odd power sums of the elements: s1, s3, ..., s(2c-1)
+	syndromes: Vec<u32>,
+}
+
+impl Sketch {
+	/// Create empty sketch of given capacity
+	pub fn new(capacity: usize) -> Self {
+		Sketch {
+			syndromes: vec![0; capacity],
+		}
+	}
+
+	/// Read serialized sketch
+	pub fn deserialize(data: &[u8]) -> Option<Self> {
+		if !data.len().is_multiple_of(4) {
+			return None;
+		}
+
+		Some(Sketch {
+			syndromes: data.chunks(4).map(LittleEndian::read_u32).collect(),
+		})
+	}
+
+	/// Serialize sketch
+	pub fn serialize(&self) -> Vec<u8> {
+		let mut data = vec![0; self.syndromes.len() * 4];
+		LittleEndian::write_u32_into(&self.syndromes, &mut data);
+		data
+	}
+
+	/// Max number of elements, which could be recovered from this sketch
+	pub fn capacity(&self) -> usize {
+		self.syndromes.len()
+	}
+
+	/// Add element to the sketch. Adding the same element twice removes it from the sketch.
+	pub fn add(&mut self, element: u32) {
+		debug_assert!(element != 0);
+		let square = field_mul(element, element);
+		let mut power = element;
+		for syndrome in &mut self.syndromes {
+			*syndrome ^= power;
+			power = field_mul(power, square);
+		}
+	}
+
+	/// Combine with other sketch. Result is the sketch of the symmetric difference of the sets.
+	pub fn merge(&mut self, other: &Sketch) {
+		self.syndromes.truncate(other.syndromes.len());
+		for (syndrome, other) in self.syndromes.iter_mut().zip(other.syndromes.iter()) {
+			*syndrome ^= *other;
+		}
+	}
+
+	/// Recover elements of the sketch. Fails if sketch contains more elements than its capacity.
+	pub fn decode(&self) -> Option<Vec<u32>> {
+		// even power sums are squares of the lesser ones: s(2k) = s(k)^2
+		let mut sums = vec![0; self.syndromes.len() * 2];
+		for (index, syndrome) in self.syndromes.iter().enumerate() {
+			sums[index * 2] = *syndrome;
+		}
+		for index in (1..sums.len()).step_by(2) {
+			let half = sums[index / 2];
+			sums[index] = field_mul(half, half);
+		}
+
+		// error locator polynomial has inverses of the elements as its roots
+		let locator = berlekamp_massey(&sums);
+		let degree = locator.len() - 1;
+		if degree == 0 {
+			return Some(Vec::new());
+		}
+		if degree > self.syndromes.len() || locator[degree] == 0 {
+			return None;
+		}
+
+		// reversed locator polynomial has elements themselves as its roots
+		let polynomial: Vec<u32> = locator.into_iter().rev().collect();
+		let roots = find_roots(&polynomial)?;
+		if roots.len() != degree {
+			return None;
+		}
+		Some(roots)
+	}
+}
+
+/// Multiplication in GF(2^32)
+fn field_mul(a: u32, b: u32) -> u32 {
+	let mut product = 0u64;
+	for bit in 0..32 {
+		if (b >> bit) & 1 == 1 {
+			product ^= (a as u64) << bit;
+		}
+	}
+	for bit in (32..64).rev() {
+		if (product >> bit) & 1 == 1 {
+			product ^= FIELD_MODULUS << (bit - 32);
+		}
+	}
+	product as u32
+}
+
+/// Multiplicative inverse in GF(2^32): a^(2^32 - 2)
+fn field_inv(a: u32) -> u32 {
+	debug_assert!(a != 0);
+	let mut result = 1;
+	for bit in (0..32).rev() {
+		result = field_mul(result, result);
+		if bit != 0 {
+			result = field_mul(result, a);
+		}
+	}
+	result
+}
+
+/// Finds the shortest linear recurrence, generating given sequence. Returns its connection polynomial.
+fn berlekamp_massey(sequence: &[u32]) -> Vec<u32> {
+	let mut current = vec![1];
+	let mut previous = vec![1];
+	let mut length = 0;
+	let mut shift = 1;
+	let mut previous_discrepancy = 1;
+	for n in 0..sequence.len() {
+		let discrepancy = current
+			.iter()
+			.enumerate()
+			.skip(1)
+			.take(length)
+			.fold(sequence[n], |acc, (i, coefficient)| acc ^ field_mul(*coefficient, sequence[n - i]));
+		if discrepancy == 0 {
+			shift += 1;
+			continue;
+		}
+
+		let coefficient = field_mul(discrepancy, field_inv(previous_discrepancy));
+		let mut next = current.clone();
+		if next.len() < previous.len() + shift {
+			next.resize(previous.len() + shift, 0);
+		}
+		for (i, value) in previous.iter().enumerate() {
+			next[i + shift] ^= field_mul(coefficient, *value);
+		}
+
+		if 2 * length <= n {
+			length = n + 1 - length;
+			previous = current;
+			previous_discrepancy = discrepancy;
+			shift = 1;
+		} else {
+			shift += 1;
+		}
+		current = next;
+	}
+
+	current.resize(length + 1, 0);
+	current
+}
+
+/// Finds roots of the monic polynomial, if it has only distinct roots in the field.
+fn find_roots(polynomial: &[u32]) -> Option<Vec<u32>> {
+	// polynomial splits into distinct linear factors iff it divides x^(2^32) - x
+	let x = poly_mod(vec![0, 1], polynomial);
+	let mut power = x.clone();
+	for _ in 0..32 {
+		power = poly_mul_mod(&power, &power, polynomial);
+	}
+	if power != x {
+		return None;
+	}
+
+	let mut roots = Vec::with_capacity(polynomial.len() - 1);
+	if split(polynomial.to_vec(), &mut roots) {
+		Some(roots)
+	} else {
+		None
+	}
+}
+
+/// Recursively splits the polynomial with distinct roots using the random trace polynomials.
+fn split(polynomial: Vec<u32>, roots: &mut Vec<u32>) -> bool {
+	match polynomial.len() {
+		0 | 1 => return true,
+		2 => {
+			roots.push(polynomial[0]);
+			return true;
+		}
+		_ => (),
+	}
+
+	for _ in 0..MAX_SPLIT_ATTEMPTS {
+		// Tr(beta * x) is either 0 or 1 for every root => gcd with it separates the roots
+		let mut term = poly_mod(vec![0, random::<u32>()], &polynomial);
+		let mut trace = term.clone();
+		for _ in 1..32 {
+			term = poly_mul_mod(&term, &term, &polynomial);
+			poly_add_assign(&mut trace, &term);
+		}
+
+		let factor = poly_gcd(polynomial.clone(), trace);
+		if factor.len() > 1 && factor.len() < polynomial.len() {
+			let quotient = poly_div(&polynomial, &factor);
+			return split(factor, roots) && split(quotient, roots);
+		}
+	}
+
+	false
+}
+
+/// Removes leading zero coefficients
+fn poly_trim(polynomial: &mut Vec<u32>) {
+	while polynomial.last() == Some(&0) {
+		polynomial.pop();
+	}
+}
+
+fn poly_add_assign(a: &mut Vec<u32>, b: &[u32]) {
+	if a.len() < b.len() {
+		a.resize(b.len(), 0);
+	}
+	for (a, b) in a.iter_mut().zip(b.iter()) {
+		*a ^= *b;
+	}
+	poly_trim(a);
+}
+
+fn poly_monic(mut polynomial: Vec<u32>) -> Vec<u32> {
+	poly_trim(&mut polynomial);
+	if let Some(&leading) = polynomial.last() {
+		let inverse = field_inv(leading);
+		for coefficient in &mut polynomial {
+			*coefficient = field_mul(*coefficient, inverse);
+		}
+	}
+	polynomial
+}
+
+/// Remainder of the division by the monic polynomial
+fn poly_mod(mut a: Vec<u32>, modulus: &[u32]) -> Vec<u32> {
+	let degree = modulus.len() - 1;
+	while a.len() > degree {
+		let leading = a.pop().expect("a.len() > degree >= 0; qed");
+		if leading != 0 {
+			let offset = a.len() - degree;
+			for (i, coefficient) in modulus.iter().take(degree).enumerate() {
+				a[offset + i] ^= field_mul(leading, *coefficient);
+			}
+		}
+	}
+	poly_trim(&mut a);
+	a
+}
+
+/// Quotient of the division by the monic polynomial
+fn poly_div(a: &[u32], divisor: &[u32]) -> Vec<u32> {
+	let degree = divisor.len() - 1;
+	let mut remainder = a.to_vec();
+	let mut quotient = vec![0; a.len() - degree];
+	while remainder.len() > degree {
+		let leading = remainder.pop().expect("remainder.len() > degree >= 0; qed");
+		let offset = remainder.len() - degree;
+		quotient[offset] = leading;
+		if leading != 0 {
+			for (i, coefficient) in divisor.iter().take(degree).enumerate() {
+				remainder[offset + i] ^= field_mul(leading, *coefficient);
+			}
+		}
+	}
+	quotient
+}
+
+fn poly_mul_mod(a: &[u32], b: &[u32], modulus: &[u32]) -> Vec<u32> {
+	if a.is_empty() || b.is_empty() {
+		return Vec::new();
+	}
+
+	let mut product = vec![0; a.len() + b.len() - 1];
+	for (i, a) in a.iter().enumerate().filter(|(_, a)| **a != 0) {
+		for (j, b) in b.iter().enumerate() {
+			product[i + j] ^= field_mul(*a, *b);
+		}
+	}
+	poly_mod(product, modulus)
+}
+
+/// Monic greatest common divisor of two polynomials
+fn poly_gcd(mut a: Vec<u32>, mut b: Vec<u32>) -> Vec<u32> {
+	poly_trim(&mut b);
+	while !b.is_empty() {
+		let divisor = poly_monic(b);
+		let remainder = poly_mod(a, &divisor);
+		a = divisor;
+		b = remainder;
+	}
+	poly_monic(a)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{field_inv, field_mul, Sketch};
+
+	fn sketch(capacity: usize, elements: &[u32]) -> Sketch {
+		let mut sketch = Sketch::new(capacity);
+		for element in elements {
+			sketch.add(*element);
+		}
+		sketch
+	}
+
+	#[test]
+	fn field_inverse() {
+		for element in &[1, 2, 0x8d, 0xdeadbeef, 0xffffffff] {
+			assert_eq!(field_mul(*element, field_inv(*element)), 1);
+		}
+	}
+
+	#[test]
+	fn sketch_serialization() {
+		let sketch = sketch(4, &[1, 2, 3]);
+		let data = sketch.serialize();
+		assert_eq!(data.len(), 16);
+		assert_eq!(&data[0..4], &[0, 0, 0, 0]);
+		assert_eq!(Sketch::deserialize(&data), Some(sketch));
+		assert_eq!(Sketch::deserialize(&data[1..]), None);
+	}
+
+	#[test]
+	fn sketch_decodes_symmetric_difference() {
+		let mut local = sketch(8, &[10, 20, 30, 0xabcdef01, 0x12345678]);
+		let remote = sketch(8, &[20, 30, 40, 0xfffffffe]);
+		local.merge(&remote);
+
+		let mut difference = local.decode().unwrap();
+		difference.sort();
+		assert_eq!(difference, vec![10, 40, 0x12345678, 0xabcdef01, 0xfffffffe]);
+
+		let mut same = sketch(8, &[5, 6]);
+		same.merge(&sketch(8, &[6, 5]));
+		assert_eq!(same.decode(), Some(vec![]));
+	}
+
+	#[test]
+	fn sketch_fails_to_decode_over_capacity() {
+		let elements: Vec<u32> = (1..=20).map(|i| i * 0x01010101).collect();
+		assert_eq!(sketch(4, &elements).decode(), None);
+
+		let mut decoded = sketch(20, &elements).decode().unwrap();
+		decoded.sort();
+		assert_eq!(decoded, elements);
+	}
+}
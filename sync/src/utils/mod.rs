@@ -8,9 +8,12 @@ mod hash_queue;
 mod known_hash_filter;
 mod memory_pool_transaction_provider;
 mod message_block_headers_provider;
+mod minisketch;
 mod orphan_blocks_pool;
 mod orphan_transactions_pool;
+mod partial_compact_block;
 mod partial_merkle_tree;
+mod reconciliation;
 mod synchronization_state;
 
 pub use self::average_speed_meter::AverageSpeedMeter;
@@ -25,7 +28,9 @@ pub use self::memory_pool_transaction_provider::MemoryPoolTransactionOutputProvi
 pub use self::message_block_headers_provider::MessageBlockHeadersProvider;
 pub use self::orphan_blocks_pool::OrphanBlocksPool;
 pub use self::orphan_transactions_pool::{OrphanTransaction, OrphanTransactionsPool};
+pub use self::partial_compact_block::{CompactBlockReconstruction, PartialCompactBlock};
 pub use self::partial_merkle_tree::{build_partial_merkle_tree, PartialMerkleTree};
+pub use self::reconciliation::{Reconciliation, ReconciliationResult};
 pub use self::synchronization_state::SynchronizationState;
 
 /// Block height type
@@ -102,6 +102,22 @@ impl OrphanTransactionsPool {
 		removed_orphans
 	}
 
+	/// Remove all transactions, depending on this parent, grouped into packages.
+	/// Direct children of the parent are packaged together with the parent, so that
+	/// the child could pay for its parent if parent doesn't pay enough fee on its own.
+	pub fn remove_packages_for_parent(&mut self, parent: &IndexedTransaction) -> Vec<Vec<IndexedTransaction>> {
+		self.remove_transactions_for_parent(&parent.hash)
+			.into_iter()
+			.map(|transaction| {
+				if transaction.raw.inputs.iter().any(|input| input.previous_output.hash == parent.hash) {
+					vec![parent.clone(), transaction]
+				} else {
+					vec![transaction]
+				}
+			})
+			.collect()
+	}
+
 	/// Remove transactions with given hashes + all dependent blocks
 	pub fn remove_transactions(&mut self, hashes: &[SHA256D]) -> Vec<IndexedTransaction> {
 		let mut removed: Vec<IndexedTransaction> = Vec::new();
@@ -251,4 +267,34 @@ mod tests {
 
 		pool.remove_transactions(&[chain.at(2).hash(), chain.at(1).hash()]);
 	}
+
+	#[test]
+	fn orphan_transaction_pool_remove_packages_for_parent() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(100)
+			.store(chain) // t1
+			.into_input(0)
+			.add_output(200)
+			.store(chain) // t1 -> t2
+			.into_input(0)
+			.add_output(300)
+			.store(chain); // t1 -> t2 -> t3
+		let t2_unknown: HashSet<SHA256D> = chain.at(1).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
+		let t3_unknown: HashSet<SHA256D> = chain.at(2).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
+
+		let mut pool = OrphanTransactionsPool::new();
+		pool.insert(chain.at(1).into(), t2_unknown); // t2
+		pool.insert(chain.at(2).into(), t3_unknown); // t3
+
+		let packages: Vec<Vec<SHA256D>> = pool
+			.remove_packages_for_parent(&chain.at(0).into())
+			.into_iter()
+			.map(|package| package.into_iter().map(|tx| tx.hash).collect())
+			.collect();
+		assert_eq!(pool.len(), 0);
+		assert_eq!(
+			packages,
+			vec![vec![chain.at(0).hash(), chain.at(1).hash()], vec![chain.at(2).hash()]]
+		);
+	}
 }
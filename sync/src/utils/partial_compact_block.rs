@@ -0,0 +1,250 @@
+use super::compact_block_builder::{short_transaction_id, short_transaction_id_keys};
+use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction, ShortTransactionID, Transaction};
+use message::common::BlockHeaderAndIDs;
+use miner::MemoryPool;
+use std::collections::HashMap;
+
+/// Maximal number of transactions in compact block (max block weight / min transaction weight)
+const MAX_COMPACT_BLOCK_TRANSACTIONS: usize = 4_000_000 / 40;
+
+/// Result of compact block reconstruction.
+#[derive(Debug)]
+pub enum CompactBlockReconstruction {
+	/// All block transactions are known => block is reconstructed
+	Complete(IndexedBlock),
+	/// Some transactions are missing and must be requested using `getblocktxn`
+	Incomplete(PartialCompactBlock),
+	/// Short ids collision or reconstructed block does not match header => full block must be requested
+	Collision,
+	/// Compact block is malformed
+	Invalid(&'static str),
+}
+
+/// Compact block with some transactions missing.
+#[derive(Debug)]
+pub struct PartialCompactBlock {
+	/// Block header
+	header: IndexedBlockHeader,
+	/// Block transactions. None if transaction is not yet known
+	transactions: Vec<Option<IndexedTransaction>>,
+}
+
+impl PartialCompactBlock {
+	/// Reconstruct block from compact block (version 2, wtxid-based short ids), using memory pool transactions.
+	pub fn reconstruct(compact_block: BlockHeaderAndIDs, memory_pool: &MemoryPool) -> CompactBlockReconstruction {
+		let transactions_len = compact_block.short_ids.len() + compact_block.prefilled_transactions.len();
+		if transactions_len == 0 || transactions_len > MAX_COMPACT_BLOCK_TRANSACTIONS {
+			return CompactBlockReconstruction::Invalid("invalid number of transactions in compact block");
+		}
+
+		// place prefilled transactions first
+		let mut transactions: Vec<Option<IndexedTransaction>> = vec![None; transactions_len];
+		for prefilled in compact_block.prefilled_transactions {
+			match transactions.get_mut(prefilled.index) {
+				Some(slot @ None) => *slot = Some(prefilled.transaction.into()),
+				_ => return CompactBlockReconstruction::Invalid("invalid prefilled transaction index in compact block"),
+			}
+		}
+
+		// remaining positions are occupied by transactions with given short ids (in order)
+		let mut short_ids_positions: HashMap<ShortTransactionID, usize> = HashMap::with_capacity(compact_block.short_ids.len());
+		let empty_positions = transactions
+			.iter()
+			.enumerate()
+			.filter(|&(_, tx)| tx.is_none())
+			.map(|(index, _)| index);
+		for (short_id, index) in compact_block.short_ids.into_iter().zip(empty_positions) {
+			if short_ids_positions.insert(short_id, index).is_some() {
+				return CompactBlockReconstruction::Collision;
+			}
+		}
+
+		// fill positions with memory pool transactions
+		let (key0, key1) = short_transaction_id_keys(compact_block.nonce, &compact_block.header);
+		let mut ambiguous_positions = Vec::new();
+		for hash in memory_pool.get_transactions_ids() {
+			let transaction = match memory_pool.read_by_hash(&hash) {
+				Some(transaction) => transaction,
+				None => continue,
+			};
+			let short_id = short_transaction_id(key0, key1, &transaction.witness_hash());
+			if let Some(&index) = short_ids_positions.get(&short_id) {
+				if transactions[index].is_some() {
+					// two memory pool transactions with the same short id => ask peer
+					ambiguous_positions.push(index);
+				} else {
+					transactions[index] = Some(IndexedTransaction::new(hash, transaction.clone()));
+				}
+			}
+		}
+		for index in ambiguous_positions {
+			transactions[index] = None;
+		}
+
+		let partial_block = PartialCompactBlock {
+			header: compact_block.header.into(),
+			transactions,
+		};
+		if partial_block.transactions.iter().any(Option::is_none) {
+			return CompactBlockReconstruction::Incomplete(partial_block);
+		}
+
+		partial_block.into_block(Vec::new())
+	}
+
+	/// Block header.
+	pub fn header(&self) -> &IndexedBlockHeader {
+		&self.header
+	}
+
+	/// Indexes of transactions that are missing.
+	pub fn missing_indexes(&self) -> Vec<usize> {
+		self.transactions
+			.iter()
+			.enumerate()
+			.filter(|&(_, tx)| tx.is_none())
+			.map(|(index, _)| index)
+			.collect()
+	}
+
+	/// Fill missing transactions (in order) and return reconstructed block.
+	/// Returns `Incomplete` if number of transactions does not match number of missing transactions.
+	pub fn into_block(mut self, missing_transactions: Vec<Transaction>) -> CompactBlockReconstruction {
+		let mut missing_transactions = missing_transactions.into_iter();
+		for slot in self.transactions.iter_mut().filter(|tx| tx.is_none()) {
+			match missing_transactions.next() {
+				Some(transaction) => *slot = Some(transaction.into()),
+				None => return CompactBlockReconstruction::Incomplete(self),
+			}
+		}
+		if missing_transactions.next().is_some() {
+			return CompactBlockReconstruction::Invalid("too many transactions in blocktxn");
+		}
+
+		let transactions = self
+			.transactions
+			.into_iter()
+			.map(|tx| tx.expect("all missing transactions are filled above; qed"))
+			.collect();
+		let block = IndexedBlock::new(self.header, transactions);
+		if block.merkle_root() != block.header.raw.merkle_root_hash {
+			return CompactBlockReconstruction::Collision;
+		}
+
+		CompactBlockReconstruction::Complete(block)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use super::super::compact_block_builder::{short_transaction_id, short_transaction_id_keys};
+	use super::{CompactBlockReconstruction, PartialCompactBlock};
+	use chain::{Block, IndexedBlock};
+	use message::common::{BlockHeaderAndIDs, PrefilledTransaction};
+	use miner::MemoryPool;
+
+	fn test_block() -> Block {
+		#[rustfmt::skip]
+		let block = test_data::block_builder()
+			.transaction().coinbase().output().value(10).build().build()
+			.transaction().output().value(20).build().build()
+			.transaction().output().value(30).build().build()
+			.merkled_header().parent(test_data::genesis().hash()).build()
+			.build();
+		block
+	}
+
+	fn compact_block(block: &Block) -> BlockHeaderAndIDs {
+		let nonce = 42;
+		let (key0, key1) = short_transaction_id_keys(nonce, &block.block_header);
+		BlockHeaderAndIDs {
+			header: block.block_header.clone(),
+			nonce,
+			short_ids: block.transactions[1..]
+				.iter()
+				.map(|tx| short_transaction_id(key0, key1, &tx.witness_hash()))
+				.collect(),
+			prefilled_transactions: vec![PrefilledTransaction {
+				index: 0,
+				transaction: block.transactions[0].clone(),
+			}],
+		}
+	}
+
+	#[test]
+	fn compact_block_is_reconstructed_from_memory_pool() {
+		let block = test_block();
+		let mut memory_pool = MemoryPool::new();
+		memory_pool
+			.insert_verified(block.transactions[1].clone().into(), &miner::NonZeroFeeCalculator)
+			.unwrap();
+		memory_pool
+			.insert_verified(block.transactions[2].clone().into(), &miner::NonZeroFeeCalculator)
+			.unwrap();
+
+		match PartialCompactBlock::reconstruct(compact_block(&block), &memory_pool) {
+			CompactBlockReconstruction::Complete(reconstructed) => assert_eq!(reconstructed, IndexedBlock::from(block)),
+			result => panic!("unexpected reconstruction result: {:?}", result),
+		}
+	}
+
+	#[test]
+	fn compact_block_is_completed_with_missing_transactions() {
+		let block = test_block();
+		let mut memory_pool = MemoryPool::new();
+		memory_pool
+			.insert_verified(block.transactions[2].clone().into(), &miner::NonZeroFeeCalculator)
+			.unwrap();
+
+		let partial_block = match PartialCompactBlock::reconstruct(compact_block(&block), &memory_pool) {
+			CompactBlockReconstruction::Incomplete(partial_block) => partial_block,
+			result => panic!("unexpected reconstruction result: {:?}", result),
+		};
+		assert_eq!(partial_block.missing_indexes(), vec![1]);
+
+		match partial_block.into_block(vec![block.transactions[1].clone()]) {
+			CompactBlockReconstruction::Complete(reconstructed) => assert_eq!(reconstructed, IndexedBlock::from(block)),
+			result => panic!("unexpected reconstruction result: {:?}", result),
+		}
+	}
+
+	#[test]
+	fn compact_block_with_duplicate_short_ids_is_collision() {
+		let block = test_block();
+		let mut compact_block = compact_block(&block);
+		compact_block.short_ids[1] = compact_block.short_ids[0];
+
+		match PartialCompactBlock::reconstruct(compact_block, &MemoryPool::new()) {
+			CompactBlockReconstruction::Collision => (),
+			result => panic!("unexpected reconstruction result: {:?}", result),
+		}
+	}
+
+	#[test]
+	fn compact_block_with_wrong_transactions_is_collision() {
+		let block = test_block();
+		let partial_block = match PartialCompactBlock::reconstruct(compact_block(&block), &MemoryPool::new()) {
+			CompactBlockReconstruction::Incomplete(partial_block) => partial_block,
+			result => panic!("unexpected reconstruction result: {:?}", result),
+		};
+
+		match partial_block.into_block(vec![block.transactions[2].clone(), block.transactions[1].clone()]) {
+			CompactBlockReconstruction::Collision => (),
+			result => panic!("unexpected reconstruction result: {:?}", result),
+		}
+	}
+
+	#[test]
+	fn compact_block_with_invalid_prefilled_index_is_invalid() {
+		let block = test_block();
+		let mut compact_block = compact_block(&block);
+		compact_block.prefilled_transactions[0].index = 3;
+
+		match PartialCompactBlock::reconstruct(compact_block, &MemoryPool::new()) {
+			CompactBlockReconstruction::Invalid(_) => (),
+			result => panic!("unexpected reconstruction result: {:?}", result),
+		}
+	}
+}
@@ -0,0 +1,332 @@
+use super::minisketch::Sketch;
+use bitcrypto::{siphash24, tagged_hash, SHA256D};
+use byteorder::{ByteOrder, LittleEndian};
+use message::types;
+use std::collections::HashMap;
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// Coefficient, used to estimate the set difference: 0.25 in `Q_PRECISION` units
+const RECON_Q: u16 = 8191;
+/// Precision of the `q` coefficient
+const Q_PRECISION: usize = 32767;
+/// Max capacity of the sketch. Larger sketches take too long to decode, so we fall back to the flooding instead
+const MAX_SKETCH_CAPACITY: usize = 128;
+/// Max number of transactions, waiting for the reconciliation. Other transactions are announced immediately
+const MAX_RECONCILIATION_SET_SIZE: usize = 3000;
+/// Time to wait for the peer response, before the reconciliation round is abandoned
+pub const RECONCILIATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Transactions reconciliation (BIP330) state of the single peer.
+#[derive(Debug)]
+pub struct Reconciliation {
+	/// Are we requesting reconciliations from this peer?
+	is_initiator: bool,
+	/// SipHash keys, used to compute short ids
+	keys: (u64, u64),
+	/// Transactions we are going to announce to the peer, by their short ids
+	set: HashMap<u32, SHA256D>,
+	/// Time when reconciliation has been requested, if we are waiting for the sketch
+	requested_at: Option<Instant>,
+	/// Set, which we have sent sketch of at given time, waiting for the reconciliation result
+	sketched_set: Option<(Instant, HashMap<u32, SHA256D>)>,
+}
+
+/// Result of the reconciliation round on the initiator side.
+#[derive(Debug, PartialEq)]
+pub struct ReconciliationResult {
+	/// Message for the peer
+	pub reconcildiff: types::ReconcilDiff,
+	/// Witness hashes of the transactions, which are missing from the peer set and should be announced
+	pub announce: Vec<SHA256D>,
+}
+
+impl Reconciliation {
+	pub fn new(is_initiator: bool, local_salt: u64, remote_salt: u64) -> Self {
+		Reconciliation {
+			is_initiator,
+			keys: short_id_keys(local_salt, remote_salt),
+			set: HashMap::new(),
+			requested_at: None,
+			sketched_set: None,
+		}
+	}
+
+	/// Are we requesting reconciliations from this peer?
+	pub fn is_initiator(&self) -> bool {
+		self.is_initiator
+	}
+
+	/// Short id of the transaction with given witness hash
+	pub fn short_id(&self, witness_hash: &SHA256D) -> u32 {
+		let hash = siphash24(self.keys.0, self.keys.1, witness_hash);
+		1 + (hash % 0xffff_ffff) as u32
+	}
+
+	/// Add transaction to the set. Returns false if the set is full and transaction should be announced right now.
+	pub fn add_transaction(&mut self, witness_hash: SHA256D) -> bool {
+		if self.set.len() >= MAX_RECONCILIATION_SET_SIZE {
+			return false;
+		}
+
+		let short_id = self.short_id(&witness_hash);
+		self.set.insert(short_id, witness_hash);
+		true
+	}
+
+	/// Initiator: start new reconciliation round, unless the previous one is still in progress.
+	pub fn request(&mut self) -> Option<types::ReqRecon> {
+		if !self.is_initiator || self.requested_at.is_some() {
+			return None;
+		}
+
+		self.requested_at = Some(Instant::now());
+		Some(types::ReqRecon {
+			set_size: self.set.len().min(u16::MAX as usize) as u16,
+			q: RECON_Q,
+		})
+	}
+
+	/// Responder: build sketch of our set. Returns None if peer should not have requested reconciliation.
+	pub fn sketch(&mut self, request: &types::ReqRecon) -> Option<types::Sketch> {
+		if self.is_initiator || self.sketched_set.is_some() {
+			return None;
+		}
+
+		let capacity = estimate_capacity(self.set.len(), request.set_size as usize, request.q as usize);
+		let mut sketch = Sketch::new(capacity);
+		for short_id in self.set.keys() {
+			sketch.add(*short_id);
+		}
+		self.sketched_set = Some((Instant::now(), mem::take(&mut self.set)));
+
+		Some(types::Sketch {
+			skdata: sketch.serialize().into(),
+		})
+	}
+
+	/// Initiator: find the difference between our set and the peer set, given its sketch.
+	/// Returns None if we have not requested reconciliation or the sketch is malformed.
+	pub fn reconcile(&mut self, sketch: &types::Sketch) -> Option<ReconciliationResult> {
+		if !self.is_initiator || self.requested_at.take().is_none() {
+			return None;
+		}
+
+		let remote_sketch = Sketch::deserialize(&sketch.skdata)?;
+		if remote_sketch.capacity() > MAX_SKETCH_CAPACITY {
+			return None;
+		}
+
+		let set = mem::take(&mut self.set);
+		let mut local_sketch = Sketch::new(remote_sketch.capacity());
+		for short_id in set.keys() {
+			local_sketch.add(*short_id);
+		}
+		local_sketch.merge(&remote_sketch);
+
+		// estimated capacity includes one spare element, so fully used sketch is most likely decoded incorrectly
+		let result = match local_sketch.decode() {
+			Some(ref difference) if difference.len() < remote_sketch.capacity() => {
+				let (announce, ask_shortids): (Vec<_>, Vec<_>) =
+					difference.iter().cloned().partition(|short_id| set.contains_key(short_id));
+				ReconciliationResult {
+					reconcildiff: types::ReconcilDiff {
+						success: true,
+						ask_shortids,
+					},
+					announce: announce.into_iter().map(|short_id| set[&short_id]).collect(),
+				}
+			}
+			// too many differences => both sides are announcing all their transactions
+			_ => ReconciliationResult {
+				reconcildiff: types::ReconcilDiff {
+					success: false,
+					ask_shortids: Vec::new(),
+				},
+				announce: set.into_values().collect(),
+			},
+		};
+
+		Some(result)
+	}
+
+	/// Responder: finish reconciliation round. Returns witness hashes of transactions, which should be announced to the peer,
+	/// or None if we have not sent sketch to the peer.
+	pub fn finish(&mut self, reconcildiff: &types::ReconcilDiff) -> Option<Vec<SHA256D>> {
+		let (_, set) = self.sketched_set.take()?;
+		if !reconcildiff.success {
+			return Some(set.into_values().collect());
+		}
+
+		Some(
+			reconcildiff
+				.ask_shortids
+				.iter()
+				.filter_map(|short_id| set.get(short_id).cloned())
+				.collect(),
+		)
+	}
+
+	/// Abandon reconciliation round, if peer has not responded in time. Returns witness hashes of
+	/// the sketched transactions, which should be announced to the peer
+	pub fn expire(&mut self, now: Instant) -> Vec<SHA256D> {
+		let is_expired = |timestamp: &Instant| now.duration_since(*timestamp) >= RECONCILIATION_TIMEOUT;
+		if self.requested_at.as_ref().is_some_and(is_expired) {
+			self.requested_at = None;
+		}
+		match self.sketched_set.take() {
+			Some((timestamp, set)) if is_expired(&timestamp) => set.into_values().collect(),
+			sketched_set => {
+				self.sketched_set = sketched_set;
+				Vec::new()
+			}
+		}
+	}
+}
+
+/// SipHash keys are derived from the salts of both peers, sorted in ascending order
+fn short_id_keys(local_salt: u64, remote_salt: u64) -> (u64, u64) {
+	let mut salts = [0u8; 16];
+	LittleEndian::write_u64(&mut salts[0..8], local_salt.min(remote_salt));
+	LittleEndian::write_u64(&mut salts[8..16], local_salt.max(remote_salt));
+	let hash = tagged_hash("Tx Relay Salting", &salts);
+	(LittleEndian::read_u64(&hash[0..8]), LittleEndian::read_u64(&hash[8..16]))
+}
+
+/// Estimates number of differences between the sets of given sizes
+fn estimate_capacity(local_size: usize, remote_size: usize, q: usize) -> usize {
+	let difference = local_size.max(remote_size) - local_size.min(remote_size);
+	let weighted_min = local_size.min(remote_size) * q / Q_PRECISION;
+	(difference + weighted_min + 1).min(MAX_SKETCH_CAPACITY)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{estimate_capacity, Reconciliation, MAX_SKETCH_CAPACITY, RECONCILIATION_TIMEOUT, RECON_Q};
+	use bitcrypto::{dhash256, SHA256D};
+	use message::types;
+	use std::time::Instant;
+
+	fn hashes(range: std::ops::Range<u8>) -> Vec<SHA256D> {
+		range.map(|i| dhash256(&[i])).collect()
+	}
+
+	fn peers() -> (Reconciliation, Reconciliation) {
+		(Reconciliation::new(true, 1, 2), Reconciliation::new(false, 2, 1))
+	}
+
+	#[test]
+	fn short_ids_are_same_for_both_peers() {
+		let (initiator, responder) = peers();
+		let hash = dhash256(b"transaction");
+		assert_eq!(initiator.short_id(&hash), responder.short_id(&hash));
+		assert!(initiator.short_id(&hash) != Reconciliation::new(true, 1, 3).short_id(&hash));
+	}
+
+	#[test]
+	fn capacity_estimation() {
+		assert_eq!(estimate_capacity(0, 0, RECON_Q as usize), 1);
+		assert_eq!(estimate_capacity(10, 4, RECON_Q as usize), 7);
+		assert_eq!(estimate_capacity(40, 40, RECON_Q as usize), 10);
+		assert_eq!(estimate_capacity(10000, 0, RECON_Q as usize), MAX_SKETCH_CAPACITY);
+	}
+
+	#[test]
+	fn reconciliation_round() {
+		let (mut initiator, mut responder) = peers();
+		let transactions = hashes(0..12);
+		for hash in &transactions[0..10] {
+			initiator.add_transaction(*hash);
+		}
+		for hash in &transactions[2..11] {
+			responder.add_transaction(*hash);
+		}
+
+		let reqrecon = initiator.request().unwrap();
+		assert_eq!(initiator.request(), None);
+		assert_eq!(reqrecon.set_size, 10);
+
+		let sketch = responder.sketch(&reqrecon).unwrap();
+		assert_eq!(responder.sketch(&reqrecon), None);
+
+		let result = initiator.reconcile(&sketch).unwrap();
+		assert!(result.reconcildiff.success);
+		let mut announce = result.announce;
+		announce.sort();
+		let mut expected = transactions[0..2].to_vec();
+		expected.sort();
+		assert_eq!(announce, expected);
+
+		let mut announce = responder.finish(&result.reconcildiff).unwrap();
+		announce.sort();
+		assert_eq!(announce, vec![transactions[10]]);
+
+		// sets are cleared after the round
+		assert_eq!(initiator.request().unwrap().set_size, 0);
+		assert_eq!(responder.finish(&result.reconcildiff), None);
+	}
+
+	#[test]
+	fn failed_reconciliation_announces_whole_set() {
+		let (mut initiator, mut responder) = peers();
+		let transactions = hashes(0..40);
+		for hash in &transactions[0..20] {
+			initiator.add_transaction(*hash);
+		}
+		for hash in &transactions[20..40] {
+			responder.add_transaction(*hash);
+		}
+
+		// peers are underestimating the difference
+		let sketch = responder.sketch(&types::ReqRecon { set_size: 20, q: 0 }).unwrap();
+		initiator.request().unwrap();
+		let result = initiator.reconcile(&sketch).unwrap();
+		assert!(!result.reconcildiff.success);
+		assert_eq!(result.announce.len(), 20);
+		assert_eq!(responder.finish(&result.reconcildiff).unwrap().len(), 20);
+	}
+
+	#[test]
+	fn expired_round_announces_sketched_set() {
+		let (mut initiator, mut responder) = peers();
+		let transactions = hashes(0..3);
+		for hash in &transactions {
+			responder.add_transaction(*hash);
+		}
+
+		let reqrecon = initiator.request().unwrap();
+		responder.sketch(&reqrecon).unwrap();
+		assert_eq!(initiator.expire(Instant::now()), vec![]);
+		assert_eq!(responder.expire(Instant::now()), vec![]);
+		assert_eq!(initiator.request(), None);
+
+		// peers have not responded in time
+		let later = Instant::now() + RECONCILIATION_TIMEOUT;
+		assert_eq!(initiator.expire(later), vec![]);
+		let mut announce = responder.expire(later);
+		announce.sort();
+		let mut expected = transactions.clone();
+		expected.sort();
+		assert_eq!(announce, expected);
+
+		// late messages are rejected and new round may be started
+		let reconcildiff = types::ReconcilDiff {
+			success: false,
+			ask_shortids: Vec::new(),
+		};
+		assert_eq!(responder.finish(&reconcildiff), None);
+		let reqrecon = initiator.request().unwrap();
+		assert!(responder.sketch(&reqrecon).is_some());
+	}
+
+	#[test]
+	fn unexpected_messages_are_rejected() {
+		let (mut initiator, mut responder) = peers();
+		assert_eq!(responder.request(), None);
+		assert_eq!(initiator.sketch(&types::ReqRecon { set_size: 0, q: RECON_Q }), None);
+		let sketch = types::Sketch { skdata: vec![0; 4].into() };
+		assert_eq!(initiator.reconcile(&sketch), None);
+		initiator.request().unwrap();
+		assert_eq!(initiator.reconcile(&types::Sketch { skdata: vec![0; 3].into() }), None);
+	}
+}
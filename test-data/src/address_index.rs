@@ -0,0 +1,17 @@
+use bitcrypto::{Hash, SHA256D};
+use chain::OutPoint;
+use storage::ScriptHashEntry;
+
+/// Builds address index entry with dummy transaction hashes, filled with given bytes.
+pub fn script_hash_entry(height: u32, tx_hash: u8, funding_tx_hash: u8, value: u64, is_spending: bool) -> ScriptHashEntry {
+	ScriptHashEntry {
+		height,
+		tx_hash: SHA256D::from_inner([tx_hash; 32]),
+		outpoint: OutPoint {
+			hash: SHA256D::from_inner([funding_tx_hash; 32]),
+			index: 0,
+		},
+		value,
+		is_spending,
+	}
+}
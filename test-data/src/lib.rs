@@ -4,14 +4,17 @@ extern crate chain;
 extern crate primitives;
 extern crate script;
 extern crate serialization as ser;
+extern crate storage;
 
 use chain::Block;
 
+pub mod address_index;
 pub mod block;
 pub mod chain_builder;
 pub mod invoke;
 pub mod time;
 
+pub use address_index::script_hash_entry;
 pub use block::{block_builder, block_hash_builder, build_n_empty_blocks, build_n_empty_blocks_from, build_n_empty_blocks_from_genesis};
 pub use chain_builder::{ChainBuilder, TransactionBuilder};
 
@@ -105,6 +105,25 @@ impl Deployments {
 			None => false,
 		}
 	}
+
+	/// Returns true if Taproot deployment is active
+	pub fn taproot(&self, number: u32, headers: &dyn BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
+		match consensus.taproot_deployment {
+			Some(taproot) => {
+				let mut cache = self.cache.lock();
+				threshold_state(
+					&mut cache,
+					taproot,
+					number,
+					headers,
+					consensus.miner_confirmation_window,
+					consensus.rule_change_activation_threshold,
+				)
+				.is_active()
+			}
+			None => false,
+		}
+	}
 }
 
 impl<'a> BlockDeployments<'a> {
@@ -124,6 +143,10 @@ impl<'a> BlockDeployments<'a> {
 	pub fn segwit(&self) -> bool {
 		self.deployments.segwit(self.number, self.headers, self.consensus)
 	}
+
+	pub fn taproot(&self) -> bool {
+		self.deployments.taproot(self.number, self.headers, self.consensus)
+	}
 }
 
 impl AsRef<Deployments> for Deployments {
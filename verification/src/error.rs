@@ -119,4 +119,45 @@ pub enum TransactionError {
 	ReturnReplayProtection,
 	/// Transaction with witness is received before SegWit is activated.
 	PrematureWitness,
+	/// Replacement transaction spends unconfirmed output that is not spent by replaced transactions (BIP125).
+	ReplacementUnconfirmedInput(usize),
+	/// Replacement transaction has lesser fee rate than one of directly replaced transactions (BIP125).
+	ReplacementFeeRate(SHA256D),
+	/// Replacement transaction doesn't pay for replaced transactions and its own relay (BIP125).
+	ReplacementFee,
+	/// Replacement transaction evicts too many transactions from the memory pool (BIP125).
+	ReplacementEvictions(usize),
+	/// Transaction has too many in-pool ancestors.
+	TooManyAncestors(usize),
+	/// Transaction with all its in-pool ancestors is too large.
+	TooLargeAncestors(usize),
+	/// In-pool ancestor of transaction would have too many descendants.
+	TooManyDescendants(SHA256D),
+	/// In-pool ancestor of transaction with all its descendants would be too large.
+	TooLargeDescendants(SHA256D),
+	/// Transaction fee rate (in satoshis per 1000 bytes) is below the memory pool min fee rate.
+	MemoryPoolFeeRate(u64),
+	/// Transaction is evicted from the full memory pool right after insertion.
+	MemoryPoolFull,
+}
+
+#[derive(Debug, PartialEq)]
+/// Possible package verification errors
+pub enum PackageError {
+	/// Package has no transactions
+	Empty,
+	/// Package has more transactions than it's allowed
+	TooManyTransactions(usize),
+	/// Package weight exceeds the limit
+	TooLarge(usize),
+	/// Transaction spends output of the transaction that follows it in the package
+	NotSorted(SHA256D),
+	/// Transaction is duplicated in the package or spends the same output as another package transaction
+	Conflict(SHA256D),
+	/// Transaction is neither the last transaction of the package, nor its parent
+	NotChildWithParents(SHA256D),
+	/// Package fee rate is below the memory pool min fee rate
+	FeeRate(u64),
+	/// One of the transactions is invalid (corresponding hash and specific transaction error)
+	Transaction(SHA256D, TransactionError),
 }
@@ -38,6 +38,11 @@
 //! B.1 VerifyMemoryPoolTransaction
 //! B.2 AcceptMemoryPoolTransaction
 //!
+//! B'. on_memory_pool_package
+//!
+//! B'.1 VerifyPackage
+//! B'.2 B for each package tx
+//!
 //! --> C. on_block_header
 //!
 //! C.1 VerifyHeader
@@ -79,6 +84,7 @@ mod work;
 mod verify_block;
 mod verify_chain;
 mod verify_header;
+mod verify_package;
 mod verify_transaction;
 
 // full verification
@@ -101,11 +107,12 @@ pub use canon::{CanonBlock, CanonHeader, CanonTransaction};
 pub use verify_block::BlockVerifier;
 pub use verify_chain::ChainVerifier;
 pub use verify_header::HeaderVerifier;
+pub use verify_package::{PackageVerifier, MAX_PACKAGE_COUNT, MAX_PACKAGE_WEIGHT};
 pub use verify_transaction::{MemoryPoolTransactionVerifier, TransactionVerifier};
 
 pub use chain_verifier::BackwardsCompatibleChainVerifier;
 pub use deployments::Deployments;
-pub use error::{Error, TransactionError};
+pub use error::{Error, PackageError, TransactionError};
 pub use sigops::transaction_sigops;
 pub use timestamp::{median_timestamp, median_timestamp_inclusive};
 pub use verification_level::VerificationLevel;
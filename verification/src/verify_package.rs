@@ -0,0 +1,213 @@
+use crate::chain::IndexedTransaction;
+use crate::error::PackageError;
+use crate::ser::{Serializable, SERIALIZE_TRANSACTION_WITNESS};
+use bitcrypto::SHA256D;
+use std::collections::{HashMap, HashSet};
+
+/// Max number of transactions in the package.
+pub const MAX_PACKAGE_COUNT: usize = 25;
+/// Max total weight of the package transactions.
+pub const MAX_PACKAGE_WEIGHT: usize = 404_000;
+
+/// Package pre-verification.
+///
+/// Package is a topologically sorted set of transactions, where the last transaction
+/// is a child of all other transactions. Such package is evaluated by the memory pool
+/// as a whole, so that the child could pay for its low-fee parents.
+pub struct PackageVerifier<'a> {
+	pub count: PackageCount<'a>,
+	pub weight: PackageWeight<'a>,
+	pub conflicts: PackageConflicts<'a>,
+	pub sorted: PackageSorted<'a>,
+	pub child_with_parents: PackageChildWithParents<'a>,
+}
+
+impl<'a> PackageVerifier<'a> {
+	pub fn new(package: &'a [IndexedTransaction]) -> Self {
+		trace!(target: "verification", "Package pre-verification {:?}", package.last().map(|tx| tx.hash));
+		PackageVerifier {
+			count: PackageCount::new(package),
+			weight: PackageWeight::new(package),
+			conflicts: PackageConflicts::new(package),
+			sorted: PackageSorted::new(package),
+			child_with_parents: PackageChildWithParents::new(package),
+		}
+	}
+
+	pub fn check(&self) -> Result<(), PackageError> {
+		self.count.check()?;
+		self.weight.check()?;
+		self.conflicts.check()?;
+		self.sorted.check()?;
+		self.child_with_parents.check()?;
+		Ok(())
+	}
+}
+
+pub struct PackageCount<'a> {
+	package: &'a [IndexedTransaction],
+}
+
+impl<'a> PackageCount<'a> {
+	fn new(package: &'a [IndexedTransaction]) -> Self {
+		PackageCount { package }
+	}
+
+	fn check(&self) -> Result<(), PackageError> {
+		match self.package.len() {
+			0 => Err(PackageError::Empty),
+			count if count > MAX_PACKAGE_COUNT => Err(PackageError::TooManyTransactions(count)),
+			_ => Ok(()),
+		}
+	}
+}
+
+pub struct PackageWeight<'a> {
+	package: &'a [IndexedTransaction],
+}
+
+impl<'a> PackageWeight<'a> {
+	fn new(package: &'a [IndexedTransaction]) -> Self {
+		PackageWeight { package }
+	}
+
+	fn check(&self) -> Result<(), PackageError> {
+		let weight = self
+			.package
+			.iter()
+			.map(|tx| tx.raw.serialized_size() * 3 + tx.raw.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS))
+			.sum();
+		if weight > MAX_PACKAGE_WEIGHT {
+			Err(PackageError::TooLarge(weight))
+		} else {
+			Ok(())
+		}
+	}
+}
+
+pub struct PackageConflicts<'a> {
+	package: &'a [IndexedTransaction],
+}
+
+impl<'a> PackageConflicts<'a> {
+	fn new(package: &'a [IndexedTransaction]) -> Self {
+		PackageConflicts { package }
+	}
+
+	fn check(&self) -> Result<(), PackageError> {
+		let mut hashes = HashSet::new();
+		let mut spent = HashSet::new();
+		for tx in self.package {
+			if !hashes.insert(tx.hash) {
+				return Err(PackageError::Conflict(tx.hash));
+			}
+
+			for input in &tx.raw.inputs {
+				if !spent.insert(&input.previous_output) {
+					return Err(PackageError::Conflict(tx.hash));
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+pub struct PackageSorted<'a> {
+	package: &'a [IndexedTransaction],
+}
+
+impl<'a> PackageSorted<'a> {
+	fn new(package: &'a [IndexedTransaction]) -> Self {
+		PackageSorted { package }
+	}
+
+	fn check(&self) -> Result<(), PackageError> {
+		let positions: HashMap<SHA256D, usize> = self.package.iter().enumerate().map(|(index, tx)| (tx.hash, index)).collect();
+		for (index, tx) in self.package.iter().enumerate() {
+			let spends_later_transaction = tx
+				.raw
+				.inputs
+				.iter()
+				.filter_map(|input| positions.get(&input.previous_output.hash))
+				.any(|position| *position >= index);
+			if spends_later_transaction {
+				return Err(PackageError::NotSorted(tx.hash));
+			}
+		}
+
+		Ok(())
+	}
+}
+
+pub struct PackageChildWithParents<'a> {
+	package: &'a [IndexedTransaction],
+}
+
+impl<'a> PackageChildWithParents<'a> {
+	fn new(package: &'a [IndexedTransaction]) -> Self {
+		PackageChildWithParents { package }
+	}
+
+	fn check(&self) -> Result<(), PackageError> {
+		let (child, parents) = match self.package.split_last() {
+			Some(split) => split,
+			None => return Ok(()),
+		};
+
+		let child_parents: HashSet<&SHA256D> = child.raw.inputs.iter().map(|input| &input.previous_output.hash).collect();
+		match parents.iter().find(|parent| !child_parents.contains(&parent.hash)) {
+			Some(parent) => Err(PackageError::NotChildWithParents(parent.hash)),
+			None => Ok(()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use super::{PackageVerifier, MAX_PACKAGE_COUNT};
+	use crate::chain::IndexedTransaction;
+	use crate::error::PackageError;
+
+	fn package(chain: &test_data::ChainBuilder, indices: &[usize]) -> Vec<IndexedTransaction> {
+		indices.iter().map(|index| chain.at(*index).into()).collect()
+	}
+
+	#[test]
+	fn package_child_with_parents() {
+		let chain = &mut test_data::ChainBuilder::new();
+		test_data::TransactionBuilder::with_output(10)
+			.store(chain) // t0
+			.set_output(20)
+			.store(chain) // t1
+			.set_input(&chain.at(0), 0)
+			.add_input(&chain.at(1), 0)
+			.set_output(25)
+			.store(chain) // t0[0] + t1[0] -> t2
+			.set_input(&chain.at(2), 0)
+			.set_output(20)
+			.store(chain); // t2[0] -> t3
+
+		assert_eq!(PackageVerifier::new(&package(chain, &[0, 1, 2])).check(), Ok(()));
+		assert_eq!(PackageVerifier::new(&package(chain, &[2])).check(), Ok(()));
+		assert_eq!(PackageVerifier::new(&package(chain, &[])).check(), Err(PackageError::Empty));
+		assert_eq!(
+			PackageVerifier::new(&package(chain, &[1, 0, 2, 1])).check(),
+			Err(PackageError::Conflict(chain.hash(1)))
+		);
+		assert_eq!(
+			PackageVerifier::new(&package(chain, &[2, 0, 1])).check(),
+			Err(PackageError::NotSorted(chain.hash(2)))
+		);
+		assert_eq!(
+			PackageVerifier::new(&package(chain, &[0, 1, 2, 3])).check(),
+			Err(PackageError::NotChildWithParents(chain.hash(0)))
+		);
+		assert_eq!(
+			PackageVerifier::new(&package(chain, &[0; MAX_PACKAGE_COUNT + 1])).check(),
+			Err(PackageError::TooManyTransactions(MAX_PACKAGE_COUNT + 1))
+		);
+	}
+}
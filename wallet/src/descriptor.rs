@@ -0,0 +1,243 @@
+//! Single key output descriptors, tracked by the wallet.
+//! https://github.com/bitcoin/bitcoin/blob/master/doc/descriptors.md
+
+use crate::Error;
+use bitcrypto::SHA256;
+use keys::{Address, Network, Public};
+use script::{compute_taproot_tweak, DescriptorKey, Script};
+use std::fmt;
+use std::str::FromStr;
+
+/// Type of outputs, generated by the wallet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputType {
+	/// `pkh(KEY)` => P2PKH address
+	Legacy,
+	/// `sh(wpkh(KEY))` => P2SH address, wrapping P2WPKH program
+	P2shSegwit,
+	/// `wpkh(KEY)` => P2WPKH address
+	Bech32,
+	/// `tr(KEY)` => P2TR address without script path (BIP86)
+	Bech32m,
+}
+
+impl OutputType {
+	pub fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(OutputType::Legacy),
+			1 => Some(OutputType::P2shSegwit),
+			2 => Some(OutputType::Bech32),
+			3 => Some(OutputType::Bech32m),
+			_ => None,
+		}
+	}
+
+	pub fn to_u8(&self) -> u8 {
+		match *self {
+			OutputType::Legacy => 0,
+			OutputType::P2shSegwit => 1,
+			OutputType::Bech32 => 2,
+			OutputType::Bech32m => 3,
+		}
+	}
+
+	/// Virtual size of input, spending output of this type
+	pub fn input_vsize(&self) -> usize {
+		match *self {
+			// 41 bytes of outpoint, sequence and script length + 107 bytes of script sig
+			OutputType::Legacy => 148,
+			// 41 + 23 bytes of script sig + 109 witness bytes / 4
+			OutputType::P2shSegwit => 91,
+			// 41 + 109 witness bytes / 4
+			OutputType::Bech32 => 68,
+			// 41 + 66 witness bytes / 4
+			OutputType::Bech32m => 58,
+		}
+	}
+}
+
+impl FromStr for OutputType {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"legacy" => Ok(OutputType::Legacy),
+			"p2sh-segwit" => Ok(OutputType::P2shSegwit),
+			"bech32" => Ok(OutputType::Bech32),
+			"bech32m" => Ok(OutputType::Bech32m),
+			_ => Err(Error::UnknownAddressType(s.into())),
+		}
+	}
+}
+
+/// Output descriptor with single public key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Descriptor {
+	output_type: OutputType,
+	public: Public,
+	descriptor: script::Descriptor,
+}
+
+impl Descriptor {
+	/// Creates descriptor. Segwit descriptors require compressed public key.
+	pub fn new(output_type: OutputType, public: Public) -> Result<Self, Error> {
+		if output_type != OutputType::Legacy {
+			if let Public::Normal(_) = public {
+				return Err(Error::Key(keys::Error::InvalidPublic));
+			}
+		}
+
+		let key = |x_only| DescriptorKey::from_public(public.clone(), x_only);
+		let descriptor = match output_type {
+			OutputType::Legacy => script::Descriptor::Pkh(key(false)),
+			OutputType::P2shSegwit => script::Descriptor::Sh(Box::new(script::Descriptor::Wpkh(key(false)))),
+			OutputType::Bech32 => script::Descriptor::Wpkh(key(false)),
+			OutputType::Bech32m => script::Descriptor::Tr {
+				internal_key: key(true),
+				tree: None,
+			},
+		};
+
+		Ok(Descriptor {
+			output_type,
+			public,
+			descriptor,
+		})
+	}
+
+	pub fn output_type(&self) -> OutputType {
+		self.output_type
+	}
+
+	pub fn public(&self) -> &Public {
+		&self.public
+	}
+
+	/// Script descriptor of the outputs, paying to the key
+	pub fn descriptor(&self) -> &script::Descriptor {
+		&self.descriptor
+	}
+
+	/// Redeem script of P2SH-wrapped descriptor
+	pub fn redeem_script(&self) -> Option<Script> {
+		match self.descriptor {
+			script::Descriptor::Sh(ref inner) => Some(inner.script_pubkey(0).expect("single key descriptor is not ranged; qed")),
+			_ => None,
+		}
+	}
+
+	/// Tweak of the taproot internal key, committing to empty script tree
+	pub fn taproot_tweak(&self) -> Result<SHA256, Error> {
+		Ok(compute_taproot_tweak(&self.public.x_only()?, None))
+	}
+
+	pub fn address(&self, network: Network) -> Address {
+		self.descriptor
+			.address(network, 0)
+			.expect("compressed public key is checked in constructor; single key descriptors have addresses; qed")
+	}
+
+	pub fn script_pubkey(&self) -> Script {
+		self.descriptor
+			.script_pubkey(0)
+			.expect("compressed public key is checked in constructor; qed")
+	}
+}
+
+impl fmt::Display for Descriptor {
+	/// Displays descriptor with checksum
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.descriptor.fmt(f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bitcrypto::FromHex;
+	use keys::Private;
+
+	fn public() -> Public {
+		// public key of the private key 1
+		let mut secret = [0u8; 32];
+		secret[31] = 1;
+		let private = Private {
+			network: Network::Mainnet,
+			secret,
+			compressed: true,
+		};
+		keys::KeyPair::from_private(private).unwrap().public().clone()
+	}
+
+	fn with_checksum(descriptor: String) -> String {
+		let checksum = script::descriptor_checksum(&descriptor).unwrap();
+		format!("{}#{}", descriptor, checksum)
+	}
+
+	#[test]
+	fn descriptor_addresses() {
+		let descriptor = |output_type| Descriptor::new(output_type, public()).unwrap();
+		assert_eq!(
+			descriptor(OutputType::Legacy).address(Network::Mainnet).to_string(),
+			"1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+		);
+		assert_eq!(
+			descriptor(OutputType::P2shSegwit).address(Network::Mainnet).to_string(),
+			"3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN"
+		);
+		assert_eq!(
+			descriptor(OutputType::Bech32).address(Network::Mainnet).to_string(),
+			"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+		);
+	}
+
+	/// First receiving address from https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+	#[test]
+	fn taproot_descriptor_address() {
+		let internal_key = "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115";
+		let public = Public::from_slice(&Vec::<u8>::from_hex(&format!("02{}", internal_key)).unwrap()).unwrap();
+		let descriptor = Descriptor::new(OutputType::Bech32m, public).unwrap();
+		assert_eq!(
+			descriptor.address(Network::Mainnet).to_string(),
+			"bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+		);
+		assert_eq!(descriptor.to_string(), with_checksum(format!("tr({})", internal_key)));
+	}
+
+	#[test]
+	fn descriptor_to_string() {
+		let descriptor = |output_type| Descriptor::new(output_type, public()).unwrap().to_string();
+		let key = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+		assert_eq!(descriptor(OutputType::Legacy), with_checksum(format!("pkh({})", key)));
+		assert_eq!(descriptor(OutputType::P2shSegwit), with_checksum(format!("sh(wpkh({}))", key)));
+		assert_eq!(descriptor(OutputType::Bech32), with_checksum(format!("wpkh({})", key)));
+		assert_eq!(descriptor(OutputType::Bech32m), with_checksum(format!("tr({})", &key[2..])));
+	}
+
+	#[test]
+	fn segwit_descriptor_requires_compressed_key() {
+		let mut secret = [0u8; 32];
+		secret[31] = 1;
+		let private = Private {
+			network: Network::Mainnet,
+			secret,
+			compressed: false,
+		};
+		let public = keys::KeyPair::from_private(private).unwrap().public().clone();
+		assert!(Descriptor::new(OutputType::Legacy, public.clone()).is_ok());
+		assert!(Descriptor::new(OutputType::Bech32, public).is_err());
+	}
+
+	#[test]
+	fn output_type_from_str() {
+		assert_eq!("bech32m".parse::<OutputType>().unwrap(), OutputType::Bech32m);
+		assert_eq!(
+			"unknown".parse::<OutputType>().unwrap_err(),
+			Error::UnknownAddressType("unknown".into())
+		);
+		for value in 0..4 {
+			assert_eq!(OutputType::from_u8(value).unwrap().to_u8(), value);
+		}
+		assert_eq!(OutputType::from_u8(4), None);
+	}
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+	/// Unknown address (output) type
+	UnknownAddressType(String),
+	/// Address belongs to other network
+	InvalidNetwork,
+	/// Requested amount is below the dust threshold
+	AmountTooSmall,
+	/// Wallet has not enough spendable funds
+	InsufficientFunds,
+	/// Key error
+	Key(keys::Error),
+	/// Wallet file error
+	Database(String),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::UnknownAddressType(ref address_type) => write!(f, "Unknown address type '{}'", address_type),
+			Error::InvalidNetwork => "Address is for other network".fmt(f),
+			Error::AmountTooSmall => "Transaction amount too small".fmt(f),
+			Error::InsufficientFunds => "Insufficient funds".fmt(f),
+			Error::Key(ref err) => write!(f, "Key error: {}", err),
+			Error::Database(ref err) => write!(f, "Wallet database error: {}", err),
+		}
+	}
+}
+
+impl From<keys::Error> for Error {
+	fn from(err: keys::Error) -> Self {
+		Error::Key(err)
+	}
+}
+
+impl From<hammersbald::Error> for Error {
+	fn from(err: hammersbald::Error) -> Self {
+		Error::Database(err.to_string())
+	}
+}
+
+impl From<serialization::Error> for Error {
+	fn from(err: serialization::Error) -> Self {
+		Error::Database(err.to_string())
+	}
+}
@@ -0,0 +1,34 @@
+//! Wallet.
+//!
+//! Tracks single key output descriptors (pkh, sh(wpkh), wpkh and tr), scans canon blocks for transactions,
+//! paying to or spending from these descriptors and creates transactions, spending wallet outputs. Keys,
+//! transactions and the last scanned block are stored in the separate hammersbald file.
+
+#[macro_use]
+extern crate log;
+extern crate bitcrypto;
+extern crate chain;
+extern crate hammersbald;
+extern crate keys;
+extern crate parking_lot;
+extern crate primitives;
+extern crate script;
+extern crate serialization;
+#[macro_use]
+extern crate serialization_derive;
+extern crate storage;
+extern crate sync;
+
+mod descriptor;
+mod error;
+mod notifier;
+mod store;
+mod wallet;
+
+pub use crate::descriptor::{Descriptor, OutputType};
+pub use crate::error::Error;
+pub use crate::notifier::WalletNotifier;
+pub use crate::store::WalletStore;
+pub use crate::wallet::{
+	BlockTip, Category, HistoryEntry, Wallet, WalletKey, WalletTransaction, WalletUtxo, DEFAULT_FEE_RATE, DUST_THRESHOLD,
+};
@@ -0,0 +1,29 @@
+use crate::Wallet;
+use bitcrypto::SHA256D;
+use std::sync::Arc;
+use sync::SyncListener;
+
+/// Scans blocks as they are canonized.
+///
+/// Blocks are scanned in the synchronization thread, so that the wallet is never behind the storage,
+/// when new transaction is created.
+pub struct WalletNotifier {
+	wallet: Arc<Wallet>,
+	storage: storage::SharedStore,
+}
+
+impl WalletNotifier {
+	pub fn new(wallet: Arc<Wallet>, storage: storage::SharedStore) -> Self {
+		WalletNotifier { wallet, storage }
+	}
+}
+
+impl SyncListener for WalletNotifier {
+	fn synchronization_state_switched(&self, _is_synchronizing: bool) {}
+
+	fn best_storage_block_inserted(&self, _block_hash: &SHA256D) {
+		if let Err(err) = self.wallet.scan(self.storage.as_store()) {
+			warn!(target: "wallet", "Error scanning wallet blocks: {}", err);
+		}
+	}
+}
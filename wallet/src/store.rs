@@ -0,0 +1,327 @@
+//! Wallet file.
+//!
+//! Keys and transactions are stored in the separate hammersbald file as append-only linked lists. Heads of
+//! the lists are stored under fixed keys and are updated in place.
+
+use crate::descriptor::{Descriptor, OutputType};
+use crate::wallet::{BlockTip, WalletKey, WalletTransaction};
+use crate::Error;
+use bitcrypto::SHA256D;
+use chain::Transaction;
+use hammersbald::{persistent, transient, HammersbaldAPI};
+use keys::{KeyPair, Network, Private, Secret};
+use parking_lot::Mutex;
+use primitives::bytes::Bytes;
+use serialization::{deserialize, serialize, serialize_with_flags, Deserializable, Serializable, SERIALIZE_TRANSACTION_WITNESS};
+
+type PRef = u64;
+
+/// Key of the keys list head
+const KEYS_KEY: &[u8] = b"keys";
+/// Key of the transaction hashes list head
+const TRANSACTIONS_KEY: &[u8] = b"transactions";
+/// Key of the last scanned block
+const TIP_KEY: &[u8] = b"tip";
+
+// Head of the append-only list.
+#[derive(Debug, Default, Serializable, Deserializable)]
+struct DbList {
+	last: PRef, // PRef of the last list entry, unused if len == 0
+	len: u32,
+}
+
+#[derive(Debug, Serializable, Deserializable)]
+struct DbKey {
+	output_type: u8,
+	secret: Bytes,
+	is_change: bool,
+	prev: PRef, // PRef of the previous DbKey
+}
+
+#[derive(Debug, Serializable, Deserializable)]
+struct DbTransactionHash {
+	hash: SHA256D,
+	prev: PRef, // PRef of the previous DbTransactionHash
+}
+
+// Keyed by transaction hash. Length of the serialized key (33 bytes) differs from the other keys.
+#[derive(Debug, Serializable, Deserializable)]
+struct DbTransactionKey {
+	prefix: u8,
+	hash: SHA256D,
+}
+
+#[derive(Debug, Serializable, Deserializable)]
+struct DbTransaction {
+	transaction: Transaction,
+	is_confirmed: bool,
+	block_hash: SHA256D, // unused if !is_confirmed
+	height: u32,         // unused if !is_confirmed
+	time: u32,
+}
+
+#[derive(Debug, Serializable, Deserializable)]
+struct DbTip {
+	hash: SHA256D,
+	height: u32,
+}
+
+impl DbTransactionKey {
+	fn from(hash: &SHA256D) -> Self {
+		DbTransactionKey { prefix: b't', hash: *hash }
+	}
+}
+
+pub struct WalletStore {
+	hammersbald: Mutex<Box<dyn HammersbaldAPI>>,
+}
+
+impl WalletStore {
+	pub fn transient() -> Result<Self, Error> {
+		Ok(WalletStore::new(transient()?))
+	}
+
+	pub fn persistent(path: &str, cache_size_mb: usize) -> Result<Self, Error> {
+		Ok(WalletStore::new(persistent(path, "wallet", cache_size_mb)?))
+	}
+
+	fn new(hammersbald: Box<dyn HammersbaldAPI>) -> Self {
+		WalletStore {
+			hammersbald: Mutex::new(hammersbald),
+		}
+	}
+
+	/// Reads all wallet keys in order of insertion
+	pub fn keys(&self, network: Network) -> Result<Vec<WalletKey>, Error> {
+		let db_keys: Vec<DbKey> = self.list(KEYS_KEY, |db_key: &DbKey| db_key.prev)?;
+		db_keys
+			.into_iter()
+			.map(|db_key| {
+				let output_type = OutputType::from_u8(db_key.output_type).ok_or_else(|| Error::Database("unknown output type".into()))?;
+				if db_key.secret.len() != 32 {
+					return Err(Error::Database("invalid secret length".into()));
+				}
+				let mut secret = Secret::default();
+				secret.copy_from_slice(&db_key.secret);
+				let private = Private {
+					network,
+					secret,
+					compressed: true,
+				};
+				let descriptor = Descriptor::new(output_type, KeyPair::from_private(private.clone())?.public().clone())?;
+				Ok(WalletKey {
+					descriptor,
+					private,
+					is_change: db_key.is_change,
+				})
+			})
+			.collect()
+	}
+
+	pub fn insert_key(&self, key: &WalletKey) -> Result<(), Error> {
+		let db_key = |prev| DbKey {
+			output_type: key.descriptor.output_type().to_u8(),
+			secret: key.private.secret.to_vec().into(),
+			is_change: key.is_change,
+			prev,
+		};
+		self.push(KEYS_KEY, db_key)
+	}
+
+	/// Reads all wallet transactions in order of insertion
+	pub fn transactions(&self) -> Result<Vec<WalletTransaction>, Error> {
+		let hashes: Vec<DbTransactionHash> = self.list(TRANSACTIONS_KEY, |entry: &DbTransactionHash| entry.prev)?;
+		hashes
+			.into_iter()
+			.map(|entry| {
+				let db_tx: DbTransaction = self
+					.get_by_key(&serialize(&DbTransactionKey::from(&entry.hash)))?
+					.ok_or_else(|| Error::Database("missing wallet transaction".into()))?;
+				Ok(WalletTransaction {
+					transaction: db_tx.transaction.into(),
+					block: if db_tx.is_confirmed {
+						Some(BlockTip {
+							hash: db_tx.block_hash,
+							height: db_tx.height,
+						})
+					} else {
+						None
+					},
+					time: db_tx.time,
+				})
+			})
+			.collect()
+	}
+
+	/// Inserts new transaction or updates existing one
+	pub fn insert_transaction(&self, tx: &WalletTransaction) -> Result<(), Error> {
+		let key = serialize(&DbTransactionKey::from(&tx.transaction.hash));
+		let is_new = self.get_by_key::<DbTransaction>(&key)?.is_none();
+		let (is_confirmed, block) = match tx.block {
+			Some(block) => (true, block),
+			None => (
+				false,
+				BlockTip {
+					hash: SHA256D::default(),
+					height: 0,
+				},
+			),
+		};
+		self.put_keyed(
+			&key,
+			&DbTransaction {
+				transaction: tx.transaction.raw.clone(),
+				is_confirmed,
+				block_hash: block.hash,
+				height: block.height,
+				time: tx.time,
+			},
+		)?;
+		if is_new {
+			let hash = tx.transaction.hash;
+			self.push(TRANSACTIONS_KEY, |prev| DbTransactionHash { hash, prev })?;
+		}
+		Ok(())
+	}
+
+	/// Last block, scanned by the wallet
+	pub fn tip(&self) -> Result<Option<BlockTip>, Error> {
+		Ok(self.get_by_key::<DbTip>(TIP_KEY)?.map(|tip| BlockTip {
+			hash: tip.hash,
+			height: tip.height,
+		}))
+	}
+
+	pub fn set_tip(&self, tip: &BlockTip) -> Result<(), Error> {
+		self.put_keyed(
+			TIP_KEY,
+			&DbTip {
+				hash: tip.hash,
+				height: tip.height,
+			},
+		)
+	}
+
+	/// Makes all changes since last flush persistent
+	pub fn flush(&self) -> Result<(), Error> {
+		Ok(self.hammersbald.lock().batch()?)
+	}
+
+	pub fn shutdown(&self) -> Result<(), Error> {
+		Ok(self.hammersbald.lock().shutdown()?)
+	}
+
+	fn get_by_key<T>(&self, key: &[u8]) -> Result<Option<T>, Error>
+	where
+		T: Deserializable,
+	{
+		match self.hammersbald.lock().get_keyed(key)? {
+			Some((_, data)) => Ok(Some(deserialize::<&[u8], T>(&data)?)),
+			None => Ok(None),
+		}
+	}
+
+	fn put_keyed<T>(&self, key: &[u8], data: &T) -> Result<(), Error>
+	where
+		T: Serializable,
+	{
+		self.hammersbald
+			.lock()
+			.put_keyed(key, &serialize_with_flags(data, SERIALIZE_TRANSACTION_WITNESS))?;
+		Ok(())
+	}
+
+	/// Appends entry, built from PRef of the previous entry, to the list
+	fn push<T, F>(&self, list_key: &[u8], entry: F) -> Result<(), Error>
+	where
+		T: Serializable,
+		F: FnOnce(PRef) -> T,
+	{
+		let list = self.get_by_key::<DbList>(list_key)?.unwrap_or_default();
+		let last = self.hammersbald.lock().put(&serialize(&entry(list.last)))?;
+		self.put_keyed(list_key, &DbList { last, len: list.len + 1 })
+	}
+
+	/// Reads all list entries, starting from the first one
+	fn list<T, F>(&self, list_key: &[u8], prev: F) -> Result<Vec<T>, Error>
+	where
+		T: Deserializable,
+		F: Fn(&T) -> PRef,
+	{
+		let list = self.get_by_key::<DbList>(list_key)?.unwrap_or_default();
+		let mut entries = Vec::with_capacity(list.len as usize);
+		let mut pref = list.last;
+		for _ in 0..list.len {
+			let (_, data) = self.hammersbald.lock().get(pref)?;
+			let entry = deserialize::<&[u8], T>(&data)?;
+			pref = prev(&entry);
+			entries.push(entry);
+		}
+		entries.reverse();
+		Ok(entries)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn key(output_type: OutputType, is_change: bool) -> WalletKey {
+		WalletKey::generate(Network::Testnet, output_type, is_change).unwrap()
+	}
+
+	#[test]
+	fn store_keys() {
+		let store = WalletStore::transient().unwrap();
+		assert!(store.keys(Network::Testnet).unwrap().is_empty());
+
+		let keys = vec![key(OutputType::Legacy, false), key(OutputType::Bech32m, true)];
+		for key in &keys {
+			store.insert_key(key).unwrap();
+		}
+		assert_eq!(store.keys(Network::Testnet).unwrap(), keys);
+	}
+
+	#[test]
+	fn store_transactions() {
+		let store = WalletStore::transient().unwrap();
+		let tx1: Transaction = test_data::TransactionBuilder::with_output(10).add_default_input(0).into();
+		let mut tx2: Transaction = test_data::TransactionBuilder::with_output(20).add_default_input(1).into();
+		// witness is stored too
+		tx2.inputs[0].script_witness = vec![vec![1, 2, 3].into()];
+		let mut wallet_tx1 = WalletTransaction {
+			transaction: tx1.into(),
+			block: None,
+			time: 100,
+		};
+		let wallet_tx2 = WalletTransaction {
+			transaction: tx2.into(),
+			block: Some(BlockTip {
+				hash: SHA256D::default(),
+				height: 10,
+			}),
+			time: 200,
+		};
+		store.insert_transaction(&wallet_tx1).unwrap();
+		store.insert_transaction(&wallet_tx2).unwrap();
+		assert_eq!(store.transactions().unwrap(), vec![wallet_tx1.clone(), wallet_tx2.clone()]);
+
+		// transaction is updated in place
+		wallet_tx1.block = wallet_tx2.block;
+		store.insert_transaction(&wallet_tx1).unwrap();
+		assert_eq!(store.transactions().unwrap(), vec![wallet_tx1, wallet_tx2]);
+	}
+
+	#[test]
+	fn store_tip() {
+		let store = WalletStore::transient().unwrap();
+		assert_eq!(store.tip().unwrap(), None);
+		let tip = BlockTip {
+			hash: SHA256D::default(),
+			height: 10,
+		};
+		store.set_tip(&tip).unwrap();
+		store.flush().unwrap();
+		assert_eq!(store.tip().unwrap(), Some(tip));
+	}
+}
@@ -0,0 +1,780 @@
+use crate::descriptor::{Descriptor, OutputType};
+use crate::store::WalletStore;
+use crate::Error;
+use bitcrypto::SHA256D;
+use chain::constants::SEQUENCE_FINAL;
+use chain::{IndexedBlock, IndexedTransaction, OutPoint, Transaction, TransactionInput, TransactionOutput};
+use keys::generator::{Generator, Random};
+use keys::{Address, KeyPair, Network, Private};
+use parking_lot::RwLock;
+use primitives::bytes::Bytes;
+use script::{Builder, ScriptExecutionData, SignatureVersion, TransactionInputSigner};
+use serialization::Serializable;
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+use storage::{BlockRef, Store};
+
+/// Number of confirmations, required to spend coinbase output
+const COINBASE_MATURITY: u32 = 100;
+/// Outputs with value below this threshold are never created by the wallet
+pub const DUST_THRESHOLD: u64 = 546;
+/// Fee rate of wallet transactions, in satoshis per 1000 virtual bytes
+pub const DEFAULT_FEE_RATE: u64 = 1_000;
+/// Virtual size of transaction version, lock time, inputs and outputs count and segwit marker
+const TRANSACTION_OVERHEAD_VSIZE: usize = 11;
+/// Virtual size of P2WPKH change output
+const CHANGE_OUTPUT_VSIZE: usize = 31;
+const SIGHASH_ALL: u32 = 1;
+/// BIP341 default signature hash type. Signature is 64 bytes long, it commits to the same data as SIGHASH_ALL.
+const SIGHASH_DEFAULT: u32 = 0;
+
+/// Block, scanned by the wallet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockTip {
+	pub hash: SHA256D,
+	pub height: u32,
+}
+
+/// Key, owned by the wallet
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletKey {
+	pub descriptor: Descriptor,
+	pub private: Private,
+	/// True if key is only used for change outputs
+	pub is_change: bool,
+}
+
+impl WalletKey {
+	/// Generates new random key
+	pub fn generate(network: Network, output_type: OutputType, is_change: bool) -> Result<Self, Error> {
+		let private = Private {
+			network,
+			secret: Random::new(network).generate()?.private().secret,
+			compressed: true,
+		};
+		let descriptor = Descriptor::new(output_type, KeyPair::from_private(private.clone())?.public().clone())?;
+		Ok(WalletKey {
+			descriptor,
+			private,
+			is_change,
+		})
+	}
+}
+
+/// Transaction, funding or spending wallet outputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletTransaction {
+	pub transaction: IndexedTransaction,
+	/// Canon block, containing the transaction. None if transaction is not confirmed
+	pub block: Option<BlockTip>,
+	/// Time of the block for confirmed transactions and time of creation for transactions, sent by the wallet
+	pub time: u32,
+}
+
+/// Unspent output, owned by the wallet
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletUtxo {
+	pub outpoint: OutPoint,
+	pub output: TransactionOutput,
+	pub address: Address,
+	pub descriptor: Descriptor,
+	pub confirmations: u32,
+}
+
+/// Category of the wallet history entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+	/// Wallet has sent funds
+	Send,
+	/// Wallet has received funds
+	Receive,
+	/// Wallet has received mature coinbase output
+	Generate,
+	/// Wallet has received coinbase output, which is not yet spendable
+	Immature,
+}
+
+/// Single entry of the wallet history. Every transaction produces entry per relevant output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+	pub txid: SHA256D,
+	pub vout: u32,
+	pub address: Option<Address>,
+	pub category: Category,
+	/// Output value. Negative for sent outputs
+	pub amount: i64,
+	/// Transaction fee (negative), paid by the wallet. Only set for sent outputs
+	pub fee: Option<i64>,
+	pub confirmations: u32,
+	pub block: Option<BlockTip>,
+	pub time: u32,
+}
+
+struct WalletData {
+	keys: Vec<WalletKey>,
+	/// Script pubkey => index of the key in `keys`
+	scripts: HashMap<Bytes, usize>,
+	transactions: HashMap<SHA256D, WalletTransaction>,
+	/// Hashes of `transactions` in order of insertion
+	order: Vec<SHA256D>,
+	/// Unspent wallet outputs => index of the key in `keys`
+	utxos: HashMap<OutPoint, usize>,
+	/// Outputs, spent by wallet transactions
+	spent: HashSet<OutPoint>,
+	/// Last scanned block
+	tip: BlockTip,
+}
+
+/// Wallet with randomly generated single key descriptors.
+///
+/// Wallet learns about its transactions by scanning canon blocks, so only transactions that have been
+/// created by the wallet itself are known before they are confirmed.
+pub struct Wallet {
+	network: Network,
+	store: WalletStore,
+	data: RwLock<WalletData>,
+}
+
+impl Wallet {
+	/// Opens the wallet. New wallet starts scanning blocks from the current best block of the storage.
+	pub fn open(store: WalletStore, network: Network, storage: &dyn Store) -> Result<Self, Error> {
+		let tip = match store.tip()? {
+			Some(tip) => tip,
+			None => {
+				let best_block = storage.best_block();
+				let tip = BlockTip {
+					hash: best_block.hash,
+					height: best_block.number,
+				};
+				store.set_tip(&tip)?;
+				store.flush()?;
+				tip
+			}
+		};
+
+		let mut data = WalletData {
+			keys: Vec::new(),
+			scripts: HashMap::new(),
+			transactions: HashMap::new(),
+			order: Vec::new(),
+			utxos: HashMap::new(),
+			spent: HashSet::new(),
+			tip,
+		};
+		for key in store.keys(network)? {
+			data.insert_key(key);
+		}
+		for tx in store.transactions()? {
+			data.insert_transaction(tx);
+		}
+
+		Ok(Wallet {
+			network,
+			store,
+			data: RwLock::new(data),
+		})
+	}
+
+	pub fn network(&self) -> Network {
+		self.network
+	}
+
+	/// Last scanned block
+	pub fn tip(&self) -> BlockTip {
+		self.data.read().tip
+	}
+
+	/// Generates new receiving address
+	pub fn new_address(&self, output_type: OutputType) -> Result<Address, Error> {
+		let mut data = self.data.write();
+		let key_index = self.new_key(&mut data, output_type, false)?;
+		Ok(data.keys[key_index].descriptor.address(self.network))
+	}
+
+	/// Descriptors of all wallet keys
+	pub fn descriptors(&self) -> Vec<Descriptor> {
+		self.data.read().keys.iter().map(|key| key.descriptor.clone()).collect()
+	}
+
+	/// Total value of spendable outputs with at least `min_conf` confirmations
+	pub fn balance(&self, min_conf: u32) -> u64 {
+		self.unspent(min_conf, u32::MAX).iter().map(|utxo| utxo.output.value).sum()
+	}
+
+	/// Spendable outputs with number of confirmations in [min_conf; max_conf] range
+	pub fn unspent(&self, min_conf: u32, max_conf: u32) -> Vec<WalletUtxo> {
+		let data = self.data.read();
+		let mut utxos: Vec<WalletUtxo> = data
+			.utxos
+			.iter()
+			.filter(|(outpoint, _)| data.is_spendable(outpoint))
+			.map(|(outpoint, key_index)| {
+				let tx = &data.transactions[&outpoint.hash];
+				let descriptor = data.keys[*key_index].descriptor.clone();
+				WalletUtxo {
+					outpoint: outpoint.clone(),
+					output: tx.transaction.raw.outputs[outpoint.index as usize].clone(),
+					address: descriptor.address(self.network),
+					descriptor,
+					confirmations: data.confirmations(tx),
+				}
+			})
+			.filter(|utxo| utxo.confirmations >= min_conf && utxo.confirmations <= max_conf)
+			.collect();
+		utxos.sort_by(|a, b| {
+			b.confirmations
+				.cmp(&a.confirmations)
+				.then_with(|| a.outpoint.hash.cmp(&b.outpoint.hash))
+				.then_with(|| a.outpoint.index.cmp(&b.outpoint.index))
+		});
+		utxos
+	}
+
+	/// Wallet history, starting from the oldest transaction
+	pub fn history(&self) -> Vec<HistoryEntry> {
+		let data = self.data.read();
+		let mut entries = Vec::new();
+		for tx in data.order.iter().map(|hash| &data.transactions[hash]) {
+			let raw = &tx.transaction.raw;
+			let confirmations = data.confirmations(tx);
+			let entry = |vout: usize, category, amount, fee| HistoryEntry {
+				txid: tx.transaction.hash,
+				vout: vout as u32,
+				address: data.output_address(self.network, &raw.outputs[vout]),
+				category,
+				amount,
+				fee,
+				confirmations,
+				block: tx.block,
+				time: tx.time,
+			};
+
+			// inputs of coinbase transaction are not wallet outputs
+			let spent: Vec<Option<&TransactionOutput>> =
+				raw.inputs.iter().map(|input| data.wallet_output(&input.previous_output)).collect();
+			let is_send = !raw.is_coinbase() && spent.iter().any(Option::is_some);
+			if is_send {
+				// fee is only known if all inputs are wallet inputs
+				let fee = match spent.iter().cloned().collect::<Option<Vec<_>>>() {
+					Some(spent) => {
+						let input_value: u64 = spent.iter().map(|output| output.value).sum();
+						Some(-((input_value - raw.total_spends()) as i64))
+					}
+					None => None,
+				};
+				for (vout, output) in raw.outputs.iter().enumerate() {
+					if !data.is_change(output) {
+						entries.push(entry(vout, Category::Send, -(output.value as i64), fee));
+					}
+				}
+			}
+
+			for (vout, output) in raw.outputs.iter().enumerate() {
+				let key_index = match data.scripts.get(&output.script_pubkey) {
+					Some(key_index) => *key_index,
+					None => continue,
+				};
+				if is_send && data.keys[key_index].is_change {
+					continue;
+				}
+				let category = match raw.is_coinbase() {
+					true if confirmations >= COINBASE_MATURITY => Category::Generate,
+					true => Category::Immature,
+					false => Category::Receive,
+				};
+				entries.push(entry(vout, category, output.value as i64, None));
+			}
+		}
+		entries
+	}
+
+	/// Scans canon blocks, which have been inserted since the last scan. Transactions of blocks, which are
+	/// no longer canon, become unconfirmed.
+	pub fn scan(&self, storage: &dyn Store) -> Result<(), Error> {
+		let mut data = self.data.write();
+		let initial_tip = data.tip;
+
+		// blocks above the best block may still be indexed by number after decanonization
+		let is_canon = |tip: &BlockTip| tip.height <= storage.best_block().number && storage.block_hash(tip.height) == Some(tip.hash);
+		while data.tip.height > 0 && !is_canon(&data.tip) {
+			let tip = data.tip;
+			let previous_hash = storage
+				.block_header(BlockRef::Hash(tip.hash))
+				.map(|header| header.raw.previous_header_hash)
+				.ok_or_else(|| Error::Database(format!("unknown wallet block {}", tip.hash)))?;
+			for tx in data.transactions.values_mut() {
+				if tx.block.map(|block| block.hash) == Some(tip.hash) {
+					tx.block = None;
+					self.store.insert_transaction(tx)?;
+				}
+			}
+			data.tip = BlockTip {
+				hash: previous_hash,
+				height: tip.height - 1,
+			};
+		}
+
+		let best_block = storage.best_block();
+		while data.tip.height < best_block.number {
+			let block = match storage.block(BlockRef::Number(data.tip.height + 1)) {
+				Some(block) => block,
+				None => break,
+			};
+			// canon chain has been reorganized during scan => rest is scanned next time
+			if block.header.raw.previous_header_hash != data.tip.hash {
+				break;
+			}
+			let tip = BlockTip {
+				hash: block.header.hash,
+				height: data.tip.height + 1,
+			};
+			self.scan_block(&mut data, &block, tip)?;
+			data.tip = tip;
+		}
+
+		if data.tip != initial_tip {
+			trace!(target: "wallet", "Wallet has scanned blocks up to {} ({})", data.tip.height, data.tip.hash);
+			self.store.set_tip(&data.tip)?;
+			self.store.flush()?;
+		}
+		Ok(())
+	}
+
+	fn scan_block(&self, data: &mut WalletData, block: &IndexedBlock, tip: BlockTip) -> Result<(), Error> {
+		for tx in &block.transactions {
+			if !data.is_relevant(&tx.raw) {
+				continue;
+			}
+
+			let wallet_tx = WalletTransaction {
+				transaction: tx.clone(),
+				block: Some(tip),
+				time: block.header.raw.time,
+			};
+			self.store.insert_transaction(&wallet_tx)?;
+			data.insert_transaction(wallet_tx);
+		}
+		Ok(())
+	}
+
+	/// Creates and signs transaction, paying `amount` to the `address`. Transaction is not added to the
+	/// wallet until `add_transaction` is called.
+	pub fn create_transaction(&self, address: &Address, amount: u64, fee_rate: u64) -> Result<Transaction, Error> {
+		if (address.network == Network::Mainnet) != (self.network == Network::Mainnet) {
+			return Err(Error::InvalidNetwork);
+		}
+		if amount < DUST_THRESHOLD {
+			return Err(Error::AmountTooSmall);
+		}
+
+		let fee = |vsize: usize| (vsize as u64 * fee_rate).div_ceil(1000);
+		let mut outputs = vec![TransactionOutput {
+			value: amount,
+			script_pubkey: Builder::build_address(address)?.to_bytes(),
+		}];
+		let mut vsize = TRANSACTION_OVERHEAD_VSIZE + outputs[0].serialized_size();
+
+		// largest outputs are spent first
+		let mut utxos = self.unspent(0, u32::MAX);
+		utxos.sort_by_key(|utxo| std::cmp::Reverse(utxo.output.value));
+		let mut selected = Vec::new();
+		let mut total = 0;
+		for utxo in utxos {
+			if total >= amount + fee(vsize + CHANGE_OUTPUT_VSIZE) {
+				break;
+			}
+			total += utxo.output.value;
+			vsize += utxo.descriptor.output_type().input_vsize();
+			selected.push(utxo);
+		}
+		if total < amount + fee(vsize) {
+			return Err(Error::InsufficientFunds);
+		}
+
+		let mut data = self.data.write();
+		// change below dust threshold is left to miners
+		let change = total.saturating_sub(amount + fee(vsize + CHANGE_OUTPUT_VSIZE));
+		if change >= DUST_THRESHOLD {
+			let change_key = self.new_key(&mut data, OutputType::Bech32, true)?;
+			outputs.push(TransactionOutput {
+				value: change,
+				script_pubkey: data.keys[change_key].descriptor.script_pubkey().to_bytes(),
+			});
+		}
+
+		let transaction = Transaction {
+			version: 2,
+			inputs: selected
+				.iter()
+				.map(|utxo| TransactionInput {
+					previous_output: utxo.outpoint.clone(),
+					script_sig: Bytes::default(),
+					sequence: SEQUENCE_FINAL,
+					script_witness: vec![],
+				})
+				.collect(),
+			outputs,
+			lock_time: 0,
+		};
+		sign_transaction(transaction, &selected, &data)
+	}
+
+	/// Adds unconfirmed transaction, created by the wallet
+	pub fn add_transaction(&self, transaction: Transaction) -> Result<(), Error> {
+		let time = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as u32)
+			.unwrap_or_default();
+		let wallet_tx = WalletTransaction {
+			transaction: transaction.into(),
+			block: None,
+			time,
+		};
+		let mut data = self.data.write();
+		self.store.insert_transaction(&wallet_tx)?;
+		self.store.flush()?;
+		data.insert_transaction(wallet_tx);
+		Ok(())
+	}
+
+	pub fn shutdown(&self) {
+		if let Err(err) = self.store.shutdown() {
+			warn!(target: "wallet", "Error closing wallet file: {}", err);
+		}
+	}
+
+	fn new_key(&self, data: &mut WalletData, output_type: OutputType, is_change: bool) -> Result<usize, Error> {
+		let key = WalletKey::generate(self.network, output_type, is_change)?;
+		self.store.insert_key(&key)?;
+		self.store.flush()?;
+		Ok(data.insert_key(key))
+	}
+}
+
+impl WalletData {
+	fn insert_key(&mut self, key: WalletKey) -> usize {
+		let index = self.keys.len();
+		self.scripts.insert(key.descriptor.script_pubkey().to_bytes(), index);
+		self.keys.push(key);
+		index
+	}
+
+	/// Inserts new transaction or updates block of known transaction
+	fn insert_transaction(&mut self, tx: WalletTransaction) {
+		let hash = tx.transaction.hash;
+		if let Some(known_tx) = self.transactions.get_mut(&hash) {
+			known_tx.block = tx.block;
+			known_tx.time = tx.time;
+			return;
+		}
+
+		if !tx.transaction.raw.is_coinbase() {
+			for input in &tx.transaction.raw.inputs {
+				self.utxos.remove(&input.previous_output);
+				self.spent.insert(input.previous_output.clone());
+			}
+		}
+		for (index, output) in tx.transaction.raw.outputs.iter().enumerate() {
+			let outpoint = OutPoint { hash, index: index as u32 };
+			if let Some(key_index) = self.scripts.get(&output.script_pubkey) {
+				if !self.spent.contains(&outpoint) {
+					self.utxos.insert(outpoint, *key_index);
+				}
+			}
+		}
+		self.order.push(hash);
+		self.transactions.insert(hash, tx);
+	}
+
+	/// Returns true if transaction pays to or spends from the wallet
+	fn is_relevant(&self, tx: &Transaction) -> bool {
+		tx.outputs.iter().any(|output| self.scripts.contains_key(&output.script_pubkey))
+			|| (!tx.is_coinbase() && tx.inputs.iter().any(|input| self.wallet_output(&input.previous_output).is_some()))
+	}
+
+	/// Returns output, if it is owned by the wallet
+	fn wallet_output(&self, outpoint: &OutPoint) -> Option<&TransactionOutput> {
+		self.transactions
+			.get(&outpoint.hash)
+			.and_then(|tx| tx.transaction.raw.outputs.get(outpoint.index as usize))
+			.filter(|output| self.scripts.contains_key(&output.script_pubkey))
+	}
+
+	fn output_address(&self, network: Network, output: &TransactionOutput) -> Option<Address> {
+		match self.scripts.get(&output.script_pubkey) {
+			Some(key_index) => Some(self.keys[*key_index].descriptor.address(network)),
+			None => {
+				let script: script::Script = output.script_pubkey.clone().into();
+				script.extract_destinations().ok().and_then(|destinations| {
+					destinations.into_iter().next().map(|destination| Address {
+						kind: destination.kind,
+						network,
+						payload: destination.payload,
+					})
+				})
+			}
+		}
+	}
+
+	fn is_change(&self, output: &TransactionOutput) -> bool {
+		match self.scripts.get(&output.script_pubkey) {
+			Some(key_index) => self.keys[*key_index].is_change,
+			None => false,
+		}
+	}
+
+	fn confirmations(&self, tx: &WalletTransaction) -> u32 {
+		match tx.block {
+			Some(block) if block.height <= self.tip.height => self.tip.height - block.height + 1,
+			_ => 0,
+		}
+	}
+
+	/// Returns false for immature coinbase outputs
+	fn is_spendable(&self, outpoint: &OutPoint) -> bool {
+		let tx = &self.transactions[&outpoint.hash];
+		!tx.transaction.raw.is_coinbase() || self.confirmations(tx) >= COINBASE_MATURITY
+	}
+}
+
+/// Signs all transaction inputs, spending given wallet outputs
+fn sign_transaction(mut transaction: Transaction, spent: &[WalletUtxo], data: &WalletData) -> Result<Transaction, Error> {
+	let signer = TransactionInputSigner::from(transaction.clone());
+	let spent_outputs: Vec<TransactionOutput> = spent.iter().map(|utxo| utxo.output.clone()).collect();
+	for (index, utxo) in spent.iter().enumerate() {
+		let key = &data.keys[data.scripts[&utxo.output.script_pubkey]];
+		let public: &[u8] = key.descriptor.public();
+		let input = &mut transaction.inputs[index];
+		match key.descriptor.output_type() {
+			OutputType::Legacy => {
+				let script_pubkey = utxo.output.script_pubkey.clone().into();
+				let hash = signer
+					.signature_hash(index, utxo.output.value, &script_pubkey, SignatureVersion::Base, SIGHASH_ALL)
+					.expect("legacy and segwit v0 signature versions are supported; qed");
+				let mut signature: Vec<u8> = key.private.sign(&hash)?.into();
+				signature.push(SIGHASH_ALL as u8);
+				input.script_sig = Builder::default().push_data(&signature).push_data(public).into_bytes();
+			}
+			OutputType::P2shSegwit | OutputType::Bech32 => {
+				let script_code = Builder::build_p2pkh(&key.descriptor.public().address_hash());
+				let hash = signer
+					.signature_hash(index, utxo.output.value, &script_code, SignatureVersion::WitnessV0, SIGHASH_ALL)
+					.expect("legacy and segwit v0 signature versions are supported; qed");
+				let mut signature: Vec<u8> = key.private.sign(&hash)?.into();
+				signature.push(SIGHASH_ALL as u8);
+				input.script_witness = vec![signature.into(), public.to_vec().into()];
+				if let Some(redeem_script) = key.descriptor.redeem_script() {
+					input.script_sig = Builder::default().push_data(&redeem_script).into_bytes();
+				}
+			}
+			OutputType::Bech32m => {
+				let hash = signer
+					.signature_hash_schnorr(
+						index,
+						&spent_outputs,
+						SIGHASH_DEFAULT,
+						SignatureVersion::Taproot,
+						&ScriptExecutionData::default(),
+					)
+					.expect("all inputs have spent outputs; SIGHASH_DEFAULT is valid; qed");
+				let private = key.private.tweak_add_x_only(&key.descriptor.taproot_tweak()?)?;
+				// auxiliary randomness is optional (BIP340) => signatures are deterministic
+				let signature = private.sign_schnorr(&hash, &[0; 32])?;
+				input.script_witness = vec![signature.to_vec().into()];
+			}
+		}
+	}
+	Ok(transaction)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use db::ham_db::HamDb;
+	use db::BlockChainDatabase;
+	use script::{verify_script, Script, TransactionSignatureChecker, VerificationFlags};
+	use serialization::SERIALIZE_TRANSACTION_WITNESS;
+
+	const OUTPUT_TYPES: [OutputType; 4] = [OutputType::Legacy, OutputType::P2shSegwit, OutputType::Bech32, OutputType::Bech32m];
+
+	fn script_pubkey(address: &Address) -> Bytes {
+		Builder::build_address(address).unwrap().to_bytes()
+	}
+
+	fn insert_block(db: &BlockChainDatabase<HamDb>, block: &IndexedBlock) {
+		db.insert(block.clone()).unwrap();
+		db.canonize(block.hash()).unwrap();
+	}
+
+	/// Wallet with 4 addresses of different types, funded at block 2, and immature coinbase output
+	fn funded_wallet() -> (Wallet, BlockChainDatabase<HamDb>, Vec<Address>, IndexedBlock) {
+		let b0: IndexedBlock = test_data::genesis().into();
+		let db = BlockChainDatabase::transient(&b0).unwrap();
+		let wallet = Wallet::open(WalletStore::transient().unwrap(), Network::Testnet, &db).unwrap();
+		let addresses: Vec<Address> = OUTPUT_TYPES.iter().map(|t| wallet.new_address(*t).unwrap()).collect();
+
+		#[rustfmt::skip]
+		let b1: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(5_000_000_000).script_pubkey("51").build().build()
+			.merkled_header().parent(*b0.hash()).build()
+			.build()
+			.into();
+		#[rustfmt::skip]
+		let b2: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(5_000_000_000).script_pubkey_bytes(script_pubkey(&addresses[0])).build().build()
+			.transaction()
+				.input().hash(b1.transactions[0].hash).index(0).build()
+				.output().value(100_000).script_pubkey_bytes(script_pubkey(&addresses[0])).build()
+				.output().value(200_000).script_pubkey_bytes(script_pubkey(&addresses[1])).build()
+				.output().value(300_000).script_pubkey_bytes(script_pubkey(&addresses[2])).build()
+				.output().value(400_000).script_pubkey_bytes(script_pubkey(&addresses[3])).build()
+				.output().value(500_000).script_pubkey("51").build()
+				.build()
+			.merkled_header().parent(*b1.hash()).build()
+			.build()
+			.into();
+		insert_block(&db, &b1);
+		insert_block(&db, &b2);
+		wallet.scan(&db).unwrap();
+		(wallet, db, addresses, b2)
+	}
+
+	fn external_address() -> Address {
+		"tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".into()
+	}
+
+	#[test]
+	fn wallet_scans_blocks() {
+		let (wallet, _db, addresses, b2) = funded_wallet();
+		assert_eq!(wallet.tip().height, 2);
+		// coinbase output is immature
+		assert_eq!(wallet.balance(1), 1_000_000);
+		let utxos = wallet.unspent(1, 1);
+		assert_eq!(utxos.len(), 4);
+		assert!(utxos.iter().all(|utxo| utxo.outpoint.hash == b2.transactions[1].hash));
+		assert!(wallet.unspent(2, u32::MAX).is_empty());
+
+		let history = wallet.history();
+		assert_eq!(history.len(), 5);
+		assert_eq!(history[0].category, Category::Immature);
+		assert_eq!(history[0].address, Some(addresses[0].clone()));
+		assert!(history[1..]
+			.iter()
+			.all(|entry| entry.category == Category::Receive && entry.confirmations == 1));
+		assert_eq!(history[4].amount, 400_000);
+		assert_eq!(history[4].address, Some(addresses[3].clone()));
+	}
+
+	#[test]
+	fn wallet_creates_valid_transaction() {
+		let (wallet, _db, _, _) = funded_wallet();
+		let tx = wallet.create_transaction(&external_address(), 900_000, DEFAULT_FEE_RATE).unwrap();
+		let spent: Vec<TransactionOutput> = tx
+			.inputs
+			.iter()
+			.map(|input| wallet.data.read().wallet_output(&input.previous_output).unwrap().clone())
+			.collect();
+		// all outputs of different types are required to pay 900_000 + fee
+		assert_eq!(spent.len(), 4);
+
+		let mut checker = TransactionSignatureChecker {
+			signer: tx.clone().into(),
+			input_index: 0,
+			input_amount: 0,
+			spent_outputs: spent.clone(),
+		};
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_strictenc(true)
+			.verify_dersig(true)
+			.verify_witness(true)
+			.verify_taproot(true);
+		for (index, (input, output)) in tx.inputs.iter().zip(spent.iter()).enumerate() {
+			checker.input_index = index;
+			checker.input_amount = output.value;
+			let script_sig: Script = input.script_sig.clone().into();
+			let script_pubkey: Script = output.script_pubkey.clone().into();
+			verify_script(
+				&script_sig,
+				&script_pubkey,
+				&input.script_witness,
+				&flags,
+				&checker,
+				SignatureVersion::Base,
+			)
+			.unwrap();
+		}
+
+		assert_eq!(tx.outputs[0].value, 900_000);
+		assert_eq!(tx.outputs[0].script_pubkey, script_pubkey(&external_address()));
+		let fee = 1_000_000 - tx.total_spends();
+		let vsize = (tx.serialized_size() * 3 + tx.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS)).div_ceil(4);
+		assert!(fee >= vsize as u64 && fee <= vsize as u64 + 10);
+	}
+
+	#[test]
+	fn wallet_tracks_sent_transaction() {
+		let (wallet, db, _, b2) = funded_wallet();
+		let tx = wallet.create_transaction(&external_address(), 150_000, DEFAULT_FEE_RATE).unwrap();
+		// the largest output is spent
+		assert_eq!(tx.inputs.len(), 1);
+		let change = tx.outputs[1].value;
+		wallet.add_transaction(tx.clone()).unwrap();
+		assert_eq!(wallet.balance(0), 600_000 + change);
+		assert_eq!(wallet.balance(1), 600_000);
+
+		let history = wallet.history();
+		let sent = history.last().unwrap();
+		assert_eq!(sent.category, Category::Send);
+		assert_eq!(sent.amount, -150_000);
+		assert_eq!(sent.fee, Some(-((400_000 - 150_000 - change) as i64)));
+		assert_eq!(sent.confirmations, 0);
+
+		#[rustfmt::skip]
+		let b3: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(10).script_pubkey("51").build().build()
+			.with_transaction(tx)
+			.merkled_header().parent(*b2.hash()).build()
+			.build()
+			.into();
+		insert_block(&db, &b3);
+		wallet.scan(&db).unwrap();
+		assert_eq!(wallet.balance(1), 600_000 + change);
+		assert_eq!(wallet.history().last().unwrap().confirmations, 1);
+	}
+
+	#[test]
+	fn wallet_reverts_decanonized_blocks() {
+		let (wallet, db, _, b2) = funded_wallet();
+		assert_eq!(db.decanonize().unwrap(), *b2.hash());
+		wallet.scan(&db).unwrap();
+		assert_eq!(wallet.tip().height, 1);
+		assert_eq!(wallet.balance(1), 0);
+		assert_eq!(wallet.balance(0), 1_000_000);
+		assert!(wallet
+			.history()
+			.iter()
+			.all(|entry| entry.confirmations == 0 && entry.block.is_none()));
+
+		db.canonize(b2.hash()).unwrap();
+		wallet.scan(&db).unwrap();
+		assert_eq!(wallet.balance(1), 1_000_000);
+	}
+
+	#[test]
+	fn wallet_rejects_invalid_sends() {
+		let (wallet, _db, _, _) = funded_wallet();
+		assert_eq!(
+			wallet.create_transaction(&external_address(), DUST_THRESHOLD - 1, DEFAULT_FEE_RATE),
+			Err(Error::AmountTooSmall)
+		);
+		assert_eq!(
+			wallet.create_transaction(&external_address(), 1_000_000, DEFAULT_FEE_RATE),
+			Err(Error::InsufficientFunds)
+		);
+		assert_eq!(
+			wallet.create_transaction(&"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".into(), 100_000, DEFAULT_FEE_RATE),
+			Err(Error::InvalidNetwork)
+		);
+	}
+}